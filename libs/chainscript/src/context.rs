@@ -103,6 +103,12 @@ pub trait Context {
     fn enforce_minimal_if(&self) -> bool {
         true
     }
+
+    /// Fail the script if it executes one of the `OP_NOPx` opcodes reserved for future soft
+    /// forks. Not a consensus rule, it keeps the opcodes free to be given a meaning later.
+    fn discourage_upgradable_nops(&self) -> bool {
+        false
+    }
 }
 
 // A test context implementation.
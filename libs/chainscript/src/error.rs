@@ -62,6 +62,8 @@ pub enum Error {
     TimeLock,
     /// Multisig lacks extra 0 dummy.
     NullDummy,
+    /// Opcode reserved for future soft forks executed.
+    DiscourageUpgradableNops,
 }
 
 #[cfg(feature = "std")]
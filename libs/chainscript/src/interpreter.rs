@@ -242,7 +242,12 @@ pub fn run_script<'a, Ctx: Context>(
                 }
             }
             Instruction::Op(opcode) => match opcode.classify() {
-                opcodes::Class::NoOp => (),
+                opcodes::Class::NoOp => ensure!(
+                    !executing
+                        || opcode == opcodes::all::OP_NOP
+                        || !ctx.discourage_upgradable_nops(),
+                    Error::DiscourageUpgradableNops
+                ),
                 opcodes::Class::IllegalOp => return Err(Error::IllegalOp),
                 opcodes::Class::ReturnOp if executing => return Err(Error::VerifyFail),
                 opcodes::Class::PushNum(x) if executing => stack.push_int(x as i64),
@@ -29,8 +29,9 @@ impl SigHash {
     const NONE: u8 = 0x02;
     const SINGLE: u8 = 0x03;
     const ANYONECANPAY: u8 = 0x80;
+    const CHAIN: u8 = 0x40;
 
-    const MASK_OUT: u8 = 0x7f;
+    const MASK_OUT: u8 = 0x3f;
     const MASK_IN: u8 = 0x80;
 
     pub fn from_u8(sighash_byte: u8) -> Option<SigHash> {
@@ -41,6 +42,24 @@ impl SigHash {
         ok.then(|| Self(sighash_byte))
     }
 
+    /// The sighash byte.
+    pub fn as_u8(&self) -> u8 {
+        self.0
+    }
+
+    /// The same sighash, also committing to the chain the transaction is signed for.
+    pub fn with_chain(self) -> Self {
+        match self.0 {
+            Self::DEFAULT => Self(Self::ALL | Self::CHAIN),
+            sighash => Self(sighash | Self::CHAIN),
+        }
+    }
+
+    /// Does the signature commit to the chain the transaction is signed for?
+    pub fn commits_to_chain(&self) -> bool {
+        self.0 & Self::CHAIN != 0
+    }
+
     pub fn input_mode(&self) -> InputMode {
         match self.0 & Self::MASK_IN {
             Self::ANYONECANPAY => InputMode::AnyoneCanPay,
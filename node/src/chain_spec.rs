@@ -280,6 +280,8 @@ fn testnet_genesis(
             // The # of validators set should also be the same here.
             // This should be the same as what's set as the initial authorities
             locked_utxos,
+            utxo_snapshot: None,
+            airdrop_root: None,
             // initial_reward_amount: 100 * MLT_UNIT
         },
         pp: PpConfig {
@@ -1,4 +1,7 @@
 use node_template_runtime::{self, opaque::Block, RuntimeApi};
+use pallet_utxo::verifier::{
+    verify_batch_parallel, ScriptVerifierExt, VerificationCache, VerificationCacheExt,
+};
 use sc_client_api::{ExecutorProvider, RemoteBackend};
 use sc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
 pub use sc_executor::NativeElseWasmExecutor;
@@ -14,7 +17,11 @@ use std::{sync::Arc, time::Duration};
 pub struct ExecutorDispatch;
 
 impl sc_executor::NativeExecutionDispatch for ExecutorDispatch {
-    type ExtendHostFunctions = frame_benchmarking::benchmarking::HostFunctions;
+    type ExtendHostFunctions = (
+        frame_benchmarking::benchmarking::HostFunctions,
+        pallet_utxo::verifier::script_verifier::HostFunctions,
+        pallet_utxo::verifier::verification_cache_api::HostFunctions,
+    );
 
     fn dispatch(method: &str, data: &[u8]) -> Option<Vec<u8>> {
         node_template_runtime::api::dispatch(method, data)
@@ -25,6 +32,24 @@ impl sc_executor::NativeExecutionDispatch for ExecutorDispatch {
     }
 }
 
+/// Number of transactions whose scripts and signatures the node remembers as valid.
+const VERIFICATION_CACHE_SIZE: usize = 16_384;
+
+/// Lets the runtime verify the scripts of the UTXO transactions on several threads, and skip
+/// verifying them at block import once the pool did.
+struct VerifierExtensionsFactory(VerificationCache);
+
+impl sc_client_api::execution_extensions::ExtensionsFactory for VerifierExtensionsFactory {
+    fn extensions_for(&self, _: sp_core::offchain::Capabilities) -> sp_externalities::Extensions {
+        let mut extensions = sp_externalities::Extensions::new();
+        extensions.register(ScriptVerifierExt(
+            verify_batch_parallel::<node_template_runtime::AccountId>,
+        ));
+        extensions.register(VerificationCacheExt(self.0.clone()));
+        extensions
+    }
+}
+
 type FullClient =
     sc_service::TFullClient<Block, RuntimeApi, NativeElseWasmExecutor<ExecutorDispatch>>;
 type FullBackend = sc_service::TFullBackend<Block>;
@@ -82,6 +107,11 @@ pub fn new_partial(
             executor,
         )?;
     let client = Arc::new(client);
+    client
+        .execution_extensions()
+        .set_extensions_factory(Box::new(VerifierExtensionsFactory(VerificationCache::new(
+            VERIFICATION_CACHE_SIZE,
+        ))));
 
     let telemetry = telemetry.map(|(worker, telemetry)| {
         task_manager.spawn_handle().spawn("telemetry", worker.run());
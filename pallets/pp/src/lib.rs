@@ -48,7 +48,7 @@ use pallet_contracts::chain_extension::{
 };
 use pp_api::ProgrammablePoolApi;
 use sp_core::{crypto::UncheckedFrom, Bytes, H256};
-use utxo_api::UtxoApi;
+use utxo_api::{Payout, UtxoApi};
 
 #[frame_support::pallet]
 pub mod pallet {
@@ -70,6 +70,15 @@ pub mod pallet {
         pub utxos: Vec<(H256, u128)>,
     }
 
+    /// ABI of a contract, the calls to the contract are checked against
+    #[derive(Clone, Encode, Decode, Eq, PartialEq, PartialOrd, Ord, RuntimeDebug)]
+    pub struct ContractAbi {
+        /// Hash of the ABI metadata of the contract
+        pub hash: H256,
+        /// Selectors of the messages of the contract
+        pub selectors: Vec<[u8; 4]>,
+    }
+
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
     pub struct Pallet<T>(_);
@@ -79,9 +88,25 @@ pub mod pallet {
     pub(super) type ContractBalances<T: Config> =
         StorageMap<_, Identity, T::AccountId, Option<ContractBalance>, ValueQuery>;
 
+    /// Account that deployed each contract, allowed to register its ABI
+    #[pallet::storage]
+    #[pallet::getter(fn contract_deployer)]
+    pub(super) type ContractDeployers<T: Config> =
+        StorageMap<_, Identity, T::AccountId, T::AccountId, OptionQuery>;
+
+    /// ABIs registered for the contracts
+    #[pallet::storage]
+    #[pallet::getter(fn contract_abi)]
+    pub(super) type ContractAbis<T: Config> =
+        StorageMap<_, Identity, T::AccountId, ContractAbi, OptionQuery>;
+
     #[pallet::event]
     #[pallet::metadata(T::AccountId = "AccountId")]
-    pub enum Event<T: Config> {}
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    pub enum Event<T: Config> {
+        /// The ABI of a contract was registered. \[contract, abi_hash\]
+        AbiRegistered(T::AccountId, H256),
+    }
 
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
@@ -98,6 +123,25 @@ pub mod pallet {
         ) -> DispatchResultWithPostInfo {
             T::Utxo::spend(&ensure_signed(origin)?, value, address, utxo, sig)
         }
+
+        /// Register the ABI of a contract, so that the calls to it are checked against the ABI
+        /// before they are paid for. Only the account that deployed the contract can do it.
+        #[pallet::weight(10_000)]
+        pub fn register_abi(
+            origin: OriginFor<T>,
+            contract: T::AccountId,
+            abi: ContractAbi,
+        ) -> DispatchResultWithPostInfo {
+            let signer = ensure_signed(origin)?;
+            ensure!(
+                <ContractDeployers<T>>::get(&contract) == Some(signer),
+                "Only the deployer can register the ABI of a contract"
+            );
+            let hash = abi.hash;
+            <ContractAbis<T>>::insert(&contract, abi);
+            Self::deposit_event(Event::<T>::AbiRegistered(contract, hash));
+            Ok(().into())
+        }
     }
 
     #[pallet::genesis_config]
@@ -120,6 +164,15 @@ pub mod pallet {
     }
 }
 
+/// Outpoints of the UTXOs the contract `contract` can currently spend, as tracked by the UTXO
+/// system. Unlike the UTXOs recorded in `ContractBalances`, the spent ones are left out.
+fn contract_outpoints<T: Config>(contract: &T::AccountId) -> Vec<H256> {
+    T::Utxo::contract_utxos(contract)
+        .into_iter()
+        .map(|(outpoint, _)| outpoint)
+        .collect()
+}
+
 /// Create Pay-to-Pubkey transaction from smart contract's UTXOs
 /// and send it by calling into the UTXO system.
 ///
@@ -142,7 +195,7 @@ fn send_p2pk_tx<T: Config>(
     let fund_info =
         <ContractBalances<T>>::get(&caller).ok_or(DispatchError::Other("Caller doesn't exist"))?;
     ensure!(fund_info.funds >= value, "Caller doesn't have enough funds");
-    let outpoints = fund_info.utxos.iter().map(|x| x.0).collect::<Vec<H256>>();
+    let outpoints = contract_outpoints::<T>(caller);
 
     T::Utxo::send_conscrit_p2pk(caller, dest, value, &outpoints)
 }
@@ -167,7 +220,7 @@ fn send_c2c_tx<T: Config>(
     let fund_info = <ContractBalances<T>>::get(caller).ok_or(DispatchError::Other(
         "Contract doesn't own any UTXO or it doesn't exist!",
     ))?;
-    let outpoints = fund_info.utxos.iter().map(|x| x.0).collect::<Vec<H256>>();
+    let outpoints = contract_outpoints::<T>(caller);
 
     T::Utxo::send_conscrit_c2c(caller, dest, fund_info.funds, data, &outpoints)
 }
@@ -204,6 +257,8 @@ where
             "Failed to instantiate smart contract"
         })?;
 
+        <ContractDeployers<T>>::insert(&res.account_id, caller);
+
         // Create balance entry for the smart contract
         <ContractBalances<T>>::insert(
             res.account_id,
@@ -254,12 +309,39 @@ where
 
         Ok(())
     }
+
+    fn verify_call(
+        dest: &T::AccountId,
+        abi_hash: Option<H256>,
+        input_data: &Vec<u8>,
+    ) -> Result<(), &'static str> {
+        <ContractBalances<T>>::get(&dest).ok_or("Contract doesn't exist!")?;
+        let abi = match <ContractAbis<T>>::get(dest) {
+            Some(abi) => abi,
+            None => {
+                ensure!(abi_hash.is_none(), "Contract has no registered ABI");
+                return Ok(());
+            }
+        };
+        if let Some(abi_hash) = abi_hash {
+            ensure!(abi_hash == abi.hash, "ABI hash doesn't match the contract");
+        }
+        // The message is selected by the first four bytes of the input
+        let selector = input_data.get(..4).ok_or("Call has no selector")?;
+        ensure!(
+            abi.selectors.iter().any(|known| &known[..] == selector),
+            "Unknown selector"
+        );
+        Ok(())
+    }
 }
 
 enum ChainExtensionCall {
     Transfer = 1000,
     Balance = 1001,
     Call = 1002,
+    Utxos = 1003,
+    Payout = 1004,
 }
 
 impl<T: pallet_contracts::Config + pallet::Config> ChainExtension<T> for Pallet<T> {
@@ -314,6 +396,23 @@ impl<T: pallet_contracts::Config + pallet::Config> ChainExtension<T> for Pallet<
                 // C2C transfers all funds as refunding to a contract is not possible (at least for now)
                 send_c2c_tx::<T>(&acc_id, &dest, &selector)?
             }
+            x if x == ChainExtensionCall::Utxos as u32 => {
+                // Lets a contract pick the UTXOs it pays out from
+                let mut env = env.buf_in_buf_out();
+                let acc_id: T::AccountId = env.read_as()?;
+                let utxos = T::Utxo::contract_utxos(&acc_id);
+
+                env.write(&utxos.encode(), false, None)
+                    .map_err(|_| DispatchError::Other("Failed to return value?"))?;
+            }
+            x if x == ChainExtensionCall::Payout as u32 => {
+                // The contract picks the UTXOs to spend, see `ChainExtensionCall::Utxos`
+                let mut env = env.buf_in_buf_out();
+                let (acc_id, outpoints, payouts): (T::AccountId, Vec<H256>, Vec<Payout>) =
+                    env.read_as_unbounded(env.in_len())?;
+
+                T::Utxo::send_conscrit_payouts(&acc_id, &payouts, &outpoints)?
+            }
             _ => {
                 log::error!("Called an unregistered `func_id`: {:}", func_id);
                 return Err(DispatchError::Other("Unimplemented function"));
@@ -16,8 +16,391 @@
 // Author(s): A. Altonen, Anton Sinitsyn
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use codec::{Decode, Encode};
+use frame_support::{dispatch::Vec, ensure};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::H256;
+use sp_runtime::traits::{BlakeTwo256, Hash};
+use sp_runtime::RuntimeDebug;
+
+/// Kind of destination an output pays to.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub enum ScriptType {
+    Pubkey,
+    CreatePP,
+    CallPP,
+    /// Pay to script hash of given script version
+    ScriptHash(u8),
+    LockForStaking,
+    LockExtraForStaking,
+    Taproot,
+    CreateStakingPool,
+    JoinStakingPool,
+    Vesting,
+    DataCarrier,
+    Auction,
+    Bid,
+    Order,
+    Stream,
+    Inheritance,
+}
+
+/// Amount of a token carried by an output.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct TokenAmount {
+    /// Base58 token id
+    pub token_id: Vec<u8>,
+    pub amount: u128,
+}
+
+/// Details of a transaction output, or of a UTXO spent by an input.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct OutputInspection {
+    /// MLT value
+    pub value: u128,
+    pub script_type: ScriptType,
+    /// Token carried by the output, `None` for plain MLT and multi-token outputs
+    pub token: Option<TokenAmount>,
+    /// Tokens carried by a multi-token output, empty for other outputs
+    pub tokens: Vec<TokenAmount>,
+}
+
+/// Details of a transaction input.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct InputInspection {
+    pub outpoint: H256,
+    /// The UTXO spent by the input, `None` if it is not in the UTXO set
+    pub utxo: Option<OutputInspection>,
+}
+
+/// Amounts of a token moved by a transaction.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct TokenDelta {
+    /// Base58 token id
+    pub token_id: Vec<u8>,
+    /// Amount spent by the inputs
+    pub spent: u128,
+    /// Amount created by the outputs, including newly issued tokens
+    pub created: u128,
+    /// Amount burned by the outputs
+    pub burned: u128,
+}
+
+/// Details of a transaction as seen by the runtime.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct TransactionInspection {
+    /// Transaction hash
+    pub id: H256,
+    pub inputs: Vec<InputInspection>,
+    pub outputs: Vec<OutputInspection>,
+    /// MLT fee paid, `None` if some inputs could not be resolved or outputs exceed inputs
+    pub fee: Option<u128>,
+    pub token_deltas: Vec<TokenDelta>,
+    /// Reason the transaction would be rejected right now, `None` if it is valid
+    pub validation_error: Option<Vec<u8>>,
+}
+
+/// Outcome of checking the witness of an input on its own.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub enum InputStatus {
+    /// The lock and the witness of the input are valid
+    Valid,
+    /// The spent UTXO is not in the UTXO set
+    Missing,
+    /// The witness can't be checked until all the UTXOs spent by the transaction are known
+    Unchecked,
+    /// Reason the input is invalid
+    Invalid(Vec<u8>),
+}
+
+/// Report of a transaction checked the way the transaction pool would check it, without
+/// submitting it.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct DryRunReport {
+    pub inspection: TransactionInspection,
+    /// Status of each input, in input order
+    pub inputs: Vec<InputStatus>,
+    /// Reason the transaction pool would reject the transaction if it was received from the
+    /// network, `None` if it would be accepted
+    pub pool_error: Option<Vec<u8>>,
+}
+
+/// A recent spend recorded in the STXO journal.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct SpentOutput {
+    /// Hash of the spending transaction
+    pub tx_hash: H256,
+    /// Block the output was spent in
+    pub block_number: u64,
+}
+
+/// An input of a transaction in a block spending an output that is not in the UTXO set, as
+/// recorded when the transaction was rejected.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct MissingInputProof {
+    /// Hash of the transaction with the input
+    pub tx_hash: H256,
+    pub outpoint: H256,
+    /// The spend of the output the input conflicts with, `None` if it is not in the STXO journal
+    pub spent_by: Option<SpentOutput>,
+}
+
+/// Balance of a pubkey recorded in a balance snapshot.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct VotingWeight {
+    /// Block the snapshot was taken at
+    pub snapshot_block: u64,
+    /// Aggregate MLT value of the pay-to-pubkey UTXOs
+    pub value: u128,
+}
+
+/// Stake unlocked by a stash account, waiting for the bonding duration to end.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct Unbonding {
+    /// MLT value being unbonded
+    pub value: u128,
+    /// Era from which the stake can be withdrawn
+    pub withdrawable_at_era: u32,
+}
+
+/// Stake locked by a stash account.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct StakingInfo {
+    /// Total MLT value of the locked UTXOs
+    pub locked_total: u128,
+    pub num_utxos: u64,
+    /// Outpoints of the locked UTXOs
+    pub outpoints: Vec<H256>,
+    /// Pending withdrawal, `None` unless the stake has been unlocked
+    pub unbonding: Option<Unbonding>,
+}
+
+/// Stake locked by a validator's stash account.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct ValidatorStake {
+    pub stash: H256,
+    /// Total MLT value of the locked UTXOs
+    pub locked_total: u128,
+    pub num_utxos: u64,
+}
+
+/// A transfer of an NFT between destinations.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct NftTransfer {
+    pub block_number: u64,
+    /// SCALE-encoded destination the NFT was sent from
+    pub from: Vec<u8>,
+    /// SCALE-encoded destination the NFT was sent to
+    pub to: Vec<u8>,
+}
+
+/// Statistics of the UTXO set, pruned UTXOs included.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, Default)]
+pub struct UtxoStats {
+    pub utxo_count: u64,
+    /// Total MLT value of the UTXOs
+    pub total_value: u128,
+    /// Base58 id of every token held by some UTXO, and the number of UTXOs holding it
+    pub token_utxo_counts: Vec<(Vec<u8>, u64)>,
+}
+
+/// A page of the UTXO set, in ascending outpoint order.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct UtxoPage {
+    /// Outpoints and SCALE-encoded outputs
+    pub utxos: Vec<(H256, Vec<u8>)>,
+    /// Start key of the next page, `None` if this is the last one
+    pub next_key: Option<H256>,
+}
+
+/// A page of the holders of a token, in ascending pubkey order.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct TokenHolderPage {
+    /// Pubkeys of the holders and their balance
+    pub holders: Vec<(H256, u128)>,
+    /// Number of holders of the token
+    pub holder_count: u32,
+    /// Cursor of the next page, `None` if this is the last one
+    pub next_cursor: Option<H256>,
+}
+
+/// Number of UTXOs in every chunk of an exported UTXO set but the last one.
+pub const UTXO_SET_CHUNK_SIZE: u32 = 1_000;
+
+/// A chunk of an exported UTXO set.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct UtxoSetChunk {
+    /// Outpoints and SCALE-encoded outputs, in ascending outpoint order
+    pub utxos: Vec<(H256, Vec<u8>)>,
+    /// Blake2b-256 hash of the SCALE-encoded `utxos`
+    pub hash: H256,
+}
+
+impl UtxoSetChunk {
+    pub fn new(utxos: Vec<(H256, Vec<u8>)>) -> Self {
+        Self {
+            hash: BlakeTwo256::hash_of(&utxos),
+            utxos,
+        }
+    }
+}
+
+/// The UTXO set at some block, serialized the same way on every node.
+///
+/// The UTXOs are split into chunks of [UTXO_SET_CHUNK_SIZE] in ascending outpoint order, and the
+/// commitment is the Blake2b-256 hash of the SCALE-encoded chunk hashes. Two nodes with the same
+/// UTXO set produce the same commitment, so comparing commitments is enough to audit them.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, Default)]
+pub struct UtxoSetExport {
+    pub chunks: Vec<UtxoSetChunk>,
+    pub commitment: H256,
+}
+
+impl UtxoSetExport {
+    pub fn new(chunks: Vec<UtxoSetChunk>) -> Self {
+        Self {
+            commitment: Self::commit(&chunks),
+            chunks,
+        }
+    }
+
+    fn commit(chunks: &[UtxoSetChunk]) -> H256 {
+        BlakeTwo256::hash_of(&chunks.iter().map(|chunk| chunk.hash).collect::<Vec<_>>())
+    }
+
+    /// Check the chunking, the chunk hashes and the commitment of the export.
+    pub fn verify(&self) -> Result<(), &'static str> {
+        if let Some((last, full)) = self.chunks.split_last() {
+            let chunk_size = UTXO_SET_CHUNK_SIZE as usize;
+            ensure!(
+                full.iter().all(|chunk| chunk.utxos.len() == chunk_size)
+                    && (1..=chunk_size).contains(&last.utxos.len()),
+                "bad chunk size"
+            );
+        }
+        ensure!(
+            self.chunks.iter().all(|chunk| chunk.hash == BlakeTwo256::hash_of(&chunk.utxos)),
+            "bad chunk hash"
+        );
+        let outpoints: Vec<&H256> = self.utxos().map(|(outpoint, _)| outpoint).collect();
+        ensure!(
+            outpoints.windows(2).all(|pair| pair[0] < pair[1]),
+            "outpoints not in ascending order"
+        );
+        ensure!(
+            self.commitment == Self::commit(&self.chunks),
+            "bad commitment"
+        );
+        Ok(())
+    }
+
+    /// All the exported UTXOs, in ascending outpoint order.
+    pub fn utxos(&self) -> impl Iterator<Item = (&H256, &Vec<u8>)> {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.utxos.iter().map(|(outpoint, utxo)| (outpoint, utxo)))
+    }
+}
+
 sp_api::decl_runtime_apis! {
     pub trait UtxoApi {
         fn send() -> u32;
+
+        /// Circulating supply of the token given by its base58 id, `None` if never issued
+        fn token_circulating_supply(token_id: Vec<u8>) -> Option<u128>;
+
+        /// MLT in circulation: the MLT issued on the UTXO ledger less the locked stake
+        fn circulating_supply() -> u128;
+
+        /// Decode a SCALE-encoded transaction and resolve its inputs against the UTXO set.
+        /// Returns an error message if the transaction can't be decoded.
+        fn decode_transaction(tx: Vec<u8>) -> Result<TransactionInspection, Vec<u8>>;
+
+        /// Check a SCALE-encoded transaction the way the transaction pool checks the
+        /// transactions it receives, reporting the status of each input, without submitting it.
+        /// Returns an error message if the transaction can't be decoded.
+        fn dry_run_spend(tx: Vec<u8>) -> Result<DryRunReport, Vec<u8>>;
+
+        /// Spend of the output at `outpoint`, `None` if it is unspent or was spent before the
+        /// STXO journal retention window
+        fn spent_output(outpoint: H256) -> Option<SpentOutput>;
+
+        /// Inputs of the transactions rejected in the block for spending outputs not in the UTXO
+        /// set, empty once the block falls out of the STXO journal retention window
+        fn missing_inputs(block_number: u64) -> Vec<MissingInputProof>;
+
+        /// Verify a SCALE-encoded reserve proof against the UTXO set. Returns the total value
+        /// of the UTXOs the signer controls, or the reason the proof is invalid.
+        fn verify_reserve_proof(proof: Vec<u8>) -> Result<u128, Vec<u8>>;
+
+        /// Voting weight of the pubkey at the given block, from the last balance snapshot taken
+        /// at or before it. `None` if no snapshot was taken by then.
+        fn voting_weight(pubkey: H256, block_number: u64) -> Option<VotingWeight>;
+
+        /// Stake locked by the stash account, `None` if the account doesn't stake
+        fn staking_info(stash: H256) -> Option<StakingInfo>;
+
+        /// Stakes of all the stash accounts with locked UTXOs
+        fn list_validator_stakes() -> Vec<ValidatorStake>;
+
+        /// Latest transfers of the NFT with given base58 token id, oldest first
+        fn nft_provenance(token_id: Vec<u8>) -> Vec<NftTransfer>;
+
+        /// Up to `limit` UTXOs in ascending outpoint order, starting right after `start_key`, or
+        /// from the first one if `None`
+        fn utxos_paged(start_key: Option<H256>, limit: u32) -> UtxoPage;
+
+        /// Up to `limit` UTXOs paying to the destination of the output descriptor, in ascending
+        /// outpoint order, starting right after `start_key`, or from the first one if `None`.
+        /// Only a bounded number of UTXOs is scanned per call, so a page may be short, even
+        /// empty, and still have a `next_key`. Returns an error message if the descriptor can't
+        /// be parsed.
+        fn scan_descriptor(
+            descriptor: Vec<u8>,
+            start_key: Option<H256>,
+            limit: u32,
+        ) -> Result<UtxoPage, Vec<u8>>;
+
+        /// Statistics of the UTXO set, read from storage without iterating over the UTXO set
+        fn utxo_stats() -> UtxoStats;
+
+        /// Up to `limit` holders of the token with given base58 token id, in ascending pubkey
+        /// order, starting right after `cursor`, or from the first one if `None`. Only the
+        /// fungible tokens paid to a pubkey are counted.
+        fn token_holders(token_id: Vec<u8>, cursor: Option<H256>, limit: u32) -> TokenHolderPage;
+
+        /// Outpoint of the unspent output at `index` of the transaction `txid`
+        fn outpoint_of(txid: H256, index: u32) -> Option<H256>;
+
+        /// Id of the transaction and index of the unspent output at `outpoint`
+        fn output_ref(outpoint: H256) -> Option<(H256, u32)>;
+
+        /// Check a token can be issued with the metadata. Returns the reason it can't otherwise.
+        fn validate_token_metadata(
+            ticker: Vec<u8>,
+            number_of_decimals: u8,
+            metadata_uri: Vec<u8>,
+        ) -> Result<(), Vec<u8>>;
     }
 }
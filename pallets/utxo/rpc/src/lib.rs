@@ -17,9 +17,14 @@
 
 use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
 use jsonrpc_derive::rpc;
-pub use pallet_utxo_rpc_runtime_api::UtxoApi as UtxoRuntimeApi;
+pub use pallet_utxo_rpc_runtime_api::{
+    DryRunReport, MissingInputProof, NftTransfer, SpentOutput, StakingInfo, TokenHolderPage,
+    TransactionInspection, UtxoApi as UtxoRuntimeApi, UtxoPage, UtxoSetChunk, UtxoSetExport,
+    UtxoStats, ValidatorStake, VotingWeight, UTXO_SET_CHUNK_SIZE,
+};
 use sp_api::ProvideRuntimeApi;
 use sp_blockchain::HeaderBackend;
+use sp_core::{Bytes, H256};
 use sp_runtime::{generic::BlockId, traits::Block as BlockT};
 use std::sync::Arc;
 
@@ -27,6 +32,100 @@ use std::sync::Arc;
 pub trait UtxoApi<BlockHash> {
     #[rpc(name = "utxo_send")]
     fn send(&self, at: Option<BlockHash>) -> Result<u32>;
+
+    #[rpc(name = "utxo_tokenCirculatingSupply")]
+    fn token_circulating_supply(
+        &self,
+        token_id: String,
+        at: Option<BlockHash>,
+    ) -> Result<Option<u128>>;
+
+    #[rpc(name = "utxo_circulatingSupply")]
+    fn circulating_supply(&self, at: Option<BlockHash>) -> Result<u128>;
+
+    #[rpc(name = "utxo_decodeTransaction")]
+    fn decode_transaction(&self, tx: Bytes, at: Option<BlockHash>)
+        -> Result<TransactionInspection>;
+
+    #[rpc(name = "utxo_dryRunSpend")]
+    fn dry_run_spend(&self, tx: Bytes, at: Option<BlockHash>) -> Result<DryRunReport>;
+
+    #[rpc(name = "utxo_spentOutput")]
+    fn spent_output(&self, outpoint: H256, at: Option<BlockHash>) -> Result<Option<SpentOutput>>;
+
+    #[rpc(name = "utxo_missingInputs")]
+    fn missing_inputs(
+        &self,
+        block_number: u64,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<MissingInputProof>>;
+
+    #[rpc(name = "utxo_verifyReserveProof")]
+    fn verify_reserve_proof(&self, proof: Bytes, at: Option<BlockHash>) -> Result<u128>;
+
+    #[rpc(name = "utxo_votingWeight")]
+    fn voting_weight(
+        &self,
+        pubkey: H256,
+        block_number: u64,
+        at: Option<BlockHash>,
+    ) -> Result<Option<VotingWeight>>;
+
+    #[rpc(name = "utxo_stakingInfo")]
+    fn staking_info(&self, stash: H256, at: Option<BlockHash>) -> Result<Option<StakingInfo>>;
+
+    #[rpc(name = "utxo_listValidatorStakes")]
+    fn list_validator_stakes(&self, at: Option<BlockHash>) -> Result<Vec<ValidatorStake>>;
+
+    #[rpc(name = "utxo_nftProvenance")]
+    fn nft_provenance(&self, token_id: String, at: Option<BlockHash>) -> Result<Vec<NftTransfer>>;
+
+    #[rpc(name = "utxo_utxosPaged")]
+    fn utxos_paged(
+        &self,
+        start_key: Option<H256>,
+        limit: u32,
+        at: Option<BlockHash>,
+    ) -> Result<UtxoPage>;
+
+    #[rpc(name = "utxo_scanDescriptor")]
+    fn scan_descriptor(
+        &self,
+        descriptor: String,
+        start_key: Option<H256>,
+        limit: u32,
+        at: Option<BlockHash>,
+    ) -> Result<UtxoPage>;
+
+    #[rpc(name = "utxo_exportUtxoSet")]
+    fn export_utxo_set(&self, at: Option<BlockHash>) -> Result<UtxoSetExport>;
+
+    #[rpc(name = "utxo_stats")]
+    fn utxo_stats(&self, at: Option<BlockHash>) -> Result<UtxoStats>;
+
+    #[rpc(name = "utxo_tokenHolders")]
+    fn token_holders(
+        &self,
+        token_id: String,
+        cursor: Option<H256>,
+        limit: u32,
+        at: Option<BlockHash>,
+    ) -> Result<TokenHolderPage>;
+
+    #[rpc(name = "utxo_outpointOf")]
+    fn outpoint_of(&self, txid: H256, index: u32, at: Option<BlockHash>) -> Result<Option<H256>>;
+
+    #[rpc(name = "utxo_outputRef")]
+    fn output_ref(&self, outpoint: H256, at: Option<BlockHash>) -> Result<Option<(H256, u32)>>;
+
+    #[rpc(name = "utxo_validateTokenMetadata")]
+    fn validate_token_metadata(
+        &self,
+        ticker: String,
+        number_of_decimals: u8,
+        metadata_uri: String,
+        at: Option<BlockHash>,
+    ) -> Result<()>;
 }
 
 /// A struct that implements the [`UtxoApi`].
@@ -75,4 +174,371 @@ where
             data: Some(format!("{:?}", e).into()),
         })
     }
+
+    fn token_circulating_supply(
+        &self,
+        token_id: String,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<u128>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.token_circulating_supply(&at, token_id.into_bytes()).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query circulating supply.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn circulating_supply(&self, at: Option<<Block as BlockT>::Hash>) -> Result<u128> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.circulating_supply(&at).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query circulating supply.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn decode_transaction(
+        &self,
+        tx: Bytes,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<TransactionInspection> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        let inspection = api.decode_transaction(&at, tx.to_vec()).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to inspect transaction.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })?;
+        inspection.map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::DecodeError as i64),
+            message: "Unable to decode transaction.".into(),
+            data: Some(String::from_utf8_lossy(&e).into_owned().into()),
+        })
+    }
+
+    fn dry_run_spend(
+        &self,
+        tx: Bytes,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<DryRunReport> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        let report = api.dry_run_spend(&at, tx.to_vec()).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to dry run transaction.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })?;
+        report.map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::DecodeError as i64),
+            message: "Unable to decode transaction.".into(),
+            data: Some(String::from_utf8_lossy(&e).into_owned().into()),
+        })
+    }
+
+    fn spent_output(
+        &self,
+        outpoint: H256,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<SpentOutput>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.spent_output(&at, outpoint).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query spent output.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn missing_inputs(
+        &self,
+        block_number: u64,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<MissingInputProof>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.missing_inputs(&at, block_number).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query missing inputs.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn verify_reserve_proof(
+        &self,
+        proof: Bytes,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<u128> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        let value = api.verify_reserve_proof(&at, proof.to_vec()).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to verify reserve proof.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })?;
+        value.map_err(|e| RpcError {
+            code: ErrorCode::InvalidParams,
+            message: "Invalid reserve proof.".into(),
+            data: Some(String::from_utf8_lossy(&e).into_owned().into()),
+        })
+    }
+
+    fn voting_weight(
+        &self,
+        pubkey: H256,
+        block_number: u64,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<VotingWeight>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.voting_weight(&at, pubkey, block_number).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query voting weight.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn staking_info(
+        &self,
+        stash: H256,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<StakingInfo>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.staking_info(&at, stash).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query staking info.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn list_validator_stakes(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<ValidatorStake>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.list_validator_stakes(&at).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query validator stakes.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn nft_provenance(
+        &self,
+        token_id: String,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<NftTransfer>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.nft_provenance(&at, token_id.into_bytes()).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query nft provenance.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn utxos_paged(
+        &self,
+        start_key: Option<H256>,
+        limit: u32,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<UtxoPage> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.utxos_paged(&at, start_key, limit).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query utxos.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn scan_descriptor(
+        &self,
+        descriptor: String,
+        start_key: Option<H256>,
+        limit: u32,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<UtxoPage> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        let result =
+            api.scan_descriptor(&at, descriptor.into_bytes(), start_key, limit)
+                .map_err(|e| RpcError {
+                    code: ErrorCode::ServerError(Error::RuntimeError as i64),
+                    message: "Unable to scan utxos.".into(),
+                    data: Some(format!("{:?}", e).into()),
+                })?;
+        result.map_err(|e| RpcError {
+            code: ErrorCode::InvalidParams,
+            message: "Invalid descriptor.".into(),
+            data: Some(String::from_utf8_lossy(&e).into_owned().into()),
+        })
+    }
+
+    fn export_utxo_set(&self, at: Option<<Block as BlockT>::Hash>) -> Result<UtxoSetExport> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        // Every chunk is a full page, read at the same block
+        let mut chunks = Vec::new();
+        let mut start_key = None;
+        loop {
+            let page =
+                api.utxos_paged(&at, start_key, UTXO_SET_CHUNK_SIZE).map_err(|e| RpcError {
+                    code: ErrorCode::ServerError(Error::RuntimeError as i64),
+                    message: "Unable to export the utxo set.".into(),
+                    data: Some(format!("{:?}", e).into()),
+                })?;
+            if !page.utxos.is_empty() {
+                chunks.push(UtxoSetChunk::new(page.utxos));
+            }
+            match page.next_key {
+                Some(next_key) => start_key = Some(next_key),
+                None => return Ok(UtxoSetExport::new(chunks)),
+            }
+        }
+    }
+
+    fn utxo_stats(&self, at: Option<<Block as BlockT>::Hash>) -> Result<UtxoStats> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.utxo_stats(&at).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query utxo set statistics.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn token_holders(
+        &self,
+        token_id: String,
+        cursor: Option<H256>,
+        limit: u32,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<TokenHolderPage> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.token_holders(&at, token_id.into_bytes(), cursor, limit)
+            .map_err(|e| RpcError {
+                code: ErrorCode::ServerError(Error::RuntimeError as i64),
+                message: "Unable to query token holders.".into(),
+                data: Some(format!("{:?}", e).into()),
+            })
+    }
+
+    fn outpoint_of(
+        &self,
+        txid: H256,
+        index: u32,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<H256>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.outpoint_of(&at, txid, index).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query the outpoint.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn output_ref(
+        &self,
+        outpoint: H256,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<(H256, u32)>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.output_ref(&at, outpoint).map_err(|e| RpcError {
+            code: ErrorCode::ServerError(Error::RuntimeError as i64),
+            message: "Unable to query the output reference.".into(),
+            data: Some(format!("{:?}", e).into()),
+        })
+    }
+
+    fn validate_token_metadata(
+        &self,
+        ticker: String,
+        number_of_decimals: u8,
+        metadata_uri: String,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<()> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        let result = api
+            .validate_token_metadata(
+                &at,
+                ticker.into_bytes(),
+                number_of_decimals,
+                metadata_uri.into_bytes(),
+            )
+            .map_err(|e| RpcError {
+                code: ErrorCode::ServerError(Error::RuntimeError as i64),
+                message: "Unable to validate token metadata.".into(),
+                data: Some(format!("{:?}", e).into()),
+            })?;
+        result.map_err(|e| RpcError {
+            code: ErrorCode::InvalidParams,
+            message: "Invalid token metadata.".into(),
+            data: Some(String::from_utf8_lossy(&e).into_owned().into()),
+        })
+    }
 }
@@ -0,0 +1,166 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SS58 addresses
+//!
+//! Destinations are addressed with bech32-encoded [Destination]s, see [crate::decode_address].
+//! As a convenience for users of the existing Substrate tooling, a pay-to-pubkey destination can
+//! also be given by the SS58 address of the account, i.e. the base58 encoding of:
+//! * the network prefix, in one byte below 64 and in two bytes up to 16383,
+//! * the 32 byte public key,
+//! * the first two bytes of `blake2_512(b"SS58PRE" ++ prefix ++ public key)` as checksum.
+
+use crate::{decode_address, Destination};
+use base58_nostd::{FromBase58, ToBase58};
+use codec::Decode;
+use frame_support::{ensure, sp_io::hashing::blake2_512};
+use sp_core::H256;
+use sp_std::prelude::*;
+
+/// Longest SS58 address of a 32 byte public key
+const MAX_SS58_LEN: usize = 50;
+
+fn checksum(payload: &[u8]) -> [u8; 2] {
+    let hash = blake2_512(&[&b"SS58PRE"[..], payload].concat());
+    [hash[0], hash[1]]
+}
+
+fn encode_prefix(prefix: u16) -> Vec<u8> {
+    match prefix {
+        0..=63 => vec![prefix as u8],
+        _ => {
+            let first = ((prefix & 0b0000_0000_1111_1100) as u8) >> 2;
+            let second = ((prefix >> 8) as u8) | (((prefix & 0b0000_0000_0000_0011) as u8) << 6);
+            vec![first | 0b0100_0000, second]
+        }
+    }
+}
+
+/// Encode `pubkey` into its SS58 address on the network `prefix`. Prefixes above 16383 can't be
+/// encoded.
+pub fn encode_ss58(pubkey: &H256, prefix: u16) -> Result<Vec<u8>, &'static str> {
+    ensure!(prefix <= 16383, "SS58 prefix out of range");
+    let mut payload = encode_prefix(prefix);
+    payload.extend_from_slice(pubkey.as_bytes());
+    let checksum = checksum(&payload);
+    payload.extend_from_slice(&checksum);
+    Ok(payload.to_base58())
+}
+
+/// Decode an SS58 address into its network prefix and public key.
+pub fn decode_ss58(address: &[u8]) -> Result<(u16, H256), &'static str> {
+    ensure!(address.len() <= MAX_SS58_LEN, "invalid SS58 address length");
+    let address = core::str::from_utf8(address).map_err(|_| "invalid SS58 address")?;
+    let data = address.from_base58().map_err(|_| "invalid SS58 address")?;
+    let (prefix, prefix_len) = match data.first() {
+        Some(0..=63) => (data[0] as u16, 1),
+        Some(64..=127) if data.len() > 1 => {
+            let lower = (data[0] << 2) | (data[1] >> 6);
+            let upper = data[1] & 0b0011_1111;
+            (lower as u16 | (upper as u16) << 8, 2)
+        }
+        _ => return Err("invalid SS58 prefix"),
+    };
+    ensure!(
+        data.len() == prefix_len + 32 + 2,
+        "invalid SS58 address length"
+    );
+    let (payload, provided) = data.split_at(prefix_len + 32);
+    ensure!(checksum(payload) == provided, "invalid SS58 checksum");
+    Ok((prefix, H256::from_slice(&payload[prefix_len..])))
+}
+
+/// Encode the SS58 address of a pay-to-pubkey destination on the network `prefix`. Other
+/// destinations have no SS58 address.
+pub fn encode_ss58_for_destination<AccountId>(
+    destination: &Destination<AccountId>,
+    prefix: u16,
+) -> Result<Vec<u8>, &'static str> {
+    match destination {
+        Destination::Pubkey(pubkey) => encode_ss58(&H256::from(pubkey.0), prefix),
+        _ => Err("destination has no SS58 address"),
+    }
+}
+
+/// Decode a bech32 address, or the SS58 address of an account on the network `prefix`, into the
+/// destination it pays to. The SS58 address of an account pays to its public key.
+pub fn decode_destination<AccountId: Decode>(
+    address: &[u8],
+    prefix: u16,
+) -> Result<Destination<AccountId>, &'static str> {
+    match decode_ss58(address) {
+        Ok((address_prefix, pubkey)) => {
+            ensure!(address_prefix == prefix, "SS58 address of another network");
+            Ok(Destination::Pubkey(pubkey.into()))
+        }
+        // Report why the address is not a valid bech32 address, the more common format
+        Err(_) => decode_address(address),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex_literal::hex;
+
+    const ALICE: [u8; 32] =
+        hex!("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d");
+    const ALICE_SS58: &[u8] = b"5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+    #[test]
+    fn test_ss58_vectors() {
+        let alice = H256::from(ALICE);
+        assert_eq!(encode_ss58(&alice, 42), Ok(ALICE_SS58.to_vec()));
+        assert_eq!(decode_ss58(ALICE_SS58), Ok((42, alice)));
+        for prefix in [0, 63, 64, 255, 16383] {
+            let address = encode_ss58(&alice, prefix).unwrap();
+            assert_eq!(decode_ss58(&address), Ok((prefix, alice)));
+        }
+        assert_eq!(encode_ss58(&alice, 16384), Err("SS58 prefix out of range"));
+
+        let mut corrupted = ALICE_SS58.to_vec();
+        corrupted[10] = b'z';
+        assert_eq!(decode_ss58(&corrupted), Err("invalid SS58 checksum"));
+        assert_eq!(
+            decode_ss58(&[b'1'; 200]),
+            Err("invalid SS58 address length")
+        );
+    }
+
+    #[test]
+    fn test_ss58_destinations() {
+        let alice = Destination::<H256>::Pubkey(H256::from(ALICE).into());
+        assert_eq!(
+            encode_ss58_for_destination(&alice, 42),
+            Ok(ALICE_SS58.to_vec())
+        );
+        assert_eq!(
+            encode_ss58_for_destination(&Destination::<H256>::DataCarrier(vec![]), 42),
+            Err("destination has no SS58 address")
+        );
+        assert_eq!(decode_destination::<H256>(ALICE_SS58, 42), Ok(alice));
+        assert_eq!(
+            decode_destination::<H256>(ALICE_SS58, 0),
+            Err("SS58 address of another network")
+        );
+        assert_eq!(
+            decode_destination::<H256>(
+                b"bc1qrft7juyfhl06emj4zzrue5ljs6q39n2jalr4c40rhtcur647n0kwueyfsn",
+                42
+            ),
+            Err("Failed to decode address: invalid HRP")
+        );
+    }
+}
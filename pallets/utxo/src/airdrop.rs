@@ -0,0 +1,78 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Merkle airdrops
+//!
+//! Rather than creating a genesis UTXO for every recipient, an airdrop is committed to by the
+//! Merkle root of its `(pubkey, amount)` entries, kept in `AirdropRoot`. The root is set at
+//! genesis or by the root origin with `set_airdrop_root`. A recipient claims their entry with the
+//! `claim` extrinsic and a Merkle proof, and a UTXO paying `amount` MLT to their pubkey is minted.
+//! Each pubkey can claim once per airdrop root, the claims are kept in `AirdropClaims`.
+//!
+//! The tree is built like the script trees of [crate::taproot], the leaves being the hashes of
+//! the SCALE-encoded entries.
+
+use crate::taproot;
+use crate::tokens::Value;
+use codec::Encode;
+use sp_core::H256;
+use sp_std::prelude::*;
+
+/// Leaf of the airdrop tree granting `amount` MLT to `pubkey`.
+pub fn leaf_hash(pubkey: &H256, amount: Value) -> H256 {
+    taproot::leaf_hash(&(pubkey, amount).encode())
+}
+
+/// Merkle root of the airdrop granting every pubkey the paired amount, `None` if there are no
+/// entries.
+pub fn airdrop_root(entries: &[(H256, Value)]) -> Option<H256> {
+    let leaves: Vec<H256> =
+        entries.iter().map(|(pubkey, amount)| leaf_hash(pubkey, *amount)).collect();
+    taproot::merkle_root(&leaves)
+}
+
+/// Merkle proof of the entry at `index`, to be passed to the `claim` extrinsic.
+pub fn claim_proof(entries: &[(H256, Value)], index: usize) -> Option<Vec<H256>> {
+    let leaves: Vec<H256> =
+        entries.iter().map(|(pubkey, amount)| leaf_hash(pubkey, *amount)).collect();
+    taproot::merkle_proof(&leaves, index)
+}
+
+/// Whether `proof` shows that the airdrop committed to by `root` grants `amount` to `pubkey`.
+pub fn verify_claim(root: &H256, pubkey: &H256, amount: Value, proof: &[H256]) -> bool {
+    taproot::root_from_proof(leaf_hash(pubkey, amount), proof) == *root
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_claim_proofs() {
+        let entries: Vec<(H256, Value)> =
+            (1u8..=5).map(|i| (H256::repeat_byte(i), i as Value * 100)).collect();
+        let root = airdrop_root(&entries).unwrap();
+        for (index, (pubkey, amount)) in entries.iter().enumerate() {
+            let proof = claim_proof(&entries, index).unwrap();
+            assert!(verify_claim(&root, pubkey, *amount, &proof));
+            // The amount is committed to
+            assert!(!verify_claim(&root, pubkey, amount + 1, &proof));
+        }
+        let proof = claim_proof(&entries, 0).unwrap();
+        assert!(!verify_claim(&root, &entries[1].0, entries[0].1, &proof));
+        assert_eq!(claim_proof(&entries, 5), None);
+        assert_eq!(airdrop_root(&[]), None);
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! NFT auctions
+//!
+//! A `Destination::Auction` output escrows an NFT until its `end` block, without a custodian.
+//! Bids are `Destination::Bid` outputs locking MLT for an auction. A bid has to reach the reserve
+//! price of the auction and exceed its best bid so far, which is refunded to its bidder as a new
+//! pay-to-pubkey UTXO. In `on_finalize` of the `end` block, the NFT goes to the best bidder and
+//! the bid to the seller, less the royalty of the NFT, or the NFT goes back to the seller if nobody
+//! bid. Neither auctions nor bids can be spent by transactions.
+
+use crate::pallet::{create_utxo, insert_utxo, nft_data_hash, record_nft_transfer, take_utxo};
+use crate::{
+    tokens::{OutputData, Value},
+    AuctionsEnding, BestBids, Config, Destination, Event, NftRoyalties, NftUniqueDataHash, Pallet,
+    TransactionFor, TransactionOutput, TransactionOutputFor, UtxoStore,
+};
+use frame_support::{
+    ensure, fail,
+    weights::{DispatchClass, Weight},
+};
+use sp_core::H256;
+use sp_runtime::traits::{BlakeTwo256, Hash, UniqueSaturatedInto};
+
+fn current_block<T: Config>() -> u64 {
+    <frame_system::Pallet<T>>::block_number().unique_saturated_into()
+}
+
+/// Check an output opening an auction.
+pub(crate) fn validate_auction<T: Config>(
+    output: &TransactionOutputFor<T>,
+) -> Result<(), &'static str> {
+    let end = match output.destination {
+        Destination::Auction { end, .. } => end,
+        _ => fail!("not an auction output"),
+    };
+    ensure!(output.value == 0, "auction output can't carry MLT");
+    match &output.data {
+        Some(OutputData::TokenTransferV1 {
+            token_id,
+            amount: 1,
+        }) if nft_data_hash::<T>(token_id).is_some() => {}
+        _ => fail!("only a single nft can be auctioned"),
+    }
+    ensure!(end > current_block::<T>(), "auction must end in the future");
+    Ok(())
+}
+
+/// Check a bid output of `tx` against the current state of its auction.
+pub(crate) fn validate_bid<T: Config>(
+    tx: &TransactionFor<T>,
+    output: &TransactionOutputFor<T>,
+) -> Result<(), &'static str> {
+    let auction = match output.destination {
+        Destination::Bid { auction, .. } => auction,
+        _ => fail!("not a bid output"),
+    };
+    ensure!(output.data.is_none(), "only MLT can be bid");
+    let (reserve_price, end) = match <UtxoStore<T>>::get(auction).map(|utxo| utxo.destination) {
+        Some(Destination::Auction {
+            reserve_price, end, ..
+        }) => (reserve_price, end),
+        _ => fail!("auction not found"),
+    };
+    ensure!(current_block::<T>() <= end, "auction has ended");
+    ensure!(
+        output.value >= reserve_price,
+        "bid is below the reserve price"
+    );
+    if let Some(best) = <BestBids<T>>::get(auction).and_then(<UtxoStore<T>>::get) {
+        ensure!(output.value > best.value, "bid must exceed the best bid");
+    }
+    let bids = tx
+        .outputs
+        .iter()
+        .filter(|other| match other.destination {
+            Destination::Bid { auction: a, .. } => a == auction,
+            _ => false,
+        })
+        .count();
+    ensure!(bids == 1, "only one bid per auction in a transaction");
+    Ok(())
+}
+
+/// Point the digital data of the NFT carried by `output` at the UTXO holding it.
+fn track_nft<T: Config>(hash: H256, output: &TransactionOutputFor<T>) {
+    if let Some(OutputData::TokenTransferV1 { token_id, .. }) = &output.data {
+        if let Some(data_hash) = nft_data_hash::<T>(token_id) {
+            <NftUniqueDataHash<T>>::insert(data_hash, hash);
+        }
+    }
+}
+
+/// Pay the winning bid of the auction at `hash` to the seller, and the royalty due on it to the
+/// beneficiary of the NFT. Returns the weight of the hooks.
+fn pay_seller<T: Config>(
+    hash: H256,
+    auction: &TransactionOutputFor<T>,
+    seller: H256,
+    bid: Value,
+) -> Weight {
+    let mut weight: Weight = 0;
+    let royalty = match &auction.data {
+        Some(OutputData::TokenTransferV1 { token_id, .. }) => <NftRoyalties<T>>::get(token_id),
+        _ => None,
+    };
+    let mut proceeds = bid;
+    if let Some(royalty) = royalty.filter(|royalty| H256::from(royalty.beneficiary) != seller) {
+        let due = royalty.amount_due(bid).min(bid);
+        if due > 0 {
+            let beneficiary = H256::from(royalty.beneficiary);
+            let payment = TransactionOutput::new_pubkey(due, beneficiary);
+            let payment_hash = BlakeTwo256::hash_of(&(&hash, "auction royalty"));
+            weight = weight.saturating_add(create_utxo::<T>(payment_hash, &payment));
+            proceeds -= due;
+        }
+    }
+    if proceeds > 0 {
+        let payment = TransactionOutput::new_pubkey(proceeds, seller);
+        let payment_hash = BlakeTwo256::hash_of(&(&hash, "auction proceeds"));
+        weight = weight.saturating_add(create_utxo::<T>(payment_hash, &payment));
+    }
+    weight
+}
+
+/// Escrow the NFT of a validated auction output until the auction ends.
+pub(crate) fn open_auction<T: Config>(hash: H256, output: &TransactionOutputFor<T>) {
+    if let Destination::Auction { end, .. } = output.destination {
+        track_nft::<T>(hash, output);
+        insert_utxo::<T>(hash, output, None);
+        <AuctionsEnding<T>>::append(end, hash);
+        <Pallet<T>>::deposit_event(Event::<T>::AuctionOpened(hash, end));
+    }
+}
+
+/// Make a validated bid output the best bid of its auction, refunding the previous one. Returns
+/// the weight of the hooks called on the refund.
+pub(crate) fn place_bid<T: Config>(hash: H256, output: &TransactionOutputFor<T>) -> Weight {
+    let mut weight: Weight = 0;
+    if let Destination::Bid { auction, .. } = output.destination {
+        let previous = <BestBids<T>>::get(auction)
+            .and_then(|previous| Some((previous, take_utxo::<T>(previous)?)));
+        if let Some((previous, (bid, spent_weight))) = previous {
+            weight = weight.saturating_add(spent_weight);
+            if let Destination::Bid { bidder, .. } = bid.destination {
+                let refund = TransactionOutput::new_pubkey(bid.value, H256::from(bidder));
+                let refund_hash = BlakeTwo256::hash_of(&(&previous, "bid refund"));
+                weight = weight.saturating_add(create_utxo::<T>(refund_hash, &refund));
+            }
+        }
+        insert_utxo::<T>(hash, output, None);
+        <BestBids<T>>::insert(auction, hash);
+        <Pallet<T>>::deposit_event(Event::<T>::BidPlaced(auction, hash, output.value));
+    }
+    weight
+}
+
+/// Settle the auctions ending at `block_number`: the NFT goes to the best bidder and the bid to
+/// the seller and the royalty beneficiary, unsold NFTs go back to the seller.
+pub(crate) fn settle_auctions<T: Config>(block_number: T::BlockNumber) {
+    let block_number: u64 = block_number.unique_saturated_into();
+    let mut weight: Weight = 0;
+    for hash in <AuctionsEnding<T>>::take(block_number) {
+        let auction = match take_utxo::<T>(hash) {
+            Some((auction, spent_weight)) => {
+                weight = weight.saturating_add(spent_weight);
+                auction
+            }
+            None => continue,
+        };
+        let seller = match auction.destination {
+            Destination::Auction { seller, .. } => seller,
+            _ => continue,
+        };
+        let winning_bid = <BestBids<T>>::take(hash).and_then(|bid_hash| {
+            let (bid, spent_weight) = take_utxo::<T>(bid_hash)?;
+            weight = weight.saturating_add(spent_weight);
+            Some((bid_hash, bid))
+        });
+        let buyer = match &winning_bid {
+            Some((_, bid)) => match bid.destination {
+                Destination::Bid { bidder, .. } => {
+                    let paid_weight =
+                        pay_seller::<T>(hash, &auction, H256::from(seller), bid.value);
+                    weight = weight.saturating_add(paid_weight);
+                    bidder
+                }
+                _ => seller,
+            },
+            None => seller,
+        };
+
+        let nft = TransactionOutput {
+            value: 0,
+            destination: Destination::Pubkey(buyer),
+            data: auction.data,
+        };
+        let nft_hash = BlakeTwo256::hash_of(&(&hash, "auction sale"));
+        track_nft::<T>(nft_hash, &nft);
+        if let Some(OutputData::TokenTransferV1 { token_id, .. }) = &nft.data {
+            record_nft_transfer::<T>(
+                token_id,
+                Destination::Pubkey(seller),
+                nft.destination.clone(),
+            );
+        }
+        weight = weight.saturating_add(create_utxo::<T>(nft_hash, &nft));
+        <Pallet<T>>::deposit_event(Event::<T>::AuctionSettled(
+            hash,
+            winning_bid.map(|(bid_hash, _)| bid_hash),
+        ));
+    }
+    if weight > 0 {
+        <frame_system::Pallet<T>>::register_extra_weight_unchecked(
+            weight,
+            DispatchClass::Mandatory,
+        );
+    }
+}
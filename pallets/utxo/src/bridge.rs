@@ -0,0 +1,74 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Token bridge hooks
+//!
+//! An output with `OutputData::TokenLockForBridgeV1` data puts tokens under bridge custody. The
+//! output is kept in `BridgeCustody` instead of the UTXO set, and the `TokensLockedForBridge` event
+//! tells the relayers to mint the tokens on the target chain. When the tokens come back, an
+//! authorized relayer calls `release_from_bridge` with a proof and the locked tokens are paid to a
+//! pubkey again. The UTXO accounting lives in this pallet, the bridge logic is plugged in through
+//! `Config::BridgeHandler`.
+
+use crate::tokens::{TokenId, Value};
+use sp_core::H256;
+
+/// Maximum length of the address on the target chain the tokens are locked for.
+pub const MAX_TARGET_ADDRESS_LEN: usize = 64;
+
+/// Bridge logic outside of this pallet.
+pub trait BridgeHandler<AccountId> {
+    /// Whether tokens can be locked for the given chain.
+    fn supports_chain(target_chain: u32) -> bool;
+
+    /// Called once tokens are locked under bridge custody at `outpoint`.
+    fn on_lock(
+        outpoint: &H256,
+        token_id: &TokenId,
+        amount: Value,
+        target_chain: u32,
+        target_address: &[u8],
+    );
+
+    /// Whether the account is allowed to release locked tokens.
+    fn is_relayer(account: &AccountId) -> bool;
+
+    /// Whether `proof` shows that the tokens locked at `outpoint` are to be released to `pubkey`.
+    fn verify_release(outpoint: &H256, pubkey: &H256, proof: &[u8]) -> bool;
+}
+
+/// No bridge, tokens can't be locked.
+impl<AccountId> BridgeHandler<AccountId> for () {
+    fn supports_chain(_target_chain: u32) -> bool {
+        false
+    }
+
+    fn on_lock(
+        _outpoint: &H256,
+        _token_id: &TokenId,
+        _amount: Value,
+        _target_chain: u32,
+        _target_address: &[u8],
+    ) {
+    }
+
+    fn is_relayer(_account: &AccountId) -> bool {
+        false
+    }
+
+    fn verify_release(_outpoint: &H256, _pubkey: &H256, _proof: &[u8]) -> bool {
+        false
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transaction builder
+//!
+//! A [TxBuilder] assembles a transaction from the UTXOs it spends and the payments it makes,
+//! keeping track of the spent outputs so that the change and the signatures don't have to be
+//! worked out by hand:
+//! ```ignore
+//! let tx = TxBuilder::new()
+//!     .add_input_from_utxo(outpoint, utxo)
+//!     .pay_to_address(value, address)?
+//!     .add_change(pubkey, fee)?
+//!     .finalize_and_sign()?;
+//! ```
+//!
+//! Inputs spending a `Destination::Pubkey`, a `Destination::Vesting`, a `Destination::Stream`,
+//! the owner of a `Destination::Inheritance` or the key path of a `Destination::Taproot` are
+//! signed with keys from the keystore. Transactions spending other
+//! destinations are better assembled with [crate::psbt::PartiallySignedTransaction].
+
+use crate::{
+    decode_address,
+    tokens::{OutputData, TokenId, Value},
+    Destination, RawBlockTime, Transaction, TransactionInput, TransactionOutput,
+};
+use codec::{Decode, Encode};
+use frame_support::ensure;
+use sp_core::H256;
+use sp_std::collections::btree_map::BTreeMap;
+use sp_std::prelude::*;
+
+/// A transaction under construction, together with the UTXOs it spends.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TxBuilder<AccountId> {
+    inputs: Vec<TransactionInput>,
+    utxos: Vec<TransactionOutput<AccountId>>,
+    outputs: Vec<TransactionOutput<AccountId>>,
+    time_lock: RawBlockTime,
+}
+
+impl<AccountId> Default for TxBuilder<AccountId> {
+    fn default() -> Self {
+        Self {
+            inputs: Vec::new(),
+            utxos: Vec::new(),
+            outputs: Vec::new(),
+            time_lock: Default::default(),
+        }
+    }
+}
+
+/// Tokens carried by an output, NFTs and newly issued tokens excluded.
+fn transferred_tokens(data: &Option<OutputData>) -> Vec<(TokenId, Value)> {
+    match data {
+        Some(OutputData::TokenTransferV1 { token_id, amount }) => {
+            vec![(token_id.clone(), *amount)]
+        }
+        Some(OutputData::TokenMultiV1(tokens)) => tokens.clone(),
+        _ => Vec::new(),
+    }
+}
+
+impl<AccountId: Encode + Clone> TxBuilder<AccountId> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Spend the UTXO `utxo` stored at `outpoint`.
+    pub fn add_input_from_utxo(
+        mut self,
+        outpoint: H256,
+        utxo: TransactionOutput<AccountId>,
+    ) -> Self {
+        self.inputs.push(TransactionInput::new_empty(outpoint));
+        self.utxos.push(utxo);
+        self
+    }
+
+    /// Pay `value` to the destination encoded in the bech32 `address`.
+    pub fn pay_to_address(mut self, value: Value, address: &[u8]) -> Result<Self, &'static str>
+    where
+        AccountId: Decode,
+    {
+        self.outputs.push(TransactionOutput {
+            value,
+            destination: decode_address(address)?,
+            data: None,
+        });
+        Ok(self)
+    }
+
+    /// Make the last payment carry `amount` of the token `token_id`.
+    pub fn set_token_transfer(
+        mut self,
+        token_id: TokenId,
+        amount: Value,
+    ) -> Result<Self, &'static str> {
+        let output = self.outputs.last_mut().ok_or("no payment to carry the tokens")?;
+        output.data = Some(OutputData::TokenTransferV1 { token_id, amount });
+        Ok(self)
+    }
+
+    /// Set the time lock of the transaction.
+    pub fn with_time_lock(mut self, time_lock: RawBlockTime) -> Self {
+        self.time_lock = time_lock;
+        self
+    }
+
+    /// Return what is left of the spent UTXOs after the payments and the `fee` to `pubkey`. Every
+    /// token is returned in an output of its own.
+    pub fn add_change(mut self, pubkey: H256, fee: Value) -> Result<Self, &'static str> {
+        let mut tokens = BTreeMap::<TokenId, Value>::new();
+        let mut value: Value = 0;
+        for utxo in &self.utxos {
+            value = value.checked_add(utxo.value).ok_or("input value overflow")?;
+            for (token_id, amount) in transferred_tokens(&utxo.data) {
+                let total = tokens.entry(token_id).or_default();
+                *total = total.checked_add(amount).ok_or("input value overflow")?;
+            }
+        }
+        let spent = self
+            .outputs
+            .iter()
+            .try_fold(fee, |spent, output| spent.checked_add(output.value))
+            .ok_or("output value overflow")?;
+        let change = value.checked_sub(spent).ok_or("insufficient funds for the payments")?;
+        for output in &self.outputs {
+            for (token_id, amount) in transferred_tokens(&output.data) {
+                let total = tokens.get_mut(&token_id).ok_or("insufficient tokens")?;
+                *total = total.checked_sub(amount).ok_or("insufficient tokens")?;
+            }
+        }
+
+        for (token_id, amount) in tokens.into_iter().filter(|(_, amount)| *amount > 0) {
+            self.outputs.push(TransactionOutput::new_p2pk_with_data(
+                0,
+                pubkey,
+                OutputData::TokenTransferV1 { token_id, amount },
+            ));
+        }
+        if change > 0 {
+            self.outputs.push(TransactionOutput::new_pubkey(change, pubkey));
+        }
+        Ok(self)
+    }
+
+    /// Build the transaction and sign every input with the key it's locked to.
+    pub fn finalize_and_sign(self) -> Result<Transaction<AccountId>, &'static str> {
+        ensure!(!self.inputs.is_empty(), "no inputs");
+        ensure!(!self.outputs.is_empty(), "no outputs");
+        let mut tx = Transaction {
+            inputs: self.inputs,
+            outputs: self.outputs,
+            time_lock: self.time_lock,
+            expires_at: None,
+        };
+        for (index, utxo) in self.utxos.iter().enumerate() {
+            let pubkey = match &utxo.destination {
+                Destination::Pubkey(pubkey)
+                | Destination::Vesting { pubkey, .. }
+                | Destination::Stream { payee: pubkey, .. }
+                | Destination::Inheritance { owner: pubkey, .. }
+                | Destination::Taproot {
+                    internal_key: pubkey,
+                    ..
+                } => *pubkey,
+                _ => return Err("input cannot be signed"),
+            };
+            tx = tx.sign(&self.utxos, index, &pubkey).ok_or("key not found")?;
+        }
+        Ok(tx)
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Payment channels
+//!
+//! Two parties can exchange payments off chain and settle only the final balances on chain:
+//! 1. The channel is opened by paying into [funding_script], a 2-of-2 multisig of both parties.
+//! 2. Each balance update is a pair of commitment transactions spending the funding output, one
+//!    held by each party, built with [Transaction::new_channel_commitment]. The holder's own
+//!    balance goes to [to_local_script], so the holder can only spend it after a relative delay,
+//!    while the counterparty can take it right away with the revocation key. Once a commitment is
+//!    superseded, its holder hands over the revocation secret key, so publishing an old state
+//!    forfeits the holder's balance.
+//!
+//!    A commitment can also be revoked by a [Revocation::Secret], whose preimage the holder
+//!    reveals instead. Then the counterparty sweeps all the funds of a revoked commitment with
+//!    its own key, so it can sign the justice transaction from [Transaction::new_justice] in
+//!    advance and leave it with a watchtower, which only has to publish it once it sees the
+//!    revoked commitment on chain.
+//! 3. The parties close the channel by signing [Transaction::new_cooperative_close], which pays
+//!    both balances out without any delay. If the counterparty is unresponsive, the latest
+//!    commitment can be published instead.
+
+use crate::{
+    sign, tokens::Value, RelativeTimeLock, Transaction, TransactionInput, TransactionOutput,
+};
+use chainscript::{opcodes::all as opc, Builder, Script};
+use codec::Encode;
+use frame_support::sp_runtime::traits::{BlakeTwo256, Hash};
+use sp_core::{sr25519, H256};
+
+/// A public key as it's pushed onto the script stack.
+fn script_pubkey(pk: &sr25519::Public) -> Vec<u8> {
+    sign::Public::from(*pk).encode()
+}
+
+/// Script of the funding output, spendable with the signatures of both `a` and `b`, in this order.
+pub fn funding_script(a: &sr25519::Public, b: &sr25519::Public) -> Script {
+    Builder::new()
+        .push_int(2)
+        .push_slice(&script_pubkey(a))
+        .push_slice(&script_pubkey(b))
+        .push_int(2)
+        .push_opcode(opc::OP_CHECKMULTISIG)
+        .into_script()
+}
+
+/// Script of the holder's balance in a commitment transaction. Spendable by `local` once the
+/// output is `to_self_delay` old, or by `revocation` at any time.
+pub fn to_local_script(
+    revocation: &sr25519::Public,
+    local: &sr25519::Public,
+    to_self_delay: RelativeTimeLock,
+) -> Script {
+    Builder::new()
+        .push_opcode(opc::OP_IF)
+        .push_slice(&script_pubkey(revocation))
+        .push_opcode(opc::OP_CHECKSIG)
+        .push_opcode(opc::OP_ELSE)
+        .push_int(to_self_delay.as_u64() as i64)
+        .push_opcode(opc::OP_CSV)
+        .push_opcode(opc::OP_DROP)
+        .push_slice(&script_pubkey(local))
+        .push_opcode(opc::OP_CHECKSIG)
+        .push_opcode(opc::OP_ENDIF)
+        .into_script()
+}
+
+/// Script of the holder's balance in a commitment revoked by a secret. Spendable by `local` once
+/// the output is `to_self_delay` old, or by `remote` at any time given the preimage of the SHA-256
+/// `revocation_hash`.
+pub fn to_local_secret_script(
+    revocation_hash: &H256,
+    remote: &sr25519::Public,
+    local: &sr25519::Public,
+    to_self_delay: RelativeTimeLock,
+) -> Script {
+    Builder::new()
+        .push_opcode(opc::OP_IF)
+        .push_opcode(opc::OP_SHA256)
+        .push_slice(revocation_hash.as_bytes())
+        .push_opcode(opc::OP_EQUALVERIFY)
+        .push_slice(&script_pubkey(remote))
+        .push_opcode(opc::OP_CHECKSIG)
+        .push_opcode(opc::OP_ELSE)
+        .push_int(to_self_delay.as_u64() as i64)
+        .push_opcode(opc::OP_CSV)
+        .push_opcode(opc::OP_DROP)
+        .push_slice(&script_pubkey(local))
+        .push_opcode(opc::OP_CHECKSIG)
+        .push_opcode(opc::OP_ENDIF)
+        .into_script()
+}
+
+/// Witness spending [funding_script] with the signatures of both parties, in the key order.
+pub fn funding_witness(sig_a: &[u8], sig_b: &[u8]) -> Script {
+    // OP_CHECKMULTISIG consumes an extra item, which has to be empty
+    Builder::new().push_int(0).push_slice(sig_a).push_slice(sig_b).into_script()
+}
+
+/// Witness spending [to_local_script] by its holder after the delay.
+pub fn to_local_delayed_witness(sig: &[u8]) -> Script {
+    Builder::new().push_slice(sig).push_int(0).into_script()
+}
+
+/// Witness spending [to_local_script] with the revocation key.
+pub fn to_local_revoked_witness(sig: &[u8]) -> Script {
+    Builder::new().push_slice(sig).push_int(1).into_script()
+}
+
+/// Witness spending [to_local_secret_script] by the counterparty with the revealed `secret`.
+pub fn to_local_penalty_witness(sig: &[u8], secret: &[u8]) -> Script {
+    Builder::new().push_slice(sig).push_slice(secret).push_int(1).into_script()
+}
+
+/// How a commitment is revoked once superseded.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Revocation {
+    /// The holder hands over the secret key of this pubkey, see [to_local_script]
+    Key(sr25519::Public),
+    /// The holder reveals the preimage of this SHA-256 hash, see [to_local_secret_script]
+    Secret(H256),
+}
+
+/// A commitment transaction as seen by its holder, the local party.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Commitment {
+    /// Key of the holder spending its balance after the delay
+    pub local: sr25519::Public,
+    /// Key the balance of the counterparty is paid to
+    pub remote: sr25519::Public,
+    /// How this commitment is revoked
+    pub revocation: Revocation,
+    /// How long the holder has to wait to spend its balance
+    pub to_self_delay: RelativeTimeLock,
+    /// Balance of the holder
+    pub local_value: Value,
+    /// Balance of the counterparty
+    pub remote_value: Value,
+}
+
+impl Commitment {
+    /// Script the holder's balance is locked with.
+    pub fn to_local_script(&self) -> Script {
+        match &self.revocation {
+            Revocation::Key(key) => to_local_script(key, &self.local, self.to_self_delay),
+            Revocation::Secret(hash) => {
+                to_local_secret_script(hash, &self.remote, &self.local, self.to_self_delay)
+            }
+        }
+    }
+
+    /// Output of the commitment transaction holding the holder's balance.
+    fn to_local_output<AccountId>(&self) -> TransactionOutput<AccountId> {
+        TransactionOutput::new_script_hash(
+            self.local_value,
+            BlakeTwo256::hash(self.to_local_script().as_ref()),
+        )
+    }
+}
+
+impl<AccountId> TransactionOutput<AccountId> {
+    /// Output funding a channel between `a` and `b`.
+    pub fn new_channel_funding(value: Value, a: &sr25519::Public, b: &sr25519::Public) -> Self {
+        Self::new_script_hash(value, BlakeTwo256::hash(funding_script(a, b).as_ref()))
+    }
+}
+
+impl<AccountId: Encode> Transaction<AccountId> {
+    fn spending_funding(funding: H256, funding_script: Script) -> Self {
+        Self {
+            inputs: vec![TransactionInput::new_script(funding, funding_script, Script::new())],
+            outputs: vec![],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+    }
+
+    fn pay_to(mut self, output: TransactionOutput<AccountId>) -> Self {
+        // Empty balances get no output
+        if output.value > 0 {
+            self.outputs.push(output);
+        }
+        self
+    }
+
+    /// Commitment transaction spending the `funding` output locked with `funding_script`. The
+    /// counterparty's balance is paid out right away, the holder's is locked with
+    /// [Commitment::to_local_script]. Whatever the balances leave of the funding value is the fee.
+    pub fn new_channel_commitment(
+        funding: H256,
+        funding_script: Script,
+        commitment: &Commitment,
+    ) -> Self {
+        Self::spending_funding(funding, funding_script)
+            .pay_to(commitment.to_local_output())
+            .pay_to(TransactionOutput::new_pubkey(
+                commitment.remote_value,
+                H256::from(commitment.remote),
+            ))
+    }
+
+    /// Transaction closing the channel funded by the `funding` output locked with
+    /// `funding_script`, paying `value_a` to `a` and `value_b` to `b` without any delay.
+    pub fn new_cooperative_close(
+        funding: H256,
+        funding_script: Script,
+        (a, value_a): (&sr25519::Public, Value),
+        (b, value_b): (&sr25519::Public, Value),
+    ) -> Self {
+        Self::spending_funding(funding, funding_script)
+            .pay_to(TransactionOutput::new_pubkey(value_a, H256::from(*a)))
+            .pay_to(TransactionOutput::new_pubkey(value_b, H256::from(*b)))
+    }
+
+    /// Justice transaction sweeping all the outputs of `revoked`, a published commitment revoked
+    /// by a [Revocation::Secret], to the counterparty's `destination`. Pays `fee` out of the
+    /// swept value. Sign it with [Transaction::sign_justice].
+    pub fn new_justice(
+        revoked: &Transaction<AccountId>,
+        commitment: &Commitment,
+        destination: H256,
+        fee: Value,
+    ) -> Option<Self>
+    where
+        AccountId: PartialEq,
+    {
+        let to_local = commitment.to_local_output();
+        let mut swept: Value = 0;
+        let inputs = revoked
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(index, output)| {
+                swept = swept.checked_add(output.value)?;
+                let outpoint = revoked.outpoint(index as u64);
+                Some(if output == &to_local {
+                    TransactionInput::new_script(
+                        outpoint,
+                        commitment.to_local_script(),
+                        Script::new(),
+                    )
+                } else {
+                    TransactionInput::new_empty(outpoint)
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            inputs,
+            outputs: vec![TransactionOutput::new_pubkey(swept.checked_sub(fee)?, destination)],
+            time_lock: Default::default(),
+            expires_at: None,
+        })
+    }
+
+    /// Sign a justice transaction spending the outputs of the `revoked` commitment with the
+    /// counterparty's key, `remote`, proving the revocation with the revealed `secret`.
+    pub fn sign_justice(
+        mut self,
+        revoked: &Transaction<AccountId>,
+        remote: &sr25519::Public,
+        secret: &[u8],
+    ) -> Option<Self> {
+        for index in 0..self.inputs.len() {
+            if self.inputs[index].lock.is_empty() {
+                self = self.sign(&revoked.outputs, index, remote)?;
+            } else {
+                let sig = self.script_signature(&revoked.outputs, index, remote)?;
+                self.inputs[index].witness = to_local_penalty_witness(&sig, secret).into_bytes();
+            }
+        }
+        Some(self)
+    }
+}
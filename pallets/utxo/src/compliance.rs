@@ -0,0 +1,61 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Token transfer policies
+//!
+//! The issuer of a token may restrict who can receive it with `set_transfer_policy`, e.g. to gate
+//! a security token behind KYC. Every output sending the token has to satisfy the policy when the
+//! transaction is validated, burning the token is always allowed. A policy is either a whitelist
+//! kept in `TokenTransferPolicy` or a callback into the pallet plugged in through
+//! `Config::TransferApprover`.
+
+use crate::tokens::{TokenId, Value};
+use crate::Destination;
+use codec::{Decode, Encode};
+use frame_support::{dispatch::Vec, RuntimeDebug};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::H256;
+
+/// Restriction on the recipients of a token.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, PartialOrd, Ord, RuntimeDebug)]
+pub enum TransferPolicy {
+    /// Only the listed pubkeys may receive the token
+    Whitelist(Vec<H256>),
+    /// Every transfer has to be approved by `Config::TransferApprover`
+    Approver,
+}
+
+/// Approves the transfers of the tokens with the `TransferPolicy::Approver` policy.
+pub trait TransferApprover<AccountId> {
+    /// Whether `amount` of the token may be sent to `destination`.
+    fn approve_transfer(
+        token_id: &TokenId,
+        destination: &Destination<AccountId>,
+        amount: Value,
+    ) -> bool;
+}
+
+/// No approver, tokens with the `TransferPolicy::Approver` policy can't be transferred.
+impl<AccountId> TransferApprover<AccountId> for () {
+    fn approve_transfer(
+        _token_id: &TokenId,
+        _destination: &Destination<AccountId>,
+        _amount: Value,
+    ) -> bool {
+        false
+    }
+}
@@ -0,0 +1,438 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Output descriptors
+//!
+//! A descriptor describes, in a short human readable string, the destination a watch-only wallet
+//! looks for in the UTXO set. The language follows the output descriptors of Bitcoin:
+//! ```text
+//! DESCRIPTOR := pk(KEY) | sh(SCRIPT)
+//! SCRIPT     := pk(KEY) | multi(K,KEY,...) | after(N,SCRIPT) | older(N,SCRIPT)
+//! ```
+//! * `KEY` is an sr25519 public key in 64 hex digits.
+//! * `pk(KEY)` pays to the key. In a script, it's `<KEY> OP_CHECKSIG`.
+//! * `multi(K,KEY,...)` is a `K` of `N` multisig, `K <KEY>... N OP_CHECKMULTISIG`.
+//! * `after(N,SCRIPT)` adds an absolute time lock, `<N> OP_CLTV OP_DROP SCRIPT`, where `N` is a
+//!   block number or UNIX time as in the transaction time lock.
+//! * `older(N,SCRIPT)` adds a relative time lock, `<N> OP_CSV OP_DROP SCRIPT`, where `N` is a raw
+//!   [RelativeTimeLock].
+//! * `sh(SCRIPT)` pays to the hash of the version 0 script.
+//!
+//! For instance, `sh(after(1000,multi(2,<A>,<B>)))` is a 2-of-2 multisig of `A` and `B` that can
+//! be spent from block 1000 on. Descriptors are serialized with lowercase hex keys, so the
+//! descriptors of a destination written by different wallets compare equal.
+
+use crate::{sign, Destination, RawBlockTime, RelativeTimeLock, ScriptVersion};
+use chainscript::{opcodes::all as opc, Builder, Script};
+use codec::Encode;
+use frame_support::{
+    ensure,
+    sp_runtime::traits::{BlakeTwo256, Hash},
+};
+use sp_core::{sr25519, H256};
+use sp_std::prelude::*;
+
+/// Longest descriptor accepted, in bytes
+pub const MAX_DESCRIPTOR_LEN: usize = 4096;
+
+/// Deepest nesting of time lock wrappers
+const MAX_DEPTH: usize = 16;
+
+/// Most keys in a `multi()`, as checked by `OP_CHECKMULTISIG`
+const MAX_MULTI_KEYS: usize = 20;
+
+/// A public key as it's pushed onto the script stack.
+fn script_pubkey(pk: &sr25519::Public) -> Vec<u8> {
+    sign::Public::from(*pk).encode()
+}
+
+/// Destination described by a descriptor.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Descriptor {
+    /// `pk(KEY)`, plain pay-to-pubkey
+    Pk(sr25519::Public),
+    /// `sh(SCRIPT)`, pay to the hash of a version 0 script
+    Sh(ScriptDescriptor),
+}
+
+/// Script described by a descriptor.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ScriptDescriptor {
+    /// `pk(KEY)`, spendable with the signature of the key
+    Pk(sr25519::Public),
+    /// `multi(K,KEY,...)`, spendable with the signatures of `K` of the keys, in their order
+    Multi(u32, Vec<sr25519::Public>),
+    /// `after(N,SCRIPT)`, the script once the absolute time lock has passed
+    After(RawBlockTime, Box<ScriptDescriptor>),
+    /// `older(N,SCRIPT)`, the script once the UTXO is old enough
+    Older(RelativeTimeLock, Box<ScriptDescriptor>),
+}
+
+impl Descriptor {
+    /// Parse a descriptor.
+    pub fn parse(descriptor: &[u8]) -> Result<Self, &'static str> {
+        ensure!(
+            descriptor.len() <= MAX_DESCRIPTOR_LEN,
+            "descriptor is too long"
+        );
+        let mut parser = Parser {
+            input: descriptor,
+            pos: 0,
+        };
+        let parsed = match parser.function()? {
+            b"pk" => Self::Pk(parser.key()?),
+            b"sh" => Self::Sh(parser.script(0)?),
+            _ => return Err("unknown descriptor function"),
+        };
+        parser.expect(b')')?;
+        ensure!(
+            parser.pos == descriptor.len(),
+            "unexpected characters after the descriptor"
+        );
+        Ok(parsed)
+    }
+
+    /// Serialize the descriptor, with lowercase hex keys.
+    pub fn to_string(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            Self::Pk(pk) => write_pk(&mut out, pk),
+            Self::Sh(script) => {
+                out.extend_from_slice(b"sh(");
+                script.write(&mut out);
+                out.push(b')');
+            }
+        }
+        out
+    }
+
+    /// Destination the described outputs pay to.
+    pub fn destination<AccountId>(&self) -> Destination<AccountId> {
+        match self {
+            Self::Pk(pk) => Destination::Pubkey(*pk),
+            Self::Sh(script) => Destination::ScriptHash(
+                ScriptVersion::V0,
+                BlakeTwo256::hash(script.script().as_ref()),
+            ),
+        }
+    }
+}
+
+impl ScriptDescriptor {
+    /// The described script.
+    pub fn script(&self) -> Script {
+        self.build(Builder::new()).into_script()
+    }
+
+    fn build(&self, builder: Builder) -> Builder {
+        match self {
+            Self::Pk(pk) => builder.push_slice(&script_pubkey(pk)).push_opcode(opc::OP_CHECKSIG),
+            Self::Multi(threshold, keys) => keys
+                .iter()
+                .fold(builder.push_int(*threshold as i64), |builder, pk| {
+                    builder.push_slice(&script_pubkey(pk))
+                })
+                .push_int(keys.len() as i64)
+                .push_opcode(opc::OP_CHECKMULTISIG),
+            Self::After(time_lock, script) => script.build(
+                builder
+                    .push_int(time_lock.as_u64() as i64)
+                    .push_opcode(opc::OP_CLTV)
+                    .push_opcode(opc::OP_DROP),
+            ),
+            Self::Older(time_lock, script) => script.build(
+                builder
+                    .push_int(time_lock.as_u64() as i64)
+                    .push_opcode(opc::OP_CSV)
+                    .push_opcode(opc::OP_DROP),
+            ),
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Pk(pk) => write_pk(out, pk),
+            Self::Multi(threshold, keys) => {
+                out.extend_from_slice(b"multi(");
+                write_number(out, *threshold as u64);
+                for pk in keys {
+                    out.push(b',');
+                    write_key(out, pk);
+                }
+                out.push(b')');
+            }
+            Self::After(time_lock, script) => {
+                out.extend_from_slice(b"after(");
+                write_number(out, time_lock.as_u64());
+                out.push(b',');
+                script.write(out);
+                out.push(b')');
+            }
+            Self::Older(time_lock, script) => {
+                out.extend_from_slice(b"older(");
+                write_number(out, time_lock.as_u64());
+                out.push(b',');
+                script.write(out);
+                out.push(b')');
+            }
+        }
+    }
+}
+
+fn write_pk(out: &mut Vec<u8>, pk: &sr25519::Public) {
+    out.extend_from_slice(b"pk(");
+    write_key(out, pk);
+    out.push(b')');
+}
+
+fn write_key(out: &mut Vec<u8>, pk: &sr25519::Public) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    for byte in pk.0.iter() {
+        out.push(HEX[(byte >> 4) as usize]);
+        out.push(HEX[(byte & 0xf) as usize]);
+    }
+}
+
+fn write_number(out: &mut Vec<u8>, mut n: u64) {
+    let start = out.len();
+    loop {
+        out.push(b'0' + (n % 10) as u8);
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    out[start..].reverse();
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn eat(&mut self, c: u8) -> bool {
+        let found = self.input.get(self.pos) == Some(&c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), &'static str> {
+        match c {
+            b'(' => ensure!(self.eat(c), "expected '(' in descriptor"),
+            b')' => ensure!(self.eat(c), "expected ')' in descriptor"),
+            _ => ensure!(self.eat(c), "expected ',' in descriptor"),
+        }
+        Ok(())
+    }
+
+    /// Characters up to the next delimiter, or the end of the input
+    fn token(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while self.input.get(self.pos).map_or(false, |c| !b"(),".contains(c)) {
+            self.pos += 1;
+        }
+        &self.input[start..self.pos]
+    }
+
+    /// Name of a function and its opening parenthesis
+    fn function(&mut self) -> Result<&'a [u8], &'static str> {
+        let name = self.token();
+        self.expect(b'(')?;
+        Ok(name)
+    }
+
+    fn number(&mut self) -> Result<u64, &'static str> {
+        let digits = self.token();
+        ensure!(
+            !digits.is_empty() && digits.iter().all(u8::is_ascii_digit),
+            "expected a number in descriptor"
+        );
+        digits.iter().try_fold(0u64, |n, digit| {
+            n.checked_mul(10)
+                .and_then(|n| n.checked_add((digit - b'0') as u64))
+                .filter(|n| *n <= i64::MAX as u64)
+                .ok_or("number out of range in descriptor")
+        })
+    }
+
+    fn key(&mut self) -> Result<sr25519::Public, &'static str> {
+        let hex = self.token();
+        ensure!(hex.len() == 64, "invalid key in descriptor");
+        let mut key = [0u8; 32];
+        for (byte, pair) in key.iter_mut().zip(hex.chunks(2)) {
+            let digit = |c: u8| (c as char).to_digit(16).ok_or("invalid key in descriptor");
+            *byte = (digit(pair[0])? << 4 | digit(pair[1])?) as u8;
+        }
+        Ok(sr25519::Public::from_raw(key))
+    }
+
+    fn script(&mut self, depth: usize) -> Result<ScriptDescriptor, &'static str> {
+        ensure!(depth <= MAX_DEPTH, "descriptor is nested too deeply");
+        let script = match self.function()? {
+            b"pk" => ScriptDescriptor::Pk(self.key()?),
+            b"multi" => {
+                let threshold = self.number()?;
+                let mut keys = Vec::new();
+                while self.eat(b',') {
+                    keys.push(self.key()?);
+                }
+                ensure!(
+                    !keys.is_empty() && keys.len() <= MAX_MULTI_KEYS,
+                    "invalid number of multi() keys"
+                );
+                ensure!(
+                    (1..=keys.len() as u64).contains(&threshold),
+                    "invalid multi() threshold"
+                );
+                ScriptDescriptor::Multi(threshold as u32, keys)
+            }
+            b"after" => {
+                let time_lock = RawBlockTime::new(self.number()?);
+                self.expect(b',')?;
+                ScriptDescriptor::After(time_lock, Box::new(self.script(depth + 1)?))
+            }
+            b"older" => {
+                let time_lock =
+                    RelativeTimeLock::new(self.number()?).ok_or("invalid relative time lock")?;
+                self.expect(b',')?;
+                ScriptDescriptor::Older(time_lock, Box::new(self.script(depth + 1)?))
+            }
+            _ => return Err("unknown descriptor function"),
+        };
+        self.expect(b')')?;
+        Ok(script)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::channels;
+
+    fn key(byte: u8) -> sr25519::Public {
+        sr25519::Public::from_raw([byte; 32])
+    }
+
+    #[test]
+    fn test_descriptor_round_trip() {
+        let a = "0a".repeat(32);
+        let b = "0b".repeat(32);
+        for descriptor in [
+            format!("pk({})", a),
+            format!("sh(pk({}))", a),
+            format!("sh(multi(1,{},{}))", a, b),
+            format!("sh(after(1000,multi(2,{},{})))", a, b),
+            format!("sh(older(4294967306,after(500000000,pk({}))))", b),
+        ] {
+            let parsed = Descriptor::parse(descriptor.as_bytes()).unwrap();
+            assert_eq!(parsed.to_string(), descriptor.into_bytes());
+        }
+
+        // Keys are serialized in lowercase
+        let upper = format!("pk({})", "AB".repeat(32));
+        assert_eq!(
+            Descriptor::parse(upper.as_bytes()).unwrap(),
+            Descriptor::Pk(key(0xab))
+        );
+        assert_eq!(
+            Descriptor::parse(upper.as_bytes()).unwrap().to_string(),
+            upper.to_lowercase().into_bytes()
+        );
+    }
+
+    #[test]
+    fn test_descriptor_errors() {
+        let a = "0a".repeat(32);
+        let cases = [
+            (format!("wpkh({})", a), "unknown descriptor function"),
+            (format!("multi(1,{})", a), "unknown descriptor function"),
+            (format!("pk({}", a), "expected ')' in descriptor"),
+            (
+                format!("pk({}))", a),
+                "unexpected characters after the descriptor",
+            ),
+            (format!("pk({})", &a[1..]), "invalid key in descriptor"),
+            (format!("pk(x{})", &a[1..]), "invalid key in descriptor"),
+            (format!("sh(multi(2,{}))", a), "invalid multi() threshold"),
+            (format!("sh(multi(0,{}))", a), "invalid multi() threshold"),
+            ("sh(multi(1))".to_string(), "invalid number of multi() keys"),
+            (
+                format!("sh(after(x,pk({})))", a),
+                "expected a number in descriptor",
+            ),
+            (
+                format!("sh(after(1 pk({})))", a),
+                "expected a number in descriptor",
+            ),
+            (
+                format!("sh(after(99999999999999999999,pk({})))", a),
+                "number out of range in descriptor",
+            ),
+            (
+                format!("sh(older({},pk({})))", 1u64 << 33, a),
+                "invalid relative time lock",
+            ),
+        ];
+        for (descriptor, error) in cases.iter() {
+            assert_eq!(
+                Descriptor::parse(descriptor.as_bytes()),
+                Err(*error),
+                "{}",
+                descriptor
+            );
+        }
+
+        let nested = format!("sh({}pk({}){})", "after(1,".repeat(20), a, ")".repeat(20));
+        assert_eq!(
+            Descriptor::parse(nested.as_bytes()),
+            Err("descriptor is nested too deeply")
+        );
+        assert_eq!(
+            Descriptor::parse(&[b'('; MAX_DESCRIPTOR_LEN + 1]),
+            Err("descriptor is too long")
+        );
+    }
+
+    #[test]
+    fn test_descriptor_destinations() {
+        let (a, b) = (key(1), key(2));
+        assert_eq!(
+            Descriptor::Pk(a).destination::<H256>(),
+            Destination::Pubkey(a)
+        );
+
+        // Same script as the funding output of a payment channel
+        let multi = Descriptor::Sh(ScriptDescriptor::Multi(2, vec![a, b]));
+        let funding = channels::funding_script(&a, &b);
+        assert_eq!(
+            multi.destination::<H256>(),
+            Destination::ScriptHash(ScriptVersion::V0, BlakeTwo256::hash(funding.as_ref()))
+        );
+
+        let time_locked =
+            ScriptDescriptor::After(RawBlockTime::new(1000), Box::new(ScriptDescriptor::Pk(a)));
+        let expected = Builder::new()
+            .push_int(1000)
+            .push_opcode(opc::OP_CLTV)
+            .push_opcode(opc::OP_DROP)
+            .push_slice(&script_pubkey(&a))
+            .push_opcode(opc::OP_CHECKSIG)
+            .into_script();
+        assert_eq!(time_locked.script(), expected);
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Escrow outputs
+//!
+//! An escrow holds a payment from a buyer to a seller in a script hash output, with an arbiter
+//! settling disputes:
+//! * The buyer and the seller can release the funds together at any time, see
+//!   [Transaction::new_escrow_release].
+//! * From the deadline on, the arbiter can release the funds together with either party, see
+//!   [Transaction::new_escrow_arbitration].
+//!
+//! The script combines `OP_CHECKMULTISIG` with an `OP_CLTV` time lock:
+//! ```text
+//! OP_IF
+//!     2 <buyer> <seller> 2 OP_CHECKMULTISIG
+//! OP_ELSE
+//!     <deadline> OP_CLTV OP_DROP 2 <buyer> <seller> <arbiter> 3 OP_CHECKMULTISIG
+//! OP_ENDIF
+//! ```
+
+use crate::{sign, tokens::Value, RawBlockTime, Transaction, TransactionInput, TransactionOutput};
+use chainscript::{opcodes::all as opc, Builder, Script};
+use codec::Encode;
+use frame_support::sp_runtime::traits::{BlakeTwo256, Hash};
+use sp_core::{sr25519, H256};
+
+/// A public key as it's pushed onto the script stack.
+fn script_pubkey(pk: &sr25519::Public) -> Vec<u8> {
+    sign::Public::from(*pk).encode()
+}
+
+/// Parties of an escrow and the deadline after which the arbiter can step in.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Escrow {
+    pub buyer: sr25519::Public,
+    pub seller: sr25519::Public,
+    pub arbiter: sr25519::Public,
+    /// Block number or UNIX time in seconds, as in the transaction time lock
+    pub deadline: RawBlockTime,
+}
+
+impl Escrow {
+    pub fn new(
+        buyer: sr25519::Public,
+        seller: sr25519::Public,
+        arbiter: sr25519::Public,
+        deadline: RawBlockTime,
+    ) -> Self {
+        Self {
+            buyer,
+            seller,
+            arbiter,
+            deadline,
+        }
+    }
+
+    /// Script the escrowed funds are locked with.
+    pub fn script(&self) -> Script {
+        Builder::new()
+            .push_opcode(opc::OP_IF)
+            .push_int(2)
+            .push_slice(&script_pubkey(&self.buyer))
+            .push_slice(&script_pubkey(&self.seller))
+            .push_int(2)
+            .push_opcode(opc::OP_CHECKMULTISIG)
+            .push_opcode(opc::OP_ELSE)
+            .push_int(self.deadline.as_u64() as i64)
+            .push_opcode(opc::OP_CLTV)
+            .push_opcode(opc::OP_DROP)
+            .push_int(2)
+            .push_slice(&script_pubkey(&self.buyer))
+            .push_slice(&script_pubkey(&self.seller))
+            .push_slice(&script_pubkey(&self.arbiter))
+            .push_int(3)
+            .push_opcode(opc::OP_CHECKMULTISIG)
+            .push_opcode(opc::OP_ENDIF)
+            .into_script()
+    }
+
+    /// Witness releasing the funds with the signatures of the buyer and the seller.
+    pub fn release_witness(sig_buyer: &[u8], sig_seller: &[u8]) -> Script {
+        // OP_CHECKMULTISIG consumes an extra item, which has to be empty
+        Builder::new()
+            .push_int(0)
+            .push_slice(sig_buyer)
+            .push_slice(sig_seller)
+            .push_int(1)
+            .into_script()
+    }
+
+    /// Witness releasing the funds with the signatures of the arbiter and either party.
+    pub fn arbitration_witness(sig_party: &[u8], sig_arbiter: &[u8]) -> Script {
+        // Both parties come before the arbiter in the script, so the order of the signatures
+        // is the same for either of them
+        Builder::new()
+            .push_int(0)
+            .push_slice(sig_party)
+            .push_slice(sig_arbiter)
+            .push_int(0)
+            .into_script()
+    }
+}
+
+impl<AccountId> TransactionOutput<AccountId> {
+    /// Output holding `value` in the given escrow.
+    pub fn new_escrow(value: Value, escrow: &Escrow) -> Self {
+        Self::new_script_hash(value, BlakeTwo256::hash(escrow.script().as_ref()))
+    }
+}
+
+impl<AccountId> Transaction<AccountId> {
+    /// Transaction releasing the escrow funds at `outpoint` to `outputs`. Needs the signatures
+    /// of the buyer and the seller in [Escrow::release_witness].
+    pub fn new_escrow_release(
+        outpoint: H256,
+        escrow: &Escrow,
+        outputs: Vec<TransactionOutput<AccountId>>,
+    ) -> Self {
+        Self {
+            inputs: vec![TransactionInput::new_script(outpoint, escrow.script(), Script::new())],
+            outputs,
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+    }
+
+    /// Transaction releasing the escrow funds at `outpoint` to `outputs` once the deadline has
+    /// passed. Needs the signatures of the arbiter and either party in
+    /// [Escrow::arbitration_witness].
+    pub fn new_escrow_arbitration(
+        outpoint: H256,
+        escrow: &Escrow,
+        outputs: Vec<TransactionOutput<AccountId>>,
+    ) -> Self {
+        Self {
+            time_lock: escrow.deadline,
+            ..Self::new_escrow_release(outpoint, escrow, outputs)
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Duplicate spend policy
+//!
+//! [CheckDuplicateSpend] is an optional signed extension keeping a signer from spending the same
+//! outpoint twice in a block. Added to the `SignedExtra` of a runtime, it:
+//! * tags `spend` extrinsics in the transaction pool with the outpoints they spend, so that the
+//!   pool only keeps one of the conflicting extrinsics of a signer, see [spend_tags].
+//! * rejects a `spend` extrinsic spending an outpoint already spent by its signer in the block
+//!   with `InvalidTransaction::Custom(DUPLICATE_SPEND)`, so that the block author drops it rather
+//!   than including it only to fail at execution. The spends are kept in `SpentBySigner`.
+
+use crate::{Call, Config, SpentBySigner, Transaction};
+use codec::{Decode, Encode};
+use frame_support::traits::IsSubType;
+use sp_core::H256;
+use sp_runtime::traits::{DispatchInfoOf, SignedExtension};
+use sp_runtime::transaction_validity::{
+    InvalidTransaction, TransactionTag, TransactionValidity, TransactionValidityError,
+    ValidTransaction,
+};
+use sp_std::{marker::PhantomData, prelude::*};
+
+/// Custom validity error of an extrinsic spending an outpoint its signer already spent in the
+/// block.
+pub const DUPLICATE_SPEND: u8 = 2;
+
+/// Transaction pool tags of the outpoints spent by `who` in `tx`. Provided by the `spend`
+/// extrinsics, so that two extrinsics of the same signer spending the same outpoint conflict.
+pub fn spend_tags<AccountId: Encode>(
+    who: &AccountId,
+    tx: &Transaction<AccountId>,
+) -> Vec<TransactionTag> {
+    tx.inputs
+        .iter()
+        .map(|input| (b"utxo-spend", who, input.outpoint).encode())
+        .collect()
+}
+
+/// Reject the extrinsics spending an outpoint already spent by their signer in the block.
+#[derive(Encode, Decode, Clone, Eq, PartialEq)]
+pub struct CheckDuplicateSpend<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> CheckDuplicateSpend<T> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: Config + Send + Sync> Default for CheckDuplicateSpend<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config + Send + Sync> sp_std::fmt::Debug for CheckDuplicateSpend<T> {
+    #[cfg(feature = "std")]
+    fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+        write!(f, "CheckDuplicateSpend")
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn fmt(&self, _: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+        Ok(())
+    }
+}
+
+impl<T: Config + Send + Sync> SignedExtension for CheckDuplicateSpend<T>
+where
+    <T as frame_system::Config>::Call: IsSubType<Call<T>>,
+{
+    const IDENTIFIER: &'static str = "CheckDuplicateSpend";
+    type AccountId = T::AccountId;
+    type Call = <T as frame_system::Config>::Call;
+    type AdditionalSigned = ();
+    type Pre = ();
+
+    fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        who: &Self::AccountId,
+        call: &Self::Call,
+        _info: &DispatchInfoOf<Self::Call>,
+        _len: usize,
+    ) -> TransactionValidity {
+        match call.is_sub_type() {
+            Some(Call::spend(tx)) => Ok(ValidTransaction {
+                provides: spend_tags(who, tx),
+                ..Default::default()
+            }),
+            _ => Ok(Default::default()),
+        }
+    }
+
+    fn pre_dispatch(
+        self,
+        who: &Self::AccountId,
+        call: &Self::Call,
+        _info: &DispatchInfoOf<Self::Call>,
+        _len: usize,
+    ) -> Result<(), TransactionValidityError> {
+        if let Some(Call::spend(tx)) = call.is_sub_type() {
+            let mut spent = <SpentBySigner<T>>::get();
+            for input in &tx.inputs {
+                let spend: (T::AccountId, H256) = (who.clone(), input.outpoint);
+                if spent.contains(&spend) {
+                    return Err(InvalidTransaction::Custom(DUPLICATE_SPEND).into());
+                }
+                spent.push(spend);
+            }
+            <SpentBySigner<T>>::put(spent);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fungible tokens through the `fungibles` traits
+//!
+//! Other pallets can see and move the balances of MLS-01 tokens without knowing about UTXOs. The
+//! balance of an account is the sum of the token UTXOs paying to its pubkey, as listed in
+//! `TokenUtxosByOwner`. A transfer spends enough of those UTXOs and pays the amount and the change
+//! in new UTXOs. Only single-token UTXOs carrying no MLT are spent, so the tokens held in other
+//! outputs count towards the balance but not towards the reducible balance. NFTs are not included.
+//!
+//! The `fungibles` traits need a `Copy` asset id, so tokens are identified by the `H160` their
+//! `TokenId` consists of.
+
+use crate::pallet::{
+    check_transfer_policy, convert_to_h256, count_utxo, index_token_utxo, uncount_utxo,
+    unindex_token_utxo,
+};
+use crate::tokens::{OutputData, TokenId, Value};
+use crate::{
+    Config, Pallet, TokenIssuanceId, TokenIssuanceTransactions, TokenSupply, TokenUtxosByOwner,
+    TransactionOutput, UtxoCreationTime, UtxoStore,
+};
+use frame_support::{
+    dispatch::{DispatchError, Vec},
+    ensure,
+    traits::{
+        tokens::{fungibles, DepositConsequence, WithdrawConsequence},
+        UnixTime,
+    },
+};
+use sp_core::{H160, H256};
+use sp_runtime::traits::{BlakeTwo256, Hash};
+use sp_runtime::TokenError;
+use sp_std::vec;
+
+/// Pubkey owning the tokens of an account, `None` if the account is not a pubkey
+fn owner<T: Config>(who: &T::AccountId) -> Option<H256> {
+    convert_to_h256::<T>(who).ok()
+}
+
+/// Single-token UTXOs of `owner` holding the token and no MLT, with their amounts
+fn spendable_utxos<T: Config>(owner: H256, token_id: &TokenId) -> Vec<(H256, Value)> {
+    <TokenUtxosByOwner<T>>::iter_prefix((owner, token_id.clone()))
+        .filter(|(outpoint, _)| match <UtxoStore<T>>::get(outpoint) {
+            Some(utxo) => {
+                utxo.value == 0
+                    && matches!(
+                        utxo.data,
+                        Some(OutputData::TokenTransferV1 { .. })
+                            | Some(OutputData::TokenIssuanceV1 { .. })
+                    )
+            }
+            None => false,
+        })
+        .collect()
+}
+
+impl<T: Config> fungibles::Inspect<T::AccountId> for Pallet<T> {
+    type AssetId = H160;
+    type Balance = Value;
+
+    fn total_issuance(asset: H160) -> Value {
+        <TokenSupply<T>>::get(TokenId::from_h160(asset)).unwrap_or(0)
+    }
+
+    fn minimum_balance(_asset: H160) -> Value {
+        0
+    }
+
+    fn balance(asset: H160, who: &T::AccountId) -> Value {
+        owner::<T>(who).map_or(0, |owner| {
+            <TokenUtxosByOwner<T>>::iter_prefix_values((owner, TokenId::from_h160(asset)))
+                .fold(0, |total: Value, amount| total.saturating_add(amount))
+        })
+    }
+
+    fn reducible_balance(asset: H160, who: &T::AccountId, _keep_alive: bool) -> Value {
+        owner::<T>(who).map_or(0, |owner| {
+            spendable_utxos::<T>(owner, &TokenId::from_h160(asset))
+                .into_iter()
+                .fold(0, |total: Value, (_, amount)| total.saturating_add(amount))
+        })
+    }
+
+    fn can_deposit(asset: H160, who: &T::AccountId, amount: Value) -> DepositConsequence {
+        if !<TokenIssuanceTransactions<T>>::contains_key(TokenId::from_h160(asset)) {
+            return DepositConsequence::UnknownAsset;
+        }
+        if owner::<T>(who).is_none() {
+            return DepositConsequence::CannotCreate;
+        }
+        match Self::balance(asset, who).checked_add(amount) {
+            Some(_) => DepositConsequence::Success,
+            None => DepositConsequence::Overflow,
+        }
+    }
+
+    fn can_withdraw(asset: H160, who: &T::AccountId, amount: Value) -> WithdrawConsequence<Value> {
+        if !<TokenIssuanceTransactions<T>>::contains_key(TokenId::from_h160(asset)) {
+            return WithdrawConsequence::UnknownAsset;
+        }
+        if Self::reducible_balance(asset, who, false) < amount {
+            return WithdrawConsequence::NoFunds;
+        }
+        WithdrawConsequence::Success
+    }
+}
+
+impl<T: Config> fungibles::Transfer<T::AccountId> for Pallet<T> {
+    fn transfer(
+        asset: H160,
+        source: &T::AccountId,
+        dest: &T::AccountId,
+        amount: Value,
+        _keep_alive: bool,
+    ) -> Result<Value, DispatchError> {
+        let token_id = TokenId::from_h160(asset);
+        let from = convert_to_h256::<T>(source)?;
+        let to = convert_to_h256::<T>(dest)?;
+        if amount == 0 {
+            return Ok(0);
+        }
+
+        let mut spent = Vec::new();
+        let mut total: Value = 0;
+        for (outpoint, value) in spendable_utxos::<T>(from, &token_id) {
+            if total >= amount {
+                break;
+            }
+            spent.push(outpoint);
+            total = total.saturating_add(value);
+        }
+        ensure!(total >= amount, TokenError::NoFunds);
+
+        let transfer = |pubkey: H256, amount: Value| {
+            TransactionOutput::new_p2pk_with_data(
+                0,
+                pubkey,
+                OutputData::TokenTransferV1 {
+                    token_id: token_id.clone(),
+                    amount,
+                },
+            )
+        };
+        let mut outputs = vec![transfer(to, amount)];
+        if total > amount {
+            outputs.push(transfer(from, total - amount));
+        }
+        for output in &outputs {
+            check_transfer_policy::<T>(output)?;
+        }
+
+        for outpoint in &spent {
+            if let Some(utxo) = <UtxoStore<T>>::take(outpoint) {
+                let issued = <TokenIssuanceId<T>>::take(outpoint);
+                unindex_token_utxo::<T>(*outpoint, &utxo, issued.clone());
+                uncount_utxo::<T>(&utxo, issued);
+            }
+            <UtxoCreationTime<T>>::remove(outpoint);
+        }
+        let now = <pallet_timestamp::Pallet<T> as UnixTime>::now();
+        let created = (
+            <frame_system::Pallet<T>>::block_number(),
+            now.as_millis() as u64,
+        );
+        let base = BlakeTwo256::hash_of(&(&spent, "fungibles transfer"));
+        for (index, output) in outputs.into_iter().enumerate() {
+            let hash = BlakeTwo256::hash_of(&(&base, index as u64));
+            index_token_utxo::<T>(hash, &output, None);
+            count_utxo::<T>(&output, None);
+            <UtxoStore<T>>::insert(hash, output);
+            <UtxoCreationTime<T>>::insert(hash, created);
+        }
+        Ok(amount)
+    }
+}
@@ -0,0 +1,59 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fuzzing harness of the transaction checks
+//!
+//! Shared by the `cargo fuzz` target in `fuzz/`, built with the `fuzzing` feature, and the property
+//! tests generating transactions in `tests.rs`. A transaction is validated and spent on the mock
+//! runtime holding the genesis UTXO of alice, and whatever the transaction:
+//! * neither validating nor spending it panics,
+//! * a transaction failing validation is never spent,
+//! * a spent transaction keeps the invariants of the ledger checked by `try_state`, so no MLT or
+//!   token is created or destroyed outside of the issuance rules.
+
+use crate::mock::*;
+use crate::{pallet, Transaction};
+use codec::Decode;
+use sp_core::H256;
+
+/// Check the transaction returned by `build`, called in the externalities of the mock runtime so
+/// it can sign with the keys of alice.
+pub fn check_transaction<F: FnOnce() -> Transaction<H256>>(build: F) {
+    alice_test_ext().execute_with(|| {
+        let tx = build();
+        let valid = pallet::validate_transaction::<Test>(&tx);
+        let spent = pallet::spend::<Test>(&H256::zero(), &tx);
+        if valid.is_err() {
+            assert!(spent.is_err(), "transaction failing validation was spent");
+        }
+        if spent.is_ok() {
+            assert_eq!(pallet::try_state::<Test>(), Ok(()));
+        }
+    })
+}
+
+/// Check the transaction encoded in the fuzzer input, if any. The fuzzer can't guess the outpoint
+/// of the genesis UTXO, so inputs spending the zero outpoint spend it instead.
+pub fn check_encoded_transaction(data: &[u8]) {
+    let mut tx = match Transaction::<H256>::decode(&mut &data[..]) {
+        Ok(tx) => tx,
+        Err(_) => return,
+    };
+    let (_, genesis) = genesis_utxo();
+    for input in tx.inputs.iter_mut().filter(|input| input.outpoint.is_zero()) {
+        input.outpoint = genesis;
+    }
+    check_transaction(|| tx)
+}
@@ -0,0 +1,65 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hooks into the UTXO set
+//!
+//! Other pallets, such as indexers or bridges, can react to the UTXO set changing without
+//! forking this pallet by implementing [OnUtxoEvent] and being plugged in through
+//! `Config::UtxoHooks`. The hooks are called from `update_storage` for every UTXO a transaction
+//! spends and every output it adds to the UTXO set, after the storage of this pallet has been
+//! updated, and likewise for the UTXOs created and spent outside of transactions: the bids,
+//! refunds and payouts of auctions, the tokens released from the bridge, the UTXOs wrapped and
+//! unwrapped, the airdrop claims and the block rewards. Outputs not added to the UTXO set, such
+//! as data carriers, staking locks and tokens moved to the bridge custody, are not reported.
+//!
+//! Each hook returns the weight it used, which is added to the weight of the block on top of the
+//! weight of the extrinsic.
+
+use crate::TransactionOutput;
+use frame_support::weights::Weight;
+use sp_core::H256;
+
+/// Reacts to transactions changing the UTXO set.
+pub trait OnUtxoEvent<AccountId> {
+    /// The output at `outpoint` was added to the UTXO set.
+    fn on_new_utxo(outpoint: &H256, output: &TransactionOutput<AccountId>) -> Weight;
+
+    /// The UTXO at `outpoint` was spent.
+    fn on_utxo_spent(outpoint: &H256, utxo: &TransactionOutput<AccountId>) -> Weight;
+}
+
+/// No hooks.
+impl<AccountId> OnUtxoEvent<AccountId> for () {
+    fn on_new_utxo(_outpoint: &H256, _output: &TransactionOutput<AccountId>) -> Weight {
+        0
+    }
+
+    fn on_utxo_spent(_outpoint: &H256, _utxo: &TransactionOutput<AccountId>) -> Weight {
+        0
+    }
+}
+
+/// Both hooks, the first one called first.
+impl<AccountId, A: OnUtxoEvent<AccountId>, B: OnUtxoEvent<AccountId>> OnUtxoEvent<AccountId>
+    for (A, B)
+{
+    fn on_new_utxo(outpoint: &H256, output: &TransactionOutput<AccountId>) -> Weight {
+        A::on_new_utxo(outpoint, output).saturating_add(B::on_new_utxo(outpoint, output))
+    }
+
+    fn on_utxo_spent(outpoint: &H256, utxo: &TransactionOutput<AccountId>) -> Weight {
+        A::on_utxo_spent(outpoint, utxo).saturating_add(B::on_utxo_spent(outpoint, utxo))
+    }
+}
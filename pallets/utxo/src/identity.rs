@@ -0,0 +1,72 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Named destinations
+//!
+//! The owner of an on-chain identity, such as one registered with `pallet-identity`, can have
+//! payments to its name sent to a destination of its choice with `set_identity_destination`. The
+//! destination is kept in `IdentityDestinations` under the display name of the identity, as given
+//! by `Config::Identities`. `send_to_address` and `send_to_addresses` then accept the name in
+//! place of an address. A name only resolves while the account that set it still holds an
+//! identity with that name.
+
+use crate::{Config, Destination, IdentityDestinations};
+use frame_support::{dispatch::Vec, ensure, traits::Get};
+
+/// Gives the names payments can be addressed to.
+pub trait IdentityProvider<AccountId> {
+    /// Display name of the identity of `who`, `None` if it has none payments can be sent to.
+    fn display_name(who: &AccountId) -> Option<Vec<u8>>;
+}
+
+/// No identities, payments can't be addressed by name.
+impl<AccountId> IdentityProvider<AccountId> for () {
+    fn display_name(_who: &AccountId) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// Check `destination` can be paid to by name: plain payments only.
+pub(crate) fn validate_identity_destination<AccountId>(
+    destination: &Destination<AccountId>,
+) -> Result<(), &'static str> {
+    ensure!(
+        matches!(
+            destination,
+            Destination::Pubkey(_) | Destination::ScriptHash(..) | Destination::Taproot { .. }
+        ),
+        "Identity destination must be a pubkey, script hash or taproot"
+    );
+    Ok(())
+}
+
+/// Destination registered for the identity named `name`, if its owner still holds it.
+pub fn identity_destination<T: Config>(name: &[u8]) -> Option<Destination<T::AccountId>> {
+    let (owner, destination) = <IdentityDestinations<T>>::get(name)?;
+    match T::Identities::display_name(&owner) {
+        Some(display_name) if display_name == name => Some(destination),
+        _ => None,
+    }
+}
+
+/// Destination of a payment to `address`: a bech32 or SS58 address, or else the name of an
+/// identity with a registered destination. The error tells why the address is invalid.
+pub fn resolve_destination<T: Config>(
+    address: &[u8],
+) -> Result<Destination<T::AccountId>, &'static str> {
+    let prefix = T::SS58Prefix::get().into();
+    crate::address::decode_destination::<T::AccountId>(address, prefix)
+        .or_else(|e| identity_destination::<T>(address).ok_or(e))
+}
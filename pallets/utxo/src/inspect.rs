@@ -0,0 +1,217 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transaction inspection
+//!
+//! Resolves the inputs of a transaction against the UTXO set and summarizes what the transaction
+//! does, so that explorers and wallets can display it using the same rules the runtime applies.
+//! [dry_run_spend] goes further and checks the transaction the way the transaction pool would,
+//! telling which input is at fault, so that wallets can show why a transaction would be rejected
+//! before broadcasting it.
+
+use crate::script::ScriptRules;
+use crate::tokens::{OutputData, TokenId, Value};
+use crate::{
+    check_input, spent_utxo, validate_pool_transaction, validate_transaction, Config, Destination,
+    TokenIssuanceId, TransactionFor, TransactionOutput,
+};
+use frame_support::sp_runtime::traits::{BlakeTwo256, Hash};
+pub use pallet_utxo_rpc_runtime_api::{
+    DryRunReport, InputInspection, InputStatus, OutputInspection, ScriptType, TokenAmount,
+    TokenDelta, TransactionInspection,
+};
+use sp_runtime::transaction_validity::TransactionSource;
+use sp_std::collections::btree_map::BTreeMap;
+use sp_std::prelude::*;
+
+fn script_type<AccountId>(destination: &Destination<AccountId>) -> ScriptType {
+    match destination {
+        Destination::Pubkey(_) => ScriptType::Pubkey,
+        Destination::CreatePP(..) => ScriptType::CreatePP,
+        Destination::CallPP(..) => ScriptType::CallPP,
+        Destination::ScriptHash(version, _) => ScriptType::ScriptHash(version.as_u8()),
+        Destination::LockForStaking { .. } => ScriptType::LockForStaking,
+        Destination::LockExtraForStaking { .. } => ScriptType::LockExtraForStaking,
+        Destination::Taproot { .. } => ScriptType::Taproot,
+        Destination::CreateStakingPool { .. } => ScriptType::CreateStakingPool,
+        Destination::JoinStakingPool { .. } => ScriptType::JoinStakingPool,
+        Destination::Vesting { .. } => ScriptType::Vesting,
+        Destination::DataCarrier(_) => ScriptType::DataCarrier,
+        Destination::Auction { .. } => ScriptType::Auction,
+        Destination::Bid { .. } => ScriptType::Bid,
+        Destination::Order { .. } => ScriptType::Order,
+        Destination::Stream { .. } => ScriptType::Stream,
+        Destination::Inheritance { .. } => ScriptType::Inheritance,
+    }
+}
+
+/// Amount of tokens carried by output data, NFTs count as one.
+fn token_amount(data: &OutputData) -> Value {
+    match data {
+        OutputData::TokenIssuanceV1 {
+            amount_to_issue, ..
+        } => *amount_to_issue,
+        OutputData::TokenTransferV1 { amount, .. } => *amount,
+        OutputData::TokenBurnV1 { amount_to_burn, .. } => *amount_to_burn,
+        OutputData::NftMintV1 { .. } => 1,
+        OutputData::TokenLockForBridgeV1 { amount, .. } => *amount,
+        // Each token of these is listed on its own, see `multi_token_amounts`
+        OutputData::TokenMultiV1(_) => 0,
+    }
+}
+
+/// Tokens carried by a multi-token output, empty for other outputs.
+fn multi_token_amounts(data: Option<&OutputData>) -> &[(TokenId, Value)] {
+    match data {
+        Some(OutputData::TokenMultiV1(tokens)) => tokens,
+        _ => &[],
+    }
+}
+
+fn inspect_output<AccountId>(
+    output: &TransactionOutput<AccountId>,
+    token_id: Option<TokenId>,
+) -> OutputInspection {
+    OutputInspection {
+        value: output.value,
+        script_type: script_type(&output.destination),
+        token: output.data.as_ref().zip(token_id).map(|(data, token_id)| TokenAmount {
+            token_id: token_id.to_string(),
+            amount: token_amount(data),
+        }),
+        tokens: multi_token_amounts(output.data.as_ref())
+            .iter()
+            .map(|(token_id, amount)| TokenAmount {
+                token_id: token_id.to_string(),
+                amount: *amount,
+            })
+            .collect(),
+    }
+}
+
+fn token_delta<'a>(
+    deltas: &'a mut BTreeMap<TokenId, TokenDelta>,
+    token_id: &TokenId,
+) -> &'a mut TokenDelta {
+    deltas.entry(token_id.clone()).or_insert_with(|| TokenDelta {
+        token_id: token_id.to_string(),
+        spent: 0,
+        created: 0,
+        burned: 0,
+    })
+}
+
+/// Summarize a transaction against the current state.
+pub fn inspect_transaction<T: Config>(tx: &TransactionFor<T>) -> TransactionInspection {
+    let first_input = tx.inputs.first();
+    let mut deltas: BTreeMap<TokenId, TokenDelta> = BTreeMap::new();
+
+    let mut mlt_in: Option<Value> = Some(0);
+    let inputs = tx
+        .inputs
+        .iter()
+        .map(|input| {
+            let utxo = spent_utxo::<T>(input);
+            let token_id = utxo.as_ref().and_then(|utxo| match utxo.data.as_ref()? {
+                // Burned tokens no longer exist
+                OutputData::TokenBurnV1 { .. } => None,
+                OutputData::TokenIssuanceV1 { .. } => <TokenIssuanceId<T>>::get(input.outpoint),
+                data => data.id(input),
+            });
+            mlt_in = mlt_in.zip(utxo.as_ref()).and_then(|(sum, utxo)| sum.checked_add(utxo.value));
+            if let (Some(token_id), Some(data)) =
+                (&token_id, utxo.as_ref().and_then(|u| u.data.as_ref()))
+            {
+                let entry = token_delta(&mut deltas, token_id);
+                entry.spent = entry.spent.saturating_add(token_amount(data));
+            }
+            for (token_id, amount) in
+                multi_token_amounts(utxo.as_ref().and_then(|u| u.data.as_ref()))
+            {
+                let entry = token_delta(&mut deltas, token_id);
+                entry.spent = entry.spent.saturating_add(*amount);
+            }
+            InputInspection {
+                outpoint: input.outpoint,
+                utxo: utxo.as_ref().map(|utxo| inspect_output(utxo, token_id)),
+            }
+        })
+        .collect();
+
+    let mut mlt_out: Option<Value> = Some(0);
+    let outputs = tx
+        .outputs
+        .iter()
+        .map(|output| {
+            let token_id =
+                output.data.as_ref().zip(first_input).and_then(|(data, input)| data.id(input));
+            mlt_out = mlt_out.and_then(|sum| sum.checked_add(output.value));
+            if let (Some(token_id), Some(data)) = (&token_id, &output.data) {
+                let entry = token_delta(&mut deltas, token_id);
+                let amount = token_amount(data);
+                match data {
+                    OutputData::TokenBurnV1 { .. } => {
+                        entry.burned = entry.burned.saturating_add(amount)
+                    }
+                    _ => entry.created = entry.created.saturating_add(amount),
+                }
+            }
+            for (token_id, amount) in multi_token_amounts(output.data.as_ref()) {
+                let entry = token_delta(&mut deltas, token_id);
+                entry.created = entry.created.saturating_add(*amount);
+            }
+            inspect_output(output, token_id)
+        })
+        .collect();
+
+    TransactionInspection {
+        id: BlakeTwo256::hash_of(tx),
+        inputs,
+        outputs,
+        fee: mlt_in.zip(mlt_out).and_then(|(mlt_in, mlt_out)| mlt_in.checked_sub(mlt_out)),
+        token_deltas: deltas.into_values().collect(),
+        validation_error: validate_transaction::<T>(tx).err().map(|e| e.as_bytes().to_vec()),
+    }
+}
+
+/// Check a transaction the way the transaction pool checks the transactions it receives from the
+/// network, without submitting it. The inputs are checked one by one, so that the report tells
+/// which of them are invalid even when the transaction fails for another reason.
+pub fn dry_run_spend<T: Config>(tx: &TransactionFor<T>) -> DryRunReport {
+    let utxos: Vec<_> = tx.inputs.iter().map(spent_utxo::<T>).collect();
+    // Signatures commit to all the spent UTXOs
+    let resolved: Option<Vec<_>> = utxos.iter().cloned().collect();
+    let inputs = utxos
+        .iter()
+        .enumerate()
+        .map(|(index, utxo)| match (utxo, &resolved) {
+            (None, _) => InputStatus::Missing,
+            (Some(_), None) => InputStatus::Unchecked,
+            (Some(_), Some(resolved)) => {
+                match check_input::<T>(tx, resolved, index, ScriptRules::Policy) {
+                    Ok(()) => InputStatus::Valid,
+                    Err(e) => InputStatus::Invalid(e.as_bytes().to_vec()),
+                }
+            }
+        })
+        .collect();
+    DryRunReport {
+        inspection: inspect_transaction::<T>(tx),
+        inputs,
+        pool_error: validate_pool_transaction::<T>(tx, TransactionSource::External)
+            .err()
+            .map(|e| e.as_bytes().to_vec()),
+    }
+}
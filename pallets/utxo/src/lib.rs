@@ -19,19 +19,58 @@
 
 pub use pallet::*;
 
+pub mod address;
+pub mod airdrop;
+pub mod auction;
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
-#[cfg(test)]
+pub mod bridge;
+pub mod builder;
+#[cfg(feature = "std")]
+pub mod channels;
+pub mod compliance;
+pub mod descriptor;
+#[cfg(feature = "std")]
+pub mod escrow;
+pub mod extension;
+pub mod fungibles;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod fuzzing;
+pub mod hooks;
+pub mod identity;
+pub mod inspect;
+pub mod migrations;
+#[cfg(any(test, feature = "fuzzing"))]
+#[cfg_attr(not(test), allow(dead_code))]
 mod mock;
+pub mod order;
+pub mod pause;
+pub mod priority;
+#[cfg(feature = "std")]
+pub mod psbt;
+#[cfg(feature = "std")]
+pub mod reference_validator;
 mod rewards;
+pub use rewards::{EraRewards, RewardSource};
 mod script;
 mod sign;
 pub mod staking;
 #[cfg(test)]
 mod staking_tests;
+pub mod stream;
+#[cfg(feature = "std")]
+pub use sign::musig;
+pub use sign::ReserveProof;
+pub use sign::SignatureScheme;
+#[cfg(feature = "std")]
+pub mod swap;
+pub mod taproot;
 #[cfg(test)]
 mod tests;
 pub mod tokens;
+pub mod verifier;
+pub mod versioned;
+pub mod vesting;
 pub mod weights;
 
 use chainscript::Builder;
@@ -43,28 +82,46 @@ use frame_support::{
 };
 use sp_core::{crypto::UncheckedFrom, H256, H512};
 use sp_runtime::sp_std::vec;
-use utxo_api::UtxoApi;
+use utxo_api::{Payout, PayoutDestination, UtxoApi};
 
 #[frame_support::pallet]
 pub mod pallet {
-    use crate::rewards::reward_block_author;
-    pub use crate::script::{BlockTime, RawBlockTime};
-    use crate::sign::{self, Scheme};
-    // todo: This part isn't fully tested, left for the next PR
-    // use crate::tokens::{NftDataHash};
-    use crate::staking::{self, StakingHelper};
-    use crate::tokens::{OutputData, TokenId, Value};
+    use crate::bridge::{self, BridgeHandler};
+    use crate::compliance::{TransferApprover, TransferPolicy};
+    use crate::hooks::OnUtxoEvent;
+    use crate::identity::IdentityProvider;
+    use crate::priority::{FeeAsset, PriorityInfo, PriorityPolicy};
+    use crate::rewards::{
+        credit_reward_pool, digest_author, reward_block_author, validate_reward_destination,
+        EraRewards, RewardSource,
+    };
+    pub use crate::script::{BlockTime, RawBlockTime, RelativeTimeLock, ScriptVersion};
+    use crate::script::{InputAge, ScriptRules};
+    use crate::sign;
+    use crate::staking::{self, EraIndex, StakingHelper};
+    use crate::taproot;
+    use crate::tokens::{
+        NftDataHash, NftRoyalty, OutputData, TickerCharset, TokenId, TokenMetadataRules, Value,
+    };
+    use crate::verifier::{self, ScriptCheck};
     use bech32;
     use chainscript::Script;
     use codec::{Decode, Encode};
     use core::marker::PhantomData;
+    use core::time::Duration;
     use frame_support::{
         dispatch::{DispatchResultWithPostInfo, Vec},
+        inherent::MakeFatalError,
         pallet_prelude::*,
-        sp_io::crypto,
-        sp_runtime::traits::{BlakeTwo256, Dispatchable, Hash},
-        sp_runtime::Percent,
-        traits::{IsSubType, UnixTime},
+        sp_runtime::traits::{
+            BlakeTwo256, Dispatchable, Hash, Saturating, UniqueSaturatedInto, Zero,
+        },
+        sp_runtime::{Perbill, Percent},
+        storage::{with_transaction, TransactionOutcome},
+        traits::{
+            Currency, EnsureOrigin, ExistenceRequirement, FindAuthor, Imbalance, IsSubType,
+            UnixTime, WithdrawReasons,
+        },
     };
     use frame_system::pallet_prelude::*;
     use hex_literal::hex;
@@ -72,11 +129,9 @@ pub mod pallet {
     #[cfg(feature = "std")]
     use serde::{Deserialize, Serialize};
     use sp_core::{
-        sp_std::collections::btree_map::BTreeMap,
+        sp_std::collections::{btree_map::BTreeMap, btree_set::BTreeSet},
         sp_std::{convert::TryInto, str, vec},
-        sr25519,
-        testing::SR25519,
-        H256, H512,
+        sr25519, H256, H512,
     };
     pub const MLT_UNIT: Value = 1_000 * 100_000_000;
 
@@ -135,10 +190,15 @@ pub mod pallet {
         /// Funds are waiting for the bonding duration to end,
         /// before withdrawal is allowed.
         FundsAtUnlockedState,
+
+        /// The unlocked stake can't be withdrawn before the bonding duration ends.
+        /// The era it becomes available at is found in `PendingWithdrawals`.
+        WithdrawalNotMature,
     }
 
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
+    #[pallet::storage_version(crate::migrations::STORAGE_VERSION)]
     pub struct Pallet<T>(PhantomData<T>);
 
     /// runtime configuration
@@ -165,6 +225,11 @@ pub mod pallet {
         #[pallet::constant]
         type RewardReductionFraction: Get<Percent>;
 
+        /// the most MLT that can ever be issued. Block rewards stop once the issuance reaches
+        /// it, and nothing else can mint past it.
+        #[pallet::constant]
+        type MaxMoney: Get<Value>;
+
         /// duration of unchanged rewards, before applying the RewardReductionFraction
         #[pallet::constant]
         type RewardReductionPeriod: Get<Self::BlockNumber>;
@@ -177,20 +242,218 @@ pub mod pallet {
         #[pallet::constant]
         type StakeWithdrawalFee: Get<Value>;
 
+        /// the highest commission a validator can set in its `LockForStaking` output.
+        #[pallet::constant]
+        type MaxValidatorCommission: Get<Perbill>;
+
+        /// where the `StakeWithdrawalFee` goes: burned, to a treasury account or to the block
+        /// author.
+        type FeeDestination: Get<staking::FeeDestination<Self::AccountId>>;
+
+        /// number of eras the unlocked stake stays locked before it can be withdrawn.
+        #[pallet::constant]
+        type BondingDuration: Get<EraIndex>;
+
+        /// the minimum fee of a transaction issuing a new token.
+        #[pallet::constant]
+        type TokenIssuanceFee: Get<Value>;
+
+        /// the tokens transaction fees can be paid in, with their MLT value.
+        type AcceptedFeeAssets: Get<Vec<FeeAsset>>;
+
+        /// the maximum number of bytes embedded in a data carrier output.
+        #[pallet::constant]
+        type MaxDataCarrierSize: Get<u32>;
+
+        /// the MLT an output kept in the UTXO set must carry for each byte of token data and
+        /// programmable pool code or input it stores. The deposit is part of the output value, so
+        /// it is reclaimed when the output is spent.
+        #[pallet::constant]
+        type StorageDepositPerByte: Get<Value>;
+
+        /// the minimum fee of a transaction minting a new NFT.
+        #[pallet::constant]
+        type NftMintFee: Get<Value>;
+
+        /// the maximum number of tokens and NFTs an issuer can create per era, keeping a single
+        /// issuer from flooding the token storage even if it pays the fees.
+        #[pallet::constant]
+        type IssuanceRateLimit: Get<u32>;
+
+        /// the fee of registering a destination to watch.
+        #[pallet::constant]
+        type WatchFee: Get<Value>;
+
+        /// whether a token ticker can only be used by the first token issued with it.
+        #[pallet::constant]
+        type UniqueTickers: Get<bool>;
+
+        /// tickers no token can be issued with, compared case-insensitively.
+        #[pallet::constant]
+        type ReservedTickers: Get<Vec<Vec<u8>>>;
+
+        /// longest ticker of a new token, at most `TokenMetadataRules::BOUNDS`.
+        #[pallet::constant]
+        type MaxTickerLength: Get<u32>;
+
+        /// most decimals of a new token, at most `TokenMetadataRules::BOUNDS`.
+        #[pallet::constant]
+        type MaxTokenDecimals: Get<u8>;
+
+        /// longest metadata uri of a new token or NFT, at most `TokenMetadataRules::BOUNDS`.
+        #[pallet::constant]
+        type MaxMetadataUriLength: Get<u32>;
+
+        /// characters the ticker of a new token may be made of.
+        #[pallet::constant]
+        type TokenTickerCharset: Get<TickerCharset>;
+
+        /// for how many eras the rewards credited and paid are kept in `RewardHistory`.
+        #[pallet::constant]
+        type RewardHistoryDepth: Get<EraIndex>;
+
+        /// for how many blocks spent outputs are kept in the STXO journal.
+        #[pallet::constant]
+        type StxoRetention: Get<Self::BlockNumber>;
+
+        /// every how many blocks a balance snapshot is taken, zero takes them only on demand.
+        #[pallet::constant]
+        type SnapshotPeriod: Get<Self::BlockNumber>;
+
+        /// how many UTXOs a balance snapshot in progress scans, and how many stale snapshot
+        /// entries are pruned, per block.
+        #[pallet::constant]
+        type SnapshotBatchSize: Get<u32>;
+
+        /// how many of the latest balance snapshots are kept, at least one.
+        #[pallet::constant]
+        type SnapshotHistoryDepth: Get<u32>;
+
+        /// for how many blocks the bodies of plain pay-to-pubkey UTXOs are kept when built with
+        /// the `pruning` feature, zero keeps them forever. Pruned UTXOs no longer count towards
+        /// balance snapshots.
+        #[pallet::constant]
+        type PruningDepth: Get<Self::BlockNumber>;
+
+        /// how many of the latest transfers of each NFT are kept in `NftProvenance`, zero keeps
+        /// no history.
+        #[pallet::constant]
+        type NftProvenanceDepth: Get<u32>;
+
+        /// the largest share of the normal block weight a single transaction may take, so that
+        /// verification-heavy transactions can't crowd out the others.
+        #[pallet::constant]
+        type MaxTransactionWeightShare: Get<Perbill>;
+
+        /// how many accounts may watch a single destination. Each UTXO sent to or spent from a
+        /// destination emits an event per watcher, which spends are charged for.
+        #[pallet::constant]
+        type MaxWatchers: Get<u32>;
+
+        /// the largest witness, in bytes, an input of a transaction entering the pool may carry.
+        /// Larger witnesses are still valid in a block.
+        #[pallet::constant]
+        type MaxStandardWitnessSize: Get<u32>;
+
+        /// the smallest value of an output without tokens the pool accepts, smaller outputs
+        /// cost more to spend than they are worth. Smaller outputs are still valid in a block.
+        #[pallet::constant]
+        type DustThreshold: Get<Value>;
+
+        /// the most UTXOs `consolidate` merges at once, keeping its weight within a block.
+        #[pallet::constant]
+        type MaxConsolidationInputs: Get<u32>;
+
+        /// the chain id committed to by the signatures bound to the chain, so that they can't be
+        /// replayed on another network. See `sign::ChainBinding`.
+        #[pallet::constant]
+        type ChainId: Get<H256>;
+
+        /// signatures not bound to the chain are accepted in the blocks before this one only.
+        #[pallet::constant]
+        type UnboundSignaturesUntil: Get<Self::BlockNumber>;
+
+        /// witnesses not in their canonical encoding are accepted in the blocks before this one
+        /// only. The pool always rejects them. See `verifier::check_witness_canonicality`.
+        #[pallet::constant]
+        type NonCanonicalWitnessesUntil: Get<Self::BlockNumber>;
+
+        /// the signature scheme the extrinsics signing on behalf of the caller sign with.
+        /// Witnesses of other schemes are accepted as long as they match the key spending.
+        /// The keys of the scheme have to be account ids, which rules out BLS.
+        #[pallet::constant]
+        type DefaultSignatureScheme: Get<sign::SignatureScheme>;
+
         /// helps in dealing with staking, using the logic outside of this pallet.
         /// An example is the `pallet-staking`, where it deals with multiple operations
         /// that utxo will convert with only a few. see the trait at pallet-utxo's `staking.rs`.
         type StakingHelper: StakingHelper<Self::AccountId>;
 
+        /// authorizes the relayers and verifies the proofs of the token bridge.
+        /// see the trait at pallet-utxo's `bridge.rs`.
+        type BridgeHandler: BridgeHandler<Self::AccountId>;
+
+        /// approves the transfers of tokens whose issuer requires it.
+        /// see the trait at pallet-utxo's `compliance.rs`.
+        type TransferApprover: TransferApprover<Self::AccountId>;
+
+        /// reacts to transactions adding outputs to the UTXO set and spending UTXOs.
+        /// see the trait at pallet-utxo's `hooks.rs`.
+        type UtxoHooks: OnUtxoEvent<Self::AccountId>;
+
+        /// the account balances MLT is wrapped from and unwrapped to.
+        type Currency: Currency<Self::AccountId, Balance = Value>;
+
+        /// calculates the priority of transactions in the pool and the minimum fee they pay.
+        /// see `priority.rs` for the available policies.
+        type PriorityPolicy: PriorityPolicy;
+
+        /// the origin allowed to restrict token issuance to the pubkeys of `TokenIssuers`, and
+        /// to add and remove them.
+        type TokenIssuerOrigin: EnsureOrigin<Self::Origin>;
+
+        /// the origin allowed to pause spends in an emergency with `set_paused`.
+        type PauseOrigin: EnsureOrigin<Self::Origin>;
+
+        /// gives the names of the on-chain identities payments can be addressed to.
+        /// see `identity.rs`.
+        type Identities: IdentityProvider<Self::AccountId>;
+
+        /// finds the block author in the pre-runtime digest of the block, e.g. the Aura or Babe
+        /// authority that sealed it. used when `pallet-authorship` did not note an author.
+        type FindAuthor: FindAuthor<Self::AccountId>;
+
         fn authorities() -> Vec<H256>;
     }
 
     pub trait WeightInfo {
         fn spend(u: u32) -> Weight;
         fn token_create(u: u32) -> Weight;
+        fn mint_nft(u: u32) -> Weight;
         fn send_to_address(u: u32) -> Weight;
+        fn burn(u: u32) -> Weight;
         fn unlock_request_for_withdrawal(u: u32) -> Weight;
         fn withdraw_stake(u: u32) -> Weight;
+        fn watch_destination(u: u32) -> Weight;
+        fn send_to_addresses(u: u32) -> Weight;
+        fn leave_staking_pool(u: u32) -> Weight;
+        fn rotate_session_keys(u: u32) -> Weight;
+        fn redelegate_stake(u: u32) -> Weight;
+        fn take_balance_snapshot(u: u32) -> Weight;
+        fn release_from_bridge(u: u32) -> Weight;
+        fn set_transfer_policy(u: u32) -> Weight;
+        fn wrap(u: u32) -> Weight;
+        fn unwrap(u: u32) -> Weight;
+        fn spend_script_bytes(b: u32) -> Weight;
+        fn notify_watchers(n: u32) -> Weight;
+        fn claim_reward(u: u32) -> Weight;
+        fn set_airdrop_root(u: u32) -> Weight;
+        fn claim(u: u32) -> Weight;
+        fn on_finalize(u: u32) -> Weight;
+        fn consolidate(u: u32) -> Weight;
+        fn set_token_issuer(u: u32) -> Weight;
+        fn set_paused(u: u32) -> Weight;
+        fn set_identity_destination(u: u32) -> Weight;
     }
 
     /// Transaction input
@@ -207,9 +470,17 @@ pub mod pallet {
     /// * `Destination::Pubkey(key)`
     ///   * `lock` has to be empty
     ///   * `witness` contains the signature for the transaction and given pubkey
-    /// * `Destination::ScriptHash(script_hash)`
+    /// * `Destination::ScriptHash(version, script_hash)`
     ///   * `lock` is the script fully expanded out, hash of `lock` has to match `script_hash`
+    ///   * `version` determines how the script is interpreted, see [ScriptVersion]
     ///   * `witness` is a script that generates the input to the `lock` script
+    /// * `Destination::Taproot { internal_key, merkle_root }`
+    ///   * either `lock` is empty and `witness` is a signature for `internal_key`
+    ///   * or `lock` is a script committed to by `merkle_root`, see [crate::taproot]
+    /// * `Destination::Inheritance { owner, heir, delay }`
+    ///   * either `lock` is empty and `witness` is a signature for `owner`
+    ///   * or `lock` is the `heir` pubkey, `witness` is a signature for it and the UTXO is at
+    ///     least `delay` blocks old
     #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
     #[derive(
         Clone, Encode, Decode, Eq, PartialEq, PartialOrd, Ord, RuntimeDebug, Hash, Default,
@@ -242,6 +513,16 @@ pub mod pallet {
             }
         }
 
+        /// New input spending a `Destination::Inheritance` UTXO with the key of the heir, the
+        /// `witness` to be filled later.
+        pub fn new_heir(outpoint: H256, heir: H256) -> Self {
+            Self {
+                outpoint,
+                lock: heir.as_bytes().to_vec(),
+                witness: Vec::new(),
+            }
+        }
+
         /// New input with lock script and witness script.
         pub fn new_script(outpoint: H256, lock: Script, witness: Script) -> Self {
             Self {
@@ -251,6 +532,18 @@ pub mod pallet {
             }
         }
 
+        /// New input spending a UTXO whose body has been pruned, the output is passed in `lock`.
+        pub fn new_pruned<AccountId: Encode>(
+            outpoint: H256,
+            output: &TransactionOutput<AccountId>,
+        ) -> Self {
+            Self {
+                outpoint,
+                lock: output.encode(),
+                witness: Vec::new(),
+            }
+        }
+
         /// Get lock hash.
         pub fn lock_hash(&self) -> H256 {
             BlakeTwo256::hash(&self.lock)
@@ -266,16 +559,32 @@ pub mod pallet {
         /// Pay to fund a new programmable pool. Takes code and data.
         CreatePP(Vec<u8>, Vec<u8>),
         /// Pay to an existing contract. Takes a destination account,
-        /// whether the call funds the contract, and input data.
-        CallPP(AccountId, bool, Vec<u8>),
-        /// Pay to script hash
-        ScriptHash(H256),
+        /// whether the call funds the contract, input data and optionally
+        /// the hash of the contract ABI the call is checked against.
+        CallPP(AccountId, bool, Vec<u8>, Option<H256>),
+        /// Pay to script hash. Takes the version of the script and its hash.
+        ScriptHash(ScriptVersion, H256),
         /// First attempt of staking.
         /// Must assign a controller, in order to bond and validate. see pallet-staking
         LockForStaking {
             stash_account: AccountId,
             controller_account: AccountId,
             session_key: Vec<u8>,
+            /// Key the stake is returned to on withdrawal, the stash account if `None`.
+            /// Allows cold staking, where the withdrawal key stays offline.
+            #[cfg_attr(feature = "std", serde(default))]
+            withdrawal_key: Option<sr25519::Public>,
+            /// Account allowed some staking operations on behalf of the stash account, see
+            /// [staking::SecondaryController].
+            #[cfg_attr(feature = "std", serde(default))]
+            secondary_controller: Option<staking::SecondaryController<AccountId>>,
+            /// Share of the rewards the validator keeps before paying its nominators, set in
+            /// the validator preferences of `pallet-staking`.
+            #[cfg_attr(feature = "std", serde(default))]
+            commission: Perbill,
+            /// The least the validator bonds itself. The value of the output can't be lower.
+            #[cfg_attr(feature = "std", serde(default))]
+            min_self_bond: Value,
         },
         /// lock more funds
         /// The controller_account should be the EXACT one for the stash_account,
@@ -284,6 +593,68 @@ pub mod pallet {
             stash_account: AccountId,
             controller_account: AccountId,
         },
+        /// Pay to a key, usually aggregated from the keys of several parties, or alternatively
+        /// to one of the scripts in a Merkle tree with given root.
+        Taproot {
+            internal_key: sr25519::Public,
+            merkle_root: H256,
+        },
+        /// Create a staking pool validating with the given accounts, and lock the first share of
+        /// it, owned by `owner`. The pool is bonded once its members lock the minimum stake.
+        CreateStakingPool {
+            stash_account: AccountId,
+            controller_account: AccountId,
+            session_key: Vec<u8>,
+            owner: sr25519::Public,
+        },
+        /// Lock a share of the staking pool of the given stash account, owned by `member`.
+        JoinStakingPool {
+            stash_account: AccountId,
+            member: sr25519::Public,
+        },
+        /// Pay to a pubkey, releasing the `total` granted linearly over `duration` blocks from the
+        /// `start` block, and nothing before `cliff` blocks pass. See the `vesting` module.
+        Vesting {
+            pubkey: sr25519::Public,
+            total: Value,
+            start: u64,
+            cliff: u64,
+            duration: u64,
+        },
+        /// Provably unspendable output embedding arbitrary data, such as the commitment anchors
+        /// of sidechains. Carries no value and is never added to the UTXO set.
+        DataCarrier(Vec<u8>),
+        /// Escrow the NFT carried by the output in an auction settled at the `end` block, selling
+        /// it to the best bid of at least `reserve_price`. See the `auction` module.
+        Auction {
+            seller: sr25519::Public,
+            reserve_price: Value,
+            end: u64,
+        },
+        /// Bid the value of the output in the auction escrowed at the `auction` outpoint. Refunded
+        /// to `bidder` once outbid.
+        Bid {
+            auction: H256,
+            bidder: sr25519::Public,
+        },
+        /// Offer the MLT or the token carried by the output for at least `ask_amount` of
+        /// `ask_token`, MLT if `None`. Filled by anyone paying `maker`, cancelled by `maker`.
+        /// See the `order` module.
+        Order {
+            maker: sr25519::Public,
+            ask_token: Option<TokenId>,
+            ask_amount: Value,
+        },
+        /// Pay the value of the output to `payee` at `rate` per block from its creation. See the
+        /// `stream` module.
+        Stream { payee: sr25519::Public, rate: Value },
+        /// Pay to `owner`, or to `heir` once the UTXO has been left unspent for `delay` blocks,
+        /// a dead man's switch. The heir spends with its pubkey as the lock of the input.
+        Inheritance {
+            owner: sr25519::Public,
+            heir: sr25519::Public,
+            delay: u64,
+        },
     }
 
     impl<AccountId> Destination<AccountId> {
@@ -294,11 +665,13 @@ pub mod pallet {
 
         /// Calculate lock commitment for given destination.
         ///
-        /// The `lock` field of the input spending the UTXO has to match this hash.
-        pub fn lock_commitment(&self) -> &H256 {
+        /// The `lock` field of the input spending the UTXO has to match this hash. Returns `None`
+        /// if the `lock` is checked against the destination together with the witness.
+        pub fn lock_commitment(&self) -> Option<&H256> {
             match self {
-                Destination::ScriptHash(hash) => hash,
-                _ => &Self::EMPTY,
+                Destination::ScriptHash(_, hash) => Some(hash),
+                Destination::Taproot { .. } | Destination::Inheritance { .. } => None,
+                _ => Some(&Self::EMPTY),
             }
         }
     }
@@ -346,6 +719,87 @@ pub mod pallet {
                     stash_account,
                     controller_account,
                     session_key,
+                    withdrawal_key: None,
+                    secondary_controller: None,
+                    commission: Perbill::zero(),
+                    min_self_bond: 0,
+                },
+                data: None,
+            }
+        }
+
+        /// Create a new stake that is returned to `withdrawal_key` instead of the stash account.
+        /// The stash and controller accounts run the validator, but can't take the funds.
+        pub fn new_cold_lock_for_staking(
+            value: Value,
+            stash_account: AccountId,
+            controller_account: AccountId,
+            session_key: Vec<u8>,
+            withdrawal_key: sr25519::Public,
+        ) -> Self {
+            Self {
+                value,
+                destination: Destination::LockForStaking {
+                    stash_account,
+                    controller_account,
+                    session_key,
+                    withdrawal_key: Some(withdrawal_key),
+                    secondary_controller: None,
+                    commission: Perbill::zero(),
+                    min_self_bond: 0,
+                },
+                data: None,
+            }
+        }
+
+        /// Create a new stake with a secondary controller, allowed the staking operations in
+        /// `permissions` on behalf of the stash account.
+        pub fn new_lock_for_staking_with_secondary_controller(
+            value: Value,
+            stash_account: AccountId,
+            controller_account: AccountId,
+            session_key: Vec<u8>,
+            secondary_account: AccountId,
+            permissions: staking::StakingPermissions,
+        ) -> Self {
+            Self {
+                value,
+                destination: Destination::LockForStaking {
+                    stash_account,
+                    controller_account,
+                    session_key,
+                    withdrawal_key: None,
+                    secondary_controller: Some(staking::SecondaryController {
+                        account: secondary_account,
+                        permissions,
+                    }),
+                    commission: Perbill::zero(),
+                    min_self_bond: 0,
+                },
+                data: None,
+            }
+        }
+
+        /// Create a new stake of a validator keeping `commission` of the rewards, and bonding
+        /// at least `min_self_bond` itself.
+        pub fn new_lock_for_staking_with_commission(
+            value: Value,
+            stash_account: AccountId,
+            controller_account: AccountId,
+            session_key: Vec<u8>,
+            commission: Perbill,
+            min_self_bond: Value,
+        ) -> Self {
+            Self {
+                value,
+                destination: Destination::LockForStaking {
+                    stash_account,
+                    controller_account,
+                    session_key,
+                    withdrawal_key: None,
+                    secondary_controller: None,
+                    commission,
+                    min_self_bond,
                 },
                 data: None,
             }
@@ -367,6 +821,145 @@ pub mod pallet {
             }
         }
 
+        /// Create a new staking pool, with `value` being the share of `owner`.
+        pub fn new_create_staking_pool(
+            value: Value,
+            stash_account: AccountId,
+            controller_account: AccountId,
+            session_key: Vec<u8>,
+            owner: sr25519::Public,
+        ) -> Self {
+            Self {
+                value,
+                destination: Destination::CreateStakingPool {
+                    stash_account,
+                    controller_account,
+                    session_key,
+                    owner,
+                },
+                data: None,
+            }
+        }
+
+        /// Create a share of an existing staking pool, owned by `member`.
+        pub fn new_join_staking_pool(
+            value: Value,
+            stash_account: AccountId,
+            member: sr25519::Public,
+        ) -> Self {
+            Self {
+                value,
+                destination: Destination::JoinStakingPool {
+                    stash_account,
+                    member,
+                },
+                data: None,
+            }
+        }
+
+        /// Create a new output holding `value` of the `total` released to the pubkey over
+        /// `duration` blocks from `start`.
+        pub fn new_vesting(
+            value: Value,
+            pubkey: H256,
+            total: Value,
+            start: u64,
+            cliff: u64,
+            duration: u64,
+        ) -> Self {
+            Self {
+                value,
+                destination: Destination::Vesting {
+                    pubkey: sr25519::Public::from_h256(pubkey),
+                    total,
+                    start,
+                    cliff,
+                    duration,
+                },
+                data: None,
+            }
+        }
+
+        /// Create a new output streaming `value` to the payee at `rate` per block.
+        pub fn new_stream(value: Value, payee: H256, rate: Value) -> Self {
+            Self {
+                value,
+                destination: Destination::Stream {
+                    payee: sr25519::Public::from_h256(payee),
+                    rate,
+                },
+                data: None,
+            }
+        }
+
+        /// Create a new output paying `value` to the owner, or to the heir after `delay` blocks
+        /// without being spent.
+        pub fn new_inheritance(value: Value, owner: H256, heir: H256, delay: u64) -> Self {
+            Self {
+                value,
+                destination: Destination::Inheritance {
+                    owner: sr25519::Public::from_h256(owner),
+                    heir: sr25519::Public::from_h256(heir),
+                    delay,
+                },
+                data: None,
+            }
+        }
+
+        /// Create a new output embedding `data`, it carries no value.
+        pub fn new_data_carrier(data: Vec<u8>) -> Self {
+            Self {
+                value: 0,
+                destination: Destination::DataCarrier(data),
+                data: None,
+            }
+        }
+
+        /// Create a new output auctioning the NFT transferred by `data` until the `end` block.
+        pub fn new_auction(seller: H256, reserve_price: Value, end: u64, data: OutputData) -> Self {
+            Self {
+                value: 0,
+                destination: Destination::Auction {
+                    seller: sr25519::Public::from_h256(seller),
+                    reserve_price,
+                    end,
+                },
+                data: Some(data),
+            }
+        }
+
+        /// Create a new output bidding `value` in the auction escrowed at the `auction` outpoint.
+        pub fn new_bid(value: Value, auction: H256, bidder: H256) -> Self {
+            Self {
+                value,
+                destination: Destination::Bid {
+                    auction,
+                    bidder: sr25519::Public::from_h256(bidder),
+                },
+                data: None,
+            }
+        }
+
+        /// Create a new output offering `value` MLT, or the token transferred by `data`, for at
+        /// least `ask_amount` of `ask_token`, MLT if `None`.
+        pub fn new_order(
+            value: Value,
+            data: Option<OutputData>,
+            maker: H256,
+            ask_token: Option<TokenId>,
+            ask_amount: Value,
+        ) -> Self {
+            Self {
+                value,
+                destination: Destination::Order {
+                    maker: sr25519::Public::from_h256(maker),
+                    ask_token,
+                    ask_amount,
+                },
+                data,
+            }
+        }
+
         /// Create a new output to create a smart contract.
         pub fn new_create_pp(value: Value, code: Vec<u8>, data: Vec<u8>) -> Self {
             Self {
@@ -376,25 +969,46 @@ pub mod pallet {
             }
         }
 
-        /// Create a new output to call a smart contract routine.
+        /// Create a new output to call a smart contract routine. If `abi_hash` is given, the
+        /// call is only valid if the contract's registered ABI has this hash.
         pub fn new_call_pp(
             value: Value,
             dest_account: AccountId,
             fund: bool,
             input: Vec<u8>,
+            abi_hash: Option<H256>,
         ) -> Self {
             Self {
                 value,
-                destination: Destination::CallPP(dest_account, fund, input),
+                destination: Destination::CallPP(dest_account, fund, input, abi_hash),
                 data: None,
             }
         }
 
         /// Create a new output to given script hash.
         pub fn new_script_hash(value: Value, hash: H256) -> Self {
+            Self::new_versioned_script_hash(value, ScriptVersion::V0, hash)
+        }
+
+        /// Create a new output to given script hash, interpreted according to `version`.
+        pub fn new_versioned_script_hash(value: Value, version: ScriptVersion, hash: H256) -> Self {
+            Self {
+                value,
+                destination: Destination::ScriptHash(version, hash),
+                data: None,
+            }
+        }
+
+        /// Create a new Taproot output, spendable by `internal_key` or by one of the scripts in
+        /// the Merkle tree with given root.
+        pub fn new_taproot(value: Value, internal_key: H256, merkle_root: H256) -> Self {
+            let internal_key = sp_core::sr25519::Public::from_h256(internal_key);
             Self {
                 value,
-                destination: Destination::ScriptHash(hash),
+                destination: Destination::Taproot {
+                    internal_key,
+                    merkle_root,
+                },
                 data: None,
             }
         }
@@ -410,6 +1024,39 @@ pub mod pallet {
         }
     }
 
+    /// A staking pool, where many holders share the stake of one validator.
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+    pub struct StakingPool<AccountId> {
+        pub controller_account: AccountId,
+        pub session_key: Vec<u8>,
+        /// Keys of the members and the value each of them has locked in the pool
+        pub members: Vec<(sr25519::Public, Value)>,
+        /// Whether the members have locked the minimum stake and the pool is validating
+        pub bonded: bool,
+    }
+
+    /// A change of the destination holding an NFT.
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+    pub struct NftTransfer<AccountId, BlockNumber> {
+        pub block_number: BlockNumber,
+        pub from: Destination<AccountId>,
+        pub to: Destination<AccountId>,
+    }
+
+    /// An input of a transaction in a block spending an output that is not in the UTXO set.
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+    pub struct MissingInputProof<BlockNumber> {
+        /// Hash of the transaction with the input
+        pub tx_hash: H256,
+        pub outpoint: H256,
+        /// Hash of the transaction that spent the output before and the block it was spent in,
+        /// if the spend is still in the STXO journal
+        pub spent_by: Option<(H256, BlockNumber)>,
+    }
+
     pub fn convert_to_h256<T: Config>(account: &T::AccountId) -> Result<H256, DispatchError> {
         let pubkey_raw: [u8; 32] = account
             .encode()
@@ -425,20 +1072,60 @@ pub mod pallet {
         pub(crate) inputs: Vec<TransactionInput>,
         pub(crate) outputs: Vec<TransactionOutput<AccountId>>,
         pub(crate) time_lock: RawBlockTime,
+        /// The transaction can't be included in a block after this time, `None` if it never
+        /// expires
+        #[cfg_attr(feature = "std", serde(default))]
+        pub(crate) expires_at: Option<RawBlockTime>,
     }
 
     impl<AccountId: Encode> Transaction<AccountId> {
         /// Get hash of output at given index.
         pub fn outpoint(&self, index: u64) -> H256 {
-            BlakeTwo256::hash_of(&(self, index)).into()
+            match self.expires_at {
+                // Encoded as before transactions could expire, so that the outpoints of such
+                // transactions did not change with expiries
+                None => {
+                    BlakeTwo256::hash_of(&(&self.inputs, &self.outputs, &self.time_lock, index))
+                }
+                Some(_) => BlakeTwo256::hash_of(&(self, index)),
+            }
+        }
+
+        /// Outpoints of all the outputs, in the order of the outputs. These are the keys the
+        /// outputs are stored at once the transaction is spent, whatever their destination.
+        ///
+        /// The outpoint of the output at `index` is the Blake2-256 hash of the SCALE encoding of
+        /// the transaction *with its witnesses*, followed by `index` as a little endian `u64`.
+        /// A transaction that never expires is encoded without its `expires_at` field.
+        /// The witnesses are not stripped, so the outpoints are only known once every input is
+        /// signed, and they change if a witness is replaced.
+        pub fn outpoint_hashes(&self) -> Vec<H256> {
+            (0..self.outputs.len() as u64).map(|index| self.outpoint(index)).collect()
         }
 
-        // A convenience method to sign a transaction. Only Schnorr supported for now.
-        pub fn sign(
+        /// Id of the transaction: the Blake2-256 hash of its SCALE encoding with the witnesses
+        /// left empty. Unlike the outpoints, it is known before the inputs are signed and it
+        /// doesn't change with the witnesses. The outputs can also be referenced by the id of
+        /// their transaction and their index, see `Pallet::outpoint_of`.
+        pub fn txid(&self) -> H256 {
+            let inputs: Vec<TransactionInput> = self
+                .inputs
+                .iter()
+                .map(|input| TransactionInput {
+                    witness: Vec::new(),
+                    ..input.clone()
+                })
+                .collect();
+            BlakeTwo256::hash_of(&(&inputs, &self.outputs, &self.time_lock, &self.expires_at))
+        }
+
+        /// A convenience method to sign a transaction with the key `pk` from the keystore. The
+        /// witness is prefixed with the signature scheme of the key, see `sign::Public::witness`.
+        pub fn sign<P: Into<sign::Public> + Copy>(
             mut self,
             utxos: &[TransactionOutput<AccountId>],
             index: usize,
-            pk: &sr25519::Public,
+            pk: &P,
         ) -> Option<Self> {
             let msg = crate::sign::TransactionSigMsg::construct(
                 Default::default(),
@@ -447,13 +1134,104 @@ pub mod pallet {
                 index as u64,
                 u32::MAX,
             );
-            self.inputs[index].witness =
-                crypto::sr25519_sign(SR25519, pk, &msg.encode())?.0.to_vec();
+            self.inputs[index].witness = (*pk).into().witness(&msg)?;
             Some(self)
         }
 
-        pub fn check_time_lock<T: Config>(&self) -> bool {
-            match self.time_lock.time() {
+        /// Sign the input at `index` with a signature committing to the chain `chain_id`, so that
+        /// it can't be replayed on another network.
+        pub fn sign_for_chain<P: Into<sign::Public> + Copy>(
+            mut self,
+            utxos: &[TransactionOutput<AccountId>],
+            index: usize,
+            pk: &P,
+            chain_id: H256,
+        ) -> Option<Self> {
+            let sighash = sign::SigHash::default().with_chain();
+            let msg = crate::sign::TransactionSigMsg::construct(
+                sighash,
+                &self,
+                utxos,
+                index as u64,
+                u32::MAX,
+            )
+            .for_chain(chain_id);
+            self.inputs[index].witness = (*pk).into().witness(&msg)?;
+            Some(self)
+        }
+
+        /// Signature of the input at `index` to be pushed by the witness of a script, such as
+        /// the channel and escrow scripts. Commits to the whole transaction. The scheme is given
+        /// by the pubkey in the script, so the signature has no prefix.
+        pub fn script_signature<P: Into<sign::Public> + Copy>(
+            &self,
+            utxos: &[TransactionOutput<AccountId>],
+            index: usize,
+            pk: &P,
+        ) -> Option<Vec<u8>> {
+            let msg = crate::sign::TransactionSigMsg::construct(
+                Default::default(),
+                self,
+                utxos,
+                index as u64,
+                u32::MAX,
+            );
+            (*pk).into().sign_raw(&msg.encode())
+        }
+
+        /// Set the witness of the input at `index`.
+        pub fn with_witness(mut self, index: usize, witness: Script) -> Self {
+            self.inputs[index].witness = witness.into_bytes();
+            self
+        }
+
+        /// Weight of spending the transaction, the same as charged by the `spend` extrinsic.
+        /// Grows with the number of inputs and outputs and with the bytes of script to verify.
+        /// Every input and output is charged for notifying `MaxWatchers` watchers, as many as its
+        /// destination may have.
+        pub fn estimated_weight<T: Config>(&self) -> Weight {
+            let entries: u32 = self
+                .inputs
+                .len()
+                .saturating_add(self.outputs.len())
+                .try_into()
+                .unwrap_or(u32::MAX);
+            let script_bytes = self.script_bytes().try_into().unwrap_or(u32::MAX);
+            <T as Config>::WeightInfo::spend(entries)
+                .saturating_add(<T as Config>::WeightInfo::spend_script_bytes(script_bytes))
+                .saturating_add(<T as Config>::WeightInfo::notify_watchers(
+                    entries.saturating_mul(T::MaxWatchers::get()),
+                ))
+        }
+
+        /// Total size of the witnesses and locks of the inputs.
+        pub fn script_bytes(&self) -> usize {
+            self.inputs
+                .iter()
+                .map(|input| input.witness.len().saturating_add(input.lock.len()))
+                .fold(0, usize::saturating_add)
+        }
+
+        /// Check the transaction fits the limits the runtime puts on a normal extrinsic, so that
+        /// wallets can tell before signing. The signature only adds to the encoded size once the
+        /// witness is filled in, and the call wrapping the transaction adds a few bytes more.
+        pub fn encoded_size_limit_check<T: Config>(&self) -> Result<(), &'static str> {
+            let max_length = *T::BlockLength::get().max.get(DispatchClass::Normal);
+            ensure!(
+                self.encoded_size() <= max_length as usize,
+                "transaction exceeds the maximum block length"
+            );
+            let max_weight = T::BlockWeights::get().get(DispatchClass::Normal).max_extrinsic;
+            ensure!(
+                max_weight.map_or(true, |max_weight| self.estimated_weight::<T>()
+                    <= max_weight),
+                "transaction exceeds the maximum extrinsic weight"
+            );
+            Ok(())
+        }
+
+        pub fn check_time_lock<T: Config>(&self) -> bool {
+            match self.time_lock.time() {
                 BlockTime::Blocks(lock_block_num) => {
                     <frame_system::Pallet<T>>::block_number() >= lock_block_num.into()
                 }
@@ -462,6 +1240,38 @@ pub mod pallet {
                 }
             }
         }
+
+        pub fn check_expiry<T: Config>(&self) -> bool {
+            match self.expires_at.map(|expiry| expiry.time()) {
+                None => true,
+                Some(BlockTime::Blocks(expiry_block_num)) => {
+                    <frame_system::Pallet<T>>::block_number() <= expiry_block_num.into()
+                }
+                Some(BlockTime::Timestamp(expiry_time)) => {
+                    <pallet_timestamp::Pallet<T> as UnixTime>::now() <= expiry_time
+                }
+            }
+        }
+
+        /// Number of blocks the transaction stays valid for. Expiry timestamps are converted to
+        /// blocks assuming a block is produced every two minimum periods of the timestamp pallet.
+        pub fn longevity<T: Config>(&self) -> TransactionLongevity {
+            match self.expires_at.map(|expiry| expiry.time()) {
+                None => TransactionLongevity::MAX,
+                Some(BlockTime::Blocks(expiry_block_num)) => {
+                    let block_num: u64 =
+                        <frame_system::Pallet<T>>::block_number().unique_saturated_into();
+                    (expiry_block_num as u64).saturating_sub(block_num).max(1)
+                }
+                Some(BlockTime::Timestamp(expiry_time)) => {
+                    let now = <pallet_timestamp::Pallet<T> as UnixTime>::now();
+                    let remaining = expiry_time.saturating_sub(now).as_millis() as u64;
+                    let period: u64 = <T as pallet_timestamp::Config>::MinimumPeriod::get()
+                        .unique_saturated_into();
+                    (remaining / period.saturating_mul(2).max(1)).max(1)
+                }
+            }
+        }
     }
 
     // Transaction output type associated with given Config.
@@ -476,14 +1286,115 @@ pub mod pallet {
     #[pallet::getter(fn reward_total)]
     pub(super) type RewardTotal<T> = StorageValue<_, Value, ValueQuery>;
 
+    /// MLT credited to the reward pool and paid to the block authors in each of the last
+    /// `RewardHistoryDepth` eras, oldest first, so that validators can reconcile their earnings.
+    #[pallet::storage]
+    #[pallet::getter(fn reward_history)]
+    pub(super) type RewardHistory<T> = StorageValue<_, Vec<EraRewards>, ValueQuery>;
+
+    /// Total MLT in existence: the genesis UTXOs plus the block rewards minted since. Fees are
+    /// only moved around through `RewardTotal`, so they don't change it.
+    #[pallet::storage]
+    #[pallet::getter(fn mlt_issuance)]
+    pub(super) type MltIssuance<T> = StorageValue<_, Value, ValueQuery>;
+
+    /// Transaction fees paid in tokens in the current block, paid to the block author.
+    #[pallet::storage]
+    #[pallet::getter(fn token_reward_total)]
+    pub(super) type TokenRewardTotal<T> = StorageMap<_, Identity, TokenId, Value, ValueQuery>;
+
+    /// Restrictions on the recipients of tokens, set by their issuers.
+    #[pallet::storage]
+    #[pallet::getter(fn transfer_policy)]
+    pub(super) type TokenTransferPolicy<T> =
+        StorageMap<_, Identity, TokenId, TransferPolicy, OptionQuery>;
+
+    /// Whether tokens and NFTs can only be issued by the pubkeys of `TokenIssuers`. Off by
+    /// default, anyone can issue.
+    #[pallet::storage]
+    #[pallet::getter(fn permissioned_issuance)]
+    pub(super) type PermissionedIssuance<T> = StorageValue<_, bool, ValueQuery>;
+
+    /// Tokens and NFTs created by an issuer in the last era it created any: hash of the
+    /// destination of the first input of the issuing transactions -> (era, count).
+    /// Capped by `IssuanceRateLimit`.
+    #[pallet::storage]
+    #[pallet::getter(fn issuance_count)]
+    pub(super) type IssuanceCount<T> = StorageMap<_, Identity, H256, (EraIndex, u32), ValueQuery>;
+
+    /// Pubkeys allowed to issue tokens and NFTs while `PermissionedIssuance` is on.
+    #[pallet::storage]
+    pub(super) type TokenIssuers<T> = StorageMap<_, Identity, H256, (), OptionQuery>;
+
+    /// The transactions halted by `set_paused`, if any. See `pause.rs`.
+    #[pallet::storage]
+    #[pallet::getter(fn paused)]
+    pub(super) type Paused<T> = StorageValue<_, crate::pause::PauseScope, OptionQuery>;
+
+    /// Destination payments to each identity name are sent to, with the account that set it:
+    /// name -> (owner, destination). See `identity.rs`.
+    #[pallet::storage]
+    pub(super) type IdentityDestinations<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        Vec<u8>,
+        (T::AccountId, Destination<T::AccountId>),
+        OptionQuery,
+    >;
+
+    /// Number of wraps so far, makes the outpoints of the wrapped UTXOs unique.
+    #[pallet::storage]
+    pub(super) type WrapNonce<T> = StorageValue<_, u64, ValueQuery>;
+
     #[pallet::storage]
     #[pallet::getter(fn current_block_author)]
     pub(super) type BlockAuthor<T> = StorageValue<_, H256, OptionQuery>;
 
+    /// Block rewards of the blocks whose author wasn't found, paid to the next block author
+    /// found. They are only minted once paid.
+    #[pallet::storage]
+    #[pallet::getter(fn deferred_reward)]
+    pub(super) type DeferredReward<T> = StorageValue<_, Value, ValueQuery>;
+
+    /// Destination the author of the current block claimed its reward to with the
+    /// `claim_reward` inherent. Taken in `on_finalize`.
+    #[pallet::storage]
+    #[pallet::getter(fn reward_destination)]
+    pub(super) type RewardDestination<T: Config> =
+        StorageValue<_, Destination<T::AccountId>, OptionQuery>;
+
+    /// Claimed reward UTXOs with the block they were earned in. They are added to the UTXO set at
+    /// the end of the next block, so that they can't be spent before the block earning them is
+    /// built upon.
+    #[pallet::storage]
+    #[pallet::getter(fn pending_rewards)]
+    pub(super) type PendingRewards<T: Config> =
+        StorageValue<_, Vec<(T::BlockNumber, TransactionOutputFor<T>)>, ValueQuery>;
+
+    /// The UTXO set: outpoint -> output. The `Identity` hasher keeps the keys as they are, so the
+    /// map is iterated in ascending outpoint order on every node. Consensus code iterating it,
+    /// such as `pick_utxo` in extrinsics, relies on that order, so the hasher must not change.
     #[pallet::storage]
     #[pallet::getter(fn utxo_store)]
     pub(super) type UtxoStore<T: Config> = StorageMap<_, Identity, H256, TransactionOutputFor<T>>;
 
+    /// Number of UTXOs in the UTXO set, pruned UTXOs included.
+    #[pallet::storage]
+    #[pallet::getter(fn utxo_count)]
+    pub(super) type UtxoCount<T> = StorageValue<_, u64, ValueQuery>;
+
+    /// Total MLT held by the UTXO set, pruned UTXOs included.
+    #[pallet::storage]
+    #[pallet::getter(fn utxo_value)]
+    pub(super) type UtxoValue<T> = StorageValue<_, Value, ValueQuery>;
+
+    /// Number of UTXOs holding every token. Tokens held by no UTXO have no entry.
+    #[pallet::storage]
+    #[pallet::getter(fn token_utxo_count)]
+    pub(super) type TokenUtxoCount<T> = StorageMap<_, Identity, TokenId, u64, ValueQuery>;
+
+    /// Transactions that issued tokens and minted NFTs. Entries are never removed, so a token id
+    /// can't be issued again after its issuance UTXO is spent or its whole supply is burned.
     #[pallet::storage]
     #[pallet::getter(fn token_issuance_transactions)]
     pub(super) type TokenIssuanceTransactions<T: Config> =
@@ -491,16 +1402,95 @@ pub mod pallet {
 
     // When someone wants to issue a token we should calculate token_id and use it when the owner
     // in other transactions will transfer the token.
+    /// Token ids of the unspent token issuance UTXOs. Removed once the issuance UTXO is spent.
     #[pallet::storage]
     #[pallet::getter(fn token_id_issuance)]
     pub(super) type TokenIssuanceId<T: Config> =
         StorageMap<_, Identity, /* outpoint */ H256, TokenId, OptionQuery>;
 
-    // todo: This part isn't fully tested, left for the next PR
-    // #[pallet::storage]
-    // #[pallet::getter(fn nft_unique_data_hash)]
-    // pub(super) type NftUniqueDataHash<T: Config> =
-    //     StorageMap<_, Identity, NftDataHash, /* UTXO */ H256, OptionQuery>;
+    /// UTXO currently holding the NFT of given digital data, or the UTXO burning it. Moved along
+    /// with the NFT on every transfer and never removed, so the same data can't be minted again.
+    #[pallet::storage]
+    #[pallet::getter(fn nft_unique_data_hash)]
+    pub(super) type NftUniqueDataHash<T: Config> =
+        StorageMap<_, Identity, NftDataHash, /* UTXO */ H256, OptionQuery>;
+
+    /// Royalty specs of NFTs minted with one. Checked on every transfer of the NFT.
+    #[pallet::storage]
+    #[pallet::getter(fn nft_royalty)]
+    pub(super) type NftRoyalties<T: Config> =
+        StorageMap<_, Identity, TokenId, NftRoyalty, OptionQuery>;
+
+    /// Merkle root of the current airdrop, see the `airdrop` module.
+    #[pallet::storage]
+    #[pallet::getter(fn airdrop_root)]
+    pub(super) type AirdropRoot<T> = StorageValue<_, H256, OptionQuery>;
+
+    /// Pubkeys that have claimed their share of an airdrop: airdrop root -> pubkey.
+    #[pallet::storage]
+    pub(super) type AirdropClaims<T> = StorageDoubleMap<
+        _,
+        Identity,
+        /* root */ H256,
+        Identity,
+        /* pubkey */ H256,
+        (),
+        OptionQuery,
+    >;
+
+    /// Owner of each NFT collection, the account that minted the first NFT into it with
+    /// `mint_nft`. Only the owner can mint into the collection.
+    #[pallet::storage]
+    #[pallet::getter(fn nft_collection_owner)]
+    pub(super) type NftCollectionOwners<T: Config> =
+        StorageMap<_, Identity, /* collection */ H256, T::AccountId, OptionQuery>;
+
+    /// NFTs minted into each collection: collection -> token id.
+    #[pallet::storage]
+    pub(super) type NftCollections<T: Config> = StorageDoubleMap<
+        _,
+        Identity,
+        /* collection */ H256,
+        Identity,
+        TokenId,
+        (),
+        OptionQuery,
+    >;
+
+    /// Latest transfers of every NFT, oldest first, at most `NftProvenanceDepth` of them.
+    #[pallet::storage]
+    #[pallet::getter(fn nft_provenance)]
+    pub(super) type NftProvenance<T: Config> = StorageMap<
+        _,
+        Identity,
+        TokenId,
+        Vec<NftTransfer<T::AccountId, T::BlockNumber>>,
+        ValueQuery,
+    >;
+
+    /// Current supply of every token, i.e. the amount issued minus the amount burned.
+    #[pallet::storage]
+    #[pallet::getter(fn token_supply)]
+    pub(super) type TokenSupply<T: Config> = StorageMap<_, Identity, TokenId, Value, OptionQuery>;
+
+    /// Total amount of every token burned so far.
+    #[pallet::storage]
+    #[pallet::getter(fn total_burned)]
+    pub(super) type TotalBurned<T: Config> = StorageMap<_, Identity, TokenId, Value, ValueQuery>;
+
+    /// The first token issued with each ticker, keyed by the uppercase ticker. Another token can
+    /// only be issued with a registered ticker if `UniqueTickers` is not set.
+    #[pallet::storage]
+    #[pallet::getter(fn ticker_registry)]
+    pub(super) type TickerRegistry<T: Config> =
+        StorageMap<_, Blake2_128Concat, Vec<u8>, TokenId, OptionQuery>;
+
+    /// Block number and timestamp (in milliseconds) at which spendable UTXOs were created. Used to
+    /// check relative time locks and to weigh the transaction priority by the age of the coins.
+    #[pallet::storage]
+    #[pallet::getter(fn utxo_creation_time)]
+    pub(super) type UtxoCreationTime<T: Config> =
+        StorageMap<_, Identity, /* outpoint */ H256, (T::BlockNumber, u64), OptionQuery>;
 
     /// Represents the validators' stakes. When a validator chooses to stop validating,
     /// the utxo here is transferred back to `UtxoStore`.
@@ -515,9 +1505,218 @@ pub mod pallet {
     pub(super) type StakingCount<T: Config> =
         StorageMap<_, Identity, T::AccountId, (u64, Value), OptionQuery>;
 
+    /// Secondary controller account -> (stash account, permitted operations). Set by the
+    /// `LockForStaking` output, removed on withdrawal.
+    #[pallet::storage]
+    #[pallet::getter(fn secondary_controllers)]
+    pub(super) type SecondaryControllers<T: Config> = StorageMap<
+        _,
+        Identity,
+        T::AccountId,
+        (T::AccountId, staking::StakingPermissions),
+        OptionQuery,
+    >;
+
+    /// Unlocked stakes waiting for the bonding duration to end: stash account -> (value, era at
+    /// which the stake can be withdrawn). Removed on withdrawal.
+    #[pallet::storage]
+    #[pallet::getter(fn pending_withdrawal)]
+    pub(super) type PendingWithdrawals<T: Config> =
+        StorageMap<_, Identity, T::AccountId, (Value, EraIndex), OptionQuery>;
+
+    /// Stash accounts whose unlocked stake becomes available in each era. Taken once the era
+    /// starts, to notify the stash accounts.
+    #[pallet::storage]
+    pub(super) type MaturingWithdrawals<T: Config> =
+        StorageMap<_, Twox64Concat, EraIndex, Vec<T::AccountId>, ValueQuery>;
+
+    /// Aggregate value of the pay-to-pubkey UTXOs of every pubkey at each balance snapshot, keyed
+    /// by the block of the snapshot. Governance uses it as the voting weight of the pubkeys.
+    #[pallet::storage]
+    pub(super) type BalanceSnapshots<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        T::BlockNumber,
+        Identity,
+        /* pubkey */ H256,
+        Value,
+        ValueQuery,
+    >;
+
+    /// Blocks the balance snapshots were taken at, in ascending order. At most
+    /// `SnapshotHistoryDepth` of them are kept.
+    #[pallet::storage]
+    #[pallet::getter(fn balance_snapshot_blocks)]
+    pub(super) type BalanceSnapshotBlocks<T: Config> =
+        StorageValue<_, Vec<T::BlockNumber>, ValueQuery>;
+
+    /// Balance snapshot being taken: the block of the snapshot and the last outpoint scanned,
+    /// `None` until the first UTXO is scanned.
+    #[pallet::storage]
+    #[pallet::getter(fn pending_balance_snapshot)]
+    pub(super) type PendingBalanceSnapshot<T: Config> =
+        StorageValue<_, (T::BlockNumber, Option<H256>), OptionQuery>;
+
+    /// Blocks of the balance snapshots dropped from `BalanceSnapshotBlocks` whose balances are
+    /// still being removed from `BalanceSnapshots`.
+    #[pallet::storage]
+    pub(super) type StaleBalanceSnapshots<T: Config> =
+        StorageValue<_, Vec<T::BlockNumber>, ValueQuery>;
+
+    /// Staking pools keyed by their stash account. Removed when the pool stake is withdrawn or
+    /// the last member leaves.
+    #[pallet::storage]
+    #[pallet::getter(fn staking_pool)]
+    pub(super) type StakingPools<T: Config> =
+        StorageMap<_, Identity, T::AccountId, StakingPool<T::AccountId>, OptionQuery>;
+
+    /// Accounts watching a destination, keyed by the hash of the destination. Watchers are
+    /// notified by events about UTXOs sent to and spent from the destination.
+    #[pallet::storage]
+    pub(super) type WatchedDestinations<T: Config> = StorageDoubleMap<
+        _,
+        Identity,
+        /* destination hash */ H256,
+        Blake2_128Concat,
+        T::AccountId,
+        (),
+        OptionQuery,
+    >;
+
+    /// Number of accounts in `WatchedDestinations` watching a destination, keyed by the hash of
+    /// the destination. At most `MaxWatchers`.
+    #[pallet::storage]
+    #[pallet::getter(fn watcher_count)]
+    pub(super) type WatcherCount<T: Config> =
+        StorageMap<_, Identity, /* destination hash */ H256, u32, ValueQuery>;
+
+    /// Fungible tokens held in pay-to-pubkey UTXOs: (owner pubkey, token) -> outpoint -> amount.
+    /// Backs the `fungibles` traits implemented in pallet-utxo's `fungibles.rs`.
+    #[pallet::storage]
+    pub(super) type TokenUtxosByOwner<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        (H256, TokenId),
+        Identity,
+        /* outpoint */ H256,
+        Value,
+        OptionQuery,
+    >;
+
+    /// Balance of every holder of every token in `TokenUtxosByOwner`, the sum of its UTXOs:
+    /// token -> owner pubkey -> amount. Ordered by owner pubkey, so that explorers can page
+    /// through the holders of a token with `UtxoApi::token_holders`.
+    #[pallet::storage]
+    pub(super) type TokenHolders<T> =
+        StorageDoubleMap<_, Identity, TokenId, Identity, /* owner */ H256, Value, OptionQuery>;
+
+    /// Number of holders of every token in `TokenHolders`. Tokens held by nobody have no entry.
+    #[pallet::storage]
+    #[pallet::getter(fn token_holder_count)]
+    pub(super) type TokenHolderCount<T> = StorageMap<_, Identity, TokenId, u32, ValueQuery>;
+
+    /// UTXOs spendable by each contract account, the `CallPP` outputs paying it:
+    /// contract -> outpoint -> value. The pool enumerates them through `UtxoApi::contract_utxos`.
+    #[pallet::storage]
+    pub(super) type ContractUtxos<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Identity,
+        /* outpoint */ H256,
+        Value,
+        OptionQuery,
+    >;
+
+    /// Outpoints of the unspent outputs by the id of their transaction and their index in it:
+    /// txid -> index -> outpoint, see `Transaction::txid`. Only the outputs a transaction adds to
+    /// `UtxoStore` are listed.
+    #[pallet::storage]
+    pub(super) type OutpointsByTxid<T> = StorageDoubleMap<
+        _,
+        Identity,
+        /* txid */ H256,
+        Twox64Concat,
+        u32,
+        /* outpoint */ H256,
+        OptionQuery,
+    >;
+
+    /// Reverse of `OutpointsByTxid`: outpoint -> (txid, index).
+    #[pallet::storage]
+    pub(super) type OutputRefs<T> = StorageMap<_, Identity, H256, (H256, u32), OptionQuery>;
+
+    /// Best bid of each open auction: auction outpoint -> bid outpoint.
+    #[pallet::storage]
+    #[pallet::getter(fn best_bid)]
+    pub(super) type BestBids<T> =
+        StorageMap<_, Identity, /* auction */ H256, /* bid */ H256, OptionQuery>;
+
+    /// Auctions settled at the end of each block: block number -> auction outpoints.
+    #[pallet::storage]
+    pub(super) type AuctionsEnding<T> = StorageMap<_, Twox64Concat, u64, Vec<H256>, ValueQuery>;
+
+    /// Outpoints picked by the sending extrinsics in the current block. Later sends in the block
+    /// skip them, so that they never pick the same UTXOs. Cleared in `on_finalize`.
+    #[pallet::storage]
+    pub(super) type ReservedOutpoints<T> = StorageValue<_, Vec<H256>, ValueQuery>;
+
+    /// Outpoints spent by each signer in the current block, kept by the optional
+    /// `CheckDuplicateSpend` signed extension. Cleared in `on_finalize`.
+    #[pallet::storage]
+    pub(super) type SpentBySigner<T: Config> =
+        StorageValue<_, Vec<(T::AccountId, H256)>, ValueQuery>;
+
+    /// Journal of the outputs spent in the last `StxoRetention` blocks: outpoint -> (hash of the
+    /// spending transaction, block number of the spend). Older entries are pruned in
+    /// `on_finalize`.
+    #[pallet::storage]
+    #[pallet::getter(fn spent_output)]
+    pub(super) type SpentOutputs<T: Config> =
+        StorageMap<_, Identity, /* outpoint */ H256, (H256, T::BlockNumber), OptionQuery>;
+
+    /// Outpoints spent in each block still in the STXO journal.
+    #[pallet::storage]
+    pub(super) type SpentInBlock<T: Config> =
+        StorageMap<_, Twox64Concat, T::BlockNumber, Vec<H256>, ValueQuery>;
+
+    /// Inputs of the transactions rejected for spending outputs not in the UTXO set, by the block
+    /// they were rejected in. Kept for `StxoRetention` blocks, like the STXO journal.
+    #[pallet::storage]
+    pub(super) type MissingInputs<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        T::BlockNumber,
+        Vec<MissingInputProof<T::BlockNumber>>,
+        ValueQuery,
+    >;
+
+    /// Outputs locking tokens under bridge custody, keyed by their outpoint. Removed when a
+    /// relayer releases the tokens.
+    #[pallet::storage]
+    #[pallet::getter(fn bridge_custody)]
+    pub(super) type BridgeCustody<T: Config> =
+        StorageMap<_, Identity, H256, TransactionOutputFor<T>, OptionQuery>;
+
+    /// Commitments to the UTXOs whose bodies have been pruned from `UtxoStore`: outpoint -> hash
+    /// of the output. Spending one takes the SCALE-encoded output in the `lock` of the input.
+    #[pallet::storage]
+    #[pallet::getter(fn pruned_utxo)]
+    pub(super) type PrunedUtxos<T: Config> =
+        StorageMap<_, Identity, /* outpoint */ H256, /* output hash */ H256, OptionQuery>;
+
+    /// Total MLT held by the pruned UTXOs.
+    #[pallet::storage]
+    pub(super) type PrunedValue<T> = StorageValue<_, Value, ValueQuery>;
+
+    /// UTXOs created in each block that get pruned once they are `PruningDepth` blocks old.
+    #[pallet::storage]
+    pub(super) type CreatedInBlock<T: Config> =
+        StorageMap<_, Twox64Concat, T::BlockNumber, Vec<H256>, ValueQuery>;
+
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
-    #[pallet::metadata(T::AccountId = "AccountId")]
+    #[pallet::metadata(T::AccountId = "AccountId", T::BlockNumber = "BlockNumber")]
     pub enum Event<T: Config> {
         TransactionSuccess(TransactionFor<T>),
 
@@ -525,6 +1724,26 @@ pub mod pallet {
         /// \[utxo_for_block_author\]
         BlockAuthorRewarded(TransactionOutput<T::AccountId>),
 
+        /// No author was found for the block, its reward and fees are deferred to the next block
+        /// author found.
+        /// \[block_number, deferred_reward\]
+        RewardDeferred(T::BlockNumber, Value),
+
+        /// MLT was credited to the reward pool paid to the block author.
+        /// \[amount, source\]
+        RewardPoolCredited(Value, RewardSource),
+
+        /// The block author was paid the block reward and the fees of the block.
+        /// \[authority, amount\]
+        RewardPaid(H256, Value),
+
+        /// `Config::authorities` is empty. The block author found is rewarded all the same.
+        NoAuthorities,
+
+        /// `Config::authorities` lists the key more than once. It is only counted once.
+        /// \[authority\]
+        DuplicateAuthority(H256),
+
         /// Unstaking is enabled after the end of bonding duration, as set in pallet-staking.
         /// \[stash_account\]
         StakeUnlocked(T::AccountId),
@@ -532,12 +1751,181 @@ pub mod pallet {
         /// Unlocked stake has been withdrawn.
         /// \[total_stake, stash_account\]
         StakeWithdrawn(Value, T::AccountId),
+
+        /// The fee of withdrawing unlocked stake has been paid, split between two destinations
+        /// when a part of it had to be burned.
+        /// \[fee, destination\]
+        WithdrawalFeePaid(Value, staking::FeeDestination<T::AccountId>),
+
+        /// Unlocked stake will become available for withdrawal at the given era.
+        /// \[stash_account, value, available_at_era\]
+        WithdrawalScheduled(T::AccountId, Value, EraIndex),
+
+        /// The bonding duration of unlocked stake has ended and it can be withdrawn.
+        /// \[stash_account, value, era\]
+        WithdrawalMatured(T::AccountId, Value, EraIndex),
+
+        /// Tokens have been burned.
+        /// \[token_id, amount\]
+        TokenBurned(TokenId, Value),
+
+        /// A token has been issued by the `issue_token` extrinsic.
+        /// \[token_id, issuer, amount\]
+        TokenIssued(TokenId, T::AccountId, Value),
+
+        /// The Merkle root of the airdrop has been set or removed.
+        /// \[root\]
+        AirdropRootSet(Option<H256>),
+
+        /// A share of the airdrop has been claimed and minted as a UTXO.
+        /// \[account, value, outpoint\]
+        AirdropClaimed(T::AccountId, Value, H256),
+
+        /// An NFT has been minted by the `mint_nft` extrinsic.
+        /// \[token_id, owner, collection\]
+        NftMinted(TokenId, T::AccountId, Option<H256>),
+
+        /// The issuance fee of a new token or NFT has been charged and added to the reward.
+        /// \[token_id, fee\]
+        FeeCharged(TokenId, Value),
+
+        /// A UTXO has been sent to a destination watched by the account.
+        /// \[watcher, outpoint, value\]
+        UtxoReceived(T::AccountId, H256, Value),
+
+        /// A UTXO of a destination watched by the account has been spent.
+        /// \[watcher, outpoint\]
+        UtxoSpent(T::AccountId, H256),
+
+        /// The stake of the stash account has moved to a new stash account.
+        /// \[stash_account, new_stash_account\]
+        StakeRedelegated(T::AccountId, T::AccountId),
+
+        /// The validator of the stash account has new session keys.
+        /// \[stash_account\]
+        SessionKeysRotated(T::AccountId),
+
+        /// A share of a staking pool has been locked.
+        /// \[stash_account, member, value\]
+        StakingPoolJoined(T::AccountId, H256, Value),
+
+        /// The members of a staking pool have locked the minimum stake and the pool is bonded.
+        /// \[stash_account, total_stake\]
+        StakingPoolBonded(T::AccountId, Value),
+
+        /// A member has left a staking pool and got back the locked shares.
+        /// \[stash_account, member, value\]
+        StakingPoolLeft(T::AccountId, H256, Value),
+
+        /// The balances of all pubkeys have been recorded.
+        /// \[block_number\]
+        BalanceSnapshotTaken(T::BlockNumber),
+
+        /// A payment of `send_to_addresses` has been made.
+        /// \[destination, outpoint, value\]
+        PaymentSent(Destination<T::AccountId>, H256, Value),
+
+        /// UTXOs of an account have been merged into one by `consolidate`.
+        /// \[account, merged_utxos, outpoint\]
+        UtxosConsolidated(T::AccountId, u32, H256),
+
+        /// Tokens have been locked under bridge custody to be minted on the target chain.
+        /// \[token_id, amount, target_chain, target_address, outpoint\]
+        TokensLockedForBridge(TokenId, Value, u32, Vec<u8>, H256),
+
+        /// Tokens locked under bridge custody have been released by a relayer.
+        /// \[token_id, amount, outpoint\]
+        TokensReleasedFromBridge(TokenId, Value, H256),
+
+        /// The issuer of a token has set or removed its transfer policy.
+        /// \[token_id, policy\]
+        TransferPolicySet(TokenId, Option<TransferPolicy>),
+
+        /// Token issuance has been restricted to the allowed issuers, or opened to anyone.
+        /// \[permissioned\]
+        PermissionedIssuanceSet(bool),
+
+        /// A pubkey has been allowed to issue tokens, or disallowed.
+        /// \[pubkey, allowed\]
+        TokenIssuerSet(H256, bool),
+
+        /// Spends have been paused, or resumed if no scope is given.
+        /// \[scope\]
+        PausedSet(Option<crate::pause::PauseScope>),
+
+        /// The destination of the payments to an identity name has been set, or removed.
+        /// \[owner, name, destination\]
+        IdentityDestinationSet(T::AccountId, Vec<u8>, Option<Destination<T::AccountId>>),
+
+        /// MLT has been moved from the balance of an account to a UTXO.
+        /// \[account, value, outpoint\]
+        Wrapped(T::AccountId, Value, H256),
+
+        /// MLT has been moved from UTXOs to the balance of an account.
+        /// \[account, value\]
+        Unwrapped(T::AccountId, Value),
+
+        /// An NFT has been escrowed in an auction settled at the end block.
+        /// \[auction, end\]
+        AuctionOpened(H256, u64),
+
+        /// A bid has become the best bid of an auction.
+        /// \[auction, bid, value\]
+        BidPlaced(H256, H256, Value),
+
+        /// An auction has been settled, the NFT going to the winning bid if any.
+        /// \[auction, winning_bid\]
+        AuctionSettled(H256, Option<H256>),
+
+        /// A transaction in the block spends an output already spent by another transaction.
+        /// \[outpoint, tx_hash, spent_by\]
+        DoubleSpendDetected(H256, H256, H256),
+
+        /// A transaction in the block spends an output that is not in the UTXO set, and whose
+        /// spend is not in the STXO journal.
+        /// \[outpoint, tx_hash\]
+        MissingInput(H256, H256),
     }
 
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(block_num: T::BlockNumber) -> Weight {
+            let snapshot_period = T::SnapshotPeriod::get();
+            if !snapshot_period.is_zero() && (block_num % snapshot_period).is_zero() {
+                start_balance_snapshot::<T>(block_num);
+            }
+            let snapshot_weight = continue_balance_snapshot::<T>();
+
+            // the rewards released in `on_finalize` and the reward of the block author
+            let rewards = <PendingRewards<T>>::decode_len().unwrap_or(0) as u32 + 1;
+            T::WeightInfo::on_finalize(rewards).saturating_add(snapshot_weight)
+        }
+
+        fn on_runtime_upgrade() -> Weight {
+            crate::migrations::migrate::<T>()
+        }
+
         fn on_finalize(block_num: T::BlockNumber) {
             reward_block_author::<T>(block_num);
+            prune_spent_outputs::<T>(block_num);
+            #[cfg(feature = "pruning")]
+            prune_utxo_bodies::<T>(block_num);
+            staking::notify_matured_withdrawals::<T>();
+            auction::settle_auctions::<T>(block_num);
+            <ReservedOutpoints<T>>::kill();
+            <SpentBySigner<T>>::kill();
+        }
+
+        fn integrity_test() {
+            assert!(
+                Self::token_metadata_rules().is_within_bounds(),
+                "token metadata rules exceed their bounds"
+            );
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade() -> Result<(), &'static str> {
+            try_state::<T>()
         }
     }
 
@@ -599,112 +1987,464 @@ pub mod pallet {
         // }
     }
 
-    pub fn validate_transaction<T: Config>(
-        tx: &TransactionFor<T>,
-    ) -> Result<ValidTransaction, &'static str> {
-        //ensure rather than assert to avoid panic
-        //both inputs and outputs should contain at least 1 and at most u32::MAX - 1 entries
-        ensure!(!tx.inputs.is_empty(), "no inputs");
-        ensure!(!tx.outputs.is_empty(), "no outputs");
-        ensure!(tx.inputs.len() < (u32::MAX as usize), "too many inputs");
-        ensure!(tx.outputs.len() < (u32::MAX as usize), "too many outputs");
+    /// Key of the caller in the default signature scheme, to sign transactions on their behalf.
+    /// Fails if the keys of the scheme are not account ids.
+    fn signing_key<T: Config>(pubkey_raw: [u8; 32]) -> Result<sign::Public, DispatchError> {
+        T::DefaultSignatureScheme::get()
+            .public(pubkey_raw)
+            .ok_or(DispatchError::Other("Failed to sign the transaction"))
+    }
 
-        //ensure each input is used only a single time
-        //maps each input into btree
-        //if map.len() > num of inputs then fail
-        //https://doc.rust-lang.org/std/collections/struct.BTreeMap.html
-        //WARNING workshop code has a bug here
-        //https://github.com/substrate-developer-hub/utxo-workshop/blob/workshop/runtime/src/utxo.rs
-        //input_map.len() > transaction.inputs.len() //THIS IS WRONG
-        {
-            let input_map: BTreeMap<_, ()> =
-                tx.inputs.iter().map(|input| (input.outpoint, ())).collect();
-            //we want map size and input size to be equal to ensure each is used only once
-            ensure!(
-                input_map.len() == tx.inputs.len(),
-                "each input should be used only once"
-            );
-        }
-        //ensure each output is unique
-        //map each output to btree to count unique elements
-        //WARNING example code has a bug here
-        //out_map.len() != transaction.outputs.len() //THIS IS WRONG
-        {
-            let out_map: BTreeMap<_, ()> = tx.outputs.iter().map(|output| (output, ())).collect();
-            //check each output is defined only once
-            ensure!(
-                out_map.len() == tx.outputs.len(),
-                "each output should be used once"
-            );
+    /// Chain the signatures are checked against in the current block.
+    pub fn chain_binding<T: Config>() -> sign::ChainBinding {
+        sign::ChainBinding {
+            chain_id: T::ChainId::get(),
+            accept_unbound: <frame_system::Pallet<T>>::block_number()
+                < T::UnboundSignaturesUntil::get(),
         }
+    }
 
-        // Verify absolute time lock
+    /// Do the witnesses checked against `rules` have to be canonical in the current block?
+    fn canonical_witnesses<T: Config>(rules: ScriptRules) -> bool {
+        rules == ScriptRules::Policy
+            || <frame_system::Pallet<T>>::block_number() >= T::NonCanonicalWitnessesUntil::get()
+    }
+
+    /// Parse the witness of the input at `index` as a signature by `pubkey`, along with the
+    /// message it signs on the `chain`.
+    fn signature_and_message<AccountId: Encode>(
+        pubkey: &sr25519::Public,
+        tx: &Transaction<AccountId>,
+        utxos: &[TransactionOutput<AccountId>],
+        index: usize,
+        chain: sign::ChainBinding,
+    ) -> Result<(sign::SignatureData, sign::TransactionSigMsg), &'static str> {
+        let sig_data = sign::Public::from(*pubkey)
+            .parse_witness(&tx.inputs[index].witness[..])
+            .ok_or("bad signature format")?;
         ensure!(
-            tx.check_time_lock::<T>(),
-            "Time lock restrictions not satisfied"
+            chain.accepts(sig_data.sighash()),
+            "signature must commit to the chain"
         );
-        // In order to avoid race condition in network we maintain a list of required utxos for a tx
-        // Example of race condition:
-        // Assume both alice and bob have 10 coins each and bob owes charlie 20 coins
-        // In order to pay charlie alice must first send 10 coins to bob which creates a new utxo
-        // If bob uses the new utxo to try and send the coins to charlie before charlie receives the alice to bob 10 coins utxo
-        // then the tx from bob to charlie is invalid. By maintaining a list of required utxos we can ensure the tx can happen as and
-        // when the utxo is available. We use max longevity at the moment. That should be fixed.
+        let msg = sign::TransactionSigMsg::construct(
+            sig_data.sighash(),
+            tx,
+            utxos,
+            index as u64,
+            u32::MAX,
+        );
+        Ok((sig_data, msg.for_chain(chain.chain_id)))
+    }
 
-        // Resolve the transaction inputs by looking up UTXOs being spent by them.
-        //
-        // This will cointain one of the following:
-        // * Ok(utxos): a vector of UTXOs each input spends.
-        // * Err(missing): a vector of outputs missing from the store
-        let input_utxos = {
-            let mut missing = Vec::new();
-            let mut resolved: Vec<TransactionOutputFor<T>> = Vec::new();
+    /// Check the witness of the input at `index` is a valid signature by `pubkey`.
+    fn check_signature<AccountId: Encode>(
+        pubkey: &sr25519::Public,
+        tx: &Transaction<AccountId>,
+        utxos: &[TransactionOutput<AccountId>],
+        index: usize,
+        chain: sign::ChainBinding,
+    ) -> Result<(), &'static str> {
+        let (sig_data, msg) = signature_and_message(pubkey, tx, utxos, index, chain)?;
+        ensure!(sig_data.verify(&msg), "signature must be valid");
+        Ok(())
+    }
+
+    /// Check the witnesses of the inputs given by their index are valid signatures by the
+    /// paired pubkeys. Several signatures are verified in one batch. If the batch fails, they
+    /// are checked one by one to tell which input is invalid.
+    fn check_signatures<AccountId: Encode>(
+        signers: &[(usize, &sr25519::Public)],
+        tx: &Transaction<AccountId>,
+        utxos: &[TransactionOutput<AccountId>],
+        chain: sign::ChainBinding,
+    ) -> Result<(), &'static str> {
+        if signers.len() > 1
+            && sign::batch(|| {
+                signers.iter().all(|(index, pubkey)| {
+                    signature_and_message(pubkey, tx, utxos, *index, chain)
+                        .map_or(false, |(sig_data, msg)| sig_data.batch_verify(&msg))
+                })
+            })
+        {
+            return Ok(());
+        }
+        for (index, pubkey) in signers {
+            check_signature(pubkey, tx, utxos, *index, chain)?;
+        }
+        Ok(())
+    }
+
+    /// Time passed since the UTXO at `outpoint` was created, if it is known.
+    fn input_age<T: Config>(outpoint: &H256) -> Option<InputAge> {
+        let (block_number, millis) = <UtxoCreationTime<T>>::get(outpoint)?;
+        let blocks = <frame_system::Pallet<T>>::block_number().saturating_sub(block_number);
+        let now = <pallet_timestamp::Pallet<T> as UnixTime>::now();
+        Some(InputAge {
+            blocks: blocks.unique_saturated_into(),
+            time: now.saturating_sub(Duration::from_millis(millis)),
+        })
+    }
+
+    /// Transaction pool tag of an outpoint. Provided by the transaction creating the UTXO and
+    /// required by the transactions spending it, so that a child is ordered after its parent.
+    pub fn outpoint_tag(outpoint: &H256) -> TransactionTag {
+        outpoint.as_fixed_bytes().to_vec()
+    }
+
+    pub fn validate_transaction<T: Config>(
+        tx: &TransactionFor<T>,
+    ) -> Result<ValidTransaction, &'static str> {
+        check_transaction::<T>(tx, ScriptRules::Consensus, TransactionSource::InBlock)
+            .map(|(valid, ..)| valid)
+    }
+
+    /// Validate a transaction entering the transaction pool from `source`.
+    ///
+    /// On top of the consensus rules, the transaction must not make use of the script features
+    /// reserved for future soft forks. Transactions received from the network must also be
+    /// standard (see [check_standard]) and pay the minimum fee set by `T::PriorityPolicy`, these
+    /// checks are skipped for transactions submitted locally. Transactions coming back from a
+    /// retracted block are only checked against the consensus rules, they were valid in a block.
+    pub fn validate_pool_transaction<T: Config>(
+        tx: &TransactionFor<T>,
+        source: TransactionSource,
+    ) -> Result<ValidTransaction, &'static str> {
+        let rules = match source {
+            TransactionSource::InBlock => ScriptRules::Consensus,
+            TransactionSource::Local | TransactionSource::External => ScriptRules::Policy,
+        };
+        check_transaction::<T>(tx, rules, source).map(|(valid, ..)| valid)
+    }
+
+    /// Check the standardness rules of the pool, keeping junk out of it. Blocks are not held to
+    /// them, so they can be relaxed without a fork:
+    /// * the witness of each input is at most `T::MaxStandardWitnessSize` bytes,
+    /// * the witness scripts of script hash inputs only push data, with minimal pushes. Their
+    ///   scripts would fail otherwise, this rejects them before any script or signature is
+    ///   verified,
+    /// * outputs without tokens carry at least `T::DustThreshold`, unless they carry no value
+    ///   at all by design, like data carriers and auctions.
+    pub fn check_standard<T: Config>(
+        tx: &TransactionFor<T>,
+        utxos: &[TransactionOutputFor<T>],
+    ) -> Result<(), &'static str> {
+        let max_witness = T::MaxStandardWitnessSize::get() as usize;
+        for (input, utxo) in tx.inputs.iter().zip(utxos) {
+            ensure!(input.witness.len() <= max_witness, "witness too large");
+            if let Destination::ScriptHash(..) = utxo.destination {
+                ensure!(
+                    crate::script::is_push_only(&input.witness),
+                    "witness script not push only"
+                );
+            }
+        }
+        let dust = T::DustThreshold::get();
+        for output in &tx.outputs {
+            let spendable = matches!(
+                output.destination,
+                Destination::Pubkey(_)
+                    | Destination::ScriptHash(..)
+                    | Destination::Taproot { .. }
+                    | Destination::Vesting { .. }
+                    | Destination::Stream { .. }
+                    | Destination::Inheritance { .. }
+                    | Destination::Order { .. }
+            );
+            ensure!(
+                !spendable || output.data.is_some() || output.value >= dust,
+                "dust output"
+            );
+        }
+        Ok(())
+    }
+
+    /// What the witness of an input is checked against.
+    enum WitnessCheck<'a> {
+        /// A signature by the key
+        Signature(&'a sr25519::Public),
+        Script(ScriptCheck),
+        /// The input is spent without a signature or a script
+        Nothing,
+    }
+
+    /// Work out how the witness of the input at `index` spending `utxo` is checked. Fail if the
+    /// UTXO can't be spent or the witness is malformed.
+    fn witness_check<'a, T: Config>(
+        input: &TransactionInput,
+        utxo: &'a TransactionOutputFor<T>,
+        index: usize,
+        rules: ScriptRules,
+    ) -> Result<WitnessCheck<'a>, &'static str> {
+        match &utxo.destination {
+            Destination::Pubkey(pubkey)
+            | Destination::Vesting { pubkey, .. }
+            | Destination::Stream { payee: pubkey, .. } => Ok(WitnessCheck::Signature(pubkey)),
+            Destination::Taproot { internal_key, .. } if input.lock.is_empty() => {
+                Ok(WitnessCheck::Signature(internal_key))
+            }
+            // Filled orders are checked against the outputs instead
+            Destination::Order { .. } if input.witness.is_empty() => Ok(WitnessCheck::Nothing),
+            Destination::Order { maker, .. } => Ok(WitnessCheck::Signature(maker)),
+            Destination::Inheritance { owner, .. } if input.lock.is_empty() => {
+                Ok(WitnessCheck::Signature(owner))
+            }
+            Destination::Inheritance { heir, delay, .. } => {
+                ensure!(
+                    input.lock.as_slice() == &heir.0[..],
+                    "inheritance lock must be the heir pubkey"
+                );
+                let age = input_age::<T>(&input.outpoint).map_or(0, |age| age.blocks);
+                ensure!(u64::from(age) >= *delay, "heir can't spend the utxo yet");
+                Ok(WitnessCheck::Signature(heir))
+            }
+            Destination::Taproot { merkle_root, .. } => {
+                let path = taproot::ScriptPathWitness::decode(&mut &input.witness[..])
+                    .map_err(|_| "bad taproot witness format")?;
+                let leaf = taproot::leaf_hash(&input.lock);
+                ensure!(
+                    taproot::root_from_proof(leaf, &path.proof) == *merkle_root,
+                    "taproot proof must be valid"
+                );
+                let age = input_age::<T>(&input.outpoint);
+                let lock = input.lock.clone();
+                Ok(WitnessCheck::Script(ScriptCheck::new(
+                    index as u64,
+                    age,
+                    path.witness,
+                    lock,
+                )))
+            }
+            Destination::CreatePP(_, _) => {
+                log::info!("TODO validate spending of OP_CREATE");
+                Ok(WitnessCheck::Nothing)
+            }
+            Destination::CallPP(..) => {
+                let spend = input
+                    .witness
+                    .get(1..)
+                    .and_then(|opcode| opcode.try_into().ok())
+                    .map(u16::from_le_bytes)
+                    .ok_or("Failed to convert witness to an opcode")?;
+                ensure!(spend == 0x1337, "OP_SPEND not found");
+                Ok(WitnessCheck::Nothing)
+            }
+            Destination::ScriptHash(version, _hash) => {
+                // Outputs reserved for future soft forks are anyone-can-spend, keep them out of
+                // the pool so they are not spent before the fork
+                ensure!(
+                    version.is_known() || rules == ScriptRules::Consensus,
+                    "unknown script version"
+                );
+                // Scripts of an unknown version always succeed
+                if !version.is_known() {
+                    return Ok(WitnessCheck::Nothing);
+                }
+                let age = input_age::<T>(&input.outpoint);
+                let (witness, lock) = (input.witness.clone(), input.lock.clone());
+                Ok(WitnessCheck::Script(ScriptCheck::new(
+                    index as u64,
+                    age,
+                    witness,
+                    lock,
+                )))
+            }
+            Destination::LockForStaking { .. }
+            | Destination::LockExtraForStaking { .. }
+            | Destination::CreateStakingPool { .. }
+            | Destination::JoinStakingPool { .. } => Err("cannot spend a staking utxo."),
+            Destination::DataCarrier(_) => Err("cannot spend a data carrier utxo."),
+            Destination::Auction { .. } | Destination::Bid { .. } => {
+                Err("cannot spend an auction utxo.")
+            }
+        }
+    }
+
+    /// Check the lock and the witness of the input at `index` on their own, against the UTXOs
+    /// `utxos` spent by the transaction. Used to tell wallets which input of an invalid
+    /// transaction is at fault.
+    pub(crate) fn check_input<T: Config>(
+        tx: &TransactionFor<T>,
+        utxos: &[TransactionOutputFor<T>],
+        index: usize,
+        rules: ScriptRules,
+    ) -> Result<(), &'static str> {
+        let (input, utxo) = (&tx.inputs[index], &utxos[index]);
+        let pruned = <PrunedUtxos<T>>::contains_key(&input.outpoint);
+        if let Some(lock_commitment) = utxo.destination.lock_commitment().filter(|_| !pruned) {
+            ensure!(
+                input.lock_hash() == *lock_commitment,
+                "Lock hash does not match"
+            );
+        }
+        if canonical_witnesses::<T>(rules) {
+            verifier::check_witness_canonicality(input, utxo)?;
+        }
+        let chain = chain_binding::<T>();
+        match witness_check::<T>(input, utxo, index, rules)? {
+            WitnessCheck::Signature(pubkey) => check_signature(pubkey, tx, utxos, index, chain),
+            WitnessCheck::Script(check) => {
+                ensure!(
+                    verifier::verify_all(tx, utxos, rules, chain, vec![check]),
+                    "script verification failed"
+                );
+                Ok(())
+            }
+            WitnessCheck::Nothing => Ok(()),
+        }
+    }
+
+    /// Fees paid by a transaction
+    #[derive(Default)]
+    pub struct TransactionFees {
+        /// MLT fee
+        pub mlt: Value,
+        /// Fees paid in the accepted fee assets
+        pub tokens: Vec<(TokenId, Value)>,
+    }
+
+    /// Check a transaction, returning its validity, the data its priority is calculated from and
+    /// the fees it pays. The standardness and minimum fee policies only apply to transactions
+    /// from an `External` source.
+    fn check_transaction<T: Config>(
+        tx: &TransactionFor<T>,
+        rules: ScriptRules,
+        source: TransactionSource,
+    ) -> Result<(ValidTransaction, PriorityInfo, TransactionFees), &'static str> {
+        let relayed = source == TransactionSource::External;
+        //ensure rather than assert to avoid panic
+        //both inputs and outputs should contain at least 1 and at most u32::MAX - 1 entries
+        ensure!(!tx.inputs.is_empty(), "no inputs");
+        ensure!(!tx.outputs.is_empty(), "no outputs");
+        ensure!(tx.inputs.len() < (u32::MAX as usize), "too many inputs");
+        ensure!(tx.outputs.len() < (u32::MAX as usize), "too many outputs");
+        crate::pause::check_not_paused::<T>(tx)?;
+        let normal = T::BlockWeights::get().get(DispatchClass::Normal).max_total;
+        let max_weight = T::MaxTransactionWeightShare::get()
+            * normal.unwrap_or_else(|| T::BlockWeights::get().max_block);
+        ensure!(
+            tx.estimated_weight::<T>() <= max_weight,
+            "transaction exceeds its share of the block weight"
+        );
+
+        //ensure each input is used only a single time
+        //maps each input into btree
+        //if map.len() > num of inputs then fail
+        //https://doc.rust-lang.org/std/collections/struct.BTreeMap.html
+        //WARNING workshop code has a bug here
+        //https://github.com/substrate-developer-hub/utxo-workshop/blob/workshop/runtime/src/utxo.rs
+        //input_map.len() > transaction.inputs.len() //THIS IS WRONG
+        {
+            let input_map: BTreeMap<_, ()> =
+                tx.inputs.iter().map(|input| (input.outpoint, ())).collect();
+            //we want map size and input size to be equal to ensure each is used only once
+            ensure!(
+                input_map.len() == tx.inputs.len(),
+                "each input should be used only once"
+            );
+        }
+        //ensure each output is unique
+        //map each output to btree to count unique elements
+        //WARNING example code has a bug here
+        //out_map.len() != transaction.outputs.len() //THIS IS WRONG
+        {
+            let out_map: BTreeMap<_, ()> = tx.outputs.iter().map(|output| (output, ())).collect();
+            //check each output is defined only once
+            ensure!(
+                out_map.len() == tx.outputs.len(),
+                "each output should be used once"
+            );
+        }
+
+        // Verify absolute time lock
+        ensure!(
+            tx.check_time_lock::<T>(),
+            "Time lock restrictions not satisfied"
+        );
+        ensure!(tx.check_expiry::<T>(), "transaction expired");
+        // In order to avoid race condition in network we maintain a list of required utxos for a tx
+        // Example of race condition:
+        // Assume both alice and bob have 10 coins each and bob owes charlie 20 coins
+        // In order to pay charlie alice must first send 10 coins to bob which creates a new utxo
+        // If bob uses the new utxo to try and send the coins to charlie before charlie receives the alice to bob 10 coins utxo
+        // then the tx from bob to charlie is invalid. By maintaining a list of required utxos we can ensure the tx can happen as and
+        // when the utxo is available. We use max longevity at the moment. That should be fixed.
+
+        // Resolve the transaction inputs by looking up UTXOs being spent by them.
+        //
+        // This will cointain one of the following:
+        // * Ok(utxos): a vector of UTXOs each input spends.
+        // * Err(missing): a vector of outputs missing from the store
+        let input_utxos = {
+            let mut missing = Vec::new();
+            let mut resolved: Vec<TransactionOutputFor<T>> = Vec::new();
 
             for input in &tx.inputs {
-                if let Some(input_utxo) = <UtxoStore<T>>::get(&input.outpoint) {
-                    let lock_commitment = input_utxo.destination.lock_commitment();
-                    ensure!(
-                        input.lock_hash() == *lock_commitment,
-                        "Lock hash does not match"
-                    );
+                if let Some(input_utxo) = spent_utxo::<T>(input) {
+                    // The lock of a pruned UTXO carries its body, checked by `spent_utxo`
+                    let pruned = <PrunedUtxos<T>>::contains_key(&input.outpoint);
+                    if let Some(lock_commitment) =
+                        input_utxo.destination.lock_commitment().filter(|_| !pruned)
+                    {
+                        ensure!(
+                            input.lock_hash() == *lock_commitment,
+                            "Lock hash does not match"
+                        );
+                    }
                     resolved.push(input_utxo);
                 } else {
-                    missing.push(input.outpoint.clone().as_fixed_bytes().to_vec());
+                    ensure!(
+                        !<PrunedUtxos<T>>::contains_key(&input.outpoint),
+                        "pruned output must be supplied in the input lock"
+                    );
+                    missing.push(outpoint_tag(&input.outpoint));
                 }
             }
 
             missing.is_empty().then(|| resolved).ok_or(missing)
         };
+        // Inputs created by transactions still in the pool are resolved once those are
+        // included, the amounts are only checked when all the inputs are known
+        let known_inputs: Vec<(&TransactionInput, TransactionOutputFor<T>)> = match &input_utxos {
+            Ok(utxos) => tx.inputs.iter().zip(utxos.iter().cloned()).collect(),
+            Err(_) => tx
+                .inputs
+                .iter()
+                .filter_map(|input| Some((input, spent_utxo::<T>(input)?)))
+                .collect(),
+        };
 
-        let full_inputs: Vec<(TokenId, TransactionOutputFor<T>)> = tx
-            .inputs
+        let full_inputs: Vec<(TokenId, TransactionOutputFor<T>)> = known_inputs
             .iter()
-            .filter_map(|input| Some((input.outpoint, <UtxoStore<T>>::get(&input.outpoint)?)))
-            .filter_map(|(outpoint, output)| match output.data {
-                Some(ref data) => match data {
-                    OutputData::TokenTransferV1 { token_id, .. } => {
-                        Some((token_id.clone(), output))
-                    }
-                    OutputData::TokenIssuanceV1 { .. } => {
-                        let token_id = <TokenIssuanceId<T>>::get(outpoint)?;
-                        Some((token_id, output))
-                    }
-                    // todo: This part isn't fully tested, left for the next PR
-                    // | OutputData::NftMintV1 { token_id, .. }
-                    // OutputData::TokenBurnV1 { .. } => None,
-                },
-                None => {
+            .map(|(input, output)| (input.outpoint, output.clone()))
+            .flat_map(|(outpoint, output)| {
+                let token_ids: Vec<TokenId> = match output.data {
+                    Some(ref data) => match data {
+                        OutputData::TokenTransferV1 { token_id, .. } => vec![token_id.clone()],
+                        OutputData::TokenIssuanceV1 { .. } => {
+                            <TokenIssuanceId<T>>::get(outpoint).into_iter().collect()
+                        }
+                        OutputData::NftMintV1 { token_id, .. } => vec![token_id.clone()],
+                        // Burned tokens no longer exist
+                        OutputData::TokenBurnV1 { .. } => Vec::new(),
+                        OutputData::TokenMultiV1(tokens) => {
+                            tokens.iter().map(|(token_id, _)| token_id.clone()).collect()
+                        }
+                        // Locked tokens are in the bridge custody, not in the UTXO set
+                        OutputData::TokenLockForBridgeV1 { .. } => Vec::new(),
+                    },
                     // We do not calculate MLT here
-                    None
-                }
+                    None => Vec::new(),
+                };
+                token_ids.into_iter().map(move |token_id| (token_id, output.clone()))
             })
             .collect();
 
         //
         let mut total_value_of_input_tokens: BTreeMap<TokenId, Value> = BTreeMap::new();
+        // Non-transferable NFTs spent by this transaction
+        let mut soul_bound_tokens: Vec<TokenId> = Vec::new();
         let mut mlt_amount_in_inputs: Value = 0;
-        for input in &tx.inputs {
-            let output = <UtxoStore<T>>::get(&input.outpoint).ok_or("missing inputs")?;
+        for (input, output) in &known_inputs {
             match &output.data {
                 Some(OutputData::TokenIssuanceV1 {
                     token_ticker,
@@ -715,19 +2455,13 @@ pub mod pallet {
                     // We have to check is this token already issued?
                     let token_id = TokenIssuanceId::<T>::get(input.outpoint)
                         .ok_or("token has never been issued")?;
-                    ensure!(
-                        token_ticker.is_ascii(),
-                        "token ticker has none ascii characters"
-                    );
-                    ensure!(
-                        metadata_uri.is_ascii(),
-                        "metadata uri has none ascii characters"
-                    );
-                    ensure!(token_ticker.len() <= 5, "token ticker is too long");
-                    ensure!(!token_ticker.is_empty(), "token ticker can't be empty");
-                    ensure!(metadata_uri.len() <= 100, "token metadata uri is too long");
+                    // The rules may have been tightened since the issuance
+                    TokenMetadataRules::BOUNDS.validate(
+                        token_ticker,
+                        *number_of_decimals,
+                        metadata_uri,
+                    )?;
                     ensure!(amount_to_issue > &0u128, "output value must be nonzero");
-                    ensure!(number_of_decimals <= &18, "too long decimals");
                     // If token has just created we can't meet another amount here.
                     ensure!(
                         !total_value_of_input_tokens.contains_key(&token_id),
@@ -761,33 +2495,71 @@ pub mod pallet {
                         .checked_add(output.value)
                         .ok_or("input value overflow")?;
                 }
-
-                // todo: This part isn't fully tested, left for the next PR
-                // Some(OutputData::TokenBurnV1 { .. }) => {
-                //     // Nothing to do here because tokens no longer exist.
-                // }
-                // Some(OutputData::NftMintV1 {
-                //     ref token_id,
-                //     data_hash,
-                //     metadata_uri,
-                // }) => {
-                //     // We have to check is this token already issued?
-                //     ensure!(
-                //         TokenIssuanceTransactions::<T>::contains_key(token_id),
-                //         "unable to use an input where NFT has not minted yet"
-                //     );
-                //     // Check is this digital data unique?
-                //     ensure!(
-                //         NftUniqueDataHash::<T>::contains_key(data_hash),
-                //         "unable to use an input where NFT digital data was changed"
-                //     );
-                //     ensure!(
-                //         metadata_uri.is_ascii(),
-                //         "metadata uri has none ascii characters"
-                //     );
-                //     // If NFT has just created we can't meet another NFT part here.
-                //     total_value_of_input_tokens.insert(token_id.clone(), 1);
-                // }
+                Some(OutputData::TokenBurnV1 { .. }) => {
+                    // Nothing to do with tokens here because they no longer exist,
+                    // but probably in this input we have a fee
+                    mlt_amount_in_inputs = mlt_amount_in_inputs
+                        .checked_add(output.value)
+                        .ok_or("input value overflow")?;
+                }
+                Some(OutputData::NftMintV1 {
+                    ref token_id,
+                    data_hash,
+                    metadata_uri,
+                    transferable,
+                    ..
+                }) => {
+                    // We have to check is this token already issued?
+                    ensure!(
+                        TokenIssuanceTransactions::<T>::contains_key(token_id),
+                        "unable to use an input where NFT has not minted yet"
+                    );
+                    // Check is this digital data unique?
+                    ensure!(
+                        NftUniqueDataHash::<T>::contains_key(data_hash),
+                        "unable to use an input where NFT digital data was changed"
+                    );
+                    ensure!(
+                        metadata_uri.is_ascii(),
+                        "metadata uri has none ascii characters"
+                    );
+                    // If NFT has just created we can't meet another NFT part here.
+                    ensure!(
+                        !total_value_of_input_tokens.contains_key(token_id),
+                        "this id can't be used for a token"
+                    );
+                    total_value_of_input_tokens.insert(token_id.clone(), 1);
+                    if !transferable {
+                        soul_bound_tokens.push(token_id.clone());
+                    }
+                    // But probably in this input we have a fee
+                    mlt_amount_in_inputs = mlt_amount_in_inputs
+                        .checked_add(output.value)
+                        .ok_or("input value overflow")?;
+                }
+                Some(OutputData::TokenMultiV1(tokens)) => {
+                    for (token_id, amount) in tokens {
+                        ensure!(
+                            TokenIssuanceTransactions::<T>::contains_key(token_id),
+                            "token has never been issued"
+                        );
+                        total_value_of_input_tokens.insert(
+                            token_id.clone(),
+                            total_value_of_input_tokens
+                                .get(token_id)
+                                .unwrap_or(&0)
+                                .checked_add(*amount)
+                                .ok_or("input value overflow")?,
+                        );
+                    }
+                    // But probably in this input we have a fee
+                    mlt_amount_in_inputs = mlt_amount_in_inputs
+                        .checked_add(output.value)
+                        .ok_or("input value overflow")?;
+                }
+                Some(OutputData::TokenLockForBridgeV1 { .. }) => {
+                    frame_support::fail!("tokens locked for the bridge can't be spent")
+                }
                 None => {
                     mlt_amount_in_inputs = mlt_amount_in_inputs
                         .checked_add(output.value)
@@ -813,19 +2585,12 @@ pub mod pallet {
                         !TokenIssuanceTransactions::<T>::contains_key(&token_id),
                         "token has already been issued"
                     );
-                    ensure!(
-                        token_ticker.is_ascii(),
-                        "token ticker has none ascii characters"
-                    );
-                    ensure!(
-                        metadata_uri.is_ascii(),
-                        "metadata uri has none ascii characters"
-                    );
-                    ensure!(token_ticker.len() <= 5, "token ticker is too long");
-                    ensure!(!token_ticker.is_empty(), "token ticker can't be empty");
-                    ensure!(metadata_uri.len() <= 100, "token metadata uri is too long");
+                    Pallet::<T>::validate_token_metadata(
+                        token_ticker,
+                        *number_of_decimals,
+                        metadata_uri,
+                    )?;
                     ensure!(amount_to_issue > &0u128, "output value must be nonzero");
-                    ensure!(number_of_decimals <= &18, "too long decimals");
 
                     // If token has just created we can't meet another amount here.
                     ensure!(
@@ -860,101 +2625,285 @@ pub mod pallet {
                         .checked_add(output.value)
                         .ok_or("input value overflow")?;
                 }
-                // todo: This part isn't fully tested, left for the next PR
-                // Some(OutputData::TokenBurnV1 { .. }) => {
-                //     // Nothing to do here because tokens no longer exist.
-                // }
-                // Some(OutputData::NftMintV1 {
-                //     ref token_id,
-                //     data_hash,
-                //     metadata_uri,
-                // }) => {
-                //     // We have to check is this token already issued?
-                //     ensure!(
-                //         !TokenIssuanceTransactions::<T>::contains_key(token_id),
-                //         "token has already been issued"
-                //     );
-                //
-                //     // Check is this digital data unique?
-                //     ensure!(
-                //         !<NftUniqueDataHash<T>>::contains_key(data_hash),
-                //         "digital data has already been minted"
-                //     );
-                //     ensure!(
-                //         metadata_uri.is_ascii(),
-                //         "metadata uri has none ascii characters"
-                //     );
-                //     // If NFT has just created we can't meet another NFT part here.
-                //     total_value_of_output_tokens.insert(token_id.clone(), 1);
-                // }
-                None => {
+                Some(OutputData::TokenBurnV1 {
+                    ref token_id,
+                    amount_to_burn,
+                }) => {
+                    ensure!(
+                        TokenIssuanceTransactions::<T>::contains_key(token_id),
+                        "token has never been issued"
+                    );
+                    // Burned tokens have to be covered by inputs just like transferred ones
+                    total_value_of_output_tokens.insert(
+                        token_id.clone(),
+                        total_value_of_output_tokens
+                            .get(token_id)
+                            .unwrap_or(&0)
+                            .checked_add(*amount_to_burn)
+                            .ok_or("output value overflow")?,
+                    );
                     mlt_amount_in_outputs = mlt_amount_in_outputs
                         .checked_add(output.value)
                         .ok_or("output value overflow")?;
                 }
-            }
-        }
-
-        // Check for token creation
-        for output in tx.outputs.iter() {
-            let tid = match output.data {
-                Some(OutputData::TokenTransferV1 { ref token_id, .. }) => token_id.clone(),
-                Some(OutputData::TokenIssuanceV1 { .. }) => TokenId::new(&tx.inputs[0]),
-                None => continue,
-                // todo: This part isn't fully tested, left for the next PR
-                // Some(OutputData::NftMintV1 { .. })
-                // | Some(OutputData::TokenBurnV1 { .. })
-                // | None => continue,
-            };
-            // If we have input and output for the same token it's not a problem
-            if full_inputs.iter().find(|&x| (x.0 == tid) && (x.1 != *output)).is_some() {
-                continue;
-            } else {
-                // But when we don't have an input for token but token id exist
-                ensure!(
-                    !<TokenIssuanceTransactions<T>>::contains_key(tid),
-                    "no inputs for the token id"
-                );
-            }
-        }
-
-        let mut new_utxos = Vec::new();
-        let mut reward = 0;
-
-        // Check that outputs are valid
-        for (output_index, output) in tx.outputs.iter().enumerate() {
-            match output.data {
-                Some(OutputData::TokenIssuanceV1 {
-                    amount_to_issue, ..
-                }) => ensure!(amount_to_issue > 0, "output value must be nonzero"),
-                Some(OutputData::TokenTransferV1 { amount, .. }) => {
-                    ensure!(amount > 0, "output value must be nonzero")
-                }
-                None => ensure!(output.value > 0, "output value must be nonzero"),
-                // todo: This part isn't fully tested, left for the next PR
-                // Some(OutputData::TokenBurnV1 { amount_to_burn, .. }) => {
-                //     ensure!(amount_to_burn > 0, "output value must be nonzero")
-                // }
-                // Some(OutputData::NftMintV1 { .. }) => {
-                //     // Nothing to check
-                // }
-            }
-            let hash = tx.outpoint(output_index as u64);
-            new_utxos.push(hash.as_fixed_bytes().to_vec());
+                Some(OutputData::NftMintV1 {
+                    ref token_id,
+                    data_hash,
+                    metadata_uri,
+                    media_type,
+                    royalty,
+                    ..
+                }) => {
+                    // We have to check is this token already issued?
+                    ensure!(
+                        !TokenIssuanceTransactions::<T>::contains_key(token_id),
+                        "token has already been issued"
+                    );
+                    // The id is derived on-chain like the id of any other token
+                    ensure!(
+                        *token_id == TokenId::new(&tx.inputs[0]),
+                        "nft token id must be derived from the first input"
+                    );
+                    data_hash.validate()?;
+                    if let Some(media_type) = media_type {
+                        crate::tokens::validate_media_type(media_type)?;
+                    }
 
-            match output.destination {
-                Destination::CreatePP(_, _) => {
-                    ensure!(!<UtxoStore<T>>::contains_key(hash), "output already exists");
+                    // Check is this digital data unique?
+                    ensure!(
+                        !<NftUniqueDataHash<T>>::contains_key(data_hash),
+                        "digital data has already been minted"
+                    );
+                    Pallet::<T>::token_metadata_rules().validate_metadata_uri(metadata_uri)?;
+                    if let Some(royalty) = royalty {
+                        ensure!(
+                            royalty.basis_points <= NftRoyalty::MAX_BASIS_POINTS,
+                            "nft royalty exceeds 100%"
+                        );
+                        ensure!(royalty.minimum > 0, "nft royalty minimum must be nonzero");
+                    }
+                    // If NFT has just created we can't meet another NFT part here.
+                    ensure!(
+                        !total_value_of_output_tokens.contains_key(token_id),
+                        "this id can't be used for a new token"
+                    );
+                    total_value_of_output_tokens.insert(token_id.clone(), 1);
+                    mlt_amount_in_outputs = mlt_amount_in_outputs
+                        .checked_add(output.value)
+                        .ok_or("output value overflow")?;
+                }
+                Some(OutputData::TokenMultiV1(tokens)) => {
+                    ensure!(!tokens.is_empty(), "multi-token output carries no tokens");
+                    let mut seen: BTreeSet<&TokenId> = BTreeSet::new();
+                    for (token_id, amount) in tokens {
+                        ensure!(
+                            seen.insert(token_id),
+                            "token appears twice in a multi-token output"
+                        );
+                        ensure!(
+                            TokenIssuanceTransactions::<T>::contains_key(token_id),
+                            "input for the token not found"
+                        );
+                        // Royalties and soul-bound NFTs are only tracked for single transfers
+                        ensure!(
+                            nft_data_hash::<T>(token_id).is_none(),
+                            "nft can't be sent in a multi-token output"
+                        );
+                        total_value_of_output_tokens.insert(
+                            token_id.clone(),
+                            total_value_of_output_tokens
+                                .get(token_id)
+                                .unwrap_or(&0)
+                                .checked_add(*amount)
+                                .ok_or("output value overflow")?,
+                        );
+                    }
+                    mlt_amount_in_outputs = mlt_amount_in_outputs
+                        .checked_add(output.value)
+                        .ok_or("output value overflow")?;
+                }
+                Some(OutputData::TokenLockForBridgeV1 {
+                    ref token_id,
+                    amount,
+                    target_chain,
+                    target_address,
+                }) => {
+                    ensure!(
+                        TokenIssuanceTransactions::<T>::contains_key(token_id),
+                        "input for the token not found"
+                    );
+                    // Royalties and soul-bound NFTs are only tracked for single transfers
+                    ensure!(
+                        nft_data_hash::<T>(token_id).is_none(),
+                        "nft can't be locked for the bridge"
+                    );
+                    ensure!(
+                        T::BridgeHandler::supports_chain(*target_chain),
+                        "bridge target chain is not supported"
+                    );
+                    ensure!(
+                        !target_address.is_empty()
+                            && target_address.len() <= bridge::MAX_TARGET_ADDRESS_LEN,
+                        "invalid bridge target address"
+                    );
+                    ensure!(output.value == 0, "bridge lock output can't carry MLT");
+                    ensure!(
+                        matches!(output.destination, Destination::Pubkey(_)),
+                        "bridge lock output must pay to a pubkey"
+                    );
+                    total_value_of_output_tokens.insert(
+                        token_id.clone(),
+                        total_value_of_output_tokens
+                            .get(token_id)
+                            .unwrap_or(&0)
+                            .checked_add(*amount)
+                            .ok_or("output value overflow")?,
+                    );
+                }
+                None => {
+                    mlt_amount_in_outputs = mlt_amount_in_outputs
+                        .checked_add(output.value)
+                        .ok_or("output value overflow")?;
+                }
+            }
+        }
+
+        // A soul-bound NFT may only be burned, so it can't be transferred by any output
+        for token_id in &soul_bound_tokens {
+            let transferred = tx.outputs.iter().any(|output| match &output.data {
+                Some(OutputData::TokenTransferV1 { token_id: id, .. }) => id == token_id,
+                _ => false,
+            });
+            ensure!(!transferred, "nft is not transferable");
+        }
+
+        // Check for token creation
+        for output in tx.outputs.iter() {
+            let tids = match output.data {
+                Some(ref data) => data.token_ids(&tx.inputs[0]),
+                None => continue,
+            };
+            for tid in tids {
+                // If we have input and output for the same token it's not a problem
+                if full_inputs.iter().find(|&x| (x.0 == tid) && (x.1 != *output)).is_some() {
+                    continue;
+                } else {
+                    // But when we don't have an input for token but token id exist
+                    ensure!(
+                        input_utxos.is_err() || !<TokenIssuanceTransactions<T>>::contains_key(tid),
+                        "no inputs for the token id"
+                    );
+                }
+            }
+        }
+
+        let mut new_utxos = Vec::new();
+        let mut info = PriorityInfo {
+            weight: tx.estimated_weight::<T>(),
+            ..Default::default()
+        };
+        let mut fees = TransactionFees::default();
+        let mut priority = 0;
+
+        // Check that outputs are valid
+        for (output_index, output) in tx.outputs.iter().enumerate() {
+            match output.data {
+                Some(OutputData::TokenIssuanceV1 {
+                    amount_to_issue, ..
+                }) => ensure!(amount_to_issue > 0, "output value must be nonzero"),
+                Some(OutputData::TokenTransferV1 { amount, .. }) => {
+                    ensure!(amount > 0, "output value must be nonzero")
+                }
+                Some(OutputData::TokenBurnV1 { amount_to_burn, .. }) => {
+                    ensure!(amount_to_burn > 0, "output value must be nonzero")
+                }
+                Some(OutputData::NftMintV1 { .. }) => {
+                    // Nothing to check
+                }
+                Some(OutputData::TokenMultiV1(ref tokens)) => ensure!(
+                    tokens.iter().all(|(_, amount)| *amount > 0),
+                    "output value must be nonzero"
+                ),
+                Some(OutputData::TokenLockForBridgeV1 { amount, .. }) => {
+                    ensure!(amount > 0, "output value must be nonzero")
+                }
+                None => match output.destination {
+                    Destination::DataCarrier(_) => {
+                        ensure!(
+                            output.value == 0,
+                            "data carrier output must have zero value"
+                        )
+                    }
+                    _ => ensure!(output.value > 0, "output value must be nonzero"),
+                },
+            }
+            check_transfer_policy::<T>(output)?;
+            ensure!(
+                output.value >= Pallet::<T>::storage_deposit(output),
+                "output value doesn't cover the storage deposit"
+            );
+            let hash = tx.outpoint(output_index as u64);
+            // Data carrier outputs can't be spent, there is nothing to provide
+            if !matches!(output.destination, Destination::DataCarrier(_)) {
+                new_utxos.push(outpoint_tag(&hash));
+            }
+
+            match output.destination {
+                Destination::CreatePP(_, _) => {
+                    ensure!(!<UtxoStore<T>>::contains_key(hash), "output already exists");
                     log::info!("TODO validate CreatePP as output");
                 }
-                Destination::CallPP(_, _, _) => {
+                Destination::CallPP(ref dest, _, ref input, abi_hash) => {
+                    ensure!(!<UtxoStore<T>>::contains_key(hash), "output already exists");
+                    // Reject malformed calls before they are paid for
+                    T::ProgrammablePool::verify_call(dest, abi_hash, input)?;
+                }
+                Destination::Pubkey(_)
+                | Destination::ScriptHash(..)
+                | Destination::Taproot { .. } => {
+                    ensure!(!<UtxoStore<T>>::contains_key(hash), "output already exists");
+                }
+                Destination::Vesting { total, .. } => {
+                    ensure!(!<UtxoStore<T>>::contains_key(hash), "output already exists");
+                    ensure!(output.data.is_none(), "only MLT can be vested");
+                    ensure!(output.value <= total, "vested value exceeds the grant");
+                }
+                Destination::Stream { rate, .. } => {
+                    ensure!(!<UtxoStore<T>>::contains_key(hash), "output already exists");
+                    ensure!(output.data.is_none(), "only MLT can be streamed");
+                    ensure!(rate > 0, "stream rate must be nonzero");
+                }
+                Destination::Inheritance { owner, heir, delay } => {
+                    ensure!(!<UtxoStore<T>>::contains_key(hash), "output already exists");
+                    ensure!(owner != heir, "heir must differ from the owner");
+                    ensure!(delay > 0, "inheritance delay must be nonzero");
+                }
+                Destination::Auction { .. } => {
+                    ensure!(!<UtxoStore<T>>::contains_key(hash), "output already exists");
+                    auction::validate_auction::<T>(output)?;
+                }
+                Destination::Bid { .. } => {
                     ensure!(!<UtxoStore<T>>::contains_key(hash), "output already exists");
-                    log::info!("TODO validate CallPP as output");
+                    auction::validate_bid::<T>(tx, output)?;
                 }
-                Destination::Pubkey(_) | Destination::ScriptHash(_) => {
+                Destination::Order { .. } => {
                     ensure!(!<UtxoStore<T>>::contains_key(hash), "output already exists");
+                    crate::order::validate_order::<T>(output)?;
+                }
+                Destination::DataCarrier(ref data) => {
+                    ensure!(
+                        output.data.is_none(),
+                        "data carrier output can't carry tokens"
+                    );
+                    ensure!(
+                        data.len() <= T::MaxDataCarrierSize::get() as usize,
+                        "data carrier output exceeds the maximum size"
+                    );
                 }
-                Destination::LockForStaking { .. } | Destination::LockExtraForStaking { .. } => {
+                Destination::LockForStaking { .. }
+                | Destination::LockExtraForStaking { .. }
+                | Destination::CreateStakingPool { .. }
+                | Destination::JoinStakingPool { .. } => {
                     staking::validate_staking_ops::<T>(output, hash)?;
                 }
             }
@@ -962,18 +2911,29 @@ pub mod pallet {
 
         // if all spent UTXOs are available, check the math and signatures
         if let Ok(input_utxos) = &input_utxos {
+            if relayed {
+                check_standard::<T>(tx, input_utxos)?;
+            }
+
             // We have to check sum of input tokens is less or equal to output tokens.
             ensure!(
                 mlt_amount_in_outputs <= mlt_amount_in_inputs,
                 "output value must not exceed input value"
             );
 
+            // The part of an accepted fee asset not sent on is paid as a fee
+            let fee_assets = T::AcceptedFeeAssets::get();
+            let fee_asset =
+                |token_id: &TokenId| fee_assets.iter().find(|asset| asset.token_id == *token_id);
+
             let mut issuance_counter = 0;
+            let mut issuance_fee: Value = 0;
             for (token_id, token_value) in &total_value_of_output_tokens {
                 match total_value_of_input_tokens.get(&token_id) {
                     Some(input_value) => {
                         ensure!(
-                            input_value == token_value,
+                            input_value == token_value
+                                || (input_value > token_value && fee_asset(token_id).is_some()),
                             "output value must not exceed input value"
                         )
                     }
@@ -982,23 +2942,30 @@ pub mod pallet {
                         // find TransactionOutput for this token_id
                         let output = &tx.outputs.iter().find(|x| match x.data {
                             Some(ref output_data) => {
-                                output_data.id(&tx.inputs[0]).as_ref() == Some(token_id)
+                                output_data.token_ids(&tx.inputs[0]).contains(token_id)
                             }
                             None => false,
                         });
 
                         match output {
                             Some(output) => match output.data {
-                                // todo: This part isn't fully tested, left for the next PR
-                                // | Some(OutputData::TokenBurnV1 { .. }) =>
-                                // Some(OutputData::NftMintV1 { .. })
+                                // If we make a new token then okay, this is not a problem
                                 Some(OutputData::TokenIssuanceV1 { .. }) => {
-                                    // If we make a new token then okay, this is not a problem
                                     issuance_counter += 1;
+                                    issuance_fee = T::TokenIssuanceFee::get();
+                                    continue;
+                                }
+                                Some(OutputData::NftMintV1 { .. }) => {
+                                    issuance_counter += 1;
+                                    issuance_fee = T::NftMintFee::get();
                                     continue;
                                 }
-                                None | Some(OutputData::TokenTransferV1 { .. }) => {
-                                    // But we can't send a token without input
+                                None
+                                | Some(OutputData::TokenTransferV1 { .. })
+                                | Some(OutputData::TokenBurnV1 { .. })
+                                | Some(OutputData::TokenMultiV1(_))
+                                | Some(OutputData::TokenLockForBridgeV1 { .. }) => {
+                                    // But we can't send or burn a token without input
                                     frame_support::fail!("input for the token not found2")
                                 }
                             },
@@ -1012,71 +2979,280 @@ pub mod pallet {
                 issuance_counter <= 1,
                 "too many issuance in one transaction"
             );
-            if issuance_counter == 1 {
-                // The sender should pay not less than 100 MLT for issuance
+            // The issuer is the owner of the first input, the token id is derived from it
+            if issuance_counter > 0 && <PermissionedIssuance<T>>::get() {
+                ensure!(
+                    matches!(input_utxos[0].destination, Destination::Pubkey(pubkey)
+                        if <TokenIssuers<T>>::contains_key(H256::from(pubkey))),
+                    "not allowed to issue tokens"
+                );
+            }
+            if issuance_counter > 0 {
+                let (era, count) =
+                    <IssuanceCount<T>>::get(BlakeTwo256::hash_of(&input_utxos[0].destination));
+                ensure!(
+                    era != T::StakingHelper::current_era() || count < T::IssuanceRateLimit::get(),
+                    "issuance rate limit reached"
+                );
+            }
+            // Tokens can't just disappear, otherwise the burned amount wouldn't add up
+            for token_id in total_value_of_input_tokens.keys() {
                 ensure!(
-                    mlt_amount_in_inputs >= crate::tokens::Mlt(100).to_munit(),
-                    "insufficient fee"
+                    total_value_of_output_tokens.contains_key(token_id)
+                        || fee_asset(token_id).is_some(),
+                    "tokens must be burned explicitly"
                 );
             }
 
+            let mut token_fee_value: Value = 0;
+            for (token_id, input_value) in &total_value_of_input_tokens {
+                let asset = match fee_asset(token_id) {
+                    Some(asset) => asset,
+                    None => continue,
+                };
+                let output_value = total_value_of_output_tokens.get(token_id).unwrap_or(&0);
+                let fee = input_value.saturating_sub(*output_value);
+                if fee > 0 {
+                    token_fee_value = token_fee_value.saturating_add(asset.to_mlt(fee));
+                    fees.tokens.push((token_id.clone(), fee));
+                }
+            }
+
+            check_nft_royalties::<T>(tx, input_utxos, &total_value_of_input_tokens)?;
+
+            // Inputs spent by a signature of their key and inputs spent by a script, checked
+            // together after the loop
+            let mut signers = Vec::new();
+            let mut scripts = Vec::new();
+            let canonical = canonical_witnesses::<T>(rules);
             for (index, (input, input_utxo)) in tx.inputs.iter().zip(input_utxos).enumerate() {
-                match &input_utxo.destination {
-                    Destination::Pubkey(pubkey) => {
-                        let msg = sign::TransactionSigMsg::construct(
-                            sign::SigHash::default(),
-                            &tx,
-                            &input_utxos,
-                            index as u64,
-                            u32::MAX,
-                        );
-                        let ok = pubkey
-                            .parse_sig(&input.witness[..])
-                            .ok_or("bad signature format")?
-                            .verify(&msg);
-                        ensure!(ok, "signature must be valid");
-                    }
-                    Destination::CreatePP(_, _) => {
-                        log::info!("TODO validate spending of OP_CREATE");
-                    }
-                    Destination::CallPP(_, _, _) => {
-                        let spend =
-                            u16::from_le_bytes(input.witness[1..].try_into().or_else(|_| {
-                                Err(DispatchError::Other(
-                                    "Failed to convert witness to an opcode",
-                                ))
-                            })?);
-                        ensure!(spend == 0x1337, "OP_SPEND not found");
-                    }
-                    Destination::ScriptHash(_hash) => {
-                        let witness = input.witness.clone();
-                        let lock = input.lock.clone();
-                        crate::script::verify(&tx, &input_utxos, index as u64, witness, lock)
-                            .map_err(|_| "script verification failed")?;
-                    }
-                    Destination::LockForStaking { .. }
-                    | Destination::LockExtraForStaking { .. } => {
-                        return Err("cannot spend a staking utxo.");
-                    }
+                if canonical {
+                    verifier::check_witness_canonicality(input, input_utxo)?;
+                }
+                match witness_check::<T>(input, input_utxo, index, rules)? {
+                    WitnessCheck::Signature(pubkey) => signers.push((index, pubkey)),
+                    WitnessCheck::Script(check) => scripts.push(check),
+                    WitnessCheck::Nothing => (),
+                }
+            }
+            // The block import can skip what the pool has already verified
+            let chain = chain_binding::<T>();
+            let cache_key = verifier::cache_key(tx, input_utxos, chain, &scripts);
+            if rules == ScriptRules::Policy || !verifier::is_verified(cache_key) {
+                ensure!(
+                    verifier::verify_all(tx, input_utxos, rules, chain, scripts),
+                    "script verification failed"
+                );
+                check_signatures(&signers, tx, input_utxos, chain)?;
+                if rules == ScriptRules::Policy {
+                    verifier::set_verified(cache_key);
+                }
+            }
+
+            // Only the released part of vesting UTXOs can be spent, the rest is locked again. The
+            // schedule locks a part of the whole grant, of which the spent UTXOs hold what is left.
+            let now: u64 = <frame_system::Pallet<T>>::block_number().unique_saturated_into();
+            let mut remaining: BTreeMap<&Destination<T::AccountId>, Value> = BTreeMap::new();
+            for utxo in input_utxos.iter() {
+                if let Destination::Vesting { .. } = &utxo.destination {
+                    let entry = remaining.entry(&utxo.destination).or_insert(0);
+                    *entry = entry.saturating_add(utxo.value);
+                }
+            }
+            for (destination, remaining) in remaining {
+                let value = match destination {
+                    Destination::Vesting {
+                        total,
+                        start,
+                        cliff,
+                        duration,
+                        ..
+                    } => crate::vesting::locked_value(*total, *start, *cliff, *duration, now)
+                        .min(remaining),
+                    _ => 0,
+                };
+                let relocked = tx
+                    .outputs
+                    .iter()
+                    .filter(|output| &output.destination == destination)
+                    .fold(0, |total: Value, output| total.saturating_add(output.value));
+                ensure!(relocked >= value, "unvested value must be locked again");
+            }
+
+            // Only the streamed part of stream UTXOs can be withdrawn, the rest goes on streaming
+            let mut streaming: BTreeMap<&Destination<T::AccountId>, Value> = BTreeMap::new();
+            for (input, utxo) in tx.inputs.iter().zip(input_utxos) {
+                if let Destination::Stream { rate, .. } = &utxo.destination {
+                    let blocks = input_age::<T>(&input.outpoint).map_or(0, |age| age.blocks);
+                    let unstreamed =
+                        crate::stream::unstreamed_value(utxo.value, *rate, blocks.into());
+                    let entry = streaming.entry(&utxo.destination).or_insert(0);
+                    *entry = entry.saturating_add(unstreamed);
                 }
             }
+            for (destination, value) in streaming {
+                let continued = tx
+                    .outputs
+                    .iter()
+                    .filter(|output| &output.destination == destination)
+                    .fold(0, |total: Value, output| total.saturating_add(output.value));
+                ensure!(continued >= value, "unstreamed value must go on streaming");
+            }
+
+            // Orders spent without the signature of their maker have to be filled
+            crate::order::check_fills::<T>(tx, input_utxos)?;
 
             // Reward at the moment only in MLT
-            reward = mlt_amount_in_inputs
+            let reward = mlt_amount_in_inputs
                 .checked_sub(mlt_amount_in_outputs)
                 .ok_or("reward underflow")?;
             if reward >= u64::MAX.into() {
                 frame_support::fail!("reward exceed allowed amount");
             }
+            // The sender pays the issuance fee, it goes to the reward like the rest of the fee
+            ensure!(reward >= issuance_fee, "insufficient fee");
+
+            fees.mlt = reward;
+            info.fee = reward.saturating_add(token_fee_value);
+            info.coin_age =
+                tx.inputs.iter().zip(input_utxos).fold(0, |total: Value, (input, utxo)| {
+                    let age = input_age::<T>(&input.outpoint).map_or(0, |age| age.blocks);
+                    total.saturating_add(utxo.value.saturating_mul(age.into()))
+                });
+            // The minimum fee is a policy, blocks paying less are still valid
+            ensure!(
+                !relayed || info.fee >= T::PriorityPolicy::minimum_fee(info.weight),
+                "fee rate too low"
+            );
+            priority = T::PriorityPolicy::priority(&info);
         }
 
-        Ok(ValidTransaction {
-            priority: reward as u64,
+        let valid = ValidTransaction {
+            priority,
             requires: input_utxos.map_or_else(|x| x, |_| Vec::new()),
             provides: new_utxos,
-            longevity: TransactionLongevity::MAX,
+            longevity: tx.longevity::<T>(),
             propagate: true,
-        })
+        };
+        Ok((valid, info, fees))
+    }
+
+    /// Apply a package of transactions to the storage, returning their priority data.
+    fn apply_package<T: Config>(
+        caller: &T::AccountId,
+        txs: &[TransactionFor<T>],
+    ) -> Result<Vec<PriorityInfo>, DispatchError> {
+        let mut infos = Vec::new();
+        for tx in txs {
+            let (valid, info, fees) =
+                check_transaction::<T>(tx, ScriptRules::Policy, TransactionSource::External)?;
+            ensure!(valid.requires.is_empty(), "missing inputs");
+            update_storage::<T>(caller, tx, &fees).map_err(|e| e.error)?;
+            infos.push(info);
+        }
+        Ok(infos)
+    }
+
+    /// Priority of a package of transactions, each one possibly spending outputs of the previous
+    /// ones, such as a parent paying a low fee and a child paying for it (CPFP).
+    ///
+    /// The transaction pool orders a child after the parents providing the tags it requires, but
+    /// it doesn't raise the priority of the parents. The package priority lets block authors and
+    /// wallets see what the parent and the child pay together. The storage is left untouched,
+    /// `caller` is only used to dry-run programmable pool calls.
+    pub fn package_priority<T: Config>(
+        caller: &T::AccountId,
+        txs: &[TransactionFor<T>],
+    ) -> Result<TransactionPriority, DispatchError> {
+        let infos =
+            with_transaction(|| TransactionOutcome::Rollback(apply_package::<T>(caller, txs)))?;
+        Ok(T::PriorityPolicy::priority(&PriorityInfo::package(&infos)))
+    }
+
+    /// Check a new token may be issued with the ticker, see `Config::ReservedTickers` and
+    /// `Config::UniqueTickers`.
+    pub(crate) fn validate_ticker<T: Config>(ticker: &[u8]) -> Result<(), &'static str> {
+        let ticker = ticker.to_ascii_uppercase();
+        ensure!(
+            !T::ReservedTickers::get()
+                .iter()
+                .any(|reserved| reserved.to_ascii_uppercase() == ticker),
+            "token ticker is reserved"
+        );
+        ensure!(
+            !T::UniqueTickers::get() || !<TickerRegistry<T>>::contains_key(&ticker),
+            "token ticker is already taken"
+        );
+        Ok(())
+    }
+
+    /// Ensure the beneficiaries of all royalty-bearing NFTs transferred by `tx` get paid.
+    ///
+    /// Every transfer owes at least the minimum royalty, wherever the seller is paid. The rate is
+    /// charged on the seller's proceeds: the MLT paid by `tx` to the destination that held the
+    /// NFT, minus the MLT that destination contributed through its own inputs. Keeping the NFT at
+    /// the same destination, burning it or escrowing it in an auction of the same seller, which
+    /// pays the royalty when settled, is not a transfer.
+    fn check_nft_royalties<T: Config>(
+        tx: &TransactionFor<T>,
+        input_utxos: &[TransactionOutputFor<T>],
+        input_tokens: &BTreeMap<TokenId, Value>,
+    ) -> Result<(), &'static str> {
+        let mlt_paid_to = |dest: &Destination<T::AccountId>| -> Value {
+            tx.outputs
+                .iter()
+                .filter(|output| output.data.is_none() && output.destination == *dest)
+                .fold(0, |total: Value, output| total.saturating_add(output.value))
+        };
+
+        for token_id in input_tokens.keys() {
+            let royalty = match <NftRoyalties<T>>::get(token_id) {
+                Some(royalty) => royalty,
+                None => continue,
+            };
+            let seller = &input_utxos
+                .iter()
+                .find(|utxo| match &utxo.data {
+                    Some(OutputData::NftMintV1 { token_id: id, .. })
+                    | Some(OutputData::TokenTransferV1 { token_id: id, .. }) => id == token_id,
+                    _ => false,
+                })
+                .ok_or("corrupted output data")?
+                .destination;
+            let beneficiary = Destination::Pubkey(royalty.beneficiary);
+            if *seller == beneficiary {
+                // The creator selling its own NFT does not owe itself anything
+                continue;
+            }
+            let new_holder = tx.outputs.iter().find_map(|output| match &output.data {
+                Some(OutputData::TokenTransferV1 { token_id: id, .. }) if id == token_id => {
+                    Some(&output.destination)
+                }
+                _ => None,
+            });
+            let transferred = match new_holder {
+                // Burned
+                None => false,
+                Some(Destination::Auction { seller: pubkey, .. }) => {
+                    *seller != Destination::Pubkey(*pubkey)
+                }
+                Some(holder) => holder != seller,
+            };
+            if !transferred {
+                continue;
+            }
+
+            let contributed = input_utxos
+                .iter()
+                .filter(|utxo| utxo.destination == *seller)
+                .fold(0, |total: Value, utxo| total.saturating_add(utxo.value));
+            let proceeds = mlt_paid_to(seller).saturating_sub(contributed);
+            ensure!(
+                mlt_paid_to(&beneficiary) >= royalty.amount_due(proceeds),
+                "nft royalty not paid"
+            );
+        }
+        Ok(())
     }
 
     /// Update storage to reflect changes made by transaction
@@ -1084,66 +3260,191 @@ pub mod pallet {
     pub fn update_storage<T: Config>(
         caller: &T::AccountId,
         tx: &TransactionFor<T>,
-        reward: Value,
+        fees: &TransactionFees,
     ) -> DispatchResultWithPostInfo {
-        // Calculate new reward total
-        let new_total = <RewardTotal<T>>::get().checked_add(reward).ok_or("Reward overflow")?;
+        credit_reward_pool::<T>(fees.mlt, RewardSource::TransactionFee)?;
+        for (token_id, fee) in &fees.tokens {
+            <TokenRewardTotal<T>>::mutate(token_id, |total| *total = total.saturating_add(*fee));
+        }
 
-        log::debug!("update_storage:: reward total: {:?}", new_total);
-        <RewardTotal<T>>::put(new_total);
+        let block_number = <frame_system::Pallet<T>>::block_number();
+        let journal_spends = !T::StxoRetention::get().is_zero();
+        let tx_hash = BlakeTwo256::hash_of(tx);
+        let txid = tx.txid();
+        let mut nft_holders = Vec::new();
+        let mut spent_utxos = Vec::new();
 
         // Removing spent UTXOs
         for input in &tx.inputs {
-            log::debug!("removing {:?} in UtxoStore.", input.outpoint);
-            <UtxoStore<T>>::remove(input.outpoint);
+            if let Some(utxo) = spent_utxo::<T>(input) {
+                if let Some(token_id) = nft_id::<T>(&utxo) {
+                    nft_holders.push((token_id, utxo.destination.clone()));
+                }
+                remove_utxo::<T>(input.outpoint, &utxo);
+                spent_utxos.push((input.outpoint, utxo));
+            }
+            if journal_spends {
+                <SpentOutputs<T>>::insert(input.outpoint, (tx_hash, block_number));
+                <SpentInBlock<T>>::append(block_number, input.outpoint);
+            }
+        }
+
+        // A created token or NFT counts against the rate limit of the owner of the first input
+        let issues = tx.outputs.iter().any(|output| {
+            matches!(
+                output.data,
+                Some(OutputData::TokenIssuanceV1 { .. }) | Some(OutputData::NftMintV1 { .. })
+            )
+        });
+        if let (true, Some((_, issuer))) = (issues, spent_utxos.first()) {
+            let era = T::StakingHelper::current_era();
+            <IssuanceCount<T>>::mutate(BlakeTwo256::hash_of(&issuer.destination), |entry| {
+                if entry.0 != era {
+                    *entry = (era, 0);
+                }
+                entry.1 = entry.1.saturating_add(1);
+            });
+        }
+
+        // The hooks see the UTXOs once they are removed
+        let mut hooks_weight: Weight = 0;
+        for (outpoint, utxo) in &spent_utxos {
+            hooks_weight = hooks_weight.saturating_add(T::UtxoHooks::on_utxo_spent(outpoint, utxo));
         }
 
         for (index, output) in tx.outputs.iter().enumerate() {
             let hash = tx.outpoint(index as u64);
+            for watcher in watchers::<T>(&output.destination) {
+                Pallet::<T>::deposit_event(Event::<T>::UtxoReceived(watcher, hash, output.value));
+            }
+
+            if let Some(OutputData::TokenLockForBridgeV1 {
+                token_id,
+                amount,
+                target_chain,
+                target_address,
+            }) = &output.data
+            {
+                log::debug!("inserting to BridgeCustody {:?} as key {:?}", output, hash);
+                <BridgeCustody<T>>::insert(hash, output);
+                T::BridgeHandler::on_lock(&hash, token_id, *amount, *target_chain, target_address);
+                Pallet::<T>::deposit_event(Event::<T>::TokensLockedForBridge(
+                    token_id.clone(),
+                    *amount,
+                    *target_chain,
+                    target_address.clone(),
+                    hash,
+                ));
+                continue;
+            }
 
             match &output.destination {
-                Destination::Pubkey(_) | Destination::ScriptHash(_) => {
-                    let hash = tx.outpoint(index as u64);
-                    log::debug!("inserting to UtxoStore {:?} as key {:?}", output, hash);
-                    <UtxoStore<T>>::insert(hash, output);
+                Destination::Pubkey(_)
+                | Destination::ScriptHash(..)
+                | Destination::Taproot { .. }
+                | Destination::Vesting { .. }
+                | Destination::Stream { .. }
+                | Destination::Inheritance { .. }
+                | Destination::Order { .. } => {
+                    insert_utxo::<T>(hash, output, Some(TokenId::new(&tx.inputs[0])));
+                    #[cfg(feature = "pruning")]
+                    if is_prunable(output) && !T::PruningDepth::get().is_zero() {
+                        <CreatedInBlock<T>>::append(block_number, hash);
+                    }
                     match &output.data {
-                        // todo: This part isn't fully tested, left for the next PR
-                        // Some(OutputData::NftMintV1 {
-                        //     token_id,
-                        //     data_hash,
-                        //     ..
-                        // }) => {
-                        //     // We have to control that digital data of NFT is unique.
-                        //     // Otherwise, anybody else might make a new NFT with exactly the same hash.
-                        //     <NftUniqueDataHash<T>>::insert(data_hash, hash);
-                        //     // Also, we should provide possibility of find an output that by token_id.
-                        //     // This output is a place where token was created. It allow us to check that a token or
-                        //     // a NFT have not created yet.
-                        //     <TokenIssuanceTransactions<T>>::insert(token_id, hash);
-                        // }
-                        Some(OutputData::TokenIssuanceV1 { .. }) => {
+                        Some(OutputData::NftMintV1 {
+                            token_id,
+                            data_hash,
+                            royalty,
+                            ..
+                        }) => {
+                            // We have to control that digital data of NFT is unique.
+                            // Otherwise, anybody else might make a new NFT with exactly the same hash.
+                            <NftUniqueDataHash<T>>::insert(data_hash, hash);
+                            // Also, we should provide possibility of find an output that by token_id.
+                            // This output is a place where token was created. It allow us to check that a token or
+                            // a NFT have not created yet.
+                            <TokenIssuanceTransactions<T>>::insert(token_id, &tx);
+                            <TokenSupply<T>>::insert(token_id, 1);
+                            Pallet::<T>::deposit_event(Event::<T>::FeeCharged(
+                                token_id.clone(),
+                                T::NftMintFee::get(),
+                            ));
+                            if let Some(royalty) = royalty {
+                                <NftRoyalties<T>>::insert(token_id, royalty);
+                            }
+                        }
+                        Some(OutputData::TokenIssuanceV1 {
+                            token_ticker,
+                            amount_to_issue,
+                            ..
+                        }) => {
                             let token_id = TokenId::new(&tx.inputs[0]);
                             // Link output hash
                             <TokenIssuanceId<T>>::insert(hash, &token_id);
+                            // The first token issued with a ticker keeps it
+                            let ticker = token_ticker.to_ascii_uppercase();
+                            if !<TickerRegistry<T>>::contains_key(&ticker) {
+                                <TickerRegistry<T>>::insert(ticker, &token_id);
+                            }
                             // For MLS-01 we save a relation between token_id and the tx where
                             // token was created.
                             <TokenIssuanceTransactions<T>>::insert(&token_id, &tx);
+                            <TokenSupply<T>>::insert(&token_id, amount_to_issue);
+                            Pallet::<T>::deposit_event(Event::<T>::FeeCharged(
+                                token_id,
+                                T::TokenIssuanceFee::get(),
+                            ));
+                        }
+                        Some(OutputData::TokenBurnV1 {
+                            token_id,
+                            amount_to_burn,
+                        }) => {
+                            if let Some(data_hash) = nft_data_hash::<T>(token_id) {
+                                <NftUniqueDataHash<T>>::insert(data_hash, hash);
+                            }
+                            <TotalBurned<T>>::mutate(token_id, |total| {
+                                *total = total.saturating_add(*amount_to_burn)
+                            });
+                            <TokenSupply<T>>::mutate(token_id, |supply| {
+                                if let Some(supply) = supply {
+                                    *supply = supply.saturating_sub(*amount_to_burn)
+                                }
+                            });
+                            Pallet::<T>::deposit_event(Event::<T>::TokenBurned(
+                                token_id.clone(),
+                                *amount_to_burn,
+                            ));
+                        }
+                        Some(OutputData::TokenTransferV1 { token_id, .. }) => {
+                            if let Some(data_hash) = nft_data_hash::<T>(token_id) {
+                                <NftUniqueDataHash<T>>::insert(data_hash, hash);
+                                if let Some((_, from)) =
+                                    nft_holders.iter().find(|(id, _)| id == token_id)
+                                {
+                                    record_nft_transfer::<T>(
+                                        token_id,
+                                        from.clone(),
+                                        output.destination.clone(),
+                                    );
+                                }
+                            }
                         }
+                        // Carries fungible tokens only, nothing to track
+                        Some(OutputData::TokenMultiV1(_)) => {}
+                        // Moved to the bridge custody before
+                        Some(OutputData::TokenLockForBridgeV1 { .. }) => {}
                         // For the security reason we are implementing all cases
-                        // todo: This part isn't fully tested, left for the next PR
-                        // Some(OutputData::TokenBurnV1 { .. })
-                        // |
-                        Some(OutputData::TokenTransferV1 { .. }) | None => continue,
+                        None => {}
                     }
                 }
                 Destination::CreatePP(script, data) => {
-                    log::debug!("inserting to UtxoStore {:?} as key {:?}", output, hash);
-                    <UtxoStore<T>>::insert(hash, output);
+                    insert_utxo::<T>(hash, output, None);
                     create::<T>(caller, script, hash, output.value, &data);
                 }
-                Destination::CallPP(acct_id, fund, data) => {
-                    log::debug!("inserting to UtxoStore {:?} as key {:?}", output, hash);
-                    <UtxoStore<T>>::insert(hash, output);
+                Destination::CallPP(acct_id, fund, data, _) => {
+                    insert_utxo::<T>(hash, output, None);
+                    <ContractUtxos<T>>::insert(acct_id, hash, output.value);
                     call::<T>(caller, acct_id, hash, output.value, *fund, data);
                 }
                 Destination::LockForStaking { .. } => {
@@ -1152,154 +3453,1679 @@ pub mod pallet {
                 Destination::LockExtraForStaking { .. } => {
                     staking::lock_extra_for_staking::<T>(hash, output)?;
                 }
+                Destination::CreateStakingPool { .. } => {
+                    staking::pool::create_pool::<T>(hash, output)?;
+                }
+                Destination::JoinStakingPool { .. } => {
+                    staking::pool::join_pool::<T>(hash, output)?;
+                }
+                Destination::DataCarrier(_) => {
+                    log::debug!("data carrier output {:?} is not stored", hash);
+                }
+                Destination::Auction { .. } => auction::open_auction::<T>(hash, output),
+                Destination::Bid { .. } => {
+                    let weight = auction::place_bid::<T>(hash, output);
+                    hooks_weight = hooks_weight.saturating_add(weight);
+                }
+            }
+
+            if <UtxoStore<T>>::contains_key(hash) {
+                <OutpointsByTxid<T>>::insert(txid, index as u32, hash);
+                <OutputRefs<T>>::insert(hash, (txid, index as u32));
+                hooks_weight =
+                    hooks_weight.saturating_add(T::UtxoHooks::on_new_utxo(&hash, output));
+            }
+        }
+
+        if hooks_weight > 0 {
+            <frame_system::Pallet<T>>::register_extra_weight_unchecked(
+                hooks_weight,
+                DispatchClass::Normal,
+            );
+        }
+
+        Ok(().into())
+    }
+
+    /// Owner and amounts of the fungible tokens held by a pay-to-pubkey output. `issued` is the id
+    /// of the token an issuance output carries.
+    fn token_holdings<T: Config>(
+        output: &TransactionOutputFor<T>,
+        issued: Option<TokenId>,
+    ) -> Vec<(H256, TokenId, Value)> {
+        let owner = match output.destination {
+            Destination::Pubkey(pubkey) => H256::from(pubkey),
+            _ => return Vec::new(),
+        };
+        let tokens = match &output.data {
+            Some(OutputData::TokenIssuanceV1 {
+                amount_to_issue, ..
+            }) => issued.map(|token_id| (token_id, *amount_to_issue)).into_iter().collect(),
+            Some(OutputData::TokenTransferV1 { token_id, amount })
+                if nft_data_hash::<T>(token_id).is_none() =>
+            {
+                vec![(token_id.clone(), *amount)]
+            }
+            Some(OutputData::TokenMultiV1(tokens)) => tokens.clone(),
+            _ => Vec::new(),
+        };
+        tokens.into_iter().map(|(token_id, amount)| (owner, token_id, amount)).collect()
+    }
+
+    /// Add a UTXO to `TokenUtxosByOwner`, `TokenHolders` and `TokenHolderCount`
+    pub(crate) fn index_token_utxo<T: Config>(
+        hash: H256,
+        output: &TransactionOutputFor<T>,
+        issued: Option<TokenId>,
+    ) {
+        for (owner, token_id, amount) in token_holdings::<T>(output, issued) {
+            <TokenUtxosByOwner<T>>::insert((owner, token_id.clone()), hash, amount);
+            if amount.is_zero() {
+                continue;
+            }
+            <TokenHolders<T>>::mutate(&token_id, owner, |balance| {
+                if balance.is_none() {
+                    <TokenHolderCount<T>>::mutate(&token_id, |count| {
+                        *count = count.saturating_add(1)
+                    });
+                }
+                *balance = Some(balance.unwrap_or(0).saturating_add(amount));
+            });
+        }
+    }
+
+    /// Remove a UTXO from `TokenUtxosByOwner`, `TokenHolders` and `TokenHolderCount`
+    pub(crate) fn unindex_token_utxo<T: Config>(
+        hash: H256,
+        output: &TransactionOutputFor<T>,
+        issued: Option<TokenId>,
+    ) {
+        for (owner, token_id, amount) in token_holdings::<T>(output, issued) {
+            <TokenUtxosByOwner<T>>::remove((owner, token_id.clone()), hash);
+            match <TokenHolders<T>>::get(&token_id, owner)
+                .map(|balance| balance.saturating_sub(amount))
+            {
+                Some(0) => {
+                    <TokenHolders<T>>::remove(&token_id, owner);
+                    <TokenHolderCount<T>>::mutate_exists(&token_id, |count| {
+                        *count =
+                            count.map(|count| count.saturating_sub(1)).filter(|count| *count > 0)
+                    });
+                }
+                Some(balance) => <TokenHolders<T>>::insert(&token_id, owner, balance),
+                None => (),
+            }
+        }
+    }
+
+    /// Tokens held by an output, whatever its destination. `issued` is the id of the token an
+    /// issuance output carries. Burned tokens are not held.
+    fn held_tokens<T: Config>(
+        output: &TransactionOutputFor<T>,
+        issued: Option<TokenId>,
+    ) -> Vec<TokenId> {
+        let mut tokens: Vec<TokenId> = match &output.data {
+            Some(OutputData::TokenIssuanceV1 { .. }) => issued.into_iter().collect(),
+            Some(OutputData::TokenTransferV1 { token_id, .. })
+            | Some(OutputData::NftMintV1 { token_id, .. }) => vec![token_id.clone()],
+            Some(OutputData::TokenMultiV1(tokens)) => {
+                tokens.iter().map(|(token_id, _)| token_id.clone()).collect()
+            }
+            _ => Vec::new(),
+        };
+        tokens.sort();
+        tokens.dedup();
+        tokens
+    }
+
+    /// Add `output` to `UtxoStore` at `outpoint`, along with the indexes and the creation time
+    /// kept on UTXOs. `issued` is the id of the token an issuance output carries.
+    pub(crate) fn insert_utxo<T: Config>(
+        outpoint: H256,
+        output: &TransactionOutputFor<T>,
+        issued: Option<TokenId>,
+    ) {
+        log::debug!("inserting to UtxoStore {:?} as key {:?}", output, outpoint);
+        <UtxoStore<T>>::insert(outpoint, output);
+        index_token_utxo::<T>(outpoint, output, issued.clone());
+        count_utxo::<T>(output, issued);
+        let block_number = <frame_system::Pallet<T>>::block_number();
+        let now = <pallet_timestamp::Pallet<T> as UnixTime>::now();
+        <UtxoCreationTime<T>>::insert(outpoint, (block_number, now.as_millis() as u64));
+    }
+
+    /// Remove the UTXO `utxo` at `outpoint` from the UTXO set, along with everything kept on it,
+    /// and tell the accounts watching its destination. The hooks are left to the caller.
+    pub(crate) fn remove_utxo<T: Config>(outpoint: H256, utxo: &TransactionOutputFor<T>) {
+        for watcher in watchers::<T>(&utxo.destination) {
+            Pallet::<T>::deposit_event(Event::<T>::UtxoSpent(watcher, outpoint));
+        }
+        if <PrunedUtxos<T>>::take(outpoint).is_some() {
+            <PrunedValue<T>>::mutate(|total| *total = total.saturating_sub(utxo.value));
+        }
+        let issued = <TokenIssuanceId<T>>::take(outpoint);
+        unindex_token_utxo::<T>(outpoint, utxo, issued.clone());
+        uncount_utxo::<T>(utxo, issued);
+        if let Destination::CallPP(contract, ..) = &utxo.destination {
+            <ContractUtxos<T>>::remove(contract, outpoint);
+        }
+        if let Some((txid, index)) = <OutputRefs<T>>::take(outpoint) {
+            <OutpointsByTxid<T>>::remove(txid, index);
+        }
+        log::debug!("removing {:?} in UtxoStore.", outpoint);
+        <UtxoStore<T>>::remove(outpoint);
+        <UtxoCreationTime<T>>::remove(outpoint);
+    }
+
+    /// Add a UTXO created outside of a transaction to the UTXO set, and tell the accounts
+    /// watching its destination and the hooks, as `update_storage` does for the outputs of
+    /// transactions. Returns the weight of the hooks.
+    pub(crate) fn create_utxo<T: Config>(
+        outpoint: H256,
+        output: &TransactionOutputFor<T>,
+    ) -> Weight {
+        for watcher in watchers::<T>(&output.destination) {
+            Pallet::<T>::deposit_event(Event::<T>::UtxoReceived(watcher, outpoint, output.value));
+        }
+        insert_utxo::<T>(outpoint, output, None);
+        T::UtxoHooks::on_new_utxo(&outpoint, output)
+    }
+
+    /// Take the UTXO at `outpoint` out of the UTXO set outside of a transaction, as
+    /// `update_storage` removes the UTXOs spent by transactions. Returns the UTXO and the weight
+    /// of the hooks.
+    pub(crate) fn take_utxo<T: Config>(
+        outpoint: H256,
+    ) -> Option<(TransactionOutputFor<T>, Weight)> {
+        let utxo = <UtxoStore<T>>::get(outpoint)?;
+        remove_utxo::<T>(outpoint, &utxo);
+        let weight = T::UtxoHooks::on_utxo_spent(&outpoint, &utxo);
+        Some((utxo, weight))
+    }
+
+    /// Add a UTXO to `UtxoCount`, `UtxoValue` and `TokenUtxoCount`
+    pub(crate) fn count_utxo<T: Config>(output: &TransactionOutputFor<T>, issued: Option<TokenId>) {
+        <UtxoCount<T>>::mutate(|count| *count = count.saturating_add(1));
+        <UtxoValue<T>>::mutate(|total| *total = total.saturating_add(output.value));
+        for token_id in held_tokens::<T>(output, issued) {
+            <TokenUtxoCount<T>>::mutate(token_id, |count| *count = count.saturating_add(1));
+        }
+    }
+
+    /// Remove a UTXO from `UtxoCount`, `UtxoValue` and `TokenUtxoCount`
+    pub(crate) fn uncount_utxo<T: Config>(
+        output: &TransactionOutputFor<T>,
+        issued: Option<TokenId>,
+    ) {
+        <UtxoCount<T>>::mutate(|count| *count = count.saturating_sub(1));
+        <UtxoValue<T>>::mutate(|total| *total = total.saturating_sub(output.value));
+        for token_id in held_tokens::<T>(output, issued) {
+            <TokenUtxoCount<T>>::mutate_exists(token_id, |count| {
+                *count = count.map(|count| count.saturating_sub(1)).filter(|count| *count > 0)
+            });
+        }
+    }
+
+    /// `MltIssuance` once `value` more MLT is minted, failing if it would exceed `MaxMoney`
+    pub(crate) fn checked_issuance<T: Config>(value: Value) -> Result<Value, &'static str> {
+        <MltIssuance<T>>::get()
+            .checked_add(value)
+            .filter(|issuance| *issuance <= T::MaxMoney::get())
+            .ok_or("MLT issuance would exceed MaxMoney")
+    }
+
+    /// Check the tokens sent by an output against the transfer policies set by their issuers
+    pub(crate) fn check_transfer_policy<T: Config>(
+        output: &TransactionOutputFor<T>,
+    ) -> Result<(), &'static str> {
+        let tokens: Vec<(&TokenId, Value)> = match &output.data {
+            Some(OutputData::TokenTransferV1 { token_id, amount })
+            | Some(OutputData::TokenLockForBridgeV1 {
+                token_id, amount, ..
+            }) => vec![(token_id, *amount)],
+            Some(OutputData::TokenMultiV1(tokens)) => {
+                tokens.iter().map(|(token_id, amount)| (token_id, *amount)).collect()
+            }
+            _ => return Ok(()),
+        };
+        for (token_id, amount) in tokens {
+            match <TokenTransferPolicy<T>>::get(token_id) {
+                None => {}
+                Some(TransferPolicy::Whitelist(recipients)) => ensure!(
+                    matches!(output.destination, Destination::Pubkey(pubkey)
+                        if recipients.contains(&H256::from(pubkey))),
+                    "token recipient is not whitelisted"
+                ),
+                Some(TransferPolicy::Approver) => ensure!(
+                    T::TransferApprover::approve_transfer(token_id, &output.destination, amount),
+                    "token transfer is not approved"
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Pubkey the token was issued or the NFT minted to
+    fn token_issuer<T: Config>(token_id: &TokenId) -> Option<H256> {
+        let tx = <TokenIssuanceTransactions<T>>::get(token_id)?;
+        tx.outputs.iter().find_map(|output| match (&output.data, &output.destination) {
+            (Some(data @ OutputData::TokenIssuanceV1 { .. }), Destination::Pubkey(pubkey))
+            | (Some(data @ OutputData::NftMintV1 { .. }), Destination::Pubkey(pubkey))
+                if data.id(&tx.inputs[0]).as_ref() == Some(token_id) =>
+            {
+                Some(H256::from(*pubkey))
+            }
+            _ => None,
+        })
+    }
+
+    /// Digital data hash of the NFT with given id, `None` for fungible tokens
+    pub(crate) fn nft_data_hash<T: Config>(token_id: &TokenId) -> Option<NftDataHash> {
+        <TokenIssuanceTransactions<T>>::get(token_id)?
+            .outputs
+            .into_iter()
+            .find_map(|output| match output.data {
+                Some(OutputData::NftMintV1 {
+                    token_id: id,
+                    data_hash,
+                    ..
+                }) if id == *token_id => Some(data_hash),
+                _ => None,
+            })
+    }
+
+    /// Token id of the NFT carried by `output`, if any
+    pub(crate) fn nft_id<T: Config>(output: &TransactionOutputFor<T>) -> Option<TokenId> {
+        match &output.data {
+            Some(OutputData::NftMintV1 { token_id, .. }) => Some(token_id.clone()),
+            Some(OutputData::TokenTransferV1 { token_id, .. })
+                if nft_data_hash::<T>(token_id).is_some() =>
+            {
+                Some(token_id.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Record a transfer of the NFT `token_id` in `NftProvenance`, dropping the oldest ones
+    /// beyond `NftProvenanceDepth`
+    pub(crate) fn record_nft_transfer<T: Config>(
+        token_id: &TokenId,
+        from: Destination<T::AccountId>,
+        to: Destination<T::AccountId>,
+    ) {
+        let depth = T::NftProvenanceDepth::get() as usize;
+        if depth == 0 || from == to {
+            return;
+        }
+        let block_number = <frame_system::Pallet<T>>::block_number();
+        <NftProvenance<T>>::mutate(token_id, |history| {
+            history.push(NftTransfer {
+                block_number,
+                from,
+                to,
+            });
+            if history.len() > depth {
+                history.drain(..history.len() - depth);
+            }
+        });
+    }
+
+    /// Remove the spends that fell out of the STXO journal retention window
+    pub(crate) fn prune_spent_outputs<T: Config>(block_num: T::BlockNumber) {
+        let retention = T::StxoRetention::get();
+        if block_num < retention {
+            return;
+        }
+        for outpoint in <SpentInBlock<T>>::take(block_num - retention) {
+            <SpentOutputs<T>>::remove(outpoint);
+        }
+        <MissingInputs<T>>::remove(block_num - retention);
+    }
+
+    /// Record the inputs of a transaction in a block that spend outputs not in the UTXO set, and
+    /// report them as double spends when the STXO journal tells which transaction spent them.
+    fn record_missing_inputs<T: Config>(tx: &TransactionFor<T>) {
+        let block_number = <frame_system::Pallet<T>>::block_number();
+        let tx_hash = BlakeTwo256::hash_of(tx);
+        for input in tx.inputs.iter().filter(|input| spent_utxo::<T>(input).is_none()) {
+            let spent_by = <SpentOutputs<T>>::get(input.outpoint);
+            match spent_by {
+                Some((spent_by, _)) => Pallet::<T>::deposit_event(Event::<T>::DoubleSpendDetected(
+                    input.outpoint,
+                    tx_hash,
+                    spent_by,
+                )),
+                None => {
+                    Pallet::<T>::deposit_event(Event::<T>::MissingInput(input.outpoint, tx_hash))
+                }
+            }
+            <MissingInputs<T>>::append(
+                block_number,
+                MissingInputProof {
+                    tx_hash,
+                    outpoint: input.outpoint,
+                    spent_by,
+                },
+            );
+        }
+    }
+
+    /// The UTXO spent by `input`. The body of a pruned UTXO is taken from the `lock` of the input,
+    /// provided it matches the commitment in `PrunedUtxos`.
+    pub(crate) fn spent_utxo<T: Config>(
+        input: &TransactionInput,
+    ) -> Option<TransactionOutputFor<T>> {
+        if let Some(utxo) = <UtxoStore<T>>::get(&input.outpoint) {
+            return Some(utxo);
+        }
+        let commitment = <PrunedUtxos<T>>::get(&input.outpoint)?;
+        TransactionOutputFor::<T>::decode(&mut &input.lock[..])
+            .ok()
+            .filter(|utxo| BlakeTwo256::hash_of(utxo) == commitment)
+    }
+
+    /// Only plain pay-to-pubkey UTXOs are pruned. Their `lock` is otherwise unused, and the token
+    /// and staking records keep pointing at UTXOs whose body stays in the state.
+    #[cfg(feature = "pruning")]
+    fn is_prunable<AccountId>(output: &TransactionOutput<AccountId>) -> bool {
+        matches!(output.destination, Destination::Pubkey(_)) && output.data.is_none()
+    }
+
+    /// Replace the bodies of the prunable UTXOs created `PruningDepth` blocks ago by their hash
+    #[cfg(feature = "pruning")]
+    pub(crate) fn prune_utxo_bodies<T: Config>(block_num: T::BlockNumber) {
+        let depth = T::PruningDepth::get();
+        if depth.is_zero() || block_num < depth {
+            return;
+        }
+        for outpoint in <CreatedInBlock<T>>::take(block_num - depth) {
+            // UTXOs spent in the meantime are gone already
+            if let Some(utxo) = <UtxoStore<T>>::take(outpoint) {
+                <PrunedUtxos<T>>::insert(outpoint, BlakeTwo256::hash_of(&utxo));
+                <PrunedValue<T>>::mutate(|total| *total = total.saturating_add(utxo.value));
+            }
+        }
+    }
+
+    /// Start a balance snapshot at `block_num`, unless one is still being taken or was taken
+    /// at that block. Return whether it was started.
+    pub(crate) fn start_balance_snapshot<T: Config>(block_num: T::BlockNumber) -> bool {
+        if <PendingBalanceSnapshot<T>>::exists()
+            || <BalanceSnapshotBlocks<T>>::get().last() == Some(&block_num)
+        {
+            return false;
+        }
+        <PendingBalanceSnapshot<T>>::put((block_num, None::<H256>));
+        true
+    }
+
+    /// Go on with the balance snapshot being taken, and the pruning of the stale ones, for up to
+    /// `SnapshotBatchSize` storage entries. The UTXO set is scanned in outpoint order, adding
+    /// the pay-to-pubkey UTXOs created by the block of the snapshot to the balance of their
+    /// pubkey. The UTXO set changes while the scan goes on, so UTXOs spent after the snapshot
+    /// block but before being scanned aren't counted. Once the scan ends the snapshot is
+    /// recorded, and the oldest one is dropped if more than `SnapshotHistoryDepth` are kept.
+    /// Return the weight used.
+    pub(crate) fn continue_balance_snapshot<T: Config>() -> Weight {
+        let mut budget = T::SnapshotBatchSize::get().max(1) as usize;
+        let mut weight = T::DbWeight::get().reads(2);
+
+        let mut stale = <StaleBalanceSnapshots<T>>::get();
+        let stale_count = stale.len();
+        while let Some(block_num) = stale.first().copied() {
+            if budget == 0 {
+                break;
+            }
+            let pubkeys: Vec<H256> =
+                <BalanceSnapshots<T>>::iter_key_prefix(block_num).take(budget).collect();
+            weight = weight.saturating_add(
+                T::DbWeight::get().reads_writes(pubkeys.len() as Weight, pubkeys.len() as Weight),
+            );
+            if pubkeys.len() < budget {
+                stale.remove(0);
+            }
+            budget -= pubkeys.len();
+            for pubkey in pubkeys {
+                <BalanceSnapshots<T>>::remove(block_num, pubkey);
+            }
+        }
+        if stale.len() != stale_count {
+            <StaleBalanceSnapshots<T>>::put(stale);
+            weight = weight.saturating_add(T::DbWeight::get().writes(1));
+        }
+
+        let (block_num, cursor) = match <PendingBalanceSnapshot<T>>::get() {
+            Some(pending) => pending,
+            None => return weight,
+        };
+        let utxos = match cursor {
+            Some(cursor) => <UtxoStore<T>>::iter_from(<UtxoStore<T>>::hashed_key_for(cursor)),
+            None => <UtxoStore<T>>::iter(),
+        };
+        let mut scanned = 0;
+        let mut last = cursor;
+        for (outpoint, utxo) in utxos.take(budget) {
+            scanned += 1;
+            last = Some(outpoint);
+            let created = <UtxoCreationTime<T>>::get(outpoint).map(|(created, _)| created);
+            match utxo.destination {
+                Destination::Pubkey(pubkey) if created.map_or(true, |c| c <= block_num) => {
+                    <BalanceSnapshots<T>>::mutate(block_num, H256::from(pubkey), |balance| {
+                        *balance = balance.saturating_add(utxo.value)
+                    });
+                    weight = weight.saturating_add(T::DbWeight::get().reads_writes(3, 1));
+                }
+                _ => weight = weight.saturating_add(T::DbWeight::get().reads(2)),
+            }
+        }
+        if scanned == budget {
+            <PendingBalanceSnapshot<T>>::put((block_num, last));
+            return weight;
+        }
+
+        <PendingBalanceSnapshot<T>>::kill();
+        let mut snapshot_blocks = <BalanceSnapshotBlocks<T>>::get();
+        snapshot_blocks.push(block_num);
+        let depth = T::SnapshotHistoryDepth::get().max(1) as usize;
+        if snapshot_blocks.len() > depth {
+            let dropped = snapshot_blocks.len() - depth;
+            <StaleBalanceSnapshots<T>>::mutate(|stale| {
+                stale.extend(snapshot_blocks.drain(..dropped))
+            });
+        }
+        <BalanceSnapshotBlocks<T>>::put(snapshot_blocks);
+        Pallet::<T>::deposit_event(Event::<T>::BalanceSnapshotTaken(block_num));
+        weight.saturating_add(T::DbWeight::get().reads_writes(2, 3))
+    }
+
+    /// Check the invariants of the ledger:
+    /// * the MLT in `UtxoStore`, `PrunedValue`, `LockedUtxos`, `PendingRewards` and `RewardTotal`
+    ///   adds up to `MltIssuance`, which doesn't exceed `MaxMoney`
+    /// * every issued token is still held by a UTXO or the bridge custody, or some of it has been
+    ///   burned
+    /// * `TokenUtxosByOwner` only lists UTXOs in `UtxoStore`
+    /// * `TokenHolders` and `TokenHolderCount` add up `TokenUtxosByOwner`
+    /// * `UtxoCount`, `UtxoValue` and `TokenUtxoCount` match the UTXO set
+    /// * `StakingCount` matches the locked utxos of every stash account
+    #[cfg(any(feature = "try-runtime", test))]
+    pub(crate) fn try_state<T: Config>() -> Result<(), &'static str> {
+        let mut mlt_total = <RewardTotal<T>>::get()
+            .checked_add(<PrunedValue<T>>::get())
+            .ok_or("MLT total overflow")?;
+        let mut held_tokens: BTreeSet<TokenId> = BTreeSet::new();
+        for (hash, utxo) in <UtxoStore<T>>::iter() {
+            mlt_total = mlt_total.checked_add(utxo.value).ok_or("MLT total overflow")?;
+            match utxo.data {
+                Some(OutputData::TokenIssuanceV1 { .. }) => {
+                    held_tokens.extend(<TokenIssuanceId<T>>::get(hash))
+                }
+                Some(OutputData::TokenTransferV1 { token_id, .. })
+                | Some(OutputData::TokenBurnV1 { token_id, .. })
+                | Some(OutputData::NftMintV1 { token_id, .. }) => {
+                    held_tokens.insert(token_id);
+                }
+                Some(OutputData::TokenMultiV1(tokens)) => {
+                    held_tokens.extend(tokens.into_iter().map(|(token_id, _)| token_id))
+                }
+                Some(OutputData::TokenLockForBridgeV1 { .. }) => {
+                    frame_support::fail!("tokens locked for the bridge are in the utxo set")
+                }
+                None => {}
+            }
+        }
+        held_tokens.extend(<TokenRewardTotal<T>>::iter_keys());
+        for (_, utxo) in <PendingRewards<T>>::get() {
+            mlt_total = mlt_total.checked_add(utxo.value).ok_or("MLT total overflow")?;
+            if let Some(OutputData::TokenTransferV1 { token_id, .. }) = utxo.data {
+                held_tokens.insert(token_id);
+            }
+        }
+        for utxo in <BridgeCustody<T>>::iter_values() {
+            if let Some(OutputData::TokenLockForBridgeV1 { token_id, .. }) = utxo.data {
+                held_tokens.insert(token_id);
+            }
+        }
+        for utxo in <LockedUtxos<T>>::iter_values() {
+            mlt_total = mlt_total.checked_add(utxo.value).ok_or("MLT total overflow")?;
+        }
+        ensure!(
+            mlt_total == <MltIssuance<T>>::get(),
+            "MLT in storage doesn't add up to the issuance"
+        );
+        ensure!(
+            mlt_total <= T::MaxMoney::get(),
+            "MLT issuance exceeds MaxMoney"
+        );
+
+        for token_id in <TokenIssuanceTransactions<T>>::iter_keys() {
+            ensure!(
+                held_tokens.contains(&token_id) || !<TotalBurned<T>>::get(&token_id).is_zero(),
+                "issued token is neither held nor burned"
+            );
+        }
+        for outpoint in <TokenUtxosByOwner<T>>::iter_keys().map(|(_, outpoint)| outpoint) {
+            ensure!(
+                <UtxoStore<T>>::contains_key(outpoint),
+                "token owner index lists a spent utxo"
+            );
+        }
+        let mut holders: BTreeMap<(TokenId, H256), Value> = BTreeMap::new();
+        for ((owner, token_id), _, amount) in <TokenUtxosByOwner<T>>::iter() {
+            let balance = holders.entry((token_id, owner)).or_insert(0);
+            *balance = balance.checked_add(amount).ok_or("token balance overflow")?;
+        }
+        holders.retain(|_, balance| !balance.is_zero());
+        let mut holder_count: BTreeMap<TokenId, u32> = BTreeMap::new();
+        for (token_id, _) in holders.keys() {
+            *holder_count.entry(token_id.clone()).or_insert(0) += 1;
+        }
+        ensure!(
+            holders
+                == <TokenHolders<T>>::iter()
+                    .map(|(token_id, owner, balance)| ((token_id, owner), balance))
+                    .collect::<BTreeMap<_, _>>()
+                && holder_count == <TokenHolderCount<T>>::iter().collect::<BTreeMap<_, _>>(),
+            "token holders don't match the token owner index"
+        );
+        for (contract, outpoint, value) in <ContractUtxos<T>>::iter() {
+            let utxo = <UtxoStore<T>>::get(outpoint);
+            ensure!(
+                matches!(utxo, Some(TransactionOutput {
+                    value: utxo_value,
+                    destination: Destination::CallPP(ref dest, ..),
+                    ..
+                }) if *dest == contract && utxo_value == value),
+                "contract utxo index doesn't match the utxo set"
+            );
+        }
+        for (txid, index, outpoint) in <OutpointsByTxid<T>>::iter() {
+            ensure!(
+                <OutputRefs<T>>::get(outpoint) == Some((txid, index)),
+                "txid index doesn't match its reverse"
+            );
+            ensure!(
+                <UtxoStore<T>>::contains_key(outpoint) || <PrunedUtxos<T>>::contains_key(outpoint),
+                "txid index lists a spent output"
+            );
+        }
+        ensure!(
+            <OutputRefs<T>>::iter_keys().count() == <OutpointsByTxid<T>>::iter_keys().count(),
+            "txid index doesn't match its reverse"
+        );
+
+        let mut utxo_count = <PrunedUtxos<T>>::iter_keys().count() as u64;
+        let mut utxo_value = <PrunedValue<T>>::get();
+        let mut token_utxo_count: BTreeMap<TokenId, u64> = BTreeMap::new();
+        for (hash, utxo) in <UtxoStore<T>>::iter() {
+            utxo_count += 1;
+            utxo_value = utxo_value.checked_add(utxo.value).ok_or("MLT total overflow")?;
+            for token_id in held_tokens::<T>(&utxo, <TokenIssuanceId<T>>::get(hash)) {
+                *token_utxo_count.entry(token_id).or_insert(0) += 1;
+            }
+        }
+        ensure!(
+            utxo_count == <UtxoCount<T>>::get()
+                && utxo_value == <UtxoValue<T>>::get()
+                && token_utxo_count == <TokenUtxoCount<T>>::iter().collect(),
+            "UTXO set statistics don't match the UTXO set"
+        );
+
+        staking::check_staking_count::<T>()
+    }
+
+    /// NFTs minted into `collection` by `mint_nft`
+    pub fn nft_collection<T: Config>(collection: H256) -> impl Iterator<Item = TokenId> {
+        <NftCollections<T>>::iter_key_prefix(collection)
+    }
+
+    /// Accounts watching `destination`
+    pub(crate) fn watchers<T: Config>(
+        destination: &Destination<T::AccountId>,
+    ) -> impl Iterator<Item = T::AccountId> {
+        <WatchedDestinations<T>>::iter_key_prefix(BlakeTwo256::hash_of(destination))
+    }
+
+    pub fn spend<T: Config>(
+        caller: &T::AccountId,
+        tx: &TransactionFor<T>,
+    ) -> DispatchResultWithPostInfo {
+        let (tx_validity, _, fees) =
+            check_transaction::<T>(tx, ScriptRules::Consensus, TransactionSource::InBlock)?;
+        if !tx_validity.requires.is_empty() {
+            // Nothing has been changed yet, so the record is kept although the spend fails
+            record_missing_inputs::<T>(tx);
+            return Err(DispatchError::Other("missing inputs").into());
+        }
+        update_storage::<T>(caller, tx, &fees)?;
+        Ok(().into())
+    }
+
+    /// Decode a bech32 address into the destination it encodes
+    pub fn decode_address<AccountId: Decode>(
+        address: &[u8],
+    ) -> Result<Destination<AccountId>, &'static str> {
+        let (_, data, _) = bech32::decode(&address.to_vec()).map_err(|e| match e {
+            bech32::Error::InvalidLength => "Failed to decode address: invalid length",
+            bech32::Error::InvalidChar(_) => "Failed to decode address: invalid character",
+            bech32::Error::MixedCase => "Failed to decode address: mixed case",
+            bech32::Error::InvalidChecksum => "Failed to decode address: invalid checksum",
+            bech32::Error::InvalidHrp => "Failed to decode address: invalid HRP",
+            _ => "Failed to decode address",
+        })?;
+
+        Destination::decode(&mut &data[..])
+            .map_err(|_| "Failed to decode buffer into `Destination`")
+    }
+
+    /// Pick the UTXOs of `caller` from UtxoStore that satisfy request `value`
+    ///
+    /// UTXOs are picked in ascending outpoint order, the iteration order of `UtxoStore`, so every
+    /// node picks the same ones. Outpoints reserved by an earlier send in the block are skipped,
+    /// the picked ones are reserved in turn.
+    /// Return a list of UTXOs that satisfy the request
+    /// Return empty vector if caller doesn't have enough UTXO
+    ///
+    // NOTE: limitation here is that this is only able to pick `Destination::Pubkey`
+    // UTXOs because the ownership of those can be easily determined.
+    // TODO: keep track of "our" UTXO separately?
+    pub fn pick_utxo<T: Config>(
+        caller: &T::AccountId,
+        value: Value,
+    ) -> (Value, Vec<H256>, Vec<TransactionOutputFor<T>>) {
+        let mut utxos = Vec::new();
+        let mut hashes = Vec::new();
+        let mut total = 0;
+        let mut reserved = <ReservedOutpoints<T>>::get();
+
+        for (hash, utxo) in UtxoStore::<T>::iter() {
+            if reserved.contains(&hash) {
+                continue;
+            }
+            match utxo.destination {
+                Destination::Pubkey(pubkey) => {
+                    if caller.encode() == pubkey.encode() {
+                        total += utxo.value;
+                        hashes.push(hash);
+                        utxos.push(utxo);
+
+                        if total >= value {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if total >= value {
+            reserved.extend_from_slice(&hashes);
+            <ReservedOutpoints<T>>::put(reserved);
+        }
+        (total, hashes, utxos)
+    }
+
+    /// Pick up to `max_inputs` of the smallest UTXOs of `caller` holding only MLT, to be
+    /// consolidated
+    ///
+    /// Ties are broken by outpoint, so every node picks the same ones. Outpoints reserved by an
+    /// earlier send in the block are skipped, the picked ones are reserved in turn if there are
+    /// at least two of them.
+    /// Return the total value of the picked UTXOs, their hashes and the UTXOs themselves
+    ///
+    // NOTE: same limitation as `pick_utxo`, only `Destination::Pubkey` UTXOs are picked.
+    pub fn pick_small_utxos<T: Config>(
+        caller: &T::AccountId,
+        max_inputs: u32,
+    ) -> (Value, Vec<H256>, Vec<TransactionOutputFor<T>>) {
+        let mut reserved = <ReservedOutpoints<T>>::get();
+        let mut picked: Vec<(H256, TransactionOutputFor<T>)> = UtxoStore::<T>::iter()
+            .filter(|(hash, utxo)| {
+                utxo.data.is_none()
+                    && matches!(utxo.destination, Destination::Pubkey(pubkey)
+                        if caller.encode() == pubkey.encode())
+                    && !reserved.contains(hash)
+            })
+            .collect();
+        picked.sort_by_key(|(hash, utxo)| (utxo.value, *hash));
+        picked.truncate(max_inputs as usize);
+
+        let total = picked.iter().fold(0, |total: Value, (_, utxo)| {
+            total.saturating_add(utxo.value)
+        });
+        let (hashes, utxos): (Vec<H256>, Vec<TransactionOutputFor<T>>) = picked.into_iter().unzip();
+        if hashes.len() >= 2 {
+            reserved.extend_from_slice(&hashes);
+            <ReservedOutpoints<T>>::put(reserved);
+        }
+        (total, hashes, utxos)
+    }
+
+    /// Pick the UTXOs of `caller` from UtxoStore that hold at least `amount` of `token_id`
+    ///
+    /// UTXOs are picked in ascending outpoint order, like in `pick_utxo`.
+    /// Return the amount of the token in picked UTXOs, their hashes and the UTXOs themselves
+    /// Return less than `amount` if caller doesn't have enough of the token
+    ///
+    // NOTE: same limitation as `pick_utxo`, only `Destination::Pubkey` UTXOs are picked.
+    // Multi-token UTXOs aren't picked either, spending them would move the other tokens too.
+    pub fn pick_token_utxo<T: Config>(
+        caller: &T::AccountId,
+        token_id: &TokenId,
+        amount: Value,
+    ) -> (Value, Vec<H256>, Vec<TransactionOutputFor<T>>) {
+        let mut utxos = Vec::new();
+        let mut hashes = Vec::new();
+        let mut total: Value = 0;
+
+        for (hash, utxo) in UtxoStore::<T>::iter() {
+            let token_amount = match &utxo.data {
+                Some(OutputData::TokenTransferV1 {
+                    token_id: id,
+                    amount,
+                }) if id == token_id => *amount,
+                Some(OutputData::TokenIssuanceV1 {
+                    amount_to_issue, ..
+                }) if <TokenIssuanceId<T>>::get(hash).as_ref() == Some(token_id) => {
+                    *amount_to_issue
+                }
+                Some(OutputData::NftMintV1 { token_id: id, .. }) if id == token_id => 1,
+                _ => continue,
+            };
+            match utxo.destination {
+                Destination::Pubkey(pubkey) if caller.encode() == pubkey.encode() => {
+                    total = total.saturating_add(token_amount);
+                    hashes.push(hash);
+                    utxos.push(utxo);
+
+                    if total >= amount {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        (total, hashes, utxos)
+    }
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {
+        #[pallet::weight(tx.estimated_weight::<T>())]
+        pub fn spend(
+            origin: OriginFor<T>,
+            tx: Transaction<T::AccountId>,
+        ) -> DispatchResultWithPostInfo {
+            spend::<T>(&ensure_signed(origin)?, &tx)?;
+            Self::deposit_event(Event::<T>::TransactionSuccess(tx));
+            Ok(().into())
+        }
+
+        #[pallet::weight(<T as Config>::WeightInfo::send_to_address(16_u32.saturating_add(address.len() as u32)))]
+        pub fn send_to_address(
+            origin: OriginFor<T>,
+            value: Value,
+            address: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            let dest = crate::identity::resolve_destination::<T>(&address)?;
+            ensure!(value > 0, "Value transferred must be larger than zero");
+
+            let signer = ensure_signed(origin)?;
+            let (total, hashes, utxos) = pick_utxo::<T>(&signer, value);
+
+            ensure!(total >= value, "Caller doesn't have enough UTXOs");
+
+            let mut inputs: Vec<TransactionInput> = Vec::new();
+            for utxo in hashes.iter() {
+                inputs.push(TransactionInput::new_empty(*utxo));
+            }
+
+            let pubkey_raw: [u8; 32] = signer
+                .encode()
+                .try_into()
+                .map_err(|_| DispatchError::Other("Failed to get caller's public key"))?;
+
+            let mut tx = Transaction {
+                inputs,
+                outputs: vec![
+                    TransactionOutput {
+                        value,
+                        destination: dest,
+                        // todo: We need to check what kind of token over here
+                        data: None,
+                    },
+                    TransactionOutput::new_pubkey(total - value, H256::from(pubkey_raw)),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            };
+
+            for i in 0..tx.inputs.len() {
+                tx = tx
+                    .sign_for_chain(&utxos, i, &signing_key::<T>(pubkey_raw)?, T::ChainId::get())
+                    .ok_or(DispatchError::Other("Failed to sign the transaction"))?;
+            }
+
+            spend::<T>(&signer, &tx)
+        }
+
+        /// Pay several recipients given by their bech32 addresses in a single transaction, with
+        /// one change output back to the caller. A `PaymentSent` event is emitted per recipient.
+        #[pallet::weight(<T as Config>::WeightInfo::send_to_addresses(payments.len() as u32))]
+        pub fn send_to_addresses(
+            origin: OriginFor<T>,
+            payments: Vec<(Value, Vec<u8>)>,
+        ) -> DispatchResultWithPostInfo {
+            ensure!(!payments.is_empty(), "No payments given");
+            let mut outputs = Vec::new();
+            let mut value: Value = 0;
+            for (amount, address) in &payments {
+                ensure!(*amount > 0, "Value transferred must be larger than zero");
+                value = value.checked_add(*amount).ok_or("Value transferred overflow")?;
+                outputs.push(TransactionOutput {
+                    value: *amount,
+                    destination: crate::identity::resolve_destination::<T>(address)?,
+                    data: None,
+                });
+            }
+
+            let signer = ensure_signed(origin)?;
+            let (total, hashes, utxos) = pick_utxo::<T>(&signer, value);
+            ensure!(total >= value, "Caller doesn't have enough UTXOs");
+
+            let pubkey_raw: [u8; 32] = signer
+                .encode()
+                .try_into()
+                .map_err(|_| DispatchError::Other("Failed to get caller's public key"))?;
+            if total > value {
+                outputs.push(TransactionOutput::new_pubkey(
+                    total - value,
+                    H256::from(pubkey_raw),
+                ));
+            }
+
+            let mut tx = Transaction {
+                inputs: hashes.into_iter().map(TransactionInput::new_empty).collect(),
+                outputs,
+                time_lock: Default::default(),
+                expires_at: None,
+            };
+            for i in 0..tx.inputs.len() {
+                tx = tx
+                    .sign_for_chain(&utxos, i, &signing_key::<T>(pubkey_raw)?, T::ChainId::get())
+                    .ok_or(DispatchError::Other("Failed to sign the transaction"))?;
+            }
+
+            spend::<T>(&signer, &tx)?;
+            for (index, output) in tx.outputs.iter().take(payments.len()).enumerate() {
+                Self::deposit_event(Event::<T>::PaymentSent(
+                    output.destination.clone(),
+                    tx.outpoint(index as u64),
+                    output.value,
+                ));
+            }
+            Ok(().into())
+        }
+
+        /// Merge up to `max_inputs` of the caller's smallest pay-to-pubkey UTXOs holding only MLT
+        /// into one UTXO, to shrink the UTXO set. At most `MaxConsolidationInputs` UTXOs are
+        /// merged. No fee is taken from the merged value, and the weight of the UTXOs not merged
+        /// is refunded.
+        #[pallet::weight(<T as Config>::WeightInfo::consolidate(
+            (*max_inputs).min(T::MaxConsolidationInputs::get())
+        ))]
+        pub fn consolidate(origin: OriginFor<T>, max_inputs: u32) -> DispatchResultWithPostInfo {
+            let signer = ensure_signed(origin)?;
+            let max_inputs = max_inputs.min(T::MaxConsolidationInputs::get());
+            ensure!(
+                max_inputs >= 2,
+                "at least two utxos have to be consolidated"
+            );
+            let (total, hashes, utxos) = pick_small_utxos::<T>(&signer, max_inputs);
+            ensure!(
+                hashes.len() >= 2,
+                "Caller doesn't have enough UTXOs to consolidate"
+            );
+
+            let pubkey_raw: [u8; 32] = signer
+                .encode()
+                .try_into()
+                .map_err(|_| DispatchError::Other("Failed to get caller's public key"))?;
+            let mut tx = Transaction {
+                inputs: hashes.into_iter().map(TransactionInput::new_empty).collect(),
+                outputs: vec![TransactionOutput::new_pubkey(total, H256::from(pubkey_raw))],
+                time_lock: Default::default(),
+                expires_at: None,
+            };
+            for i in 0..tx.inputs.len() {
+                tx = tx
+                    .sign_for_chain(&utxos, i, &signing_key::<T>(pubkey_raw)?, T::ChainId::get())
+                    .ok_or(DispatchError::Other("Failed to sign the transaction"))?;
+            }
+
+            spend::<T>(&signer, &tx)?;
+            let merged = tx.inputs.len() as u32;
+            Self::deposit_event(Event::<T>::UtxosConsolidated(
+                signer,
+                merged,
+                tx.outpoint(0),
+            ));
+            Ok(Some(<T as Config>::WeightInfo::consolidate(merged)).into())
+        }
+
+        /// Burn `amount` of the caller's `token_id` tokens. Left over tokens and MLT
+        /// of the spent UTXOs are returned to the caller.
+        #[pallet::weight(<T as Config>::WeightInfo::burn(1 as u32))]
+        pub fn burn(
+            origin: OriginFor<T>,
+            token_id: TokenId,
+            amount: Value,
+        ) -> DispatchResultWithPostInfo {
+            ensure!(amount > 0, "Value burned must be larger than zero");
+
+            let signer = ensure_signed(origin)?;
+            let (total, hashes, utxos) = pick_token_utxo::<T>(&signer, &token_id, amount);
+            ensure!(total >= amount, "Caller doesn't have enough tokens");
+
+            let pubkey_raw: [u8; 32] = signer
+                .encode()
+                .try_into()
+                .map_err(|_| DispatchError::Other("Failed to get caller's public key"))?;
+            let mlt = utxos
+                .iter()
+                .try_fold(0, |total: Value, utxo| total.checked_add(utxo.value))
+                .ok_or("input value overflow")?;
+
+            // The burn output carries its storage deposit out of the MLT of the spent UTXOs
+            let mut burned = TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(pubkey_raw),
+                OutputData::TokenBurnV1 {
+                    token_id: token_id.clone(),
+                    amount_to_burn: amount,
+                },
+            );
+            burned.value = Self::storage_deposit(&burned);
+            let mlt = mlt
+                .checked_sub(burned.value)
+                .ok_or("Caller's token UTXOs don't cover the storage deposit")?;
+            let mut outputs = vec![burned];
+            if total > amount {
+                outputs.push(TransactionOutput::new_p2pk_with_data(
+                    mlt,
+                    H256::from(pubkey_raw),
+                    OutputData::TokenTransferV1 {
+                        token_id,
+                        amount: total - amount,
+                    },
+                ));
+            } else if mlt > 0 {
+                outputs.push(TransactionOutput::new_pubkey(mlt, H256::from(pubkey_raw)));
+            }
+
+            let mut tx = Transaction {
+                inputs: hashes.into_iter().map(TransactionInput::new_empty).collect(),
+                outputs,
+                time_lock: Default::default(),
+                expires_at: None,
+            };
+            for i in 0..tx.inputs.len() {
+                tx = tx
+                    .sign_for_chain(&utxos, i, &signing_key::<T>(pubkey_raw)?, T::ChainId::get())
+                    .ok_or(DispatchError::Other("Failed to sign the transaction"))?;
+            }
+
+            spend::<T>(&signer, &tx)
+        }
+
+        /// Issue `amount_to_issue` of a new token to the caller. The token id is derived from the
+        /// first UTXO spent, the issuance fee is paid from the caller's UTXOs and the rest of
+        /// their MLT is returned to the caller.
+        #[pallet::weight(<T as Config>::WeightInfo::token_create(1 as u32))]
+        pub fn issue_token(
+            origin: OriginFor<T>,
+            token_ticker: Vec<u8>,
+            amount_to_issue: Value,
+            number_of_decimals: u8,
+            metadata_uri: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            let signer = ensure_signed(origin)?;
+            let pubkey_raw: [u8; 32] = signer
+                .encode()
+                .try_into()
+                .map_err(|_| DispatchError::Other("Failed to get caller's public key"))?;
+            let mut issuance = TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(pubkey_raw),
+                OutputData::TokenIssuanceV1 {
+                    token_ticker,
+                    amount_to_issue,
+                    number_of_decimals,
+                    metadata_uri,
+                },
+            );
+            // The issuance output carries its storage deposit
+            issuance.value = Self::storage_deposit(&issuance);
+            let fee = T::TokenIssuanceFee::get().saturating_add(issuance.value);
+            let (total, hashes, utxos) = pick_utxo::<T>(&signer, fee);
+            ensure!(
+                total >= fee && !hashes.is_empty(),
+                "Caller doesn't have enough UTXOs"
+            );
+
+            let mut outputs = vec![issuance];
+            if total > fee {
+                outputs.push(TransactionOutput::new_pubkey(
+                    total - fee,
+                    H256::from(pubkey_raw),
+                ));
+            }
+
+            let mut tx = Transaction {
+                inputs: hashes.into_iter().map(TransactionInput::new_empty).collect(),
+                outputs,
+                time_lock: Default::default(),
+                expires_at: None,
+            };
+            for i in 0..tx.inputs.len() {
+                tx = tx
+                    .sign_for_chain(&utxos, i, &signing_key::<T>(pubkey_raw)?, T::ChainId::get())
+                    .ok_or(DispatchError::Other("Failed to sign the transaction"))?;
+            }
+
+            spend::<T>(&signer, &tx)?;
+            Self::deposit_event(Event::<T>::TokenIssued(
+                TokenId::new(&tx.inputs[0]),
+                signer,
+                amount_to_issue,
+            ));
+            Ok(().into())
+        }
+
+        /// Mint an NFT of the digital data `data_hash` to the caller, into `collection` if given.
+        /// The first NFT minted into a collection makes the caller its owner, only the owner can
+        /// mint into it later. The token id is derived from the first UTXO spent, the mint fee is
+        /// paid from the caller's UTXOs and the rest of their MLT is returned to the caller.
+        #[pallet::weight(<T as Config>::WeightInfo::mint_nft(1 as u32))]
+        pub fn mint_nft(
+            origin: OriginFor<T>,
+            collection: Option<H256>,
+            data_hash: NftDataHash,
+            metadata_uri: Vec<u8>,
+            royalty: Option<NftRoyalty>,
+        ) -> DispatchResultWithPostInfo {
+            let signer = ensure_signed(origin)?;
+            ensure!(
+                !<NftUniqueDataHash<T>>::contains_key(&data_hash),
+                "digital data has already been minted"
+            );
+            if let Some(owner) =
+                collection.and_then(|collection| <NftCollectionOwners<T>>::get(collection))
+            {
+                ensure!(owner == signer, "nft collection belongs to another account");
+            }
+
+            let pubkey_raw: [u8; 32] = signer
+                .encode()
+                .try_into()
+                .map_err(|_| DispatchError::Other("Failed to get caller's public key"))?;
+            let mint = |token_id: TokenId| {
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(pubkey_raw),
+                    OutputData::NftMintV1 {
+                        token_id,
+                        data_hash: data_hash.clone(),
+                        metadata_uri: metadata_uri.clone(),
+                        media_type: None,
+                        transferable: true,
+                        royalty: royalty.clone(),
+                    },
+                )
+            };
+            // The size of the mint output doesn't depend on the token id, known once the UTXOs
+            // paying for it are picked
+            let deposit = Self::storage_deposit(&mint(TokenId::from_outpoint(&H256::zero())));
+            let fee = T::NftMintFee::get().saturating_add(deposit);
+            let (total, hashes, utxos) = pick_utxo::<T>(&signer, fee);
+            ensure!(
+                total >= fee && !hashes.is_empty(),
+                "Caller doesn't have enough UTXOs"
+            );
+
+            let token_id = TokenId::from_outpoint(&hashes[0]);
+            let mut nft = mint(token_id.clone());
+            nft.value = deposit;
+            let mut outputs = vec![nft];
+            if total > fee {
+                outputs.push(TransactionOutput::new_pubkey(
+                    total - fee,
+                    H256::from(pubkey_raw),
+                ));
+            }
+
+            let mut tx = Transaction {
+                inputs: hashes.into_iter().map(TransactionInput::new_empty).collect(),
+                outputs,
+                time_lock: Default::default(),
+                expires_at: None,
+            };
+            for i in 0..tx.inputs.len() {
+                tx = tx
+                    .sign_for_chain(&utxos, i, &signing_key::<T>(pubkey_raw)?, T::ChainId::get())
+                    .ok_or(DispatchError::Other("Failed to sign the transaction"))?;
+            }
+
+            spend::<T>(&signer, &tx)?;
+            if let Some(collection) = collection {
+                if !<NftCollectionOwners<T>>::contains_key(collection) {
+                    <NftCollectionOwners<T>>::insert(collection, &signer);
+                }
+                <NftCollections<T>>::insert(collection, &token_id, ());
+            }
+            Self::deposit_event(Event::<T>::NftMinted(token_id, signer, collection));
+            Ok(().into())
+        }
+
+        /// unlock the stake using the STASH ACCOUNT. Stops validating, and allow access to withdraw.
+        /// If used with `pallet-staking`, it uses the `BondingDuration`
+        /// to set the period/era on when to withdraw.
+        /// A secondary controller with the `unlock` permission can call it too.
+        #[pallet::weight(<T as Config>::WeightInfo::unlock_request_for_withdrawal(1 as u32))]
+        pub fn unlock_request_for_withdrawal(
+            stash_origin: OriginFor<T>,
+        ) -> DispatchResultWithPostInfo {
+            let stash_account = staking::acting_stash_account::<T>(
+                ensure_signed(stash_origin)?,
+                staking::StakingOperation::Unlock,
+            )?;
+            staking::unlock_request_for_withdrawal::<T>(stash_account)
+        }
+
+        /// withdraw unlocked stake using the STASH ACCOUNT. Make sure the era for withdrawal has passed.
+        /// If used with `pallet-staking`,it can be found in the ledger of datatype `StakingLedger`,
+        /// the field `unlocking` of datatype `UnlockChunk`,
+        /// and at field `era`.
+        /// A secondary controller with the `withdraw` permission can call it too; the stake still
+        /// goes to the withdrawal key or the stash account.
+        #[pallet::weight(<T as Config>::WeightInfo::withdraw_stake(1 as u32))]
+        pub fn withdraw_stake(stash_origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+            let stash_account = staking::acting_stash_account::<T>(
+                ensure_signed(stash_origin)?,
+                staking::StakingOperation::Withdraw,
+            )?;
+            staking::withdraw::<T>(stash_account)
+        }
+
+        /// Move the stake to a new stash and controller account, using the STASH ACCOUNT.
+        /// Unlike unlocking and locking again, this skips the `BondingDuration` wait, e.g. to
+        /// migrate the validator to a new machine.
+        #[pallet::weight(<T as Config>::WeightInfo::redelegate_stake(1 as u32))]
+        pub fn redelegate_stake(
+            stash_origin: OriginFor<T>,
+            new_stash_account: T::AccountId,
+            new_controller_account: T::AccountId,
+            session_key: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            staking::redelegate::<T>(
+                ensure_signed(stash_origin)?,
+                new_stash_account,
+                new_controller_account,
+                session_key,
+            )
+        }
+
+        /// Set new session keys for the validator, using the STASH ACCOUNT.
+        /// Saves going through `pallet-session` with the controller account.
+        /// A secondary controller with the `rotate_session_keys` permission can call it too.
+        #[pallet::weight(<T as Config>::WeightInfo::rotate_session_keys(1 as u32))]
+        pub fn rotate_session_keys(
+            stash_origin: OriginFor<T>,
+            new_keys: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            let stash_account = staking::acting_stash_account::<T>(
+                ensure_signed(stash_origin)?,
+                staking::StakingOperation::RotateSessionKeys,
+            )?;
+            staking::rotate_session_keys::<T>(stash_account, new_keys)
+        }
+
+        /// Release the tokens locked under bridge custody at `outpoint` to `pubkey`, once they have
+        /// come back from the target chain. Only a relayer may call it, with a proof checked by
+        /// `Config::BridgeHandler`.
+        #[pallet::weight(<T as Config>::WeightInfo::release_from_bridge(1 as u32))]
+        pub fn release_from_bridge(
+            origin: OriginFor<T>,
+            outpoint: H256,
+            pubkey: H256,
+            proof: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            let relayer = ensure_signed(origin)?;
+            ensure!(
+                T::BridgeHandler::is_relayer(&relayer),
+                Error::<T>::NoPermission
+            );
+            let locked = <BridgeCustody<T>>::get(outpoint).ok_or("tokens are not locked")?;
+            ensure!(
+                T::BridgeHandler::verify_release(&outpoint, &pubkey, &proof),
+                "invalid bridge release proof"
+            );
+            let (token_id, amount) = match locked.data {
+                Some(OutputData::TokenLockForBridgeV1 {
+                    token_id, amount, ..
+                }) => (token_id, amount),
+                _ => frame_support::fail!("corrupted output data"),
+            };
+
+            let released = TransactionOutput::new_p2pk_with_data(
+                0,
+                pubkey,
+                OutputData::TokenTransferV1 {
+                    token_id: token_id.clone(),
+                    amount,
+                },
+            );
+            let hash = BlakeTwo256::hash_of(&(&outpoint, "bridge release"));
+            <BridgeCustody<T>>::remove(outpoint);
+            let hooks_weight = create_utxo::<T>(hash, &released);
+            <frame_system::Pallet<T>>::register_extra_weight_unchecked(
+                hooks_weight,
+                DispatchClass::Normal,
+            );
+            Self::deposit_event(Event::<T>::TokensReleasedFromBridge(token_id, amount, hash));
+            Ok(().into())
+        }
+
+        /// Set the policy restricting who can receive the token, `None` lifts the restriction.
+        /// Only the pubkey the token was issued to may call it.
+        #[pallet::weight(<T as Config>::WeightInfo::set_transfer_policy(1 as u32))]
+        pub fn set_transfer_policy(
+            origin: OriginFor<T>,
+            token_id: TokenId,
+            policy: Option<TransferPolicy>,
+        ) -> DispatchResultWithPostInfo {
+            let caller = convert_to_h256::<T>(&ensure_signed(origin)?)?;
+            let issuer = token_issuer::<T>(&token_id).ok_or("token has never been issued")?;
+            ensure!(caller == issuer, Error::<T>::NoPermission);
+            match &policy {
+                Some(policy) => <TokenTransferPolicy<T>>::insert(&token_id, policy),
+                None => <TokenTransferPolicy<T>>::remove(&token_id),
+            }
+            Self::deposit_event(Event::<T>::TransferPolicySet(token_id, policy));
+            Ok(().into())
+        }
+
+        /// Restrict the issuance of tokens and NFTs to the pubkeys of `TokenIssuers`, or open it
+        /// to anyone. `TokenIssuerOrigin` only.
+        #[pallet::weight(<T as Config>::WeightInfo::set_token_issuer(1 as u32))]
+        pub fn set_permissioned_issuance(
+            origin: OriginFor<T>,
+            permissioned: bool,
+        ) -> DispatchResultWithPostInfo {
+            T::TokenIssuerOrigin::ensure_origin(origin)?;
+            <PermissionedIssuance<T>>::put(permissioned);
+            Self::deposit_event(Event::<T>::PermissionedIssuanceSet(permissioned));
+            Ok(().into())
+        }
+
+        /// Allow `pubkey` to issue tokens and NFTs while the issuance is permissioned, or
+        /// disallow it. `TokenIssuerOrigin` only.
+        #[pallet::weight(<T as Config>::WeightInfo::set_token_issuer(1 as u32))]
+        pub fn set_token_issuer(
+            origin: OriginFor<T>,
+            pubkey: H256,
+            allowed: bool,
+        ) -> DispatchResultWithPostInfo {
+            T::TokenIssuerOrigin::ensure_origin(origin)?;
+            if allowed {
+                <TokenIssuers<T>>::insert(pubkey, ());
+            } else {
+                <TokenIssuers<T>>::remove(pubkey);
+            }
+            Self::deposit_event(Event::<T>::TokenIssuerSet(pubkey, allowed));
+            Ok(().into())
+        }
+
+        /// Halt the spends in `scope` in an emergency, or resume them all with `None`. Staking
+        /// withdrawals go on. `PauseOrigin` only.
+        #[pallet::weight(<T as Config>::WeightInfo::set_paused(1 as u32))]
+        pub fn set_paused(
+            origin: OriginFor<T>,
+            scope: Option<crate::pause::PauseScope>,
+        ) -> DispatchResultWithPostInfo {
+            T::PauseOrigin::ensure_origin(origin)?;
+            <Paused<T>>::set(scope);
+            Self::deposit_event(Event::<T>::PausedSet(scope));
+            Ok(().into())
+        }
+
+        /// Send the payments to the display name of the caller's identity to `destination`, or
+        /// stop resolving the name with `None`. A name already set by another account can only be
+        /// taken over once that account no longer holds an identity with the name.
+        #[pallet::weight(<T as Config>::WeightInfo::set_identity_destination(1 as u32))]
+        pub fn set_identity_destination(
+            origin: OriginFor<T>,
+            destination: Option<Destination<T::AccountId>>,
+        ) -> DispatchResultWithPostInfo {
+            let caller = ensure_signed(origin)?;
+            let name = T::Identities::display_name(&caller).ok_or("Caller has no identity")?;
+            if let Some((owner, _)) = <IdentityDestinations<T>>::get(&name) {
+                ensure!(
+                    owner == caller || crate::identity::identity_destination::<T>(&name).is_none(),
+                    "Identity name is registered by another account"
+                );
+            }
+            match &destination {
+                Some(destination) => {
+                    crate::identity::validate_identity_destination(destination)?;
+                    <IdentityDestinations<T>>::insert(&name, (caller.clone(), destination.clone()));
+                }
+                None => <IdentityDestinations<T>>::remove(&name),
             }
+            Self::deposit_event(Event::<T>::IdentityDestinationSet(
+                caller,
+                name,
+                destination,
+            ));
+            Ok(().into())
         }
 
-        Ok(().into())
-    }
-
-    pub fn spend<T: Config>(
-        caller: &T::AccountId,
-        tx: &TransactionFor<T>,
-    ) -> DispatchResultWithPostInfo {
-        let tx_validity = validate_transaction::<T>(tx)?;
-        ensure!(tx_validity.requires.is_empty(), "missing inputs");
-        update_storage::<T>(caller, tx, tx_validity.priority as Value)?;
-        Ok(().into())
-    }
+        /// Move `value` MLT from the free balance of the caller to a new UTXO paying to the
+        /// caller's pubkey.
+        #[pallet::weight(<T as Config>::WeightInfo::wrap(1 as u32))]
+        pub fn wrap(origin: OriginFor<T>, value: Value) -> DispatchResultWithPostInfo {
+            let caller = ensure_signed(origin)?;
+            let pubkey = convert_to_h256::<T>(&caller)?;
+            ensure!(value > 0, Error::<T>::BalanceZero);
+            let issuance = checked_issuance::<T>(value)?;
+            // the balance is burned and minted again as a UTXO
+            let _ = T::Currency::withdraw(
+                &caller,
+                value,
+                WithdrawReasons::TRANSFER,
+                ExistenceRequirement::KeepAlive,
+            )?;
 
-    /// Pick the UTXOs of `caller` from UtxoStore that satisfy request `value`
-    ///
-    /// Return a list of UTXOs that satisfy the request
-    /// Return empty vector if caller doesn't have enough UTXO
-    ///
-    // NOTE: limitation here is that this is only able to pick `Destination::Pubkey`
-    // UTXOs because the ownership of those can be easily determined.
-    // TODO: keep track of "our" UTXO separately?
-    pub fn pick_utxo<T: Config>(
-        caller: &T::AccountId,
-        value: Value,
-    ) -> (Value, Vec<H256>, Vec<TransactionOutputFor<T>>) {
-        let mut utxos = Vec::new();
-        let mut hashes = Vec::new();
-        let mut total = 0;
+            let nonce = <WrapNonce<T>>::mutate(|nonce| {
+                *nonce += 1;
+                *nonce
+            });
+            let hash = BlakeTwo256::hash_of(&(&pubkey, nonce, "wrap"));
+            let hooks_weight =
+                create_utxo::<T>(hash, &TransactionOutput::new_pubkey(value, pubkey));
+            <frame_system::Pallet<T>>::register_extra_weight_unchecked(
+                hooks_weight,
+                DispatchClass::Normal,
+            );
+            <MltIssuance<T>>::put(issuance);
+            Self::deposit_event(Event::<T>::Wrapped(caller, value, hash));
+            Ok(().into())
+        }
 
-        for (hash, utxo) in UtxoStore::<T>::iter() {
-            match utxo.destination {
-                Destination::Pubkey(pubkey) => {
-                    if caller.encode() == pubkey.encode() {
-                        total += utxo.value;
-                        hashes.push(hash);
-                        utxos.push(utxo);
+        /// Remove the given UTXOs paying MLT to the caller's pubkey and credit their value to the
+        /// free balance of the caller. The UTXOs are spent like the inputs of a transaction,
+        /// which the pause of all spends halts.
+        #[pallet::weight(<T as Config>::WeightInfo::unwrap(outpoints.len() as u32))]
+        pub fn unwrap(origin: OriginFor<T>, outpoints: Vec<H256>) -> DispatchResultWithPostInfo {
+            let caller = ensure_signed(origin)?;
+            let pubkey = convert_to_h256::<T>(&caller)?;
+            ensure!(!outpoints.is_empty(), "no utxos to unwrap");
+            crate::pause::check_mlt_spend_not_paused::<T>()?;
+
+            let mut value: Value = 0;
+            for (index, outpoint) in outpoints.iter().enumerate() {
+                ensure!(
+                    !outpoints[..index].contains(outpoint),
+                    "each utxo can be unwrapped only once"
+                );
+                let utxo = <UtxoStore<T>>::get(outpoint).ok_or("utxo doesn't exist")?;
+                ensure!(utxo.data.is_none(), "only MLT can be unwrapped");
+                ensure!(
+                    matches!(utxo.destination, Destination::Pubkey(owner)
+                        if H256::from(owner) == pubkey),
+                    Error::<T>::NoPermission
+                );
+                value = value.checked_add(utxo.value).ok_or("input value overflow")?;
+            }
 
-                        if total >= value {
-                            break;
-                        }
-                    }
+            // nothing is credited below the existential deposit, the UTXOs are kept then
+            let credited = T::Currency::deposit_creating(&caller, value);
+            ensure!(
+                credited.peek() == value,
+                "unwrapped value is below the existential deposit"
+            );
+            let block_number = <frame_system::Pallet<T>>::block_number();
+            let journal_spends = !T::StxoRetention::get().is_zero();
+            let unwrap_hash = BlakeTwo256::hash_of(&(&caller, &outpoints, "unwrap"));
+            let mut hooks_weight: Weight = 0;
+            for outpoint in &outpoints {
+                if let Some((_, weight)) = take_utxo::<T>(*outpoint) {
+                    hooks_weight = hooks_weight.saturating_add(weight);
+                }
+                if journal_spends {
+                    <SpentOutputs<T>>::insert(outpoint, (unwrap_hash, block_number));
+                    <SpentInBlock<T>>::append(block_number, outpoint);
                 }
-                _ => {}
             }
+            <frame_system::Pallet<T>>::register_extra_weight_unchecked(
+                hooks_weight,
+                DispatchClass::Normal,
+            );
+            <MltIssuance<T>>::mutate(|issuance| *issuance = issuance.saturating_sub(value));
+            Self::deposit_event(Event::<T>::Unwrapped(caller, value));
+            Ok(().into())
         }
 
-        (total, hashes, utxos)
-    }
-
-    #[pallet::call]
-    impl<T: Config> Pallet<T> {
-        #[pallet::weight(<T as Config>::WeightInfo::spend(tx.inputs.len().saturating_add(tx.outputs.len()) as u32))]
-        pub fn spend(
+        /// Set the Merkle root of the airdrop, or remove it to stop the claims. Root only.
+        #[pallet::weight(<T as Config>::WeightInfo::set_airdrop_root(1 as u32))]
+        pub fn set_airdrop_root(
             origin: OriginFor<T>,
-            tx: Transaction<T::AccountId>,
+            root: Option<H256>,
         ) -> DispatchResultWithPostInfo {
-            spend::<T>(&ensure_signed(origin)?, &tx)?;
-            Self::deposit_event(Event::<T>::TransactionSuccess(tx));
+            ensure_root(origin)?;
+            <AirdropRoot<T>>::set(root);
+            Self::deposit_event(Event::<T>::AirdropRootSet(root));
             Ok(().into())
         }
 
-        #[pallet::weight(<T as Config>::WeightInfo::send_to_address(16_u32.saturating_add(address.len() as u32)))]
-        pub fn send_to_address(
+        /// Claim the `value` MLT granted to the caller's pubkey by the airdrop, `proof` being the
+        /// Merkle proof of the grant. A new UTXO paying to the caller's pubkey is minted. Every
+        /// pubkey can claim once per airdrop.
+        #[pallet::weight(<T as Config>::WeightInfo::claim(proof.len() as u32))]
+        pub fn claim(
             origin: OriginFor<T>,
             value: Value,
-            address: Vec<u8>,
+            proof: Vec<H256>,
         ) -> DispatchResultWithPostInfo {
-            let (_, data, _) = bech32::decode(&address).map_err(|e| match e {
-                bech32::Error::InvalidLength => {
-                    DispatchError::Other("Failed to decode address: invalid length")
-                }
-                bech32::Error::InvalidChar(_) => {
-                    DispatchError::Other("Failed to decode address: invalid character")
-                }
-                bech32::Error::MixedCase => {
-                    DispatchError::Other("Failed to decode address: mixed case")
-                }
-                bech32::Error::InvalidChecksum => {
-                    DispatchError::Other("Failed to decode address: invalid checksum")
-                }
-                bech32::Error::InvalidHrp => {
-                    DispatchError::Other("Failed to decode address: invalid HRP")
-                }
-                _ => DispatchError::Other("Failed to decode address"),
-            })?;
+            let caller = ensure_signed(origin)?;
+            let pubkey = convert_to_h256::<T>(&caller)?;
+            let root = <AirdropRoot<T>>::get().ok_or("no airdrop to claim")?;
+            ensure!(
+                !<AirdropClaims<T>>::contains_key(root, pubkey),
+                "airdrop already claimed"
+            );
+            ensure!(
+                crate::airdrop::verify_claim(&root, &pubkey, value, &proof),
+                "invalid airdrop proof"
+            );
+            ensure!(value > 0, Error::<T>::BalanceZero);
+            let issuance = checked_issuance::<T>(value)?;
+
+            let hash = BlakeTwo256::hash_of(&(&pubkey, root, "airdrop"));
+            let hooks_weight =
+                create_utxo::<T>(hash, &TransactionOutput::new_pubkey(value, pubkey));
+            <frame_system::Pallet<T>>::register_extra_weight_unchecked(
+                hooks_weight,
+                DispatchClass::Normal,
+            );
+            <AirdropClaims<T>>::insert(root, pubkey, ());
+            <MltIssuance<T>>::put(issuance);
+            Self::deposit_event(Event::<T>::AirdropClaimed(caller, value, hash));
+            Ok(().into())
+        }
 
-            let dest: Destination<T::AccountId> = Destination::decode(&mut &data[..])
-                .map_err(|_| DispatchError::Other("Failed to decode buffer into `Destination`"))?;
-            ensure!(value > 0, "Value transferred must be larger than zero");
+        /// Start a balance snapshot of all pubkeys at the current block, on top of the ones
+        /// taken every `SnapshotPeriod` blocks. Like those, it is taken over the next blocks,
+        /// `SnapshotBatchSize` UTXOs at a time.
+        #[pallet::weight(<T as Config>::WeightInfo::take_balance_snapshot(1 as u32))]
+        pub fn take_balance_snapshot(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            let block_number = <frame_system::Pallet<T>>::block_number();
+            ensure!(
+                <BalanceSnapshotBlocks<T>>::get().last() != Some(&block_number),
+                "Balance snapshot already taken in this block"
+            );
+            ensure!(
+                start_balance_snapshot::<T>(block_number),
+                "Balance snapshot already being taken"
+            );
+            Ok(().into())
+        }
 
-            let signer = ensure_signed(origin)?;
-            let (total, hashes, utxos) = pick_utxo::<T>(&signer, value);
+        /// Leave the staking pool of the given stash account and get back the shares the caller
+        /// locked in it. Allowed only until the pool is bonded. The stake of a bonded pool is
+        /// returned to its members pro-rata once the stash account withdraws it.
+        #[pallet::weight(<T as Config>::WeightInfo::leave_staking_pool(1 as u32))]
+        pub fn leave_staking_pool(
+            origin: OriginFor<T>,
+            stash_account: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            let member = convert_to_h256::<T>(&ensure_signed(origin)?)?;
+            staking::pool::leave_pool::<T>(stash_account, sr25519::Public::from_h256(member))
+        }
 
-            ensure!(total >= value, "Caller doesn't have enough UTXOs");
+        /// Watch `destination` for incoming and outgoing UTXOs. The caller is notified by the
+        /// `UtxoReceived` and `UtxoSpent` events, which lets light wallets track funds without
+        /// scanning the UTXO set. The `WatchFee` is paid from the caller's UTXOs. A destination
+        /// can have at most `MaxWatchers` watchers.
+        #[pallet::weight(<T as Config>::WeightInfo::watch_destination(1 as u32))]
+        pub fn watch_destination(
+            origin: OriginFor<T>,
+            destination: Destination<T::AccountId>,
+        ) -> DispatchResultWithPostInfo {
+            let signer = ensure_signed(origin)?;
+            let destination_hash = BlakeTwo256::hash_of(&destination);
+            ensure!(
+                !<WatchedDestinations<T>>::contains_key(destination_hash, &signer),
+                "Destination already watched"
+            );
+            let watcher_count = <WatcherCount<T>>::get(destination_hash);
+            ensure!(
+                watcher_count < T::MaxWatchers::get(),
+                "Destination has too many watchers"
+            );
 
-            let mut inputs: Vec<TransactionInput> = Vec::new();
-            for utxo in hashes.iter() {
-                inputs.push(TransactionInput::new_empty(*utxo));
+            let fee = T::WatchFee::get();
+            if fee > 0 {
+                // A transaction needs an output, so the change must not be zero
+                let (total, hashes, utxos) = pick_utxo::<T>(&signer, fee.saturating_add(1));
+                ensure!(total > fee, "Caller doesn't have enough UTXOs");
+
+                let pubkey_raw: [u8; 32] = signer
+                    .encode()
+                    .try_into()
+                    .map_err(|_| DispatchError::Other("Failed to get caller's public key"))?;
+
+                let mut tx = Transaction {
+                    inputs: hashes.into_iter().map(TransactionInput::new_empty).collect(),
+                    outputs: vec![TransactionOutput::new_pubkey(
+                        total - fee,
+                        H256::from(pubkey_raw),
+                    )],
+                    time_lock: Default::default(),
+                    expires_at: None,
+                };
+                for i in 0..tx.inputs.len() {
+                    tx = tx
+                        .sign_for_chain(
+                            &utxos,
+                            i,
+                            &signing_key::<T>(pubkey_raw)?,
+                            T::ChainId::get(),
+                        )
+                        .ok_or(DispatchError::Other("Failed to sign the transaction"))?;
+                }
+                spend::<T>(&signer, &tx)?;
             }
 
-            let pubkey_raw: [u8; 32] = signer
-                .encode()
-                .try_into()
-                .map_err(|_| DispatchError::Other("Failed to get caller's public key"))?;
+            <WatchedDestinations<T>>::insert(destination_hash, &signer, ());
+            <WatcherCount<T>>::insert(destination_hash, watcher_count.saturating_add(1));
+            Ok(().into())
+        }
 
-            let mut tx = Transaction {
-                inputs,
-                outputs: vec![
-                    TransactionOutput {
-                        value,
-                        destination: dest,
-                        // todo: We need to check what kind of token over here
-                        data: None,
-                    },
-                    TransactionOutput::new_pubkey(total - value, H256::from(pubkey_raw)),
-                ],
-                time_lock: Default::default(),
-            };
+        /// Stop watching `destination`. The `WatchFee` is not refunded.
+        #[pallet::weight(<T as Config>::WeightInfo::watch_destination(1 as u32))]
+        pub fn unwatch_destination(
+            origin: OriginFor<T>,
+            destination: Destination<T::AccountId>,
+        ) -> DispatchResultWithPostInfo {
+            let signer = ensure_signed(origin)?;
+            let destination_hash = BlakeTwo256::hash_of(&destination);
+            ensure!(
+                <WatchedDestinations<T>>::contains_key(destination_hash, &signer),
+                "Destination not watched"
+            );
+            <WatchedDestinations<T>>::remove(destination_hash, &signer);
+            <WatcherCount<T>>::mutate_exists(destination_hash, |count| {
+                *count = count.map(|count| count.saturating_sub(1)).filter(|count| *count > 0)
+            });
+            Ok(().into())
+        }
 
-            for i in 0..tx.inputs.len() {
-                tx = tx
-                    .sign(&utxos, i, &sr25519::Public(pubkey_raw))
-                    .ok_or(DispatchError::Other("Failed to sign the transaction"))?;
+        /// Claim the reward of the current block to `destination` rather than to the pubkey of the
+        /// block author. Placed by the block author as an inherent, and only once per block. The
+        /// reward of a staking pool is still split among its members. The author is found like in
+        /// `reward_block_author`; without one the reward is deferred, and the claim is ignored.
+        #[pallet::weight((<T as Config>::WeightInfo::claim_reward(1 as u32), DispatchClass::Mandatory))]
+        pub fn claim_reward(
+            origin: OriginFor<T>,
+            destination: Destination<T::AccountId>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_none(origin)?;
+            ensure!(
+                !<RewardDestination<T>>::exists(),
+                "Reward already claimed in this block"
+            );
+            validate_reward_destination::<T>(&destination)?;
+            if <BlockAuthor<T>>::get().or_else(digest_author::<T>).is_none() {
+                log::warn!("no author found for the block, ignoring the reward claim");
+                return Ok(().into());
             }
+            <RewardDestination<T>>::put(destination);
+            Ok(().into())
+        }
+    }
 
-            spend::<T>(&signer, &tx)
+    /// Identifier of the inherent data carrying the destination the block author claims the
+    /// block reward to.
+    pub const REWARD_INHERENT_IDENTIFIER: InherentIdentifier = *b"utxorwrd";
+
+    #[pallet::inherent]
+    impl<T: Config> ProvideInherent for Pallet<T> {
+        type Call = Call<T>;
+        type Error = MakeFatalError<()>;
+        const INHERENT_IDENTIFIER: InherentIdentifier = REWARD_INHERENT_IDENTIFIER;
+
+        fn create_inherent(data: &InherentData) -> Option<Self::Call> {
+            // Without a claim the reward goes to the pubkey of the block author
+            let destination = data
+                .get_data::<Destination<T::AccountId>>(&REWARD_INHERENT_IDENTIFIER)
+                .ok()
+                .flatten()
+                .filter(|destination| validate_reward_destination::<T>(destination).is_ok())?;
+            Some(Call::claim_reward(destination))
         }
 
-        /// unlock the stake using the STASH ACCOUNT. Stops validating, and allow access to withdraw.
-        /// If used with `pallet-staking`, it uses the `BondingDuration`
-        /// to set the period/era on when to withdraw.
-        #[pallet::weight(<T as Config>::WeightInfo::unlock_request_for_withdrawal(1 as u32))]
-        pub fn unlock_request_for_withdrawal(
-            stash_origin: OriginFor<T>,
-        ) -> DispatchResultWithPostInfo {
-            staking::unlock_request_for_withdrawal::<T>(ensure_signed(stash_origin)?)
+        fn check_inherent(call: &Self::Call, _data: &InherentData) -> Result<(), Self::Error> {
+            match call {
+                Call::claim_reward(destination) => validate_reward_destination::<T>(destination)
+                    .map_err(|_| MakeFatalError::from(())),
+                _ => Ok(()),
+            }
         }
 
-        /// withdraw unlocked stake using the STASH ACCOUNT. Make sure the era for withdrawal has passed.
-        /// If used with `pallet-staking`,it can be found in the ledger of datatype `StakingLedger`,
-        /// the field `unlocking` of datatype `UnlockChunk`,
-        /// and at field `era`.
-        #[pallet::weight(<T as Config>::WeightInfo::withdraw_stake(1 as u32))]
-        pub fn withdraw_stake(stash_origin: OriginFor<T>) -> DispatchResultWithPostInfo {
-            staking::withdraw::<T>(ensure_signed(stash_origin)?)
+        fn is_inherent(call: &Self::Call) -> bool {
+            matches!(call, Call::claim_reward(..))
         }
     }
 
@@ -1308,6 +5134,11 @@ pub mod pallet {
         pub genesis_utxos: Vec<TransactionOutputFor<T>>,
         /// initially staked utxos of the initial validators.
         pub locked_utxos: Vec<TransactionOutputFor<T>>,
+        /// UTXO set exported from another chain with `utxo_exportUtxoSet`, imported at the same
+        /// outpoints.
+        pub utxo_snapshot: Option<pallet_utxo_rpc_runtime_api::UtxoSetExport>,
+        /// Merkle root of the airdrop claimable from genesis, see the `airdrop` module.
+        pub airdrop_root: Option<H256>,
         // /// the amount to reward block authors/producers.
         // pub initial_reward_amount:Value
     }
@@ -1318,6 +5149,8 @@ pub mod pallet {
             Self {
                 genesis_utxos: vec![],
                 locked_utxos: vec![],
+                utxo_snapshot: None,
+                airdrop_root: None,
             }
         }
     }
@@ -1325,18 +5158,40 @@ pub mod pallet {
     #[pallet::genesis_build]
     impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
         fn build(&self) {
+            let mut issuance = self
+                .genesis_utxos
+                .iter()
+                .chain(&self.locked_utxos)
+                .fold(0, |total: Value, u| total.saturating_add(u.value));
+
+            if let Some(snapshot) = &self.utxo_snapshot {
+                snapshot.verify().expect("UTXO set snapshot must match its commitment");
+                for (outpoint, utxo) in snapshot.utxos() {
+                    let utxo = TransactionOutputFor::<T>::decode(&mut &utxo[..])
+                        .expect("UTXO set snapshot must hold valid outputs");
+                    issuance = issuance.saturating_add(utxo.value);
+                    index_token_utxo::<T>(*outpoint, &utxo, None);
+                    count_utxo::<T>(&utxo, None);
+                    UtxoStore::<T>::insert(outpoint, utxo);
+                }
+            }
+            assert!(
+                issuance <= T::MaxMoney::get(),
+                "Genesis MLT must not exceed MaxMoney"
+            );
+            <MltIssuance<T>>::put(issuance);
+            if let Some(root) = self.airdrop_root {
+                <AirdropRoot<T>>::put(root);
+            }
+
             self.genesis_utxos.iter().cloned().enumerate().for_each(|(index, u)| {
                 // added the index and the `genesis` on the hashing, to indicate that these utxos are from the beginning of the chain.
+                count_utxo::<T>(&u, None);
                 UtxoStore::<T>::insert(BlakeTwo256::hash_of(&(&u, index as u64, "genesis")), u);
             });
 
             self.locked_utxos.iter().cloned().enumerate().for_each(|(index, u)| {
-                if let Destination::LockForStaking {
-                    stash_account,
-                    controller_account: _,
-                    session_key: _,
-                } = &u.destination
-                {
+                if let Destination::LockForStaking { stash_account, .. } = &u.destination {
                     <StakingCount<T>>::insert(stash_account.clone(), (1, u.value));
                 }
 
@@ -1347,11 +5202,289 @@ pub mod pallet {
     }
 }
 
+/// Most UTXOs returned by one call of [Pallet::utxos_paged].
+pub const MAX_UTXOS_PER_PAGE: u32 = 1_000;
+
+/// Most holders returned by one call of [Pallet::token_holders].
+pub const MAX_HOLDERS_PER_PAGE: u32 = 1_000;
+
+/// Most UTXOs scanned by one call of [Pallet::scan_descriptor], matching or not.
+pub const MAX_UTXOS_SCANNED_PER_PAGE: u32 = 10_000;
+
 impl<T: Config> crate::Pallet<T> {
     pub fn send() -> u32 {
         1337
     }
 
+    /// Amount of the token in circulation: the issued amount minus the amount burned so far.
+    /// Return `None` if the token has never been issued.
+    pub fn token_circulating_supply(
+        token_id: &crate::tokens::TokenId,
+    ) -> Option<crate::tokens::Value> {
+        <TokenSupply<T>>::get(token_id)
+    }
+
+    /// Decode a SCALE-encoded transaction and summarize it against the current state.
+    pub fn decode_transaction(
+        tx: &[u8],
+    ) -> Result<crate::inspect::TransactionInspection, &'static str> {
+        let tx: TransactionFor<T> =
+            codec::Decode::decode(&mut &tx[..]).map_err(|_| "invalid transaction encoding")?;
+        Ok(crate::inspect::inspect_transaction::<T>(&tx))
+    }
+
+    /// Decode a SCALE-encoded transaction and check it the way the transaction pool would,
+    /// without submitting it.
+    pub fn dry_run_spend(tx: &[u8]) -> Result<crate::inspect::DryRunReport, &'static str> {
+        let tx: TransactionFor<T> =
+            codec::Decode::decode(&mut &tx[..]).map_err(|_| "invalid transaction encoding")?;
+        Ok(crate::inspect::dry_run_spend::<T>(&tx))
+    }
+
+    /// Voting weight of `pubkey` at `block_number`: its balance in the last snapshot taken at or
+    /// before the block. Return the block of the snapshot and the balance, or `None` if no
+    /// snapshot was taken by then.
+    pub fn voting_weight(
+        pubkey: H256,
+        block_number: T::BlockNumber,
+    ) -> Option<(T::BlockNumber, crate::tokens::Value)> {
+        let snapshot_blocks = <BalanceSnapshotBlocks<T>>::get();
+        let snapshot_block = match snapshot_blocks.binary_search(&block_number) {
+            Ok(index) => snapshot_blocks[index],
+            Err(0) => return None,
+            Err(index) => snapshot_blocks[index - 1],
+        };
+        Some((
+            snapshot_block,
+            <BalanceSnapshots<T>>::get(snapshot_block, pubkey),
+        ))
+    }
+
+    /// Stake locked by the stash account with the given public key, `None` if it doesn't stake.
+    pub fn staking_info(stash: H256) -> Option<pallet_utxo_rpc_runtime_api::StakingInfo> {
+        let stash_account: T::AccountId = codec::Decode::decode(&mut stash.as_bytes()).ok()?;
+        let (num_utxos, locked_total) = <StakingCount<T>>::get(&stash_account)?;
+        Some(pallet_utxo_rpc_runtime_api::StakingInfo {
+            locked_total,
+            num_utxos,
+            outpoints: crate::staking::get_all_locked_utxo_outpoints::<T>(&stash_account),
+            unbonding: <PendingWithdrawals<T>>::get(&stash_account).map(|(value, era)| {
+                pallet_utxo_rpc_runtime_api::Unbonding {
+                    value,
+                    withdrawable_at_era: era,
+                }
+            }),
+        })
+    }
+
+    /// Up to `limit` UTXOs in ascending outpoint order, starting right after `start_key`, or
+    /// from the first one if `None`. The order doesn't depend on the node or on UTXOs added
+    /// meanwhile, so passing the last outpoint of a page as `start_key` gets the next page.
+    /// At most [MAX_UTXOS_PER_PAGE] UTXOs are returned.
+    pub fn utxos_paged(
+        start_key: Option<H256>,
+        limit: u32,
+    ) -> Vec<(H256, TransactionOutputFor<T>)> {
+        let limit = limit.min(MAX_UTXOS_PER_PAGE) as usize;
+        match start_key {
+            Some(start_key) => <UtxoStore<T>>::iter_from(<UtxoStore<T>>::hashed_key_for(start_key))
+                .take(limit)
+                .collect(),
+            None => <UtxoStore<T>>::iter().take(limit).collect(),
+        }
+    }
+
+    /// Up to `limit` holders of `token_id` with their balance, in ascending pubkey order,
+    /// starting right after `cursor`, or from the first one if `None`. Only the fungible tokens
+    /// of pay-to-pubkey UTXOs are counted, like in the `fungibles` traits. At most
+    /// [MAX_HOLDERS_PER_PAGE] holders are returned, `token_holder_count` tells how many there are.
+    pub fn token_holders(
+        token_id: &crate::tokens::TokenId,
+        cursor: Option<H256>,
+        limit: u32,
+    ) -> Vec<(H256, Value)> {
+        let limit = limit.min(MAX_HOLDERS_PER_PAGE) as usize;
+        match cursor {
+            Some(cursor) => {
+                <TokenHolders<T>>::iter_from(<TokenHolders<T>>::hashed_key_for(token_id, cursor))
+                    .take_while(|(holder_token, ..)| holder_token == token_id)
+                    .map(|(_, owner, balance)| (owner, balance))
+                    .take(limit)
+                    .collect()
+            }
+            None => <TokenHolders<T>>::iter_prefix(token_id).take(limit).collect(),
+        }
+    }
+
+    /// Up to `limit` UTXOs paying to the destination of `descriptor`, in ascending outpoint
+    /// order, starting right after `start_key`, or from the first one if `None`, with the start
+    /// key of the next page, `None` once the whole UTXO set is scanned. The UTXO set is scanned
+    /// until enough UTXOs are found or [MAX_UTXOS_SCANNED_PER_PAGE] UTXOs are scanned, so a page
+    /// may have fewer UTXOs, even none, and still be followed by another. Pruned UTXOs are
+    /// skipped. At most [MAX_UTXOS_PER_PAGE] UTXOs are returned.
+    pub fn scan_descriptor(
+        descriptor: &crate::descriptor::Descriptor,
+        start_key: Option<H256>,
+        limit: u32,
+    ) -> (Vec<(H256, TransactionOutputFor<T>)>, Option<H256>) {
+        scan_utxos::<T>(
+            &descriptor.destination::<T::AccountId>(),
+            start_key,
+            limit,
+            MAX_UTXOS_SCANNED_PER_PAGE,
+        )
+    }
+
+    /// Statistics of the UTXO set, kept up to date as UTXOs are created and spent so that they
+    /// can be read without iterating over the UTXO set.
+    pub fn utxo_stats() -> pallet_utxo_rpc_runtime_api::UtxoStats {
+        pallet_utxo_rpc_runtime_api::UtxoStats {
+            utxo_count: <UtxoCount<T>>::get(),
+            total_value: <UtxoValue<T>>::get(),
+            token_utxo_counts: <TokenUtxoCount<T>>::iter()
+                .map(|(token_id, count)| (token_id.to_string(), count))
+                .collect(),
+        }
+    }
+
+    /// Inputs of the transactions rejected in the block for spending outputs not in the UTXO set,
+    /// with the spends they conflict with. Kept for `StxoRetention` blocks.
+    pub fn missing_inputs(block_number: T::BlockNumber) -> Vec<MissingInputProof<T::BlockNumber>> {
+        <MissingInputs<T>>::get(block_number)
+    }
+
+    /// UTXOs the contract account `contract` can spend, with their values, in the order of
+    /// their outpoints.
+    pub fn contract_utxos(contract: &T::AccountId) -> Vec<(H256, Value)> {
+        let mut utxos: Vec<(H256, Value)> = <ContractUtxos<T>>::iter_prefix(contract).collect();
+        utxos.sort();
+        utxos
+    }
+
+    /// Outpoint of the output at `index` of the transaction `txid`, if it is unspent, its body
+    /// possibly pruned. The UTXOs
+    /// that were not added by a transaction since the outputs are indexed by txid, such as the
+    /// genesis UTXOs, the block rewards and the UTXOs created before the index, are referenced by
+    /// their outpoint and the index 0.
+    pub fn outpoint_of(txid: H256, index: u32) -> Option<H256> {
+        match <OutpointsByTxid<T>>::get(txid, index) {
+            Some(outpoint) => Some(outpoint),
+            None if index == 0
+                && (<UtxoStore<T>>::contains_key(txid) || <PrunedUtxos<T>>::contains_key(txid))
+                && !<OutputRefs<T>>::contains_key(txid) =>
+            {
+                Some(txid)
+            }
+            None => None,
+        }
+    }
+
+    /// Id of the transaction and index of the unspent output at `outpoint`, the reverse of
+    /// `outpoint_of`.
+    pub fn output_ref(outpoint: H256) -> Option<(H256, u32)> {
+        if !<UtxoStore<T>>::contains_key(outpoint) && !<PrunedUtxos<T>>::contains_key(outpoint) {
+            return None;
+        }
+        Some(<OutputRefs<T>>::get(outpoint).unwrap_or((outpoint, 0)))
+    }
+
+    /// Rules the metadata of new tokens follow, from the `Config` constants.
+    pub fn token_metadata_rules() -> crate::tokens::TokenMetadataRules {
+        crate::tokens::TokenMetadataRules {
+            max_ticker_length: T::MaxTickerLength::get(),
+            max_decimals: T::MaxTokenDecimals::get(),
+            max_metadata_uri_length: T::MaxMetadataUriLength::get(),
+            ticker_charset: T::TokenTickerCharset::get(),
+        }
+    }
+
+    /// Check a token can be issued with the metadata: it follows the rules of
+    /// `token_metadata_rules`, and the ticker is neither reserved nor, if tickers are unique,
+    /// already taken.
+    pub fn validate_token_metadata(
+        ticker: &[u8],
+        number_of_decimals: u8,
+        metadata_uri: &[u8],
+    ) -> Result<(), &'static str> {
+        Self::token_metadata_rules().validate(ticker, number_of_decimals, metadata_uri)?;
+        pallet::validate_ticker::<T>(ticker)
+    }
+
+    /// MLT `output` must carry for the bytes it keeps in the UTXO set, at `StorageDepositPerByte`:
+    /// its token data and the code or input of a programmable pool. Data carriers and tokens
+    /// locked for the bridge are not kept in the UTXO set and need no deposit. Neither do
+    /// auctions and orders, which escrow the tokens they sell in outputs without value.
+    pub fn storage_deposit(output: &TransactionOutputFor<T>) -> Value {
+        let pool_size = match &output.destination {
+            Destination::DataCarrier(_)
+            | Destination::Auction { .. }
+            | Destination::Order { .. } => return 0,
+            Destination::CreatePP(code, data) => code.len() + data.len(),
+            Destination::CallPP(_, _, input, _) => input.len(),
+            _ => 0,
+        };
+        let data_size = match &output.data {
+            Some(crate::tokens::OutputData::TokenLockForBridgeV1 { .. }) => return 0,
+            Some(data) => data.encoded_size(),
+            None => 0,
+        };
+        T::StorageDepositPerByte::get().saturating_mul(pool_size.saturating_add(data_size) as Value)
+    }
+
+    /// The authorities of `Config::authorities`, each listed once in the order they first appear.
+    pub fn authorities() -> Vec<H256> {
+        crate::rewards::dedup_authorities(T::authorities()).0
+    }
+
+    /// MLT in circulation: the MLT issued on the UTXO ledger less the stake locked by the stash
+    /// accounts. The issuance itself never exceeds `MaxMoney`.
+    pub fn circulating_supply() -> Value {
+        let staked = <StakingCount<T>>::iter_values()
+            .fold(0, |total: Value, (_, locked)| total.saturating_add(locked));
+        <MltIssuance<T>>::get().saturating_sub(staked)
+    }
+
+    /// Stakes of all the stash accounts with locked UTXOs.
+    pub fn validator_stakes() -> Vec<pallet_utxo_rpc_runtime_api::ValidatorStake> {
+        <StakingCount<T>>::iter()
+            .filter_map(|(stash_account, (num_utxos, locked_total))| {
+                Some(pallet_utxo_rpc_runtime_api::ValidatorStake {
+                    stash: convert_to_h256::<T>(&stash_account).ok()?,
+                    locked_total,
+                    num_utxos,
+                })
+            })
+            .collect()
+    }
+
+    /// Verify a reserve proof against the current UTXO set. Return the total MLT value of the
+    /// UTXOs the signer is proven to control.
+    pub fn verify_reserve_proof(
+        proof: &ReserveProof,
+    ) -> Result<crate::tokens::Value, &'static str> {
+        frame_support::ensure!(!proof.outpoints.is_empty(), "no outpoints");
+        frame_support::ensure!(
+            proof.outpoints.len() == proof.signatures.len(),
+            "one signature per outpoint required"
+        );
+        let unique: sp_std::collections::btree_set::BTreeSet<_> = proof.outpoints.iter().collect();
+        frame_support::ensure!(unique.len() == proof.outpoints.len(), "duplicate outpoint");
+
+        proof.outpoints.iter().enumerate().try_fold(
+            0,
+            |total: crate::tokens::Value, (index, outpoint)| {
+                let utxo = <UtxoStore<T>>::get(outpoint).ok_or("outpoint is not unspent")?;
+                let pubkey = match &utxo.destination {
+                    Destination::Pubkey(pubkey) => pubkey,
+                    Destination::Taproot { internal_key, .. } => internal_key,
+                    _ => return Err("outpoint is not controlled by a key"),
+                };
+                frame_support::ensure!(proof.verify(index, pubkey), "signature must be valid");
+                total.checked_add(utxo.value).ok_or("value overflow")
+            },
+        )
+    }
+
     // todo: This part isn't fully tested, left for the next PR
     // pub fn nft_read(
     //     nft_id: &core::primitive::str,
@@ -1381,7 +5514,7 @@ fn coin_picker<T: Config>(outpoints: &Vec<H256>) -> Result<Vec<TransactionInput>
     for outpoint in outpoints.iter() {
         let tx = <UtxoStore<T>>::get(&outpoint).ok_or("UTXO doesn't exist!")?;
         match tx.destination {
-            Destination::CallPP(_, _, _) => {
+            Destination::CallPP(..) => {
                 inputs.push(TransactionInput::new_script(
                     *outpoint,
                     Builder::new().into_script(),
@@ -1397,6 +5530,49 @@ fn coin_picker<T: Config>(outpoints: &Vec<H256>) -> Result<Vec<TransactionInput>
     Ok(inputs)
 }
 
+/// Output paying `payout` on behalf of a contract
+fn payout_output<T: Config>(payout: &Payout) -> TransactionOutputFor<T> {
+    let mut output = match payout.destination {
+        PayoutDestination::Pubkey(pubkey) => TransactionOutput::new_pubkey(payout.value, pubkey),
+        PayoutDestination::ScriptHash(hash) => {
+            TransactionOutput::new_script_hash(payout.value, hash)
+        }
+    };
+    output.data = payout.token.map(|(token_id, amount)| tokens::OutputData::TokenTransferV1 {
+        token_id: tokens::TokenId::from_h160(token_id),
+        amount,
+    });
+    output
+}
+
+/// Up to `limit` UTXOs paying to `destination`, scanning at most `max_scanned` UTXOs of the UTXO
+/// set from right after `start_key`, with the start key of the next page if any is left to scan.
+fn scan_utxos<T: Config>(
+    destination: &Destination<T::AccountId>,
+    start_key: Option<H256>,
+    limit: u32,
+    max_scanned: u32,
+) -> (Vec<(H256, TransactionOutputFor<T>)>, Option<H256>) {
+    let limit = limit.min(MAX_UTXOS_PER_PAGE) as usize;
+    let utxos = match start_key {
+        Some(start_key) => <UtxoStore<T>>::iter_from(<UtxoStore<T>>::hashed_key_for(start_key)),
+        None => <UtxoStore<T>>::iter(),
+    };
+    let mut found = Vec::new();
+    let mut cursor = start_key;
+    for (scanned, (outpoint, utxo)) in utxos.enumerate() {
+        // Another UTXO is left, so the page is followed by another
+        if found.len() >= limit || scanned >= max_scanned as usize {
+            return (found, cursor);
+        }
+        cursor = Some(outpoint);
+        if &utxo.destination == destination {
+            found.push((outpoint, utxo));
+        }
+    }
+    (found, None)
+}
+
 impl<T: Config> UtxoApi for Pallet<T>
 where
     T::AccountId: UncheckedFrom<T::Hash> + AsRef<[u8]>,
@@ -1416,6 +5592,7 @@ where
                 inputs: vec![TransactionInput::new_with_signature(utxo, sig)],
                 outputs: vec![TransactionOutputFor::<T>::new_pubkey(value, address)],
                 time_lock: Default::default(),
+                expires_at: None,
             },
         )
     }
@@ -1445,12 +5622,17 @@ where
                 inputs: coin_picker::<T>(outpoints)?,
                 outputs: vec![TransactionOutput::new_pubkey(value, H256::from(pubkey_raw))],
                 time_lock: Default::default(),
+                expires_at: None,
             },
         )
         .map_err(|_| "Failed to spend the transaction!")?;
         Ok(())
     }
 
+    fn contract_utxos(contract: &T::AccountId) -> Vec<(H256, u128)> {
+        Pallet::<T>::contract_utxos(contract)
+    }
+
     fn send_conscrit_c2c(
         caller: &Self::AccountId,
         dest: &Self::AccountId,
@@ -1467,11 +5649,40 @@ where
                     dest.clone(),
                     true,
                     data.clone(),
+                    None,
                 )],
                 time_lock: Default::default(),
+                expires_at: None,
             },
         )
         .map_err(|_| "Failed to spend the transaction!")?;
         Ok(())
     }
+
+    fn send_conscrit_payouts(
+        caller: &Self::AccountId,
+        payouts: &Vec<Payout>,
+        outpoints: &Vec<H256>,
+    ) -> Result<(), DispatchError> {
+        frame_support::ensure!(!payouts.is_empty(), "No payouts given");
+        // A contract can only pay out from its own UTXOs
+        frame_support::ensure!(
+            outpoints
+                .iter()
+                .all(|outpoint| <ContractUtxos<T>>::contains_key(caller, outpoint)),
+            "UTXO isn't owned by the contract"
+        );
+
+        spend::<T>(
+            caller,
+            &Transaction {
+                inputs: coin_picker::<T>(outpoints)?,
+                outputs: payouts.iter().map(payout_output::<T>).collect(),
+                time_lock: Default::default(),
+                expires_at: None,
+            },
+        )
+        .map_err(|e| e.error)?;
+        Ok(())
+    }
 }
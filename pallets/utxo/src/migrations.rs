@@ -0,0 +1,162 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storage migrations
+//!
+//! The outputs kept in `UtxoStore` and `LockedUtxos`, and the transactions kept in
+//! `TokenIssuanceTransactions`, are stored SCALE-encoded, so a change of their layout has to
+//! re-encode the stored values on the runtime upgrade bringing it. The
+//! migrations run in `on_runtime_upgrade`, each one once, as tracked by the storage version of
+//! the pallet.
+
+use crate::{
+    Config, Destination, LockedUtxos, Pallet, ScriptVersion, TokenIssuanceTransactions,
+    Transaction, TransactionInput, TransactionOutput, UtxoStore,
+};
+use codec::{Decode, Encode};
+use frame_support::{
+    traits::{Get, GetStorageVersion, StorageVersion},
+    weights::Weight,
+};
+use sp_core::{sr25519, H256};
+use sp_std::vec::Vec;
+
+/// Storage version of the pallet, bumped with every migration
+pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
+/// Layouts of the pallet before storage versions were tracked
+pub mod v0 {
+    use super::*;
+    use crate::tokens::{OutputData, Value};
+    use crate::RawBlockTime;
+
+    /// `Destination` as stored before script hashes were versioned
+    #[derive(Encode, Decode)]
+    pub enum Destination<AccountId> {
+        Pubkey(sr25519::Public),
+        CreatePP(Vec<u8>, Vec<u8>),
+        CallPP(AccountId, bool, Vec<u8>),
+        ScriptHash(H256),
+        LockForStaking {
+            stash_account: AccountId,
+            controller_account: AccountId,
+            session_key: Vec<u8>,
+        },
+        LockExtraForStaking {
+            stash_account: AccountId,
+            controller_account: AccountId,
+        },
+    }
+
+    /// `TransactionOutput` with a version 0 destination
+    #[derive(Encode, Decode)]
+    pub struct TransactionOutput<AccountId> {
+        pub value: Value,
+        pub destination: Destination<AccountId>,
+        pub data: Option<OutputData>,
+    }
+
+    /// `Transaction` as stored before transactions could expire
+    #[derive(Encode, Decode)]
+    pub struct Transaction<AccountId> {
+        pub inputs: Vec<TransactionInput>,
+        pub outputs: Vec<TransactionOutput<AccountId>>,
+        pub time_lock: RawBlockTime,
+    }
+
+    impl<AccountId> From<Destination<AccountId>> for super::Destination<AccountId> {
+        fn from(destination: Destination<AccountId>) -> Self {
+            match destination {
+                Destination::Pubkey(pubkey) => Self::Pubkey(pubkey),
+                Destination::CreatePP(code, data) => Self::CreatePP(code, data),
+                // Calls stored before ABI hashes existed aren't checked against an ABI
+                Destination::CallPP(account, fund, data) => Self::CallPP(account, fund, data, None),
+                // The scripts of all stored hashes were written for the initial version
+                Destination::ScriptHash(hash) => Self::ScriptHash(ScriptVersion::V0, hash),
+                // Withdrawn to the stash account, without commission or self bond, as before
+                // these fields existed
+                Destination::LockForStaking {
+                    stash_account,
+                    controller_account,
+                    session_key,
+                } => Self::LockForStaking {
+                    stash_account,
+                    controller_account,
+                    session_key,
+                    withdrawal_key: None,
+                    secondary_controller: None,
+                    commission: Default::default(),
+                    min_self_bond: 0,
+                },
+                Destination::LockExtraForStaking {
+                    stash_account,
+                    controller_account,
+                } => Self::LockExtraForStaking {
+                    stash_account,
+                    controller_account,
+                },
+            }
+        }
+    }
+
+    impl<AccountId> From<TransactionOutput<AccountId>> for super::TransactionOutput<AccountId> {
+        fn from(output: TransactionOutput<AccountId>) -> Self {
+            Self {
+                value: output.value,
+                destination: output.destination.into(),
+                data: output.data,
+            }
+        }
+    }
+
+    impl<AccountId> From<Transaction<AccountId>> for super::Transaction<AccountId> {
+        fn from(tx: Transaction<AccountId>) -> Self {
+            Self {
+                inputs: tx.inputs,
+                outputs: tx.outputs.into_iter().map(Into::into).collect(),
+                time_lock: tx.time_lock,
+                expires_at: None,
+            }
+        }
+    }
+}
+
+/// Re-encode the stored outputs with the destinations of version 1, and the stored transactions
+/// without an expiry. Returns the weight used.
+fn migrate_to_v1<T: Config>() -> Weight {
+    let mut translated: Weight = 0;
+    let mut translate = |_: H256, output: v0::TransactionOutput<T::AccountId>| {
+        translated += 1;
+        Some(TransactionOutput::from(output))
+    };
+    <UtxoStore<T>>::translate(&mut translate);
+    <LockedUtxos<T>>::translate(&mut translate);
+    <TokenIssuanceTransactions<T>>::translate(|_, tx: v0::Transaction<T::AccountId>| {
+        translated += 1;
+        Some(Transaction::from(tx))
+    });
+    StorageVersion::new(1).put::<Pallet<T>>();
+    log::info!("migrated {} values to storage version 1", translated);
+    T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+}
+
+/// Run the migrations the stored data has not gone through yet. Returns the weight used.
+pub fn migrate<T: Config>() -> Weight {
+    let mut weight = T::DbWeight::get().reads(1);
+    if <Pallet<T>>::on_chain_storage_version() < 1 {
+        weight = weight.saturating_add(migrate_to_v1::<T>());
+    }
+    weight
+}
@@ -15,12 +15,18 @@
 //
 // Author(s): C. Yap
 use crate as pallet_utxo;
-use pallet_utxo::staking::StakingHelper;
-use pallet_utxo::TransactionOutput;
+use pallet_utxo::bridge::BridgeHandler;
+use pallet_utxo::compliance::TransferApprover;
+use pallet_utxo::hooks::OnUtxoEvent;
+use pallet_utxo::identity::IdentityProvider;
+use pallet_utxo::priority::{FeeAsset, FeePerWeight};
+use pallet_utxo::staking::{EraIndex, FeeDestination, StakingHelper};
+use pallet_utxo::{SignatureScheme, TransactionOutput};
 use pp_api::ProgrammablePoolApi;
 
-use crate::tokens::Value;
+use crate::tokens::{TickerCharset, TokenId, Value};
 use crate::MLT_UNIT;
+use codec::Decode;
 use frame_support::dispatch::DispatchResultWithPostInfo;
 use frame_support::{dispatch::Vec, weights::Weight};
 use frame_support::{
@@ -29,15 +35,18 @@ use frame_support::{
     sp_runtime::{
         testing::Header,
         traits::{BlakeTwo256, Hash, IdentityLookup},
-        Percent,
+        ConsensusEngineId, Perbill, Percent,
     },
-    traits::GenesisBuild,
+    traits::{FindAuthor, GenesisBuild},
 };
 use frame_system::Config as SysConfig;
+use sp_consensus_aura::{Slot, AURA_ENGINE_ID};
+use sp_consensus_babe::{digests::PreDigest, BABE_ENGINE_ID};
 use sp_core::{
     sp_std::{cell::RefCell, collections::btree_map::BTreeMap, marker::PhantomData, vec},
     sr25519::Public,
-    testing::SR25519,
+    testing::{TaskExecutor, SR25519},
+    traits::TaskExecutorExt,
     H256,
 };
 use sp_keystore::{testing::KeyStore, KeystoreExt, SyncCryptoStore};
@@ -54,12 +63,14 @@ pub struct MockStaking<T: pallet_utxo::Config> {
     pub lock_map: BTreeMap<T::AccountId, Option<T::BlockNumber>>,
     pub ctrl_map: BTreeMap<T::AccountId, T::AccountId>,
     pub lock_ctrl_map: BTreeMap<T::AccountId, T::AccountId>,
+    pub commission_map: BTreeMap<T::AccountId, Perbill>,
     pub marker: PhantomData<T>,
 }
 
 thread_local! {
     pub static AUTHORITIES: RefCell<Vec<Public>> = RefCell::new(vec![]);
     pub static MOCK_STAKING: RefCell<MockStaking<Test>> = RefCell::new(MockStaking::new());
+    pub static UTXO_EVENTS: RefCell<Vec<UtxoEvent>> = RefCell::new(vec![]);
 }
 
 pub const ALICE_PHRASE: &str =
@@ -105,6 +116,19 @@ impl<T: SysConfig> ProgrammablePoolApi for MockPool<T> {
     ) -> Result<(), &'static str> {
         Ok(())
     }
+
+    fn verify_call(
+        _dest: &Self::AccountId,
+        abi_hash: Option<H256>,
+        _input_data: &Vec<u8>,
+    ) -> Result<(), &'static str> {
+        match abi_hash {
+            Some(abi_hash) if abi_hash != MockAbiHash::get() => {
+                Err("ABI hash doesn't match the contract")
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 impl MockStaking<Test> {
@@ -115,6 +139,7 @@ impl MockStaking<Test> {
             lock_map: BTreeMap::new(),
             ctrl_map: BTreeMap::new(),
             lock_ctrl_map: BTreeMap::new(),
+            commission_map: BTreeMap::new(),
             marker: Default::default(),
         }
     }
@@ -183,6 +208,7 @@ impl<T: pallet_utxo::Config> StakingHelper<AccountId> for MockStaking<T> {
         controller_account: &AccountId,
         _rotate_keys: &Vec<u8>,
         _value: u128,
+        commission: Perbill,
     ) -> DispatchResultWithPostInfo {
         MOCK_STAKING.with(|stake_info| {
             let mut stake_info = stake_info.borrow_mut();
@@ -204,6 +230,7 @@ impl<T: pallet_utxo::Config> StakingHelper<AccountId> for MockStaking<T> {
                 .lock_ctrl_map
                 .insert(stash_account.clone(), controller_account.clone());
             stake_info.ctrl_map.insert(controller_account.clone(), stash_account.clone());
+            stake_info.commission_map.insert(stash_account.clone(), commission);
 
             Ok(().into())
         })
@@ -272,6 +299,171 @@ impl<T: pallet_utxo::Config> StakingHelper<AccountId> for MockStaking<T> {
             }
         })
     }
+
+    fn redelegate(
+        stash_account: &AccountId,
+        new_stash_account: &AccountId,
+        new_controller_account: &AccountId,
+        _session_key: &Vec<u8>,
+        _value: u128,
+        commission: Perbill,
+    ) -> DispatchResultWithPostInfo {
+        MOCK_STAKING.with(|stake_info| {
+            let mut stake_info = stake_info.borrow_mut();
+
+            if !stake_info.lock_map.contains_key(stash_account) {
+                Err(pallet_utxo::Error::<T>::StashAccountNotFound)?
+            }
+
+            let ctrl_account = stake_info.lock_ctrl_map.remove(stash_account).unwrap();
+            stake_info.ctrl_map.remove(&ctrl_account);
+            stake_info.lock_map.remove(stash_account);
+            stake_info.commission_map.remove(stash_account);
+
+            stake_info.lock_map.insert(new_stash_account.clone(), None);
+            stake_info.commission_map.insert(new_stash_account.clone(), commission);
+            stake_info
+                .lock_ctrl_map
+                .insert(new_stash_account.clone(), new_controller_account.clone());
+            stake_info
+                .ctrl_map
+                .insert(new_controller_account.clone(), new_stash_account.clone());
+
+            Ok(().into())
+        })
+    }
+
+    fn rotate_session_keys(
+        stash_account: &AccountId,
+        _session_key: &Vec<u8>,
+    ) -> DispatchResultWithPostInfo {
+        MOCK_STAKING.with(|stake_info| {
+            if !stake_info.borrow().lock_map.contains_key(stash_account) {
+                Err(pallet_utxo::Error::<T>::StashAccountNotFound)?
+            }
+
+            Ok(().into())
+        })
+    }
+
+    fn current_era() -> EraIndex {
+        // every block is a new era
+        MOCK_STAKING.with(|stake_info| stake_info.borrow().current_block as EraIndex)
+    }
+}
+
+/// Chain the mock bridge supports
+pub const BRIDGE_CHAIN: u32 = 1;
+
+/// Account of the only relayer of the mock bridge
+pub fn bridge_relayer() -> AccountId {
+    H256::repeat_byte(0xbb)
+}
+
+/// Proof accepted by the mock bridge, the hash of the lock outpoint and the pubkey
+pub fn bridge_release_proof(outpoint: &H256, pubkey: &H256) -> Vec<u8> {
+    BlakeTwo256::hash_of(&(outpoint, pubkey)).as_bytes().to_vec()
+}
+
+pub struct MockBridge;
+
+impl BridgeHandler<AccountId> for MockBridge {
+    fn supports_chain(target_chain: u32) -> bool {
+        target_chain == BRIDGE_CHAIN
+    }
+
+    fn on_lock(
+        _outpoint: &H256,
+        _token_id: &TokenId,
+        _amount: Value,
+        _target_chain: u32,
+        _target_address: &[u8],
+    ) {
+    }
+
+    fn is_relayer(account: &AccountId) -> bool {
+        *account == bridge_relayer()
+    }
+
+    fn verify_release(outpoint: &H256, pubkey: &H256, proof: &[u8]) -> bool {
+        proof == bridge_release_proof(outpoint, pubkey).as_slice()
+    }
+}
+
+/// Largest transfer approved by the mock approver
+pub const APPROVED_TRANSFER_LIMIT: Value = 500;
+
+pub struct MockApprover;
+
+impl TransferApprover<AccountId> for MockApprover {
+    fn approve_transfer(
+        _token_id: &TokenId,
+        _destination: &pallet_utxo::Destination<AccountId>,
+        amount: Value,
+    ) -> bool {
+        amount <= APPROVED_TRANSFER_LIMIT
+    }
+}
+
+/// Change of the UTXO set recorded by the mock hooks
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum UtxoEvent {
+    New(H256),
+    Spent(H256),
+}
+
+/// Identities named in `IdentityNames`
+pub struct MockIdentities;
+
+impl IdentityProvider<AccountId> for MockIdentities {
+    fn display_name(who: &AccountId) -> Option<Vec<u8>> {
+        IdentityNames::get()
+            .into_iter()
+            .find(|(account, _)| account == who)
+            .map(|(_, name)| name)
+    }
+}
+
+/// Finds the author of a block in `AUTHORITIES`, like `pallet-aura` by the slot of an Aura block
+/// and `pallet-babe` by the authority index of a Babe block.
+pub struct MockFindAuthor;
+
+impl FindAuthor<AccountId> for MockFindAuthor {
+    fn find_author<'a, I>(digests: I) -> Option<AccountId>
+    where
+        I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+    {
+        let authorities = <Test as pallet_utxo::Config>::authorities();
+        for (engine_id, mut data) in digests {
+            let index = if engine_id == AURA_ENGINE_ID {
+                let slot = Slot::decode(&mut data).ok()?;
+                *slot % (authorities.len() as u64).max(1)
+            } else if engine_id == BABE_ENGINE_ID {
+                PreDigest::decode(&mut data).ok()?.authority_index().into()
+            } else {
+                continue;
+            };
+            return authorities.get(index as usize).copied();
+        }
+        None
+    }
+}
+
+/// Weight the mock hooks report for every change
+pub const UTXO_HOOK_WEIGHT: Weight = 1_000;
+
+pub struct MockUtxoHooks;
+
+impl OnUtxoEvent<AccountId> for MockUtxoHooks {
+    fn on_new_utxo(outpoint: &H256, _output: &TransactionOutput<AccountId>) -> Weight {
+        UTXO_EVENTS.with(|events| events.borrow_mut().push(UtxoEvent::New(*outpoint)));
+        UTXO_HOOK_WEIGHT
+    }
+
+    fn on_utxo_spent(outpoint: &H256, _utxo: &TransactionOutput<AccountId>) -> Weight {
+        UTXO_EVENTS.with(|events| events.borrow_mut().push(UtxoEvent::Spent(*outpoint)));
+        UTXO_HOOK_WEIGHT
+    }
 }
 
 // Configure a mock runtime to test the pallet.
@@ -283,6 +475,7 @@ frame_support::construct_runtime!(
     {
         System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
         Timestamp: pallet_timestamp::{Pallet, Call, Storage, Inherent},
+        Balances: pallet_balances::{Pallet, Call, Storage, Event<T>},
         Utxo: pallet_utxo::{Pallet, Call, Config<T>, Storage, Event<T>},
     }
 );
@@ -315,7 +508,7 @@ impl SysConfig for Test {
     type DbWeight = ();
     type Version = ();
     type PalletInfo = PalletInfo;
-    type AccountData = ();
+    type AccountData = pallet_balances::AccountData<Value>;
     type OnNewAccount = ();
     type OnKilledAccount = ();
     type SystemWeightInfo = ();
@@ -323,6 +516,22 @@ impl SysConfig for Test {
     type OnSetCode = ();
 }
 
+parameter_types! {
+    pub const ExistentialDeposit: Value = 1;
+}
+
+impl pallet_balances::Config for Test {
+    type MaxLocks = ();
+    type Balance = Value;
+    type Event = Event;
+    type DustRemoval = ();
+    type ExistentialDeposit = ExistentialDeposit;
+    type AccountStore = System;
+    type WeightInfo = ();
+    type MaxReserves = ();
+    type ReserveIdentifier = [u8; 8];
+}
+
 impl pallet_timestamp::Config for Test {
     type Moment = u64;
     type OnTimestampSet = ();
@@ -336,8 +545,44 @@ parameter_types! {
     pub const InitialReward: u128 = 100;
     pub const DefaultMinimumReward: u128 = 1;
     pub const StakeWithdrawalFee: u128 = 1;
-    pub const RewardReductionPeriod: BlockNumber = 5;
-    pub const RewardReductionFraction: Percent = Percent::from_percent(25);
+    pub static StakeWithdrawalFeeDestination: FeeDestination<AccountId> = FeeDestination::RewardPool;
+    pub const MaxValidatorCommission: Perbill = Perbill::from_percent(50);
+    pub const BondingDuration: EraIndex = 5;
+    pub const TokenIssuanceFee: u128 = 10;
+    pub const MaxDataCarrierSize: u32 = 80;
+    pub static StorageDepositPerByte: Value = 0;
+    pub static AcceptedFeeAssets: Vec<FeeAsset> = vec![];
+    pub const NftMintFee: u128 = 5;
+    pub static IssuanceRateLimit: u32 = u32::MAX;
+    pub const WatchFee: u128 = 3;
+    pub static UniqueTickers: bool = true;
+    pub ReservedTickers: Vec<Vec<u8>> = vec![b"MLT".to_vec()];
+    pub static MaxTickerLength: u32 = 5;
+    pub const MaxTokenDecimals: u8 = 18;
+    pub const MaxMetadataUriLength: u32 = 100;
+    pub static TokenTickerCharset: TickerCharset = TickerCharset::Ascii;
+    pub const RewardHistoryDepth: EraIndex = 3;
+    pub const StxoRetention: BlockNumber = 3;
+    pub const SnapshotPeriod: BlockNumber = 10;
+    pub static SnapshotBatchSize: u32 = 2;
+    pub const SnapshotHistoryDepth: u32 = 2;
+    pub static PruningDepth: BlockNumber = 0;
+    pub const NftProvenanceDepth: u32 = 2;
+    pub static MaxTransactionWeightShare: Perbill = Perbill::from_percent(25);
+    pub static MaxWatchers: u32 = 3;
+    pub static MaxStandardWitnessSize: u32 = 10 * 1024;
+    pub static DustThreshold: Value = 0;
+    pub const MaxConsolidationInputs: u32 = 3;
+    pub ChainId: H256 = H256::repeat_byte(0x4d);
+    pub static UnboundSignaturesUntil: BlockNumber = BlockNumber::MAX;
+    pub static NonCanonicalWitnessesUntil: BlockNumber = BlockNumber::MAX;
+    pub const DefaultSignatureScheme: SignatureScheme = SignatureScheme::Schnorr;
+    pub static RewardReductionPeriod: BlockNumber = 5;
+    pub static RewardReductionFraction: Percent = Percent::from_percent(25);
+    pub static MaxMoney: Value = Value::MAX;
+    pub static MinimumFeeRate: Value = 0;
+    pub static IdentityNames: Vec<(AccountId, Vec<u8>)> = vec![];
+    pub MockAbiHash: H256 = H256::repeat_byte(0xab);
 }
 
 impl pallet_utxo::Config for Test {
@@ -347,6 +592,7 @@ impl pallet_utxo::Config for Test {
     type ProgrammablePool = MockPool<Test>;
     type RewardReductionFraction = RewardReductionFraction;
     type RewardReductionPeriod = RewardReductionPeriod;
+    type MaxMoney = MaxMoney;
 
     fn authorities() -> Vec<H256> {
         AUTHORITIES.with(|auths| {
@@ -356,10 +602,51 @@ impl pallet_utxo::Config for Test {
     }
 
     type StakingHelper = MockStaking<Test>;
+    type BridgeHandler = MockBridge;
+    type TransferApprover = MockApprover;
+    type UtxoHooks = MockUtxoHooks;
+    type Currency = Balances;
     type MinimumStake = MinimumStake;
     type StakeWithdrawalFee = StakeWithdrawalFee;
+    type MaxValidatorCommission = MaxValidatorCommission;
+    type FeeDestination = StakeWithdrawalFeeDestination;
+    type BondingDuration = BondingDuration;
+    type TokenIssuanceFee = TokenIssuanceFee;
+    type MaxDataCarrierSize = MaxDataCarrierSize;
+    type StorageDepositPerByte = StorageDepositPerByte;
+    type AcceptedFeeAssets = AcceptedFeeAssets;
+    type NftMintFee = NftMintFee;
+    type IssuanceRateLimit = IssuanceRateLimit;
+    type WatchFee = WatchFee;
+    type UniqueTickers = UniqueTickers;
+    type ReservedTickers = ReservedTickers;
+    type MaxTickerLength = MaxTickerLength;
+    type MaxTokenDecimals = MaxTokenDecimals;
+    type MaxMetadataUriLength = MaxMetadataUriLength;
+    type TokenTickerCharset = TokenTickerCharset;
+    type RewardHistoryDepth = RewardHistoryDepth;
+    type StxoRetention = StxoRetention;
+    type SnapshotPeriod = SnapshotPeriod;
+    type SnapshotBatchSize = SnapshotBatchSize;
+    type SnapshotHistoryDepth = SnapshotHistoryDepth;
+    type PruningDepth = PruningDepth;
+    type NftProvenanceDepth = NftProvenanceDepth;
+    type MaxTransactionWeightShare = MaxTransactionWeightShare;
+    type MaxWatchers = MaxWatchers;
+    type MaxStandardWitnessSize = MaxStandardWitnessSize;
+    type DustThreshold = DustThreshold;
+    type MaxConsolidationInputs = MaxConsolidationInputs;
+    type ChainId = ChainId;
+    type UnboundSignaturesUntil = UnboundSignaturesUntil;
+    type NonCanonicalWitnessesUntil = NonCanonicalWitnessesUntil;
+    type DefaultSignatureScheme = DefaultSignatureScheme;
     type InitialReward = InitialReward;
     type DefaultMinimumReward = DefaultMinimumReward;
+    type PriorityPolicy = FeePerWeight<MinimumFeeRate>;
+    type TokenIssuerOrigin = frame_system::EnsureRoot<H256>;
+    type PauseOrigin = frame_system::EnsureRoot<H256>;
+    type Identities = MockIdentities;
+    type FindAuthor = MockFindAuthor;
 }
 
 fn create_pub_key(keystore: &KeyStore, phrase: &str) -> Public {
@@ -378,12 +665,15 @@ pub fn alice_test_ext() -> TestExternalities {
             H256::from(alice_pub_key),
         )],
         locked_utxos: vec![],
+        utxo_snapshot: None,
+        airdrop_root: None,
     }
     .assimilate_storage(&mut t)
     .unwrap();
 
     let mut ext = TestExternalities::from(t);
     ext.register_extension(KeystoreExt(std::sync::Arc::new(keystore)));
+    ext.register_extension(TaskExecutorExt::new(TaskExecutor::new()));
     ext
 }
 
@@ -403,12 +693,15 @@ pub fn alice_test_ext_and_keys() -> (TestExternalities, Public, Public) {
             H256::from(alice_pub_key),
         )],
         locked_utxos: vec![],
+        utxo_snapshot: None,
+        airdrop_root: None,
     }
     .assimilate_storage(&mut t)
     .unwrap();
 
     let mut ext = TestExternalities::from(t);
     ext.register_extension(KeystoreExt(std::sync::Arc::new(keystore)));
+    ext.register_extension(TaskExecutorExt::new(TaskExecutor::new()));
     (ext, alice_pub_key, karl_pub_key)
 }
 
@@ -451,12 +744,15 @@ pub fn multiple_keys_test_ext() -> (TestExternalities, Vec<(Public, H256)>) {
             //  alice is the stash and tom is a controller account.
             TransactionOutput::new_lock_for_staking(10, alice_hash, tom_hash, vec![3, 1]),
         ],
+        utxo_snapshot: None,
+        airdrop_root: None,
     }
     .assimilate_storage(&mut t)
     .unwrap();
 
     let mut ext = TestExternalities::from(t);
     ext.register_extension(KeystoreExt(std::sync::Arc::new(keystore)));
+    ext.register_extension(TaskExecutorExt::new(TaskExecutor::new()));
 
     MOCK_STAKING.with(|stake_info| {
         let mut stake_info = stake_info.borrow_mut();
@@ -465,10 +761,8 @@ pub fn multiple_keys_test_ext() -> (TestExternalities, Vec<(Public, H256)>) {
         stake_info.ctrl_map.insert(tom_hash, alice_hash);
     });
 
-    AUTHORITIES.with(|auths| {
-        let mut auths = auths.borrow_mut();
-        auths.push(alice_pub_key);
-    });
+    // the thread may have run another test before, don't list alice twice
+    AUTHORITIES.with(|auths| *auths.borrow_mut() = vec![alice_pub_key]);
 
     (
         ext,
@@ -0,0 +1,189 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Order outputs
+//!
+//! A `Destination::Order` output offers its MLT, or the single fungible token it carries, for at
+//! least `ask_amount` of the asked asset, MLT or a token. Anyone can fill the order by spending
+//! the UTXO with an empty witness, as long as the transaction pays the maker its due in
+//! pay-to-pubkey outputs. A partial fill leaves the rest of the offer in a remainder order of the
+//! same maker and assets, asking at least the price of the original for it. The maker cancels the
+//! order by spending the UTXO with a signature.
+//!
+//! A transaction fills at most one order of a maker for each pair of assets, so that the
+//! remainder of every order is known.
+
+use crate::pallet::nft_data_hash;
+use crate::tokens::{OutputData, TokenId, Value};
+use crate::{Config, Destination, TokenIssuanceTransactions, TransactionFor, TransactionOutput};
+use frame_support::sp_runtime::helpers_128bit::multiply_by_rational;
+use frame_support::{ensure, fail};
+use sp_core::sr25519;
+use sp_std::collections::{btree_map::BTreeMap, btree_set::BTreeSet};
+
+/// Asset and amount offered by an order output: MLT if the token is `None`.
+pub fn offer<AccountId>(output: &TransactionOutput<AccountId>) -> Option<(Option<TokenId>, Value)> {
+    match &output.data {
+        None => Some((None, output.value)),
+        Some(OutputData::TokenTransferV1 { token_id, amount }) if output.value == 0 => {
+            Some((Some(token_id.clone()), *amount))
+        }
+        _ => None,
+    }
+}
+
+/// Part of `ask_amount`, asked for `offered`, that is still asked for the `rest` of it.
+pub fn remainder_ask(ask_amount: Value, offered: Value, rest: Value) -> Value {
+    if rest >= offered {
+        return ask_amount;
+    }
+    // `rest < offered`, so the result never exceeds `ask_amount`
+    multiply_by_rational(ask_amount, rest, offered).unwrap_or(ask_amount)
+}
+
+/// Amount of the asset `token` paid by `output`, MLT if `None`.
+fn payment<AccountId>(output: &TransactionOutput<AccountId>, token: &Option<TokenId>) -> Value {
+    match (token, &output.data) {
+        (None, _) => output.value,
+        (Some(token), Some(OutputData::TokenTransferV1 { token_id, amount }))
+            if token == token_id =>
+        {
+            *amount
+        }
+        (Some(token), Some(OutputData::TokenMultiV1(tokens))) => tokens
+            .iter()
+            .filter(|(token_id, _)| token_id == token)
+            .fold(0, |total: Value, (_, amount)| total.saturating_add(*amount)),
+        _ => 0,
+    }
+}
+
+/// Check an output placing an order.
+pub(crate) fn validate_order<T: Config>(
+    output: &TransactionOutput<T::AccountId>,
+) -> Result<(), &'static str> {
+    let (ask_token, ask_amount) = match &output.destination {
+        Destination::Order {
+            ask_token,
+            ask_amount,
+            ..
+        } => (ask_token, *ask_amount),
+        _ => fail!("not an order output"),
+    };
+    ensure!(ask_amount > 0, "order must ask for a nonzero amount");
+    let offered_token = match offer(output) {
+        Some((token, _)) => token,
+        None => fail!("order must offer MLT or a single token"),
+    };
+    if let Some(token_id) = &offered_token {
+        ensure!(
+            nft_data_hash::<T>(token_id).is_none(),
+            "nfts can't be offered in orders"
+        );
+    }
+    if let Some(token_id) = ask_token {
+        ensure!(
+            <TokenIssuanceTransactions<T>>::contains_key(token_id),
+            "asked token not found"
+        );
+        ensure!(
+            nft_data_hash::<T>(token_id).is_none(),
+            "nfts can't be asked in orders"
+        );
+    }
+    ensure!(
+        offered_token != *ask_token,
+        "order must exchange different assets"
+    );
+    Ok(())
+}
+
+/// Check the orders `tx` fills, the order UTXOs among `utxos` it spends with an empty witness.
+/// The rest of each order has to be offered again at the same price or better, and the makers
+/// have to be paid for the part taken.
+pub(crate) fn check_fills<T: Config>(
+    tx: &TransactionFor<T>,
+    utxos: &[TransactionOutput<T::AccountId>],
+) -> Result<(), &'static str> {
+    let mut due: BTreeMap<(&sr25519::Public, &Option<TokenId>), Value> = BTreeMap::new();
+    let mut filled = BTreeSet::new();
+    for (input, utxo) in tx.inputs.iter().zip(utxos) {
+        let (maker, ask_token, ask_amount) = match &utxo.destination {
+            Destination::Order {
+                maker,
+                ask_token,
+                ask_amount,
+            } if input.witness.is_empty() => (maker, ask_token, *ask_amount),
+            _ => continue,
+        };
+        let (offered_token, offered) = offer(utxo).ok_or("corrupted order")?;
+        ensure!(
+            filled.insert((maker, ask_token, offered_token.clone())),
+            "only one order of a maker per pair of assets can be filled"
+        );
+
+        let mut remainders = tx.outputs.iter().filter_map(|output| match &output.destination {
+            Destination::Order {
+                maker: m,
+                ask_token: a,
+                ask_amount,
+            } if m == maker && a == ask_token => match offer(output) {
+                Some((token, rest)) if token == offered_token => Some((rest, *ask_amount)),
+                _ => None,
+            },
+            _ => None,
+        });
+        let (rest, rest_ask) = remainders.next().unwrap_or((0, 0));
+        ensure!(
+            remainders.next().is_none(),
+            "order has more than one remainder"
+        );
+        ensure!(rest < offered, "order fill must take some of the order");
+        let kept = remainder_ask(ask_amount, offered, rest);
+        ensure!(rest_ask >= kept, "remainder order asks too little");
+
+        let entry = due.entry((maker, ask_token)).or_insert(0);
+        *entry = entry.checked_add(ask_amount - kept).ok_or("order payment overflow")?;
+    }
+
+    for ((maker, ask_token), owed) in due {
+        let paid = tx
+            .outputs
+            .iter()
+            .filter(|output| matches!(output.destination, Destination::Pubkey(pubkey) if pubkey == *maker))
+            .fold(0, |total: Value, output| total.saturating_add(payment(output, ask_token)));
+        ensure!(paid >= owed, "order maker is not paid in full");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_remainder_ask() {
+        // 100 offered for 30
+        assert_eq!(remainder_ask(30, 100, 0), 0);
+        assert_eq!(remainder_ask(30, 100, 50), 15);
+        // The maker is owed 30 - 9 = 21 for the 70 taken, rounded up
+        assert_eq!(remainder_ask(30, 100, 33), 9);
+        assert_eq!(remainder_ask(30, 100, 100), 30);
+        assert_eq!(
+            remainder_ask(u128::MAX, u128::MAX, u128::MAX - 1),
+            u128::MAX - 1
+        );
+    }
+}
@@ -0,0 +1,65 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Emergency pause of spends
+//!
+//! `Config::PauseOrigin` can halt spending with `set_paused` while a consensus bug is being fixed,
+//! without waiting for a runtime upgrade. Either all transactions are rejected, or only those
+//! moving, issuing or burning tokens. The check is part of the transaction checks, so the paused
+//! transactions are kept out of the pool as well as out of blocks. Unwrapping UTXOs into account
+//! balances is halted along with all transactions. Staking is not paused: the stash accounts can
+//! still unlock and withdraw their stake.
+
+use crate::{Config, Paused, TransactionFor, UtxoStore};
+use codec::{Decode, Encode};
+use frame_support::{ensure, RuntimeDebug};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+/// The transactions halted by `set_paused`.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub enum PauseScope {
+    /// Every transaction
+    All,
+    /// Transactions with token outputs, or spending UTXOs that hold tokens
+    Tokens,
+}
+
+/// Fails if the transaction is halted by the current pause.
+pub(crate) fn check_not_paused<T: Config>(tx: &TransactionFor<T>) -> Result<(), &'static str> {
+    match <Paused<T>>::get() {
+        None => Ok(()),
+        Some(PauseScope::All) => Err("spends are paused"),
+        Some(PauseScope::Tokens) => {
+            let has_tokens = tx.outputs.iter().any(|output| output.data.is_some())
+                || tx.inputs.iter().any(|input| {
+                    <UtxoStore<T>>::get(input.outpoint).map_or(false, |utxo| utxo.data.is_some())
+                });
+            ensure!(!has_tokens, "token operations are paused");
+            Ok(())
+        }
+    }
+}
+
+/// Fails if the spends of MLT UTXOs outside of transactions, such as `unwrap`, are halted by the
+/// current pause.
+pub(crate) fn check_mlt_spend_not_paused<T: Config>() -> Result<(), &'static str> {
+    ensure!(
+        <Paused<T>>::get() != Some(PauseScope::All),
+        "spends are paused"
+    );
+    Ok(())
+}
@@ -0,0 +1,179 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transaction pool priority
+//!
+//! The priority of a transaction in the pool is calculated by `Config::PriorityPolicy` from the
+//! fee it pays, its weight and the age of the coins it spends. The policy also sets the minimum
+//! fee a transaction has to pay to enter the pool. The minimum fee is not a consensus rule, so
+//! blocks containing cheaper transactions are still valid.
+//!
+//! Fees can also be paid in the tokens listed in `Config::AcceptedFeeAssets`. These count towards
+//! the priority and the minimum fee at the MLT value given by the listed rate, and are paid to the
+//! block author as they are.
+
+use crate::tokens::{TokenId, Value};
+use codec::{Decode, Encode};
+use core::marker::PhantomData;
+use frame_support::{
+    sp_runtime::{
+        helpers_128bit::multiply_by_rational, traits::UniqueSaturatedInto,
+        transaction_validity::TransactionPriority,
+    },
+    traits::Get,
+    weights::Weight,
+    RuntimeDebug,
+};
+
+/// Fee rates are expressed in units of value per this much weight.
+pub const FEE_RATE_WEIGHT: Weight = 1_000_000;
+
+/// Data of a transaction its priority is calculated from.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub struct PriorityInfo {
+    /// Fee paid in MLT, including the MLT value of the fee paid in tokens
+    pub fee: Value,
+    /// Weight of spending the transaction
+    pub weight: Weight,
+    /// Sum of the values of the spent UTXOs multiplied by their age in blocks. UTXOs of unknown
+    /// age, such as the genesis ones, don't contribute.
+    pub coin_age: Value,
+}
+
+impl PriorityInfo {
+    /// Combined data of a package of transactions, such as a parent and a child paying for it.
+    pub fn package(infos: &[PriorityInfo]) -> Self {
+        infos.iter().fold(Self::default(), |total, info| Self {
+            fee: total.fee.saturating_add(info.fee),
+            weight: total.weight.saturating_add(info.weight),
+            coin_age: total.coin_age.saturating_add(info.coin_age),
+        })
+    }
+
+    /// Fee per [FEE_RATE_WEIGHT] units of weight.
+    pub fn fee_rate(&self) -> Value {
+        fee_rate(self.fee, self.weight)
+    }
+}
+
+fn fee_rate(fee: Value, weight: Weight) -> Value {
+    fee.saturating_mul(FEE_RATE_WEIGHT.into()) / Value::from(weight.max(1))
+}
+
+/// A token accepted to pay transaction fees, worth `mlt_value` MLT per `token_amount` units.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct FeeAsset {
+    pub token_id: TokenId,
+    pub mlt_value: Value,
+    pub token_amount: Value,
+}
+
+impl FeeAsset {
+    /// MLT value of `amount` units of the token, rounded down.
+    pub fn to_mlt(&self, amount: Value) -> Value {
+        multiply_by_rational(amount, self.mlt_value, self.token_amount.max(1)).unwrap_or(Value::MAX)
+    }
+}
+
+/// Calculates transaction priority in the pool.
+pub trait PriorityPolicy {
+    /// Priority of a transaction, higher is included first.
+    fn priority(info: &PriorityInfo) -> TransactionPriority;
+
+    /// Minimum fee of a transaction of given weight to be accepted into the pool.
+    fn minimum_fee(_weight: Weight) -> Value {
+        0
+    }
+}
+
+/// The priority is the fee, no minimum fee.
+impl PriorityPolicy for () {
+    fn priority(info: &PriorityInfo) -> TransactionPriority {
+        info.fee.unique_saturated_into()
+    }
+}
+
+/// The priority is the fee rate. Transactions paying less than `MinFeeRate` per
+/// [FEE_RATE_WEIGHT] units of weight are rejected.
+pub struct FeePerWeight<MinFeeRate>(PhantomData<MinFeeRate>);
+
+impl<MinFeeRate: Get<Value>> PriorityPolicy for FeePerWeight<MinFeeRate> {
+    fn priority(info: &PriorityInfo) -> TransactionPriority {
+        info.fee_rate().unique_saturated_into()
+    }
+
+    fn minimum_fee(weight: Weight) -> Value {
+        minimum_fee(MinFeeRate::get(), weight)
+    }
+}
+
+/// Like [FeePerWeight], but the coin age of the spent UTXOs counts towards the priority as if
+/// `AgeFactor` units of coin age paid one unit of fee. The minimum fee still has to be paid.
+pub struct AgeWeighted<MinFeeRate, AgeFactor>(PhantomData<(MinFeeRate, AgeFactor)>);
+
+impl<MinFeeRate: Get<Value>, AgeFactor: Get<Value>> PriorityPolicy
+    for AgeWeighted<MinFeeRate, AgeFactor>
+{
+    fn priority(info: &PriorityInfo) -> TransactionPriority {
+        let age_bonus = info.coin_age / AgeFactor::get().max(1);
+        fee_rate(info.fee.saturating_add(age_bonus), info.weight).unique_saturated_into()
+    }
+
+    fn minimum_fee(weight: Weight) -> Value {
+        minimum_fee(MinFeeRate::get(), weight)
+    }
+}
+
+/// Fee paying exactly `rate` for given weight, rounded up.
+fn minimum_fee(rate: Value, weight: Weight) -> Value {
+    let rate_weight = Value::from(FEE_RATE_WEIGHT);
+    let fee = rate.saturating_mul(weight.into());
+    fee / rate_weight + Value::from(fee % rate_weight != 0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    frame_support::parameter_types! {
+        pub const MinFeeRate: Value = 10;
+        pub const AgeFactor: Value = 1_000;
+    }
+
+    #[test]
+    fn test_priority_policies() {
+        let info = PriorityInfo {
+            fee: 100,
+            weight: 2_000_000,
+            coin_age: 50_000,
+        };
+        assert_eq!(<() as PriorityPolicy>::priority(&info), 100);
+        assert_eq!(<() as PriorityPolicy>::minimum_fee(info.weight), 0);
+        assert_eq!(FeePerWeight::<MinFeeRate>::priority(&info), 50);
+        assert_eq!(FeePerWeight::<MinFeeRate>::minimum_fee(info.weight), 20);
+        assert_eq!(FeePerWeight::<MinFeeRate>::minimum_fee(1_500_001), 16);
+        assert_eq!(AgeWeighted::<MinFeeRate, AgeFactor>::priority(&info), 75);
+        assert_eq!(
+            AgeWeighted::<MinFeeRate, AgeFactor>::minimum_fee(info.weight),
+            20
+        );
+
+        let weightless = PriorityInfo { weight: 0, ..info };
+        assert_eq!(
+            FeePerWeight::<MinFeeRate>::priority(&weightless),
+            100_000_000
+        );
+    }
+}
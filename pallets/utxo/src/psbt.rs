@@ -0,0 +1,249 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Partially signed transactions
+//!
+//! A [PartiallySignedTransaction] carries an unsigned transaction together with everything a
+//! signer needs to sign it without access to the chain state, and collects the signatures as
+//! they are made. The envelope is SCALE encoded to be passed between devices:
+//! 1. The creator makes an envelope with [PartiallySignedTransaction::new] and fills in the
+//!    spent UTXOs, the sighash types and the key derivation hints.
+//! 2. Each signer signs the inputs it holds the keys for with [PartiallySignedTransaction::sign],
+//!    or adds a signature made elsewhere (e.g. by a hardware wallet over
+//!    [PartiallySignedTransaction::signature_message]) with
+//!    [PartiallySignedTransaction::add_signature].
+//! 3. Envelopes signed independently are merged with [PartiallySignedTransaction::combine].
+//! 4. [PartiallySignedTransaction::finalize] turns the signatures into witnesses and
+//!    [PartiallySignedTransaction::extract] produces the transaction to submit.
+//!
+//! Inputs spending a `Destination::Pubkey`, a `Destination::Vesting`, a `Destination::Stream`, a
+//! `Destination::Inheritance` or the key path of a `Destination::Taproot` are finalized
+//! automatically. For script inputs, the witness has to be
+//! assembled from the partial signatures by the caller and stored in [PsbtInput::final_witness].
+
+use crate::sign::{SignatureScheme, TransactionSigMsg, WITNESS_SCHEME_MARKER};
+use crate::{Destination, Transaction, TransactionOutput};
+use codec::{Decode, Encode};
+use frame_support::{ensure, sp_io::crypto};
+use sp_core::{sr25519, testing::SR25519};
+use sp_std::collections::btree_map::BTreeMap;
+use sp_std::prelude::*;
+
+pub use crate::sign::SigHash;
+
+/// Where a key comes from, so that a signer can find out whether it holds it.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, Default)]
+pub struct KeyOrigin {
+    /// Fingerprint of the master key
+    pub fingerprint: [u8; 4],
+    /// Derivation path from the master key
+    pub path: Vec<u32>,
+}
+
+/// Signing data of one input.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug)]
+pub struct PsbtInput<AccountId> {
+    /// The output spent by the input
+    pub utxo: Option<TransactionOutput<AccountId>>,
+    /// Sighash byte the signatures commit with, 0 for the default sighash
+    pub sighash: u8,
+    /// Signatures collected so far
+    pub partial_sigs: BTreeMap<sr25519::Public, sr25519::Signature>,
+    /// Origins of the keys involved in spending the input
+    pub derivations: BTreeMap<sr25519::Public, KeyOrigin>,
+    /// The witness of the input once it is finalized
+    pub final_witness: Option<Vec<u8>>,
+}
+
+impl<AccountId> Default for PsbtInput<AccountId> {
+    fn default() -> Self {
+        Self {
+            utxo: None,
+            sighash: 0,
+            partial_sigs: BTreeMap::new(),
+            derivations: BTreeMap::new(),
+            final_witness: None,
+        }
+    }
+}
+
+/// Signing data of one output.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, Default)]
+pub struct PsbtOutput {
+    /// Origins of the keys the output pays to, used by signers to recognize change outputs
+    pub derivations: BTreeMap<sr25519::Public, KeyOrigin>,
+}
+
+/// A transaction in the process of being signed by one or more parties.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug)]
+pub struct PartiallySignedTransaction<AccountId> {
+    /// The unsigned transaction
+    pub tx: Transaction<AccountId>,
+    /// Signing data of the inputs, one per transaction input
+    pub inputs: Vec<PsbtInput<AccountId>>,
+    /// Signing data of the outputs, one per transaction output
+    pub outputs: Vec<PsbtOutput>,
+}
+
+impl<AccountId: Encode + Clone + Eq> PartiallySignedTransaction<AccountId> {
+    /// Create an envelope for an unsigned transaction.
+    pub fn new(tx: Transaction<AccountId>) -> Result<Self, &'static str> {
+        ensure!(
+            tx.inputs.iter().all(|input| input.witness.is_empty()),
+            "transaction must be unsigned"
+        );
+        Ok(Self {
+            inputs: tx.inputs.iter().map(|_| Default::default()).collect(),
+            outputs: tx.outputs.iter().map(|_| Default::default()).collect(),
+            tx,
+        })
+    }
+
+    fn input(&mut self, index: usize) -> Result<&mut PsbtInput<AccountId>, &'static str> {
+        self.inputs.get_mut(index).ok_or("input index out of range")
+    }
+
+    /// Record the UTXO spent by the input at `index`.
+    pub fn set_utxo(
+        &mut self,
+        index: usize,
+        utxo: TransactionOutput<AccountId>,
+    ) -> Result<(), &'static str> {
+        let lock_hash = self.tx.inputs.get(index).ok_or("input index out of range")?.lock_hash();
+        if let Some(lock_commitment) = utxo.destination.lock_commitment() {
+            ensure!(lock_hash == *lock_commitment, "Lock hash does not match");
+        }
+        self.input(index)?.utxo = Some(utxo);
+        Ok(())
+    }
+
+    /// Select the sighash used to sign the input at `index`.
+    pub fn set_sighash(&mut self, index: usize, sighash: SigHash) -> Result<(), &'static str> {
+        let input = self.input(index)?;
+        ensure!(input.partial_sigs.is_empty(), "input already signed");
+        input.sighash = sighash.encode()[0];
+        Ok(())
+    }
+
+    /// All the UTXOs spent by the transaction.
+    pub fn utxos(&self) -> Result<Vec<TransactionOutput<AccountId>>, &'static str> {
+        self.inputs
+            .iter()
+            .map(|input| input.utxo.clone().ok_or("missing utxo"))
+            .collect()
+    }
+
+    fn sighash(&self, index: usize) -> Result<SigHash, &'static str> {
+        match self.inputs.get(index).ok_or("input index out of range")?.sighash {
+            0 => Ok(SigHash::default()),
+            byte => SigHash::from_u8(byte).ok_or("invalid sighash"),
+        }
+    }
+
+    /// The message to be signed for the input at `index`.
+    pub fn signature_message(&self, index: usize) -> Result<Vec<u8>, &'static str> {
+        let sighash = self.sighash(index)?;
+        let utxos = self.utxos()?;
+        let msg = TransactionSigMsg::construct(sighash, &self.tx, &utxos, index as u64, u32::MAX);
+        Ok(msg.encode())
+    }
+
+    /// Add a signature for the input at `index` made by `pubkey`.
+    pub fn add_signature(
+        &mut self,
+        index: usize,
+        pubkey: sr25519::Public,
+        signature: sr25519::Signature,
+    ) -> Result<(), &'static str> {
+        let msg = self.signature_message(index)?;
+        ensure!(
+            crypto::sr25519_verify(&signature, &msg, &pubkey),
+            "signature must be valid"
+        );
+        self.input(index)?.partial_sigs.insert(pubkey, signature);
+        Ok(())
+    }
+
+    /// Sign the input at `index` with a key from the keystore. Only Schnorr supported for now.
+    pub fn sign(&mut self, index: usize, pubkey: &sr25519::Public) -> Result<(), &'static str> {
+        let msg = self.signature_message(index)?;
+        let signature = crypto::sr25519_sign(SR25519, pubkey, &msg).ok_or("key not found")?;
+        self.input(index)?.partial_sigs.insert(*pubkey, signature);
+        Ok(())
+    }
+
+    /// Merge the data collected in two envelopes of the same transaction.
+    pub fn combine(mut self, other: Self) -> Result<Self, &'static str> {
+        ensure!(self.tx == other.tx, "transactions must match");
+        for (input, other) in self.inputs.iter_mut().zip(other.inputs) {
+            match (&input.utxo, other.utxo) {
+                (Some(utxo), Some(other)) => ensure!(*utxo == other, "utxos must match"),
+                (None, other) => input.utxo = other,
+                (Some(_), None) => (),
+            }
+            ensure!(input.sighash == other.sighash, "sighashes must match");
+            input.partial_sigs.extend(other.partial_sigs);
+            input.derivations.extend(other.derivations);
+            if input.final_witness.is_none() {
+                input.final_witness = other.final_witness;
+            }
+        }
+        for (output, other) in self.outputs.iter_mut().zip(other.outputs) {
+            output.derivations.extend(other.derivations);
+        }
+        Ok(self)
+    }
+
+    /// Build the witnesses of the inputs that are not finalized yet.
+    pub fn finalize(&mut self) -> Result<(), &'static str> {
+        for (input, tx_input) in self.inputs.iter_mut().zip(&self.tx.inputs) {
+            if input.final_witness.is_some() {
+                continue;
+            }
+            let utxo = input.utxo.as_ref().ok_or("missing utxo")?;
+            let pubkey = match &utxo.destination {
+                Destination::Pubkey(pubkey)
+                | Destination::Vesting { pubkey, .. }
+                | Destination::Stream { payee: pubkey, .. } => pubkey,
+                Destination::Inheritance { owner, .. } if tx_input.lock.is_empty() => owner,
+                Destination::Inheritance { heir, .. } => heir,
+                Destination::Taproot { internal_key, .. } if tx_input.lock.is_empty() => {
+                    internal_key
+                }
+                _ => return Err("input cannot be finalized"),
+            };
+            let signature = input.partial_sigs.get(pubkey).ok_or("missing signature")?;
+            let mut witness = vec![WITNESS_SCHEME_MARKER, SignatureScheme::Schnorr as u8];
+            witness.extend_from_slice(&signature.0);
+            // The default sighash is implied by a missing sighash byte
+            if input.sighash != 0 {
+                witness.push(input.sighash);
+            }
+            input.final_witness = Some(witness);
+            input.partial_sigs.clear();
+            input.derivations.clear();
+        }
+        Ok(())
+    }
+
+    /// Extract the signed transaction once all the inputs are finalized.
+    pub fn extract(self) -> Result<Transaction<AccountId>, &'static str> {
+        let mut tx = self.tx;
+        for (tx_input, input) in tx.inputs.iter_mut().zip(self.inputs) {
+            tx_input.witness = input.final_witness.ok_or("input not finalized")?;
+        }
+        Ok(tx)
+    }
+}
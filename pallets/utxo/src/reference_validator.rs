@@ -0,0 +1,174 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reference implementation of the value conservation rules
+//!
+//! A deliberately simple and slow restatement of the MLT and token conservation rules enforced by
+//! the transaction checks, written from the rules rather than from the production code:
+//! * the MLT of the outputs doesn't exceed the MLT of the inputs, the rest is the fee,
+//! * the fee covers the issuance fee of a token or NFT created by the transaction,
+//! * a transaction creates at most one token or NFT, with the id derived from its first input,
+//! * any other token sent or burned by the outputs comes from the inputs, in the same amount, or
+//!   in a smaller one when the token is accepted to pay fees,
+//! * tokens in the inputs can't vanish, they are sent on, burned or paid as a fee.
+//!
+//! The differential tests in `tests.rs` run randomized transactions through both implementations
+//! and assert they agree, so the production checks can't silently change what they accept when
+//! they are refactored. The other rules, such as signatures, locks or token metadata, are not
+//! covered.
+
+use crate::{
+    tokens::{OutputData, TokenId, Value},
+    Config, TokenIssuanceId, Transaction, UtxoStore,
+};
+use frame_support::ensure;
+use sp_core::H256;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// MLT and tokens an input brings to a transaction.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputValue {
+    pub mlt: Value,
+    pub tokens: Vec<(TokenId, Value)>,
+}
+
+/// The fees of creating a token or an NFT.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct IssuanceFees {
+    pub token_issuance: Value,
+    pub nft_mint: Value,
+}
+
+/// What spending the UTXO at `outpoint` brings to a transaction, `None` if it can't be spent.
+pub fn input_value<T: Config>(outpoint: &H256) -> Option<InputValue> {
+    let utxo = <UtxoStore<T>>::get(outpoint)?;
+    let tokens = match utxo.data {
+        None | Some(OutputData::TokenBurnV1 { .. }) => Vec::new(),
+        Some(OutputData::TokenIssuanceV1 {
+            amount_to_issue, ..
+        }) => vec![(<TokenIssuanceId<T>>::get(outpoint)?, amount_to_issue)],
+        Some(OutputData::TokenTransferV1 { token_id, amount }) => vec![(token_id, amount)],
+        Some(OutputData::NftMintV1 { token_id, .. }) => vec![(token_id, 1)],
+        Some(OutputData::TokenMultiV1(tokens)) => tokens,
+        Some(OutputData::TokenLockForBridgeV1 { .. }) => return None,
+    };
+    Some(InputValue {
+        mlt: utxo.value,
+        tokens,
+    })
+}
+
+fn add(total: &mut Value, value: Value) -> Result<(), &'static str> {
+    *total = total.checked_add(value).ok_or("value overflow")?;
+    Ok(())
+}
+
+/// Check `tx`, spending `inputs`, conserves MLT and tokens, returning the MLT fee it pays.
+/// `fee_assets` are the tokens accepted to pay fees.
+pub fn check_conservation<AccountId>(
+    tx: &Transaction<AccountId>,
+    inputs: &[InputValue],
+    fee_assets: &[TokenId],
+    fees: IssuanceFees,
+) -> Result<Value, &'static str> {
+    let new_token_id = TokenId::new(tx.inputs.first().ok_or("no inputs")?);
+
+    let mut mlt_in: Value = 0;
+    let mut tokens_in: BTreeMap<TokenId, Value> = BTreeMap::new();
+    for input in inputs {
+        add(&mut mlt_in, input.mlt)?;
+        for (token_id, amount) in &input.tokens {
+            add(tokens_in.entry(token_id.clone()).or_default(), *amount)?;
+        }
+    }
+
+    let mut mlt_out: Value = 0;
+    let mut tokens_out: BTreeMap<TokenId, Value> = BTreeMap::new();
+    let mut issuance_fees = Vec::new();
+    for output in &tx.outputs {
+        add(&mut mlt_out, output.value)?;
+        let (issuance_fee, moved) = match &output.data {
+            None => (None, Vec::new()),
+            Some(OutputData::TokenIssuanceV1 {
+                amount_to_issue, ..
+            }) => (
+                Some(fees.token_issuance),
+                vec![(new_token_id.clone(), *amount_to_issue)],
+            ),
+            Some(OutputData::NftMintV1 { token_id, .. }) => {
+                ensure!(
+                    *token_id == new_token_id,
+                    "nft id not derived from the first input"
+                );
+                (Some(fees.nft_mint), vec![(token_id.clone(), 1)])
+            }
+            Some(OutputData::TokenTransferV1 { token_id, amount }) => {
+                (None, vec![(token_id.clone(), *amount)])
+            }
+            Some(OutputData::TokenBurnV1 {
+                token_id,
+                amount_to_burn,
+            }) => (None, vec![(token_id.clone(), *amount_to_burn)]),
+            Some(OutputData::TokenLockForBridgeV1 {
+                token_id, amount, ..
+            }) => (None, vec![(token_id.clone(), *amount)]),
+            Some(OutputData::TokenMultiV1(tokens)) => {
+                let ids: BTreeSet<&TokenId> = tokens.iter().map(|(token_id, _)| token_id).collect();
+                ensure!(!tokens.is_empty(), "no tokens in a multi-token output");
+                ensure!(
+                    ids.len() == tokens.len(),
+                    "token twice in a multi-token output"
+                );
+                (None, tokens.clone())
+            }
+        };
+        match issuance_fee {
+            Some(fee) => issuance_fees.push(fee),
+            // Only the tokens created by the transaction come from nowhere
+            None => ensure!(
+                moved.iter().all(|(token_id, _)| tokens_in.contains_key(token_id)),
+                "token sent without an input"
+            ),
+        }
+        for (token_id, amount) in moved {
+            add(tokens_out.entry(token_id).or_default(), amount)?;
+        }
+    }
+    ensure!(issuance_fees.len() <= 1, "more than one token created");
+
+    for (token_id, amount_out) in &tokens_out {
+        if let Some(amount_in) = tokens_in.get(token_id) {
+            ensure!(
+                amount_in == amount_out
+                    || (amount_in > amount_out && fee_assets.contains(token_id)),
+                "token outputs don't match the inputs"
+            );
+        }
+    }
+    for token_id in tokens_in.keys() {
+        ensure!(
+            tokens_out.contains_key(token_id) || fee_assets.contains(token_id),
+            "tokens vanished"
+        );
+    }
+
+    let fee = mlt_in.checked_sub(mlt_out).ok_or("outputs exceed the inputs")?;
+    ensure!(fee < u64::MAX as Value, "fee too large");
+    ensure!(
+        fee >= issuance_fees.into_iter().max().unwrap_or(0),
+        "issuance fee not paid"
+    );
+    Ok(fee)
+}
@@ -16,15 +16,52 @@
 // Author(s): C. Yap
 
 use crate::{
-    convert_to_h256, tokens::Value, BlockAuthor, Config, Event, Pallet, RewardTotal,
+    convert_to_h256,
+    staking::{pool::pro_rata_shares, EraIndex, StakingHelper},
+    tokens::{OutputData, TokenId, Value},
+    BlockAuthor, Config, DeferredReward, Destination, Event, MltIssuance, Pallet, PendingRewards,
+    RewardDestination, RewardHistory, RewardTotal, StakingPools, TokenRewardTotal,
     TransactionOutput, UtxoStore,
 };
 
-use frame_support::traits::Get;
+use codec::{Decode, Encode};
+use frame_support::{
+    ensure,
+    traits::{FindAuthor, Get},
+    weights::DispatchClass,
+    RuntimeDebug,
+};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 use sp_core::H256;
 use sp_runtime::traits::{BlakeTwo256, CheckedDiv, Hash, SaturatedConversion, Zero};
 use sp_runtime::Percent;
+use sp_std::collections::btree_set::BTreeSet;
 use sp_std::convert::TryInto;
+use sp_std::prelude::*;
+
+/// Where the MLT credited to the reward pool, `RewardTotal`, comes from.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub enum RewardSource {
+    /// The fee of a transaction
+    TransactionFee,
+    /// The fee of withdrawing unlocked stake
+    WithdrawalFee,
+}
+
+/// MLT credited to the reward pool and paid out of it in an era, see `RewardHistory`.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, Default)]
+pub struct EraRewards {
+    pub era: EraIndex,
+    /// Credited from transaction fees
+    pub transaction_fees: Value,
+    /// Credited from stake withdrawal fees
+    pub withdrawal_fees: Value,
+    /// Paid to each block author, block rewards included, in the order they were first paid
+    pub paid: Vec<(H256, Value)>,
+}
 
 /// handle event when a block author is found.
 impl<T> pallet_authorship::EventHandler<T::AccountId, T::BlockNumber> for Pallet<T>
@@ -48,25 +85,31 @@ where
     }
 }
 
+/// The author of the current block found by `Config::FindAuthor` in the pre-runtime digests of
+/// the block, such as the slot of an Aura block or the authority index of a Babe block.
+pub fn digest_author<T: Config>() -> Option<H256> {
+    let digest = <frame_system::Pallet<T>>::digest();
+    let pre_runtime_digests = digest.logs().iter().filter_map(|item| item.as_pre_runtime());
+    let author = T::FindAuthor::find_author(pre_runtime_digests)?;
+    convert_to_h256::<T>(&author)
+        .map_err(|e| log::warn!("failed to convert the digest author: {:?}", e))
+        .ok()
+}
+
 /// checks at what period the given block number belongs to.
 /// If it exceeds to the maximum value of u8 datatype,
 /// reduction_fraction is already way over 100%.
 fn increase_reduction_fraction<T: Config>(block_number: T::BlockNumber) -> Option<u8> {
     let reduction_period: T::BlockNumber = T::RewardReductionPeriod::get();
 
-    // When reduction_period is not set, there's no such thing as "block period".
-    // There's no need to compute for the "current" block period.
-    // Reward will not decrease at all.
-    if reduction_period.is_zero() {
-        return Some(0);
-    }
-
     // get at what period the current block number is at.
-    match block_number
-        .checked_div(&reduction_period)
-        .expect("successfully retrieved current block period.")
-        .try_into()
-    {
+    // When reduction_period is not set, there's no such thing as "block period",
+    // and the reward will not decrease at all.
+    let block_period = match block_number.checked_div(&reduction_period) {
+        Some(block_period) => block_period,
+        None => return Some(0),
+    };
+    match block_period.try_into() {
         Ok(result) => Some(result),
         Err(_) => {
             // block_period exceeds the maximum threshold.
@@ -79,6 +122,10 @@ fn increase_reduction_fraction<T: Config>(block_number: T::BlockNumber) -> Optio
 /// How much a reward is reduced, will be based on the config's`RewardReductionFraction`.
 fn get_block_author_reward<T: Config>(block_number: T::BlockNumber) -> Value {
     let reduction_fraction = T::RewardReductionFraction::get().deconstruct();
+    // The reward is never reduced
+    if reduction_fraction == 0 {
+        return T::InitialReward::get();
+    }
     let last_block_rewarded_period = (100u8 / reduction_fraction) - 1;
 
     match increase_reduction_fraction::<T>(block_number) {
@@ -101,42 +148,274 @@ fn get_block_author_reward<T: Config>(block_number: T::BlockNumber) -> Value {
     T::DefaultMinimumReward::get()
 }
 
+/// Key of the `index`th reward utxo paid for the block `block_number` authored by `author`.
+/// The author and the index salt the key, so that equal rewards, e.g. to a pool member listed
+/// twice, are never merged into one.
+pub(crate) fn reward_utxo_hash<AccountId: Encode>(
+    block_number: u64,
+    author: &H256,
+    index: u32,
+    utxo: &TransactionOutput<AccountId>,
+) -> H256 {
+    BlakeTwo256::hash_of(&(utxo, block_number, author, index, "author_reward"))
+}
+
 fn insert_to_utxo_store<T: Config>(
     block_number: T::BlockNumber,
-    block_author: H256,
-    reward: Value,
+    author: &H256,
+    index: u32,
+    utxo: TransactionOutput<T::AccountId>,
 ) {
-    let utxo = TransactionOutput::new_pubkey(reward, block_author.clone());
+    // nothing is left to pay once the issuance reached `MaxMoney` in a block without fees
+    if utxo.value.is_zero() && utxo.data.is_none() {
+        return;
+    }
+    let hash = reward_utxo_hash(block_number.saturated_into::<u64>(), author, index, &utxo);
 
-    //TODO: https://github.com/mintlayer/core/pull/83#discussion_r742773343
-    let hash = {
-        let b_num = block_number.saturated_into::<u64>();
-        BlakeTwo256::hash_of(&(&utxo, b_num, "author_reward"))
-    };
+    if <UtxoStore<T>>::contains_key(hash) {
+        // the salt makes this unreachable, but a reward must never overwrite a utxo
+        log::error!("reward utxo {:?} already exists", hash);
+        return;
+    }
+    // the hooks run in `on_finalize`, after the weight of the block was set
+    let hooks_weight = crate::pallet::create_utxo::<T>(hash, &utxo);
+    if hooks_weight > 0 {
+        <frame_system::Pallet<T>>::register_extra_weight_unchecked(
+            hooks_weight,
+            DispatchClass::Mandatory,
+        );
+    }
+
+    <Pallet<T>>::deposit_event(Event::<T>::BlockAuthorRewarded(utxo));
+}
+
+/// Splits the authorities into the distinct ones, in the order they first appear, and the keys
+/// listed again after their first appearance.
+pub(crate) fn dedup_authorities(authorities: Vec<H256>) -> (Vec<H256>, Vec<H256>) {
+    let mut seen = BTreeSet::new();
+    authorities.into_iter().partition(|authority| seen.insert(*authority))
+}
+
+/// Reports an authority set that can't be used as is. The rewards don't depend on the authority
+/// set: an author is paid once per block whether it is listed once, several times or not at all.
+fn check_authorities<T: Config>() {
+    let authorities = T::authorities();
+    if authorities.is_empty() {
+        log::warn!("the authority set is empty");
+        <Pallet<T>>::deposit_event(Event::<T>::NoAuthorities);
+        return;
+    }
+    for duplicate in dedup_authorities(authorities).1 {
+        log::warn!("authority {:?} is listed more than once", duplicate);
+        <Pallet<T>>::deposit_event(Event::<T>::DuplicateAuthority(duplicate));
+    }
+}
 
-    if !<UtxoStore<T>>::contains_key(hash) {
-        <UtxoStore<T>>::insert(hash, utxo.clone());
+/// A utxo paying `amount` of the token fees to `recipient`.
+fn token_fee_utxo<T: Config>(
+    recipient: H256,
+    token_id: TokenId,
+    amount: Value,
+) -> TransactionOutput<T::AccountId> {
+    TransactionOutput::new_p2pk_with_data(
+        0,
+        recipient,
+        OutputData::TokenTransferV1 { token_id, amount },
+    )
+}
+
+/// Updates the rewards of the current era in `RewardHistory` with `update`, dropping the eras
+/// older than the last `RewardHistoryDepth` ones.
+fn record_era_rewards<T: Config>(update: impl FnOnce(&mut EraRewards)) {
+    let depth = T::RewardHistoryDepth::get();
+    if depth.is_zero() {
+        return;
+    }
+    let era = T::StakingHelper::current_era();
+    <RewardHistory<T>>::mutate(|history| {
+        history.retain(|rewards| rewards.era.saturating_add(depth) > era);
+        if history.last().map_or(true, |rewards| rewards.era != era) {
+            history.push(EraRewards {
+                era,
+                ..Default::default()
+            });
+        }
+        if let Some(rewards) = history.last_mut() {
+            update(rewards);
+        }
+    });
+}
+
+/// Credits `amount` from `source` to the reward pool, paid to the author of the current block.
+pub(crate) fn credit_reward_pool<T: Config>(
+    amount: Value,
+    source: RewardSource,
+) -> Result<(), &'static str> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+    let new_total = <RewardTotal<T>>::get().checked_add(amount).ok_or("Reward overflow")?;
+    log::debug!("reward total: {:?}", new_total);
+    <RewardTotal<T>>::put(new_total);
+    record_era_rewards::<T>(|rewards| {
+        let credited = match source {
+            RewardSource::TransactionFee => &mut rewards.transaction_fees,
+            RewardSource::WithdrawalFee => &mut rewards.withdrawal_fees,
+        };
+        *credited = credited.saturating_add(amount);
+    });
+    <Pallet<T>>::deposit_event(Event::<T>::RewardPoolCredited(amount, source));
+    Ok(())
+}
+
+/// Records `amount` paid to the block `author`.
+fn record_reward_paid<T: Config>(author: H256, amount: Value) {
+    if amount.is_zero() {
+        return;
+    }
+    record_era_rewards::<T>(|rewards| {
+        match rewards.paid.iter_mut().find(|(paid_to, _)| *paid_to == author) {
+            Some((_, paid)) => *paid = paid.saturating_add(amount),
+            None => rewards.paid.push((author, amount)),
+        }
+    });
+    <Pallet<T>>::deposit_event(Event::<T>::RewardPaid(author, amount));
+}
 
-        <Pallet<T>>::deposit_event(Event::<T>::BlockAuthorRewarded(utxo));
+/// Adds the rewards claimed in the previous block to the utxo set.
+fn release_pending_rewards<T: Config>() {
+    for (index, (earned_at, utxo)) in <PendingRewards<T>>::take().into_iter().enumerate() {
+        // the claimed destination stands for the author, it is unique to the block
+        let claimed_by = BlakeTwo256::hash_of(&utxo.destination);
+        insert_to_utxo_store::<T>(earned_at, &claimed_by, index as u32, utxo);
     }
 }
 
+/// Checks a destination the reward of a block is claimed to with the `claim_reward` inherent.
+pub(crate) fn validate_reward_destination<T: Config>(
+    destination: &Destination<T::AccountId>,
+) -> Result<(), &'static str> {
+    ensure!(
+        matches!(
+            destination,
+            Destination::Pubkey(_) | Destination::ScriptHash(..) | Destination::Taproot { .. }
+        ),
+        "Reward can't be claimed to the destination"
+    );
+    Ok(())
+}
+
+/// Holds the reward and the token fees claimed to `destination` until the next block.
+fn claim_to_destination<T: Config>(
+    block_number: T::BlockNumber,
+    destination: Destination<T::AccountId>,
+    reward_amount: Value,
+    token_fees: Vec<(TokenId, Value)>,
+) {
+    let mut pending = sp_std::vec![(
+        block_number,
+        TransactionOutput {
+            value: reward_amount,
+            destination: destination.clone(),
+            data: None,
+        },
+    )];
+    pending.extend(token_fees.into_iter().map(|(token_id, amount)| {
+        let utxo = TransactionOutput {
+            value: 0,
+            destination: destination.clone(),
+            data: Some(OutputData::TokenTransferV1 { token_id, amount }),
+        };
+        (block_number, utxo)
+    }));
+    <PendingRewards<T>>::put(pending);
+}
+
 /// Rewards the block author with a utxo of value based on the `BlockAuthorRewardAmount`
-/// and the transaction fees.
+/// and the transaction fees. The fees paid in tokens are paid in further utxos.
+/// A reward claimed to another destination with the `claim_reward` inherent is only added to
+/// the utxo set at the end of the next block.
+/// The author is the one noted by `pallet-authorship`, or else the one found in the pre-runtime
+/// digest of the block, see `digest_author`.
+/// When no author was found for the block, e.g. while the authorities are not set, the reward
+/// and the fees are deferred and paid to the next block author found on top of its own.
 pub(crate) fn reward_block_author<T: Config>(block_number: T::BlockNumber) {
+    check_authorities::<T>();
+    release_pending_rewards::<T>();
+    let claimed_destination = <RewardDestination<T>>::take();
+
+    // As written on the definition of Take:
+    // Take a value from storage, removing it afterwards.
+    // This is taking a value of the BlockAuthor storage, freeing it up.
+    // Without an author noted by `pallet-authorship`, the one sealing the block is looked up in
+    // its digest.
+    let block_author = match <BlockAuthor<T>>::take().or_else(digest_author::<T>) {
+        Some(block_author) => block_author,
+        None => {
+            // the fees stay in `RewardTotal` and `TokenRewardTotal`
+            let deferred = get_block_author_reward::<T>(block_number);
+            <DeferredReward<T>>::mutate(|total| *total = total.saturating_add(deferred));
+            log::warn!(
+                "no author found for block {:?}, deferring its reward.",
+                block_number
+            );
+            <Pallet<T>>::deposit_event(Event::<T>::RewardDeferred(block_number, deferred));
+            return;
+        }
+    };
+
     // As written on the definition of Take:
     // Take a value from storage, removing it afterwards.
     // This is taking a value of the RewardTotal storage, freeing it up.
     let transaction_fees = <RewardTotal<T>>::take();
-
-    if let Some(reward_amount) =
-        get_block_author_reward::<T>(block_number).checked_add(transaction_fees)
-    {
-        // As written on the definition of Take:
-        // Take a value from storage, removing it afterwards.
-        // This is taking a value of the BlockAuthor storage, freeing it up.
-        let block_author = <BlockAuthor<T>>::take().expect("Block author found.");
-        insert_to_utxo_store::<T>(block_number, block_author, reward_amount)
+    // no more MLT is minted once the issuance reaches `MaxMoney`
+    let headroom = T::MaxMoney::get().saturating_sub(<MltIssuance<T>>::get());
+    let block_reward = get_block_author_reward::<T>(block_number)
+        .saturating_add(<DeferredReward<T>>::take())
+        .min(headroom);
+
+    if let Some(reward_amount) = block_reward.checked_add(transaction_fees) {
+        // the fees already exist, only the block reward is new MLT
+        <MltIssuance<T>>::mutate(|issuance| *issuance = issuance.saturating_add(block_reward));
+        record_reward_paid::<T>(block_author, reward_amount);
+        let token_fees: Vec<(TokenId, Value)> = <TokenRewardTotal<T>>::drain().collect();
+
+        // the reward of a staking pool is split among its members
+        let staking_pool = T::AccountId::decode(&mut block_author.as_bytes())
+            .ok()
+            .and_then(|stash_account| <StakingPools<T>>::get(stash_account));
+        let mut utxos = Vec::new();
+        match staking_pool {
+            Some(staking_pool) => {
+                for (member, share) in pro_rata_shares(&staking_pool.members, reward_amount) {
+                    utxos.push(TransactionOutput::new_pubkey(share, H256::from(member)));
+                }
+                for (token_id, fee) in token_fees {
+                    for (member, share) in pro_rata_shares(&staking_pool.members, fee) {
+                        if share > 0 {
+                            utxos.push(token_fee_utxo::<T>(
+                                H256::from(member),
+                                token_id.clone(),
+                                share,
+                            ));
+                        }
+                    }
+                }
+            }
+            None => {
+                if let Some(destination) = claimed_destination {
+                    claim_to_destination::<T>(block_number, destination, reward_amount, token_fees);
+                    return;
+                }
+                utxos.push(TransactionOutput::new_pubkey(reward_amount, block_author));
+                for (token_id, fee) in token_fees {
+                    utxos.push(token_fee_utxo::<T>(block_author, token_id, fee));
+                }
+            }
+        }
+        for (index, utxo) in utxos.into_iter().enumerate() {
+            insert_to_utxo_store::<T>(block_number, &block_author, index as u32, utxo);
+        }
     } else {
         //TODO: what's the actual behaviour (or if this happens at all)
         log::warn!("problem adding the block author reward and the fees.");
@@ -149,6 +428,109 @@ pub(crate) fn reward_block_author<T: Config>(block_number: T::BlockNumber) {
 mod test {
     use super::*;
     use crate::mock::*;
+    use proptest::prelude::*;
+    use sp_consensus_aura::{Slot, AURA_ENGINE_ID};
+    use sp_consensus_babe::{
+        digests::{PreDigest, SecondaryPlainPreDigest},
+        BABE_ENGINE_ID,
+    };
+    use sp_runtime::{ConsensusEngineId, DigestItem};
+
+    fn set_authorities(keys: Vec<H256>) {
+        AUTHORITIES.with(|auths| {
+            *auths.borrow_mut() =
+                keys.iter().map(|key| sp_core::sr25519::Public::from_h256(*key)).collect()
+        })
+    }
+
+    /// Rewards block 1, sealed with the pre-runtime digest `pre_digest` of `engine_id` by alice
+    /// or bob, and returns the author found.
+    fn reward_sealed_block(engine_id: ConsensusEngineId, pre_digest: Vec<u8>) -> Option<H256> {
+        alice_test_ext().execute_with(|| {
+            System::set_block_number(1);
+            set_authorities(vec![H256::repeat_byte(1), H256::repeat_byte(2)]);
+            <RewardTotal<Test>>::put(7);
+            assert_eq!(digest_author::<Test>(), None);
+
+            System::deposit_log(DigestItem::PreRuntime(engine_id, pre_digest));
+            let author = digest_author::<Test>();
+            reward_block_author::<Test>(1);
+            match author {
+                // the author gets the block reward and the fees
+                Some(author) => {
+                    let reward = TransactionOutput::new_pubkey(107, author);
+                    assert!(<UtxoStore<Test>>::iter_values().any(|utxo| utxo == reward));
+                    assert_eq!(<RewardTotal<Test>>::get(), 0);
+                }
+                // the reward is deferred and the fees stay in the pool
+                None => {
+                    assert_eq!(<DeferredReward<Test>>::get(), 100);
+                    assert_eq!(<RewardTotal<Test>>::get(), 7);
+                }
+            }
+            set_authorities(vec![]);
+            author
+        })
+    }
+
+    #[test]
+    fn aura_author_test() {
+        let (alice, bob) = (H256::repeat_byte(1), H256::repeat_byte(2));
+        // the author of a slot takes turns among the authorities
+        let sealed_in_slot =
+            |slot: u64| reward_sealed_block(AURA_ENGINE_ID, Slot::from(slot).encode());
+        assert_eq!(sealed_in_slot(4), Some(alice));
+        assert_eq!(sealed_in_slot(7), Some(bob));
+        // the digests of other engines are ignored
+        assert_eq!(reward_sealed_block(*b"test", 7u64.encode()), None);
+    }
+
+    #[test]
+    fn babe_author_test() {
+        let (alice, bob) = (H256::repeat_byte(1), H256::repeat_byte(2));
+        let sealed_by = |authority_index: u32| {
+            let pre_digest = PreDigest::SecondaryPlain(SecondaryPlainPreDigest {
+                authority_index,
+                slot: Slot::from(3),
+            });
+            reward_sealed_block(BABE_ENGINE_ID, pre_digest.encode())
+        };
+        assert_eq!(sealed_by(0), Some(alice));
+        assert_eq!(sealed_by(1), Some(bob));
+        // an index out of the authority set gives no author
+        assert_eq!(sealed_by(2), None);
+    }
+
+    #[test]
+    fn noted_author_test() {
+        alice_test_ext().execute_with(|| {
+            System::set_block_number(1);
+            let (alice, bob) = (H256::repeat_byte(1), H256::repeat_byte(2));
+            set_authorities(vec![alice, bob]);
+
+            // the author noted by `pallet-authorship` comes before the one of the digest
+            System::deposit_log(DigestItem::PreRuntime(
+                AURA_ENGINE_ID,
+                Slot::from(1).encode(),
+            ));
+            assert_eq!(digest_author::<Test>(), Some(bob));
+            <BlockAuthor<Test>>::put(alice);
+            UTXO_EVENTS.with(|events| events.borrow_mut().clear());
+            reward_block_author::<Test>(1);
+            let reward = TransactionOutput::new_pubkey(100, alice);
+            assert!(<UtxoStore<Test>>::iter_values().any(|utxo| utxo == reward));
+            // added like the outputs of transactions
+            let hash = reward_utxo_hash(1, &alice, 0, &reward);
+            assert!(<crate::UtxoCreationTime<Test>>::get(hash).is_some());
+            assert_eq!(
+                UTXO_EVENTS.with(|events| events.borrow().clone()),
+                vec![UtxoEvent::New(hash)]
+            );
+            assert!(<UtxoStore<Test>>::iter_values().all(|utxo| utxo.destination
+                != Destination::Pubkey(sp_core::sr25519::Public::from_h256(bob))));
+            set_authorities(vec![]);
+        });
+    }
 
     #[test]
     fn increase_reduction_fraction_test() {
@@ -174,6 +556,10 @@ mod test {
 
             // at Block 42000, multiplication factor of the reduction fraction is way off. (nevermind the remainder)
             assert_eq!(increase_reduction_fraction::<Test>(4200), None);
+
+            // without a ReductionPeriod, the reward is never reduced
+            RewardReductionPeriod::set(0);
+            assert_eq!(increase_reduction_fraction::<Test>(4200), Some(0));
         });
     }
 
@@ -213,6 +599,163 @@ mod test {
 
             // exceeds the reduction fraction of 100%, so reward is constantly at 1 based on testnet.
             assert_eq!(get_block_author_reward::<Test>(5000), 1);
+
+            // without a reduction fraction, the reward is never reduced.
+            RewardReductionFraction::set(&Percent::zero());
+            assert_eq!(get_block_author_reward::<Test>(5000), 100);
+        });
+    }
+
+    proptest! {
+        #[test]
+        fn prop_block_author_reward_bounded(block_number in any::<u64>(), fraction in 0u8..=100) {
+            let reward = alice_test_ext().execute_with(|| {
+                RewardReductionFraction::set(&Percent::from_percent(fraction));
+                get_block_author_reward::<Test>(block_number)
+            });
+            prop_assert!(reward <= InitialReward::get());
+            prop_assert!(reward >= DefaultMinimumReward::get().min(InitialReward::get()));
+        }
+    }
+
+    #[test]
+    fn deferred_reward_test() {
+        alice_test_ext().execute_with(|| {
+            System::set_block_number(1);
+            let issuance = <MltIssuance<Test>>::get();
+            <RewardTotal<Test>>::put(7);
+
+            // without an author, nothing is paid nor minted
+            reward_block_author::<Test>(1);
+            assert_eq!(<DeferredReward<Test>>::get(), 100);
+            assert_eq!(<RewardTotal<Test>>::get(), 7);
+            assert_eq!(<MltIssuance<Test>>::get(), issuance);
+            assert!(System::events().iter().any(
+                |r| r.event == crate::mock::Event::Utxo(Event::<Test>::RewardDeferred(1, 100))
+            ));
+            reward_block_author::<Test>(2);
+            assert_eq!(<DeferredReward<Test>>::get(), 200);
+
+            // the next author found gets the deferred rewards and fees on top of its own
+            let author = H256::repeat_byte(1);
+            <BlockAuthor<Test>>::put(author);
+            reward_block_author::<Test>(3);
+            assert_eq!(<DeferredReward<Test>>::get(), 0);
+            assert_eq!(<RewardTotal<Test>>::get(), 0);
+            assert_eq!(<MltIssuance<Test>>::get(), issuance + 300);
+            let reward = TransactionOutput::new_pubkey(307, author);
+            assert!(<UtxoStore<Test>>::iter_values().any(|utxo| utxo == reward));
+        });
+    }
+
+    #[test]
+    fn reward_history_test() {
+        alice_test_ext().execute_with(|| {
+            System::set_block_number(1);
+            let era = <Test as Config>::StakingHelper::current_era();
+            let author = H256::repeat_byte(1);
+            let utxo_event = |event| crate::mock::Event::Utxo(event);
+
+            // the credits and the payments of an era add up
+            assert_eq!(
+                credit_reward_pool::<Test>(7, RewardSource::TransactionFee),
+                Ok(())
+            );
+            assert_eq!(
+                credit_reward_pool::<Test>(1, RewardSource::WithdrawalFee),
+                Ok(())
+            );
+            assert_eq!(
+                credit_reward_pool::<Test>(0, RewardSource::TransactionFee),
+                Ok(())
+            );
+            <BlockAuthor<Test>>::put(author);
+            reward_block_author::<Test>(1);
+            let events: Vec<_> = System::events().into_iter().map(|r| r.event).collect();
+            assert!(
+                events.contains(&utxo_event(Event::<Test>::RewardPoolCredited(
+                    7,
+                    RewardSource::TransactionFee
+                )))
+            );
+            assert!(
+                events.contains(&utxo_event(Event::<Test>::RewardPoolCredited(
+                    1,
+                    RewardSource::WithdrawalFee
+                )))
+            );
+            assert!(events.contains(&utxo_event(Event::<Test>::RewardPaid(author, 108))));
+            let first_era = EraRewards {
+                era,
+                transaction_fees: 7,
+                withdrawal_fees: 1,
+                paid: vec![(author, 108)],
+            };
+            assert_eq!(Pallet::<Test>::reward_history(), vec![first_era.clone()]);
+
+            // only the last `RewardHistoryDepth` eras are kept
+            next_block();
+            assert_eq!(
+                credit_reward_pool::<Test>(2, RewardSource::TransactionFee),
+                Ok(())
+            );
+            assert_eq!(Pallet::<Test>::reward_history().len(), 2);
+            next_block();
+            next_block();
+            <BlockAuthor<Test>>::put(author);
+            reward_block_author::<Test>(2);
+            let history = Pallet::<Test>::reward_history();
+            assert_eq!(
+                history.iter().map(|rewards| rewards.era).collect::<Vec<_>>(),
+                vec![era + 1, era + 3]
+            );
+            assert_eq!(history[1].paid, vec![(author, 102)]);
+        });
+    }
+
+    #[test]
+    fn authority_set_test() {
+        alice_test_ext().execute_with(|| {
+            System::set_block_number(1);
+            let (alice, bob) = (H256::repeat_byte(1), H256::repeat_byte(2));
+
+            // an empty set is reported, the author is still rewarded
+            set_authorities(vec![]);
+            <BlockAuthor<Test>>::put(alice);
+            reward_block_author::<Test>(1);
+            assert!(System::events()
+                .iter()
+                .any(|r| r.event == crate::mock::Event::Utxo(Event::<Test>::NoAuthorities)));
+            let reward = TransactionOutput::new_pubkey(100, alice);
+            assert!(<UtxoStore<Test>>::iter_values().any(|utxo| utxo == reward));
+
+            // a duplicate is reported and only counted once
+            set_authorities(vec![alice, bob, alice]);
+            assert_eq!(Pallet::<Test>::authorities(), vec![alice, bob]);
+            System::reset_events();
+            <BlockAuthor<Test>>::put(alice);
+            reward_block_author::<Test>(2);
+            let duplicates = System::events()
+                .iter()
+                .filter(|r| {
+                    r.event == crate::mock::Event::Utxo(Event::<Test>::DuplicateAuthority(alice))
+                })
+                .count();
+            assert_eq!(duplicates, 1);
+            let rewards = <UtxoStore<Test>>::iter_values().filter(|utxo| *utxo == reward).count();
+            assert_eq!(rewards, 2);
+            set_authorities(vec![]);
         });
     }
+
+    #[test]
+    fn reward_utxo_hash_test() {
+        let reward = TransactionOutput::<H256>::new_pubkey(100, H256::repeat_byte(1));
+        let author = H256::repeat_byte(2);
+        let hash = reward_utxo_hash(1, &author, 0, &reward);
+        // equal rewards of a block are told apart by their index, and by their author
+        assert_ne!(hash, reward_utxo_hash(1, &author, 1, &reward));
+        assert_ne!(hash, reward_utxo_hash(1, &H256::repeat_byte(3), 0, &reward));
+        assert_ne!(hash, reward_utxo_hash(2, &author, 0, &reward));
+    }
 }
@@ -15,7 +15,10 @@
 //
 // Author(s): L. Kuklinek
 
-use crate::{sign, Transaction, TransactionOutput};
+use crate::{
+    sign::{self, ChainBinding},
+    Transaction, TransactionOutput,
+};
 use chainscript::context::ParseResult;
 use codec::{Decode, Encode};
 use core::time::Duration;
@@ -89,11 +92,119 @@ impl PartialOrd for BlockTime {
     }
 }
 
+/// Relative time lock as checked by `OP_CSV`.
+///
+/// Similar to BIP 68, the lock is a number of seconds if the `SECONDS` flag is set, otherwise it
+/// is a number of blocks. The amount is stored in the 32 bits below the flag. Higher bits are
+/// reserved and must be zero.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct RelativeTimeLock(u64);
+
+impl RelativeTimeLock {
+    const SECONDS: u64 = 1 << 32;
+    const AMOUNT_MASK: u64 = Self::SECONDS - 1;
+
+    /// Create a relative time lock from its raw representation. Fails if reserved bits are set.
+    pub fn new(raw: u64) -> Option<Self> {
+        (raw <= (Self::SECONDS | Self::AMOUNT_MASK)).then(|| Self(raw))
+    }
+
+    /// Relative time lock of given number of blocks
+    pub fn blocks(blocks: u32) -> Self {
+        Self(blocks as u64)
+    }
+
+    /// Relative time lock of given number of seconds
+    pub fn seconds(secs: u32) -> Self {
+        Self(Self::SECONDS | secs as u64)
+    }
+
+    /// Get the lock as a u64
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    /// Get the time span that has to pass since the UTXO was created
+    pub fn time(&self) -> BlockTime {
+        let amount = self.0 & Self::AMOUNT_MASK;
+        if self.0 & Self::SECONDS == 0 {
+            BlockTime::Blocks(amount as u32)
+        } else {
+            BlockTime::Timestamp(Duration::from_secs(amount))
+        }
+    }
+}
+
+/// Time passed since the UTXO being spent was created.
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Default)]
+pub struct InputAge {
+    /// Number of blocks since the UTXO was created
+    pub blocks: u32,
+    /// Real world time since the UTXO was created
+    pub time: Duration,
+}
+
+impl InputAge {
+    /// Is the UTXO old enough to satisfy given relative time lock?
+    pub fn satisfies(&self, lock: RelativeTimeLock) -> bool {
+        match lock.time() {
+            BlockTime::Blocks(blocks) => self.blocks >= blocks,
+            BlockTime::Timestamp(time) => self.time >= time,
+        }
+    }
+}
+
+/// Version of the script committed to in `Destination::ScriptHash`.
+///
+/// Versions not known to this node are reserved for future soft forks. Scripts of an unknown
+/// version are not executed, spending them always succeeds at the consensus level. That allows
+/// new opcodes or semantics to be introduced by assigning them a new version.
+#[derive(Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Encode, Decode, Debug, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct ScriptVersion(u8);
+
+impl ScriptVersion {
+    /// The initial script version
+    pub const V0: Self = Self(0);
+
+    /// The latest script version known to this node
+    pub const LATEST: Self = Self::V0;
+
+    /// Create a new script version
+    pub fn new(version: u8) -> Self {
+        Self(version)
+    }
+
+    /// Get the version as a u8
+    pub fn as_u8(&self) -> u8 {
+        self.0
+    }
+
+    /// Is the meaning of this version known to this node?
+    pub fn is_known(&self) -> bool {
+        *self <= Self::LATEST
+    }
+}
+
+/// Set of rules the scripts are checked against.
+#[derive(Eq, PartialEq, Clone, Copy, Encode, Decode, Debug)]
+pub enum ScriptRules {
+    /// Rules every transaction included in a block has to follow.
+    Consensus,
+    /// Consensus rules with extra restrictions imposed on transactions entering the pool, so
+    /// that the features reserved for future soft forks are not used in the meantime and that
+    /// the signatures checked by scripts are canonical.
+    Policy,
+}
+
 /// Mintlayer script context.
 struct MLContext<'a, AccountId> {
     tx: &'a Transaction<AccountId>,
     utxos: &'a [TransactionOutput<AccountId>],
     index: u64,
+    rules: ScriptRules,
+    chain: ChainBinding,
+    age: Option<InputAge>,
 }
 
 impl<'a, AccountId: 'a + Encode> chainscript::Context for MLContext<'a, AccountId> {
@@ -132,32 +243,79 @@ impl<'a, AccountId: 'a + Encode> chainscript::Context for MLContext<'a, AccountI
     }
 
     /// Extract a signature and sighash.
+    ///
+    /// The pool only accepts canonical signatures, see [sign::SignatureData::is_canonical].
     fn parse_signature(&self, pk: Self::Public, sig: &[u8]) -> Option<Self::SignatureData> {
         pk.parse_sig(sig)
+            .filter(|sd| self.rules == ScriptRules::Consensus || sd.is_canonical(sig))
     }
 
     /// Verify signature.
     fn verify_signature(&self, sd: &Self::SignatureData, _: &[u8], sep_idx: u32) -> bool {
         use sign::TransactionSigMsg as Msg;
+        if !self.chain.accepts(sd.sighash()) {
+            return false;
+        }
         let msg = Msg::construct(sd.sighash(), &self.tx, self.utxos, self.index, sep_idx);
-        sd.verify(&msg)
+        sd.verify(&msg.for_chain(self.chain.chain_id))
     }
 
     /// Verify absolute time lock.
     fn check_lock_time(&self, time: i64) -> bool {
         time >= 0 && self.tx.time_lock.time() >= RawBlockTime::new(time as u64).time()
     }
+
+    /// Verify relative time lock.
+    ///
+    /// Fails if the age of the UTXO being spent is not known.
+    fn check_sequence(&self, sequence: i64) -> bool {
+        let lock = (sequence >= 0).then(|| RelativeTimeLock::new(sequence as u64)).flatten();
+        match (lock, self.age) {
+            (Some(lock), Some(age)) => age.satisfies(lock),
+            _ => false,
+        }
+    }
+
+    /// Upgradable NOPs are only allowed by the consensus rules.
+    fn discourage_upgradable_nops(&self) -> bool {
+        self.rules == ScriptRules::Policy
+    }
+}
+
+/// Is the `witness` script made of minimal data pushes only?
+pub fn is_push_only(witness: &[u8]) -> bool {
+    use chainscript::{opcodes::Class, script::Instruction, Script};
+    Script::from(witness.to_vec()).instructions_minimal().all(|instr| match instr {
+        Ok(Instruction::PushBytes(_)) => true,
+        Ok(Instruction::Op(opcode)) => matches!(opcode.classify(), Class::PushNum(_)),
+        Err(_) => false,
+    })
 }
 
-/// Verify mintlayer script.
-pub fn verify<AccountId: Encode>(
+/// Verify version 0 `lock` script given the `witness` script generating its input.
+///
+/// The `age` of the UTXO being spent is used to check relative time locks, these always fail if it
+/// is not known. Scripts of an unknown version are not verified at all, see [ScriptVersion].
+/// The signatures checked by the script are checked against the `chain`.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_script<AccountId: Encode>(
     tx: &Transaction<AccountId>,
     utxos: &[TransactionOutput<AccountId>],
     index: u64,
+    rules: ScriptRules,
+    chain: ChainBinding,
+    age: Option<InputAge>,
     witness: Vec<u8>,
     lock: Vec<u8>,
 ) -> chainscript::Result<()> {
-    let ctx = MLContext { tx, utxos, index };
+    let ctx = MLContext {
+        tx,
+        utxos,
+        index,
+        rules,
+        chain,
+        age,
+    };
     chainscript::verify_witness_lock(&ctx, &witness.into(), &lock.into())
 }
 
@@ -166,8 +324,13 @@ pub(crate) mod test {
     use super::*;
     use chainscript::Context;
     use core::time::Duration;
-    use sp_core::sr25519;
     use proptest::prelude::*;
+    use sp_core::{sr25519, H256};
+
+    const TEST_CHAIN: ChainBinding = ChainBinding {
+        chain_id: H256::zero(),
+        accept_unbound: true,
+    };
 
     // Generate block time in seconds
     pub fn gen_block_time_real() -> impl Strategy<Value = RawBlockTime> {
@@ -180,11 +343,15 @@ pub(crate) mod test {
             inputs: vec![],
             outputs: vec![],
             time_lock: Default::default(),
+            expires_at: None,
         };
         let ctx = MLContext {
             tx: &tx,
             utxos: &[],
             index: 0,
+            rules: ScriptRules::Consensus,
+            chain: TEST_CHAIN,
+            age: None,
         };
         let key = sr25519::Public::from_raw([42u8; 32]);
         let mut keydata = vec![0u8];
@@ -200,11 +367,15 @@ pub(crate) mod test {
             inputs: Vec::new(),
             outputs: Vec::new(),
             time_lock: BlockTime::Timestamp(Duration::from_secs(1_000_000_000)).as_raw().unwrap(),
+            expires_at: None,
         };
         let ctx = MLContext {
             tx: &tx,
             utxos: &[],
             index: 0,
+            rules: ScriptRules::Consensus,
+            chain: TEST_CHAIN,
+            age: None,
         };
         let script = chainscript::Builder::new()
             .push_int(BlockTime::Blocks(5).as_raw().unwrap().as_u64() as i64)
@@ -213,4 +384,91 @@ pub(crate) mod test {
         let result = chainscript::run_script(&ctx, &script, Vec::new().into());
         assert_eq!(result, Err(chainscript::Error::TimeLock));
     }
+
+    #[test]
+    fn test_upgradable_nops_policy() {
+        let tx = Transaction::<u64> {
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        let script = chainscript::Builder::new()
+            .push_opcode(chainscript::opcodes::all::OP_NOP)
+            .push_opcode(chainscript::opcodes::all::OP_NOP5)
+            .into_script();
+        let run = |rules| {
+            let ctx = MLContext {
+                tx: &tx,
+                utxos: &[],
+                index: 0,
+                rules,
+                chain: TEST_CHAIN,
+                age: None,
+            };
+            chainscript::run_script(&ctx, &script, Vec::new().into()).map(|_| ())
+        };
+        assert_eq!(run(ScriptRules::Consensus), Ok(()));
+        assert_eq!(
+            run(ScriptRules::Policy),
+            Err(chainscript::Error::DiscourageUpgradableNops)
+        );
+    }
+
+    #[test]
+    fn test_unknown_version_anyone_can_spend() {
+        let lock = chainscript::Builder::new()
+            .push_opcode(chainscript::opcodes::all::OP_RETURN)
+            .into_script();
+        let tx = Transaction::<u64> {
+            inputs: vec![crate::TransactionInput::new_script(
+                H256::zero(),
+                lock,
+                Default::default(),
+            )],
+            outputs: Vec::new(),
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        let spend = |version| verify(&tx, &[], 0, version, ScriptRules::Consensus, None);
+        assert_eq!(
+            spend(ScriptVersion::V0),
+            Err(chainscript::Error::VerifyFail)
+        );
+        assert_eq!(spend(ScriptVersion::new(1)), Ok(()));
+    }
+
+    #[test]
+    fn test_relative_time_lock() {
+        let tx = Transaction::<u64> {
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        let age = InputAge {
+            blocks: 10,
+            time: Duration::from_secs(600),
+        };
+        let check = |age, lock: u64| {
+            let ctx = MLContext {
+                tx: &tx,
+                utxos: &[],
+                index: 0,
+                rules: ScriptRules::Consensus,
+                chain: TEST_CHAIN,
+                age,
+            };
+            ctx.check_sequence(lock as i64)
+        };
+
+        assert!(check(Some(age), RelativeTimeLock::blocks(10).as_u64()));
+        assert!(!check(Some(age), RelativeTimeLock::blocks(11).as_u64()));
+        assert!(check(Some(age), RelativeTimeLock::seconds(600).as_u64()));
+        assert!(!check(Some(age), RelativeTimeLock::seconds(601).as_u64()));
+        // Unknown age or reserved bits set
+        assert!(!check(None, RelativeTimeLock::blocks(0).as_u64()));
+        assert!(!check(Some(age), 1 << 33));
+        assert!(!check(Some(age), u64::MAX));
+    }
 }
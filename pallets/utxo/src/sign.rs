@@ -21,23 +21,54 @@
 //! 1. Tools to construct byte string to be signed when signing a transaction.
 //!    See [TransactionOutputSigMsg::construct].
 //! 2. Tools to verify signatures using multiple signature schemes.
-//!    See [Public] and [SignatureData].
+//!    See [Public] and [SignatureData]. The witness of an input spent by a signature names its
+//!    scheme in a prefix, see [Public::witness].
+//! 3. Tools to produce a single signature for a key aggregated from multiple parties' keys.
+//!    See [musig].
+//! 4. Tools to prove control over UTXOs without spending them. See [ReserveProof].
+//! 5. BLS12-381 signatures, behind the `bls` feature. See [bls].
 
 use crate::{Transaction, TransactionOutput};
 
 use chainscript::context::ParseResult;
 pub use chainscript::sighash::SigHash;
 use chainscript::sighash::{InputMode, OutputMode};
-use codec::{Decode, DecodeAll, Encode};
+use codec::{Decode, DecodeAll, Encode, Output};
+use core::convert::TryInto;
 use frame_support::sp_io::crypto;
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
-use sp_core::{sr25519, H256};
+use sp_core::{sr25519, testing::SR25519, H256};
 use sp_runtime::traits::{BlakeTwo256, Hash};
 use sp_std::prelude::*;
 use variant_count::VariantCount;
 
 /// Transaction data comitted to in a signature.
+///
+/// The message signed is the SCALE encoding of this structure. The serialization is part of the
+/// consensus rules and is stable, so external signers such as hardware wallets can reproduce it.
+/// All hashes are Blake2b-256 hashes of SCALE-encoded data. The encoding is:
+///
+/// * sighash, 1 byte
+/// * inputs, depending on the sighash input mode
+///   * `0x00`, hash of the outpoints of all inputs, hash of all the outputs spent by the inputs,
+///     index of the input being signed as 8-byte little endian integer
+///   * `0x01` if `SIGHASH_ANYONECANPAY`, outpoint of the input being signed, hash of the output
+///     it spends
+/// * outputs, depending on the sighash output mode
+///   * `0x01` if `SIGHASH_ALL` or default, hash of all the outputs
+///   * `0x02` if `SIGHASH_NONE`
+///   * `0x03` if `SIGHASH_SINGLE`, hash of the output at the index of the input being signed or
+///     zeros if there is no such output
+/// * time lock as 8-byte little endian integer
+/// * expiry, `0x00` if the transaction never expires, otherwise `0x01` followed by the expiry as
+///   8-byte little endian integer
+/// * index of the last `OP_CODESEPARATOR` as 4-byte little endian integer, `0xffffffff` if none
+/// * chain id, 32 bytes, only if the sighash commits to the chain (flag `0x40`)
+///
+/// The spent outputs are committed to in full, including their values and destinations. A signer
+/// given the spent outputs can therefore check the amounts it is signing off without trusting the
+/// party that constructed the transaction.
 #[derive(Eq, PartialEq, Clone, Encode)]
 pub struct TransactionSigMsg {
     /// Sighash
@@ -48,8 +79,50 @@ pub struct TransactionSigMsg {
     outputs: TransactionOutputSigMsg,
     /// Time lock used
     time_lock: u64,
+    /// Expiry used
+    expires_at: Option<u64>,
     /// OP_CODESEPARATOR position (or 0xffffffff if none seen so far)
     codesep_idx: u32,
+    /// Chain signed for, if the sighash commits to it
+    chain_id: ChainId,
+}
+
+/// Chain id committed to in a signature. Encoded as nothing if the signature doesn't commit to
+/// the chain, so that the messages signed before chain-bound signatures were introduced stay the
+/// same.
+#[derive(Eq, PartialEq, Clone)]
+struct ChainId(Option<H256>);
+
+impl Encode for ChainId {
+    fn size_hint(&self) -> usize {
+        self.0.as_ref().map_or(0, Encode::size_hint)
+    }
+
+    fn encode_to<W: Output + ?Sized>(&self, dest: &mut W) {
+        if let Some(chain_id) = &self.0 {
+            chain_id.encode_to(dest);
+        }
+    }
+}
+
+/// Chain the signatures of transactions are checked against.
+///
+/// A signature whose sighash commits to the chain (see [SigHash::with_chain]) can't be replayed
+/// on a network with another chain id, even if it has the same genesis UTXOs. Signatures not
+/// committing to the chain are accepted during a migration window only.
+#[derive(Eq, PartialEq, Clone, Copy, Encode, Decode, Debug)]
+pub struct ChainBinding {
+    /// Chain id committed to by the signatures bound to the chain
+    pub chain_id: H256,
+    /// Whether the signatures not bound to the chain are still accepted
+    pub accept_unbound: bool,
+}
+
+impl ChainBinding {
+    /// Is a signature with given sighash acceptable on the chain?
+    pub fn accepts(&self, sighash: SigHash) -> bool {
+        sighash.commits_to_chain() || self.accept_unbound
+    }
 }
 
 /// Transaction input data comitted to in a signature.
@@ -139,20 +212,83 @@ impl TransactionSigMsg {
             // Time lock
             time_lock: tx.time_lock.as_u64(),
 
+            // Expiry
+            expires_at: tx.expires_at.map(|expiry| expiry.as_u64()),
+
             // Code separator position
             codesep_idx,
+
+            // Chain, filled in by `for_chain`
+            chain_id: ChainId(None),
+        }
+    }
+
+    /// Commit to the chain `chain_id` if the sighash commits to the chain.
+    pub fn for_chain(mut self, chain_id: H256) -> Self {
+        self.chain_id = ChainId(self.sighash.commits_to_chain().then(|| chain_id));
+        self
+    }
+}
+
+/// Identifier of a signature scheme, written in the prefix of the witnesses signed with it.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Eq, PartialEq, Clone, Copy, Encode, Decode, Debug)]
+pub enum SignatureScheme {
+    /// Schnorr signatures over sr25519
+    #[codec(index = 0)]
+    Schnorr = 0,
+    /// BLS signatures over BLS12-381
+    #[cfg(feature = "bls")]
+    #[codec(index = 1)]
+    Bls = 1,
+}
+
+impl SignatureScheme {
+    /// Public key of this scheme with given key bytes, such as those of an account id. `None`
+    /// if the keys of the scheme are not 32 bytes long.
+    pub fn public(self, pk: [u8; 32]) -> Option<Public> {
+        match self {
+            SignatureScheme::Schnorr => Some(Public::Schnorr(sr25519::Public(pk))),
+            #[cfg(feature = "bls")]
+            SignatureScheme::Bls => None,
         }
     }
 }
 
+/// First byte of a witness prefixed with its signature scheme. A prefixed witness is made of
+/// this marker, the [SignatureScheme] byte, the signature and the sighash byte unless it is the
+/// default. Witnesses without the prefix are Schnorr signatures, as made before the prefix was
+/// introduced. A Schnorr witness is 64 or 65 bytes long without the prefix and 66 or 67 bytes
+/// long with it, so the two can't be confused.
+pub const WITNESS_SCHEME_MARKER: u8 = 0xff;
+
 /// Signature schemes. Identified by the public key type.
 pub trait Scheme: Sized {
     /// Signature type corresponding to the pubkey type for this scheme.
-    type Signature: Decode;
+    type Signature: Decode + Encode;
+
+    /// Identifier of the scheme in the witness prefix.
+    const SCHEME: SignatureScheme;
 
     /// Verify signature against raw data.
     fn verify_raw(&self, sig: &Self::Signature, msg: &[u8]) -> bool;
 
+    /// Sign raw data with the key from the keystore, `None` if the keystore doesn't have it.
+    fn sign_raw(&self, msg: &[u8]) -> Option<Self::Signature>;
+
+    /// Verify signature against raw data as a part of the current batch, see [batch]. Verified
+    /// right away where the scheme has no batch verification.
+    fn batch_verify_raw(&self, sig: &Self::Signature, msg: &[u8]) -> bool {
+        self.verify_raw(sig, msg)
+    }
+
+    /// Is `sig` in the canonical encoding of the scheme? Only canonical signatures are accepted
+    /// where they end up in an outpoint, so that no one but the signer can turn a valid signature
+    /// into another valid one.
+    fn is_canonical_sig(_sig: &Self::Signature) -> bool {
+        true
+    }
+
     /// Parse signature & sighash and bundle it with a pubkey.
     fn parse_sig(self, sig: &[u8]) -> Option<SignatureDataFor<Self>> {
         let mut input = sig;
@@ -174,9 +310,51 @@ pub trait Scheme: Sized {
 impl Scheme for sr25519::Public {
     type Signature = sr25519::Signature;
 
+    const SCHEME: SignatureScheme = SignatureScheme::Schnorr;
+
     fn verify_raw(&self, sig: &Self::Signature, msg: &[u8]) -> bool {
         crypto::sr25519_verify(sig, msg, self)
     }
+
+    fn sign_raw(&self, msg: &[u8]) -> Option<Self::Signature> {
+        crypto::sr25519_sign(SR25519, self, msg)
+    }
+
+    fn batch_verify_raw(&self, sig: &Self::Signature, msg: &[u8]) -> bool {
+        crypto::sr25519_batch_verify(sig, msg, self)
+    }
+
+    /// The schnorrkel marker bit is set and the scalar is reduced modulo the group order.
+    fn is_canonical_sig(sig: &Self::Signature) -> bool {
+        let mut s: [u8; 32] = sig.0[32..].try_into().expect("signatures are 64 bytes long");
+        if s[31] & SR25519_MARKER == 0 {
+            return false;
+        }
+        s[31] &= !SR25519_MARKER;
+        // Little endian numbers, compared from the most significant byte
+        s.iter().rev().lt(SR25519_GROUP_ORDER.iter().rev())
+    }
+}
+
+/// Bit of the last byte of a schnorrkel signature telling it apart from the older ed25519-style
+/// signatures. Signatures without it are only accepted by the deprecated verification.
+const SR25519_MARKER: u8 = 0x80;
+
+/// Order of the ristretto255 group, little endian.
+const SR25519_GROUP_ORDER: [u8; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+];
+
+/// Verify the signatures pushed by `push` in one batch. The host verifies a batch in the
+/// background while `push` runs and all at once when it is finished, which is faster than
+/// verifying each signature on its own. Signatures are pushed by the `batch_verify` methods.
+/// Return whether `push` succeeded and all the signatures are valid.
+pub fn batch(push: impl FnOnce() -> bool) -> bool {
+    crypto::start_batch_verify();
+    let pushed = push();
+    // The batch has to be finished even if pushing failed
+    crypto::finish_batch_verify() && pushed
 }
 
 /// A public key. An enum to accommodate for multiple signature schemes.
@@ -184,7 +362,12 @@ impl Scheme for sr25519::Public {
 #[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Encode, Decode, Debug, VariantCount)]
 pub enum Public {
     /// Schnorr public key
+    #[codec(index = 0)]
     Schnorr(sr25519::Public),
+    /// BLS public key
+    #[cfg(feature = "bls")]
+    #[codec(index = 1)]
+    Bls(bls::Public),
 }
 
 impl Public {
@@ -205,8 +388,69 @@ impl Public {
     pub fn parse_sig(self, sig: &[u8]) -> Option<SignatureData> {
         match self {
             Public::Schnorr(pk) => pk.parse_sig(sig).map(SignatureData::Schnorr),
+            #[cfg(feature = "bls")]
+            Public::Bls(pk) => pk.parse_sig(sig).map(SignatureData::Bls),
+        }
+    }
+
+    /// Signature scheme of the key.
+    pub fn scheme(&self) -> SignatureScheme {
+        match self {
+            Public::Schnorr(_) => sr25519::Public::SCHEME,
+            #[cfg(feature = "bls")]
+            Public::Bls(_) => bls::Public::SCHEME,
+        }
+    }
+
+    /// Sign raw data with the key from the keystore. Returns the encoded signature.
+    pub fn sign_raw(&self, msg: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            Public::Schnorr(pk) => pk.sign_raw(msg).map(|sig| sig.encode()),
+            #[cfg(feature = "bls")]
+            Public::Bls(pk) => pk.sign_raw(msg).map(|sig| sig.encode()),
+        }
+    }
+
+    /// Sign `msg` with the key from the keystore into the witness of the input it is made for,
+    /// prefixed with the signature scheme, see [WITNESS_SCHEME_MARKER].
+    pub fn witness(&self, msg: &TransactionSigMsg) -> Option<Vec<u8>> {
+        let mut witness = vec![WITNESS_SCHEME_MARKER, self.scheme() as u8];
+        witness.extend(self.sign_raw(&msg.encode())?);
+        // The default sighash is implied by a missing sighash byte
+        if msg.sighash != SigHash::default() {
+            witness.push(msg.sighash.as_u8());
+        }
+        Some(witness)
+    }
+
+    /// Is `witness` the canonical witness of a signature by the key? It has to be prefixed with
+    /// the scheme of the key, leave the default sighash out and carry a canonical signature, see
+    /// [Scheme::is_canonical_sig]. Whether the signature is valid is not checked.
+    pub fn is_canonical_witness(self, witness: &[u8]) -> bool {
+        match witness {
+            [WITNESS_SCHEME_MARKER, scheme, sig @ ..] if *scheme == self.scheme() as u8 => {
+                self.parse_sig(sig).map_or(false, |sig_data| sig_data.is_canonical(sig))
+            }
+            _ => false,
         }
     }
+
+    /// Parse the witness of an input spent by a signature of the key. The scheme in the prefix
+    /// has to be the scheme of the key. Witnesses without the prefix are parsed as Schnorr
+    /// signatures.
+    pub fn parse_witness(self, witness: &[u8]) -> Option<SignatureData> {
+        let prefixed = match witness {
+            [WITNESS_SCHEME_MARKER, scheme, sig @ ..] if *scheme == self.scheme() as u8 => {
+                self.parse_sig(sig)
+            }
+            _ => None,
+        };
+        prefixed.or_else(|| match self.scheme() {
+            SignatureScheme::Schnorr => self.parse_sig(witness),
+            #[cfg(feature = "bls")]
+            SignatureScheme::Bls => None,
+        })
+    }
 }
 
 impl From<sr25519::Public> for Public {
@@ -215,6 +459,13 @@ impl From<sr25519::Public> for Public {
     }
 }
 
+#[cfg(feature = "bls")]
+impl From<bls::Public> for Public {
+    fn from(pk: bls::Public) -> Self {
+        Self::Bls(pk)
+    }
+}
+
 /// A signature together with its usage information for particular signature scheme.
 pub struct SignatureDataFor<P: Scheme> {
     pubkey: P,
@@ -226,11 +477,35 @@ impl<P: Scheme> SignatureDataFor<P> {
     pub fn verify<T: Encode>(&self, msg: &T) -> bool {
         self.pubkey.verify_raw(&self.signature, &msg.encode())
     }
+
+    /// Verify the signature as a part of the current batch, see [batch].
+    pub fn batch_verify<T: Encode>(&self, msg: &T) -> bool {
+        self.pubkey.batch_verify_raw(&self.signature, &msg.encode())
+    }
+
+    /// Get sighash
+    pub fn sighash(&self) -> SigHash {
+        self.sighash
+    }
+
+    /// Is `sig`, the bytes this was parsed from, the canonical encoding of the signature and the
+    /// sighash? The default sighash has to be left out.
+    pub fn is_canonical(&self, sig: &[u8]) -> bool {
+        let sighash_len = if self.sighash == SigHash::default() {
+            0
+        } else {
+            1
+        };
+        sig.len() == self.signature.encoded_size() + sighash_len
+            && P::is_canonical_sig(&self.signature)
+    }
 }
 
 /// Signature data for multiple possible key types
 pub enum SignatureData {
     Schnorr(SignatureDataFor<sr25519::Public>),
+    #[cfg(feature = "bls")]
+    Bls(SignatureDataFor<bls::Public>),
 }
 
 impl SignatureData {
@@ -238,13 +513,746 @@ impl SignatureData {
     pub fn verify<T: Encode>(&self, msg: &T) -> bool {
         match self {
             SignatureData::Schnorr(sd) => sd.verify(msg),
+            #[cfg(feature = "bls")]
+            SignatureData::Bls(sd) => sd.verify(msg),
+        }
+    }
+
+    /// Verify signature against a message as a part of the current batch, see [batch].
+    pub fn batch_verify<T: Encode>(&self, msg: &T) -> bool {
+        match self {
+            SignatureData::Schnorr(sd) => sd.batch_verify(msg),
+            #[cfg(feature = "bls")]
+            SignatureData::Bls(sd) => sd.batch_verify(msg),
+        }
+    }
+
+    /// Is `sig`, the bytes this was parsed from, canonical? See [SignatureDataFor::is_canonical].
+    pub fn is_canonical(&self, sig: &[u8]) -> bool {
+        match self {
+            SignatureData::Schnorr(sd) => sd.is_canonical(sig),
+            #[cfg(feature = "bls")]
+            SignatureData::Bls(sd) => sd.is_canonical(sig),
         }
     }
 
     /// Get sighash
     pub fn sighash(&self) -> SigHash {
         match self {
-            SignatureData::Schnorr(s) => s.sighash,
+            SignatureData::Schnorr(s) => s.sighash(),
+            #[cfg(feature = "bls")]
+            SignatureData::Bls(s) => s.sighash(),
+        }
+    }
+}
+
+/// Prefix of the messages signed in reserve proofs. It can't be a valid sighash byte, so the
+/// message can never be mistaken for a transaction signature message.
+const RESERVE_PROOF_TAG: [u8; 21] = *b"MintlayerReserveProof";
+
+/// Proof that the signer controls a set of UTXOs, e.g. to prove the reserves of an exchange.
+///
+/// Each outpoint is signed for by the key controlling its UTXO. All the signatures commit to the
+/// challenge and to all the outpoints, so a proof can neither be split nor reused for another
+/// challenge. The proof is checked against the UTXO set of a particular block, see
+/// `Pallet::verify_reserve_proof`.
+#[derive(Eq, PartialEq, Clone, Encode, Decode, Debug)]
+pub struct ReserveProof {
+    /// Challenge chosen by the verifier, such as an auditor's nonce
+    pub challenge: Vec<u8>,
+    /// The outpoints claimed
+    pub outpoints: Vec<H256>,
+    /// Signatures, one per outpoint
+    pub signatures: Vec<sr25519::Signature>,
+}
+
+impl ReserveProof {
+    /// Sign `challenge` with the keys from the keystore controlling given outpoints.
+    /// Only Schnorr supported for now.
+    pub fn sign(challenge: Vec<u8>, outpoints: &[(H256, sr25519::Public)]) -> Option<Self> {
+        let mut proof = Self {
+            challenge,
+            outpoints: outpoints.iter().map(|(outpoint, _)| *outpoint).collect(),
+            signatures: Vec::new(),
+        };
+        let msg = proof.message();
+        proof.signatures = outpoints
+            .iter()
+            .map(|(_, pubkey)| crypto::sr25519_sign(SR25519, pubkey, &msg))
+            .collect::<Option<_>>()?;
+        Some(proof)
+    }
+
+    /// The message signed by every key of the proof.
+    pub fn message(&self) -> Vec<u8> {
+        (RESERVE_PROOF_TAG, &self.challenge, &self.outpoints).encode()
+    }
+
+    /// Verify the signature for the outpoint at `index` made by `pubkey`.
+    pub fn verify(&self, index: usize, pubkey: &sr25519::Public) -> bool {
+        match self.signatures.get(index) {
+            Some(signature) => pubkey.verify_raw(signature, &self.message()),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::TransactionInput;
+    use hex_literal::hex;
+
+    // Test vectors for external signers. Two inputs spending 1000 and 2000 units paid to keys
+    // 0xaa.. and 0xbb.., one output paying 2500 units to key 0xbb.., no time lock.
+    fn test_tx() -> (Transaction<H256>, Vec<TransactionOutput<H256>>) {
+        let tx = Transaction {
+            inputs: vec![
+                TransactionInput::new_empty(H256::repeat_byte(0x11)),
+                TransactionInput::new_empty(H256::repeat_byte(0x22)),
+            ],
+            outputs: vec![TransactionOutput::new_pubkey(2500, H256::repeat_byte(0xbb))],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        let spending = vec![
+            TransactionOutput::new_pubkey(1000, H256::repeat_byte(0xaa)),
+            TransactionOutput::new_pubkey(2000, H256::repeat_byte(0xbb)),
+        ];
+        (tx, spending)
+    }
+
+    fn sig_msg(sighash: u8, index: u64) -> Vec<u8> {
+        let (tx, spending) = test_tx();
+        let sighash = SigHash::from_u8(sighash).unwrap_or_default();
+        TransactionSigMsg::construct(sighash, &tx, &spending, index, u32::MAX).encode()
+    }
+
+    #[test]
+    fn test_sig_msg_vectors() {
+        assert_eq!(
+            sig_msg(0x00, 0),
+            hex!(
+                "00
+                 00
+                 17efe9c03d3741acc1d05fa23eb72bc5ce66d1bf3703f6c74656378ff5b6e387
+                 8d569efeafc049e2d3e2d6dd004e73b8742ef3aed8044a3f42034a7f28aa890d
+                 0000000000000000
+                 01
+                 dcc4f0f55ce6d14bda7e49ea64340357733a9acf1d80327105989219d0fc256a
+                 0000000000000000
+                 00
+                 ffffffff"
+            )
+            .to_vec()
+        );
+        assert_eq!(
+            sig_msg(0x83, 1),
+            hex!(
+                "83
+                 01
+                 2222222222222222222222222222222222222222222222222222222222222222
+                 3623272ac65687c6f7f54e44a10834cc46adf612dfab785e99bc53172cc66927
+                 03
+                 0000000000000000000000000000000000000000000000000000000000000000
+                 0000000000000000
+                 00
+                 ffffffff"
+            )
+            .to_vec()
+        );
+        assert_eq!(
+            sig_msg(0x82, 0),
+            hex!(
+                "82
+                 01
+                 1111111111111111111111111111111111111111111111111111111111111111
+                 c3fd7b4f13e8fac300ef749be104c61ece52183eed8de2d45b97f826edf4ecb7
+                 02
+                 0000000000000000
+                 00
+                 ffffffff"
+            )
+            .to_vec()
+        );
+    }
+
+    #[test]
+    fn test_sig_msg_chain_id() {
+        let (tx, spending) = test_tx();
+        let chain_id = H256::repeat_byte(0xcc);
+        let msg = |sighash| {
+            let sighash = SigHash::from_u8(sighash).expect("valid sighash");
+            TransactionSigMsg::construct(sighash, &tx, &spending, 0, u32::MAX)
+        };
+        assert_eq!(
+            msg(0x41).for_chain(chain_id).encode(),
+            hex!(
+                "41
+                 00
+                 17efe9c03d3741acc1d05fa23eb72bc5ce66d1bf3703f6c74656378ff5b6e387
+                 8d569efeafc049e2d3e2d6dd004e73b8742ef3aed8044a3f42034a7f28aa890d
+                 0000000000000000
+                 01
+                 dcc4f0f55ce6d14bda7e49ea64340357733a9acf1d80327105989219d0fc256a
+                 0000000000000000
+                 00
+                 ffffffff
+                 cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc"
+            )
+            .to_vec()
+        );
+        // Signatures not bound to the chain sign the same message as before
+        assert!(msg(0x01).for_chain(chain_id) == msg(0x01));
+        assert_eq!(SigHash::default().with_chain().as_u8(), 0x41);
+
+        let binding = ChainBinding {
+            chain_id,
+            accept_unbound: false,
+        };
+        assert!(binding.accepts(SigHash::default().with_chain()));
+        assert!(!binding.accepts(SigHash::default()));
+    }
+
+    #[test]
+    fn test_sig_msg_commits_to_spent_amounts() {
+        let (tx, mut spending) = test_tx();
+        let msg = TransactionSigMsg::construct(SigHash::default(), &tx, &spending, 0, u32::MAX);
+        // Lying about the value of the other input changes the message
+        spending[1].value += 1;
+        let msg2 = TransactionSigMsg::construct(SigHash::default(), &tx, &spending, 0, u32::MAX);
+        assert!(msg != msg2);
+    }
+
+    #[test]
+    fn test_parse_witness() {
+        let pubkey = Public::from(sr25519::Public([0xaa; 32]));
+        let sighash = |witness: &[u8]| pubkey.parse_witness(witness).map(|sd| sd.sighash().as_u8());
+        let prefixed = |sig: &[u8]| [&[WITNESS_SCHEME_MARKER, 0][..], sig].concat();
+
+        // Witnesses without the prefix
+        assert_eq!(sighash(&[0x11; 64]), Some(0x00));
+        assert_eq!(sighash(&[&[0x11; 64][..], &[0x41]].concat()), Some(0x41));
+        // A Schnorr signature may start with the marker itself
+        assert_eq!(sighash(&prefixed(&[0x11; 62])), Some(0x00));
+
+        // Witnesses with the prefix
+        assert_eq!(sighash(&prefixed(&[0x11; 64])), Some(0x00));
+        assert_eq!(
+            sighash(&prefixed(&[&[0x11; 64][..], &[0x41]].concat())),
+            Some(0x41)
+        );
+        let mut unknown_scheme = prefixed(&[0x11; 64]);
+        unknown_scheme[1] = 0x01;
+        assert_eq!(sighash(&unknown_scheme), None);
+        assert_eq!(sighash(&[0x11; 66]), None);
+    }
+
+    #[test]
+    fn test_canonical_witness() {
+        let pubkey = Public::from(sr25519::Public([0xaa; 32]));
+        let prefixed = |sig: &[u8]| [&[WITNESS_SCHEME_MARKER, 0][..], sig].concat();
+        // A signature with the given scalar, marked as schnorrkel
+        let sig = |s: [u8; 32]| {
+            let mut sig = [0x11; 64];
+            sig[32..].copy_from_slice(&s);
+            sig[63] |= SR25519_MARKER;
+            sig
+        };
+        let valid = sig([0x11; 32]);
+
+        assert!(pubkey.is_canonical_witness(&prefixed(&valid)));
+        assert!(pubkey.is_canonical_witness(&prefixed(&[&valid[..], &[0x41]].concat())));
+        // Unprefixed witnesses and the default sighash given explicitly
+        assert!(!pubkey.is_canonical_witness(&valid));
+        assert!(!pubkey.is_canonical_witness(&prefixed(&[&valid[..], &[0x00]].concat())));
+        // Missing marker
+        let mut unmarked = valid;
+        unmarked[63] &= !SR25519_MARKER;
+        assert!(!pubkey.is_canonical_witness(&prefixed(&unmarked)));
+
+        // Scalars up to the group order
+        let mut below = SR25519_GROUP_ORDER;
+        below[0] -= 1;
+        assert!(pubkey.is_canonical_witness(&prefixed(&sig(below))));
+        assert!(!pubkey.is_canonical_witness(&prefixed(&sig(SR25519_GROUP_ORDER))));
+        let mut above = SR25519_GROUP_ORDER;
+        above[16] = 1;
+        assert!(!pubkey.is_canonical_witness(&prefixed(&sig(above))));
+    }
+}
+
+/// MuSig2 multi-party signing over sr25519.
+///
+/// A group of signers aggregates their public keys into a single key with [KeyAgg]. A payment
+/// to the aggregated key looks like any other `Destination::Pubkey` output, and so does the
+/// signature spending it. Signing takes two rounds:
+/// 1. Every signer generates a fresh nonce pair with [nonce_gen] and sends out the public part.
+///    This can be done before the message to sign is known.
+/// 2. Once the message is known and the public nonces of all signers are aggregated with
+///    [aggregate_nonces], every signer produces a [partial_sign]ature and sends it out.
+///
+/// The partial signatures are then combined with [aggregate_signatures] into a signature that
+/// verifies against the aggregated key. A secret nonce must never be used for two signatures,
+/// doing so leaks the secret key, which is why [SecretNonce] is consumed by signing.
+#[cfg(feature = "std")]
+pub mod musig {
+    use super::*;
+    use curve25519_dalek::{
+        constants::RISTRETTO_BASEPOINT_POINT,
+        ristretto::{CompressedRistretto, RistrettoPoint},
+        scalar::Scalar,
+        traits::Identity,
+    };
+    use schnorrkel::context::SigningTranscript;
+    use sp_core::Pair;
+
+    /// Signing context used by substrate for sr25519 signatures
+    const SIGNING_CTX: &[u8] = b"substrate";
+    /// Context for the hashes specific to MuSig2
+    const MUSIG_CTX: &[u8] = b"mintlayer-musig2";
+
+    fn decompress(pk: &sr25519::Public) -> Option<RistrettoPoint> {
+        CompressedRistretto(pk.0).decompress()
+    }
+
+    /// Aggregated public key of a group of signers.
+    pub struct KeyAgg {
+        keys: Vec<sr25519::Public>,
+        coefs: Vec<Scalar>,
+        point: RistrettoPoint,
+    }
+
+    impl KeyAgg {
+        /// Aggregate given keys. The order of the keys matters and has to be the same for all
+        /// the signers. Fails if there are no keys, some of them are not valid or repeated.
+        pub fn new(keys: &[sr25519::Public]) -> Option<Self> {
+            let distinct = keys.iter().enumerate().all(|(i, key)| !keys[..i].contains(key));
+            if keys.is_empty() || !distinct {
+                return None;
+            }
+            let mut t = schnorrkel::signing_context(MUSIG_CTX).bytes(b"key-agg");
+            for key in keys {
+                t.commit_point(b"key", &CompressedRistretto(key.0));
+            }
+            let coefs: Vec<Scalar> = keys
+                .iter()
+                .map(|key| {
+                    let mut t = t.clone();
+                    t.commit_point(b"signer", &CompressedRistretto(key.0));
+                    t.challenge_scalar(b"coef")
+                })
+                .collect();
+            let mut point = RistrettoPoint::identity();
+            for (key, coef) in keys.iter().zip(&coefs) {
+                point += coef * decompress(key)?;
+            }
+            Some(Self {
+                keys: keys.to_vec(),
+                coefs,
+                point,
+            })
+        }
+
+        /// The aggregated public key
+        pub fn public(&self) -> sr25519::Public {
+            sr25519::Public(self.point.compress().to_bytes())
+        }
+
+        fn coef(&self, key: &sr25519::Public) -> Option<Scalar> {
+            let pos = self.keys.iter().position(|k| k == key)?;
+            Some(self.coefs[pos])
+        }
+    }
+
+    /// Secret nonce pair of a signer. Consumed by [partial_sign] so that it is not reused.
+    pub struct SecretNonce(Scalar, Scalar);
+
+    /// Public nonce pair of a signer sent out in the first round.
+    #[derive(Eq, PartialEq, Clone, Copy, Encode, Decode, Debug)]
+    pub struct PublicNonce(pub [u8; 32], pub [u8; 32]);
+
+    /// Public nonces of all the signers aggregated.
+    #[derive(Eq, PartialEq, Clone, Copy, Debug)]
+    pub struct AggNonce(RistrettoPoint, RistrettoPoint);
+
+    /// Partial signature of a signer sent out in the second round.
+    #[derive(Eq, PartialEq, Clone, Copy, Encode, Decode, Debug)]
+    pub struct PartialSignature(pub [u8; 32]);
+
+    /// Generate a fresh nonce pair for one signature.
+    pub fn nonce_gen() -> (SecretNonce, PublicNonce) {
+        let mut rng = rand_core::OsRng;
+        let (r1, r2) = (Scalar::random(&mut rng), Scalar::random(&mut rng));
+        let pub_nonce = PublicNonce(
+            (r1 * RISTRETTO_BASEPOINT_POINT).compress().to_bytes(),
+            (r2 * RISTRETTO_BASEPOINT_POINT).compress().to_bytes(),
+        );
+        (SecretNonce(r1, r2), pub_nonce)
+    }
+
+    /// Aggregate the public nonces of all the signers. Fails if some of them are not valid.
+    pub fn aggregate_nonces(nonces: &[PublicNonce]) -> Option<AggNonce> {
+        let mut agg = AggNonce(RistrettoPoint::identity(), RistrettoPoint::identity());
+        for PublicNonce(r1, r2) in nonces {
+            agg.0 += CompressedRistretto(*r1).decompress()?;
+            agg.1 += CompressedRistretto(*r2).decompress()?;
+        }
+        Some(agg)
+    }
+
+    /// Calculate the coefficient of the second nonce, the nonce of the final signature and the
+    /// challenge it commits to.
+    fn nonce_and_challenge(
+        key_agg: &KeyAgg,
+        nonce: &AggNonce,
+        msg: &[u8],
+    ) -> (Scalar, RistrettoPoint, Scalar) {
+        let pk = key_agg.point.compress();
+        let mut t = schnorrkel::signing_context(MUSIG_CTX).bytes(msg);
+        t.commit_point(b"pk", &pk);
+        t.commit_point(b"R1", &nonce.0.compress());
+        t.commit_point(b"R2", &nonce.1.compress());
+        let b = t.challenge_scalar(b"nonce-coef");
+        let r = nonce.0 + b * nonce.1;
+
+        // Same as the challenge of a plain sr25519 signature
+        let mut t = schnorrkel::signing_context(SIGNING_CTX).bytes(msg);
+        t.proto_name(b"Schnorr-sig");
+        t.commit_point(b"sign:pk", &pk);
+        t.commit_point(b"sign:R", &r.compress());
+        (b, r, t.challenge_scalar(b"sign:c"))
+    }
+
+    /// Produce the partial signature of `pair` for `msg`. Fails if `pair` is not one of the
+    /// aggregated keys.
+    pub fn partial_sign(
+        pair: &sr25519::Pair,
+        secret_nonce: SecretNonce,
+        key_agg: &KeyAgg,
+        nonce: &AggNonce,
+        msg: &[u8],
+    ) -> Option<PartialSignature> {
+        let coef = key_agg.coef(&pair.public())?;
+        let mut secret = [0u8; 32];
+        secret.copy_from_slice(&pair.to_raw_vec()[..32]);
+        let secret = Scalar::from_canonical_bytes(secret)?;
+
+        let SecretNonce(r1, r2) = secret_nonce;
+        let (b, _, c) = nonce_and_challenge(key_agg, nonce, msg);
+        Some(PartialSignature(
+            (r1 + b * r2 + c * coef * secret).to_bytes(),
+        ))
+    }
+
+    /// Combine the partial signatures of all the signers into a signature for the aggregated key.
+    pub fn aggregate_signatures(
+        key_agg: &KeyAgg,
+        nonce: &AggNonce,
+        msg: &[u8],
+        partial_sigs: &[PartialSignature],
+    ) -> Option<sr25519::Signature> {
+        let mut s = Scalar::zero();
+        for PartialSignature(sig) in partial_sigs {
+            s += Scalar::from_canonical_bytes(*sig)?;
+        }
+        let (_, r, _) = nonce_and_challenge(key_agg, nonce, msg);
+        let mut sig = [0u8; 64];
+        sig[..32].copy_from_slice(r.compress().as_bytes());
+        sig[32..].copy_from_slice(s.as_bytes());
+        // Marks the signature as schnorrkel, as opposed to ed25519
+        sig[63] |= 128;
+        Some(sr25519::Signature::from_raw(sig))
+    }
+
+    /// Message signed by the witness of the input at `index` with the default sighash.
+    pub fn signing_message<AccountId: Encode>(
+        tx: &Transaction<AccountId>,
+        utxos: &[TransactionOutput<AccountId>],
+        index: usize,
+    ) -> Vec<u8> {
+        TransactionSigMsg::construct(Default::default(), tx, utxos, index as u64, u32::MAX).encode()
+    }
+}
+
+/// BLS signatures over BLS12-381, with the public keys in G1 and the signatures in G2.
+///
+/// The runtime has no host functions for BLS yet, so the signatures are verified in the runtime
+/// itself, which is slow, and the keystore can't make them. The scheme is therefore behind the
+/// `bls` feature. BLS keys are 48 bytes long and can't be `Destination::Pubkey` keys, they are
+/// used in scripts.
+///
+/// Signatures aggregate well:
+/// * The keys of a key set, such as the authorities, are aggregated into a single key with
+///   [KeyAgg], the signatures of all the members by [KeyAgg::aggregate_signatures]. Every key is
+///   weighted by a coefficient committing to the whole set, so a member can't pick its key to
+///   cancel the others out.
+/// * The signatures of several inputs by the same key, each signing its own message, add up
+///   into one signature checked by [verify_inputs] with two pairings, however many inputs.
+#[cfg(feature = "bls")]
+pub mod bls {
+    use super::*;
+    use bls12_381::{
+        hash_to_curve::{ExpandMsgXmd, HashToCurve},
+        multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Gt, Scalar,
+    };
+    use frame_support::sp_io::hashing::blake2_512;
+
+    /// Domain separation tag of the hashes of the signed messages to G2
+    const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_MINTLAYER";
+    /// Context of the coefficients of the aggregated keys
+    const KEY_AGG_CTX: &[u8] = b"mintlayer-bls-key-agg";
+
+    /// Compressed G1 point
+    #[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Encode, Decode, Debug)]
+    pub struct Public(pub [u8; 48]);
+
+    /// Compressed G2 point
+    #[derive(Eq, PartialEq, Clone, Copy, Encode, Decode, Debug)]
+    pub struct Signature(pub [u8; 96]);
+
+    #[cfg(feature = "std")]
+    impl Serialize for Public {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            sp_core::bytes::serialize(&self.0, serializer)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl<'de> Deserialize<'de> for Public {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let mut pk = [0u8; 48];
+            sp_core::bytes::deserialize_check_len(
+                deserializer,
+                sp_core::bytes::ExpectedLen::Exact(&mut pk[..]),
+            )?;
+            Ok(Public(pk))
+        }
+    }
+
+    impl Public {
+        /// The key as a curve point. `None` if it is not a valid point of the G1 subgroup or the
+        /// identity, which any signature would verify against.
+        fn point(&self) -> Option<G1Affine> {
+            let point: Option<G1Affine> = G1Affine::from_compressed(&self.0).into();
+            point.filter(|point| !bool::from(point.is_identity()))
+        }
+    }
+
+    impl Signature {
+        fn point(&self) -> Option<G2Affine> {
+            G2Affine::from_compressed(&self.0).into()
+        }
+    }
+
+    fn hash_to_g2(msg: &[u8]) -> G2Affine {
+        <G2Projective as HashToCurve<ExpandMsgXmd<sha2::Sha256>>>::hash_to_curve(msg, DST).into()
+    }
+
+    /// Check `e(pk, hash) == e(g1, sig)`.
+    fn pairing_check(pk: &G1Affine, hash: G2Affine, sig: &G2Affine) -> bool {
+        let hash = G2Prepared::from(hash);
+        let sig = G2Prepared::from(*sig);
+        let generator = -G1Affine::generator();
+        multi_miller_loop(&[(pk, &hash), (&generator, &sig)]).final_exponentiation()
+            == Gt::identity()
+    }
+
+    /// Verify `sig` is a signature of `msg` by `pk`.
+    pub fn verify(pk: &Public, sig: &Signature, msg: &[u8]) -> bool {
+        match (pk.point(), sig.point()) {
+            (Some(pk), Some(sig)) => pairing_check(&pk, hash_to_g2(msg), &sig),
+            _ => false,
+        }
+    }
+
+    /// Add up signatures, such as the signatures of several inputs by the same key.
+    pub fn add_signatures(sigs: &[Signature]) -> Option<Signature> {
+        let mut sum = G2Projective::identity();
+        for sig in sigs {
+            sum += sig.point()?;
+        }
+        Some(Signature(G2Affine::from(sum).to_compressed()))
+    }
+
+    /// Verify `sig` is the sum of the signatures of all `msgs` by `pk`, such as the signature
+    /// messages of the inputs of a transaction spent by the same key. The messages have to be
+    /// distinct, otherwise a signature could be counted for several of them.
+    pub fn verify_inputs(pk: &Public, msgs: &[Vec<u8>], sig: &Signature) -> bool {
+        let distinct = msgs.iter().enumerate().all(|(i, msg)| !msgs[..i].contains(msg));
+        if msgs.is_empty() || !distinct {
+            return false;
+        }
+        let hash: G2Projective = msgs.iter().map(|msg| G2Projective::from(hash_to_g2(msg))).sum();
+        match (pk.point(), sig.point()) {
+            (Some(pk), Some(sig)) => pairing_check(&pk, hash.into(), &sig),
+            _ => false,
+        }
+    }
+
+    /// Aggregated public key of a key set.
+    pub struct KeyAgg {
+        keys: Vec<Public>,
+        coefs: Vec<Scalar>,
+        public: Public,
+    }
+
+    impl KeyAgg {
+        /// Aggregate given keys. The order of the keys matters and has to be the same for all
+        /// the signers. Fails if there are no keys, some of them are not valid or repeated.
+        pub fn new(keys: &[Public]) -> Option<Self> {
+            let distinct = keys.iter().enumerate().all(|(i, key)| !keys[..i].contains(key));
+            if keys.is_empty() || !distinct {
+                return None;
+            }
+            let coefs: Vec<Scalar> = keys
+                .iter()
+                .map(|key| Scalar::from_bytes_wide(&blake2_512(&(KEY_AGG_CTX, keys, key).encode())))
+                .collect();
+            let mut point = G1Projective::identity();
+            for (key, coef) in keys.iter().zip(&coefs) {
+                point += key.point()? * coef;
+            }
+            Some(Self {
+                keys: keys.to_vec(),
+                coefs,
+                public: Public(G1Affine::from(point).to_compressed()),
+            })
+        }
+
+        /// The aggregated key.
+        pub fn public(&self) -> Public {
+            self.public
+        }
+
+        /// Combine the signatures of the same message by all the keys, in the order of the
+        /// keys, into a signature for the aggregated key.
+        pub fn aggregate_signatures(&self, sigs: &[Signature]) -> Option<Signature> {
+            if sigs.len() != self.keys.len() {
+                return None;
+            }
+            let mut sum = G2Projective::identity();
+            for (sig, coef) in sigs.iter().zip(&self.coefs) {
+                sum += sig.point()? * coef;
+            }
+            Some(Signature(G2Affine::from(sum).to_compressed()))
+        }
+    }
+
+    // BLS signatures, verified in the runtime until host functions are available.
+    impl Scheme for Public {
+        type Signature = Signature;
+
+        const SCHEME: SignatureScheme = SignatureScheme::Bls;
+
+        fn verify_raw(&self, sig: &Self::Signature, msg: &[u8]) -> bool {
+            verify(self, sig, msg)
+        }
+
+        fn sign_raw(&self, _msg: &[u8]) -> Option<Self::Signature> {
+            // The keystore doesn't hold BLS keys
+            None
+        }
+    }
+
+    /// A BLS key pair, for signers outside of the keystore.
+    #[cfg(feature = "std")]
+    pub struct Pair(Scalar);
+
+    #[cfg(feature = "std")]
+    impl Pair {
+        /// Derive a key pair from a secret seed.
+        pub fn from_seed(seed: &[u8; 32]) -> Self {
+            Self(Scalar::from_bytes_wide(&blake2_512(seed)))
+        }
+
+        pub fn public(&self) -> Public {
+            Public(G1Affine::from(G1Affine::generator() * self.0).to_compressed())
+        }
+
+        pub fn sign(&self, msg: &[u8]) -> Signature {
+            Signature(G2Affine::from(hash_to_g2(msg) * self.0).to_compressed())
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        fn pairs() -> Vec<Pair> {
+            (1u8..=3).map(|i| Pair::from_seed(&[i; 32])).collect()
+        }
+
+        #[test]
+        fn test_verify() {
+            let pair = Pair::from_seed(&[1; 32]);
+            let sig = pair.sign(b"message");
+            assert!(verify(&pair.public(), &sig, b"message"));
+            assert!(!verify(&pair.public(), &sig, b"other message"));
+            assert!(!verify(&pairs()[1].public(), &sig, b"message"));
+            // The identity key is rejected
+            let identity = Public(G1Affine::identity().to_compressed());
+            assert!(!verify(
+                &identity,
+                &Signature(G2Affine::identity().to_compressed()),
+                b""
+            ));
+
+            // Through the signature scheme, with a sighash
+            let witness = [&sig.encode()[..], &[0x41]].concat();
+            let sig_data = pair.public().parse_sig(&witness).unwrap();
+            assert_eq!(sig_data.sighash().as_u8(), 0x41);
+            assert!(pair.public().sign_raw(b"message").is_none());
+        }
+
+        #[test]
+        fn test_key_agg() {
+            let pairs = pairs();
+            let keys: Vec<Public> = pairs.iter().map(Pair::public).collect();
+            let key_agg = KeyAgg::new(&keys).unwrap();
+            let sigs: Vec<Signature> = pairs.iter().map(|pair| pair.sign(b"reward")).collect();
+            let sig = key_agg.aggregate_signatures(&sigs).unwrap();
+            assert!(verify(&key_agg.public(), &sig, b"reward"));
+
+            // All the members have to sign, in the order of the keys
+            assert!(key_agg.aggregate_signatures(&sigs[1..]).is_none());
+            let swapped = [sigs[1], sigs[0], sigs[2]];
+            let sig = key_agg.aggregate_signatures(&swapped).unwrap();
+            assert!(!verify(&key_agg.public(), &sig, b"reward"));
+
+            // The order of the keys changes the aggregated key
+            let other = KeyAgg::new(&[keys[1], keys[0], keys[2]]).unwrap();
+            assert!(other.public() != key_agg.public());
+            assert!(KeyAgg::new(&[keys[0], keys[0]]).is_none());
+            assert!(KeyAgg::new(&[]).is_none());
+        }
+
+        #[test]
+        fn test_verify_inputs() {
+            let pairs = pairs();
+            let keys: Vec<Public> = pairs.iter().map(Pair::public).collect();
+            let key_agg = KeyAgg::new(&keys).unwrap();
+            let msgs: Vec<Vec<u8>> = (0u64..4).map(|index| index.encode()).collect();
+
+            // Every input is signed by the key set, the signatures of the inputs are added up
+            let input_sigs: Vec<Signature> = msgs
+                .iter()
+                .map(|msg| {
+                    let sigs: Vec<Signature> = pairs.iter().map(|pair| pair.sign(msg)).collect();
+                    key_agg.aggregate_signatures(&sigs).unwrap()
+                })
+                .collect();
+            let sig = add_signatures(&input_sigs).unwrap();
+            assert!(verify_inputs(&key_agg.public(), &msgs, &sig));
+
+            // A missing input signature
+            let partial = add_signatures(&input_sigs[1..]).unwrap();
+            assert!(!verify_inputs(&key_agg.public(), &msgs, &partial));
+            // The messages have to be distinct
+            let repeated = vec![msgs[0].clone(), msgs[0].clone()];
+            let sig = add_signatures(&[input_sigs[0], input_sigs[0]]).unwrap();
+            assert!(!verify_inputs(&key_agg.public(), &repeated, &sig));
+            assert!(!verify_inputs(&key_agg.public(), &[], &sig));
         }
     }
 }
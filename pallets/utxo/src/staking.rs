@@ -15,23 +15,90 @@
 //
 // Author(s): C. Yap
 
+use crate::pallet::count_utxo;
 use crate::{
-    convert_to_h256, tokens::Value, Config, Destination, Error, Event, LockedUtxos, Pallet,
-    RewardTotal, StakingCount, TransactionOutput, UtxoStore,
+    convert_to_h256, tokens::Value, Config, Destination, Error, Event, LockedUtxos,
+    MaturingWithdrawals, MltIssuance, Pallet, PendingWithdrawals, RewardSource,
+    SecondaryControllers, StakingCount, StakingPools, TransactionOutput, UtxoStore,
 };
+use codec::{Decode, Encode};
 use frame_support::{
     dispatch::{DispatchResultWithPostInfo, Vec},
     ensure, fail,
-    traits::Get,
+    traits::{Currency, Get, Imbalance},
+    RuntimeDebug,
 };
-use sp_core::H256;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::{sr25519, H256};
 use sp_runtime::traits::{BlakeTwo256, Hash};
 use sp_runtime::transaction_validity::{TransactionLongevity, ValidTransaction};
+use sp_runtime::Perbill;
 use sp_std::vec;
 
+#[cfg(any(feature = "try-runtime", test))]
+pub(crate) use crate::staking::utils::check_staking_count;
+pub(crate) use crate::staking::utils::get_all_locked_utxo_outpoints;
 use crate::staking::utils::remove_locked_utxos;
 pub use validation::*;
 
+/// Counter of staking eras, the same as in `pallet-staking`.
+pub type EraIndex = u32;
+
+/// Staking operations done on behalf of a stash account.
+#[derive(Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum StakingOperation {
+    RotateSessionKeys,
+    Unlock,
+    Withdraw,
+}
+
+/// The staking operations a secondary controller may do on behalf of its stash account.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, PartialOrd, Ord, RuntimeDebug, Default)]
+pub struct StakingPermissions {
+    /// `rotate_session_keys`
+    pub rotate_session_keys: bool,
+    /// `unlock_request_for_withdrawal`
+    pub unlock: bool,
+    /// `withdraw_stake`, paid to the withdrawal key or the stash account all the same
+    pub withdraw: bool,
+}
+
+impl StakingPermissions {
+    /// Whether the operation is permitted.
+    pub fn allows(&self, operation: StakingOperation) -> bool {
+        match operation {
+            StakingOperation::RotateSessionKeys => self.rotate_session_keys,
+            StakingOperation::Unlock => self.unlock,
+            StakingOperation::Withdraw => self.withdraw,
+        }
+    }
+}
+
+/// A second account acting for a stash account, restricted to the operations it is permitted,
+/// e.g. a hot key rotating the session keys while the stash account stays offline. It can't
+/// redelegate the stake, and the stake is never withdrawn to it.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, PartialOrd, Ord, RuntimeDebug)]
+pub struct SecondaryController<AccountId> {
+    pub account: AccountId,
+    pub permissions: StakingPermissions,
+}
+
+/// Where the fee of withdrawing unlocked stake goes, see `Config::FeeDestination`.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub enum FeeDestination<AccountId> {
+    /// The fee is destroyed.
+    Burn,
+    /// The fee is credited to the free balance of the account, like unwrapped MLT. The part below
+    /// the existential deposit of a new account is burned.
+    Treasury(AccountId),
+    /// The fee is paid to the block author, like the transaction fees.
+    RewardPool,
+}
+
 /// A helper trait to handle staking NOT found in pallet-utxo.
 pub trait StakingHelper<AccountId> {
     fn get_controller_account(stash_account: &AccountId) -> Result<AccountId, &'static str>;
@@ -52,11 +119,14 @@ pub trait StakingHelper<AccountId> {
     /// In `pallet-staking`, its job is like an "accountant" to the stash account.
     /// * `session_key` - to get up-to-date with validators, eras, sessions. see `pallet-session`.
     /// * `value` - the amount to stake/bond/stash
+    /// * `commission` - the share of the rewards the validator keeps. see `pallet-staking`'s
+    /// `ValidatorPrefs`.
     fn lock_for_staking(
         stash_account: &AccountId,
         controller_account: &AccountId,
         session_key: &Vec<u8>,
         value: Value,
+        commission: Perbill,
     ) -> DispatchResultWithPostInfo;
 
     /// stake more funds for the validator
@@ -69,6 +139,26 @@ pub trait StakingHelper<AccountId> {
 
     /// transfer balance from the locked state to the actual free balance.
     fn withdraw(stash_account: &AccountId) -> DispatchResultWithPostInfo;
+
+    /// the era the staking is currently at.
+    fn current_era() -> EraIndex;
+
+    /// move the stake of a validator to a new stash and controller account at once,
+    /// without waiting for the bonding duration.
+    fn redelegate(
+        stash_account: &AccountId,
+        new_stash_account: &AccountId,
+        new_controller_account: &AccountId,
+        session_key: &Vec<u8>,
+        value: Value,
+        commission: Perbill,
+    ) -> DispatchResultWithPostInfo;
+
+    /// set new session keys for the validator of the stash account. see `pallet-session`.
+    fn rotate_session_keys(
+        stash_account: &AccountId,
+        session_key: &Vec<u8>,
+    ) -> DispatchResultWithPostInfo;
 }
 
 /// Calls the outside staking logic to lock some funds
@@ -81,6 +171,9 @@ pub(crate) fn lock_for_staking<T: Config>(
         stash_account,
         controller_account,
         session_key,
+        secondary_controller,
+        commission,
+        ..
     } = &output.destination
     {
         T::StakingHelper::lock_for_staking(
@@ -88,7 +181,14 @@ pub(crate) fn lock_for_staking<T: Config>(
             controller_account,
             session_key,
             output.value,
+            *commission,
         )?;
+        if let Some(secondary) = secondary_controller {
+            <SecondaryControllers<T>>::insert(
+                &secondary.account,
+                (stash_account.clone(), secondary.permissions),
+            );
+        }
         return utils::add_to_locked_utxos::<T>(hash_key, output, stash_account);
     }
     fail!(Error::<T>::InvalidOperation)
@@ -111,6 +211,24 @@ pub(crate) fn lock_extra_for_staking<T: Config>(
     fail!(Error::<T>::InvalidOperation)
 }
 
+/// The stash account `caller` does `operation` for: the caller itself, unless it is the
+/// secondary controller of a stash account permitted to do the operation.
+pub(crate) fn acting_stash_account<T: Config>(
+    caller: T::AccountId,
+    operation: StakingOperation,
+) -> Result<T::AccountId, Error<T>> {
+    if <StakingCount<T>>::contains_key(&caller) {
+        return Ok(caller);
+    }
+    match <SecondaryControllers<T>>::get(&caller) {
+        Some((stash_account, permissions)) => {
+            ensure!(permissions.allows(operation), Error::<T>::NoPermission);
+            Ok(stash_account)
+        }
+        None => Ok(caller),
+    }
+}
+
 /// unlocking the staked funds outside of the `pallet-utxo`.
 /// also means you don't want to be a validator anymore.
 pub(crate) fn unlock_request_for_withdrawal<T: Config>(
@@ -119,19 +237,142 @@ pub(crate) fn unlock_request_for_withdrawal<T: Config>(
     validate_unlock_request_for_withdrawal::<T>(&stash_account)?;
 
     let res = T::StakingHelper::unlock_request_for_withdrawal(&stash_account)?;
-    <Pallet<T>>::deposit_event(Event::<T>::StakeUnlocked(stash_account));
+
+    let (_, value) = <StakingCount<T>>::get(&stash_account).unwrap_or((0, 0));
+    let available_at_era =
+        T::StakingHelper::current_era().saturating_add(T::BondingDuration::get());
+    <PendingWithdrawals<T>>::insert(&stash_account, (value, available_at_era));
+    <MaturingWithdrawals<T>>::append(available_at_era, stash_account.clone());
+
+    <Pallet<T>>::deposit_event(Event::<T>::StakeUnlocked(stash_account.clone()));
+    <Pallet<T>>::deposit_event(Event::<T>::WithdrawalScheduled(
+        stash_account,
+        value,
+        available_at_era,
+    ));
     Ok(res)
 }
 
+/// Moves the stake of the stash account to a new validator, and the locked utxos with it.
+/// The old validator is chilled and the stake is bonded again under the new accounts.
+pub(crate) fn redelegate<T: Config>(
+    stash_account: T::AccountId,
+    new_stash_account: T::AccountId,
+    new_controller_account: T::AccountId,
+    session_key: Vec<u8>,
+) -> DispatchResultWithPostInfo {
+    validate_redelegation::<T>(
+        &stash_account,
+        &new_stash_account,
+        &new_controller_account,
+        &session_key,
+    )?;
+
+    let staking_count =
+        <StakingCount<T>>::take(&stash_account).ok_or(Error::<T>::StashAccountNotFound)?;
+    let res = T::StakingHelper::redelegate(
+        &stash_account,
+        &new_stash_account,
+        &new_controller_account,
+        &session_key,
+        staking_count.1,
+        utils::commission::<T>(&stash_account),
+    )?;
+
+    // the secondary controller keeps acting for the stake
+    if let Some(secondary) = utils::secondary_controller::<T>(&stash_account) {
+        <SecondaryControllers<T>>::insert(
+            &secondary.account,
+            (new_stash_account.clone(), secondary.permissions),
+        );
+    }
+    for outpoint in utils::get_all_locked_utxo_outpoints::<T>(&stash_account) {
+        <LockedUtxos<T>>::mutate(outpoint, |utxo| {
+            if let Some(utxo) = utxo {
+                match &mut utxo.destination {
+                    Destination::LockForStaking {
+                        stash_account,
+                        controller_account,
+                        session_key: key,
+                        ..
+                    } => {
+                        *stash_account = new_stash_account.clone();
+                        *controller_account = new_controller_account.clone();
+                        *key = session_key.clone();
+                    }
+                    Destination::LockExtraForStaking {
+                        stash_account,
+                        controller_account,
+                    } => {
+                        *stash_account = new_stash_account.clone();
+                        *controller_account = new_controller_account.clone();
+                    }
+                    _ => {}
+                }
+            }
+        });
+    }
+    <StakingCount<T>>::insert(&new_stash_account, staking_count);
+
+    <Pallet<T>>::deposit_event(Event::<T>::StakeRedelegated(
+        stash_account,
+        new_stash_account,
+    ));
+    Ok(res)
+}
+
+/// Sets new session keys for the validator of the stash account.
+/// A staking pool that is not bonded yet only keeps them until it is.
+pub(crate) fn rotate_session_keys<T: Config>(
+    stash_account: T::AccountId,
+    session_key: Vec<u8>,
+) -> DispatchResultWithPostInfo {
+    validate_rotate_session_keys::<T>(&stash_account, &session_key)?;
+
+    let res = match <StakingPools<T>>::get(&stash_account) {
+        Some(mut staking_pool) if !staking_pool.bonded => {
+            staking_pool.session_key = session_key;
+            <StakingPools<T>>::insert(&stash_account, staking_pool);
+            ().into()
+        }
+        _ => T::StakingHelper::rotate_session_keys(&stash_account, &session_key)?,
+    };
+
+    <Pallet<T>>::deposit_event(Event::<T>::SessionKeysRotated(stash_account));
+    Ok(res)
+}
+
+/// Notifies the stash accounts whose unlocked stake can be withdrawn from the current era on.
+pub(crate) fn notify_matured_withdrawals<T: Config>() {
+    let era = T::StakingHelper::current_era();
+    if !<MaturingWithdrawals<T>>::contains_key(era) {
+        return;
+    }
+    for stash_account in <MaturingWithdrawals<T>>::take(era) {
+        // skip the stakes already withdrawn, or unlocked again for a later era
+        if let Some((value, available_at_era)) = <PendingWithdrawals<T>>::get(&stash_account) {
+            if available_at_era == era {
+                <Pallet<T>>::deposit_event(Event::<T>::WithdrawalMatured(
+                    stash_account,
+                    value,
+                    era,
+                ));
+            }
+        }
+    }
+}
+
 /// Consolidates all unlocked utxos  into one, and moves it to `UtxoStore`.
+/// The utxo goes to the withdrawal key of the stake if it has one, otherwise to the stash account.
 /// Make SURE that `fn unlock(...)` has been called and the era for withdrawal has passed, before
 /// performing a withdrawal.
 pub(crate) fn withdraw<T: Config>(stash_account: T::AccountId) -> DispatchResultWithPostInfo {
     validate_withdrawal::<T>(&stash_account)?;
 
     let res = T::StakingHelper::withdraw(&stash_account)?;
+    <PendingWithdrawals<T>>::remove(&stash_account);
 
-    let stash_pubkey = convert_to_h256::<T>(&stash_account)?;
+    let withdrawal_pubkey = utils::withdrawal_pubkey::<T>(&stash_account)?;
 
     // remove from the `StakingCount` storage
     let (_, mut total) =
@@ -142,6 +383,10 @@ pub(crate) fn withdraw<T: Config>(stash_account: T::AccountId) -> DispatchResult
         .checked_sub(fee)
         .ok_or("Total amount of Locked UTXOs is less than minimum?")?;
 
+    if let Some(secondary) = utils::secondary_controller::<T>(&stash_account) {
+        <SecondaryControllers<T>>::remove(&secondary.account);
+    }
+
     let outpoints = remove_locked_utxos::<T>(&stash_account);
     log::debug!(
         "removed a total of {} in the LockedUtxo storage.",
@@ -149,18 +394,65 @@ pub(crate) fn withdraw<T: Config>(stash_account: T::AccountId) -> DispatchResult
     );
 
     let hash = BlakeTwo256::hash_of(&outpoints);
-    // move locked utxo back to UtxoStore
-    let utxo = TransactionOutput::new_pubkey(total, stash_pubkey);
-    <UtxoStore<T>>::insert(hash, utxo);
+    match <StakingPools<T>>::take(&stash_account) {
+        // split the stake of a pool among its members
+        Some(staking_pool) => {
+            for (index, (member, share)) in
+                pool::pro_rata_shares(&staking_pool.members, total).into_iter().enumerate()
+            {
+                let utxo = TransactionOutput::new_pubkey(share, H256::from(member));
+                count_utxo::<T>(&utxo, None);
+                <UtxoStore<T>>::insert(BlakeTwo256::hash_of(&(hash, index as u64)), utxo);
+            }
+        }
+        // move locked utxo back to UtxoStore
+        None => {
+            let utxo = TransactionOutput::new_pubkey(total, withdrawal_pubkey);
+            count_utxo::<T>(&utxo, None);
+            <UtxoStore<T>>::insert(hash, utxo);
+        }
+    }
 
-    // insert the fee into the reward total
-    let reward_total = <RewardTotal<T>>::take();
-    <RewardTotal<T>>::put(reward_total + fee);
+    pay_withdrawal_fee::<T>(fee)?;
 
     <Pallet<T>>::deposit_event(Event::<T>::StakeWithdrawn(total, stash_account));
     Ok(res)
 }
 
+/// Pays the withdrawal `fee` to `Config::FeeDestination`.
+fn pay_withdrawal_fee<T: Config>(fee: Value) -> Result<(), &'static str> {
+    if fee == 0 {
+        return Ok(());
+    }
+    let burned = match T::FeeDestination::get() {
+        FeeDestination::Burn => fee,
+        FeeDestination::Treasury(treasury) => {
+            let credited = T::Currency::deposit_creating(&treasury, fee).peek();
+            if credited > 0 {
+                <MltIssuance<T>>::mutate(|issuance| *issuance = issuance.saturating_sub(credited));
+                <Pallet<T>>::deposit_event(Event::<T>::WithdrawalFeePaid(
+                    credited,
+                    FeeDestination::Treasury(treasury),
+                ));
+            }
+            fee - credited
+        }
+        FeeDestination::RewardPool => {
+            crate::rewards::credit_reward_pool::<T>(fee, RewardSource::WithdrawalFee)?;
+            <Pallet<T>>::deposit_event(Event::<T>::WithdrawalFeePaid(
+                fee,
+                FeeDestination::RewardPool,
+            ));
+            0
+        }
+    };
+    if burned > 0 {
+        <MltIssuance<T>>::mutate(|issuance| *issuance = issuance.saturating_sub(burned));
+        <Pallet<T>>::deposit_event(Event::<T>::WithdrawalFeePaid(burned, FeeDestination::Burn));
+    }
+    Ok(())
+}
+
 pub mod validation {
     use super::*;
     use crate::staking::utils::get_all_locked_utxo_outpoints;
@@ -187,11 +479,30 @@ pub mod validation {
                 stash_account,
                 controller_account,
                 session_key,
+                secondary_controller,
+                commission,
+                min_self_bond,
+                ..
             } => {
                 ensure!(
                     tx.value >= T::MinimumStake::get(),
                     "output value must be equal or more than the minimum stake"
                 );
+                ensure!(
+                    tx.value >= *min_self_bond,
+                    "output value must be equal or more than the minimum self-bond"
+                );
+                ensure!(
+                    *commission <= T::MaxValidatorCommission::get(),
+                    "commission is higher than the maximum"
+                );
+                if let Some(secondary) = secondary_controller {
+                    validate_secondary_controller::<T>(
+                        stash_account,
+                        controller_account,
+                        &secondary.account,
+                    )?;
+                }
                 validate_lock_for_staking_requirements::<T>(
                     stash_account,
                     controller_account,
@@ -205,6 +516,35 @@ pub mod validation {
                 ensure!(tx.value > 0, "output value must be nonzero");
                 validate_lock_extra_for_staking_requirements::<T>(stash_account, controller_account)
             }
+            Destination::CreateStakingPool {
+                stash_account,
+                controller_account,
+                session_key,
+                ..
+            } => {
+                ensure!(tx.value > 0, "output value must be nonzero");
+                ensure!(
+                    !<StakingPools<T>>::contains_key(stash_account),
+                    "staking pool already exists"
+                );
+                validate_lock_for_staking_requirements::<T>(
+                    stash_account,
+                    controller_account,
+                    session_key,
+                )
+            }
+            Destination::JoinStakingPool { stash_account, .. } => {
+                ensure!(tx.value > 0, "output value must be nonzero");
+                let staking_pool =
+                    <StakingPools<T>>::get(stash_account).ok_or("staking pool not found")?;
+                // a bonded pool takes no new members once its stake is being unlocked
+                ensure!(
+                    !staking_pool.bonded
+                        || T::StakingHelper::are_funds_locked(&staking_pool.controller_account),
+                    "Cannot join a staking pool when its funds are unlocked."
+                );
+                Ok(().into())
+            }
             _non_staking_destinations => {
                 fail!(Error::<T>::InvalidOperation)
             }
@@ -234,6 +574,11 @@ pub mod validation {
             "specified stash account is a controller account"
         );
 
+        ensure!(
+            !<SecondaryControllers<T>>::contains_key(stash_account),
+            "specified stash account is a secondary controller"
+        );
+
         ensure!(
             !T::StakingHelper::is_controller_account_exist(controller_account),
             "specified controller account is already used."
@@ -242,6 +587,35 @@ pub mod validation {
         Ok(().into())
     }
 
+    /// Checks whether the account can be the secondary controller of a new stake.
+    fn validate_secondary_controller<T: Config>(
+        stash_account: &T::AccountId,
+        controller_account: &T::AccountId,
+        secondary_account: &T::AccountId,
+    ) -> DispatchResultWithPostInfo {
+        ensure!(
+            secondary_account != stash_account && secondary_account != controller_account,
+            "the secondary controller must be a separate account"
+        );
+
+        ensure!(
+            !<StakingCount<T>>::contains_key(secondary_account),
+            "specified secondary controller is a stash account"
+        );
+
+        ensure!(
+            !<SecondaryControllers<T>>::contains_key(secondary_account),
+            "specified secondary controller is already used."
+        );
+
+        ensure!(
+            !T::StakingHelper::is_controller_account_exist(secondary_account),
+            "specified secondary controller is a controller account"
+        );
+
+        Ok(().into())
+    }
+
     /// Checks whether a transaction is valid to do extra locking of utxos for staking
     fn validate_lock_extra_for_staking_requirements<T: Config>(
         stash_account: &T::AccountId,
@@ -268,6 +642,76 @@ pub mod validation {
         Ok(().into())
     }
 
+    /// Checks whether the stake of the stash account can move to the new accounts.
+    pub(crate) fn validate_redelegation<T: Config>(
+        stash_account: &T::AccountId,
+        new_stash_account: &T::AccountId,
+        new_controller_account: &T::AccountId,
+        session_key: &Vec<u8>,
+    ) -> DispatchResultWithPostInfo {
+        ensure!(
+            <StakingCount<T>>::contains_key(stash_account),
+            Error::<T>::StashAccountNotFound
+        );
+
+        // the members of a pool chose its validator.
+        ensure!(
+            !<StakingPools<T>>::contains_key(stash_account),
+            "the stake of a staking pool cannot be redelegated"
+        );
+
+        let controller_account = T::StakingHelper::get_controller_account(stash_account)?;
+        ensure!(
+            T::StakingHelper::are_funds_locked(&controller_account),
+            Error::<T>::FundsAtUnlockedState
+        );
+
+        validate_lock_for_staking_requirements::<T>(
+            new_stash_account,
+            new_controller_account,
+            session_key,
+        )
+    }
+
+    /// Checks whether the stash account can set the given session keys.
+    pub(crate) fn validate_rotate_session_keys<T: Config>(
+        stash_account: &T::AccountId,
+        session_key: &Vec<u8>,
+    ) -> DispatchResultWithPostInfo {
+        ensure!(
+            <StakingCount<T>>::contains_key(stash_account),
+            Error::<T>::StashAccountNotFound
+        );
+
+        ensure!(
+            T::StakingHelper::can_decode_session_key(session_key),
+            "please input a valid session key."
+        );
+
+        Ok(().into())
+    }
+
+    /// Checks whether the given member can leave the staking pool of the stash account.
+    pub(crate) fn validate_leave_pool<T: Config>(
+        stash_account: &T::AccountId,
+        member: &sr25519::Public,
+    ) -> DispatchResultWithPostInfo {
+        let staking_pool = <StakingPools<T>>::get(stash_account).ok_or("staking pool not found")?;
+
+        // the stake of a bonded pool is only returned on withdrawal, to all members at once.
+        ensure!(
+            !staking_pool.bonded,
+            "Cannot leave a bonded staking pool. Its stake is returned on withdrawal."
+        );
+
+        ensure!(
+            staking_pool.members.iter().any(|(key, _)| key == member),
+            "not a member of the staking pool"
+        );
+
+        Ok(().into())
+    }
+
     /// Checks whether unlock request is allowed.
     pub(crate) fn validate_unlock_request_for_withdrawal<T: Config>(
         stash_account: &T::AccountId,
@@ -277,6 +721,12 @@ pub mod validation {
             Error::<T>::StashAccountNotFound
         );
 
+        // the members of a pool that is not bonded yet can simply leave it
+        ensure!(
+            <StakingPools<T>>::get(stash_account).map_or(true, |staking_pool| staking_pool.bonded),
+            "staking pool is not bonded yet"
+        );
+
         let controller_account = T::StakingHelper::get_controller_account(stash_account)?;
 
         // unlock operation is allowed ONLY for locked funds.
@@ -319,6 +769,16 @@ pub mod validation {
             "Funds are still locked. Perform `unlock_request_for_withdrawal` first."
         );
 
+        if let Some((_, available_at_era)) = <PendingWithdrawals<T>>::get(stash_account) {
+            ensure!(
+                T::StakingHelper::current_era() >= available_at_era,
+                Error::<T>::WithdrawalNotMature
+            );
+        }
+
+        // the funds have to go somewhere
+        utils::withdrawal_pubkey::<T>(stash_account)?;
+
         let new_hash = BlakeTwo256::hash_of(&outpoints).as_fixed_bytes().to_vec();
 
         Ok(ValidTransaction {
@@ -331,24 +791,207 @@ pub mod validation {
     }
 }
 
+/// Staking pools, where holders lock UTXOs into the stake of a single validator.
+///
+/// A pool is created by a `CreateStakingPool` output and gets more shares through
+/// `JoinStakingPool` outputs. The shares are kept in `LockedUtxos` under the stash account of the
+/// pool, just like the outputs of a regular stake. Once they add up to `MinimumStake` the pool is
+/// bonded, and later shares are bonded as extra stake. Until then, members can leave the pool.
+/// Block rewards of the pool and its withdrawn stake are split among the members pro-rata.
+pub mod pool {
+    use super::*;
+    use crate::StakingPool;
+    use sp_runtime::helpers_128bit::multiply_by_rational;
+
+    /// Creates the staking pool of a `CreateStakingPool` output, with its owner as the first member.
+    pub(crate) fn create_pool<T: Config>(
+        hash_key: H256,
+        output: &TransactionOutput<T::AccountId>,
+    ) -> DispatchResultWithPostInfo {
+        if let Destination::CreateStakingPool {
+            stash_account,
+            controller_account,
+            session_key,
+            owner,
+        } = &output.destination
+        {
+            let mut staking_pool = StakingPool {
+                controller_account: controller_account.clone(),
+                session_key: session_key.clone(),
+                members: vec![],
+                bonded: false,
+            };
+            return add_share::<T>(stash_account, &mut staking_pool, hash_key, output, owner);
+        }
+        fail!(Error::<T>::InvalidOperation)
+    }
+
+    /// Adds the share of a `JoinStakingPool` output to its staking pool.
+    pub(crate) fn join_pool<T: Config>(
+        hash_key: H256,
+        output: &TransactionOutput<T::AccountId>,
+    ) -> DispatchResultWithPostInfo {
+        if let Destination::JoinStakingPool {
+            stash_account,
+            member,
+        } = &output.destination
+        {
+            let mut staking_pool =
+                <StakingPools<T>>::get(stash_account).ok_or("staking pool not found")?;
+            if staking_pool.bonded {
+                T::StakingHelper::lock_extra_for_staking(stash_account, output.value)?;
+            }
+            return add_share::<T>(stash_account, &mut staking_pool, hash_key, output, member);
+        }
+        fail!(Error::<T>::InvalidOperation)
+    }
+
+    /// Locks the output as a share of the member, and bonds the pool once it holds enough.
+    fn add_share<T: Config>(
+        stash_account: &T::AccountId,
+        staking_pool: &mut StakingPool<T::AccountId>,
+        hash_key: H256,
+        output: &TransactionOutput<T::AccountId>,
+        member: &sr25519::Public,
+    ) -> DispatchResultWithPostInfo {
+        match staking_pool.members.iter_mut().find(|(key, _)| key == member) {
+            Some((_, value)) => {
+                *value = value.checked_add(output.value).ok_or("exceeded limit of pool share")?;
+            }
+            None => staking_pool.members.push((*member, output.value)),
+        }
+        utils::add_to_locked_utxos::<T>(hash_key, output, stash_account)?;
+
+        let (_, total) = <StakingCount<T>>::get(stash_account).unwrap_or((0, 0));
+        if !staking_pool.bonded && total >= T::MinimumStake::get() {
+            T::StakingHelper::lock_for_staking(
+                stash_account,
+                &staking_pool.controller_account,
+                &staking_pool.session_key,
+                total,
+                Perbill::zero(),
+            )?;
+            staking_pool.bonded = true;
+            <Pallet<T>>::deposit_event(Event::<T>::StakingPoolBonded(stash_account.clone(), total));
+        }
+        <StakingPools<T>>::insert(stash_account, staking_pool);
+
+        <Pallet<T>>::deposit_event(Event::<T>::StakingPoolJoined(
+            stash_account.clone(),
+            H256::from(*member),
+            output.value,
+        ));
+        Ok(().into())
+    }
+
+    /// Moves the shares of the member out of the staking pool and back to `UtxoStore`,
+    /// consolidated into one utxo. The pool is removed when its last member leaves.
+    pub(crate) fn leave_pool<T: Config>(
+        stash_account: T::AccountId,
+        member: sr25519::Public,
+    ) -> DispatchResultWithPostInfo {
+        validate_leave_pool::<T>(&stash_account, &member)?;
+
+        let mut staking_pool =
+            <StakingPools<T>>::get(&stash_account).ok_or("staking pool not found")?;
+        staking_pool.members.retain(|(key, _)| *key != member);
+
+        let outpoints: Vec<H256> = LockedUtxos::<T>::iter()
+            .filter_map(|(k, v)| match v.destination {
+                Destination::CreateStakingPool {
+                    stash_account: stash,
+                    owner: key,
+                    ..
+                }
+                | Destination::JoinStakingPool {
+                    stash_account: stash,
+                    member: key,
+                } if stash == stash_account && key == member => Some(k),
+                _ => None,
+            })
+            .collect();
+        let mut share: Value = 0;
+        for k in &outpoints {
+            if let Some(utxo) = LockedUtxos::<T>::take(*k) {
+                share = share.checked_add(utxo.value).ok_or("exceeded limit of pool share")?;
+            }
+        }
+
+        let (num_of_utxos, total) =
+            <StakingCount<T>>::get(&stash_account).ok_or(Error::<T>::StashAccountNotFound)?;
+        if staking_pool.members.is_empty() {
+            <StakingCount<T>>::remove(&stash_account);
+            <StakingPools<T>>::remove(&stash_account);
+        } else {
+            <StakingCount<T>>::insert(
+                &stash_account,
+                (
+                    num_of_utxos.saturating_sub(outpoints.len() as u64),
+                    total.saturating_sub(share),
+                ),
+            );
+            <StakingPools<T>>::insert(&stash_account, staking_pool);
+        }
+
+        let utxo = TransactionOutput::new_pubkey(share, H256::from(member));
+        count_utxo::<T>(&utxo, None);
+        <UtxoStore<T>>::insert(BlakeTwo256::hash_of(&outpoints), utxo);
+
+        <Pallet<T>>::deposit_event(Event::<T>::StakingPoolLeft(
+            stash_account,
+            H256::from(member),
+            share,
+        ));
+        Ok(().into())
+    }
+
+    /// Splits `amount` among the members in proportion to the value they locked in the pool.
+    /// The remainder of the division goes to the first member, the owner of the pool.
+    pub fn pro_rata_shares(
+        members: &[(sr25519::Public, Value)],
+        amount: Value,
+    ) -> Vec<(sr25519::Public, Value)> {
+        let total = members.iter().fold(0, |total: Value, (_, value)| total.saturating_add(*value));
+        if total == 0 {
+            return members.first().map(|(owner, _)| (*owner, amount)).into_iter().collect();
+        }
+
+        let mut shares: Vec<(sr25519::Public, Value)> = members
+            .iter()
+            .map(|(member, value)| {
+                // the share never exceeds `amount`, so the multiplication can't overflow
+                let share = multiply_by_rational(amount, *value, total).unwrap_or(0);
+                (*member, share)
+            })
+            .collect();
+        let distributed =
+            shares.iter().fold(0, |sum: Value, (_, share)| sum.saturating_add(*share));
+        if let Some((_, share)) = shares.first_mut() {
+            *share = share.saturating_add(amount.saturating_sub(distributed));
+        }
+        shares.retain(|(_, share)| *share > 0);
+        shares
+    }
+}
+
 mod utils {
     use super::*;
     use sp_runtime::DispatchError;
+    #[cfg(any(feature = "try-runtime", test))]
+    use sp_std::collections::btree_map::BTreeMap;
 
     /// Retrieves all the outpoints owned by the given stash acount.
     // TODO: keep track of "our" Locked UTXO separately?
     pub fn get_all_locked_utxo_outpoints<T: Config>(stash_acc: &T::AccountId) -> Vec<H256> {
         LockedUtxos::<T>::iter()
             .filter_map(|(k, v)| match v.destination {
-                Destination::LockForStaking {
-                    stash_account,
-                    controller_account: _,
-                    session_key: _,
-                }
+                Destination::LockForStaking { stash_account, .. }
                 | Destination::LockExtraForStaking {
                     stash_account,
                     controller_account: _,
-                } => {
+                }
+                | Destination::CreateStakingPool { stash_account, .. }
+                | Destination::JoinStakingPool { stash_account, .. } => {
                     if *stash_acc == stash_account {
                         Some(k)
                     } else {
@@ -360,6 +1003,52 @@ mod utils {
             .collect()
     }
 
+    /// The key the stake of the given stash account is withdrawn to: the withdrawal key of its
+    /// `LockForStaking` utxo if set, otherwise the stash account itself.
+    pub fn withdrawal_pubkey<T: Config>(stash_acc: &T::AccountId) -> Result<H256, DispatchError> {
+        let withdrawal_key =
+            LockedUtxos::<T>::iter_values().find_map(|utxo| match utxo.destination {
+                Destination::LockForStaking {
+                    stash_account,
+                    withdrawal_key: Some(withdrawal_key),
+                    ..
+                } if *stash_acc == stash_account => Some(withdrawal_key),
+                _ => None,
+            });
+        match withdrawal_key {
+            Some(withdrawal_key) => Ok(H256::from(withdrawal_key)),
+            None => convert_to_h256::<T>(stash_acc),
+        }
+    }
+
+    /// The secondary controller of the given stash account, set in its `LockForStaking` utxo.
+    pub fn secondary_controller<T: Config>(
+        stash_acc: &T::AccountId,
+    ) -> Option<SecondaryController<T::AccountId>> {
+        LockedUtxos::<T>::iter_values().find_map(|utxo| match utxo.destination {
+            Destination::LockForStaking {
+                stash_account,
+                secondary_controller,
+                ..
+            } if *stash_acc == stash_account => secondary_controller,
+            _ => None,
+        })
+    }
+
+    /// The commission of the given stash account, set in its `LockForStaking` utxo.
+    pub fn commission<T: Config>(stash_acc: &T::AccountId) -> Perbill {
+        LockedUtxos::<T>::iter_values()
+            .find_map(|utxo| match utxo.destination {
+                Destination::LockForStaking {
+                    stash_account,
+                    commission,
+                    ..
+                } if *stash_acc == stash_account => Some(commission),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
     /// removes all locked utxos of the given stash_account.
     /// returns the list of outpoints removed from the `LockedUtxo` storage
     pub fn remove_locked_utxos<T: Config>(stash_account: &T::AccountId) -> Vec<H256> {
@@ -371,6 +1060,36 @@ mod utils {
         outpoints
     }
 
+    /// Checks `StakingCount` against the utxos in `LockedUtxos` of every stash account.
+    #[cfg(any(feature = "try-runtime", test))]
+    pub fn check_staking_count<T: Config>() -> Result<(), &'static str> {
+        let mut counts: BTreeMap<T::AccountId, (u64, Value)> = BTreeMap::new();
+        for utxo in LockedUtxos::<T>::iter_values() {
+            let stash_account = match utxo.destination {
+                Destination::LockForStaking { stash_account, .. }
+                | Destination::LockExtraForStaking { stash_account, .. }
+                | Destination::CreateStakingPool { stash_account, .. }
+                | Destination::JoinStakingPool { stash_account, .. } => stash_account,
+                _non_staking_destination => fail!("a non-staking utxo is locked"),
+            };
+            let (num_of_utxos, total) = counts.entry(stash_account).or_insert((0, 0));
+            *num_of_utxos += 1;
+            *total = total.saturating_add(utxo.value);
+        }
+
+        for (stash_account, staking_count) in StakingCount::<T>::iter() {
+            ensure!(
+                counts.remove(&stash_account) == Some(staking_count),
+                "StakingCount doesn't match the locked utxos"
+            );
+        }
+        ensure!(
+            counts.is_empty(),
+            "locked utxos of a stash account missing from StakingCount"
+        );
+        Ok(())
+    }
+
     /// adds to the `LockedUtxo` storage
     /// add to the `StakingCount` storage
     pub fn add_to_locked_utxos<T: Config>(
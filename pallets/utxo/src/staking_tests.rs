@@ -16,11 +16,20 @@
 // Author(s): C. Yap
 
 use crate::{
-    mock::*, Destination, Error, LockedUtxos, StakingCount, Transaction, TransactionInput,
-    TransactionOutput, UtxoStore,
+    mock::*,
+    staking::{FeeDestination, StakingPermissions},
+    BlockAuthor, Destination, Error, LockedUtxos, MaturingWithdrawals, SecondaryControllers,
+    StakingCount, StakingPools, Transaction, TransactionInput, TransactionOutput, UtxoStore,
 };
 use codec::Encode;
-use frame_support::{assert_err, assert_ok, sp_io::crypto};
+use frame_support::{
+    assert_err, assert_ok,
+    sp_io::crypto,
+    sp_runtime::{
+        traits::{BlakeTwo256, Hash},
+        Perbill,
+    },
+};
 use sp_core::{sp_std::vec, testing::SR25519, H256};
 
 // JUST FOR SEEKING BUG IN FUNCTIONAL TEST
@@ -38,6 +47,7 @@ fn staking_first_time() {
             inputs: vec![TransactionInput::new_empty(karl_genesis)],
             outputs: vec![TransactionOutput::new_pubkey(100, H256::from(alice_pub_key))],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign(&[utxo], 0, &karl_pub_key)
         .expect("karl's pub key not found");
@@ -61,6 +71,7 @@ fn staking_first_time() {
                 ),
             ],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign(&[utxo.clone()], 0, &alice_pub_key)
         .expect("Alice's pub key not found");
@@ -103,6 +114,7 @@ fn simple_staking() {
                 TransactionOutput::new_pubkey(90, H256::from(karl_pub_key)),
             ],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign(&[utxo], 0, &karl_pub_key)
         .expect("karl's pub key not found");
@@ -143,6 +155,7 @@ fn less_than_minimum_stake() {
                 TransactionOutput::new_pubkey(90, H256::from(karl_pub_key)),
             ],
             time_lock: Default::default(),
+            expires_at: None,
         };
         let karl_sig = crypto::sr25519_sign(SR25519, &karl_pub_key, &tx.encode()).unwrap();
         tx.inputs[0].witness = karl_sig.0.to_vec();
@@ -176,6 +189,10 @@ fn non_mlt_staking() {
                         stash_account: H256::from(karl_pub_key),
                         controller_account: H256::from(greg_pub_key),
                         session_key: vec![2, 1],
+                        withdrawal_key: None,
+                        secondary_controller: None,
+                        commission: Perbill::zero(),
+                        min_self_bond: 0,
                     },
                     data: Some(OutputData::TokenIssuanceV1 {
                         token_ticker: "Token".as_bytes().to_vec(),
@@ -188,6 +205,7 @@ fn non_mlt_staking() {
                 TransactionOutput::new_pubkey(80, H256::from(karl_pub_key)),
             ],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign(&[utxo], 0, &karl_pub_key)
         .expect("karl's pub key not found");
@@ -219,6 +237,7 @@ fn controller_staking_again() {
                 TransactionOutput::new_pubkey(90, H256::from(tom_pub_key)),
             ],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign(&[utxo], 0, &tom_pub_key)
         .expect(" tom's pub key not found");
@@ -251,6 +270,7 @@ fn stash_account_is_staking() {
                 TransactionOutput::new_pubkey(90, H256::from(alice_pub_key)),
             ],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign(&[utxo.clone()], 0, &alice_pub_key)
         .expect("alice's public key not found");
@@ -281,6 +301,7 @@ fn simple_staking_extra() {
                 TransactionOutput::new_pubkey(70, H256::from(alice_pub_key)),
             ],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign(&[utxo], 0, &alice_pub_key)
         .expect(" alice's pub key not found");
@@ -319,6 +340,7 @@ fn non_validator_staking_extra() {
                 TransactionOutput::new_pubkey(100, H256::from(greg_pub_key)),
             ],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign(&[utxo], 0, &greg_pub_key)
         .expect("greg's pub key not found");
@@ -357,6 +379,500 @@ fn pausing_and_withdrawing() {
     })
 }
 
+#[test]
+fn cold_staking_withdrawal() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        let (karl_pub_key, karl_genesis) = keys_and_hashes[1];
+        let (greg_pub_key, _) = keys_and_hashes[2];
+        let (tom_pub_key, _) = keys_and_hashes[3];
+
+        let utxo = UtxoStore::<Test>::get(karl_genesis).expect("karl's utxo does not exist");
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(karl_genesis)],
+            outputs: vec![
+                // KARL (index 1) validates with GREG (index 2) as the controller account,
+                // but the funds can only be withdrawn to TOM's (index 3) key.
+                TransactionOutput::new_cold_lock_for_staking(
+                    10,
+                    H256::from(karl_pub_key),
+                    H256::from(greg_pub_key),
+                    vec![2, 1],
+                    tom_pub_key,
+                ),
+                TransactionOutput::new_pubkey(90, H256::from(karl_pub_key)),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign(&[utxo], 0, &karl_pub_key)
+        .expect("karl's pub key not found");
+        let locked_utxo_hash = tx.outpoint(0);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+
+        assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(
+            H256::from(karl_pub_key)
+        )));
+        for _ in 1..6 {
+            next_block();
+        }
+        assert_ok!(Utxo::withdraw_stake(Origin::signed(H256::from(
+            karl_pub_key
+        ))));
+
+        let withdrawn = UtxoStore::<Test>::get(BlakeTwo256::hash_of(&vec![locked_utxo_hash]))
+            .expect("withdrawn utxo does not exist");
+        assert_eq!(withdrawn.destination, Destination::Pubkey(tom_pub_key));
+        assert_eq!(withdrawn.value, 10 - StakeWithdrawalFee::get());
+    })
+}
+
+#[test]
+fn validator_commission_and_self_bond() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        let (karl_pub_key, karl_genesis) = keys_and_hashes[1];
+        let karl_hash = H256::from(karl_pub_key);
+        let greg_hash = H256::from(keys_and_hashes[2].0);
+
+        let utxo = UtxoStore::<Test>::get(karl_genesis).expect("karl's utxo does not exist");
+        let stake_with = |commission, min_self_bond| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(karl_genesis)],
+                outputs: vec![
+                    TransactionOutput::new_lock_for_staking_with_commission(
+                        10,
+                        karl_hash,
+                        greg_hash,
+                        vec![2, 1],
+                        commission,
+                        min_self_bond,
+                    ),
+                    TransactionOutput::new_pubkey(90, karl_hash),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign(&[utxo.clone()], 0, &karl_pub_key)
+            .expect("karl's pub key not found")
+        };
+
+        assert_err!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                stake_with(Perbill::from_percent(60), 0)
+            ),
+            "commission is higher than the maximum"
+        );
+        assert_err!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                stake_with(Perbill::from_percent(10), 11)
+            ),
+            "output value must be equal or more than the minimum self-bond"
+        );
+
+        // KARL (index 1) validates with GREG (index 2) as the controller account, keeping 10%
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            stake_with(Perbill::from_percent(10), 10)
+        ));
+        let commission = MOCK_STAKING
+            .with(|stake_info| stake_info.borrow().commission_map.get(&karl_hash).cloned());
+        assert_eq!(commission, Some(Perbill::from_percent(10)));
+    })
+}
+
+#[test]
+fn secondary_controller_staking() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice_hash = H256::from(keys_and_hashes[0].0);
+        let (karl_pub_key, karl_genesis) = keys_and_hashes[1];
+        let karl_hash = H256::from(karl_pub_key);
+        let greg_hash = H256::from(keys_and_hashes[2].0);
+        let tom_hash = H256::from(keys_and_hashes[3].0);
+        let permissions = StakingPermissions {
+            rotate_session_keys: true,
+            unlock: true,
+            withdraw: false,
+        };
+
+        let utxo = UtxoStore::<Test>::get(karl_genesis).expect("karl's utxo does not exist");
+        let stake_with = |secondary_hash| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(karl_genesis)],
+                outputs: vec![
+                    TransactionOutput::new_lock_for_staking_with_secondary_controller(
+                        10,
+                        karl_hash,
+                        greg_hash,
+                        vec![2, 1],
+                        secondary_hash,
+                        permissions,
+                    ),
+                    TransactionOutput::new_pubkey(90, karl_hash),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign(&[utxo.clone()], 0, &karl_pub_key)
+            .expect("karl's pub key not found")
+        };
+
+        // ALICE (index 0) is already a stash account, and GREG (index 2) is the controller
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), stake_with(alice_hash)),
+            "specified secondary controller is a stash account"
+        );
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), stake_with(greg_hash)),
+            "the secondary controller must be a separate account"
+        );
+
+        // KARL (index 1) validates, with TOM (index 3) as the secondary controller
+        let tx = stake_with(tom_hash);
+        let locked_utxo_hash = tx.outpoint(0);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert_eq!(
+            SecondaryControllers::<Test>::get(tom_hash),
+            Some((karl_hash, permissions))
+        );
+
+        assert_ok!(Utxo::rotate_session_keys(
+            Origin::signed(tom_hash),
+            vec![3, 2]
+        ));
+        assert!(System::events()
+            .iter()
+            .any(|r| r.event == Event::Utxo(crate::Event::SessionKeysRotated(karl_hash))));
+
+        assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(
+            tom_hash
+        )));
+        for _ in 1..6 {
+            next_block();
+        }
+        assert_err!(
+            Utxo::withdraw_stake(Origin::signed(tom_hash)),
+            Error::<Test>::NoPermission
+        );
+        assert_ok!(Utxo::withdraw_stake(Origin::signed(karl_hash)));
+        assert!(!SecondaryControllers::<Test>::contains_key(tom_hash));
+
+        let withdrawn = UtxoStore::<Test>::get(BlakeTwo256::hash_of(&vec![locked_utxo_hash]))
+            .expect("withdrawn utxo does not exist");
+        assert_eq!(withdrawn.destination, Destination::Pubkey(karl_pub_key));
+    })
+}
+
+#[test]
+fn staking_pool_rewards_and_withdrawal() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let (karl_pub_key, karl_genesis) = keys_and_hashes[1];
+        let (greg_pub_key, greg_genesis) = keys_and_hashes[2];
+        let karl_hash = H256::from(karl_pub_key);
+        let greg_hash = H256::from(greg_pub_key);
+
+        // KARL (index 1) creates a pool with GREG (index 2) as the controller account,
+        // but locks less than the minimum stake of 10.
+        let utxo = UtxoStore::<Test>::get(karl_genesis).expect("karl's utxo does not exist");
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(karl_genesis)],
+            outputs: vec![
+                TransactionOutput::new_create_staking_pool(
+                    4,
+                    karl_hash,
+                    greg_hash,
+                    vec![2, 1],
+                    karl_pub_key,
+                ),
+                TransactionOutput::new_pubkey(106, karl_hash),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign(&[utxo], 0, &karl_pub_key)
+        .expect("karl's pub key not found");
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert!(!StakingPools::<Test>::get(karl_hash).expect("pool does not exist").bonded);
+        assert_err!(
+            Utxo::unlock_request_for_withdrawal(Origin::signed(karl_hash)),
+            "staking pool is not bonded yet"
+        );
+
+        // GREG joins the pool, which then holds enough to be bonded.
+        let utxo = UtxoStore::<Test>::get(greg_genesis).expect("greg's utxo does not exist");
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(greg_genesis)],
+            outputs: vec![
+                TransactionOutput::new_join_staking_pool(6, karl_hash, greg_pub_key),
+                TransactionOutput::new_pubkey(114, greg_hash),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign(&[utxo], 0, &greg_pub_key)
+        .expect("greg's pub key not found");
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert!(StakingPools::<Test>::get(karl_hash).expect("pool does not exist").bonded);
+        assert_eq!(StakingCount::<Test>::get(karl_hash), Some((2, 10)));
+        assert!(System::events()
+            .iter()
+            .any(|r| r.event == Event::Utxo(crate::Event::StakingPoolBonded(karl_hash, 10))));
+        assert_err!(
+            Utxo::leave_staking_pool(Origin::signed(greg_hash), karl_hash),
+            "Cannot leave a bonded staking pool. Its stake is returned on withdrawal."
+        );
+
+        // The block reward of 100 is split among the members.
+        BlockAuthor::<Test>::put(karl_hash);
+        crate::rewards::reward_block_author::<Test>(1);
+        for (index, &(pubkey, share)) in [(karl_hash, 40), (greg_hash, 60)].iter().enumerate() {
+            let reward = TransactionOutput::new_pubkey(share, pubkey);
+            let hash = crate::rewards::reward_utxo_hash(1, &karl_hash, index as u32, &reward);
+            assert_eq!(UtxoStore::<Test>::get(hash), Some(reward));
+        }
+
+        // The withdrawn stake of 9, after the fee, is split too. KARL gets the remainder.
+        assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(
+            karl_hash
+        )));
+        for _ in 1..6 {
+            next_block();
+        }
+        assert_ok!(Utxo::withdraw_stake(Origin::signed(karl_hash)));
+        assert!(!StakingPools::<Test>::contains_key(karl_hash));
+        for (pubkey, share) in [(karl_hash, 4), (greg_hash, 5)] {
+            let withdrawn = TransactionOutput::new_pubkey(share, pubkey);
+            assert!(UtxoStore::<Test>::iter_values().any(|utxo| utxo == withdrawn));
+        }
+    })
+}
+
+#[test]
+fn leaving_staking_pool() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        let (karl_pub_key, karl_genesis) = keys_and_hashes[1];
+        let (greg_pub_key, greg_genesis) = keys_and_hashes[2];
+        let (tom_pub_key, _) = keys_and_hashes[3];
+        let karl_hash = H256::from(karl_pub_key);
+        let greg_hash = H256::from(greg_pub_key);
+
+        // joining a pool that doesn't exist
+        let utxo = UtxoStore::<Test>::get(greg_genesis).expect("greg's utxo does not exist");
+        let mut tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(greg_genesis)],
+            outputs: vec![TransactionOutput::new_join_staking_pool(3, karl_hash, greg_pub_key)],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign(&[utxo.clone()], 0, &greg_pub_key)
+        .expect("greg's pub key not found");
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx.clone()),
+            "staking pool not found"
+        );
+
+        let karl_utxo = UtxoStore::<Test>::get(karl_genesis).expect("karl's utxo does not exist");
+        let create_tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(karl_genesis)],
+            outputs: vec![TransactionOutput::new_create_staking_pool(
+                4,
+                karl_hash,
+                greg_hash,
+                vec![2, 1],
+                karl_pub_key,
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign(&[karl_utxo], 0, &karl_pub_key)
+        .expect("karl's pub key not found");
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), create_tx));
+
+        tx.outputs.push(TransactionOutput::new_pubkey(117, greg_hash));
+        let tx = tx.sign(&[utxo], 0, &greg_pub_key).expect("greg's pub key not found");
+        let share_hash = tx.outpoint(0);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert_eq!(StakingCount::<Test>::get(karl_hash), Some((2, 7)));
+
+        // GREG leaves the pool that hasn't been bonded yet and gets his share back.
+        assert_err!(
+            Utxo::leave_staking_pool(Origin::signed(H256::from(tom_pub_key)), karl_hash),
+            "not a member of the staking pool"
+        );
+        assert_ok!(Utxo::leave_staking_pool(
+            Origin::signed(greg_hash),
+            karl_hash
+        ));
+        assert!(!LockedUtxos::<Test>::contains_key(share_hash));
+        assert_eq!(
+            UtxoStore::<Test>::get(BlakeTwo256::hash_of(&vec![share_hash])),
+            Some(TransactionOutput::new_pubkey(3, greg_hash))
+        );
+        assert_eq!(StakingCount::<Test>::get(karl_hash), Some((1, 4)));
+
+        // the pool is gone with its last member
+        assert_ok!(Utxo::leave_staking_pool(
+            Origin::signed(karl_hash),
+            karl_hash
+        ));
+        assert!(!StakingPools::<Test>::contains_key(karl_hash));
+        assert!(!StakingCount::<Test>::contains_key(karl_hash));
+    })
+}
+
+#[test]
+fn redelegating_stake() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice_hash = H256::from(keys_and_hashes[0].0);
+        let karl_hash = H256::from(keys_and_hashes[1].0);
+        let greg_hash = H256::from(keys_and_hashes[2].0);
+        let tom_hash = H256::from(keys_and_hashes[3].0);
+        let locked_utxo_hash = LockedUtxos::<Test>::iter().map(|(key, _)| key).next().unwrap();
+
+        assert_err!(
+            Utxo::redelegate_stake(Origin::signed(karl_hash), greg_hash, tom_hash, vec![2, 1]),
+            Error::<Test>::StashAccountNotFound
+        );
+        // TOM (index 3) is still the controller of ALICE (index 0)
+        assert_err!(
+            Utxo::redelegate_stake(Origin::signed(alice_hash), karl_hash, tom_hash, vec![2, 1]),
+            "specified controller account is already used."
+        );
+
+        // ALICE moves her stake to KARL (index 1), with GREG (index 2) as the controller.
+        assert_ok!(Utxo::redelegate_stake(
+            Origin::signed(alice_hash),
+            karl_hash,
+            greg_hash,
+            vec![2, 1]
+        ));
+        assert!(!StakingCount::<Test>::contains_key(alice_hash));
+        assert_eq!(StakingCount::<Test>::get(karl_hash), Some((1, 10)));
+        assert_eq!(
+            LockedUtxos::<Test>::get(locked_utxo_hash).map(|utxo| utxo.destination),
+            Some(Destination::LockForStaking {
+                stash_account: karl_hash,
+                controller_account: greg_hash,
+                session_key: vec![2, 1],
+                withdrawal_key: None,
+                secondary_controller: None,
+                commission: Perbill::zero(),
+                min_self_bond: 0,
+            })
+        );
+        assert!(
+            System::events()
+                .iter()
+                .any(|r| r.event
+                    == Event::Utxo(crate::Event::StakeRedelegated(alice_hash, karl_hash)))
+        );
+
+        // the stake is withdrawn by the new stash account
+        assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(
+            karl_hash
+        )));
+        for _ in 1..6 {
+            next_block();
+        }
+        assert_ok!(Utxo::withdraw_stake(Origin::signed(karl_hash)));
+        assert!(!LockedUtxos::<Test>::contains_key(locked_utxo_hash));
+    })
+}
+
+#[test]
+fn withdrawal_fee_destinations() {
+    let treasury = H256::repeat_byte(0x77);
+    let withdraw_with = |destination: FeeDestination<H256>| {
+        let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+        test_ext.execute_with(|| {
+            System::set_block_number(1);
+            StakeWithdrawalFeeDestination::set(&destination);
+            let alice_hash = H256::from(keys_and_hashes[0].0);
+            assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(
+                alice_hash
+            )));
+            for _ in 1..6 {
+                next_block();
+            }
+            let (issuance, reward_total) = (Utxo::mlt_issuance(), Utxo::reward_total());
+            assert_ok!(Utxo::withdraw_stake(Origin::signed(alice_hash)));
+            let fee = StakeWithdrawalFee::get();
+            assert!(System::events().iter().any(|r| r.event
+                == Event::Utxo(crate::Event::WithdrawalFeePaid(fee, destination.clone()))));
+            (
+                issuance - Utxo::mlt_issuance(),
+                Utxo::reward_total() - reward_total,
+                Balances::free_balance(&treasury),
+            )
+        })
+    };
+
+    // (burned or moved out of the UTXO ledger, paid to the block author, held by the treasury)
+    assert_eq!(withdraw_with(FeeDestination::RewardPool), (0, 1, 0));
+    assert_eq!(withdraw_with(FeeDestination::Burn), (1, 0, 0));
+    assert_eq!(withdraw_with(FeeDestination::Treasury(treasury)), (1, 0, 1));
+}
+
+#[test]
+fn rotating_session_keys() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice_hash = H256::from(keys_and_hashes[0].0);
+        let (karl_pub_key, karl_genesis) = keys_and_hashes[1];
+        let karl_hash = H256::from(karl_pub_key);
+
+        assert_ok!(Utxo::rotate_session_keys(
+            Origin::signed(alice_hash),
+            vec![3, 2]
+        ));
+        assert!(System::events()
+            .iter()
+            .any(|r| r.event == Event::Utxo(crate::Event::SessionKeysRotated(alice_hash))));
+
+        // KARL (index 1) is not staking
+        assert_err!(
+            Utxo::rotate_session_keys(Origin::signed(karl_hash), vec![3, 2]),
+            Error::<Test>::StashAccountNotFound
+        );
+
+        // the keys of a pool that is not bonded yet are kept until it is
+        let utxo = UtxoStore::<Test>::get(karl_genesis).expect("karl's utxo does not exist");
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(karl_genesis)],
+            outputs: vec![TransactionOutput::new_create_staking_pool(
+                4,
+                karl_hash,
+                H256::from(keys_and_hashes[2].0),
+                vec![2, 1],
+                karl_pub_key,
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign(&[utxo], 0, &karl_pub_key)
+        .expect("karl's pub key not found");
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert_ok!(Utxo::rotate_session_keys(
+            Origin::signed(karl_hash),
+            vec![4, 2]
+        ));
+        assert_eq!(
+            StakingPools::<Test>::get(karl_hash).map(|pool| pool.session_key),
+            Some(vec![4, 2])
+        );
+    })
+}
+
 #[test]
 fn non_validator_pausing() {
     let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
@@ -396,9 +912,107 @@ fn withdrawing_before_expected_period() {
         // ALICE is not waiting for the withdrawal period.
         assert_err!(
             Utxo::withdraw_stake(Origin::signed(H256::from(alice_pub_key))),
-            "not yet time to withdraw"
+            Error::<Test>::WithdrawalNotMature
         );
     })
 }
 
+#[test]
+fn withdrawal_matures_at_era() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice_hash = H256::from(keys_and_hashes[0].0);
+
+        assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(
+            alice_hash
+        )));
+        // the mock starts every block a new era, at era 0
+        assert_eq!(Utxo::pending_withdrawal(alice_hash), Some((10, 5)));
+        assert!(System::events()
+            .iter()
+            .any(|r| r.event == Event::Utxo(crate::Event::WithdrawalScheduled(alice_hash, 10, 5))));
+
+        let matured = Event::Utxo(crate::Event::WithdrawalMatured(alice_hash, 10, 5));
+        for _ in 1..5 {
+            next_block();
+        }
+        crate::staking::notify_matured_withdrawals::<Test>();
+        assert!(!System::events().iter().any(|r| r.event == matured));
+        assert_err!(
+            Utxo::withdraw_stake(Origin::signed(alice_hash)),
+            Error::<Test>::WithdrawalNotMature
+        );
+
+        next_block();
+        crate::staking::notify_matured_withdrawals::<Test>();
+        assert!(System::events().iter().any(|r| r.event == matured));
+        assert!(!MaturingWithdrawals::<Test>::contains_key(5));
+        assert_ok!(Utxo::withdraw_stake(Origin::signed(alice_hash)));
+        assert_eq!(Utxo::pending_withdrawal(alice_hash), None);
+    })
+}
+
 //TODO: add more test scenarios
+
+#[test]
+fn staking_count_invariant() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        let alice_hash = H256::from(keys_and_hashes[0].0);
+        assert_ok!(crate::pallet::try_state::<Test>());
+
+        assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(
+            alice_hash
+        )));
+        for _ in 1..6 {
+            next_block();
+        }
+        assert_ok!(Utxo::withdraw_stake(Origin::signed(alice_hash)));
+        assert_ok!(crate::pallet::try_state::<Test>());
+
+        StakingCount::<Test>::insert(alice_hash, (1, 10));
+        assert_err!(
+            crate::pallet::try_state::<Test>(),
+            "StakingCount doesn't match the locked utxos"
+        );
+    })
+}
+
+#[test]
+fn staking_info_of_stash_account() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        let alice_hash = H256::from(keys_and_hashes[0].0);
+        let karl_hash = H256::from(keys_and_hashes[1].0);
+        assert_eq!(Utxo::staking_info(karl_hash), None);
+
+        let info = Utxo::staking_info(alice_hash).expect("alice is staking");
+        assert_eq!((info.locked_total, info.num_utxos), (10, 1));
+        assert_eq!(
+            info.outpoints,
+            LockedUtxos::<Test>::iter_keys().collect::<Vec<_>>()
+        );
+        assert_eq!(info.unbonding, None);
+        assert_eq!(
+            Utxo::validator_stakes(),
+            vec![pallet_utxo_rpc_runtime_api::ValidatorStake {
+                stash: alice_hash,
+                locked_total: 10,
+                num_utxos: 1,
+            }]
+        );
+
+        assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(
+            alice_hash
+        )));
+        let unbonding = Utxo::staking_info(alice_hash).and_then(|info| info.unbonding);
+        assert_eq!(
+            unbonding,
+            Some(pallet_utxo_rpc_runtime_api::Unbonding {
+                value: 10,
+                withdrawable_at_era: 5,
+            })
+        );
+    })
+}
@@ -0,0 +1,60 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Streaming payments
+//!
+//! A `Destination::Stream` output pays its value to the payee at `rate` MLT per block, counted
+//! from the block the UTXO was created in. The payee can spend the UTXO at any time, but the
+//! transaction has to pay the part not streamed yet back to the same destination. The stream
+//! goes on from this continuation output, so a salary can be paid once and withdrawn by the
+//! payee whenever they like.
+
+use crate::tokens::Value;
+
+/// Part of `value` streamed at `rate` per block after `blocks` blocks.
+pub fn streamed_value(value: Value, rate: Value, blocks: u64) -> Value {
+    rate.saturating_mul(blocks.into()).min(value)
+}
+
+/// Part of `value` still to be streamed at `rate` per block after `blocks` blocks, which has to go
+/// on streaming. Zero once `rate * blocks` exceeds `value`.
+pub fn unstreamed_value(value: Value, rate: Value, blocks: u64) -> Value {
+    value.saturating_sub(streamed_value(value, rate, blocks))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_streamed_value() {
+        assert_eq!(streamed_value(1000, 10, 0), 0);
+        assert_eq!(streamed_value(1000, 10, 42), 420);
+        assert_eq!(streamed_value(1000, 10, 100), 1000);
+        assert_eq!(streamed_value(1000, 10, 1_000), 1000);
+        assert_eq!(streamed_value(1000, Value::MAX, u64::MAX), 1000);
+    }
+
+    #[test]
+    fn test_unstreamed_value() {
+        assert_eq!(unstreamed_value(1000, 10, 0), 1000);
+        assert_eq!(unstreamed_value(1000, 10, 42), 580);
+        assert_eq!(unstreamed_value(1000, 10, 100), 0);
+        // rate * blocks exceeds the value
+        assert_eq!(unstreamed_value(1000, 10, 101), 0);
+        assert_eq!(unstreamed_value(1000, 300, 7), 0);
+        assert_eq!(unstreamed_value(1000, Value::MAX, u64::MAX), 0);
+    }
+}
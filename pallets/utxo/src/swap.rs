@@ -0,0 +1,87 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Atomic swaps of tokens between two parties
+//!
+//! Two parties can exchange token A for token B in a single transaction, without an escrow:
+//! 1. The maker creates an offer with [Transaction::new_swap_offer], spending its token A and
+//!    asking for the token B output in return. The offer is signed with [offer_sighash], which
+//!    commits to the maker's input and the output at the same index only.
+//! 2. The taker appends its token B input and the token A output it receives with
+//!    [Transaction::add_counterparty_input] and signs its input with [accept_sighash].
+//!
+//! The transaction is accepted as a whole or not at all, so no party can take the other's
+//! tokens without delivering its own.
+
+pub use crate::sign::SigHash;
+use crate::sign::{Public, TransactionSigMsg};
+use crate::{Transaction, TransactionInput, TransactionOutput};
+use codec::Encode;
+
+/// `SIGHASH_SINGLE | SIGHASH_ANYONECANPAY`
+const SINGLE_ANYONECANPAY: u8 = 0x83;
+/// `SIGHASH_ALL`
+const ALL: u8 = 0x01;
+
+/// Sighash for the maker of an offer. Commits to the signed input and the output at the same
+/// index only, leaving the counterparty free to add its own input and output.
+pub fn offer_sighash() -> SigHash {
+    SigHash::from_u8(SINGLE_ANYONECANPAY).expect("valid sighash")
+}
+
+/// Sighash for the taker of an offer. Commits to the whole transaction.
+pub fn accept_sighash() -> SigHash {
+    SigHash::from_u8(ALL).expect("valid sighash")
+}
+
+impl<AccountId: Encode> Transaction<AccountId> {
+    /// Create a swap offer giving away the UTXO spent by `input` in exchange for `ask`.
+    pub fn new_swap_offer(input: TransactionInput, ask: TransactionOutput<AccountId>) -> Self {
+        Self {
+            inputs: vec![input],
+            outputs: vec![ask],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+    }
+
+    /// Add the counterparty's side of a swap: the `input` it gives away and the `output` it
+    /// receives in return. Both are appended, so the input and output pairs of the maker keep
+    /// their indices.
+    pub fn add_counterparty_input(
+        mut self,
+        input: TransactionInput,
+        output: TransactionOutput<AccountId>,
+    ) -> Self {
+        self.inputs.push(input);
+        self.outputs.push(output);
+        self
+    }
+
+    /// Sign the input at `index` committing only to the parts of the transaction selected by
+    /// `sighash`. With `SIGHASH_ANYONECANPAY`, `utxos` still has to match the inputs known at
+    /// the time of signing.
+    pub fn partial_sign<P: Into<Public> + Copy>(
+        mut self,
+        utxos: &[TransactionOutput<AccountId>],
+        index: usize,
+        pk: &P,
+        sighash: SigHash,
+    ) -> Option<Self> {
+        let msg = TransactionSigMsg::construct(sighash, &self, utxos, index as u64, u32::MAX);
+        self.inputs[index].witness = (*pk).into().witness(&msg)?;
+        Some(self)
+    }
+}
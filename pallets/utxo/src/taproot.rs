@@ -0,0 +1,120 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Taproot-style outputs
+//!
+//! A `Destination::Taproot` output can be spent in one of two ways:
+//! * Key path: the `lock` is empty and the `witness` is a signature by the `internal_key`, just
+//!   like for `Destination::Pubkey`. The internal key is usually an aggregate of the keys of all
+//!   the parties (e.g. MuSig), so a cooperative spend looks like any single-signature spend.
+//! * Script path: the `lock` is one of the scripts committed to by the `merkle_root` and the
+//!   `witness` is an encoded [ScriptPathWitness] carrying the Merkle proof of the script and the
+//!   witness script satisfying it. Scripts are interpreted like `Destination::ScriptHash` ones of
+//!   version 0.
+//!
+//! Unlike BIP 341, the internal key is not tweaked with the Merkle root since sr25519 point
+//! arithmetic is not available in the runtime. The Merkle root is committed to in the destination.
+
+use codec::{Decode, Encode};
+use frame_support::sp_runtime::traits::{BlakeTwo256, Hash};
+use sp_core::H256;
+use sp_std::prelude::*;
+
+/// Witness of a script path spend.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, Default)]
+pub struct ScriptPathWitness {
+    /// Hashes of the sibling nodes on the path from the script leaf to the Merkle root
+    pub proof: Vec<H256>,
+    /// Witness script generating the input to the spent script
+    pub witness: Vec<u8>,
+}
+
+/// Domain separation tag of leaf hashes, so that an inner node can't be passed off as a script
+const LEAF_TAG: u8 = 0;
+/// Domain separation tag of inner node hashes
+const BRANCH_TAG: u8 = 1;
+
+/// Hash of a script leaf.
+pub fn leaf_hash(script: &[u8]) -> H256 {
+    BlakeTwo256::hash(&[&[LEAF_TAG][..], script].concat())
+}
+
+/// Hash of an inner node. The children are sorted so that proofs do not need to record whether
+/// the sibling is on the left or on the right.
+pub fn branch_hash(a: &H256, b: &H256) -> H256 {
+    let (l, r) = if a <= b { (a, b) } else { (b, a) };
+    BlakeTwo256::hash(&[&[BRANCH_TAG][..], l.as_bytes(), r.as_bytes()].concat())
+}
+
+/// Calculate the Merkle root from a leaf and its proof.
+pub fn root_from_proof(leaf: H256, proof: &[H256]) -> H256 {
+    proof.iter().fold(leaf, |node, sibling| branch_hash(&node, sibling))
+}
+
+/// Hash the nodes of a tree level pairwise, the last node is moved up if it has no sibling.
+fn next_level(level: &[H256]) -> Vec<H256> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [a, b] => branch_hash(a, b),
+            [a] => *a,
+            _ => unreachable!("chunks of two"),
+        })
+        .collect()
+}
+
+/// Calculate the Merkle root of given leaves.
+pub fn merkle_root(leaves: &[H256]) -> Option<H256> {
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level.pop()
+}
+
+/// Calculate the Merkle proof of the leaf at `index`, to be used with [root_from_proof].
+pub fn merkle_proof(leaves: &[H256], mut index: usize) -> Option<Vec<H256>> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let mut proof = Vec::new();
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        level = next_level(&level);
+        index /= 2;
+    }
+    Some(proof)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_merkle_proofs() {
+        let leaves: Vec<H256> = (0u8..5).map(|i| leaf_hash(&[i])).collect();
+        let root = merkle_root(&leaves).unwrap();
+        for (index, leaf) in leaves.iter().enumerate() {
+            let proof = merkle_proof(&leaves, index).unwrap();
+            assert_eq!(root_from_proof(*leaf, &proof), root);
+        }
+        assert_eq!(merkle_proof(&leaves, 5), None);
+        assert_eq!(merkle_root(&leaves[..1]), Some(leaves[0]));
+        assert_eq!(merkle_root(&[]), None);
+    }
+}
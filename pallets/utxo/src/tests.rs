@@ -16,15 +16,27 @@
 // Author(s): C. Yap
 
 use crate::{
-    mock::*, tokens::Value, BlockTime, Destination, RawBlockTime, RewardTotal, Transaction,
-    TransactionInput, TransactionOutput, UtxoStore,
+    builder::TxBuilder,
+    channels,
+    compliance::TransferPolicy,
+    escrow, inspect,
+    mock::*,
+    pause::PauseScope,
+    priority::{FeeAsset, PriorityInfo},
+    psbt::PartiallySignedTransaction,
+    swap, taproot,
+    tokens::{TickerCharset, Value},
+    BlockTime, Destination, NftUniqueDataHash, RawBlockTime, RelativeTimeLock, ReserveProof,
+    RewardTotal, ScriptVersion, Transaction, TransactionInput, TransactionOutput, UtxoStore,
+    WatcherCount,
 };
-use chainscript::{opcodes::all as opc, Builder};
-use codec::Encode;
+use chainscript::{opcodes::all as opc, Builder, Script};
+use codec::{Decode, Encode};
 use frame_support::{
     assert_err, assert_noop, assert_ok,
     sp_io::crypto,
     sp_runtime::traits::{BlakeTwo256, Hash},
+    sp_runtime::transaction_validity::TransactionSource,
 };
 
 use crate::script::test::gen_block_time_real;
@@ -62,7 +74,7 @@ impl<AccountId: Encode> Transaction<AccountId> {
 #[test]
 fn pubkey_commitment_hash() {
     let dest = Destination::<u64>::Pubkey(Public([0; 32]).into());
-    assert_eq!(dest.lock_commitment(), &BlakeTwo256::hash(&[]));
+    assert_eq!(dest.lock_commitment(), Some(&BlakeTwo256::hash(&[])));
 }
 
 #[test]
@@ -87,6 +99,7 @@ fn test_script_preimage() {
                 script_hash,
             )],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign_unchecked(&[utxo0], 0, &alice_pub_key);
 
@@ -97,6 +110,7 @@ fn test_script_preimage() {
                 H256::zero(),
             )],
             time_lock: Default::default(),
+            expires_at: None,
         };
 
         assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1));
@@ -105,1323 +119,5405 @@ fn test_script_preimage() {
 }
 
 #[test]
-fn test_unchecked_2nd_output() {
+fn test_script_unknown_version() {
     execute_with_alice(|alice_pub_key| {
-        // Create and sign a transaction
+        // A script that can never be satisfied under the current rules
+        let script = Builder::new().push_opcode(opc::OP_RETURN).into_script();
+        let script_hash: H256 = BlakeTwo256::hash(script.as_ref());
+
         let (utxo0, input0) = tx_input_gen_no_signature();
         let tx1 = Transaction {
             inputs: vec![input0],
-            outputs: vec![
-                TransactionOutput::new_pubkey(
-                    ALICE_GENESIS_BALANCE - 30,
-                    H256::from(alice_pub_key),
-                ),
-                TransactionOutput::new_pubkey(
-                    ALICE_GENESIS_BALANCE - 50,
-                    H256::from(alice_pub_key),
-                ),
-            ],
+            outputs: vec![TransactionOutput::new_versioned_script_hash(
+                ALICE_GENESIS_BALANCE - 50,
+                ScriptVersion::new(1),
+                script_hash,
+            )],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
 
-        // Calculate output 1 hash.
-        let utxo1_hash = tx1.outpoint(1);
-        // Now artificially insert utxo1 (that pays to a pubkey) to the output set.
-        UtxoStore::<Test>::insert(utxo1_hash, &tx1.outputs[1]);
-        // When adding a transaction, the output should be reported as already present.
-        assert_err!(
-            Utxo::spend(Origin::signed(H256::zero()), tx1),
-            "output already exists"
+        let tx2 = Transaction {
+            inputs: vec![TransactionInput::new_script(tx1.outpoint(0), script, Default::default())],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 120,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+
+        // Not relayed by the pool, but valid in a block
+        assert_eq!(
+            crate::pallet::validate_pool_transaction::<Test>(&tx2, TransactionSource::External),
+            Err("unknown script version")
         );
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx2));
     })
 }
 
 #[test]
-fn test_simple_tx() {
+fn test_parallel_script_verification() {
+    for parallel in [false, true] {
+        let mut test_ext = alice_test_ext();
+        if parallel {
+            test_ext.register_extension(crate::verifier::ScriptVerifierExt(
+                crate::verifier::verify_batch_parallel::<H256>,
+            ));
+        }
+        test_ext.execute_with(|| {
+            let alice_pub_key = crypto::sr25519_public_keys(SR25519)[0];
+            let passwords: Vec<&[u8]> = vec![b"one", b"two", b"three"];
+            let scripts: Vec<_> = passwords
+                .iter()
+                .map(|password| {
+                    Builder::new()
+                        .push_opcode(opc::OP_SHA256)
+                        .push_slice(&sp_core::hashing::sha2_256(password))
+                        .push_opcode(opc::OP_EQUAL)
+                        .into_script()
+                })
+                .collect();
+
+            let (utxo0, input0) = tx_input_gen_no_signature();
+            let tx1 = Transaction {
+                inputs: vec![input0],
+                outputs: scripts
+                    .iter()
+                    .map(|script| {
+                        TransactionOutput::new_script_hash(100, BlakeTwo256::hash(script.as_ref()))
+                    })
+                    .collect(),
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+            assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
+
+            let spend = |witnesses: &[&[u8]]| Transaction {
+                inputs: scripts
+                    .iter()
+                    .zip(witnesses)
+                    .enumerate()
+                    .map(|(index, (script, witness))| {
+                        let witness = Builder::new().push_slice(witness).into_script();
+                        TransactionInput::new_script(
+                            tx1.outpoint(index as u64),
+                            script.clone(),
+                            witness,
+                        )
+                    })
+                    .collect(),
+                outputs: vec![TransactionOutput::new_pubkey(250, H256::from(alice_pub_key))],
+                time_lock: Default::default(),
+                expires_at: None,
+            };
+
+            // An invalid script anywhere fails the transaction
+            for index in 0..passwords.len() {
+                let mut witnesses = passwords.clone();
+                witnesses[index] = b"wrong";
+                assert_noop!(
+                    Utxo::spend(Origin::signed(H256::zero()), spend(&witnesses)),
+                    "script verification failed"
+                );
+            }
+            assert_ok!(Utxo::spend(Origin::signed(H256::zero()), spend(&passwords)));
+        })
+    }
+}
+
+#[test]
+fn test_taproot_key_path() {
     execute_with_alice(|alice_pub_key| {
-        // Alice wants to send herself a new utxo of value 50.
         let (utxo0, input0) = tx_input_gen_no_signature();
-        let tx = Transaction {
+        let tx1 = Transaction {
             inputs: vec![input0],
-            outputs: vec![TransactionOutput::new_pubkey(
+            outputs: vec![TransactionOutput::new_taproot(
                 ALICE_GENESIS_BALANCE - 50,
                 H256::from(alice_pub_key),
+                H256::zero(),
             )],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
 
-        let new_utxo_hash = tx.outpoint(0);
-
-        let (_, init_utxo) = genesis_utxo();
-        assert!(UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
-        assert!(!UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
-        assert!(UtxoStore::<Test>::contains_key(new_utxo_hash));
-        assert_eq!(
-            ALICE_GENESIS_BALANCE - 50,
-            UtxoStore::<Test>::get(new_utxo_hash).unwrap().value
+        let tx2 = Transaction {
+            inputs: vec![TransactionInput::new_empty(tx1.outpoint(0))],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 120,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        let mut bad_tx2 = tx2.clone();
+        bad_tx2.inputs[0].witness = vec![0u8; 64];
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), bad_tx2),
+            "signature must be valid"
         );
+        let tx2 = tx2.sign_unchecked(&tx1.outputs, 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx2));
     })
 }
 
 #[test]
-fn attack_with_sending_to_own_account() {
-    let (mut test_ext, _alice, karl_pub_key) = alice_test_ext_and_keys();
+fn test_batch_signature_verification() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
     test_ext.execute_with(|| {
-        // Karl wants to send himself a new utxo of value 50 out of thin air.
-        let mut tx = Transaction {
-            inputs: vec![TransactionInput::new_empty(H256::zero())],
-            outputs: vec![TransactionOutput::new_pubkey(50, H256::from(karl_pub_key))],
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx1 = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_pubkey(100, H256::from(alice_pub_key)),
+                TransactionOutput::new_pubkey(200, H256::from(karl_pub_key)),
+                TransactionOutput::new_pubkey(300, H256::from(alice_pub_key)),
+            ],
             time_lock: Default::default(),
-        };
-
-        let karl_sig = crypto::sr25519_sign(SR25519, &karl_pub_key, &tx.encode()).unwrap();
-        tx.inputs[0].witness = karl_sig.0.to_vec();
-
-        assert_noop!(
-            Utxo::spend(Origin::signed(H256::zero()), tx),
-            "missing inputs"
-        );
-    });
-}
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
 
-#[test]
-fn attack_with_empty_transactions() {
-    alice_test_ext().execute_with(|| {
-        // We should use the real input because. Otherwise, appears another error
-        let (_, input) = tx_input_gen_no_signature();
-        assert_err!(
-            Utxo::spend(Origin::signed(H256::zero()), Transaction::default()), // empty tx
-            "no inputs"
-        );
+        let tx2 = Transaction {
+            inputs: (0..3).map(|index| TransactionInput::new_empty(tx1.outpoint(index))).collect(),
+            outputs: vec![TransactionOutput::new_pubkey(550, H256::from(karl_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&tx1.outputs, 0, &alice_pub_key)
+        .sign_unchecked(&tx1.outputs, 1, &karl_pub_key)
+        .sign_unchecked(&tx1.outputs, 2, &alice_pub_key);
 
+        // A single invalid signature fails the batch, and the inputs are checked one by one
+        for index in 0..3 {
+            let mut bad_tx2 = tx2.clone();
+            bad_tx2.inputs[index].witness = tx2.inputs[(index + 1) % 3].witness.clone();
+            assert_err!(
+                Utxo::spend(Origin::signed(H256::zero()), bad_tx2),
+                "signature must be valid"
+            );
+        }
+        let mut bad_tx2 = tx2.clone();
+        bad_tx2.inputs[1].witness = vec![0u8; 3];
         assert_err!(
-            Utxo::spend(
-                Origin::signed(H256::zero()),
-                Transaction {
-                    inputs: vec![input], // an empty tx
-                    outputs: vec![],
-                    time_lock: Default::default()
-                }
-            ),
-            "no outputs"
+            Utxo::spend(Origin::signed(H256::zero()), bad_tx2),
+            "bad signature format"
         );
-    });
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx2));
+    })
 }
 
 #[test]
-fn attack_by_double_counting_input() {
-    execute_with_alice(|alice_pub_key| {
+fn test_verification_cache() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    let cache = crate::verifier::VerificationCache::new(2);
+    test_ext.register_extension(crate::verifier::VerificationCacheExt(cache.clone()));
+    test_ext.execute_with(|| {
         let (utxo0, input0) = tx_input_gen_no_signature();
-        let utxos = [utxo0.clone(), utxo0];
         let tx = Transaction {
-            // a double spend of the same UTXO!
-            inputs: vec![input0.clone(), input0],
-            outputs: vec![TransactionOutput::new_pubkey(100, H256::from(alice_pub_key))],
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_pubkey(100, H256::from(karl_pub_key))],
             time_lock: Default::default(),
-        }
-        .sign_unchecked(&utxos[..], 0, &alice_pub_key)
-        .sign_unchecked(&utxos[..], 1, &alice_pub_key);
+            expires_at: None,
+        };
+        let utxos = vec![utxo0];
+        let chain = crate::pallet::chain_binding::<Test>();
+        let key = |tx: &Transaction<H256>| crate::verifier::cache_key(tx, &utxos, chain, &[]);
 
+        // Transactions failing in the pool are not cached
+        let mut bad_tx = tx.clone();
+        bad_tx.inputs[0].witness = vec![0u8; 64];
         assert_err!(
-            Utxo::spend(Origin::signed(H256::zero()), tx),
-            "each input should be used only once"
+            crate::pallet::validate_pool_transaction::<Test>(&bad_tx, TransactionSource::External),
+            "signature must be valid"
         );
-    });
-}
+        assert!(!cache.contains(&key(&bad_tx)));
 
-#[test]
-fn attack_with_invalid_signature() {
-    execute_with_alice(|alice_pub_key| {
-        let (_utxo0, input0) = genesis_utxo();
-        let tx = Transaction {
-            // Just a random signature!
-            inputs: vec![TransactionInput::new_with_signature(input0, H512::random())],
-            outputs: vec![TransactionOutput::new_pubkey(100, H256::from(alice_pub_key))],
-            time_lock: Default::default(),
-        };
+        let tx = tx.sign_unchecked(&utxos, 0, &alice_pub_key);
+        assert_ok!(crate::pallet::validate_pool_transaction::<Test>(
+            &tx,
+            TransactionSource::External
+        ));
+        assert!(cache.contains(&key(&tx)));
 
+        // A witness changed by anyone relaying the transaction keeps its txid, but not its key
+        let mut malleated = tx.clone();
+        let last = malleated.inputs[0].witness.len() - 1;
+        malleated.inputs[0].witness[last] ^= 1;
+        assert_eq!(malleated.txid(), tx.txid());
+        assert_ne!(key(&malleated), key(&tx));
         assert_err!(
-            Utxo::spend(Origin::signed(H256::zero()), tx),
+            crate::pallet::validate_transaction::<Test>(&malleated),
             "signature must be valid"
         );
-    });
-}
 
-#[test]
-fn attack_by_permanently_sinking_outputs() {
-    execute_with_alice(|alice_pub_key| {
-        let (utxo0, input0) = tx_input_gen_no_signature();
-        let tx = Transaction {
-            inputs: vec![input0],
-            //A 0 value output burns this output forever!
-            outputs: vec![TransactionOutput::new_pubkey(0, H256::from(alice_pub_key))],
-            time_lock: Default::default(),
-        }
-        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        // The block import trusts the cache, block validation doesn't add to it
+        cache.insert(key(&bad_tx));
+        assert_ok!(crate::pallet::validate_transaction::<Test>(&bad_tx));
+        let mut other_tx = tx.clone();
+        other_tx.outputs[0].value = 50;
+        let other_tx = other_tx.sign_unchecked(&utxos, 0, &alice_pub_key);
+        assert_ok!(crate::pallet::validate_transaction::<Test>(&other_tx));
+        assert!(!cache.contains(&key(&other_tx)));
 
-        assert_noop!(
-            Utxo::spend(Origin::signed(H256::zero()), tx),
-            "output value must be nonzero"
-        );
-    });
+        // The least recently used key is dropped
+        assert!(cache.contains(&key(&tx)));
+        cache.insert(key(&other_tx));
+        assert!(!cache.contains(&key(&bad_tx)));
+        assert!(cache.contains(&key(&tx)));
+    })
 }
 
 #[test]
-fn attack_by_overflowing_value() {
+fn test_chain_bound_signatures() {
     execute_with_alice(|alice_pub_key| {
         let (utxo0, input0) = tx_input_gen_no_signature();
         let tx = Transaction {
             inputs: vec![input0],
-            outputs: vec![
-                TransactionOutput::new_pubkey(Value::MAX, H256::from(alice_pub_key)),
-                // Attempts to do overflow total output value
-                TransactionOutput::new_pubkey(10, H256::from(alice_pub_key)),
-            ],
+            outputs: vec![TransactionOutput::new_pubkey(100, H256::from(alice_pub_key))],
             time_lock: Default::default(),
-        }
-        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+            expires_at: None,
+        };
+        let utxos = [utxo0];
+        let unbound = tx.clone().sign_unchecked(&utxos, 0, &alice_pub_key);
+        let other_chain = tx
+            .clone()
+            .sign_for_chain(&utxos, 0, &alice_pub_key, H256::repeat_byte(0x01))
+            .unwrap();
+        let bound = tx.sign_for_chain(&utxos, 0, &alice_pub_key, ChainId::get()).unwrap();
 
+        // Signatures not bound to the chain are only accepted during the migration window
+        assert_ok!(crate::pallet::validate_transaction::<Test>(&unbound));
+        UnboundSignaturesUntil::set(&0);
         assert_err!(
-            Utxo::spend(Origin::signed(H256::zero()), tx),
-            "output value overflow"
+            crate::pallet::validate_transaction::<Test>(&unbound),
+            "signature must commit to the chain"
         );
-    });
+        assert_err!(
+            crate::pallet::validate_transaction::<Test>(&other_chain),
+            "signature must be valid"
+        );
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), bound));
+
+        // The transactions built by the extrinsics are bound to the chain
+        let addr = "ml1qrft7juyfhl06emj4zzrue5ljs6q39n2jalr4c40rhtcur647n0kwueyfsn";
+        assert_ok!(Utxo::send_to_address(
+            Origin::signed(H256::from(alice_pub_key)),
+            10,
+            addr.as_bytes().to_vec()
+        ));
+    })
 }
 
 #[test]
-fn attack_by_overspending() {
+fn test_witness_scheme_prefix() {
     execute_with_alice(|alice_pub_key| {
         let (utxo0, input0) = tx_input_gen_no_signature();
         let tx = Transaction {
             inputs: vec![input0],
-            outputs: vec![
-                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE, H256::from(alice_pub_key)),
-                // Creates 2 new utxo out of thin air
-                TransactionOutput::new_pubkey(2, H256::from(alice_pub_key)),
-            ],
+            outputs: vec![TransactionOutput::new_pubkey(100, H256::from(alice_pub_key))],
             time_lock: Default::default(),
-        }
-        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+            expires_at: None,
+        };
+        let utxos = [utxo0];
+        let signed = tx.sign_unchecked(&utxos, 0, &alice_pub_key);
+        let witness = signed.inputs[0].witness.clone();
+        assert_eq!(witness[..2], [crate::sign::WITNESS_SCHEME_MARKER, 0]);
+        assert_eq!(witness.len(), 66);
 
-        assert_noop!(
-            Utxo::spend(Origin::signed(H256::zero()), tx),
-            "output value must not exceed input value"
+        // A witness of another scheme than the key's is rejected
+        let mut other_scheme = signed.clone();
+        other_scheme.inputs[0].witness[1] = 1;
+        assert_err!(
+            crate::pallet::validate_transaction::<Test>(&other_scheme),
+            "bad signature format"
         );
+
+        // Witnesses without the prefix are still Schnorr signatures
+        let mut unprefixed = signed.clone();
+        unprefixed.inputs[0].witness = witness[2..].to_vec();
+        assert_ok!(crate::pallet::validate_transaction::<Test>(&unprefixed));
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), signed));
     })
 }
 
-// first send 10 tokens to karl and return the rest back to alice
-// then send the rest of the tokens to karl
 #[test]
-fn tx_from_alice_to_karl() {
-    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
-    test_ext.execute_with(|| {
-        // alice sends 10 tokens to karl and the rest back to herself
+fn test_witness_canonicality() {
+    execute_with_alice(|alice_pub_key| {
         let (utxo0, input0) = tx_input_gen_no_signature();
-        let tx = Transaction {
+        let password: &[u8] = "Hello!".as_bytes();
+        let script = Builder::new()
+            .push_opcode(opc::OP_SHA256)
+            .push_slice(&sp_core::hashing::sha2_256(password))
+            .push_opcode(opc::OP_EQUAL)
+            .into_script();
+        let tx1 = Transaction {
             inputs: vec![input0],
-            outputs: vec![
-                TransactionOutput::new_pubkey(10, H256::from(karl_pub_key)),
-                TransactionOutput::new_pubkey(
-                    ALICE_GENESIS_BALANCE - 90,
-                    H256::from(alice_pub_key),
-                ),
-            ],
+            outputs: vec![TransactionOutput::new_script_hash(
+                ALICE_GENESIS_BALANCE - 50,
+                BlakeTwo256::hash(script.as_ref()),
+            )],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        let witness = tx1.inputs[0].witness.clone();
+        let with_witness = |witness: Vec<u8>| {
+            let mut tx = tx1.clone();
+            tx.inputs[0].witness = witness;
+            tx
+        };
+        let mut unmarked = witness.clone();
+        unmarked[65] &= 0x7f;
+        let other_encodings = [
+            with_witness(witness[2..].to_vec()),
+            with_witness([&witness[..], &[0x00]].concat()),
+            with_witness(unmarked),
+        ];
 
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
-        let new_utxo_hash = tx.outpoint(1);
-        let new_utxo = tx.outputs[1].clone();
+        // Other encodings of the signature are accepted in blocks until canonical witnesses are
+        // enforced, never by the pool
+        assert_ok!(crate::pallet::validate_transaction::<Test>(
+            &other_encodings[0]
+        ));
+        for tx in other_encodings.iter() {
+            assert_err!(
+                crate::pallet::validate_pool_transaction::<Test>(tx, TransactionSource::Local),
+                "witness not canonical"
+            );
+        }
+        NonCanonicalWitnessesUntil::set(&0);
+        for tx in other_encodings.iter() {
+            assert_err!(
+                crate::pallet::validate_transaction::<Test>(tx),
+                "witness not canonical"
+            );
+        }
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
 
-        // then send rest of the tokens to karl (proving that the first tx was successful)
-        let tx = Transaction {
-            inputs: vec![TransactionInput::new_empty(new_utxo_hash)],
+        // Witness scripts push their data with minimal pushes
+        let spend_script = |witness: Vec<u8>| Transaction {
+            inputs: vec![TransactionInput::new_script(
+                tx1.outpoint(0),
+                script.clone(),
+                Script::from(witness),
+            )],
             outputs: vec![TransactionOutput::new_pubkey(
-                ALICE_GENESIS_BALANCE - 90,
-                H256::from(karl_pub_key),
+                ALICE_GENESIS_BALANCE - 100,
+                H256::from(alice_pub_key),
             )],
             time_lock: Default::default(),
-        }
-        .sign_unchecked(&[new_utxo], 0, &alice_pub_key);
+            expires_at: None,
+        };
+        let pushdata1 = [&[opc::OP_PUSHDATA1.into_u8(), password.len() as u8][..], password];
+        assert_err!(
+            crate::pallet::validate_transaction::<Test>(&spend_script(pushdata1.concat())),
+            "witness not canonical"
+        );
+        let witness = Builder::new().push_slice(password).into_script();
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            spend_script(witness.into_bytes())
+        ));
+    })
+}
 
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
-    });
+/// `witness` with the byte at `index` flipped by `mask`, `cut` bytes cut off its end and `extra`
+/// bytes appended.
+fn mutate_witness(
+    mut witness: Vec<u8>,
+    index: prop::sample::Index,
+    mask: u8,
+    cut: usize,
+    extra: Vec<u8>,
+) -> Vec<u8> {
+    witness[index.index(witness.len())] ^= mask;
+    witness.truncate(witness.len().saturating_sub(cut));
+    witness.extend(extra);
+    witness
 }
 
-// alice sends 90 tokens to herself and donates 10 tokens for the block authors
-#[test]
-fn test_reward() {
-    execute_with_alice(|alice_pub_key| {
-        let (utxo0, input0) = tx_input_gen_no_signature();
+proptest! {
+    #[test]
+    fn prop_signature_witness_not_malleable(
+        index in any::<prop::sample::Index>(),
+        mask in 1u8..,
+        cut in 0usize..3,
+        extra in prop::collection::vec(any::<u8>(), 0..3),
+    ) {
+        let (tx, mutated, result) = execute_with_alice(|alice| {
+            NonCanonicalWitnessesUntil::set(&0);
+            let (utxo0, input0) = tx_input_gen_no_signature();
+            let tx = Transaction {
+                inputs: vec![input0],
+                outputs: vec![TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 50, H256::from(alice))],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo0], 0, &alice);
+            let mut mutated = tx.clone();
+            mutated.inputs[0].witness =
+                mutate_witness(tx.inputs[0].witness.clone(), index, mask, cut, extra.clone());
+            let result = crate::pallet::validate_transaction::<Test>(&mutated);
+            (tx, mutated, result)
+        });
 
-        // Check the default parameters
-        let utxos = UtxoStore::<Test>::get(input0.outpoint).unwrap();
-        assert_eq!(utxos.value, ALICE_GENESIS_BALANCE);
-        let reward = RewardTotal::<Test>::get();
-        assert_eq!(reward, 0);
+        // Only the witness signed can spend the input, so the outpoints can't change
+        prop_assert!(result.is_err() || mutated.outpoint(0) == tx.outpoint(0));
+    }
 
-        // Make a new transaction
-        let tx = Transaction {
-            inputs: vec![input0],
-            outputs: vec![TransactionOutput::new_pubkey(
-                ALICE_GENESIS_BALANCE - 90,
-                H256::from(alice_pub_key),
-            )],
-            time_lock: Default::default(),
-        }
-        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+    #[test]
+    fn prop_script_witness_not_malleable(
+        index in any::<prop::sample::Index>(),
+        mask in 1u8..,
+        cut in 0usize..3,
+        extra in prop::collection::vec(any::<u8>(), 0..3),
+    ) {
+        let (tx, mutated, result) = execute_with_alice(|alice| {
+            NonCanonicalWitnessesUntil::set(&0);
+            let password: &[u8] = "Hello!".as_bytes();
+            let script = Builder::new()
+                .push_opcode(opc::OP_SHA256)
+                .push_slice(&sp_core::hashing::sha2_256(password))
+                .push_opcode(opc::OP_EQUAL)
+                .into_script();
+            let (utxo0, input0) = tx_input_gen_no_signature();
+            let tx1 = Transaction {
+                inputs: vec![input0],
+                outputs: vec![TransactionOutput::new_script_hash(
+                    ALICE_GENESIS_BALANCE - 50,
+                    BlakeTwo256::hash(script.as_ref()),
+                )],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo0], 0, &alice);
+            assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
 
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
-        let utxo_hash = tx.outpoint(0);
+            let witness = Builder::new().push_slice(password).into_script();
+            let tx = Transaction {
+                inputs: vec![TransactionInput::new_script(tx1.outpoint(0), script, witness)],
+                outputs: vec![TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 100, H256::from(alice))],
+                time_lock: Default::default(),
+                expires_at: None,
+            };
+            let mut mutated = tx.clone();
+            mutated.inputs[0].witness =
+                mutate_witness(tx.inputs[0].witness.clone(), index, mask, cut, extra.clone());
+            let result = crate::pallet::validate_transaction::<Test>(&mutated);
+            (tx, mutated, result)
+        });
 
-        // if the previous spend succeeded, there should be one utxo
-        // that has a value of ALICE_GENESIS_BALANCE - 90 and a reward that has a value of 90
-        let utxos = UtxoStore::<Test>::get(utxo_hash).unwrap();
-        let reward = RewardTotal::<Test>::get();
-        assert_eq!(utxos.value, ALICE_GENESIS_BALANCE - 90);
-        assert_eq!(reward, 90);
+        prop_assert!(result.is_err() || mutated.outpoint(0) == tx.outpoint(0));
+    }
+}
+
+/// An output paying any value to any key, with any token data. The tokens are the one issued by a
+/// transaction spending the genesis UTXO, or any other.
+fn arb_output() -> impl Strategy<Value = TransactionOutput<H256>> {
+    let token_id = prop_oneof![
+        Just(TokenId::from_outpoint(&genesis_utxo().1)),
+        any::<[u8; 20]>().prop_map(|id| TokenId::from_h160(id.into())),
+    ];
+    let data = prop_oneof![
+        Just(None),
+        (any::<Value>(), any::<u8>()).prop_map(|(amount_to_issue, number_of_decimals)| {
+            Some(OutputData::TokenIssuanceV1 {
+                token_ticker: b"FUZZ".to_vec(),
+                amount_to_issue,
+                number_of_decimals,
+                metadata_uri: vec![],
+            })
+        }),
+        (token_id.clone(), any::<Value>())
+            .prop_map(|(token_id, amount)| Some(OutputData::TokenTransferV1 { token_id, amount })),
+        (token_id, any::<Value>()).prop_map(|(token_id, amount_to_burn)| {
+            Some(OutputData::TokenBurnV1 {
+                token_id,
+                amount_to_burn,
+            })
+        }),
+    ];
+    let value = prop_oneof![0..=ALICE_GENESIS_BALANCE, any::<Value>()];
+    (value, any::<[u8; 32]>(), data).prop_map(|(value, key, data)| TransactionOutput {
+        value,
+        destination: Destination::Pubkey(Public(key)),
+        data,
     })
 }
 
+/// Adversarial changes to a transaction spending the genesis UTXO.
+#[derive(Clone, Debug)]
+enum TxMutation {
+    /// The genesis UTXO is spent twice
+    DuplicateInput,
+    /// An input spends an output that doesn't exist
+    MissingInput(H256),
+    /// The lock doesn't match the commitment of the genesis UTXO
+    MismatchedLock(Vec<u8>),
+    /// The inputs are not signed
+    Unsigned,
+    /// An input spends a contract UTXO with an empty witness, missing the spend opcode
+    EmptyCallWitness,
+}
+
+fn arb_tx_mutation() -> impl Strategy<Value = TxMutation> {
+    prop_oneof![
+        Just(TxMutation::DuplicateInput),
+        any::<[u8; 32]>().prop_map(|outpoint| TxMutation::MissingInput(H256(outpoint))),
+        prop::collection::vec(any::<u8>(), 1..8).prop_map(TxMutation::MismatchedLock),
+        Just(TxMutation::Unsigned),
+        Just(TxMutation::EmptyCallWitness),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn prop_transaction_checks_hold(
+        outputs in prop::collection::vec(arb_output(), 1..4),
+        mutation in prop::option::of(arb_tx_mutation()),
+    ) {
+        crate::fuzzing::check_transaction(|| {
+            let alice = crypto::sr25519_public_keys(SR25519)[0];
+            let (utxo0, input0) = tx_input_gen_no_signature();
+            let mut inputs = vec![input0];
+            let call = TransactionOutput::new_call_pp(10, H256::zero(), false, vec![], None);
+            match &mutation {
+                Some(TxMutation::DuplicateInput) => inputs.push(inputs[0].clone()),
+                Some(TxMutation::MissingInput(outpoint)) => {
+                    inputs.push(TransactionInput::new_empty(*outpoint))
+                }
+                Some(TxMutation::MismatchedLock(lock)) => inputs[0].lock = lock.clone(),
+                Some(TxMutation::EmptyCallWitness) => {
+                    let outpoint = H256::repeat_byte(0xcc);
+                    crate::pallet::insert_utxo::<Test>(outpoint, &call, None);
+                    inputs.push(TransactionInput::new_empty(outpoint))
+                }
+                Some(TxMutation::Unsigned) | None => (),
+            }
+            let mut utxos = vec![utxo0; inputs.len()];
+            // The contract UTXO is spent without a witness
+            let signed = match mutation {
+                Some(TxMutation::EmptyCallWitness) => {
+                    utxos[1] = call;
+                    1
+                }
+                _ => inputs.len(),
+            };
+            let mut tx = Transaction {
+                inputs,
+                outputs: outputs.clone(),
+                time_lock: Default::default(),
+                expires_at: None,
+            };
+            if !matches!(mutation, Some(TxMutation::Unsigned)) {
+                for index in 0..signed {
+                    tx = tx.sign_unchecked(&utxos, index, &alice);
+                }
+            }
+            tx
+        });
+    }
+
+    #[test]
+    fn prop_encoded_transaction_checks_hold(data in prop::collection::vec(any::<u8>(), 0..512)) {
+        crate::fuzzing::check_encoded_transaction(&data);
+    }
+}
+
+/// A token of an output of the differential tests: the one spent by the inputs, one that doesn't
+/// exist, or the one the transaction creates.
+#[derive(Clone, Debug)]
+enum RefToken {
+    Spent,
+    Unknown([u8; 20]),
+    New,
+}
+
+/// The data of an output of the differential tests.
+#[derive(Clone, Debug)]
+enum RefData {
+    Mlt,
+    Issue(Value),
+    Mint(RefToken, [u8; 32]),
+    Transfer(RefToken, Value),
+    Burn(RefToken, Value),
+    Multi(Vec<(RefToken, Value)>),
+}
+
+fn arb_ref_token() -> impl Strategy<Value = RefToken> {
+    prop_oneof![
+        4 => Just(RefToken::Spent),
+        1 => any::<[u8; 20]>().prop_map(RefToken::Unknown),
+        1 => Just(RefToken::New),
+    ]
+}
+
+/// Amounts of the spent token, 1000 in total, biased towards adding up.
+fn arb_ref_amount() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        2 => Just(1_000 as Value),
+        1 => Just(500 as Value),
+        2 => 1..=1_000 as Value,
+        1 => 1..=Value::MAX,
+    ]
+}
+
+fn arb_ref_output() -> impl Strategy<Value = (Value, RefData)> {
+    let data = prop_oneof![
+        4 => Just(RefData::Mlt),
+        1 => arb_ref_amount().prop_map(RefData::Issue),
+        1 => (arb_ref_token(), any::<[u8; 32]>()).prop_map(|(token, hash)| RefData::Mint(token, hash)),
+        4 => (arb_ref_token(), arb_ref_amount())
+            .prop_map(|(token, amount)| RefData::Transfer(token, amount)),
+        2 => (arb_ref_token(), arb_ref_amount()).prop_map(|(token, amount)| RefData::Burn(token, amount)),
+        1 => prop::collection::vec((arb_ref_token(), arb_ref_amount()), 0..3).prop_map(RefData::Multi),
+    ];
+    let value = prop_oneof![1..=1_000_000 as Value, 1..=Value::MAX];
+    (value, data)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    #[test]
+    // The production checks accept exactly the transactions conserving MLT and tokens according to
+    // the reference validator, and the fee paid is the one it computes
+    fn prop_conservation_matches_reference_validator(
+        spent in prop_oneof![Just(vec![0]), Just(vec![1]), Just(vec![0, 1]), Just(vec![1, 0])],
+        outputs in prop::collection::vec(arb_ref_output(), 1..4),
+        change_fee in prop::option::of(0..=20 as Value),
+        fee_asset in any::<bool>(),
+    ) {
+        use crate::reference_validator::{check_conservation, input_value, IssuanceFees};
+
+        let (production, reference, paid) = execute_with_alice(|alice_pub_key| {
+            let alice = H256::from(alice_pub_key);
+            let (utxo0, input0) = tx_input_gen_no_signature();
+            let spent_token = TokenId::new(&input0);
+            // The UTXOs spent: MLT and 1000 tokens
+            let setup = Transaction {
+                inputs: vec![input0],
+                outputs: vec![
+                    TransactionOutput::new_pubkey(1_000_000, alice),
+                    TransactionOutput::new_p2pk_with_data(
+                        100,
+                        alice,
+                        OutputData::TokenIssuanceV1 {
+                            token_ticker: b"RefT".to_vec(),
+                            amount_to_issue: 1_000,
+                            number_of_decimals: 2,
+                            metadata_uri: b"mintlayer.org".to_vec(),
+                        },
+                    ),
+                    TransactionOutput::new_pubkey(
+                        ALICE_GENESIS_BALANCE - 1_000_100 - TokenIssuanceFee::get(),
+                        alice,
+                    ),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+            assert_ok!(Utxo::spend(Origin::signed(H256::zero()), setup.clone()));
+            if fee_asset {
+                AcceptedFeeAssets::set(&vec![FeeAsset {
+                    token_id: spent_token.clone(),
+                    mlt_value: 1,
+                    token_amount: 10,
+                }]);
+            }
+
+            let inputs: Vec<_> = spent
+                .iter()
+                .map(|index| TransactionInput::new_empty(setup.outpoint(*index)))
+                .collect();
+            let utxos: Vec<_> = spent.iter().map(|index| setup.outputs[*index as usize].clone()).collect();
+            let new_token = TokenId::new(&inputs[0]);
+            let token_id = |token: &RefToken| match token {
+                RefToken::Spent => spent_token.clone(),
+                RefToken::Unknown(id) => TokenId::from_h160((*id).into()),
+                RefToken::New => new_token.clone(),
+            };
+            let mut outputs: Vec<_> = outputs
+                .iter()
+                .enumerate()
+                .map(|(index, (value, data))| {
+                    let data = match data {
+                        RefData::Mlt => None,
+                        RefData::Issue(amount_to_issue) => Some(OutputData::TokenIssuanceV1 {
+                            token_ticker: b"NewT".to_vec(),
+                            amount_to_issue: *amount_to_issue,
+                            number_of_decimals: 0,
+                            metadata_uri: vec![],
+                        }),
+                        RefData::Mint(token, hash) => Some(OutputData::NftMintV1 {
+                            token_id: token_id(token),
+                            data_hash: NftDataHash::Hash32(*hash),
+                            metadata_uri: b"mintlayer.org".to_vec(),
+                            media_type: None,
+                            transferable: true,
+                            royalty: None,
+                        }),
+                        RefData::Transfer(token, amount) => Some(OutputData::TokenTransferV1 {
+                            token_id: token_id(token),
+                            amount: *amount,
+                        }),
+                        RefData::Burn(token, amount_to_burn) => Some(OutputData::TokenBurnV1 {
+                            token_id: token_id(token),
+                            amount_to_burn: *amount_to_burn,
+                        }),
+                        RefData::Multi(tokens) => Some(OutputData::TokenMultiV1(
+                            tokens.iter().map(|(token, amount)| (token_id(token), *amount)).collect(),
+                        )),
+                    };
+                    TransactionOutput {
+                        value: *value,
+                        destination: Destination::Pubkey(Public([index as u8 + 1; 32])),
+                        data,
+                    }
+                })
+                .collect();
+            // The change leaves a small fee, around the issuance fees
+            if let Some(change_fee) = change_fee {
+                let value_in: Value = utxos.iter().map(|utxo| utxo.value).sum();
+                let value_out =
+                    outputs.iter().fold(0, |total: Value, output| total.saturating_add(output.value));
+                if let Some(change) = value_in.checked_sub(value_out).and_then(|left| left.checked_sub(change_fee)) {
+                    if change > 0 {
+                        outputs.push(TransactionOutput::new_pubkey(change, alice));
+                    }
+                }
+            }
+            let mut tx = Transaction {
+                inputs,
+                outputs,
+                time_lock: Default::default(),
+                expires_at: None,
+            };
+            for index in 0..tx.inputs.len() {
+                tx = tx.sign_unchecked(&utxos, index, &alice_pub_key);
+            }
+
+            let input_values: Vec<_> = tx
+                .inputs
+                .iter()
+                .map(|input| input_value::<Test>(&input.outpoint).unwrap())
+                .collect();
+            let fee_assets: Vec<_> =
+                AcceptedFeeAssets::get().into_iter().map(|asset| asset.token_id).collect();
+            let fees = IssuanceFees {
+                token_issuance: TokenIssuanceFee::get(),
+                nft_mint: NftMintFee::get(),
+            };
+            let reference = check_conservation(&tx, &input_values, &fee_assets, fees);
+            let production = crate::pallet::validate_transaction::<Test>(&tx).map(|_| ());
+
+            let reward = RewardTotal::<Test>::get();
+            let paid = production
+                .and_then(|_| Utxo::spend(Origin::signed(H256::zero()), tx).map_err(|_| "not spent"))
+                .map(|_| RewardTotal::<Test>::get() - reward);
+            (production, reference, paid)
+        });
+
+        prop_assert_eq!(production.is_ok(), reference.is_ok(), "{:?} {:?}", production, reference);
+        if let Ok(fee) = reference {
+            prop_assert_eq!(paid, Ok(fee));
+        }
+    }
+}
+
 #[test]
-fn test_reward_overflow() {
+fn test_taproot_script_path() {
     execute_with_alice(|alice_pub_key| {
-        let (utxo0, input0) = tx_input_gen_no_signature();
-
-        // Check the default parameters
-        let utxos = UtxoStore::<Test>::get(input0.outpoint).unwrap();
-        assert_eq!(utxos.value, ALICE_GENESIS_BALANCE);
-        let reward = RewardTotal::<Test>::get();
-        assert_eq!(reward, 0);
+        // Two alternative scripts, each one can be redeemed by revealing a preimage of a hash
+        let scripts: Vec<_> = ["Hello!", "Bye!"]
+            .iter()
+            .map(|password| {
+                let password_hash = sp_core::hashing::sha2_256(password.as_bytes());
+                Builder::new()
+                    .push_opcode(opc::OP_SHA256)
+                    .push_slice(&password_hash)
+                    .push_opcode(opc::OP_EQUAL)
+                    .into_script()
+            })
+            .collect();
+        let leaves: Vec<_> = scripts.iter().map(|s| taproot::leaf_hash(s.as_ref())).collect();
+        let merkle_root = taproot::merkle_root(&leaves).unwrap();
 
-        // Make a new transaction where
-        // Input balance:  4_000_000_000_000_000_000_000
-        // u64::MAX:          18_446_744_073_709_551_615
-        // the difference: 3_981_553_255_926_290_448_385
-        let tx = Transaction {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx1 = Transaction {
             inputs: vec![input0],
-            outputs: vec![TransactionOutput::new_pubkey(
-                3_981_553_255_926_290_448_385,
+            outputs: vec![TransactionOutput::new_taproot(
+                ALICE_GENESIS_BALANCE - 50,
                 H256::from(alice_pub_key),
+                merkle_root,
             )],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
+
+        let spend = |script: &Script, proof: Vec<H256>| {
+            let witness = Builder::new().push_slice("Bye!".as_bytes()).into_script();
+            let witness = taproot::ScriptPathWitness {
+                proof,
+                witness: witness.into_bytes(),
+            };
+            Transaction {
+                inputs: vec![TransactionInput {
+                    outpoint: tx1.outpoint(0),
+                    lock: script.clone().into_bytes(),
+                    witness: witness.encode(),
+                }],
+                outputs: vec![TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 120,
+                    H256::from(alice_pub_key),
+                )],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+        };
+
+        // A script not in the tree
+        let other_script = Builder::new().push_int(1).into_script();
+        let proof = taproot::merkle_proof(&leaves, 1).unwrap();
         assert_err!(
-            Utxo::spend(Origin::signed(H256::zero()), tx),
-            "reward exceed allowed amount"
+            Utxo::spend(Origin::signed(H256::zero()), spend(&other_script, proof)),
+            "taproot proof must be valid"
         );
+        // A script in the tree with a wrong witness
+        let proof = taproot::merkle_proof(&leaves, 0).unwrap();
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), spend(&scripts[0], proof)),
+            "script verification failed"
+        );
+        let proof = taproot::merkle_proof(&leaves, 1).unwrap();
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            spend(&scripts[1], proof)
+        ));
     })
 }
 
 #[test]
-fn test_script() {
+fn test_musig_aggregated_key() {
+    use crate::musig;
+    use sp_core::Pair;
+
     execute_with_alice(|alice_pub_key| {
+        let pairs: Vec<_> =
+            (1u8..=3).map(|i| sp_core::sr25519::Pair::from_seed(&[i; 32])).collect();
+        let keys: Vec<_> = pairs.iter().map(|pair| pair.public()).collect();
+        let key_agg = musig::KeyAgg::new(&keys).unwrap();
+        assert!(musig::KeyAgg::new(&[keys[0], keys[0]]).is_none());
+
         let (utxo0, input0) = tx_input_gen_no_signature();
-        let tx = Transaction {
+        let tx1 = Transaction {
             inputs: vec![input0],
             outputs: vec![TransactionOutput::new_pubkey(
-                ALICE_GENESIS_BALANCE - 90,
-                H256::from(alice_pub_key),
+                ALICE_GENESIS_BALANCE - 50,
+                H256::from(key_agg.public()),
             )],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
 
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
-    })
-}
-
-#[test]
-fn test_time_lock_tx() {
-    execute_with_alice(|alice_pub_key| {
-        let (utxo0, input0) = tx_input_gen_no_signature();
-        let tx = Transaction {
-            inputs: vec![input0],
+        let mut tx2 = Transaction {
+            inputs: vec![TransactionInput::new_empty(tx1.outpoint(0))],
             outputs: vec![TransactionOutput::new_pubkey(
-                ALICE_GENESIS_BALANCE - 90,
+                ALICE_GENESIS_BALANCE - 120,
                 H256::from(alice_pub_key),
             )],
-            time_lock: BlockTime::Blocks(10).as_raw().unwrap(),
-        }
-        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+
+        // Round 1: nonce exchange
+        let (secret_nonces, public_nonces): (Vec<_>, Vec<_>) =
+            pairs.iter().map(|_| musig::nonce_gen()).unzip();
+        let nonce = musig::aggregate_nonces(&public_nonces).unwrap();
+
+        // Round 2: partial signatures
+        let msg = musig::signing_message(&tx2, &tx1.outputs, 0);
+        let partial_sigs: Vec<_> = pairs
+            .iter()
+            .zip(secret_nonces)
+            .map(|(pair, secret)| musig::partial_sign(pair, secret, &key_agg, &nonce, &msg))
+            .collect::<Option<_>>()
+            .unwrap();
+
+        // Missing partial signature
+        let sig = musig::aggregate_signatures(&key_agg, &nonce, &msg, &partial_sigs[1..]);
+        tx2.inputs[0].witness = sig.unwrap().0.to_vec();
         assert_err!(
-            Utxo::spend(Origin::signed(H256::zero()), tx),
-            "Time lock restrictions not satisfied",
+            Utxo::spend(Origin::signed(H256::zero()), tx2.clone()),
+            "signature must be valid"
         );
+
+        let sig = musig::aggregate_signatures(&key_agg, &nonce, &msg, &partial_sigs);
+        tx2.inputs[0].witness = sig.unwrap().0.to_vec();
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx2));
     })
 }
 
 #[test]
-fn test_time_lock_script_fail() {
-    execute_with_alice(|alice_pub_key| {
+fn test_psbt_two_signers() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
         let (utxo0, input0) = tx_input_gen_no_signature();
-        let script = Builder::new().push_int(10).push_opcode(opc::OP_CLTV).into_script();
-        let script_hash: H256 = BlakeTwo256::hash(script.as_ref());
         let tx1 = Transaction {
             inputs: vec![input0],
-            outputs: vec![TransactionOutput::new_script_hash(
-                ALICE_GENESIS_BALANCE - 90,
-                script_hash,
-            )],
+            outputs: vec![
+                TransactionOutput::new_pubkey(100, H256::from(alice_pub_key)),
+                TransactionOutput::new_taproot(
+                    ALICE_GENESIS_BALANCE - 200,
+                    H256::from(karl_pub_key),
+                    H256::zero(),
+                ),
+            ],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign_unchecked(&[utxo0], 0, &alice_pub_key);
-        let outpoint = tx1.outpoint(0);
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1));
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
 
-        // The following should fail because the transaction-level time lock does not conform to
-        // the time lock restrictions imposed by the scripting system.
+        // Alice and Karl each sign their own input on a separate device
         let tx2 = Transaction {
-            inputs: vec![TransactionInput::new_script(outpoint, script, Default::default())],
+            inputs: vec![
+                TransactionInput::new_empty(tx1.outpoint(0)),
+                TransactionInput::new_empty(tx1.outpoint(1)),
+            ],
             outputs: vec![TransactionOutput::new_pubkey(
-                ALICE_GENESIS_BALANCE - 150,
+                ALICE_GENESIS_BALANCE - 300,
                 H256::from(alice_pub_key),
             )],
             time_lock: Default::default(),
+            expires_at: None,
         };
+        let mut psbt = PartiallySignedTransaction::new(tx2.clone()).unwrap();
+        assert_err!(psbt.signature_message(0), "missing utxo");
+        psbt.set_utxo(0, tx1.outputs[0].clone()).unwrap();
+        psbt.set_utxo(1, tx1.outputs[1].clone()).unwrap();
+
+        let mut alice_psbt = psbt.clone();
+        alice_psbt.sign(0, &alice_pub_key).unwrap();
+        let mut karl_psbt = PartiallySignedTransaction::decode(&mut &psbt.encode()[..]).unwrap();
+        karl_psbt.sign(1, &karl_pub_key).unwrap();
+        assert_err!(alice_psbt.clone().extract(), "input not finalized");
+        assert_err!(alice_psbt.clone().finalize(), "missing signature");
+
+        let mut other_tx = tx2;
+        other_tx.time_lock = RawBlockTime::new(1);
+        let other_psbt = PartiallySignedTransaction::new(other_tx).unwrap();
         assert_err!(
-            Utxo::spend(Origin::signed(H256::zero()), tx2),
-            "script verification failed"
+            alice_psbt.clone().combine(other_psbt),
+            "transactions must match"
         );
+
+        let mut psbt = alice_psbt.combine(karl_psbt).unwrap();
+        psbt.finalize().unwrap();
+        let tx2 = psbt.extract().unwrap();
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx2));
     })
 }
 
 #[test]
-fn attack_double_spend_by_tweaking_input() {
+fn test_minimum_fee_rate() {
     execute_with_alice(|alice_pub_key| {
-        // Prepare and send a transaction with a 50-token output
         let (utxo0, input0) = tx_input_gen_no_signature();
-        let drop_script = Builder::new().push_opcode(opc::OP_DROP).into_script();
-        let drop_script_hash = BlakeTwo256::hash(drop_script.as_ref());
-        let tx0 = Transaction {
+        let fee = 1_000;
+        let tx = Transaction {
             inputs: vec![input0],
-            outputs: vec![TransactionOutput::new_script_hash(
-                ALICE_GENESIS_BALANCE - 50,
-                drop_script_hash,
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - fee,
+                H256::from(alice_pub_key),
             )],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign_unchecked(&[utxo0], 0, &alice_pub_key);
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx0.clone()));
+        let info = PriorityInfo {
+            fee,
+            weight: tx.estimated_weight::<Test>(),
+            coin_age: 0,
+        };
+        let valid =
+            crate::pallet::validate_pool_transaction::<Test>(&tx, TransactionSource::External)
+                .unwrap();
+        assert_eq!(valid.priority, info.fee_rate() as u64);
 
-        // Create a transaction that spends the same input 10 times by slightly modifying the
-        // redeem script.
-        let inputs: Vec<_> = (0..10)
-            .map(|i| {
-                let witness = Builder::new().push_int(1).push_int(i as i64).into_script();
-                TransactionInput::new_script(tx0.outpoint(0), drop_script.clone(), witness)
-            })
-            .collect();
+        MinimumFeeRate::set(&(info.fee_rate() + 1));
+        assert_err!(
+            crate::pallet::validate_pool_transaction::<Test>(&tx, TransactionSource::External),
+            "fee rate too low"
+        );
+        // The minimum fee is not a consensus rule
+        assert_ok!(crate::pallet::validate_transaction::<Test>(&tx));
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+    })
+}
+
+#[test]
+fn test_standardness_policy() {
+    execute_with_alice(|alice_pub_key| {
+        let script = Builder::new().push_opcode(opc::OP_EQUAL).into_script();
+        let (utxo0, input0) = tx_input_gen_no_signature();
         let tx1 = Transaction {
-            inputs,
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_pubkey(100, H256::from(alice_pub_key)),
+                TransactionOutput::new_script_hash(200, BlakeTwo256::hash(script.as_ref())),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+
+        // Dust and large witnesses are kept out of the pool, but valid in a block
+        DustThreshold::set(&150);
+        assert_err!(
+            crate::pallet::validate_pool_transaction::<Test>(&tx1, TransactionSource::External),
+            "dust output"
+        );
+        DustThreshold::set(&0);
+        MaxStandardWitnessSize::set(&(tx1.inputs[0].witness.len() as u32 - 1));
+        assert_err!(
+            crate::pallet::validate_pool_transaction::<Test>(&tx1, TransactionSource::External),
+            "witness too large"
+        );
+        MaxStandardWitnessSize::set(&(10 * 1024));
+        assert_ok!(crate::pallet::validate_pool_transaction::<Test>(
+            &tx1,
+            TransactionSource::External
+        ));
+        DustThreshold::set(&150);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
+        DustThreshold::set(&0);
+
+        let spend_script = |witness: Script| Transaction {
+            inputs: vec![TransactionInput::new_script(tx1.outpoint(1), script.clone(), witness)],
+            outputs: vec![TransactionOutput::new_pubkey(150, H256::from(alice_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        let witness = Builder::new().push_slice(b"mintlayer").push_opcode(opc::OP_DUP);
+        assert_err!(
+            crate::pallet::validate_pool_transaction::<Test>(
+                &spend_script(witness.into_script()),
+                TransactionSource::External
+            ),
+            "witness script not push only"
+        );
+        let witness = Builder::new().push_slice(b"mintlayer").push_slice(b"mintlayer");
+        assert_ok!(crate::pallet::validate_pool_transaction::<Test>(
+            &spend_script(witness.into_script()),
+            TransactionSource::External
+        ));
+    })
+}
+
+#[test]
+fn test_transaction_source_policy() {
+    execute_with_alice(|alice_pub_key| {
+        let script = Builder::new().push_opcode(opc::OP_RETURN).into_script();
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_versioned_script_hash(
+                100,
+                ScriptVersion::new(1),
+                BlakeTwo256::hash(script.as_ref()),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        let validate = |source| crate::pallet::validate_pool_transaction::<Test>(&tx, source);
+
+        // The standardness and fee policies only apply to transactions relayed by peers
+        DustThreshold::set(&150);
+        MinimumFeeRate::set(&Value::MAX);
+        assert_err!(validate(TransactionSource::External), "dust output");
+        assert_ok!(validate(TransactionSource::Local));
+        assert_ok!(validate(TransactionSource::InBlock));
+        DustThreshold::set(&0);
+        assert_err!(validate(TransactionSource::External), "fee rate too low");
+        MinimumFeeRate::set(&0);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+
+        // Script features reserved for soft forks stay out of the pool, unless in a block
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_script(tx.outpoint(0), script, Default::default())],
+            outputs: vec![TransactionOutput::new_pubkey(50, H256::from(alice_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        let validate = |source| crate::pallet::validate_pool_transaction::<Test>(&tx, source);
+        assert_err!(validate(TransactionSource::Local), "unknown script version");
+        assert_ok!(validate(TransactionSource::InBlock));
+    })
+}
+
+#[test]
+fn test_child_pays_for_parent() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let parent = Transaction {
+            inputs: vec![input0],
             outputs: vec![TransactionOutput::new_pubkey(
-                ALICE_GENESIS_BALANCE - 500,
+                ALICE_GENESIS_BALANCE - 10,
                 H256::from(alice_pub_key),
             )],
             time_lock: Default::default(),
-        };
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        let child = Transaction {
+            inputs: vec![TransactionInput::new_empty(parent.outpoint(0))],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 10_000,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&parent.outputs, 0, &alice_pub_key);
+
+        // The child waits in the pool for the parent to provide its input
+        let parent_valid =
+            crate::pallet::validate_pool_transaction::<Test>(&parent, TransactionSource::External)
+                .unwrap();
+        let child_valid =
+            crate::pallet::validate_pool_transaction::<Test>(&child, TransactionSource::External)
+                .unwrap();
+        let tag = crate::pallet::outpoint_tag(&parent.outpoint(0));
+        assert_eq!(parent_valid.provides, vec![tag.clone()]);
+        assert_eq!(child_valid.requires, vec![tag]);
+        assert_eq!(child_valid.priority, 0);
+
+        // Together they pay more than the parent alone, without touching the storage
+        let package = crate::pallet::package_priority::<Test>(
+            &H256::zero(),
+            &[parent.clone(), child.clone()],
+        );
+        assert!(package.unwrap() > parent_valid.priority);
+        assert!(UtxoStore::<Test>::contains_key(parent.inputs[0].outpoint));
+        assert!(!UtxoStore::<Test>::contains_key(parent.outpoint(0)));
         assert_err!(
-            Utxo::spend(Origin::signed(H256::zero()), tx1),
-            "each input should be used only once"
+            crate::pallet::package_priority::<Test>(&H256::zero(), &[child.clone()]),
+            "missing inputs"
         );
-    });
+
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), parent));
+        let child_valid =
+            crate::pallet::validate_pool_transaction::<Test>(&child, TransactionSource::External)
+                .unwrap();
+        assert!(child_valid.requires.is_empty());
+        assert!(child_valid.priority > parent_valid.priority);
+    })
 }
 
 #[test]
-fn test_send_to_address() {
-    let (mut test_ext, alice_pub_key, _karl_pub_key) = alice_test_ext_and_keys();
+fn test_watch_destination() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
     test_ext.execute_with(|| {
-        // `addr` is bech32-encoded, SCALE-encoded `Destination::Pubkey(alice_pub_key)`
-        let addr = "ml1qrft7juyfhl06emj4zzrue5ljs6q39n2jalr4c40rhtcur647n0kwueyfsn";
+        // Events are only deposited from the first block on
+        System::set_block_number(1);
+        let alice = H256::from(alice_pub_key);
+        let karl = Destination::Pubkey(karl_pub_key);
 
-        assert_err!(
-            Utxo::send_to_address(
-                Origin::signed(H256::from(alice_pub_key)),
-                0,
-                addr.as_bytes().to_vec(),
-            ),
-            "Value transferred must be larger than zero",
+        assert_ok!(Utxo::watch_destination(Origin::signed(alice), karl.clone()));
+        assert_eq!(RewardTotal::<Test>::get(), WatchFee::get());
+        assert_eq!(Utxo::watcher_count(BlakeTwo256::hash_of(&karl)), 1);
+        assert_noop!(
+            Utxo::watch_destination(Origin::signed(alice), karl.clone()),
+            "Destination already watched"
         );
-
-        assert_err!(
-            Utxo::send_to_address(
-                Origin::signed(H256::from(alice_pub_key)),
-                ALICE_GENESIS_BALANCE * 10,
-                addr.as_bytes().to_vec(),
-            ),
-            "Caller doesn't have enough UTXOs",
+        MaxWatchers::set(&1);
+        assert_noop!(
+            Utxo::watch_destination(Origin::signed(H256::zero()), karl.clone()),
+            "Destination has too many watchers"
+        );
+        MaxWatchers::set(&3);
+        assert_noop!(
+            Utxo::watch_destination(Origin::signed(H256::zero()), karl.clone()),
+            "Caller doesn't have enough UTXOs"
         );
 
-        // send 10 utxo to alice
-        assert_ok!(Utxo::send_to_address(
-            Origin::signed(H256::from(alice_pub_key)),
-            10,
-            addr.as_bytes().to_vec(),
-        ));
+        let (_, hashes, utxos) = crate::pick_utxo::<Test>(&alice, 1);
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(hashes[0])],
+            outputs: vec![TransactionOutput::new_pubkey(100, H256::from(karl_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&utxos, 0, &alice_pub_key);
+        let received = tx.outpoint(0);
+        assert_ok!(Utxo::spend(Origin::signed(alice), tx.clone()));
+        assert!(System::events()
+            .iter()
+            .any(|record| record.event
+                == Event::Utxo(crate::Event::UtxoReceived(alice, received, 100))));
 
-        // try to transfer to scripthash
-        let addr = "ml1qvvknne0acfzfd2ewksccgrgl4qlhcwewq4gjm75mtcpg26al66d5l5sz9k";
-        assert_ok!(Utxo::send_to_address(
-            Origin::signed(H256::from(alice_pub_key)),
-            20,
-            addr.as_bytes().to_vec(),
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(received)],
+            outputs: vec![TransactionOutput::new_pubkey(90, alice)],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&tx.outputs, 0, &karl_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(alice), tx));
+        assert!(System::events()
+            .iter()
+            .any(|record| record.event == Event::Utxo(crate::Event::UtxoSpent(alice, received))));
+
+        assert_ok!(Utxo::unwatch_destination(
+            Origin::signed(alice),
+            karl.clone()
         ));
+        assert!(!WatcherCount::<Test>::contains_key(BlakeTwo256::hash_of(
+            &karl
+        )));
+        assert_noop!(
+            Utxo::unwatch_destination(Origin::signed(alice), karl),
+            "Destination not watched"
+        );
+    })
+}
 
-        // invalid length
-        let addr = "ml1qrft7juyfhl06emj4zzrue5ljs6q39n2jalr4c40rhtcur647n0kwue1yfsn";
+#[test]
+fn test_vesting() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let karl = H256::from(karl_pub_key);
+        // 1000 released to KARL over 100 blocks from block 10, nothing before block 30
+        let (utxo0, input0) = tx_input_gen_no_signature();
+
+        // a vesting output cannot hold more than its grant
+        let tx = Transaction {
+            inputs: vec![input0.clone()],
+            outputs: vec![TransactionOutput::new_vesting(1001, karl, 1000, 10, 20, 100)],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
         assert_err!(
-            Utxo::send_to_address(
-                Origin::signed(H256::from(alice_pub_key)),
-                40,
-                addr.as_bytes().to_vec(),
-            ),
-            "Failed to decode address: invalid length",
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            "vested value exceeds the grant"
         );
 
-        // invalid character
-        let addr = "ml1qyzqqpqpäääypw";
+        let vesting = TransactionOutput::new_vesting(1000, karl, 1000, 10, 20, 100);
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                vesting.clone(),
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 1000,
+                    H256::from(alice_pub_key),
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        let outpoint = tx.outpoint(0);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+
+        let spend_vested = |vested: Value| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(outpoint)],
+                outputs: vec![
+                    TransactionOutput::new_pubkey(vested, karl),
+                    TransactionOutput::new_vesting(1000 - vested, karl, 1000, 10, 20, 100),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign(&[vesting.clone()], 0, &karl_pub_key)
+            .expect("karl's pub key not found")
+        };
+
+        System::set_block_number(29);
         assert_err!(
-            Utxo::send_to_address(
-                Origin::signed(H256::from(alice_pub_key)),
-                40,
-                addr.as_bytes().to_vec(),
-            ),
-            "Failed to decode address: invalid character",
+            Utxo::spend(Origin::signed(H256::zero()), spend_vested(1)),
+            "unvested value must be locked again"
         );
 
-        // mixed case
-        let addr = "ml1qrft7juyfhl06emj4zzrue5ljs6q39n2JALR4c40rhtcur647n0kWUYEFSN";
+        // half of the value is released at block 60
+        System::set_block_number(60);
         assert_err!(
-            Utxo::send_to_address(
-                Origin::signed(H256::from(alice_pub_key)),
-                40,
-                addr.as_bytes().to_vec(),
-            ),
-            "Failed to decode address: mixed case",
+            Utxo::spend(Origin::signed(H256::zero()), spend_vested(501)),
+            "unvested value must be locked again"
+        );
+        let tx = spend_vested(500);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert_eq!(
+            UtxoStore::<Test>::get(tx.outpoint(1)).map(|utxo| utxo.value),
+            Some(500)
         );
 
-        // invalid checksum
-        let addr = "ml1qrft7juyfhl06emj4zzrue5ljs6q39n2jalr4c40rhtcur647n0kwueyf66";
+        // spending the relocked UTXO again in the same block releases nothing more
+        let spend_relocked = |spent: &Transaction<H256>, vested: Value| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(spent.outpoint(1))],
+                outputs: vec![
+                    TransactionOutput::new_pubkey(vested, karl),
+                    TransactionOutput::new_vesting(
+                        spent.outputs[1].value - vested,
+                        karl,
+                        1000,
+                        10,
+                        20,
+                        100,
+                    ),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign(&[spent.outputs[1].clone()], 0, &karl_pub_key)
+            .expect("karl's pub key not found")
+        };
         assert_err!(
-            Utxo::send_to_address(
-                Origin::signed(H256::from(alice_pub_key)),
-                40,
-                addr.as_bytes().to_vec(),
-            ),
-            "Failed to decode address: invalid checksum",
+            Utxo::spend(Origin::signed(H256::zero()), spend_relocked(&tx, 1)),
+            "unvested value must be locked again"
         );
+        let tx = spend_relocked(&tx, 0);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
 
-        // invalid HRP
-        let addr = "bc1qrft7juyfhl06emj4zzrue5ljs6q39n2jalr4c40rhtcur647n0kwueyfsn";
+        // ten blocks later, 100 more of the grant is released
+        System::set_block_number(70);
         assert_err!(
-            Utxo::send_to_address(
-                Origin::signed(H256::from(alice_pub_key)),
-                40,
-                addr.as_bytes().to_vec(),
-            ),
-            "Failed to decode address: invalid HRP",
+            Utxo::spend(Origin::signed(H256::zero()), spend_relocked(&tx, 101)),
+            "unvested value must be locked again"
         );
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            spend_relocked(&tx, 100)
+        ));
     })
 }
 
-proptest! {
-    #[test]
-    fn prop_gen_block_time_real_works(bt in gen_block_time_real()) {
-        // This generator should not sample block-based time.
-        prop_assert!(!bt.is_blocks());
-    }
-
-    #[test]
-    fn prop_time_lock_realtime_with_script(
-        script_lock_time in gen_block_time_real(),
-        tx_lock_time in gen_block_time_real(),
-        current_time in gen_block_time_real(),
-    ) {
-        let result = execute_with_alice(|alice| {
-            // Convert seconds to milliseconds
-            Timestamp::set_timestamp(current_time.as_u64() * 1000);
+#[test]
+fn test_stream() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let karl = H256::from(karl_pub_key);
+        // 1000 streamed to KARL at 10 per block from block 10
+        System::set_block_number(10);
+        let stream = TransactionOutput::new_stream(1000, karl, 10);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                stream.clone(),
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 1000,
+                    H256::from(alice_pub_key),
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        let outpoint = tx.outpoint(0);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
 
-            let (utxo0, input0) = tx_input_gen_no_signature();
-            let script = Builder::new()
-                .push_int(script_lock_time.as_u64() as i64)
-                .push_opcode(opc::OP_CLTV)
-                .into_script();
-            let script_hash: H256 = BlakeTwo256::hash(script.as_ref());
-            let tx1 = Transaction {
-                inputs: vec![input0],
-                outputs: vec![TransactionOutput::new_script_hash(ALICE_GENESIS_BALANCE - 90, script_hash)],
+        let withdraw = |outpoint: H256, utxo: &TransactionOutput<H256>, streamed: Value| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(outpoint)],
+                outputs: vec![
+                    TransactionOutput::new_pubkey(streamed, karl),
+                    TransactionOutput::new_stream(utxo.value - streamed, karl, 10),
+                ],
                 time_lock: Default::default(),
+                expires_at: None,
             }
-            .sign_unchecked(&[utxo0], 0, &alice);
-            let outpoint = tx1.outpoint(0);
-            assert!(Utxo::spend(Origin::signed(H256::zero()), tx1).is_ok());
+            .sign_unchecked(&[utxo.clone()], 0, &karl_pub_key)
+        };
 
-            let tx2 = Transaction {
-                inputs: vec![TransactionInput::new_script(outpoint, script, Default::default())],
-                outputs: vec![TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - u32::MAX as Value, H256::from(alice))],
-                time_lock: tx_lock_time,
-            };
-            Utxo::spend(Origin::signed(H256::zero()), tx2)
-        });
-
-        // The transaction should be accepted if and only if:
-        // current time >= transaction lock time >= script lock time
-        let model = current_time.as_u64() >= tx_lock_time.as_u64()
-            && tx_lock_time.as_u64() >= script_lock_time.as_u64();
-        prop_assert_eq!(result.is_ok(), model);
-    }
+        // 300 are streamed at block 40
+        System::set_block_number(40);
+        assert_err!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                withdraw(outpoint, &stream, 301)
+            ),
+            "unstreamed value must go on streaming"
+        );
+        let tx = withdraw(outpoint, &stream, 300);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
 
-    #[test]
-    fn prop_time_lock_realtime_monotonic(
-        tx_lock_time in gen_block_time_real(),
-        time0 in gen_block_time_real(),
-        time1 in gen_block_time_real(),
-    ) {
-        // Make sure time0 and time1 are in order
-        let (time0, time1) = (
-            std::cmp::min_by_key(time0, time1, RawBlockTime::as_u64),
-            std::cmp::max_by_key(time0, time1, RawBlockTime::as_u64),
+        // The continuation streams from the block it was created in
+        let continuation = tx.outputs[1].clone();
+        assert_eq!(continuation.value, 700);
+        System::set_block_number(45);
+        assert_err!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                withdraw(tx.outpoint(1), &continuation, 51)
+            ),
+            "unstreamed value must go on streaming"
         );
-        let (res0, res1) = execute_with_alice(|alice| {
-            let (utxo0, input0) = tx_input_gen_no_signature();
-            let tx = Transaction {
-                inputs: vec![input0],
-                outputs: vec![TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 50, H256::from(alice))],
-                time_lock: tx_lock_time,
-            }
-            .sign_unchecked(&[utxo0], 0, &alice);
 
-            Timestamp::set_timestamp(time0.as_u64() * 1000);
-            let res0 = crate::pallet::validate_transaction::<Test>(&tx);
+        // Everything is streamed by block 110
+        System::set_block_number(110);
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(tx.outpoint(1))],
+            outputs: vec![TransactionOutput::new_pubkey(700, karl)],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[continuation], 0, &karl_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+    })
+}
 
-            Timestamp::set_timestamp(time1.as_u64() * 1000);
-            let res1 = crate::pallet::validate_transaction::<Test>(&tx);
+#[test]
+fn test_inheritance() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let (alice, karl) = (H256::from(alice_pub_key), H256::from(karl_pub_key));
+        // Karl inherits from Alice once her UTXOs are left unspent for 100 blocks
+        System::set_block_number(10);
+        let inheritance = TransactionOutput::new_inheritance(500, alice, karl, 100);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                inheritance.clone(),
+                inheritance.clone(),
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 1000, alice),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
 
-            (res0, res1)
-        });
+        let spend = |input: TransactionInput, key: &Public| {
+            Transaction {
+                inputs: vec![input],
+                outputs: vec![TransactionOutput::new_pubkey(500, H256::from(*key))],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[inheritance.clone()], 0, key)
+        };
 
-        // The flow of time cannot turn a valid transaction int an invalid one.
-        // This is an implication: If a transaction validates at time0, it validates at time1.
-        prop_assert!(!res0.is_ok() || res1.is_ok());
+        // Alice can spend at any time, Karl only from block 110
+        System::set_block_number(50);
+        assert_err!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                spend(
+                    TransactionInput::new_heir(tx.outpoint(1), karl),
+                    &karl_pub_key
+                )
+            ),
+            "heir can't spend the utxo yet"
+        );
+        assert_err!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                spend(TransactionInput::new_empty(tx.outpoint(1)), &karl_pub_key)
+            ),
+            "signature must be valid"
+        );
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            spend(TransactionInput::new_empty(tx.outpoint(0)), &alice_pub_key)
+        ));
 
-        // Check the error message given if the transaction validation fails.
-        if let Err(e) = res0 {
-            prop_assert_eq!(e, "Time lock restrictions not satisfied");
+        System::set_block_number(110);
+        assert_err!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                spend(
+                    TransactionInput::new_heir(tx.outpoint(1), alice),
+                    &alice_pub_key
+                )
+            ),
+            "inheritance lock must be the heir pubkey"
+        );
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            spend(
+                TransactionInput::new_heir(tx.outpoint(1), karl),
+                &karl_pub_key
+            )
+        ));
+    })
+}
+
+#[test]
+fn test_spent_output_journal() {
+    execute_with_alice(|alice_pub_key| {
+        System::set_block_number(1);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let outpoint = input0.outpoint;
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 10,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
         }
-    }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert_eq!(
+            Utxo::spent_output(outpoint),
+            Some((BlakeTwo256::hash_of(&tx), 1))
+        );
+        assert_eq!(Utxo::spent_output(tx.outpoint(0)), None);
 
-    #[test]
-    fn prop_time_lock_realtime_neighbourhood(before in gen_block_time_real()) {
-        let now = RawBlockTime::new(before.as_u64() + 1);
-        let after = RawBlockTime::new(now.as_u64() + 1);
+        // Kept for `StxoRetention` blocks
+        for block in 1..StxoRetention::get() {
+            crate::pallet::prune_spent_outputs::<Test>(block);
+            assert!(Utxo::spent_output(outpoint).is_some());
+        }
+        crate::pallet::prune_spent_outputs::<Test>(1 + StxoRetention::get());
+        assert_eq!(Utxo::spent_output(outpoint), None);
+    })
+}
 
-        let (res_before, res_now, res_after) = execute_with_alice(|alice| {
-            let (utxo0, input0) = tx_input_gen_no_signature();
-            let tx = Transaction {
-                inputs: vec![input0],
-                outputs: vec![TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 50, H256::from(alice))],
-                time_lock: now,
+#[test]
+fn test_missing_input_proofs() {
+    execute_with_alice(|alice_pub_key| {
+        System::set_block_number(1);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let outpoint = input0.outpoint;
+        let spend_to = |value: Value| {
+            Transaction {
+                inputs: vec![input0.clone()],
+                outputs: vec![TransactionOutput::new_pubkey(value, H256::from(alice_pub_key))],
+                time_lock: Default::default(),
+                expires_at: None,
             }
-            .sign_unchecked(&[utxo0], 0, &alice);
+            .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key)
+        };
+        let tx1 = spend_to(ALICE_GENESIS_BALANCE - 10);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
 
-            Timestamp::set_timestamp(before.as_u64() * 1000);
-            let res_before = crate::pallet::validate_transaction::<Test>(&tx);
+        // Spending the genesis UTXO again is a double spend of tx1
+        let tx2 = spend_to(ALICE_GENESIS_BALANCE - 20);
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx2.clone()),
+            "missing inputs"
+        );
+        let (tx1_hash, tx2_hash) = (BlakeTwo256::hash_of(&tx1), BlakeTwo256::hash_of(&tx2));
+        assert!(System::events().iter().any(|record| record.event
+            == Event::Utxo(crate::Event::DoubleSpendDetected(
+                outpoint, tx2_hash, tx1_hash
+            ))));
 
-            Timestamp::set_timestamp(now.as_u64() * 1000);
-            let res_now = crate::pallet::validate_transaction::<Test>(&tx);
+        // An output that never existed
+        let tx3 = Transaction {
+            inputs: vec![TransactionInput::new_empty(H256::zero())],
+            outputs: vec![TransactionOutput::new_pubkey(50, H256::from(alice_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx3.clone()),
+            "missing inputs"
+        );
+        let tx3_hash = BlakeTwo256::hash_of(&tx3);
+        assert!(System::events().iter().any(|record| record.event
+            == Event::Utxo(crate::Event::MissingInput(H256::zero(), tx3_hash))));
 
-            Timestamp::set_timestamp(after.as_u64() * 1000);
-            let res_after = crate::pallet::validate_transaction::<Test>(&tx);
+        assert_eq!(
+            Utxo::missing_inputs(1),
+            vec![
+                crate::MissingInputProof {
+                    tx_hash: tx2_hash,
+                    outpoint,
+                    spent_by: Some((tx1_hash, 1)),
+                },
+                crate::MissingInputProof {
+                    tx_hash: tx3_hash,
+                    outpoint: H256::zero(),
+                    spent_by: None,
+                },
+            ]
+        );
 
-            (res_before, res_now, res_after)
-        });
+        // Kept for `StxoRetention` blocks, like the spends
+        crate::pallet::prune_spent_outputs::<Test>(StxoRetention::get());
+        assert_eq!(Utxo::missing_inputs(1).len(), 2);
+        crate::pallet::prune_spent_outputs::<Test>(1 + StxoRetention::get());
+        assert_eq!(Utxo::missing_inputs(1), vec![]);
+    })
+}
 
-        prop_assert_eq!(res_before, Err("Time lock restrictions not satisfied"));
-        prop_assert!(res_now.is_ok());
-        prop_assert!(res_after.is_ok());
-    }
+#[test]
+fn test_balance_snapshots() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let alice = H256::from(alice_pub_key);
+        let karl = H256::from(karl_pub_key);
+        System::set_block_number(1);
+        assert_eq!(Utxo::voting_weight(alice, 1), None);
+        assert_noop!(
+            Utxo::take_balance_snapshot(Origin::signed(alice)),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(Utxo::take_balance_snapshot(Origin::root()));
+        assert_noop!(
+            Utxo::take_balance_snapshot(Origin::root()),
+            "Balance snapshot already being taken"
+        );
+        crate::pallet::continue_balance_snapshot::<Test>();
+        assert_eq!(Utxo::balance_snapshot_blocks(), vec![1]);
+        assert_eq!(Utxo::pending_balance_snapshot(), None);
+        assert_noop!(
+            Utxo::take_balance_snapshot(Origin::root()),
+            "Balance snapshot already taken in this block"
+        );
 
-    #[test]
-    fn prop_time_lock_realtime_overflow(
-        time in ((u64::MAX / 1000 + 1)..=u64::MAX).prop_map(RawBlockTime::new)
-    ) {
-        execute_with_alice(|alice| {
-            let (utxo0, input0) = tx_input_gen_no_signature();
-            let tx = Transaction {
-                inputs: vec![input0],
-                outputs: vec![TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 50, H256::from(alice))],
-                time_lock: time,
-            }
-            .sign_unchecked(&[utxo0], 0, &alice);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_pubkey(50, karl),
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 50, alice),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
 
-            // Check validate_transaction does not crash due to time lock being close to u64::MAX
-            let _ = crate::pallet::validate_transaction::<Test>(&tx);
-        });
-    }
-}
+        // the periodic snapshot scans `SnapshotBatchSize` UTXOs per block
+        System::set_block_number(SnapshotPeriod::get());
+        <Utxo as frame_support::traits::Hooks<u64>>::on_initialize(SnapshotPeriod::get());
+        assert_eq!(
+            Utxo::pending_balance_snapshot().map(|(block, _)| block),
+            Some(10)
+        );
+        assert_eq!(Utxo::balance_snapshot_blocks(), vec![1]);
 
-// Testing token creation:
-// use crate::tokens::{NftDataHash, TokenId};
-use crate::tokens::TokenId;
-use rand::Rng;
+        // UTXOs created after the snapshot block don't count
+        System::set_block_number(11);
+        crate::pallet::insert_utxo::<Test>(
+            H256::repeat_byte(0xff),
+            &TransactionOutput::new_pubkey(7, karl),
+            None,
+        );
+        <Utxo as frame_support::traits::Hooks<u64>>::on_initialize(11);
+        assert_eq!(Utxo::pending_balance_snapshot(), None);
+        assert_eq!(Utxo::balance_snapshot_blocks(), vec![1, 10]);
+        assert!(System::events()
+            .iter()
+            .any(|r| r.event == Event::Utxo(crate::Event::BalanceSnapshotTaken(10))));
 
-fn build_random_vec(len: usize) -> Vec<u8> {
-    let mut rng = rand::thread_rng();
-    let mut vec = Vec::with_capacity(len);
-    for _ in 0..len {
-        vec.push(rng.gen::<u8>());
-    }
-    vec
+        assert_eq!(
+            Utxo::voting_weight(alice, 5),
+            Some((1, ALICE_GENESIS_BALANCE))
+        );
+        assert_eq!(Utxo::voting_weight(karl, 9), Some((1, 0)));
+        assert_eq!(Utxo::voting_weight(karl, 10), Some((10, 50)));
+        assert_eq!(
+            Utxo::voting_weight(alice, 12),
+            Some((10, ALICE_GENESIS_BALANCE - 50))
+        );
+
+        // only the latest `SnapshotHistoryDepth` snapshots are kept
+        System::set_block_number(20);
+        <Utxo as frame_support::traits::Hooks<u64>>::on_initialize(20);
+        while Utxo::pending_balance_snapshot().is_some() {
+            crate::pallet::continue_balance_snapshot::<Test>();
+        }
+        assert_eq!(Utxo::balance_snapshot_blocks(), vec![10, 20]);
+        assert_eq!(Utxo::voting_weight(alice, 5), None);
+        assert_eq!(Utxo::voting_weight(karl, 25), Some((20, 57)));
+        crate::pallet::continue_balance_snapshot::<Test>();
+        assert_eq!(
+            crate::BalanceSnapshots::<Test>::iter_prefix(1u64).count(),
+            0
+        );
+        assert!(crate::StaleBalanceSnapshots::<Test>::get().is_empty());
+    })
 }
 
 #[test]
-// Simple creation of tokens
-fn test_token_issuance() {
+fn test_ledger_invariants() {
     execute_with_alice(|alice_pub_key| {
+        assert_ok!(crate::pallet::try_state::<Test>());
+
+        // the issuance fee goes to the reward total
         let (utxo0, input0) = tx_input_gen_no_signature();
-        let output_new = TransactionOutput {
-            value: ALICE_GENESIS_BALANCE,
-            destination: Destination::Pubkey(alice_pub_key),
-            data: Some(OutputData::TokenIssuanceV1 {
-                //token_id: TokenId::new_asset(first_input_hash),
-                token_ticker: "BensT".as_bytes().to_vec(),
-                amount_to_issue: 1_000_000_000,
-                number_of_decimals: 2,
-                metadata_uri: "mintlayer.org".as_bytes().to_vec(),
-            }),
-        };
         let tx = Transaction {
             inputs: vec![input0],
-            outputs: vec![output_new],
+            outputs: vec![TransactionOutput::new_p2pk_with_data(
+                ALICE_GENESIS_BALANCE - TokenIssuanceFee::get(),
+                H256::from(alice_pub_key),
+                OutputData::TokenIssuanceV1 {
+                    token_ticker: "BensT".as_bytes().to_vec(),
+                    amount_to_issue: 1_000,
+                    number_of_decimals: 2,
+                    metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                },
+            )],
             time_lock: Default::default(),
+            expires_at: None,
         }
         .sign_unchecked(&[utxo0], 0, &alice_pub_key);
-        let new_utxo_hash = tx.outpoint(0);
-        let (_, init_utxo) = genesis_utxo();
-        // submit tx - in the test it makes a new UTXO. Checks before that this UTXO has not created yet.
-        // After calling `Utxo::spend`, we should check that Storages successfully changed.
-        // If it successfully wrote a new UTXO in the Storage, tx goes through all verifications correctly.
-        assert!(UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
-        assert!(!UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
-        // Checking a new UTXO
-        assert!(UtxoStore::<Test>::contains_key(new_utxo_hash));
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert_ok!(crate::pallet::try_state::<Test>());
 
-        match UtxoStore::<Test>::get(new_utxo_hash).expect("The new output not found").data {
-            Some(OutputData::TokenIssuanceV1 {
-                //token_id,
-                token_ticker,
-                amount_to_issue,
-                number_of_decimals,
-                metadata_uri,
-            }) => {
-                //assert_eq!(TokenId::new_asset(first_input_hash), token_id);
-                assert_eq!(1_000_000_000, amount_to_issue);
-                assert_eq!("BensT".as_bytes().to_vec(), token_ticker);
-                assert_eq!(2, number_of_decimals);
-                assert_eq!("mintlayer.org".as_bytes().to_vec(), metadata_uri);
-            }
-            _ => panic!("Transaction data is corrupted"),
-        }
-    });
-}
+        // the block reward is minted
+        crate::BlockAuthor::<Test>::put(H256::from(alice_pub_key));
+        crate::rewards::reward_block_author::<Test>(1);
+        assert_eq!(Utxo::mlt_issuance(), ALICE_GENESIS_BALANCE + 100);
+        assert_ok!(crate::pallet::try_state::<Test>());
 
-// todo: This part isn't fully tested, left for the next PR
-// #[test]
-// // Simple creation of NFT
-// fn test_nft_mint() {
-//     execute_with_alice(|alice_pub_key| {
-//         let (utxo0, input0) = tx_input_gen_no_signature();
-//         let first_input_hash = BlakeTwo256::hash(&input0.outpoint.as_ref());
-//         let data_hash = NftDataHash::Raw(vec![1, 2, 3, 4, 5]);
-//         let output = TransactionOutput {
-//             value: 0,
-//             destination: Destination::Pubkey(alice_pub_key),
-//             data: Some(OutputData::NftMintV1 {
-//                 token_id: TokenId::new_asset(first_input_hash),
-//                 data_hash: data_hash.clone(),
-//                 metadata_uri: "mintlayer.org".as_bytes().to_vec(),
-//             }),
-//         };
-//         let tx = Transaction {
-//             inputs: vec![input0],
-//             outputs: vec![output],
-//             time_lock: Default::default(),
-//         }
-//         .sign_unchecked(&[utxo0], 0, &alice_pub_key);
-//         let new_utxo_hash = tx.outpoint(0);
-//         let (_, init_utxo) = genesis_utxo();
-//         assert!(UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
-//         assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
-//         assert!(!UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
-//         assert!(UtxoStore::<Test>::contains_key(new_utxo_hash));
-//         assert_eq!(
-//             data_hash,
-//             UtxoStore::<Test>::get(new_utxo_hash)
-//                 .unwrap()
-//                 .data
-//                 .map(|x| match x {
-//                     OutputData::NftMintV1 { data_hash, .. } => data_hash,
-//                     _ => NftDataHash::Raw(Vec::new()),
-//                 })
-//                 .unwrap_or(NftDataHash::Raw(Vec::new()))
-//         );
-//     })
-// }
-//
-// #[test]
-// // NFT might be only unique, we can't create a few nft for one item
-// fn test_nft_unique() {
-//     execute_with_alice(|alice_pub_key| {
-//         let (utxo0, input0) = tx_input_gen_no_signature();
-//         let first_input_hash = BlakeTwo256::hash(&input0.outpoint.as_ref());
-//
-//         let mut nft_data = OutputData::NftMintV1 {
-//             token_id: TokenId::new_asset(first_input_hash),
-//             data_hash: NftDataHash::Hash32([255; 32]),
-//             metadata_uri: "mintlayer.org".as_bytes().to_vec(),
-//         };
-//         let tx = Transaction {
-//             inputs: vec![input0.clone()],
-//             outputs: vec![
-//                 TransactionOutput {
-//                     value: 0,
-//                     destination: Destination::Pubkey(alice_pub_key),
-//                     data: Some(nft_data.clone()),
-//                 },
-//                 TransactionOutput::new_pubkey(50, H256::from(alice_pub_key)),
-//             ],
-//             time_lock: Default::default(),
-//         }
-//         .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
-//         let new_utxo_hash = tx.outpoint(1);
-//         let (_, init_utxo) = genesis_utxo();
-//         // Submit
-//         assert!(UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
-//         assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
-//         assert!(!UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
-//         // Checking a new UTXO
-//         assert!(UtxoStore::<Test>::contains_key(new_utxo_hash));
-//         let new_utxo = tx.outputs[1].clone();
-//
-//         if let OutputData::NftMintV1 {
-//             ref mut token_id, ..
-//         } = nft_data
-//         {
-//             *token_id = TokenId::new_asset(H256::random());
-//         }
-//         let tx = Transaction {
-//             inputs: vec![TransactionInput::new_empty(new_utxo_hash.clone())],
-//             outputs: vec![TransactionOutput {
-//                 value: 0,
-//                 destination: Destination::Pubkey(alice_pub_key),
-//                 data: Some(nft_data.clone()),
-//             }],
-//             time_lock: Default::default(),
-//         }
-//         .sign_unchecked(&[new_utxo], 0, &alice_pub_key);
-//         // Submit
-//         assert!(UtxoStore::<Test>::contains_key(H256::from(new_utxo_hash)));
-//         frame_support::assert_err_ignore_postinfo!(
-//             Utxo::spend(Origin::signed(H256::zero()), tx),
-//             "digital data has already been minted"
-//         );
-//     });
-// }
+        // a token can't vanish
+        let utxo = UtxoStore::<Test>::get(tx.outpoint(0)).unwrap();
+        UtxoStore::<Test>::insert(
+            tx.outpoint(0),
+            TransactionOutput::new_pubkey(utxo.value, H256::from(alice_pub_key)),
+        );
+        assert_err!(
+            crate::pallet::try_state::<Test>(),
+            "issued token is neither held nor burned"
+        );
 
-// This macro using for the fast creation and sending a tx
-macro_rules! test_tx {
-    ($data: ident, $checking: tt, $err: expr) => {
-        execute_with_alice(|alice_pub_key| {
-            let (utxo0, input0) = tx_input_gen_no_signature();
-            let output_new = TransactionOutput {
-                value: ALICE_GENESIS_BALANCE - 1,
-                destination: Destination::Pubkey(alice_pub_key),
-                data: Some($data.clone()),
-            };
-            let tx = Transaction {
-                inputs: vec![input0],
-                outputs: vec![output_new],
-                time_lock: Default::default(),
-            }
-            .sign_unchecked(&[utxo0], 0, &alice_pub_key);
-            let new_utxo_hash = tx.outpoint(0);
-            let (_, init_utxo) = genesis_utxo();
-            // Send
-            assert!(UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
-            // We can check what error we are expecting
-            if stringify!($checking) == "Err" {
-                frame_support::assert_err_ignore_postinfo!(
-                    Utxo::spend(Origin::signed(H256::zero()), tx),
-                    $err
-                );
-                assert!(UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
-                assert!(!UtxoStore::<Test>::contains_key(new_utxo_hash));
-            } else if stringify!($checking) == "Ok" {
-                // We can check is that success
-                assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
-                assert!(!UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
-                assert!(UtxoStore::<Test>::contains_key(new_utxo_hash));
-            }
-        });
-    };
+        // neither can MLT
+        UtxoStore::<Test>::remove(tx.outpoint(0));
+        assert_err!(
+            crate::pallet::try_state::<Test>(),
+            "MLT in storage doesn't add up to the issuance"
+        );
+    })
 }
 
 #[test]
-fn test_tokens_issuance_empty_ticker() {
-    // Ticker empty
-    let data = OutputData::TokenIssuanceV1 {
-        token_ticker: vec![],
-        amount_to_issue: 1_000_000_000,
-        number_of_decimals: 2,
-        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
-    };
-    test_tx!(data, Err, "token ticker can't be empty");
-}
+fn test_permissioned_issuance() {
+    execute_with_alice(|alice_pub_key| {
+        let alice = H256::from(alice_pub_key);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_p2pk_with_data(
+                ALICE_GENESIS_BALANCE - TokenIssuanceFee::get(),
+                alice,
+                OutputData::TokenIssuanceV1 {
+                    token_ticker: "BensT".as_bytes().to_vec(),
+                    amount_to_issue: 1_000,
+                    number_of_decimals: 2,
+                    metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                },
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
 
-#[test]
-fn test_tokens_issuance_too_big_ticker() {
-    // Ticker too long
-    let data = OutputData::TokenIssuanceV1 {
-        token_ticker: Vec::from([b"A"[0]; 10_000]),
-        amount_to_issue: 1_000_000_000,
-        number_of_decimals: 2,
-        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
-    };
-    test_tx!(data, Err, "token ticker is too long");
-}
+        assert_noop!(
+            Utxo::set_permissioned_issuance(Origin::signed(alice), true),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(Utxo::set_permissioned_issuance(Origin::root(), true));
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx.clone()),
+            "not allowed to issue tokens"
+        );
 
-#[test]
-fn test_tokens_issuance_amount_zero() {
-    // Amount to issue is zero
-    let data = OutputData::TokenIssuanceV1 {
-        token_ticker: b"BensT".to_vec(),
-        amount_to_issue: 0,
-        number_of_decimals: 2,
-        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
-    };
-    test_tx!(data, Err, "output value must be nonzero");
+        assert_ok!(Utxo::set_token_issuer(Origin::root(), alice, true));
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert!(System::events()
+            .iter()
+            .any(|r| r.event == Event::Utxo(crate::Event::TokenIssuerSet(alice, true))));
+    })
 }
 
 #[test]
-fn test_tokens_issuance_too_big_decimals() {
-    // Number of decimals more than 18 numbers
-    let data = OutputData::TokenIssuanceV1 {
-        token_ticker: b"BensT".to_vec(),
-        amount_to_issue: 1_000_000_000,
-        number_of_decimals: 19,
-        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
-    };
-    test_tx!(data, Err, "too long decimals");
-}
+fn test_issuance_rate_limit() {
+    execute_with_alice(|alice_pub_key| {
+        IssuanceRateLimit::set(&1);
+        let alice = H256::from(alice_pub_key);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let half = ALICE_GENESIS_BALANCE / 2;
+        let split = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_pubkey(half, alice),
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - half, alice),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), split.clone()));
 
-#[test]
-fn test_tokens_issuance_empty_metadata() {
-    // metadata_uri empty
-    let data = OutputData::TokenIssuanceV1 {
-        token_ticker: b"BensT".to_vec(),
-        amount_to_issue: 1_000_000_000,
-        number_of_decimals: 18,
-        metadata_uri: vec![],
-    };
-    test_tx!(data, Ok, "");
-}
+        let issue_from = |index: u64, ticker: &str| {
+            let utxo = split.outputs[index as usize].clone();
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(split.outpoint(index))],
+                outputs: vec![TransactionOutput::new_p2pk_with_data(
+                    utxo.value - TokenIssuanceFee::get(),
+                    alice,
+                    OutputData::TokenIssuanceV1 {
+                        token_ticker: ticker.as_bytes().to_vec(),
+                        amount_to_issue: 1_000,
+                        number_of_decimals: 2,
+                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                    },
+                )],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo], 0, &alice_pub_key)
+        };
 
-#[test]
-fn test_tokens_issuance_too_long_metadata() {
-    // metadata_uri too long
-    let data = OutputData::TokenIssuanceV1 {
-        token_ticker: b"BensT".to_vec(),
-        amount_to_issue: 1_000_000_000,
-        number_of_decimals: 18,
-        metadata_uri: Vec::from([0u8; 10_000]),
-    };
-    test_tx!(data, Err, "token metadata uri is too long");
-}
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            issue_from(0, "BensT")
+        ));
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), issue_from(1, "BensU")),
+            "issuance rate limit reached"
+        );
 
-#[test]
-fn test_tokens_issuance_with_junk_data() {
-    // The data field of the maximum allowed length filled with random garbage
-    let mut rng = rand::thread_rng();
-    let garbage = build_random_vec(100);
-    let data = OutputData::TokenIssuanceV1 {
-        token_ticker: vec![0, 255, 254, 2, 1],
-        amount_to_issue: rng.gen::<u64>() as u128,
-        number_of_decimals: 18,
-        metadata_uri: garbage.clone(),
-    };
-    test_tx!(data, Err, "token ticker has none ascii characters");
+        // the limit is per era
+        next_block();
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            issue_from(1, "BensU")
+        ));
+        assert_eq!(
+            Utxo::issuance_count(BlakeTwo256::hash_of(&Destination::<H256>::Pubkey(
+                alice_pub_key
+            ))),
+            (1, 1)
+        );
+    })
 }
 
 #[test]
-fn test_tokens_issuance_with_corrupted_uri() {
-    let mut rng = rand::thread_rng();
-    let garbage = build_random_vec(100);
-    // garbage uri
-    let data = OutputData::TokenIssuanceV1 {
-        token_ticker: b"BensT".to_vec(),
-        amount_to_issue: rng.gen::<u64>() as u128,
-        number_of_decimals: 18,
-        metadata_uri: garbage,
-    };
-    test_tx!(data, Err, "metadata uri has none ascii characters");
+fn test_emergency_pause() {
+    let (mut test_ext, keys_and_hashes) = multiple_keys_test_ext();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice = H256::from(keys_and_hashes[0].0);
+        let (karl_pub_key, karl_genesis) = keys_and_hashes[1];
+        let karl = H256::from(karl_pub_key);
+        let utxo = UtxoStore::<Test>::get(karl_genesis).expect("karl's utxo does not exist");
+        let spend_to = |output| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(karl_genesis)],
+                outputs: vec![output],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign(&[utxo.clone()], 0, &karl_pub_key)
+            .expect("karl's pub key not found")
+        };
+        let payment = spend_to(TransactionOutput::new_pubkey(utxo.value, karl));
+        let issuance = spend_to(TransactionOutput::new_p2pk_with_data(
+            utxo.value - TokenIssuanceFee::get(),
+            karl,
+            OutputData::TokenIssuanceV1 {
+                token_ticker: "BensT".as_bytes().to_vec(),
+                amount_to_issue: 1_000,
+                number_of_decimals: 2,
+                metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+            },
+        ));
+
+        assert_noop!(
+            Utxo::set_paused(Origin::signed(alice), Some(PauseScope::All)),
+            sp_runtime::DispatchError::BadOrigin
+        );
+
+        assert_ok!(Utxo::set_paused(Origin::root(), Some(PauseScope::Tokens)));
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), issuance),
+            "token operations are paused"
+        );
+
+        // ALICE (index 0) can still withdraw her stake
+        assert_ok!(Utxo::set_paused(Origin::root(), Some(PauseScope::All)));
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), payment.clone()),
+            "spends are paused"
+        );
+        assert_ok!(Utxo::unlock_request_for_withdrawal(Origin::signed(alice)));
+        for _ in 1..6 {
+            next_block();
+        }
+        assert_ok!(Utxo::withdraw_stake(Origin::signed(alice)));
+
+        assert_ok!(Utxo::set_paused(Origin::root(), None));
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), payment));
+        assert!(System::events()
+            .iter()
+            .any(|r| r.event == Event::Utxo(crate::Event::PausedSet(None))));
+    })
 }
 
 #[test]
-fn test_two_token_creation_in_one_tx() {
+fn test_utxo_stats() {
     execute_with_alice(|alice_pub_key| {
+        let stats = Utxo::utxo_stats();
+        assert_eq!(stats.utxo_count, 1);
+        assert_eq!(stats.total_value, ALICE_GENESIS_BALANCE);
+        assert!(stats.token_utxo_counts.is_empty());
+
+        // the issued token is held by one UTXO, the fee leaves the UTXO set
         let (utxo0, input0) = tx_input_gen_no_signature();
+        let token_id = TokenId::new(&input0);
+        let value = ALICE_GENESIS_BALANCE - TokenIssuanceFee::get();
         let tx = Transaction {
             inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_p2pk_with_data(
+                value,
+                H256::from(alice_pub_key),
+                OutputData::TokenIssuanceV1 {
+                    token_ticker: "BensT".as_bytes().to_vec(),
+                    amount_to_issue: 1_000,
+                    number_of_decimals: 2,
+                    metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                },
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let stats = Utxo::utxo_stats();
+        assert_eq!(stats.utxo_count, 1);
+        assert_eq!(stats.total_value, value);
+        assert_eq!(stats.token_utxo_counts, vec![(token_id.to_string(), 1)]);
+
+        // split the token in two UTXOs and the MLT in a third one
+        let utxo1 = tx.outputs[0].clone();
+        let transfer = |amount| {
+            TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(alice_pub_key),
+                OutputData::TokenTransferV1 {
+                    token_id: token_id.clone(),
+                    amount,
+                },
+            )
+        };
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(tx.outpoint(0))],
             outputs: vec![
-                TransactionOutput::new_p2pk_with_data(
-                    0,
-                    H256::from(alice_pub_key),
-                    OutputData::TokenIssuanceV1 {
-                        token_ticker: b"Enric".to_vec(),
-                        amount_to_issue: 1_000_000_000,
-                        number_of_decimals: 2,
-                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
-                    },
-                ),
-                TransactionOutput::new_p2pk_with_data(
-                    0,
-                    H256::from(alice_pub_key),
-                    OutputData::TokenIssuanceV1 {
-                        token_ticker: b"Ben".to_vec(),
-                        amount_to_issue: 2_000_000_000,
-                        number_of_decimals: 3,
-                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
-                    },
-                ),
+                transfer(400),
+                transfer(600),
+                TransactionOutput::new_pubkey(value, H256::from(alice_pub_key)),
             ],
             time_lock: Default::default(),
+            expires_at: None,
         }
-        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
-        frame_support::assert_err_ignore_postinfo!(
-            Utxo::spend(Origin::signed(H256::zero()), tx),
-            "this id can't be used for a new token"
-        );
-    });
+        .sign_unchecked(&[utxo1], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        let stats = Utxo::utxo_stats();
+        assert_eq!(stats.utxo_count, 3);
+        assert_eq!(stats.total_value, value);
+        assert_eq!(stats.token_utxo_counts, vec![(token_id.to_string(), 2)]);
+        assert_ok!(crate::pallet::try_state::<Test>());
+    })
 }
 
-// Let's wrap common acts
-fn test_tx_issuance_for_transfer<F>(expecting_err_msg: &'static str, test_func: F)
-where
-    F: Fn(TokenId, Public, Public, H256, TransactionOutput<H256>) -> Transaction<H256>,
-{
+#[test]
+fn test_token_holders() {
     let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
     test_ext.execute_with(|| {
-        // Alice issue 1_000_000_000 MLS-01, and send them to Karl
         let (utxo0, input0) = tx_input_gen_no_signature();
+        let token_id = TokenId::new(&input0);
+        let value = ALICE_GENESIS_BALANCE - TokenIssuanceFee::get();
         let tx = Transaction {
-            inputs: vec![input0.clone()],
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_p2pk_with_data(
+                value,
+                H256::from(alice_pub_key),
+                OutputData::TokenIssuanceV1 {
+                    token_ticker: "BensT".as_bytes().to_vec(),
+                    amount_to_issue: 1_000,
+                    number_of_decimals: 2,
+                    metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                },
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert_eq!(Utxo::token_holder_count(&token_id), 1);
+        assert_eq!(
+            Utxo::token_holders(&token_id, None, 10),
+            vec![(H256::from(alice_pub_key), 1_000)]
+        );
+
+        // Alice keeps 100 in two UTXOs, Karl receives 900
+        let utxo1 = tx.outputs[0].clone();
+        let transfer = |pubkey, amount| {
+            TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(pubkey),
+                OutputData::TokenTransferV1 {
+                    token_id: token_id.clone(),
+                    amount,
+                },
+            )
+        };
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(tx.outpoint(0))],
             outputs: vec![
-                TransactionOutput::new_pubkey(
-                    ALICE_GENESIS_BALANCE - 90,
-                    H256::from(alice_pub_key),
-                ),
-                TransactionOutput::new_p2pk_with_data(
-                    10,
-                    H256::from(karl_pub_key),
-                    OutputData::TokenIssuanceV1 {
-                        token_ticker: "BensT".as_bytes().to_vec(),
-                        amount_to_issue: 1_000_000_000,
-                        // Should be not more than 18 numbers
-                        number_of_decimals: 2,
-                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
-                    },
-                ),
+                transfer(alice_pub_key, 40),
+                transfer(alice_pub_key, 60),
+                transfer(karl_pub_key, 900),
+                TransactionOutput::new_pubkey(value, H256::from(alice_pub_key)),
             ],
             time_lock: Default::default(),
+            expires_at: None,
         }
-        .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
-        let token_id = TokenId::new(&tx.inputs[0]);
+        .sign_unchecked(&[utxo1], 0, &alice_pub_key);
         assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert_eq!(Utxo::token_holder_count(&token_id), 2);
+        let mut holders = vec![(H256::from(alice_pub_key), 100), (H256::from(karl_pub_key), 900)];
+        holders.sort();
+        assert_eq!(Utxo::token_holders(&token_id, None, 10), holders);
+        assert_ok!(crate::pallet::try_state::<Test>());
 
-        let token_utxo_hash = tx.outpoint(1);
-        let token_utxo: TransactionOutput<H256> = tx.outputs[1].clone();
-        // Call a test func
-        let tx = test_func(
-            token_id,
-            alice_pub_key,
-            karl_pub_key,
-            token_utxo_hash,
-            token_utxo,
+        // paging resumes right after the cursor
+        assert_eq!(
+            Utxo::token_holders(&token_id, None, 1),
+            holders[..1].to_vec()
         );
-        frame_support::assert_err_ignore_postinfo!(
-            Utxo::spend(Origin::signed(H256::zero()), tx),
-            expecting_err_msg
+        assert_eq!(
+            Utxo::token_holders(&token_id, Some(holders[0].0), 10),
+            holders[1..].to_vec()
         );
-    });
+        assert!(Utxo::token_holders(&token_id, Some(holders[1].0), 10).is_empty());
+
+        // Karl sends everything to Alice and is no longer a holder
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(tx.outpoint(2))],
+            outputs: vec![transfer(alice_pub_key, 900)],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[tx.outputs[2].clone()], 0, &karl_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert_eq!(Utxo::token_holder_count(&token_id), 1);
+        assert_eq!(
+            Utxo::token_holders(&token_id, None, 10),
+            vec![(H256::from(alice_pub_key), 1_000)]
+        );
+        assert_ok!(crate::pallet::try_state::<Test>());
+    })
 }
 
+#[cfg(feature = "pruning")]
 #[test]
-fn test_token_transfer_with_wrong_token_id() {
-    let test_fun = Box::new(
-        move |_token_id,
-              alice_pub_key,
-              karl_pub_key,
-              token_utxo_hash,
-              token_utxo: TransactionOutput<H256>| {
-            let input = TransactionInput::new_empty(token_utxo_hash);
+fn test_pruned_utxo_spend() {
+    execute_with_alice(|alice_pub_key| {
+        PruningDepth::set(&2);
+        System::set_block_number(1);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let outpoint = tx.outpoint(0);
+        let utxo = UtxoStore::<Test>::get(outpoint).unwrap();
+
+        // too young to be pruned
+        crate::pallet::prune_utxo_bodies::<Test>(2);
+        assert!(UtxoStore::<Test>::contains_key(outpoint));
+        crate::pallet::prune_utxo_bodies::<Test>(3);
+        assert!(!UtxoStore::<Test>::contains_key(outpoint));
+        assert_eq!(
+            Utxo::pruned_utxo(outpoint),
+            Some(BlakeTwo256::hash_of(&utxo))
+        );
+        assert_ok!(crate::pallet::try_state::<Test>());
+
+        let spend = |input: TransactionInput| {
             Transaction {
-                inputs: vec![input.clone()],
-                outputs: vec![TransactionOutput::new_p2pk_with_data(
-                    ALICE_GENESIS_BALANCE - u64::MAX as Value,
+                inputs: vec![input],
+                outputs: vec![TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE,
                     H256::from(alice_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id: TokenId::new(&input),
-                        amount: 100_000_000,
-                    },
                 )],
                 time_lock: Default::default(),
+                expires_at: None,
             }
-            .sign_unchecked(&[token_utxo.clone()], 0, &karl_pub_key)
-        },
-    );
-    test_tx_issuance_for_transfer("input for the token not found", test_fun);
+            .sign_unchecked(&[utxo.clone()], 0, &alice_pub_key)
+        };
+
+        // the body has to be supplied and match the commitment
+        let forged =
+            TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE + 1, H256::from(alice_pub_key));
+        for input in [
+            TransactionInput::new_empty(outpoint),
+            TransactionInput::new_pruned(outpoint, &forged),
+        ] {
+            frame_support::assert_err_ignore_postinfo!(
+                Utxo::spend(Origin::signed(H256::zero()), spend(input)),
+                "pruned output must be supplied in the input lock"
+            );
+        }
+
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            spend(TransactionInput::new_pruned(outpoint, &utxo))
+        ));
+        assert_eq!(Utxo::pruned_utxo(outpoint), None);
+        assert_eq!(crate::PrunedValue::<Test>::get(), 0);
+        assert_ok!(crate::pallet::try_state::<Test>());
+    })
 }
 
 #[test]
-fn test_token_transfer_exceed_amount_tokens() {
-    let test_fun = Box::new(
-        move |token_id,
-              alice_pub_key,
-              karl_pub_key,
-              token_utxo_hash,
-              token_utxo: TransactionOutput<H256>| {
-            Transaction {
-                inputs: vec![TransactionInput::new_empty(token_utxo_hash)],
-                outputs: vec![TransactionOutput::new_p2pk_with_data(
-                    0,
-                    H256::from(alice_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id,
-                        amount: 1_000_000_001,
-                    },
-                )],
-                time_lock: Default::default(),
-            }
-            .sign_unchecked(&[token_utxo.clone()], 0, &karl_pub_key)
-        },
-    );
-    test_tx_issuance_for_transfer("output value must not exceed input value", test_fun);
+fn test_airdrop_claim() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let (alice, karl) = (H256::from(alice_pub_key), H256::from(karl_pub_key));
+        let entries = vec![(alice, 1_000), (karl, 500), (H256::repeat_byte(3), 1)];
+        let root = crate::airdrop::airdrop_root(&entries).unwrap();
+        let proof = crate::airdrop::claim_proof(&entries, 1).unwrap();
+        assert_err!(
+            Utxo::claim(Origin::signed(karl), 500, proof.clone()),
+            "no airdrop to claim"
+        );
+        assert_noop!(
+            Utxo::set_airdrop_root(Origin::signed(karl), Some(root)),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(Utxo::set_airdrop_root(Origin::root(), Some(root)));
+
+        // The amount and the claimant are committed to
+        assert_err!(
+            Utxo::claim(Origin::signed(karl), 501, proof.clone()),
+            "invalid airdrop proof"
+        );
+        assert_err!(
+            Utxo::claim(Origin::signed(alice), 500, proof.clone()),
+            "invalid airdrop proof"
+        );
+        UTXO_EVENTS.with(|events| events.borrow_mut().clear());
+        assert_ok!(Utxo::claim(Origin::signed(karl), 500, proof.clone()));
+        let claimed: Vec<_> = UtxoStore::<Test>::iter_values()
+            .filter(|utxo| utxo.destination == Destination::Pubkey(karl_pub_key))
+            .collect();
+        assert_eq!(claimed, vec![TransactionOutput::new_pubkey(500, karl)]);
+        let hash = BlakeTwo256::hash_of(&(&karl, root, "airdrop"));
+        assert_eq!(
+            UTXO_EVENTS.with(|events| events.borrow().clone()),
+            vec![UtxoEvent::New(hash)]
+        );
+        assert!(crate::UtxoCreationTime::<Test>::get(hash).is_some());
+        assert_ok!(crate::pallet::try_state::<Test>());
+
+        // Only once
+        assert_err!(
+            Utxo::claim(Origin::signed(karl), 500, proof),
+            "airdrop already claimed"
+        );
+        let proof = crate::airdrop::claim_proof(&entries, 0).unwrap();
+        assert_ok!(Utxo::claim(Origin::signed(alice), 1_000, proof));
+    })
 }
 
 #[test]
-fn test_token_transfer_exceed_amount_mlt() {
-    let test_fun = Box::new(
-        move |token_id: TokenId,
-              alice_pub_key,
-              karl_pub_key,
-              token_utxo_hash,
-              token_utxo: TransactionOutput<H256>| {
-            Transaction {
-                inputs: vec![TransactionInput::new_empty(token_utxo_hash)],
-                outputs: vec![TransactionOutput::new_p2pk_with_data(
-                    1_000_000_000,
-                    H256::from(alice_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id: token_id.clone(),
-                        amount: 1_000_000_000,
-                    },
-                )],
-                time_lock: Default::default(),
-            }
-            .sign_unchecked(&[token_utxo.clone()], 0, &karl_pub_key)
-        },
-    );
-    test_tx_issuance_for_transfer("output value must not exceed input value", test_fun);
+fn test_transaction_limits_check() {
+    execute_with_alice(|alice_pub_key| {
+        let (_, input0) = tx_input_gen_no_signature();
+        let mut tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        assert_eq!(
+            tx.estimated_weight::<Test>(),
+            <<Test as crate::Config>::WeightInfo as crate::WeightInfo>::spend(2)
+                + <<Test as crate::Config>::WeightInfo as crate::WeightInfo>::notify_watchers(
+                    2 * MaxWatchers::get()
+                )
+        );
+        assert_ok!(tx.encoded_size_limit_check::<Test>());
+
+        tx.outputs[0].data = Some(OutputData::TokenIssuanceV1 {
+            token_ticker: "BensT".as_bytes().to_vec(),
+            amount_to_issue: 1_000,
+            number_of_decimals: 2,
+            metadata_uri: vec![0; 4 * 1024 * 1024],
+        });
+        assert_err!(
+            tx.encoded_size_limit_check::<Test>(),
+            "transaction exceeds the maximum block length"
+        );
+    })
 }
 
 #[test]
-fn test_token_transfer_send_part_others_burn() {
-    let test_fun = Box::new(
-        move |token_id: TokenId,
-              alice_pub_key,
-              karl_pub_key,
-              token_utxo_hash,
-              token_utxo: TransactionOutput<H256>| {
+fn test_transaction_weight_share() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_pubkey(100, H256::from(alice_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+
+        // The witness adds to the weight
+        let weight = tx.estimated_weight::<Test>();
+        assert_eq!(tx.script_bytes(), 64);
+        assert_eq!(
+            weight,
+            <<Test as crate::Config>::WeightInfo as crate::WeightInfo>::spend(2)
+                + <<Test as crate::Config>::WeightInfo as crate::WeightInfo>::spend_script_bytes(
+                    64
+                )
+                + <<Test as crate::Config>::WeightInfo as crate::WeightInfo>::notify_watchers(
+                    2 * MaxWatchers::get()
+                )
+        );
+
+        let normal = <Test as frame_system::Config>::BlockWeights::get()
+            .get(frame_support::weights::DispatchClass::Normal)
+            .max_total
+            .unwrap();
+        MaxTransactionWeightShare::set(&sp_runtime::Perbill::from_rational(
+            weight + 5_000_000,
+            normal,
+        ));
+        assert_ok!(crate::pallet::validate_transaction::<Test>(&tx));
+
+        let mut heavy_tx = tx.clone();
+        heavy_tx.inputs[0].witness.extend_from_slice(&[0; 1_000]);
+        assert_err!(
+            crate::pallet::validate_transaction::<Test>(&heavy_tx),
+            "transaction exceeds its share of the block weight"
+        );
+        MaxTransactionWeightShare::set(&sp_runtime::Perbill::from_percent(25));
+    })
+}
+
+#[test]
+fn test_data_carrier_output() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let spend = |value: Value, data: Vec<u8>| {
+            let mut carrier = TransactionOutput::new_data_carrier(data);
+            carrier.value = value;
             Transaction {
-                inputs: vec![TransactionInput::new_empty(token_utxo_hash)],
+                inputs: vec![input0.clone()],
                 outputs: vec![
-                    // Send only 30%, let's forget about another 70% of tokens
-                    TransactionOutput::new_p2pk_with_data(
-                        0,
+                    TransactionOutput::new_pubkey(
+                        ALICE_GENESIS_BALANCE - value,
                         H256::from(alice_pub_key),
-                        OutputData::TokenTransferV1 {
-                            token_id: token_id.clone(),
-                            amount: 300_000_000,
-                        },
                     ),
+                    carrier,
                 ],
                 time_lock: Default::default(),
+                expires_at: None,
             }
-            .sign_unchecked(&[token_utxo.clone()], 0, &karl_pub_key)
-        },
-    );
-    test_tx_issuance_for_transfer("output value must not exceed input value", test_fun);
-}
+            .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key)
+        };
+
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), spend(1, b"anchor".to_vec())),
+            "data carrier output must have zero value"
+        );
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), spend(0, vec![0; 81])),
+            "data carrier output exceeds the maximum size"
+        );
+
+        let tx = spend(0, vec![0; 80]);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert!(UtxoStore::<Test>::contains_key(tx.outpoint(0)));
+        assert!(!UtxoStore::<Test>::contains_key(tx.outpoint(1)));
+    })
+}
 
 #[test]
-fn test_token_transfer() {
-    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
-    test_ext.execute_with(|| {
-        // Alice issue 1_000_000_000 MLS-01, and send them to Karl
+fn test_call_pp_abi_check() {
+    execute_with_alice(|alice_pub_key| {
         let (utxo0, input0) = tx_input_gen_no_signature();
-        let tx = Transaction {
+        let call = |abi_hash: Option<H256>| {
+            Transaction {
+                inputs: vec![input0.clone()],
+                outputs: vec![
+                    TransactionOutput::new_pubkey(
+                        ALICE_GENESIS_BALANCE - 10,
+                        H256::from(alice_pub_key),
+                    ),
+                    TransactionOutput::new_call_pp(
+                        10,
+                        H256::repeat_byte(0xcc),
+                        false,
+                        vec![0x63, 0x3a, 0xa5, 0x51],
+                        abi_hash,
+                    ),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key)
+        };
+
+        // A call built against another ABI is rejected before the funds are spent
+        assert_err!(
+            crate::pallet::validate_transaction::<Test>(&call(Some(H256::repeat_byte(0x01)))),
+            "ABI hash doesn't match the contract"
+        );
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                call(Some(H256::repeat_byte(0x01)))
+            ),
+            "ABI hash doesn't match the contract"
+        );
+        assert!(UtxoStore::<Test>::contains_key(input0.outpoint));
+        // Without an ABI hash, the call is checked against the registered ABI, if any
+        assert_ok!(crate::pallet::validate_transaction::<Test>(&call(None)));
+
+        let tx = call(Some(MockAbiHash::get()));
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert!(UtxoStore::<Test>::contains_key(tx.outpoint(1)));
+    })
+}
+
+#[test]
+fn test_contract_utxos() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let contract = H256::repeat_byte(0xcc);
+        let call = |value: Value| {
+            TransactionOutput::new_call_pp(
+                value,
+                contract,
+                false,
+                vec![0x63, 0x3a, 0xa5, 0x51],
+                None,
+            )
+        };
+        let tx1 = Transaction {
             inputs: vec![input0],
             outputs: vec![
                 TransactionOutput::new_pubkey(
-                    ALICE_GENESIS_BALANCE - 90,
+                    ALICE_GENESIS_BALANCE - 30,
                     H256::from(alice_pub_key),
                 ),
-                TransactionOutput::new_p2pk_with_data(
-                    90,
-                    H256::from(karl_pub_key),
-                    OutputData::TokenIssuanceV1 {
-                        token_ticker: "BensT".as_bytes().to_vec(),
-                        amount_to_issue: 1_000_000_000,
-                        // Should be not more than 18 numbers
-                        number_of_decimals: 2,
-                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
-                    },
-                ),
+                call(10),
+                call(20),
             ],
             time_lock: Default::default(),
+            expires_at: None,
         }
-        .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
-        let token_id = TokenId::new(&tx.inputs[0]);
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
-        let token_utxo_hash = tx.outpoint(1);
-        let token_utxo = tx.outputs[1].clone();
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
+        let mut expected = vec![(tx1.outpoint(1), 10), (tx1.outpoint(2), 20)];
+        expected.sort();
+        assert_eq!(Utxo::contract_utxos(&contract), expected);
+        assert_eq!(Utxo::contract_utxos(&H256::zero()), vec![]);
 
-        // Let's send 300_000_000 and rest back
-        let tx = Transaction {
-            inputs: vec![TransactionInput::new_empty(token_utxo_hash)],
+        // The contract pays out one of its UTXOs, which is no longer listed
+        let payout = Transaction {
+            inputs: vec![TransactionInput::new_script(
+                tx1.outpoint(1),
+                Builder::new().into_script(),
+                Builder::new().push_int(0x1337).into_script(),
+            )],
+            outputs: vec![TransactionOutput::new_pubkey(10, H256::from(alice_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), payout));
+        assert_eq!(Utxo::contract_utxos(&contract), vec![(tx1.outpoint(2), 20)]);
+        assert_ok!(crate::pallet::try_state::<Test>());
+    })
+}
+
+#[test]
+fn test_contract_payouts() {
+    use utxo_api::{Payout, PayoutDestination, UtxoApi};
+
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let contract = H256::repeat_byte(0xcc);
+        let mut call = TransactionOutput::new_call_pp(
+            100,
+            contract,
+            false,
+            vec![0x63, 0x3a, 0xa5, 0x51],
+            None,
+        );
+        call.data = Some(OutputData::TokenIssuanceV1 {
+            token_ticker: "BensT".as_bytes().to_vec(),
+            amount_to_issue: 1_000,
+            number_of_decimals: 2,
+            metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+        });
+        let tx1 = Transaction {
+            inputs: vec![input0],
             outputs: vec![
-                TransactionOutput::new_p2pk_with_data(
-                    0,
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - TokenIssuanceFee::get() - 100,
                     H256::from(alice_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id: token_id.clone(),
-                        amount: 300_000_000,
-                    },
-                ),
-                TransactionOutput::new_p2pk_with_data(
-                    0,
-                    H256::from(karl_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id: token_id.clone(),
-                        amount: 700_000_000,
-                    },
                 ),
+                call,
             ],
             time_lock: Default::default(),
+            expires_at: None,
         }
-        .sign_unchecked(&[token_utxo.clone()], 0, &karl_pub_key);
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
-        let alice_tokens_utxo_hash = tx.outpoint(0);
-        let karl_tokens_utxo_hash = tx.outpoint(1);
-        let karl_tokens_utxo = tx.outputs[1].clone();
-        assert!(!UtxoStore::<Test>::contains_key(H256::from(
-            token_utxo_hash
-        )));
-        assert!(UtxoStore::<Test>::contains_key(alice_tokens_utxo_hash));
-        assert!(UtxoStore::<Test>::contains_key(karl_tokens_utxo_hash));
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
+        let token_id = crate::tokens::TokenId::new(&tx1.inputs[0]);
 
-        // should be success
-        let tx = Transaction {
-            inputs: vec![TransactionInput::new_empty(karl_tokens_utxo_hash)],
+        let script_hash = H256::repeat_byte(0x11);
+        let payout = |destination, value, amount| Payout {
+            destination,
+            value,
+            token: Some((token_id.as_h160(), amount)),
+        };
+        let payouts = vec![
+            payout(
+                PayoutDestination::Pubkey(H256::from(alice_pub_key)),
+                40,
+                600,
+            ),
+            payout(PayoutDestination::ScriptHash(script_hash), 50, 400),
+        ];
+
+        // Only the UTXOs of the contract can be paid out from
+        assert_err!(
+            Utxo::send_conscrit_payouts(&contract, &payouts, &vec![tx1.outpoint(0)]),
+            "UTXO isn't owned by the contract"
+        );
+        assert_err!(
+            Utxo::send_conscrit_payouts(&contract, &vec![], &vec![tx1.outpoint(1)]),
+            "No payouts given"
+        );
+        // The tokens paid out must be covered by the inputs
+        let overspent = vec![payout(PayoutDestination::ScriptHash(script_hash), 50, 1_001)];
+        assert!(
+            Utxo::send_conscrit_payouts(&contract, &overspent, &vec![tx1.outpoint(1)]).is_err()
+        );
+
+        assert_ok!(Utxo::send_conscrit_payouts(
+            &contract,
+            &payouts,
+            &vec![tx1.outpoint(1)]
+        ));
+        assert_eq!(Utxo::contract_utxos(&contract), vec![]);
+        let paid: Vec<_> = UtxoStore::<Test>::iter_values()
+            .filter(|utxo| utxo.data.is_some())
+            .map(|utxo| (utxo.destination, utxo.value))
+            .collect();
+        assert_eq!(paid.len(), 2);
+        assert!(paid.contains(&(Destination::ScriptHash(ScriptVersion::V0, script_hash), 50)));
+        assert_ok!(crate::pallet::try_state::<Test>());
+    })
+}
+
+#[test]
+fn test_outpoint_by_txid() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let genesis = input0.outpoint;
+        // UTXOs not created by a transaction are referenced by their outpoint
+        assert_eq!(Utxo::output_ref(genesis), Some((genesis, 0)));
+        assert_eq!(Utxo::outpoint_of(genesis, 0), Some(genesis));
+        assert_eq!(Utxo::outpoint_of(genesis, 1), None);
+
+        let tx1 = Transaction {
+            inputs: vec![input0],
             outputs: vec![
-                TransactionOutput::new_p2pk_with_data(
-                    0,
+                TransactionOutput::new_pubkey(10, H256::from(alice_pub_key)),
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 10,
                     H256::from(alice_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id: token_id.clone(),
-                        amount: 400_000_000,
-                    },
-                ),
-                TransactionOutput::new_p2pk_with_data(
-                    0,
-                    H256::from(karl_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id: token_id.clone(),
-                        amount: 300_000_000,
-                    },
                 ),
             ],
             time_lock: Default::default(),
+            expires_at: None,
+        };
+        // The txid doesn't commit to the witnesses
+        let txid = tx1.txid();
+        let tx1 = tx1.sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_eq!(tx1.txid(), txid);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
+
+        assert_eq!(Utxo::outpoint_of(genesis, 0), None);
+        assert_eq!(Utxo::outpoint_of(txid, 1), Some(tx1.outpoint(1)));
+        assert_eq!(Utxo::output_ref(tx1.outpoint(1)), Some((txid, 1)));
+
+        let tx2 = Transaction {
+            inputs: vec![TransactionInput::new_empty(tx1.outpoint(0))],
+            outputs: vec![TransactionOutput::new_pubkey(10, H256::from(alice_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
         }
-        .sign_unchecked(&[karl_tokens_utxo], 0, &karl_pub_key);
+        .sign_unchecked(&tx1.outputs[..1], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx2.clone()));
+        assert_eq!(Utxo::outpoint_of(txid, 0), None);
+        assert_eq!(Utxo::output_ref(tx1.outpoint(0)), None);
+        assert_eq!(Utxo::outpoint_of(tx2.txid(), 0), Some(tx2.outpoint(0)));
+        assert_ok!(crate::pallet::try_state::<Test>());
+    })
+}
+
+#[test]
+fn test_utxo_hooks() {
+    execute_with_alice(|alice_pub_key| {
+        UTXO_EVENTS.with(|events| events.borrow_mut().clear());
+        let block_weight =
+            || *System::block_weight().get(frame_support::weights::DispatchClass::Normal);
+        let weight_before = block_weight();
+
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let genesis = input0.outpoint;
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_pubkey(10, H256::from(alice_pub_key)),
+                // Not added to the UTXO set, so not reported
+                TransactionOutput::new_data_carrier(b"anchor".to_vec()),
+                TransactionOutput::new_pubkey(20, H256::from(alice_pub_key)),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
         assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+
         assert_eq!(
-            300_000_000,
-            UtxoStore::<Test>::get(alice_tokens_utxo_hash)
-                .unwrap()
-                .data
-                .map(|x| match x {
-                    OutputData::TokenTransferV1 { amount, .. } => amount,
-                    _ => 0,
-                })
-                .unwrap_or(0)
+            UTXO_EVENTS.with(|events| events.borrow().clone()),
+            vec![
+                UtxoEvent::Spent(genesis),
+                UtxoEvent::New(tx.outpoint(0)),
+                UtxoEvent::New(tx.outpoint(2)),
+            ]
         );
+        // The weight of the hooks is added to the block
+        assert_eq!(block_weight(), weight_before + 3 * UTXO_HOOK_WEIGHT);
 
-        let new_alice_tokens_utxo_hash = tx.outpoint(0);
-        assert!(UtxoStore::<Test>::contains_key(new_alice_tokens_utxo_hash));
+        // Nothing is reported for a rejected transaction
+        UTXO_EVENTS.with(|events| events.borrow_mut().clear());
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(H256::zero())],
+            outputs: vec![TransactionOutput::new_pubkey(50, H256::from(alice_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            "missing inputs"
+        );
+        assert_eq!(UTXO_EVENTS.with(|events| events.borrow().len()), 0);
+    })
+}
+
+#[test]
+fn test_reserve_proof() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let outpoint = input0.outpoint;
+        let challenge = b"audit 2021-11".to_vec();
+        let proof = ReserveProof::sign(challenge.clone(), &[(outpoint, alice_pub_key)]).unwrap();
         assert_eq!(
-            400_000_000,
-            UtxoStore::<Test>::get(new_alice_tokens_utxo_hash)
-                .unwrap()
-                .data
-                .map(|x| match x {
-                    OutputData::TokenTransferV1 { amount, .. } => amount,
-                    _ => 0,
-                })
-                .unwrap_or(0)
+            Utxo::verify_reserve_proof(&proof),
+            Ok(ALICE_GENESIS_BALANCE)
         );
-    });
+
+        // The signatures commit to the challenge
+        let replayed = ReserveProof {
+            challenge: b"audit 2021-12".to_vec(),
+            ..proof.clone()
+        };
+        assert_err!(
+            Utxo::verify_reserve_proof(&replayed),
+            "signature must be valid"
+        );
+        // Only the key controlling the UTXO can sign for it
+        let forged = ReserveProof::sign(challenge.clone(), &[(outpoint, karl_pub_key)]).unwrap();
+        assert_err!(
+            Utxo::verify_reserve_proof(&forged),
+            "signature must be valid"
+        );
+        let doubled = ReserveProof::sign(
+            challenge.clone(),
+            &[(outpoint, alice_pub_key), (outpoint, alice_pub_key)],
+        )
+        .unwrap();
+        assert_err!(Utxo::verify_reserve_proof(&doubled), "duplicate outpoint");
+
+        // Spent UTXOs are no longer reserves
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 10,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert_err!(
+            Utxo::verify_reserve_proof(&proof),
+            "outpoint is not unspent"
+        );
+    })
 }
 
-// todo: This part isn't fully tested, left for the next PR
-// #[test]
-// fn test_nft_transferring() {
-//     let (mut test_ext, alice_pub_key, karl_pub_key) = new_test_ext_and_keys();
-//     test_ext.execute_with(|| {
-//         let token_id = TokenId::new_asset(H256::random());
-//         // Alice issue 1000 MLS-01, and send them to Karl and the rest back to herself
-//         let (utxo0, input0) = tx_input_gen_no_signature();
-//         let data_hash = NftDataHash::Raw(build_random_vec(32));
-//         let tx = Transaction {
-//             inputs: vec![input0],
-//             outputs: vec![
-//                 TransactionOutput::new_pubkey(90, H256::from(alice_pub_key)),
-//                 TransactionOutput::new_p2pk_with_data(
-//                     10,
-//                     H256::from(karl_pub_key),
-//                     OutputData::NftMintV1 {
-//                         token_id: token_id.clone(),
-//                         data_hash: data_hash.clone(),
-//                         metadata_uri: "mintlayer.org".as_bytes().to_vec(),
-//                     },
-//                 ),
-//             ],
-//             time_lock: Default::default(),
-//         }
-//         .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
-//         assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
-//         let token_utxo_hash = tx.outpoint(1);
-//         let token_utxo = tx.outputs[1].clone();
-//
-//         // Let's fail on wrong token id
-//         let tx = Transaction {
+#[test]
+fn test_decode_transaction() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0.clone()],
+            outputs: vec![
+                TransactionOutput::new_pubkey(100, H256::from(alice_pub_key)),
+                TransactionOutput::new_script_hash(ALICE_GENESIS_BALANCE - 200, H256::zero()),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        let inspection = Utxo::decode_transaction(&tx.encode()).unwrap();
+        assert_eq!(inspection.id, BlakeTwo256::hash_of(&tx));
+        assert_eq!(inspection.inputs[0].outpoint, input0.outpoint);
+        let spent = inspection.inputs[0].utxo.as_ref().unwrap();
+        assert_eq!(spent.value, utxo0.value);
+        assert_eq!(spent.script_type, inspect::ScriptType::Pubkey);
+        assert_eq!(
+            inspection.outputs[1].script_type,
+            inspect::ScriptType::ScriptHash(0)
+        );
+        assert_eq!(inspection.fee, Some(100));
+        assert!(inspection.token_deltas.is_empty());
+        assert_eq!(
+            inspection.validation_error,
+            Some(b"bad signature format".to_vec())
+        );
+
+        let tx = tx.sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        let inspection = Utxo::decode_transaction(&tx.encode()).unwrap();
+        assert_eq!(inspection.validation_error, None);
+
+        assert_err!(
+            Utxo::decode_transaction(&tx.encode()[..10]),
+            "invalid transaction encoding"
+        );
+    })
+}
+
+#[test]
+fn test_dry_run_spend() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let missing = TransactionInput::new_empty(H256::repeat_byte(0x11));
+        let tx = Transaction {
+            inputs: vec![input0.clone(), missing],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 100,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        // The witnesses can't be checked until every spent UTXO is known
+        let report = Utxo::dry_run_spend(&tx.encode()).unwrap();
+        assert_eq!(
+            report.inputs,
+            vec![inspect::InputStatus::Unchecked, inspect::InputStatus::Missing]
+        );
+        assert_eq!(report.inspection.fee, None);
+        assert_eq!(report.pool_error, None);
+
+        let tx = Transaction {
+            inputs: vec![input0],
+            ..tx
+        };
+        let report = Utxo::dry_run_spend(&tx.encode()).unwrap();
+        assert_eq!(
+            report.inputs,
+            vec![inspect::InputStatus::Invalid(b"bad signature format".to_vec())]
+        );
+        assert_eq!(report.inspection.fee, Some(100));
+        assert_eq!(report.pool_error, Some(b"bad signature format".to_vec()));
+
+        let tx = tx.sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        let report = Utxo::dry_run_spend(&tx.encode()).unwrap();
+        assert_eq!(report.inputs, vec![inspect::InputStatus::Valid]);
+        assert_eq!(report.pool_error, None);
+        // Nothing was submitted
+        assert!(UtxoStore::<Test>::contains_key(tx.inputs[0].outpoint));
+
+        assert_err!(
+            Utxo::dry_run_spend(&tx.encode()[..10]),
+            "invalid transaction encoding"
+        );
+    })
+}
+
+#[test]
+fn test_unchecked_2nd_output() {
+    execute_with_alice(|alice_pub_key| {
+        // Create and sign a transaction
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx1 = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 30,
+                    H256::from(alice_pub_key),
+                ),
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 50,
+                    H256::from(alice_pub_key),
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+
+        // Calculate output 1 hash.
+        let utxo1_hash = tx1.outpoint(1);
+        // Now artificially insert utxo1 (that pays to a pubkey) to the output set.
+        UtxoStore::<Test>::insert(utxo1_hash, &tx1.outputs[1]);
+        // When adding a transaction, the output should be reported as already present.
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx1),
+            "output already exists"
+        );
+    })
+}
+
+#[test]
+fn test_simple_tx() {
+    execute_with_alice(|alice_pub_key| {
+        // Alice wants to send herself a new utxo of value 50.
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 50,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+
+        let new_utxo_hash = tx.outpoint(0);
+
+        let (_, init_utxo) = genesis_utxo();
+        assert!(UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert!(!UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
+        assert!(UtxoStore::<Test>::contains_key(new_utxo_hash));
+        assert_eq!(
+            ALICE_GENESIS_BALANCE - 50,
+            UtxoStore::<Test>::get(new_utxo_hash).unwrap().value
+        );
+    })
+}
+
+#[test]
+fn attack_with_sending_to_own_account() {
+    let (mut test_ext, _alice, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        // Karl wants to send himself a new utxo of value 50 out of thin air.
+        let mut tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(H256::zero())],
+            outputs: vec![TransactionOutput::new_pubkey(50, H256::from(karl_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+
+        let karl_sig = crypto::sr25519_sign(SR25519, &karl_pub_key, &tx.encode()).unwrap();
+        tx.inputs[0].witness = karl_sig.0.to_vec();
+
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            "missing inputs"
+        );
+    });
+}
+
+#[test]
+fn attack_with_empty_transactions() {
+    alice_test_ext().execute_with(|| {
+        // We should use the real input because. Otherwise, appears another error
+        let (_, input) = tx_input_gen_no_signature();
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), Transaction::default()), // empty tx
+            "no inputs"
+        );
+
+        assert_err!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                Transaction {
+                    inputs: vec![input], // an empty tx
+                    outputs: vec![],
+                    time_lock: Default::default(),
+                    expires_at: None,
+                }
+            ),
+            "no outputs"
+        );
+    });
+}
+
+#[test]
+fn attack_by_double_counting_input() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let utxos = [utxo0.clone(), utxo0];
+        let tx = Transaction {
+            // a double spend of the same UTXO!
+            inputs: vec![input0.clone(), input0],
+            outputs: vec![TransactionOutput::new_pubkey(100, H256::from(alice_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&utxos[..], 0, &alice_pub_key)
+        .sign_unchecked(&utxos[..], 1, &alice_pub_key);
+
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            "each input should be used only once"
+        );
+    });
+}
+
+#[test]
+fn attack_with_invalid_signature() {
+    execute_with_alice(|alice_pub_key| {
+        let (_utxo0, input0) = genesis_utxo();
+        let tx = Transaction {
+            // Just a random signature!
+            inputs: vec![TransactionInput::new_with_signature(input0, H512::random())],
+            outputs: vec![TransactionOutput::new_pubkey(100, H256::from(alice_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            "signature must be valid"
+        );
+    });
+}
+
+#[test]
+fn attack_by_permanently_sinking_outputs() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            //A 0 value output burns this output forever!
+            outputs: vec![TransactionOutput::new_pubkey(0, H256::from(alice_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+
+        assert_noop!(
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            "output value must be nonzero"
+        );
+    });
+}
+
+#[test]
+fn attack_by_overflowing_value() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_pubkey(Value::MAX, H256::from(alice_pub_key)),
+                // Attempts to do overflow total output value
+                TransactionOutput::new_pubkey(10, H256::from(alice_pub_key)),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            "output value overflow"
+        );
+    });
+}
+
+#[test]
+fn attack_by_overspending() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE, H256::from(alice_pub_key)),
+                // Creates 2 new utxo out of thin air
+                TransactionOutput::new_pubkey(2, H256::from(alice_pub_key)),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+
+        assert_noop!(
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            "output value must not exceed input value"
+        );
+    })
+}
+
+// first send 10 tokens to karl and return the rest back to alice
+// then send the rest of the tokens to karl
+#[test]
+fn tx_from_alice_to_karl() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        // alice sends 10 tokens to karl and the rest back to herself
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_pubkey(10, H256::from(karl_pub_key)),
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 90,
+                    H256::from(alice_pub_key),
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let new_utxo_hash = tx.outpoint(1);
+        let new_utxo = tx.outputs[1].clone();
+
+        // then send rest of the tokens to karl (proving that the first tx was successful)
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(new_utxo_hash)],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 90,
+                H256::from(karl_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[new_utxo], 0, &alice_pub_key);
+
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+    });
+}
+
+// alice sends 90 tokens to herself and donates 10 tokens for the block authors
+#[test]
+fn test_reward() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+
+        // Check the default parameters
+        let utxos = UtxoStore::<Test>::get(input0.outpoint).unwrap();
+        assert_eq!(utxos.value, ALICE_GENESIS_BALANCE);
+        let reward = RewardTotal::<Test>::get();
+        assert_eq!(reward, 0);
+
+        // Make a new transaction
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 90,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let utxo_hash = tx.outpoint(0);
+
+        // if the previous spend succeeded, there should be one utxo
+        // that has a value of ALICE_GENESIS_BALANCE - 90 and a reward that has a value of 90
+        let utxos = UtxoStore::<Test>::get(utxo_hash).unwrap();
+        let reward = RewardTotal::<Test>::get();
+        assert_eq!(utxos.value, ALICE_GENESIS_BALANCE - 90);
+        assert_eq!(reward, 90);
+    })
+}
+
+#[test]
+fn test_reward_overflow() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+
+        // Check the default parameters
+        let utxos = UtxoStore::<Test>::get(input0.outpoint).unwrap();
+        assert_eq!(utxos.value, ALICE_GENESIS_BALANCE);
+        let reward = RewardTotal::<Test>::get();
+        assert_eq!(reward, 0);
+
+        // Make a new transaction where
+        // Input balance:  4_000_000_000_000_000_000_000
+        // u64::MAX:          18_446_744_073_709_551_615
+        // the difference: 3_981_553_255_926_290_448_385
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_pubkey(
+                3_981_553_255_926_290_448_385,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            "reward exceed allowed amount"
+        );
+    })
+}
+
+#[test]
+fn test_script() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 90,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+    })
+}
+
+#[test]
+fn test_time_lock_tx() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 90,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: BlockTime::Blocks(10).as_raw().unwrap(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            "Time lock restrictions not satisfied",
+        );
+    })
+}
+
+#[test]
+fn test_transaction_expiry() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 90,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: BlockTime::Blocks(10).as_raw(),
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+
+        System::set_block_number(4);
+        let valid = crate::pallet::validate_transaction::<Test>(&tx).unwrap();
+        assert_eq!(valid.longevity, 6);
+
+        // The expiry is committed to by the signature
+        let mut bad_tx = tx.clone();
+        bad_tx.expires_at = BlockTime::Blocks(20).as_raw();
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), bad_tx),
+            "signature must be valid"
+        );
+
+        System::set_block_number(11);
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx.clone()),
+            "transaction expired"
+        );
+        System::set_block_number(10);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+    })
+}
+
+#[test]
+fn test_time_lock_script_fail() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let script = Builder::new().push_int(10).push_opcode(opc::OP_CLTV).into_script();
+        let script_hash: H256 = BlakeTwo256::hash(script.as_ref());
+        let tx1 = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_script_hash(
+                ALICE_GENESIS_BALANCE - 90,
+                script_hash,
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        let outpoint = tx1.outpoint(0);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1));
+
+        // The following should fail because the transaction-level time lock does not conform to
+        // the time lock restrictions imposed by the scripting system.
+        let tx2 = Transaction {
+            inputs: vec![TransactionInput::new_script(outpoint, script, Default::default())],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 150,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx2),
+            "script verification failed"
+        );
+    })
+}
+
+#[test]
+fn test_relative_time_lock_script() {
+    execute_with_alice(|alice_pub_key| {
+        let lock_time = RelativeTimeLock::blocks(3).as_u64() as i64;
+        let script = Builder::new().push_int(lock_time).push_opcode(opc::OP_CSV).into_script();
+        let script_hash: H256 = BlakeTwo256::hash(script.as_ref());
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        System::set_block_number(5);
+        let tx1 = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_script_hash(
+                ALICE_GENESIS_BALANCE - 90,
+                script_hash,
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        let outpoint = tx1.outpoint(0);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1));
+
+        let tx2 = Transaction {
+            inputs: vec![TransactionInput::new_script(outpoint, script, Default::default())],
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 150,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        // The UTXO is only two blocks old
+        System::set_block_number(7);
+        assert_noop!(
+            Utxo::spend(Origin::signed(H256::zero()), tx2.clone()),
+            "script verification failed"
+        );
+        System::set_block_number(8);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx2));
+    })
+}
+
+#[test]
+fn attack_double_spend_by_tweaking_input() {
+    execute_with_alice(|alice_pub_key| {
+        // Prepare and send a transaction with a 50-token output
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let drop_script = Builder::new().push_opcode(opc::OP_DROP).into_script();
+        let drop_script_hash = BlakeTwo256::hash(drop_script.as_ref());
+        let tx0 = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_script_hash(
+                ALICE_GENESIS_BALANCE - 50,
+                drop_script_hash,
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx0.clone()));
+
+        // Create a transaction that spends the same input 10 times by slightly modifying the
+        // redeem script.
+        let inputs: Vec<_> = (0..10)
+            .map(|i| {
+                let witness = Builder::new().push_int(1).push_int(i as i64).into_script();
+                TransactionInput::new_script(tx0.outpoint(0), drop_script.clone(), witness)
+            })
+            .collect();
+        let tx1 = Transaction {
+            inputs,
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 500,
+                H256::from(alice_pub_key),
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        assert_err!(
+            Utxo::spend(Origin::signed(H256::zero()), tx1),
+            "each input should be used only once"
+        );
+    });
+}
+
+#[test]
+fn test_send_to_addresses() {
+    let (mut test_ext, alice_pub_key, _karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice = H256::from(alice_pub_key);
+        // bech32-encoded `Destination::Pubkey(alice_pub_key)` and a script hash
+        let alice_addr = "ml1qrft7juyfhl06emj4zzrue5ljs6q39n2jalr4c40rhtcur647n0kwueyfsn";
+        let script_addr = "ml1qvvknne0acfzfd2ewksccgrgl4qlhcwewq4gjm75mtcpg26al66d5l5sz9k";
+        let payments =
+            vec![(10, alice_addr.as_bytes().to_vec()), (20, script_addr.as_bytes().to_vec())];
+
+        assert_err!(
+            Utxo::send_to_addresses(Origin::signed(alice), vec![]),
+            "No payments given"
+        );
+        assert_err!(
+            Utxo::send_to_addresses(
+                Origin::signed(alice),
+                vec![(0, alice_addr.as_bytes().to_vec())]
+            ),
+            "Value transferred must be larger than zero"
+        );
+        assert_err!(
+            Utxo::send_to_addresses(
+                Origin::signed(alice),
+                vec![
+                    (1, alice_addr.as_bytes().to_vec()),
+                    (ALICE_GENESIS_BALANCE, alice_addr.as_bytes().to_vec())
+                ]
+            ),
+            "Caller doesn't have enough UTXOs"
+        );
+
+        assert_ok!(Utxo::send_to_addresses(Origin::signed(alice), payments));
+        let payments: Vec<_> = System::events()
+            .into_iter()
+            .filter_map(|record| match record.event {
+                Event::Utxo(crate::Event::PaymentSent(destination, outpoint, value)) => {
+                    Some((destination, outpoint, value))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(payments.len(), 2);
+        assert_eq!(payments[0].0, Destination::Pubkey(alice_pub_key));
+        assert_eq!(payments[1].2, 20);
+        // Both payments and the change in one transaction
+        let (_, output) = UtxoStore::<Test>::iter()
+            .find(|(_, output)| output.value == ALICE_GENESIS_BALANCE - 30)
+            .unwrap();
+        assert_eq!(output.destination, Destination::Pubkey(alice_pub_key));
+        assert!(UtxoStore::<Test>::contains_key(payments[0].1));
+        assert!(UtxoStore::<Test>::contains_key(payments[1].1));
+    })
+}
+
+#[test]
+fn test_consolidate() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice = H256::from(alice_pub_key);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let mut outputs: Vec<_> = [10, 40, 20, 30]
+            .iter()
+            .map(|value| TransactionOutput::new_pubkey(*value, alice))
+            .collect();
+        outputs.push(TransactionOutput::new_pubkey(
+            ALICE_GENESIS_BALANCE - 100,
+            alice,
+        ));
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs,
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(alice), tx));
+
+        assert_err!(
+            Utxo::consolidate(Origin::signed(alice), 1),
+            "at least two utxos have to be consolidated"
+        );
+        assert_err!(
+            Utxo::consolidate(Origin::signed(H256::from(karl_pub_key)), 10),
+            "Caller doesn't have enough UTXOs to consolidate"
+        );
+
+        // the smallest UTXOs are merged, up to `MaxConsolidationInputs`, without a fee
+        let post_info = Utxo::consolidate(Origin::signed(alice), 10).unwrap();
+        let weight = <<Test as crate::Config>::WeightInfo as crate::WeightInfo>::consolidate(
+            MaxConsolidationInputs::get(),
+        );
+        assert_eq!(post_info.actual_weight, Some(weight));
+        let outpoint = System::events()
+            .into_iter()
+            .find_map(|record| match record.event {
+                Event::Utxo(crate::Event::UtxosConsolidated(account, 3, outpoint)) => {
+                    assert_eq!(account, alice);
+                    Some(outpoint)
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            UtxoStore::<Test>::get(outpoint),
+            Some(TransactionOutput::new_pubkey(60, alice))
+        );
+        let mut values: Vec<Value> =
+            UtxoStore::<Test>::iter_values().map(|utxo| utxo.value).collect();
+        values.sort();
+        assert_eq!(values, vec![40, 60, ALICE_GENESIS_BALANCE - 100]);
+
+        // fewer UTXOs than the maximum are merged, the rest of the weight is refunded
+        let post_info = Utxo::consolidate(Origin::signed(alice), 2).unwrap();
+        assert_eq!(
+            post_info.actual_weight,
+            Some(<<Test as crate::Config>::WeightInfo as crate::WeightInfo>::consolidate(2))
+        );
+        assert!(UtxoStore::<Test>::iter_values().any(|utxo| utxo.value == 100));
+        assert_ok!(crate::pallet::try_state::<Test>());
+    })
+}
+
+#[test]
+fn test_send_to_address() {
+    let (mut test_ext, alice_pub_key, _karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        // `addr` is bech32-encoded, SCALE-encoded `Destination::Pubkey(alice_pub_key)`
+        let addr = "ml1qrft7juyfhl06emj4zzrue5ljs6q39n2jalr4c40rhtcur647n0kwueyfsn";
+
+        assert_err!(
+            Utxo::send_to_address(
+                Origin::signed(H256::from(alice_pub_key)),
+                0,
+                addr.as_bytes().to_vec(),
+            ),
+            "Value transferred must be larger than zero",
+        );
+
+        assert_err!(
+            Utxo::send_to_address(
+                Origin::signed(H256::from(alice_pub_key)),
+                ALICE_GENESIS_BALANCE * 10,
+                addr.as_bytes().to_vec(),
+            ),
+            "Caller doesn't have enough UTXOs",
+        );
+
+        // send 10 utxo to alice
+        assert_ok!(Utxo::send_to_address(
+            Origin::signed(H256::from(alice_pub_key)),
+            10,
+            addr.as_bytes().to_vec(),
+        ));
+
+        // try to transfer to scripthash
+        let addr = "ml1qvvknne0acfzfd2ewksccgrgl4qlhcwewq4gjm75mtcpg26al66d5l5sz9k";
+        assert_ok!(Utxo::send_to_address(
+            Origin::signed(H256::from(alice_pub_key)),
+            20,
+            addr.as_bytes().to_vec(),
+        ));
+
+        // SS58 address of alice's account
+        let addr = crate::address::encode_ss58(&H256::from(alice_pub_key), 42).unwrap();
+        assert_ok!(Utxo::send_to_address(
+            Origin::signed(H256::from(alice_pub_key)),
+            30,
+            addr,
+        ));
+        assert!(UtxoStore::<Test>::iter_values()
+            .any(|output| output.value == 30
+                && output.destination == Destination::Pubkey(alice_pub_key)));
+        let addr = crate::address::encode_ss58(&H256::from(alice_pub_key), 0).unwrap();
+        assert_err!(
+            Utxo::send_to_address(Origin::signed(H256::from(alice_pub_key)), 30, addr),
+            "SS58 address of another network",
+        );
+
+        // invalid length
+        let addr = "ml1qrft7juyfhl06emj4zzrue5ljs6q39n2jalr4c40rhtcur647n0kwue1yfsn";
+        assert_err!(
+            Utxo::send_to_address(
+                Origin::signed(H256::from(alice_pub_key)),
+                40,
+                addr.as_bytes().to_vec(),
+            ),
+            "Failed to decode address: invalid length",
+        );
+
+        // invalid character
+        let addr = "ml1qyzqqpqpäääypw";
+        assert_err!(
+            Utxo::send_to_address(
+                Origin::signed(H256::from(alice_pub_key)),
+                40,
+                addr.as_bytes().to_vec(),
+            ),
+            "Failed to decode address: invalid character",
+        );
+
+        // mixed case
+        let addr = "ml1qrft7juyfhl06emj4zzrue5ljs6q39n2JALR4c40rhtcur647n0kWUYEFSN";
+        assert_err!(
+            Utxo::send_to_address(
+                Origin::signed(H256::from(alice_pub_key)),
+                40,
+                addr.as_bytes().to_vec(),
+            ),
+            "Failed to decode address: mixed case",
+        );
+
+        // invalid checksum
+        let addr = "ml1qrft7juyfhl06emj4zzrue5ljs6q39n2jalr4c40rhtcur647n0kwueyf66";
+        assert_err!(
+            Utxo::send_to_address(
+                Origin::signed(H256::from(alice_pub_key)),
+                40,
+                addr.as_bytes().to_vec(),
+            ),
+            "Failed to decode address: invalid checksum",
+        );
+
+        // invalid HRP
+        let addr = "bc1qrft7juyfhl06emj4zzrue5ljs6q39n2jalr4c40rhtcur647n0kwueyfsn";
+        assert_err!(
+            Utxo::send_to_address(
+                Origin::signed(H256::from(alice_pub_key)),
+                40,
+                addr.as_bytes().to_vec(),
+            ),
+            "Failed to decode address: invalid HRP",
+        );
+    })
+}
+
+#[test]
+fn test_identity_destination() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let alice = H256::from(alice_pub_key);
+        let karl = H256::from(karl_pub_key);
+        let name = b"alice".to_vec();
+        IdentityNames::set(&vec![(alice, name.clone())]);
+
+        assert_err!(
+            Utxo::set_identity_destination(
+                Origin::signed(karl),
+                Some(Destination::Pubkey(karl_pub_key))
+            ),
+            "Caller has no identity"
+        );
+        assert_err!(
+            Utxo::set_identity_destination(
+                Origin::signed(alice),
+                Some(Destination::CreatePP(vec![], vec![]))
+            ),
+            "Identity destination must be a pubkey, script hash or taproot"
+        );
+        // The name doesn't resolve before a destination is set for it
+        assert!(Utxo::send_to_address(Origin::signed(alice), 10, name.clone()).is_err());
+
+        // Payments to alice's name go to karl's pubkey
+        assert_ok!(Utxo::set_identity_destination(
+            Origin::signed(alice),
+            Some(Destination::Pubkey(karl_pub_key))
+        ));
+        assert_ok!(Utxo::send_to_address(
+            Origin::signed(alice),
+            10,
+            name.clone()
+        ));
+        assert!(UtxoStore::<Test>::iter_values()
+            .any(|output| output.value == 10
+                && output.destination == Destination::Pubkey(karl_pub_key)));
+
+        // Karl can't take the name over while alice holds it
+        IdentityNames::set(&vec![(alice, name.clone()), (karl, name.clone())]);
+        assert_err!(
+            Utxo::set_identity_destination(
+                Origin::signed(karl),
+                Some(Destination::Pubkey(karl_pub_key))
+            ),
+            "Identity name is registered by another account"
+        );
+
+        // Once alice loses the name it no longer resolves and karl can take it over
+        IdentityNames::set(&vec![(karl, name.clone())]);
+        assert!(Utxo::send_to_address(Origin::signed(alice), 20, name.clone()).is_err());
+        assert_ok!(Utxo::set_identity_destination(
+            Origin::signed(karl),
+            Some(Destination::Pubkey(alice_pub_key))
+        ));
+        assert_ok!(Utxo::send_to_address(
+            Origin::signed(alice),
+            20,
+            name.clone()
+        ));
+        assert!(UtxoStore::<Test>::iter_values()
+            .any(|output| output.value == 20
+                && output.destination == Destination::Pubkey(alice_pub_key)));
+
+        // Removing the destination stops resolving the name
+        assert_ok!(Utxo::set_identity_destination(Origin::signed(karl), None));
+        assert!(crate::identity::identity_destination::<Test>(&name).is_none());
+        IdentityNames::set(&vec![]);
+    })
+}
+
+#[test]
+fn test_tx_builder_pays_to_address() {
+    execute_with_alice(|alice_pub_key| {
+        // `addr` is bech32-encoded, SCALE-encoded `Destination::Pubkey(alice_pub_key)`
+        let addr = "ml1qrft7juyfhl06emj4zzrue5ljs6q39n2jalr4c40rhtcur647n0kwueyfsn";
+        let (utxo0, hash0) = genesis_utxo();
+        let tx = TxBuilder::new()
+            .add_input_from_utxo(hash0, utxo0)
+            .pay_to_address(10, addr.as_bytes())
+            .unwrap()
+            .add_change(H256::from(alice_pub_key), 50)
+            .unwrap()
+            .finalize_and_sign()
+            .unwrap();
+        assert_eq!(
+            tx.outputs,
+            vec![
+                TransactionOutput::new_pubkey(10, H256::from(alice_pub_key)),
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 60,
+                    H256::from(alice_pub_key)
+                ),
+            ]
+        );
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert!(UtxoStore::<Test>::contains_key(tx.outpoint(1)));
+    })
+}
+
+#[test]
+fn test_tx_builder_token_change() {
+    execute_with_alice(|alice_pub_key| {
+        let addr = "ml1qrft7juyfhl06emj4zzrue5ljs6q39n2jalr4c40rhtcur647n0kwueyfsn";
+        let alice = H256::from(alice_pub_key);
+        let token_id = TokenId::new(&TransactionInput::new_empty(H256::zero()));
+        let tokens = |amount| OutputData::TokenTransferV1 {
+            token_id: token_id.clone(),
+            amount,
+        };
+        let builder = TxBuilder::new()
+            .add_input_from_utxo(
+                H256::repeat_byte(1),
+                TransactionOutput::new_pubkey(100, alice),
+            )
+            .add_input_from_utxo(
+                H256::repeat_byte(2),
+                TransactionOutput::new_p2pk_with_data(0, alice, tokens(1_000)),
+            );
+
+        // The tokens go with the last payment only
+        assert_err!(
+            builder.clone().set_token_transfer(token_id.clone(), 300),
+            "no payment to carry the tokens"
+        );
+        let builder = builder
+            .pay_to_address(0, addr.as_bytes())
+            .unwrap()
+            .set_token_transfer(token_id.clone(), 300)
+            .unwrap();
+        assert_err!(
+            builder.clone().add_change(alice, 101),
+            "insufficient funds for the payments"
+        );
+        assert_err!(
+            builder
+                .clone()
+                .set_token_transfer(token_id.clone(), 1_001)
+                .unwrap()
+                .add_change(alice, 0),
+            "insufficient tokens"
+        );
+
+        let tx = builder.add_change(alice, 10).unwrap().finalize_and_sign().unwrap();
+        assert_eq!(
+            tx.outputs,
+            vec![
+                TransactionOutput::new_p2pk_with_data(0, alice, tokens(300)),
+                TransactionOutput::new_p2pk_with_data(0, alice, tokens(700)),
+                TransactionOutput::new_pubkey(90, alice),
+            ]
+        );
+        assert!(tx.inputs.iter().all(|input| !input.witness.is_empty()));
+
+        // Only inputs locked to a key can be signed
+        assert_err!(
+            TxBuilder::new()
+                .add_input_from_utxo(H256::zero(), TransactionOutput::new_script_hash(10, alice))
+                .add_change(alice, 0)
+                .unwrap()
+                .finalize_and_sign(),
+            "input cannot be signed"
+        );
+    })
+}
+
+#[test]
+fn test_check_duplicate_spend() {
+    use crate::extension::{CheckDuplicateSpend, DUPLICATE_SPEND};
+    use sp_runtime::traits::SignedExtension;
+    use sp_runtime::transaction_validity::InvalidTransaction;
+
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0.clone()],
+            outputs: vec![TransactionOutput::new_pubkey(50, H256::from(alice_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        let call = Call::Utxo(crate::Call::spend(tx.clone()));
+        let (alice, karl) = (H256::from(alice_pub_key), H256::repeat_byte(1));
+        let info = Default::default();
+
+        // The spent outpoints are tagged per signer
+        let valid = CheckDuplicateSpend::<Test>::new().validate(&alice, &call, &info, 0).unwrap();
+        assert_eq!(valid.provides, crate::extension::spend_tags(&alice, &tx));
+        assert_ne!(valid.provides, crate::extension::spend_tags(&karl, &tx));
+
+        // The second spend of the outpoint by the same signer is rejected until the block ends
+        assert_ok!(CheckDuplicateSpend::<Test>::new().pre_dispatch(&alice, &call, &info, 0));
+        assert_eq!(
+            CheckDuplicateSpend::<Test>::new().pre_dispatch(&alice, &call, &info, 0),
+            Err(InvalidTransaction::Custom(DUPLICATE_SPEND).into())
+        );
+        assert_ok!(CheckDuplicateSpend::<Test>::new().pre_dispatch(&karl, &call, &info, 0));
+        <Utxo as frame_support::traits::Hooks<u64>>::on_finalize(1);
+        assert_ok!(CheckDuplicateSpend::<Test>::new().pre_dispatch(&alice, &call, &info, 0));
+    })
+}
+
+#[test]
+fn test_send_to_address_twice_in_block() {
+    let (mut test_ext, alice_pub_key, _karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let alice = H256::from(alice_pub_key);
+        // `addr` is bech32-encoded, SCALE-encoded `Destination::Pubkey(alice_pub_key)`
+        let addr = "ml1qrft7juyfhl06emj4zzrue5ljs6q39n2jalr4c40rhtcur647n0kwueyfsn";
+
+        assert_ok!(Utxo::send_to_address(
+            Origin::signed(alice),
+            10,
+            addr.as_bytes().to_vec(),
+        ));
+        let first = crate::ReservedOutpoints::<Test>::get();
+        assert!(!first.is_empty());
+
+        // The second send spends the outputs of the first one, never its inputs
+        assert_ok!(Utxo::send_to_address(
+            Origin::signed(alice),
+            ALICE_GENESIS_BALANCE - 10,
+            addr.as_bytes().to_vec(),
+        ));
+        let reserved = crate::ReservedOutpoints::<Test>::get();
+        assert!(reserved.len() > first.len());
+        assert!(reserved[first.len()..].iter().all(|outpoint| !first.contains(outpoint)));
+
+        <Utxo as frame_support::traits::Hooks<u64>>::on_finalize(1);
+        assert!(crate::ReservedOutpoints::<Test>::get().is_empty());
+    })
+}
+
+proptest! {
+    #[test]
+    fn prop_gen_block_time_real_works(bt in gen_block_time_real()) {
+        // This generator should not sample block-based time.
+        prop_assert!(!bt.is_blocks());
+    }
+
+    #[test]
+    fn prop_time_lock_realtime_with_script(
+        script_lock_time in gen_block_time_real(),
+        tx_lock_time in gen_block_time_real(),
+        current_time in gen_block_time_real(),
+    ) {
+        let result = execute_with_alice(|alice| {
+            // Convert seconds to milliseconds
+            Timestamp::set_timestamp(current_time.as_u64() * 1000);
+
+            let (utxo0, input0) = tx_input_gen_no_signature();
+            let script = Builder::new()
+                .push_int(script_lock_time.as_u64() as i64)
+                .push_opcode(opc::OP_CLTV)
+                .into_script();
+            let script_hash: H256 = BlakeTwo256::hash(script.as_ref());
+            let tx1 = Transaction {
+                inputs: vec![input0],
+                outputs: vec![TransactionOutput::new_script_hash(ALICE_GENESIS_BALANCE - 90, script_hash)],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo0], 0, &alice);
+            let outpoint = tx1.outpoint(0);
+            assert!(Utxo::spend(Origin::signed(H256::zero()), tx1).is_ok());
+
+            let tx2 = Transaction {
+                inputs: vec![TransactionInput::new_script(outpoint, script, Default::default())],
+                outputs: vec![TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - u32::MAX as Value, H256::from(alice))],
+                time_lock: tx_lock_time,
+                expires_at: None,
+            };
+            Utxo::spend(Origin::signed(H256::zero()), tx2)
+        });
+
+        // The transaction should be accepted if and only if:
+        // current time >= transaction lock time >= script lock time
+        let model = current_time.as_u64() >= tx_lock_time.as_u64()
+            && tx_lock_time.as_u64() >= script_lock_time.as_u64();
+        prop_assert_eq!(result.is_ok(), model);
+    }
+
+    #[test]
+    fn prop_time_lock_realtime_monotonic(
+        tx_lock_time in gen_block_time_real(),
+        time0 in gen_block_time_real(),
+        time1 in gen_block_time_real(),
+    ) {
+        // Make sure time0 and time1 are in order
+        let (time0, time1) = (
+            std::cmp::min_by_key(time0, time1, RawBlockTime::as_u64),
+            std::cmp::max_by_key(time0, time1, RawBlockTime::as_u64),
+        );
+        let (res0, res1) = execute_with_alice(|alice| {
+            let (utxo0, input0) = tx_input_gen_no_signature();
+            let tx = Transaction {
+                inputs: vec![input0],
+                outputs: vec![TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 50, H256::from(alice))],
+                time_lock: tx_lock_time,
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo0], 0, &alice);
+
+            Timestamp::set_timestamp(time0.as_u64() * 1000);
+            let res0 = crate::pallet::validate_transaction::<Test>(&tx);
+
+            Timestamp::set_timestamp(time1.as_u64() * 1000);
+            let res1 = crate::pallet::validate_transaction::<Test>(&tx);
+
+            (res0, res1)
+        });
+
+        // The flow of time cannot turn a valid transaction int an invalid one.
+        // This is an implication: If a transaction validates at time0, it validates at time1.
+        prop_assert!(!res0.is_ok() || res1.is_ok());
+
+        // Check the error message given if the transaction validation fails.
+        if let Err(e) = res0 {
+            prop_assert_eq!(e, "Time lock restrictions not satisfied");
+        }
+    }
+
+    #[test]
+    fn prop_time_lock_realtime_neighbourhood(before in gen_block_time_real()) {
+        let now = RawBlockTime::new(before.as_u64() + 1);
+        let after = RawBlockTime::new(now.as_u64() + 1);
+
+        let (res_before, res_now, res_after) = execute_with_alice(|alice| {
+            let (utxo0, input0) = tx_input_gen_no_signature();
+            let tx = Transaction {
+                inputs: vec![input0],
+                outputs: vec![TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 50, H256::from(alice))],
+                time_lock: now,
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo0], 0, &alice);
+
+            Timestamp::set_timestamp(before.as_u64() * 1000);
+            let res_before = crate::pallet::validate_transaction::<Test>(&tx);
+
+            Timestamp::set_timestamp(now.as_u64() * 1000);
+            let res_now = crate::pallet::validate_transaction::<Test>(&tx);
+
+            Timestamp::set_timestamp(after.as_u64() * 1000);
+            let res_after = crate::pallet::validate_transaction::<Test>(&tx);
+
+            (res_before, res_now, res_after)
+        });
+
+        prop_assert_eq!(res_before, Err("Time lock restrictions not satisfied"));
+        prop_assert!(res_now.is_ok());
+        prop_assert!(res_after.is_ok());
+    }
+
+    #[test]
+    fn prop_time_lock_realtime_overflow(
+        time in ((u64::MAX / 1000 + 1)..=u64::MAX).prop_map(RawBlockTime::new)
+    ) {
+        execute_with_alice(|alice| {
+            let (utxo0, input0) = tx_input_gen_no_signature();
+            let tx = Transaction {
+                inputs: vec![input0],
+                outputs: vec![TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 50, H256::from(alice))],
+                time_lock: time,
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo0], 0, &alice);
+
+            // Check validate_transaction does not crash due to time lock being close to u64::MAX
+            let _ = crate::pallet::validate_transaction::<Test>(&tx);
+        });
+    }
+}
+
+// Testing token creation:
+use crate::tokens::{NftDataHash, NftRoyalty, TokenId};
+use rand::Rng;
+
+fn build_random_vec(len: usize) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    let mut vec = Vec::with_capacity(len);
+    for _ in 0..len {
+        vec.push(rng.gen::<u8>());
+    }
+    vec
+}
+
+#[test]
+// Simple creation of tokens
+fn test_token_issuance() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let output_new = TransactionOutput {
+            value: ALICE_GENESIS_BALANCE - TokenIssuanceFee::get(),
+            destination: Destination::Pubkey(alice_pub_key),
+            data: Some(OutputData::TokenIssuanceV1 {
+                //token_id: TokenId::new_asset(first_input_hash),
+                token_ticker: "BensT".as_bytes().to_vec(),
+                amount_to_issue: 1_000_000_000,
+                number_of_decimals: 2,
+                metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+            }),
+        };
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![output_new],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        let new_utxo_hash = tx.outpoint(0);
+        let token_id = TokenId::new(&tx.inputs[0]);
+        let (_, init_utxo) = genesis_utxo();
+        // submit tx - in the test it makes a new UTXO. Checks before that this UTXO has not created yet.
+        // After calling `Utxo::spend`, we should check that Storages successfully changed.
+        // If it successfully wrote a new UTXO in the Storage, tx goes through all verifications correctly.
+        assert!(UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
+        assert_eq!(Utxo::token_supply(&token_id), None);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert!(!UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
+        assert_eq!(Utxo::token_supply(&token_id), Some(1_000_000_000));
+        // Checking a new UTXO
+        assert!(UtxoStore::<Test>::contains_key(new_utxo_hash));
+
+        match UtxoStore::<Test>::get(new_utxo_hash).expect("The new output not found").data {
+            Some(OutputData::TokenIssuanceV1 {
+                //token_id,
+                token_ticker,
+                amount_to_issue,
+                number_of_decimals,
+                metadata_uri,
+            }) => {
+                //assert_eq!(TokenId::new_asset(first_input_hash), token_id);
+                assert_eq!(1_000_000_000, amount_to_issue);
+                assert_eq!("BensT".as_bytes().to_vec(), token_ticker);
+                assert_eq!(2, number_of_decimals);
+                assert_eq!("mintlayer.org".as_bytes().to_vec(), metadata_uri);
+            }
+            _ => panic!("Transaction data is corrupted"),
+        }
+    });
+}
+
+#[test]
+fn test_token_ticker_registry() {
+    execute_with_alice(|alice_pub_key| {
+        let issue = |input: TransactionInput, utxo: TransactionOutput<H256>, ticker: &[u8]| {
+            Transaction {
+                inputs: vec![input],
+                outputs: vec![TransactionOutput::new_p2pk_with_data(
+                    utxo.value - TokenIssuanceFee::get(),
+                    H256::from(alice_pub_key),
+                    OutputData::TokenIssuanceV1 {
+                        token_ticker: ticker.to_vec(),
+                        amount_to_issue: 1_000,
+                        number_of_decimals: 2,
+                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                    },
+                )],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo], 0, &alice_pub_key)
+        };
+
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                issue(input0.clone(), utxo0.clone(), b"mlt")
+            ),
+            "token ticker is reserved"
+        );
+
+        let tx = issue(input0, utxo0, b"BensT");
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let token_id = TokenId::new(&tx.inputs[0]);
+        assert_eq!(
+            Utxo::ticker_registry(b"BENST".to_vec()),
+            Some(token_id.clone())
+        );
+
+        let next = || {
+            (
+                TransactionInput::new_empty(tx.outpoint(0)),
+                tx.outputs[0].clone(),
+            )
+        };
+        let (input1, utxo1) = next();
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), issue(input1, utxo1, b"BENST")),
+            "token ticker is already taken"
+        );
+
+        // Without the uniqueness rule the ticker can be reused, the first token keeps it
+        UniqueTickers::set(false);
+        let (input1, utxo1) = next();
+        let mut reused = issue(input1, utxo1.clone(), b"BENST");
+        // the tokens of the first issuance stay with Alice
+        reused.outputs.push(TransactionOutput::new_p2pk_with_data(
+            0,
+            H256::from(alice_pub_key),
+            OutputData::TokenTransferV1 {
+                token_id: token_id.clone(),
+                amount: 1_000,
+            },
+        ));
+        let reused = reused.sign_unchecked(&[utxo1], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), reused));
+        assert_eq!(Utxo::ticker_registry(b"BENST".to_vec()), Some(token_id));
+        UniqueTickers::set(true);
+    });
+}
+
+#[test]
+// Simple creation of NFT
+fn test_nft_mint() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let data_hash = NftDataHash::Raw(vec![1, 2, 3, 4, 5]);
+        let token_id = TokenId::new(&input0);
+        let output = TransactionOutput {
+            value: 0,
+            destination: Destination::Pubkey(alice_pub_key),
+            data: Some(OutputData::NftMintV1 {
+                token_id: token_id.clone(),
+                data_hash: data_hash.clone(),
+                metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                media_type: None,
+                transferable: true,
+                royalty: None,
+            }),
+        };
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                output,
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 50,
+                    H256::from(alice_pub_key),
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        let new_utxo_hash = tx.outpoint(0);
+        let (_, init_utxo) = genesis_utxo();
+        assert!(UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert!(!UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
+        assert!(UtxoStore::<Test>::contains_key(new_utxo_hash));
+        assert_eq!(Utxo::token_supply(&token_id), Some(1));
+        assert_eq!(
+            NftUniqueDataHash::<Test>::get(&data_hash),
+            Some(new_utxo_hash)
+        );
+        assert_eq!(
+            data_hash,
+            UtxoStore::<Test>::get(new_utxo_hash)
+                .unwrap()
+                .data
+                .map(|x| match x {
+                    OutputData::NftMintV1 { data_hash, .. } => data_hash,
+                    _ => NftDataHash::Raw(Vec::new()),
+                })
+                .unwrap_or(NftDataHash::Raw(Vec::new()))
+        );
+    })
+}
+
+#[test]
+// NFT might be only unique, we can't create a few nft for one item
+fn test_nft_unique() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+
+        let mut nft_data = OutputData::NftMintV1 {
+            token_id: TokenId::new(&input0),
+            data_hash: NftDataHash::Hash32([255; 32]),
+            metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+            media_type: None,
+            transferable: true,
+            royalty: None,
+        };
+        let tx = Transaction {
+            inputs: vec![input0.clone()],
+            outputs: vec![
+                TransactionOutput {
+                    value: 0,
+                    destination: Destination::Pubkey(alice_pub_key),
+                    data: Some(nft_data.clone()),
+                },
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 50,
+                    H256::from(alice_pub_key),
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
+        let new_utxo_hash = tx.outpoint(1);
+        let (_, init_utxo) = genesis_utxo();
+        // Submit
+        assert!(UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert!(!UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
+        // Checking a new UTXO
+        assert!(UtxoStore::<Test>::contains_key(new_utxo_hash));
+        let new_utxo = tx.outputs[1].clone();
+
+        let new_input = TransactionInput::new_empty(new_utxo_hash.clone());
+        if let OutputData::NftMintV1 {
+            ref mut token_id, ..
+        } = nft_data
+        {
+            *token_id = TokenId::new(&new_input);
+        }
+        let tx = Transaction {
+            inputs: vec![new_input],
+            outputs: vec![
+                TransactionOutput {
+                    value: 0,
+                    destination: Destination::Pubkey(alice_pub_key),
+                    data: Some(nft_data.clone()),
+                },
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 100,
+                    H256::from(alice_pub_key),
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[new_utxo], 0, &alice_pub_key);
+        // Submit
+        assert!(UtxoStore::<Test>::contains_key(H256::from(new_utxo_hash)));
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            "digital data has already been minted"
+        );
+    });
+}
+
+#[test]
+// A soul-bound NFT can be burned, but never moved to another output
+fn test_nft_soul_bound() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let token_id = TokenId::new(&input0);
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(alice_pub_key),
+                    OutputData::NftMintV1 {
+                        token_id: token_id.clone(),
+                        data_hash: NftDataHash::Hash32([8; 32]),
+                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                        media_type: None,
+                        transferable: false,
+                        royalty: None,
+                    },
+                ),
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 10,
+                    H256::from(alice_pub_key),
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+
+        let utxos = [tx.outputs[0].clone(), tx.outputs[1].clone()];
+        let inputs = vec![
+            TransactionInput::new_empty(tx.outpoint(0)),
+            TransactionInput::new_empty(tx.outpoint(1)),
+        ];
+        let change =
+            TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 20, H256::from(alice_pub_key));
+
+        // Giving the badge to Karl is not allowed
+        let transfer = Transaction {
+            inputs: inputs.clone(),
+            outputs: vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(karl_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: token_id.clone(),
+                        amount: 1,
+                    },
+                ),
+                change.clone(),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&utxos, 0, &alice_pub_key)
+        .sign_unchecked(&utxos, 1, &alice_pub_key);
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), transfer),
+            "nft is not transferable"
+        );
+
+        // But it can be burned
+        let burn = Transaction {
+            inputs,
+            outputs: vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(alice_pub_key),
+                    OutputData::TokenBurnV1 {
+                        token_id: token_id.clone(),
+                        amount_to_burn: 1,
+                    },
+                ),
+                change,
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&utxos, 0, &alice_pub_key)
+        .sign_unchecked(&utxos, 1, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), burn));
+        assert!(!UtxoStore::<Test>::contains_key(tx.outpoint(0)));
+        assert_eq!(Utxo::token_circulating_supply(&token_id), Some(0));
+    });
+}
+
+#[test]
+// The unique data hash follows the NFT, and a spent issuance can't be repeated
+fn test_nft_transfer_and_reissuance() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let token_id = TokenId::new(&input0);
+        let data_hash = NftDataHash::Hash32([9; 32]);
+        let nft = |token_id: &TokenId, data_hash: &NftDataHash| OutputData::NftMintV1 {
+            token_id: token_id.clone(),
+            data_hash: data_hash.clone(),
+            metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+            media_type: None,
+            transferable: true,
+            royalty: None,
+        };
+        let mint = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(alice_pub_key),
+                    nft(&token_id, &data_hash),
+                ),
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 10,
+                    H256::from(alice_pub_key),
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), mint.clone()));
+
+        let transfer = Transaction {
+            inputs: vec![
+                TransactionInput::new_empty(mint.outpoint(0)),
+                TransactionInput::new_empty(mint.outpoint(1)),
+            ],
+            outputs: vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(karl_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: token_id.clone(),
+                        amount: 1,
+                    },
+                ),
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 20,
+                    H256::from(alice_pub_key),
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&mint.outputs, 0, &alice_pub_key)
+        .sign_unchecked(&mint.outputs, 1, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), transfer.clone()));
+        assert_eq!(
+            NftUniqueDataHash::<Test>::get(&data_hash),
+            Some(transfer.outpoint(0))
+        );
+        assert!(Utxo::token_issuance_transactions(&token_id).is_some());
+
+        // Neither the token id nor the digital data can be minted again
+        let remint = |data: OutputData| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(transfer.outpoint(1))],
+                outputs: vec![
+                    TransactionOutput::new_p2pk_with_data(0, H256::from(alice_pub_key), data),
+                    TransactionOutput::new_pubkey(
+                        ALICE_GENESIS_BALANCE - 30,
+                        H256::from(alice_pub_key),
+                    ),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&transfer.outputs[1..], 0, &alice_pub_key)
+        };
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                remint(nft(&token_id, &NftDataHash::Hash32([10; 32])))
+            ),
+            "token has already been issued"
+        );
+        let new_token_id = TokenId::new(&TransactionInput::new_empty(transfer.outpoint(1)));
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                remint(nft(&new_token_id, &data_hash))
+            ),
+            "digital data has already been minted"
+        );
+    });
+}
+
+#[test]
+fn test_nft_provenance() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let token_id = TokenId::new(&input0);
+        let mint = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(alice_pub_key),
+                OutputData::NftMintV1 {
+                    token_id: token_id.clone(),
+                    data_hash: NftDataHash::Hash32([11; 32]),
+                    metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                    media_type: None,
+                    transferable: true,
+                    royalty: None,
+                },
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), mint.clone()));
+        assert!(Utxo::nft_provenance(&token_id).is_empty());
+
+        // The NFT goes back and forth between Alice and Karl
+        let mut holder = mint;
+        let owners = [karl_pub_key, alice_pub_key, karl_pub_key];
+        let mut from = alice_pub_key;
+        for (block, to) in (1..).zip(owners) {
+            System::set_block_number(block);
+            let transfer = Transaction {
+                inputs: vec![TransactionInput::new_empty(holder.outpoint(0))],
+                outputs: vec![TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(to),
+                    OutputData::TokenTransferV1 {
+                        token_id: token_id.clone(),
+                        amount: 1,
+                    },
+                )],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&holder.outputs, 0, &from);
+            assert_ok!(Utxo::spend(Origin::signed(H256::zero()), transfer.clone()));
+            holder = transfer;
+            from = to;
+        }
+
+        // Only the latest `NftProvenanceDepth` transfers are kept
+        assert_eq!(
+            Utxo::nft_provenance(&token_id),
+            vec![
+                crate::NftTransfer {
+                    block_number: 2,
+                    from: Destination::Pubkey(karl_pub_key),
+                    to: Destination::Pubkey(alice_pub_key),
+                },
+                crate::NftTransfer {
+                    block_number: 3,
+                    from: Destination::Pubkey(alice_pub_key),
+                    to: Destination::Pubkey(karl_pub_key),
+                },
+            ]
+        );
+    });
+}
+
+#[test]
+// Selling an NFT has to pay the royalty to its beneficiary
+fn test_nft_royalty() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let beneficiary = Public::from_raw([7u8; 32]);
+        // Alice mints an NFT for Karl with 10% royalty, and at least 50
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let token_id = TokenId::new(&input0);
+        let mint = |minimum: Value| {
+            Transaction {
+                inputs: vec![input0.clone()],
+                outputs: vec![
+                    TransactionOutput::new_p2pk_with_data(
+                        0,
+                        H256::from(karl_pub_key),
+                        OutputData::NftMintV1 {
+                            token_id: token_id.clone(),
+                            data_hash: NftDataHash::Hash32([7; 32]),
+                            metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                            media_type: Some("image/png".as_bytes().to_vec()),
+                            transferable: true,
+                            royalty: Some(NftRoyalty {
+                                beneficiary,
+                                basis_points: 1_000,
+                                minimum,
+                            }),
+                        },
+                    ),
+                    TransactionOutput::new_pubkey(
+                        ALICE_GENESIS_BALANCE - 10,
+                        H256::from(alice_pub_key),
+                    ),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key)
+        };
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), mint(0)),
+            "nft royalty minimum must be nonzero"
+        );
+        let tx = mint(50);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert!(crate::NftRoyalties::<Test>::contains_key(&token_id));
+
+        // Karl sells the NFT to Alice for 1000
+        let utxos = [tx.outputs[0].clone(), tx.outputs[1].clone()];
+        let sale = |royalty_paid: Value| {
+            let mut outputs = vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(alice_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: token_id.clone(),
+                        amount: 1,
+                    },
+                ),
+                TransactionOutput::new_pubkey(1000, H256::from(karl_pub_key)),
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 2000,
+                    H256::from(alice_pub_key),
+                ),
+            ];
+            if royalty_paid > 0 {
+                outputs.push(TransactionOutput::new_pubkey(
+                    royalty_paid,
+                    H256::from(beneficiary),
+                ));
+            }
+            Transaction {
+                inputs: vec![
+                    TransactionInput::new_empty(tx.outpoint(0)),
+                    TransactionInput::new_empty(tx.outpoint(1)),
+                ],
+                outputs,
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&utxos, 0, &karl_pub_key)
+            .sign_unchecked(&utxos, 1, &alice_pub_key)
+        };
+
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), sale(0)),
+            "nft royalty not paid"
+        );
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), sale(99)),
+            "nft royalty not paid"
+        );
+
+        let sale = sale(100);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), sale.clone()));
+
+        // Alice gives the NFT to Karl, maybe paid elsewhere, owing the minimum
+        let utxos = [sale.outputs[0].clone(), sale.outputs[2].clone()];
+        let gift = |royalty_paid: Value| {
+            Transaction {
+                inputs: vec![
+                    TransactionInput::new_empty(sale.outpoint(0)),
+                    TransactionInput::new_empty(sale.outpoint(2)),
+                ],
+                outputs: vec![
+                    TransactionOutput::new_p2pk_with_data(
+                        0,
+                        H256::from(karl_pub_key),
+                        OutputData::TokenTransferV1 {
+                            token_id: token_id.clone(),
+                            amount: 1,
+                        },
+                    ),
+                    TransactionOutput::new_pubkey(
+                        ALICE_GENESIS_BALANCE - 3000,
+                        H256::from(alice_pub_key),
+                    ),
+                    TransactionOutput::new_pubkey(royalty_paid, H256::from(beneficiary)),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&utxos, 0, &alice_pub_key)
+            .sign_unchecked(&utxos, 1, &alice_pub_key)
+        };
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), gift(49)),
+            "nft royalty not paid"
+        );
+        let gift = gift(50);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), gift.clone()));
+
+        // Moving the NFT to the same destination owes nothing
+        let nft = gift.outputs[0].clone();
+        let keep = Transaction {
+            inputs: vec![TransactionInput::new_empty(gift.outpoint(0))],
+            outputs: vec![nft.clone()],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[nft], 0, &karl_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), keep));
+    });
+}
+
+#[test]
+fn test_nft_auction() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        // Events are only deposited from the first block on
+        System::set_block_number(1);
+        let (alice, karl) = (H256::from(alice_pub_key), H256::from(karl_pub_key));
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let token_id = TokenId::new(&input0);
+        let data_hash = NftDataHash::Hash32([3; 32]);
+        let mint = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    alice,
+                    OutputData::NftMintV1 {
+                        token_id: token_id.clone(),
+                        data_hash: data_hash.clone(),
+                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                        media_type: None,
+                        transferable: true,
+                        royalty: None,
+                    },
+                ),
+                TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 10, alice),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), mint.clone()));
+
+        // Alice escrows the NFT in an auction ending at block 10, and funds Karl
+        let nft = OutputData::TokenTransferV1 {
+            token_id: token_id.clone(),
+            amount: 1,
+        };
+        let open = |end: u64| {
+            Transaction {
+                inputs: vec![
+                    TransactionInput::new_empty(mint.outpoint(0)),
+                    TransactionInput::new_empty(mint.outpoint(1)),
+                ],
+                outputs: vec![
+                    TransactionOutput::new_auction(alice, 100, end, nft.clone()),
+                    TransactionOutput::new_pubkey(1_000, karl),
+                    TransactionOutput::new_pubkey(ALICE_GENESIS_BALANCE - 1_020, alice),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&mint.outputs, 0, &alice_pub_key)
+            .sign_unchecked(&mint.outputs, 1, &alice_pub_key)
+        };
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), open(1)),
+            "auction must end in the future"
+        );
+        let open = open(10);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), open.clone()));
+        let auction = open.outpoint(0);
+        assert_eq!(NftUniqueDataHash::<Test>::get(&data_hash), Some(auction));
+        assert!(System::events()
+            .iter()
+            .any(|record| record.event == Event::Utxo(crate::Event::AuctionOpened(auction, 10))));
+
+        let bid = |outpoint: H256, utxo: &TransactionOutput<H256>, value: Value, key: &Public| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(outpoint)],
+                outputs: vec![
+                    TransactionOutput::new_bid(value, auction, H256::from(*key)),
+                    TransactionOutput::new_pubkey(utxo.value - value - 10, H256::from(*key)),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo.clone()], 0, key)
+        };
+        let (karl_outpoint, karl_utxo) = (open.outpoint(1), open.outputs[1].clone());
+        let (alice_outpoint, alice_utxo) = (open.outpoint(2), open.outputs[2].clone());
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                bid(karl_outpoint, &karl_utxo, 50, &karl_pub_key)
+            ),
+            "bid is below the reserve price"
+        );
+        let alice_bid = bid(alice_outpoint, &alice_utxo, 150, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), alice_bid.clone()));
+        assert_eq!(Utxo::best_bid(auction), Some(alice_bid.outpoint(0)));
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                bid(karl_outpoint, &karl_utxo, 150, &karl_pub_key)
+            ),
+            "bid must exceed the best bid"
+        );
+
+        // Outbidding refunds the previous best bid
+        let karl_bid = bid(karl_outpoint, &karl_utxo, 200, &karl_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), karl_bid.clone()));
+        assert_eq!(Utxo::best_bid(auction), Some(karl_bid.outpoint(0)));
+        assert!(UtxoStore::<Test>::get(alice_bid.outpoint(0)).is_none());
+        let refund = BlakeTwo256::hash_of(&(&alice_bid.outpoint(0), "bid refund"));
+        assert_eq!(
+            UtxoStore::<Test>::get(refund),
+            Some(TransactionOutput::new_pubkey(150, alice))
+        );
+        assert_eq!(crate::OutputRefs::<Test>::get(alice_bid.outpoint(0)), None);
+        assert!(crate::UtxoCreationTime::<Test>::get(alice_bid.outpoint(0)).is_none());
+        assert!(crate::UtxoCreationTime::<Test>::get(refund).is_some());
+        assert_ok!(crate::pallet::try_state::<Test>());
+
+        // Neither the auction nor the bids can be spent
+        let escrowed = [
+            (
+                auction,
+                &open.outputs[0],
+                TransactionOutput::new_p2pk_with_data(0, karl, nft.clone()),
+            ),
+            (
+                karl_bid.outpoint(0),
+                &karl_bid.outputs[0],
+                TransactionOutput::new_pubkey(1, karl),
+            ),
+        ];
+        for (outpoint, utxo, output) in escrowed {
+            let tx = Transaction {
+                inputs: vec![TransactionInput::new_empty(outpoint)],
+                outputs: vec![output],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo.clone()], 0, &karl_pub_key);
+            frame_support::assert_err_ignore_postinfo!(
+                Utxo::spend(Origin::signed(H256::zero()), tx),
+                "cannot spend an auction utxo."
+            );
+        }
+
+        System::set_block_number(11);
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                bid(
+                    karl_bid.outpoint(1),
+                    &karl_bid.outputs[1],
+                    300,
+                    &karl_pub_key
+                )
+            ),
+            "auction has ended"
+        );
+
+        // The NFT goes to Karl and his bid to Alice
+        crate::auction::settle_auctions::<Test>(10);
+        assert!(UtxoStore::<Test>::get(auction).is_none());
+        assert!(UtxoStore::<Test>::get(karl_bid.outpoint(0)).is_none());
+        assert_eq!(Utxo::best_bid(auction), None);
+        let sale = BlakeTwo256::hash_of(&(&auction, "auction sale"));
+        assert_eq!(
+            UtxoStore::<Test>::get(sale),
+            Some(TransactionOutput::new_p2pk_with_data(0, karl, nft))
+        );
+        assert_eq!(NftUniqueDataHash::<Test>::get(&data_hash), Some(sale));
+        for settled in [auction, karl_bid.outpoint(0)] {
+            assert_eq!(crate::OutputRefs::<Test>::get(settled), None);
+            assert!(crate::UtxoCreationTime::<Test>::get(settled).is_none());
+        }
+        let proceeds = BlakeTwo256::hash_of(&(&auction, "auction proceeds"));
+        assert_eq!(
+            UtxoStore::<Test>::get(proceeds),
+            Some(TransactionOutput::new_pubkey(200, alice))
+        );
+        assert!(System::events().iter().any(|record| record.event
+            == Event::Utxo(crate::Event::AuctionSettled(
+                auction,
+                Some(karl_bid.outpoint(0))
+            ))));
+        assert_ok!(crate::pallet::try_state::<Test>());
+    });
+}
+
+#[test]
+fn test_order_fill() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let (alice, karl) = (H256::from(alice_pub_key), H256::from(karl_pub_key));
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let token_id = TokenId::new(&input0);
+        let value = ALICE_GENESIS_BALANCE - TokenIssuanceFee::get();
+        let issue = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_p2pk_with_data(
+                value,
+                alice,
+                OutputData::TokenIssuanceV1 {
+                    token_ticker: "BensT".as_bytes().to_vec(),
+                    amount_to_issue: 1_000,
+                    number_of_decimals: 2,
+                    metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                },
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), issue.clone()));
+
+        // Alice offers her 1000 tokens for 500 MLT, and funds Karl
+        let tokens = |amount| OutputData::TokenTransferV1 {
+            token_id: token_id.clone(),
+            amount,
+        };
+        let place = |order: TransactionOutput<H256>| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(issue.outpoint(0))],
+                outputs: vec![
+                    order,
+                    TransactionOutput::new_pubkey(1_000, karl),
+                    TransactionOutput::new_pubkey(value - 1_000, alice),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&issue.outputs, 0, &alice_pub_key)
+        };
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(
+                Origin::signed(H256::zero()),
+                place(TransactionOutput::new_order(
+                    0,
+                    Some(tokens(1_000)),
+                    alice,
+                    Some(token_id.clone()),
+                    500
+                ))
+            ),
+            "order must exchange different assets"
+        );
+        let placed = place(TransactionOutput::new_order(
+            0,
+            Some(tokens(1_000)),
+            alice,
+            None,
+            500,
+        ));
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), placed.clone()));
+
+        // Karl takes 400 tokens for 200 MLT, the rest is offered for 300 MLT
+        let (order, karl_utxo) = (placed.outpoint(0), placed.outputs[1].clone());
+        let fill = |payment: Value, remainder_ask: Value| {
+            Transaction {
+                inputs: vec![
+                    TransactionInput::new_empty(order),
+                    TransactionInput::new_empty(placed.outpoint(1)),
+                ],
+                outputs: vec![
+                    TransactionOutput::new_p2pk_with_data(0, karl, tokens(400)),
+                    TransactionOutput::new_order(0, Some(tokens(600)), alice, None, remainder_ask),
+                    TransactionOutput::new_pubkey(payment, alice),
+                    TransactionOutput::new_pubkey(1_000 - payment, karl),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(
+                &[placed.outputs[0].clone(), karl_utxo.clone()],
+                1,
+                &karl_pub_key,
+            )
+        };
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), fill(199, 300)),
+            "order maker is not paid in full"
+        );
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), fill(200, 299)),
+            "remainder order asks too little"
+        );
+        let filled = fill(200, 300);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), filled.clone()));
+        assert!(UtxoStore::<Test>::get(order).is_none());
+        assert_eq!(
+            UtxoStore::<Test>::get(filled.outpoint(1)),
+            Some(filled.outputs[1].clone())
+        );
+
+        // Only Alice can cancel the remainder
+        let cancel = |key: &Public| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(filled.outpoint(1))],
+                outputs: vec![TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(*key),
+                    tokens(600),
+                )],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[filled.outputs[1].clone()], 0, key)
+        };
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), cancel(&karl_pub_key)),
+            "signature must be valid"
+        );
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            cancel(&alice_pub_key)
+        ));
+        assert_ok!(crate::pallet::try_state::<Test>());
+    });
+}
+
+// This macro using for the fast creation and sending a tx
+macro_rules! test_tx {
+    ($data: ident, $checking: tt, $err: expr) => {
+        execute_with_alice(|alice_pub_key| {
+            let (utxo0, input0) = tx_input_gen_no_signature();
+            let output_new = TransactionOutput {
+                value: ALICE_GENESIS_BALANCE - TokenIssuanceFee::get(),
+                destination: Destination::Pubkey(alice_pub_key),
+                data: Some($data.clone()),
+            };
+            let tx = Transaction {
+                inputs: vec![input0],
+                outputs: vec![output_new],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+            let new_utxo_hash = tx.outpoint(0);
+            let (_, init_utxo) = genesis_utxo();
+            // Send
+            assert!(UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
+            // We can check what error we are expecting
+            if stringify!($checking) == "Err" {
+                frame_support::assert_err_ignore_postinfo!(
+                    Utxo::spend(Origin::signed(H256::zero()), tx),
+                    $err
+                );
+                assert!(UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
+                assert!(!UtxoStore::<Test>::contains_key(new_utxo_hash));
+            } else if stringify!($checking) == "Ok" {
+                // We can check is that success
+                assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+                assert!(!UtxoStore::<Test>::contains_key(H256::from(init_utxo)));
+                assert!(UtxoStore::<Test>::contains_key(new_utxo_hash));
+            }
+        });
+    };
+}
+
+#[test]
+fn test_tokens_issuance_empty_ticker() {
+    // Ticker empty
+    let data = OutputData::TokenIssuanceV1 {
+        token_ticker: vec![],
+        amount_to_issue: 1_000_000_000,
+        number_of_decimals: 2,
+        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+    };
+    test_tx!(data, Err, "token ticker can't be empty");
+}
+
+#[test]
+fn test_tokens_issuance_too_big_ticker() {
+    // Ticker too long
+    let data = OutputData::TokenIssuanceV1 {
+        token_ticker: Vec::from([b"A"[0]; 10_000]),
+        amount_to_issue: 1_000_000_000,
+        number_of_decimals: 2,
+        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+    };
+    test_tx!(data, Err, "token ticker is too long");
+}
+
+#[test]
+fn test_tokens_issuance_amount_zero() {
+    // Amount to issue is zero
+    let data = OutputData::TokenIssuanceV1 {
+        token_ticker: b"BensT".to_vec(),
+        amount_to_issue: 0,
+        number_of_decimals: 2,
+        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+    };
+    test_tx!(data, Err, "output value must be nonzero");
+}
+
+#[test]
+fn test_tokens_issuance_too_big_decimals() {
+    // Number of decimals more than 18 numbers
+    let data = OutputData::TokenIssuanceV1 {
+        token_ticker: b"BensT".to_vec(),
+        amount_to_issue: 1_000_000_000,
+        number_of_decimals: 19,
+        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+    };
+    test_tx!(data, Err, "too long decimals");
+}
+
+#[test]
+fn test_tokens_issuance_empty_metadata() {
+    // metadata_uri empty
+    let data = OutputData::TokenIssuanceV1 {
+        token_ticker: b"BensT".to_vec(),
+        amount_to_issue: 1_000_000_000,
+        number_of_decimals: 18,
+        metadata_uri: vec![],
+    };
+    test_tx!(data, Ok, "");
+}
+
+#[test]
+fn test_tokens_issuance_too_long_metadata() {
+    // metadata_uri too long
+    let data = OutputData::TokenIssuanceV1 {
+        token_ticker: b"BensT".to_vec(),
+        amount_to_issue: 1_000_000_000,
+        number_of_decimals: 18,
+        metadata_uri: Vec::from([0u8; 10_000]),
+    };
+    test_tx!(data, Err, "token metadata uri is too long");
+}
+
+#[test]
+fn test_tokens_issuance_with_junk_data() {
+    // The data field of the maximum allowed length filled with random garbage
+    let mut rng = rand::thread_rng();
+    let garbage = build_random_vec(100);
+    let data = OutputData::TokenIssuanceV1 {
+        token_ticker: vec![0, 255, 254, 2, 1],
+        amount_to_issue: rng.gen::<u64>() as u128,
+        number_of_decimals: 18,
+        metadata_uri: garbage.clone(),
+    };
+    test_tx!(data, Err, "token ticker has none ascii characters");
+}
+
+#[test]
+fn test_tokens_issuance_with_corrupted_uri() {
+    let mut rng = rand::thread_rng();
+    let garbage = build_random_vec(100);
+    // garbage uri
+    let data = OutputData::TokenIssuanceV1 {
+        token_ticker: b"BensT".to_vec(),
+        amount_to_issue: rng.gen::<u64>() as u128,
+        number_of_decimals: 18,
+        metadata_uri: garbage,
+    };
+    test_tx!(data, Err, "metadata uri has none ascii characters");
+}
+
+#[test]
+fn test_two_token_creation_in_one_tx() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(alice_pub_key),
+                    OutputData::TokenIssuanceV1 {
+                        token_ticker: b"Enric".to_vec(),
+                        amount_to_issue: 1_000_000_000,
+                        number_of_decimals: 2,
+                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                    },
+                ),
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(alice_pub_key),
+                    OutputData::TokenIssuanceV1 {
+                        token_ticker: b"Ben".to_vec(),
+                        amount_to_issue: 2_000_000_000,
+                        number_of_decimals: 3,
+                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                    },
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            "this id can't be used for a new token"
+        );
+    });
+}
+
+// Let's wrap common acts
+fn test_tx_issuance_for_transfer<F>(expecting_err_msg: &'static str, test_func: F)
+where
+    F: Fn(TokenId, Public, Public, H256, TransactionOutput<H256>) -> Transaction<H256>,
+{
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        // Alice issue 1_000_000_000 MLS-01, and send them to Karl
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0.clone()],
+            outputs: vec![
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 90,
+                    H256::from(alice_pub_key),
+                ),
+                TransactionOutput::new_p2pk_with_data(
+                    10,
+                    H256::from(karl_pub_key),
+                    OutputData::TokenIssuanceV1 {
+                        token_ticker: "BensT".as_bytes().to_vec(),
+                        amount_to_issue: 1_000_000_000,
+                        // Should be not more than 18 numbers
+                        number_of_decimals: 2,
+                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                    },
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
+        let token_id = TokenId::new(&tx.inputs[0]);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+
+        let token_utxo_hash = tx.outpoint(1);
+        let token_utxo: TransactionOutput<H256> = tx.outputs[1].clone();
+        // Call a test func
+        let tx = test_func(
+            token_id,
+            alice_pub_key,
+            karl_pub_key,
+            token_utxo_hash,
+            token_utxo,
+        );
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            expecting_err_msg
+        );
+    });
+}
+
+#[test]
+fn test_token_transfer_with_wrong_token_id() {
+    let test_fun = Box::new(
+        move |_token_id,
+              alice_pub_key,
+              karl_pub_key,
+              token_utxo_hash,
+              token_utxo: TransactionOutput<H256>| {
+            let input = TransactionInput::new_empty(token_utxo_hash);
+            Transaction {
+                inputs: vec![input.clone()],
+                outputs: vec![TransactionOutput::new_p2pk_with_data(
+                    ALICE_GENESIS_BALANCE - u64::MAX as Value,
+                    H256::from(alice_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: TokenId::new(&input),
+                        amount: 100_000_000,
+                    },
+                )],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[token_utxo.clone()], 0, &karl_pub_key)
+        },
+    );
+    test_tx_issuance_for_transfer("input for the token not found", test_fun);
+}
+
+#[test]
+fn test_token_transfer_exceed_amount_tokens() {
+    let test_fun = Box::new(
+        move |token_id,
+              alice_pub_key,
+              karl_pub_key,
+              token_utxo_hash,
+              token_utxo: TransactionOutput<H256>| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(token_utxo_hash)],
+                outputs: vec![TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(alice_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id,
+                        amount: 1_000_000_001,
+                    },
+                )],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[token_utxo.clone()], 0, &karl_pub_key)
+        },
+    );
+    test_tx_issuance_for_transfer("output value must not exceed input value", test_fun);
+}
+
+#[test]
+fn test_token_transfer_exceed_amount_mlt() {
+    let test_fun = Box::new(
+        move |token_id: TokenId,
+              alice_pub_key,
+              karl_pub_key,
+              token_utxo_hash,
+              token_utxo: TransactionOutput<H256>| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(token_utxo_hash)],
+                outputs: vec![TransactionOutput::new_p2pk_with_data(
+                    1_000_000_000,
+                    H256::from(alice_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: token_id.clone(),
+                        amount: 1_000_000_000,
+                    },
+                )],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[token_utxo.clone()], 0, &karl_pub_key)
+        },
+    );
+    test_tx_issuance_for_transfer("output value must not exceed input value", test_fun);
+}
+
+#[test]
+fn test_token_transfer_send_part_others_burn() {
+    let test_fun = Box::new(
+        move |token_id: TokenId,
+              alice_pub_key,
+              karl_pub_key,
+              token_utxo_hash,
+              token_utxo: TransactionOutput<H256>| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(token_utxo_hash)],
+                outputs: vec![
+                    // Send only 30%, let's forget about another 70% of tokens
+                    TransactionOutput::new_p2pk_with_data(
+                        0,
+                        H256::from(alice_pub_key),
+                        OutputData::TokenTransferV1 {
+                            token_id: token_id.clone(),
+                            amount: 300_000_000,
+                        },
+                    ),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[token_utxo.clone()], 0, &karl_pub_key)
+        },
+    );
+    test_tx_issuance_for_transfer("output value must not exceed input value", test_fun);
+}
+
+#[test]
+fn test_token_transfer() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        // Alice issue 1_000_000_000 MLS-01, and send them to Karl
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - 90,
+                    H256::from(alice_pub_key),
+                ),
+                TransactionOutput::new_p2pk_with_data(
+                    90,
+                    H256::from(karl_pub_key),
+                    OutputData::TokenIssuanceV1 {
+                        token_ticker: "BensT".as_bytes().to_vec(),
+                        amount_to_issue: 1_000_000_000,
+                        // Should be not more than 18 numbers
+                        number_of_decimals: 2,
+                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                    },
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
+        let token_id = TokenId::new(&tx.inputs[0]);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let token_utxo_hash = tx.outpoint(1);
+        let token_utxo = tx.outputs[1].clone();
+
+        // Let's send 300_000_000 and rest back
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(token_utxo_hash)],
+            outputs: vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(alice_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: token_id.clone(),
+                        amount: 300_000_000,
+                    },
+                ),
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(karl_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: token_id.clone(),
+                        amount: 700_000_000,
+                    },
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[token_utxo.clone()], 0, &karl_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let alice_tokens_utxo_hash = tx.outpoint(0);
+        let karl_tokens_utxo_hash = tx.outpoint(1);
+        let karl_tokens_utxo = tx.outputs[1].clone();
+        assert!(!UtxoStore::<Test>::contains_key(H256::from(
+            token_utxo_hash
+        )));
+        // The issuance is spent, but the token stays registered
+        assert_eq!(Utxo::token_id_issuance(token_utxo_hash), None);
+        assert!(Utxo::token_issuance_transactions(&token_id).is_some());
+        assert!(UtxoStore::<Test>::contains_key(alice_tokens_utxo_hash));
+        assert!(UtxoStore::<Test>::contains_key(karl_tokens_utxo_hash));
+
+        // should be success
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(karl_tokens_utxo_hash)],
+            outputs: vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(alice_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: token_id.clone(),
+                        amount: 400_000_000,
+                    },
+                ),
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(karl_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: token_id.clone(),
+                        amount: 300_000_000,
+                    },
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[karl_tokens_utxo], 0, &karl_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert_eq!(
+            300_000_000,
+            UtxoStore::<Test>::get(alice_tokens_utxo_hash)
+                .unwrap()
+                .data
+                .map(|x| match x {
+                    OutputData::TokenTransferV1 { amount, .. } => amount,
+                    _ => 0,
+                })
+                .unwrap_or(0)
+        );
+
+        let new_alice_tokens_utxo_hash = tx.outpoint(0);
+        assert!(UtxoStore::<Test>::contains_key(new_alice_tokens_utxo_hash));
+        assert_eq!(
+            400_000_000,
+            UtxoStore::<Test>::get(new_alice_tokens_utxo_hash)
+                .unwrap()
+                .data
+                .map(|x| match x {
+                    OutputData::TokenTransferV1 { amount, .. } => amount,
+                    _ => 0,
+                })
+                .unwrap_or(0)
+        );
+    });
+}
+
+// todo: This part isn't fully tested, left for the next PR
+// #[test]
+// fn test_nft_transferring() {
+//     let (mut test_ext, alice_pub_key, karl_pub_key) = new_test_ext_and_keys();
+//     test_ext.execute_with(|| {
+//         let token_id = TokenId::new_asset(H256::random());
+//         // Alice issue 1000 MLS-01, and send them to Karl and the rest back to herself
+//         let (utxo0, input0) = tx_input_gen_no_signature();
+//         let data_hash = NftDataHash::Raw(build_random_vec(32));
+//         let tx = Transaction {
+//             inputs: vec![input0],
+//             outputs: vec![
+//                 TransactionOutput::new_pubkey(90, H256::from(alice_pub_key)),
+//                 TransactionOutput::new_p2pk_with_data(
+//                     10,
+//                     H256::from(karl_pub_key),
+//                     OutputData::NftMintV1 {
+//                         token_id: token_id.clone(),
+//                         data_hash: data_hash.clone(),
+//                         metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+//                     },
+//                 ),
+//             ],
+//             time_lock: Default::default(),
+//         }
+//         .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
+//         assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+//         let token_utxo_hash = tx.outpoint(1);
+//         let token_utxo = tx.outputs[1].clone();
+//
+//         // Let's fail on wrong token id
+//         let tx = Transaction {
 //             inputs: vec![TransactionInput::new_empty(token_utxo_hash)],
 //             outputs: vec![TransactionOutput::new_p2pk_with_data(
 //                 0,
@@ -1511,22 +5607,1779 @@ fn test_token_transfer() {
 // }
 
 #[test]
-// Test tx where Input with token and without MLT, output has token (without MLT)
-fn test_token_creation_with_insufficient_fee() {
+// Test tx where Input with token and without MLT, output has token (without MLT)
+fn test_token_creation_with_insufficient_fee() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        // Alice issue 1000 MLS-01, and send them to Karl and the rest back to herself
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - u64::MAX as Value,
+                    H256::from(karl_pub_key),
+                ),
+                TransactionOutput::new_p2pk_with_data(
+                    crate::tokens::Mlt(99).to_munit(),
+                    H256::from(karl_pub_key),
+                    OutputData::TokenIssuanceV1 {
+                        token_ticker: "BensT".as_bytes().to_vec(),
+                        amount_to_issue: 1_000_000_000,
+                        number_of_decimals: 2,
+                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                    },
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
+
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let token_id = TokenId::new(&tx.inputs[0]);
+        let token_utxo_hash = tx.outpoint(1);
+        let token_utxo = tx.outputs[1].clone();
+        let tx = Transaction {
+            inputs: vec![
+                // Use here token issuance for example
+                TransactionInput::new_empty(token_utxo_hash),
+            ],
+            outputs: vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(karl_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id,
+                        amount: 1_000_000_000,
+                    },
+                ),
+                // One unit short of the issuance fee
+                TransactionOutput::new_p2pk_with_data(
+                    crate::tokens::Mlt(99).to_munit() - TokenIssuanceFee::get() + 1,
+                    H256::from(karl_pub_key),
+                    OutputData::TokenIssuanceV1 {
+                        token_ticker: b"Enric".to_vec(),
+                        amount_to_issue: 1_000_000_000,
+                        number_of_decimals: 2,
+                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                    },
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[token_utxo], 0, &karl_pub_key);
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            "insufficient fee"
+        );
+    });
+}
+
+#[test]
+fn test_nft_mint_with_insufficient_fee() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let mint = |fee: Value| {
+            Transaction {
+                inputs: vec![input0.clone()],
+                outputs: vec![
+                    TransactionOutput::new_p2pk_with_data(
+                        0,
+                        H256::from(alice_pub_key),
+                        OutputData::NftMintV1 {
+                            token_id: TokenId::new(&input0),
+                            data_hash: NftDataHash::Hash32([9; 32]),
+                            metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                            media_type: None,
+                            transferable: true,
+                            royalty: None,
+                        },
+                    ),
+                    TransactionOutput::new_pubkey(
+                        ALICE_GENESIS_BALANCE - fee,
+                        H256::from(alice_pub_key),
+                    ),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key)
+        };
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), mint(NftMintFee::get() - 1)),
+            "insufficient fee"
+        );
+        // Minting an NFT is charged with its own fee, not the token issuance one
+        assert!(NftMintFee::get() < TokenIssuanceFee::get());
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            mint(NftMintFee::get())
+        ));
+    });
+}
+
+#[test]
+fn test_transfer_and_issuance_in_one_tx() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        // Alice issue 1_000_000_000 MLS-01, and send them to Karl
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE
+                        - crate::tokens::Mlt(1000).to_munit()
+                        - TokenIssuanceFee::get(),
+                    H256::from(alice_pub_key),
+                ),
+                TransactionOutput::new_p2pk_with_data(
+                    crate::tokens::Mlt(1000).to_munit(),
+                    H256::from(karl_pub_key),
+                    OutputData::TokenIssuanceV1 {
+                        token_ticker: "BensT".as_bytes().to_vec(),
+                        amount_to_issue: 1_000_000_000,
+                        number_of_decimals: 2,
+                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                    },
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
+        let first_issuance_token_id = TokenId::new(&tx.inputs[0]);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let token_utxo_hash = tx.outpoint(1);
+        let token_utxo = tx.outputs[1].clone();
+
+        // Let's send 300_000_000 and rest back and create another token
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(token_utxo_hash)],
+            outputs: vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(alice_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: first_issuance_token_id.clone(),
+                        amount: 300_000_000,
+                    },
+                ),
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(karl_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: first_issuance_token_id.clone(),
+                        amount: 700_000_000,
+                    },
+                ),
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(karl_pub_key),
+                    OutputData::TokenIssuanceV1 {
+                        token_ticker: "Token".as_bytes().to_vec(),
+                        amount_to_issue: 5_000_000_000,
+                        // Should be not more than 18 numbers
+                        number_of_decimals: 12,
+                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                    },
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[token_utxo.clone()], 0, &karl_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let alice_transfer_utxo_hash = tx.outpoint(0);
+        let karl_transfer_utxo_hash = tx.outpoint(1);
+        let karl_issuance_utxo_hash = tx.outpoint(2);
+        assert!(!UtxoStore::<Test>::contains_key(H256::from(
+            token_utxo_hash
+        )));
+        assert!(UtxoStore::<Test>::contains_key(alice_transfer_utxo_hash));
+        assert!(UtxoStore::<Test>::contains_key(karl_transfer_utxo_hash));
+        assert!(UtxoStore::<Test>::contains_key(karl_issuance_utxo_hash));
+
+        // Let's check token transfer
+        UtxoStore::<Test>::get(alice_transfer_utxo_hash)
+            .unwrap()
+            .data
+            .map(|x| match x {
+                OutputData::TokenTransferV1 { token_id, amount } => {
+                    assert_eq!(token_id, first_issuance_token_id);
+                    assert_eq!(amount, 300_000_000);
+                }
+                _ => {
+                    panic!("corrupted data");
+                }
+            })
+            .unwrap();
+
+        UtxoStore::<Test>::get(karl_transfer_utxo_hash)
+            .unwrap()
+            .data
+            .map(|x| match x {
+                OutputData::TokenTransferV1 { token_id, amount } => {
+                    assert_eq!(token_id, first_issuance_token_id);
+                    assert_eq!(amount, 700_000_000);
+                }
+                _ => {
+                    panic!("corrupted data");
+                }
+            })
+            .unwrap();
+
+        // Let's check token issuance
+        UtxoStore::<Test>::get(karl_issuance_utxo_hash)
+            .unwrap()
+            .data
+            .map(|x| match x {
+                OutputData::TokenIssuanceV1 {
+                    token_ticker,
+                    amount_to_issue,
+                    number_of_decimals,
+                    metadata_uri,
+                } => {
+                    assert_eq!(token_ticker, "Token".as_bytes().to_vec());
+                    assert_eq!(amount_to_issue, 5_000_000_000);
+                    assert_eq!(number_of_decimals, 12);
+                    assert_eq!(metadata_uri, "mintlayer.org".as_bytes().to_vec());
+                }
+                _ => {
+                    panic!("corrupted data");
+                }
+            })
+            .unwrap();
+    });
+}
+
+#[test]
+fn test_transfer_for_multiple_tokens() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        //
+        // Issue token 1 and send all tokens to Karl
+        //
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![TransactionOutput::new_p2pk_with_data(
+                ALICE_GENESIS_BALANCE - crate::tokens::Mlt(100).to_munit(),
+                H256::from(karl_pub_key),
+                OutputData::TokenIssuanceV1 {
+                    token_ticker: "TKN1".as_bytes().to_vec(),
+                    amount_to_issue: 1_000_000_000,
+                    number_of_decimals: 2,
+                    metadata_uri: "tkn1.mintlayer.org".as_bytes().to_vec(),
+                },
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
+        let tkn1_token_id = TokenId::new(&tx.inputs[0]);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let tkn1_utxo_hash = tx.outpoint(0);
+        let tkn1_utxo = tx.outputs[0].clone();
+        //
+        // Issue token 2 and send all tokens to Alice
+        //
+        let input1 = TransactionInput::new_empty(tkn1_utxo_hash);
+        let tx = Transaction {
+            inputs: vec![input1],
+            outputs: vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(alice_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: tkn1_token_id.clone(),
+                        amount: 1_000_000_000,
+                    },
+                ),
+                TransactionOutput::new_p2pk_with_data(
+                    ALICE_GENESIS_BALANCE
+                        - crate::tokens::Mlt(100).to_munit()
+                        - TokenIssuanceFee::get(),
+                    H256::from(alice_pub_key),
+                    OutputData::TokenIssuanceV1 {
+                        token_ticker: "TKN2".as_bytes().to_vec(),
+                        amount_to_issue: 2_000_000_000,
+                        number_of_decimals: 4,
+                        metadata_uri: "tkn2.mintlayer.org".as_bytes().to_vec(),
+                    },
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[tkn1_utxo.clone()], 0, &karl_pub_key);
+        let tkn2_token_id = TokenId::new(&tx.inputs[0]);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let tkn1_utxo_hash = tx.outpoint(0);
+        let tkn2_utxo_hash = tx.outpoint(1);
+        //
+        // Issue token 3 and send all tokens to Karl
+        //
+        let input1 = TransactionInput::new_empty(tkn1_utxo_hash);
+        let input2 = TransactionInput::new_empty(tkn2_utxo_hash);
+        let prev_utxos = [tx.outputs[0].clone(), tx.outputs[1].clone()];
+        let tx = Transaction {
+            inputs: vec![input1, input2],
+            outputs: vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(karl_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: tkn1_token_id.clone(),
+                        amount: 1_000_000_000,
+                    },
+                ),
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(karl_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: tkn2_token_id.clone(),
+                        amount: 2_000_000_000,
+                    },
+                ),
+                TransactionOutput::new_p2pk_with_data(
+                    ALICE_GENESIS_BALANCE
+                        - crate::tokens::Mlt(100).to_munit()
+                        - 2 * TokenIssuanceFee::get(),
+                    H256::from(karl_pub_key),
+                    OutputData::TokenIssuanceV1 {
+                        token_ticker: "TKN3".as_bytes().to_vec(),
+                        amount_to_issue: 3_000_000_000,
+                        number_of_decimals: 6,
+                        metadata_uri: "tkn3.mintlayer.org".as_bytes().to_vec(),
+                    },
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&prev_utxos, 0, &alice_pub_key)
+        .sign_unchecked(&prev_utxos, 1, &alice_pub_key);
+        let tkn3_token_id = TokenId::new(&tx.inputs[0]);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let tkn1_utxo_hash = tx.outpoint(0);
+        let tkn2_utxo_hash = tx.outpoint(1);
+        let tkn3_utxo_hash = tx.outpoint(2);
+
+        //
+        // Transfer 3 kinds of tokens to Alice and check them all
+        //
+        let input1 = TransactionInput::new_empty(tkn1_utxo_hash);
+        let input2 = TransactionInput::new_empty(tkn2_utxo_hash);
+        let input3 = TransactionInput::new_empty(tkn3_utxo_hash);
+        let prev_utxos = [tx.outputs[0].clone(), tx.outputs[1].clone(), tx.outputs[2].clone()];
+        let tx = Transaction {
+            inputs: vec![input1, input2, input3],
+            outputs: vec![
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(alice_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: tkn1_token_id.clone(),
+                        amount: 1_000_000_000,
+                    },
+                ),
+                TransactionOutput::new_p2pk_with_data(
+                    0,
+                    H256::from(alice_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: tkn2_token_id.clone(),
+                        amount: 2_000_000_000,
+                    },
+                ),
+                TransactionOutput::new_p2pk_with_data(
+                    ALICE_GENESIS_BALANCE
+                        - crate::tokens::Mlt(100).to_munit()
+                        - 2 * TokenIssuanceFee::get(),
+                    H256::from(alice_pub_key),
+                    OutputData::TokenTransferV1 {
+                        token_id: tkn3_token_id.clone(),
+                        amount: 3_000_000_000,
+                    },
+                ),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&prev_utxos, 0, &karl_pub_key)
+        .sign_unchecked(&prev_utxos, 1, &karl_pub_key)
+        .sign_unchecked(&prev_utxos, 2, &karl_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let tkn1_utxo_hash = tx.outpoint(0);
+        let tkn2_utxo_hash = tx.outpoint(1);
+        let tkn3_utxo_hash = tx.outpoint(2);
+        // Check tkn1
+        UtxoStore::<Test>::get(tkn1_utxo_hash)
+            .unwrap()
+            .data
+            .map(|x| match x {
+                OutputData::TokenTransferV1 { token_id, amount } => {
+                    assert_eq!(token_id, tkn1_token_id);
+                    assert_eq!(amount, 1_000_000_000);
+                }
+                _ => {
+                    panic!("corrupted data");
+                }
+            })
+            .unwrap();
+        // Check tkn2
+        UtxoStore::<Test>::get(tkn2_utxo_hash)
+            .unwrap()
+            .data
+            .map(|x| match x {
+                OutputData::TokenTransferV1 { token_id, amount } => {
+                    assert_eq!(token_id, tkn2_token_id);
+                    assert_eq!(amount, 2_000_000_000);
+                }
+                _ => {
+                    panic!("corrupted data");
+                }
+            })
+            .unwrap();
+        // Check tkn3
+        UtxoStore::<Test>::get(tkn3_utxo_hash)
+            .unwrap()
+            .data
+            .map(|x| match x {
+                OutputData::TokenTransferV1 { token_id, amount } => {
+                    assert_eq!(token_id, tkn3_token_id);
+                    assert_eq!(amount, 3_000_000_000);
+                }
+                _ => {
+                    panic!("corrupted data");
+                }
+            })
+            .unwrap();
+    });
+}
+
+// Alice holds 1_000 of token A, Karl holds 2_000 of token B.
+// Returns both token ids and the outpoints of their tokens.
+fn swap_tokens_setup(
+    alice_pub_key: Public,
+    karl_pub_key: Public,
+) -> ((TokenId, H256), (TokenId, H256)) {
+    let issue = |ticker: &str, amount: Value| OutputData::TokenIssuanceV1 {
+        token_ticker: ticker.as_bytes().to_vec(),
+        amount_to_issue: amount,
+        number_of_decimals: 2,
+        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+    };
+
+    let (utxo0, input0) = tx_input_gen_no_signature();
+    let tx1 = Transaction {
+        inputs: vec![input0],
+        outputs: vec![
+            TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(alice_pub_key),
+                issue("TKNA", 1_000),
+            ),
+            TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - TokenIssuanceFee::get(),
+                H256::from(alice_pub_key),
+            ),
+        ],
+        time_lock: Default::default(),
+        expires_at: None,
+    }
+    .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+    assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx1.clone()));
+    let token_a = TokenId::new(&tx1.inputs[0]);
+
+    let tx2 = Transaction {
+        inputs: vec![TransactionInput::new_empty(tx1.outpoint(1))],
+        outputs: vec![
+            TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(karl_pub_key),
+                issue("TKNB", 2_000),
+            ),
+            TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - 2 * TokenIssuanceFee::get(),
+                H256::from(alice_pub_key),
+            ),
+        ],
+        time_lock: Default::default(),
+        expires_at: None,
+    }
+    .sign_unchecked(&[tx1.outputs[1].clone()], 0, &alice_pub_key);
+    assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx2.clone()));
+    let token_b = TokenId::new(&tx2.inputs[0]);
+
+    ((token_a, tx1.outpoint(0)), (token_b, tx2.outpoint(0)))
+}
+
+#[test]
+// Alice and Karl exchange their tokens in one transaction, each signing its own side
+fn test_atomic_swap() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let ((token_a, outpoint_a), (token_b, outpoint_b)) =
+            swap_tokens_setup(alice_pub_key, karl_pub_key);
+        let utxo_a = UtxoStore::<Test>::get(outpoint_a).unwrap();
+        let utxo_b = UtxoStore::<Test>::get(outpoint_b).unwrap();
+        let transfer = |pubkey: Public, token_id: &TokenId, amount: Value| {
+            TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(pubkey),
+                OutputData::TokenTransferV1 {
+                    token_id: token_id.clone(),
+                    amount,
+                },
+            )
+        };
+
+        // Alice offers her 1_000 A for 2_000 B
+        let offer = Transaction::new_swap_offer(
+            TransactionInput::new_empty(outpoint_a),
+            transfer(alice_pub_key, &token_b, 2_000),
+        )
+        .partial_sign(&[utxo_a.clone()], 0, &alice_pub_key, swap::offer_sighash())
+        .unwrap();
+
+        // Karl accepts
+        let utxos = [utxo_a, utxo_b];
+        let tx = offer
+            .add_counterparty_input(
+                TransactionInput::new_empty(outpoint_b),
+                transfer(karl_pub_key, &token_a, 1_000),
+            )
+            .partial_sign(&utxos, 1, &karl_pub_key, swap::accept_sighash())
+            .unwrap();
+
+        // Karl can't take a bigger share of the B tokens, Alice's signature covers her output
+        let mut cheat = tx.clone();
+        cheat.outputs[0] = transfer(alice_pub_key, &token_b, 1_000);
+        cheat.outputs.push(transfer(karl_pub_key, &token_b, 1_000));
+        let cheat = cheat.partial_sign(&utxos, 1, &karl_pub_key, swap::accept_sighash()).unwrap();
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), cheat),
+            "signature must be valid"
+        );
+
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert!(!UtxoStore::<Test>::contains_key(outpoint_a));
+        assert!(!UtxoStore::<Test>::contains_key(outpoint_b));
+        assert_eq!(
+            UtxoStore::<Test>::get(tx.outpoint(0)),
+            Some(tx.outputs[0].clone())
+        );
+        assert_eq!(
+            UtxoStore::<Test>::get(tx.outpoint(1)),
+            Some(tx.outputs[1].clone())
+        );
+    });
+}
+
+#[test]
+// An offer signed with the default sighash commits to all inputs and can't be taken
+fn test_atomic_swap_default_sighash() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let ((token_a, outpoint_a), (token_b, outpoint_b)) =
+            swap_tokens_setup(alice_pub_key, karl_pub_key);
+        let utxo_a = UtxoStore::<Test>::get(outpoint_a).unwrap();
+        let utxo_b = UtxoStore::<Test>::get(outpoint_b).unwrap();
+
+        let utxos = [utxo_a.clone(), utxo_b];
+        let tx = Transaction::new_swap_offer(
+            TransactionInput::new_empty(outpoint_a),
+            TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(alice_pub_key),
+                OutputData::TokenTransferV1 {
+                    token_id: token_b,
+                    amount: 2_000,
+                },
+            ),
+        )
+        .sign_unchecked(&[utxo_a], 0, &alice_pub_key)
+        .add_counterparty_input(
+            TransactionInput::new_empty(outpoint_b),
+            TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(karl_pub_key),
+                OutputData::TokenTransferV1 {
+                    token_id: token_a,
+                    amount: 1_000,
+                },
+            ),
+        )
+        .partial_sign(&utxos, 1, &karl_pub_key, swap::accept_sighash())
+        .unwrap();
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), tx),
+            "signature must be valid"
+        );
+    });
+}
+
+#[test]
+// Alice and Karl send both their tokens to Karl in a single output
+fn test_multi_token_output() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let ((token_a, outpoint_a), (token_b, outpoint_b)) =
+            swap_tokens_setup(alice_pub_key, karl_pub_key);
+        let utxos = [
+            UtxoStore::<Test>::get(outpoint_a).unwrap(),
+            UtxoStore::<Test>::get(outpoint_b).unwrap(),
+        ];
+        let multi = |tokens: Vec<(&TokenId, Value)>| {
+            TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(karl_pub_key),
+                OutputData::TokenMultiV1(
+                    tokens.into_iter().map(|(id, amount)| (id.clone(), amount)).collect(),
+                ),
+            )
+        };
+
+        let tx = Transaction {
+            inputs: vec![
+                TransactionInput::new_empty(outpoint_a),
+                TransactionInput::new_empty(outpoint_b),
+            ],
+            outputs: vec![multi(vec![(&token_a, 1_000), (&token_b, 2_000)])],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&utxos, 0, &alice_pub_key)
+        .sign_unchecked(&utxos, 1, &karl_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert_eq!(
+            UtxoStore::<Test>::get(tx.outpoint(0)),
+            Some(tx.outputs[0].clone())
+        );
+
+        let spend_multi = |outputs: Vec<TransactionOutput<H256>>| {
+            let spend = Transaction {
+                inputs: vec![TransactionInput::new_empty(tx.outpoint(0))],
+                outputs,
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[tx.outputs[0].clone()], 0, &karl_pub_key);
+            Utxo::spend(Origin::signed(H256::zero()), spend)
+        };
+        frame_support::assert_err_ignore_postinfo!(
+            spend_multi(vec![multi(vec![
+                (&token_a, 500),
+                (&token_a, 500),
+                (&token_b, 2_000)
+            ])]),
+            "token appears twice in a multi-token output"
+        );
+        frame_support::assert_err_ignore_postinfo!(
+            spend_multi(vec![multi(vec![(&token_a, 1_000), (&token_b, 2_001)])]),
+            "output value must not exceed input value"
+        );
+        frame_support::assert_err_ignore_postinfo!(
+            spend_multi(vec![multi(vec![(&token_a, 1_000)])]),
+            "tokens must be burned explicitly"
+        );
+        frame_support::assert_err_ignore_postinfo!(
+            spend_multi(vec![multi(vec![])]),
+            "multi-token output carries no tokens"
+        );
+
+        // The tokens can be split again into single token outputs
+        let transfer = |token_id: &TokenId, amount: Value| {
+            TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(karl_pub_key),
+                OutputData::TokenTransferV1 {
+                    token_id: token_id.clone(),
+                    amount,
+                },
+            )
+        };
+        assert_ok!(spend_multi(vec![
+            transfer(&token_a, 1_000),
+            transfer(&token_b, 2_000)
+        ]));
+        assert!(!UtxoStore::<Test>::contains_key(tx.outpoint(0)));
+    });
+}
+
+#[test]
+fn test_token_bridge() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let ((token_a, outpoint_a), _) = swap_tokens_setup(alice_pub_key, karl_pub_key);
+        let utxo_a = UtxoStore::<Test>::get(outpoint_a).unwrap();
+        let lock_tx = |target_chain: u32| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(outpoint_a)],
+                outputs: vec![
+                    TransactionOutput::new_p2pk_with_data(
+                        0,
+                        H256::from(alice_pub_key),
+                        OutputData::TokenLockForBridgeV1 {
+                            token_id: token_a.clone(),
+                            amount: 400,
+                            target_chain,
+                            target_address: b"0xdead".to_vec(),
+                        },
+                    ),
+                    TransactionOutput::new_p2pk_with_data(
+                        0,
+                        H256::from(alice_pub_key),
+                        OutputData::TokenTransferV1 {
+                            token_id: token_a.clone(),
+                            amount: 600,
+                        },
+                    ),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo_a.clone()], 0, &alice_pub_key)
+        };
+
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), lock_tx(BRIDGE_CHAIN + 1)),
+            "bridge target chain is not supported"
+        );
+        let tx = lock_tx(BRIDGE_CHAIN);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        let lock = tx.outpoint(0);
+        assert_eq!(Utxo::bridge_custody(lock), Some(tx.outputs[0].clone()));
+        assert!(!UtxoStore::<Test>::contains_key(lock));
+        assert!(System::events().iter().any(|r| r.event
+            == Event::Utxo(crate::Event::TokensLockedForBridge(
+                token_a.clone(),
+                400,
+                BRIDGE_CHAIN,
+                b"0xdead".to_vec(),
+                lock
+            ))));
+        assert_ok!(crate::pallet::try_state::<Test>());
+
+        // only a relayer with a valid proof releases the tokens
+        let karl = H256::from(karl_pub_key);
+        let proof = bridge_release_proof(&lock, &karl);
+        assert_err!(
+            Utxo::release_from_bridge(Origin::signed(karl), lock, karl, proof.clone()),
+            crate::Error::<Test>::NoPermission
+        );
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::release_from_bridge(
+                Origin::signed(bridge_relayer()),
+                lock,
+                H256::from(alice_pub_key),
+                proof.clone()
+            ),
+            "invalid bridge release proof"
+        );
+        UTXO_EVENTS.with(|events| events.borrow_mut().clear());
+        assert_ok!(Utxo::release_from_bridge(
+            Origin::signed(bridge_relayer()),
+            lock,
+            karl,
+            proof.clone()
+        ));
+        assert_eq!(Utxo::bridge_custody(lock), None);
+        let released = BlakeTwo256::hash_of(&(&lock, "bridge release"));
+        // added like the outputs of transactions
+        assert_eq!(
+            UTXO_EVENTS.with(|events| events.borrow().clone()),
+            vec![UtxoEvent::New(released)]
+        );
+        assert!(crate::UtxoCreationTime::<Test>::get(released).is_some());
+        assert_eq!(
+            UtxoStore::<Test>::get(released),
+            Some(TransactionOutput::new_p2pk_with_data(
+                0,
+                karl,
+                OutputData::TokenTransferV1 {
+                    token_id: token_a.clone(),
+                    amount: 400,
+                },
+            ))
+        );
+        assert_ok!(crate::pallet::try_state::<Test>());
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::release_from_bridge(Origin::signed(bridge_relayer()), lock, karl, proof),
+            "tokens are not locked"
+        );
+    })
+}
+
+#[test]
+// Alice pays the fee in TKNA once it is accepted as a fee asset
+fn test_fee_paid_in_tokens() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let ((token_a, outpoint_a), _) = swap_tokens_setup(alice_pub_key, karl_pub_key);
+        let utxo_a = UtxoStore::<Test>::get(outpoint_a).unwrap();
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(outpoint_a)],
+            outputs: vec![TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(alice_pub_key),
+                OutputData::TokenTransferV1 {
+                    token_id: token_a.clone(),
+                    amount: 900,
+                },
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo_a], 0, &alice_pub_key);
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), tx.clone()),
+            "output value must not exceed input value"
+        );
+
+        // 10 TKNA are worth 1 MLT
+        let asset = FeeAsset {
+            token_id: token_a.clone(),
+            mlt_value: 1,
+            token_amount: 10,
+        };
+        assert_eq!(asset.to_mlt(105), 10);
+        AcceptedFeeAssets::set(&vec![asset]);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert_eq!(Utxo::token_reward_total(&token_a), 100);
+        assert_ok!(crate::pallet::try_state::<Test>());
+
+        // the token fee is paid to the block author
+        let karl = H256::from(karl_pub_key);
+        crate::BlockAuthor::<Test>::put(karl);
+        crate::rewards::reward_block_author::<Test>(1);
+        assert_eq!(Utxo::token_reward_total(&token_a), 0);
+        let fee_utxo = TransactionOutput::new_p2pk_with_data(
+            0,
+            karl,
+            OutputData::TokenTransferV1 {
+                token_id: token_a.clone(),
+                amount: 100,
+            },
+        );
+        assert!(UtxoStore::<Test>::iter_values().any(|utxo| utxo == fee_utxo));
+        assert_ok!(crate::pallet::try_state::<Test>());
+    })
+}
+
+#[test]
+// The block author claims the reward to a script, it's paid at the end of the next block
+fn test_claim_reward_inherent() {
+    use frame_support::inherent::{InherentData, ProvideInherent};
+
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let destination = Destination::ScriptHash(ScriptVersion::new(0), H256::repeat_byte(7));
+        let mut data = InherentData::new();
+        assert_eq!(Utxo::create_inherent(&data), None);
+        data.put_data(crate::REWARD_INHERENT_IDENTIFIER, &destination).unwrap();
+        let call = Utxo::create_inherent(&data).unwrap();
+        assert!(Utxo::is_inherent(&call));
+        assert_eq!(call, crate::Call::claim_reward(destination.clone()));
+        assert!(Utxo::check_inherent(&call, &data).is_ok());
+
+        // A destination rewards can't be claimed to is neither put in an inherent nor accepted
+        let data_carrier = Destination::DataCarrier(vec![1]);
+        let mut invalid_data = InherentData::new();
+        invalid_data.put_data(crate::REWARD_INHERENT_IDENTIFIER, &data_carrier).unwrap();
+        assert_eq!(Utxo::create_inherent(&invalid_data), None);
+        let invalid_call = crate::Call::claim_reward(data_carrier);
+        assert!(Utxo::check_inherent(&invalid_call, &invalid_data).is_err());
+
+        // Without an author the claim is ignored, the reward being deferred
+        assert_ok!(Utxo::claim_reward(Origin::none(), destination.clone()));
+        assert!(!crate::RewardDestination::<Test>::exists());
+
+        // The author found in the digest of the block can claim
+        let karl = H256::from(karl_pub_key);
+        AUTHORITIES.with(|auths| *auths.borrow_mut() = vec![karl_pub_key]);
+        System::deposit_log(sp_runtime::DigestItem::PreRuntime(
+            sp_consensus_aura::AURA_ENGINE_ID,
+            sp_consensus_aura::Slot::from(1).encode(),
+        ));
+        assert_ok!(Utxo::claim_reward(Origin::none(), destination.clone()));
+        assert!(crate::RewardDestination::<Test>::exists());
+        crate::RewardDestination::<Test>::kill();
+        AUTHORITIES.with(|auths| auths.borrow_mut().clear());
+        crate::BlockAuthor::<Test>::put(karl);
+        assert_noop!(
+            Utxo::claim_reward(Origin::signed(karl), destination.clone()),
+            frame_support::error::BadOrigin
+        );
+        assert_err!(
+            Utxo::claim_reward(Origin::none(), Destination::DataCarrier(vec![1])),
+            "Reward can't be claimed to the destination"
+        );
+        assert_ok!(Utxo::claim_reward(Origin::none(), destination.clone()));
+        assert_err!(
+            Utxo::claim_reward(Origin::none(), destination.clone()),
+            "Reward already claimed in this block"
+        );
+
+        // The reward is held for a block
+        crate::rewards::reward_block_author::<Test>(1);
+        let reward = TransactionOutput {
+            value: 100,
+            destination,
+            data: None,
+        };
+        assert_eq!(Utxo::pending_rewards(), vec![(1, reward.clone())]);
+        assert!(!UtxoStore::<Test>::iter_values().any(|utxo| utxo == reward));
+        assert_ok!(crate::pallet::try_state::<Test>());
+
+        // Without a claim, the reward of the next block goes to the author right away
+        crate::BlockAuthor::<Test>::put(H256::from(alice_pub_key));
+        crate::rewards::reward_block_author::<Test>(2);
+        assert!(Utxo::pending_rewards().is_empty());
+        assert!(UtxoStore::<Test>::iter_values().any(|utxo| utxo == reward));
+        let alice_reward = TransactionOutput::new_pubkey(100, H256::from(alice_pub_key));
+        assert!(UtxoStore::<Test>::iter_values().any(|utxo| utxo == alice_reward));
+        assert_ok!(crate::pallet::try_state::<Test>());
+    })
+}
+
+#[test]
+// Only the recipients allowed by the policy Alice sets for TKNA can receive it
+fn test_token_transfer_policy() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let ((token_a, outpoint_a), _) = swap_tokens_setup(alice_pub_key, karl_pub_key);
+        let utxo_a = UtxoStore::<Test>::get(outpoint_a).unwrap();
+        let alice = H256::from(alice_pub_key);
+        let karl = H256::from(karl_pub_key);
+        let transfer = |pubkey: H256, amount: Value| {
+            TransactionOutput::new_p2pk_with_data(
+                0,
+                pubkey,
+                OutputData::TokenTransferV1 {
+                    token_id: token_a.clone(),
+                    amount,
+                },
+            )
+        };
+        let tx = |to_karl: Value| {
+            Transaction {
+                inputs: vec![TransactionInput::new_empty(outpoint_a)],
+                outputs: vec![transfer(karl, to_karl), transfer(alice, 1_000 - to_karl)],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo_a.clone()], 0, &alice_pub_key)
+        };
+
+        // only the issuer sets the policy
+        let whitelist = TransferPolicy::Whitelist(vec![alice]);
+        assert_err!(
+            Utxo::set_transfer_policy(
+                Origin::signed(karl),
+                token_a.clone(),
+                Some(whitelist.clone())
+            ),
+            crate::Error::<Test>::NoPermission
+        );
+        assert_ok!(Utxo::set_transfer_policy(
+            Origin::signed(alice),
+            token_a.clone(),
+            Some(whitelist.clone())
+        ));
+        assert_eq!(Utxo::transfer_policy(&token_a), Some(whitelist.clone()));
+        assert!(System::events().iter().any(|r| r.event
+            == Event::Utxo(crate::Event::TransferPolicySet(
+                token_a.clone(),
+                Some(whitelist.clone())
+            ))));
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), tx(400)),
+            "token recipient is not whitelisted"
+        );
+
+        // the approver allows at most `APPROVED_TRANSFER_LIMIT` per output
+        assert_ok!(Utxo::set_transfer_policy(
+            Origin::signed(alice),
+            token_a.clone(),
+            Some(TransferPolicy::Approver)
+        ));
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), tx(400)),
+            "token transfer is not approved"
+        );
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            tx(APPROVED_TRANSFER_LIMIT)
+        ));
+
+        assert_ok!(Utxo::set_transfer_policy(
+            Origin::signed(alice),
+            token_a.clone(),
+            None
+        ));
+        assert_eq!(Utxo::transfer_policy(&token_a), None);
+    })
+}
+
+#[test]
+// Alice's TKNA balance is seen and moved through the fungibles traits
+fn test_fungibles_traits() {
     let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
     test_ext.execute_with(|| {
-        // Alice issue 1000 MLS-01, and send them to Karl and the rest back to herself
+        use frame_support::traits::tokens::{
+            fungibles::{Inspect, Transfer},
+            WithdrawConsequence,
+        };
+        let ((token_a, _), _) = swap_tokens_setup(alice_pub_key, karl_pub_key);
+        let asset = token_a.as_h160();
+        let alice = H256::from(alice_pub_key);
+        let karl = H256::from(karl_pub_key);
+        assert_eq!(Utxo::total_issuance(asset), 1_000);
+        assert_eq!(Utxo::balance(asset, &alice), 1_000);
+        assert_eq!(Utxo::reducible_balance(asset, &alice, false), 1_000);
+        assert_eq!(Utxo::balance(asset, &karl), 0);
+        assert_eq!(
+            Utxo::can_withdraw(asset, &alice, 1_001),
+            WithdrawConsequence::NoFunds
+        );
+
+        assert_eq!(Utxo::transfer(asset, &alice, &karl, 300, false), Ok(300));
+        assert_eq!(Utxo::balance(asset, &alice), 700);
+        assert_eq!(Utxo::balance(asset, &karl), 300);
+        assert_eq!(
+            Utxo::transfer(asset, &karl, &alice, 301, false),
+            Err(sp_runtime::TokenError::NoFunds.into())
+        );
+        assert_ok!(crate::pallet::try_state::<Test>());
+
+        // the tokens moved by a transaction follow their UTXOs
+        let (outpoint, _) = crate::TokenUtxosByOwner::<Test>::iter_prefix((karl, token_a.clone()))
+            .next()
+            .unwrap();
+        let utxo = UtxoStore::<Test>::get(outpoint).unwrap();
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_empty(outpoint)],
+            outputs: vec![TransactionOutput::new_p2pk_with_data(
+                0,
+                alice,
+                OutputData::TokenTransferV1 {
+                    token_id: token_a.clone(),
+                    amount: 300,
+                },
+            )],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo], 0, &karl_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert_eq!(Utxo::balance(asset, &alice), 1_000);
+        assert_eq!(Utxo::balance(asset, &karl), 0);
+        assert_ok!(crate::pallet::try_state::<Test>());
+    })
+}
+
+#[test]
+// Alice moves MLT from her account balance to a UTXO and back
+fn test_wrap_and_unwrap() {
+    execute_with_alice(|alice_pub_key| {
+        use frame_support::traits::Currency;
+        System::set_block_number(1);
+        let alice = H256::from(alice_pub_key);
+        let _ = Balances::deposit_creating(&alice, 1_000);
+
+        // the account has to stay alive
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::wrap(Origin::signed(alice), 1_000),
+            pallet_balances::Error::<Test>::KeepAlive
+        );
+        UTXO_EVENTS.with(|events| events.borrow_mut().clear());
+        assert_ok!(Utxo::wrap(Origin::signed(alice), 400));
+        assert_eq!(Balances::free_balance(&alice), 600);
+        let wrapped = BlakeTwo256::hash_of(&(&alice, 1u64, "wrap"));
+        assert_eq!(
+            UtxoStore::<Test>::get(wrapped),
+            Some(TransactionOutput::new_pubkey(400, alice))
+        );
+        assert_eq!(
+            UTXO_EVENTS.with(|events| events.borrow().clone()),
+            vec![UtxoEvent::New(wrapped)]
+        );
+        assert!(System::events()
+            .iter()
+            .any(|r| r.event == Event::Utxo(crate::Event::Wrapped(alice, 400, wrapped))));
+        assert_eq!(Utxo::mlt_issuance(), ALICE_GENESIS_BALANCE + 400);
+        assert_ok!(crate::pallet::try_state::<Test>());
+
+        // only the owner unwraps, and only MLT
+        let (_, genesis) = genesis_utxo();
+        assert_err!(
+            Utxo::unwrap(Origin::signed(H256::repeat_byte(1)), vec![wrapped]),
+            crate::Error::<Test>::NoPermission
+        );
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::unwrap(Origin::signed(alice), vec![wrapped, wrapped]),
+            "each utxo can be unwrapped only once"
+        );
+        assert_ok!(Utxo::set_paused(Origin::root(), Some(PauseScope::All)));
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::unwrap(Origin::signed(alice), vec![wrapped]),
+            "spends are paused"
+        );
+        assert_ok!(Utxo::set_paused(Origin::root(), None));
+
+        // spent like the inputs of a transaction
+        UTXO_EVENTS.with(|events| events.borrow_mut().clear());
+        assert_ok!(Utxo::unwrap(Origin::signed(alice), vec![wrapped, genesis]));
+        assert_eq!(
+            Balances::free_balance(&alice),
+            1_000 + ALICE_GENESIS_BALANCE
+        );
+        assert!(!UtxoStore::<Test>::contains_key(wrapped));
+        assert!(!UtxoStore::<Test>::contains_key(genesis));
+        assert!(crate::UtxoCreationTime::<Test>::get(wrapped).is_none());
+        assert_eq!(
+            UTXO_EVENTS.with(|events| events.borrow().clone()),
+            vec![UtxoEvent::Spent(wrapped), UtxoEvent::Spent(genesis)]
+        );
+        assert_eq!(Utxo::spent_output(wrapped).map(|(_, block)| block), Some(1));
+        assert!(System::events()
+            .iter()
+            .any(|r| r.event
+                == Event::Utxo(crate::Event::Unwrapped(alice, 400 + ALICE_GENESIS_BALANCE))));
+        assert_eq!(Utxo::mlt_issuance(), 0);
+        assert_ok!(crate::pallet::try_state::<Test>());
+    })
+}
+
+#[test]
+fn test_max_money() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        use frame_support::traits::Currency;
+        System::set_block_number(1);
+        let alice = H256::from(alice_pub_key);
+        let karl = H256::from(karl_pub_key);
+        let issuance = Utxo::mlt_issuance();
+        assert_eq!(Utxo::circulating_supply(), issuance);
+        MaxMoney::set(&(issuance + 150));
+
+        // the block rewards stop at the cap
+        crate::BlockAuthor::<Test>::put(karl);
+        crate::rewards::reward_block_author::<Test>(1);
+        assert_eq!(Utxo::mlt_issuance(), issuance + 100);
+        crate::BlockAuthor::<Test>::put(karl);
+        crate::rewards::reward_block_author::<Test>(2);
+        assert_eq!(Utxo::mlt_issuance(), issuance + 150);
+        let utxos = UtxoStore::<Test>::iter_values().count();
+        crate::BlockAuthor::<Test>::put(karl);
+        crate::rewards::reward_block_author::<Test>(3);
+        assert_eq!(Utxo::mlt_issuance(), issuance + 150);
+        // no empty reward is paid
+        assert_eq!(UtxoStore::<Test>::iter_values().count(), utxos);
+        assert_eq!(Utxo::circulating_supply(), issuance + 150);
+        assert_ok!(crate::pallet::try_state::<Test>());
+
+        // nothing else mints past it
+        let _ = Balances::deposit_creating(&alice, 1_000);
+        assert_err!(
+            Utxo::wrap(Origin::signed(alice), 1),
+            "MLT issuance would exceed MaxMoney"
+        );
+        assert_eq!(Balances::free_balance(&alice), 1_000);
+        MaxMoney::set(&(issuance + 151));
+        assert_ok!(Utxo::wrap(Origin::signed(alice), 1));
+        assert_eq!(Utxo::mlt_issuance(), issuance + 151);
+        assert_ok!(crate::pallet::try_state::<Test>());
+    })
+}
+
+// Open a channel funded by alice, returns the funding outpoint and UTXO
+fn channel_funding_setup(
+    alice_pub_key: Public,
+    karl_pub_key: Public,
+) -> (H256, TransactionOutput<H256>) {
+    let (utxo0, input0) = tx_input_gen_no_signature();
+    let funding = TransactionOutput::new_channel_funding(1_000, &alice_pub_key, &karl_pub_key);
+    let tx = Transaction {
+        inputs: vec![input0],
+        outputs: vec![funding.clone()],
+        time_lock: Default::default(),
+        expires_at: None,
+    }
+    .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+    assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+    (tx.outpoint(0), funding)
+}
+
+// Commitment transaction held by alice, signed by both parties
+fn channel_commitment_setup(
+    alice_pub_key: Public,
+    karl_pub_key: Public,
+    revocation: channels::Revocation,
+) -> (channels::Commitment, Transaction<H256>) {
+    let (funding_outpoint, funding) = channel_funding_setup(alice_pub_key, karl_pub_key);
+    let commitment = channels::Commitment {
+        local: alice_pub_key,
+        remote: karl_pub_key,
+        revocation,
+        to_self_delay: RelativeTimeLock::blocks(3),
+        local_value: 600,
+        remote_value: 390,
+    };
+    let funding_script = channels::funding_script(&alice_pub_key, &karl_pub_key);
+    let tx = Transaction::new_channel_commitment(funding_outpoint, funding_script, &commitment);
+    let sig_alice = tx.script_signature(&[funding.clone()], 0, &alice_pub_key).unwrap();
+    let sig_karl = tx.script_signature(&[funding], 0, &karl_pub_key).unwrap();
+    let tx = tx.with_witness(0, channels::funding_witness(&sig_alice, &sig_karl));
+    (commitment, tx)
+}
+
+#[test]
+fn test_channel_unilateral_close() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let revocation = channels::Revocation::Key(crypto::sr25519_generate(SR25519, None));
+        System::set_block_number(5);
+        let (commitment, commitment_tx) =
+            channel_commitment_setup(alice_pub_key, karl_pub_key, revocation);
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            commitment_tx.clone()
+        ));
+        // The counterparty's balance is paid out right away
+        assert_eq!(
+            UtxoStore::<Test>::get(commitment_tx.outpoint(1)),
+            Some(TransactionOutput::new_pubkey(390, H256::from(karl_pub_key)))
+        );
+
+        let to_local = commitment_tx.outputs[0].clone();
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_script(
+                commitment_tx.outpoint(0),
+                commitment.to_local_script(),
+                Script::new(),
+            )],
+            outputs: vec![TransactionOutput::new_pubkey(590, H256::from(alice_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        let sig = tx.script_signature(&[to_local], 0, &alice_pub_key).unwrap();
+        let tx = tx.with_witness(0, channels::to_local_delayed_witness(&sig));
+        // The holder's balance is only two blocks old
+        System::set_block_number(7);
+        assert_noop!(
+            Utxo::spend(Origin::signed(H256::zero()), tx.clone()),
+            "script verification failed"
+        );
+        System::set_block_number(8);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+    })
+}
+
+#[test]
+fn test_channel_revoked_commitment() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        // Alice handed over the revocation key, then published the old commitment anyway
+        let revocation = crypto::sr25519_generate(SR25519, None);
+        let (commitment, commitment_tx) = channel_commitment_setup(
+            alice_pub_key,
+            karl_pub_key,
+            channels::Revocation::Key(revocation),
+        );
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            commitment_tx.clone()
+        ));
+
+        let to_local = commitment_tx.outputs[0].clone();
+        let tx = Transaction {
+            inputs: vec![TransactionInput::new_script(
+                commitment_tx.outpoint(0),
+                commitment.to_local_script(),
+                Script::new(),
+            )],
+            outputs: vec![TransactionOutput::new_pubkey(590, H256::from(karl_pub_key))],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+
+        // Alice can't take the delayed path early, nor sign the revocation path herself
+        let sig = tx.script_signature(&[to_local.clone()], 0, &alice_pub_key).unwrap();
+        for witness in [
+            channels::to_local_delayed_witness(&sig),
+            channels::to_local_revoked_witness(&sig),
+        ] {
+            assert_noop!(
+                Utxo::spend(
+                    Origin::signed(H256::zero()),
+                    tx.clone().with_witness(0, witness)
+                ),
+                "script verification failed"
+            );
+        }
+
+        // Karl takes the whole balance right away with the revocation key
+        let sig = tx.script_signature(&[to_local], 0, &revocation).unwrap();
+        let tx = tx.with_witness(0, channels::to_local_revoked_witness(&sig));
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+    })
+}
+
+#[test]
+fn test_channel_justice_transaction() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let secret = [7u8; 32];
+        let revocation = channels::Revocation::Secret(H256(sp_core::hashing::sha2_256(&secret)));
+        let (commitment, commitment_tx) =
+            channel_commitment_setup(alice_pub_key, karl_pub_key, revocation);
+        assert_ok!(Utxo::spend(
+            Origin::signed(H256::zero()),
+            commitment_tx.clone()
+        ));
+
+        // Karl pre-signed the justice transaction, a watchtower publishes it
+        let karl = H256::from(karl_pub_key);
+        let justice = Transaction::new_justice(&commitment_tx, &commitment, karl, 10).unwrap();
+        assert_eq!(justice.inputs.len(), 2);
+        assert_eq!(
+            justice.outputs,
+            vec![TransactionOutput::new_pubkey(980, karl)]
+        );
+
+        // Only the revealed secret proves the revocation
+        let forged = justice.clone().sign_justice(&commitment_tx, &karl_pub_key, &[8u8; 32]);
+        assert_noop!(
+            Utxo::spend(Origin::signed(H256::zero()), forged.unwrap()),
+            "script verification failed"
+        );
+        // Alice can't claim the revoked balance with her own key
+        let sig = justice.script_signature(&commitment_tx.outputs, 0, &alice_pub_key).unwrap();
+        let forged = justice
+            .clone()
+            .sign_justice(&commitment_tx, &karl_pub_key, &secret)
+            .unwrap()
+            .with_witness(0, channels::to_local_delayed_witness(&sig));
+        assert_noop!(
+            Utxo::spend(Origin::signed(H256::zero()), forged),
+            "script verification failed"
+        );
+
+        let justice = justice.sign_justice(&commitment_tx, &karl_pub_key, &secret).unwrap();
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), justice.clone()));
+        assert!(UtxoStore::<Test>::get(commitment_tx.outpoint(0)).is_none());
+        assert!(UtxoStore::<Test>::get(commitment_tx.outpoint(1)).is_none());
+        assert_eq!(
+            UtxoStore::<Test>::get(justice.outpoint(0)),
+            Some(TransactionOutput::new_pubkey(980, karl))
+        );
+    })
+}
+
+#[test]
+fn test_channel_cooperative_close() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let (funding_outpoint, funding) = channel_funding_setup(alice_pub_key, karl_pub_key);
+        let tx = Transaction::new_cooperative_close(
+            funding_outpoint,
+            channels::funding_script(&alice_pub_key, &karl_pub_key),
+            (&alice_pub_key, 600),
+            (&karl_pub_key, 390),
+        );
+        let sig_alice = tx.script_signature(&[funding.clone()], 0, &alice_pub_key).unwrap();
+        let sig_karl = tx.script_signature(&[funding], 0, &karl_pub_key).unwrap();
+
+        // Both signatures are needed, in the key order
+        for witness in [
+            channels::funding_witness(&sig_alice, &sig_alice),
+            channels::funding_witness(&sig_karl, &sig_alice),
+        ] {
+            assert_noop!(
+                Utxo::spend(
+                    Origin::signed(H256::zero()),
+                    tx.clone().with_witness(0, witness)
+                ),
+                "script verification failed"
+            );
+        }
+
+        let tx = tx.with_witness(0, channels::funding_witness(&sig_alice, &sig_karl));
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert_eq!(
+            UtxoStore::<Test>::get(tx.outpoint(0)),
+            Some(TransactionOutput::new_pubkey(
+                600,
+                H256::from(alice_pub_key)
+            ))
+        );
+        assert_eq!(
+            UtxoStore::<Test>::get(tx.outpoint(1)),
+            Some(TransactionOutput::new_pubkey(390, H256::from(karl_pub_key)))
+        );
+    })
+}
+
+// Alice pays 1000 into an escrow for Karl, arbitrated by a third key
+fn escrow_setup(
+    alice_pub_key: Public,
+    karl_pub_key: Public,
+) -> (escrow::Escrow, H256, TransactionOutput<H256>) {
+    let arbiter = crypto::sr25519_generate(SR25519, None);
+    let escrow = escrow::Escrow::new(alice_pub_key, karl_pub_key, arbiter, RawBlockTime::new(10));
+    let (utxo0, input0) = tx_input_gen_no_signature();
+    let tx = Transaction {
+        inputs: vec![input0],
+        outputs: vec![TransactionOutput::new_escrow(1_000, &escrow)],
+        time_lock: Default::default(),
+        expires_at: None,
+    }
+    .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+    assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+    (escrow, tx.outpoint(0), tx.outputs[0].clone())
+}
+
+#[test]
+fn test_escrow_release() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        System::set_block_number(5);
+        let (escrow, outpoint, utxo) = escrow_setup(alice_pub_key, karl_pub_key);
+        let payment = TransactionOutput::new_pubkey(990, H256::from(karl_pub_key));
+        let tx = Transaction::new_escrow_release(outpoint, &escrow, vec![payment.clone()]);
+        let sig_alice = tx.script_signature(&[utxo.clone()], 0, &alice_pub_key).unwrap();
+        let sig_karl = tx.script_signature(&[utxo.clone()], 0, &karl_pub_key).unwrap();
+        let sig_arbiter = tx.script_signature(&[utxo], 0, &escrow.arbiter).unwrap();
+
+        // The arbiter can't step in before the deadline, nor can a party release alone
+        for witness in [
+            escrow::Escrow::arbitration_witness(&sig_karl, &sig_arbiter),
+            escrow::Escrow::release_witness(&sig_karl, &sig_karl),
+        ] {
+            assert_noop!(
+                Utxo::spend(
+                    Origin::signed(H256::zero()),
+                    tx.clone().with_witness(0, witness)
+                ),
+                "script verification failed"
+            );
+        }
+
+        let witness = escrow::Escrow::release_witness(&sig_alice, &sig_karl);
+        let tx = tx.with_witness(0, witness);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert_eq!(UtxoStore::<Test>::get(tx.outpoint(0)), Some(payment));
+    })
+}
+
+#[test]
+fn test_escrow_arbitration() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let (escrow, outpoint, utxo) = escrow_setup(alice_pub_key, karl_pub_key);
+        // The arbiter refunds Alice once the deadline has passed
+        System::set_block_number(10);
+        let refund = TransactionOutput::new_pubkey(990, H256::from(alice_pub_key));
+        let tx = Transaction::new_escrow_arbitration(outpoint, &escrow, vec![refund.clone()]);
+        let sig_alice = tx.script_signature(&[utxo.clone()], 0, &alice_pub_key).unwrap();
+        let sig_karl = tx.script_signature(&[utxo.clone()], 0, &karl_pub_key).unwrap();
+        let sig_arbiter = tx.script_signature(&[utxo], 0, &escrow.arbiter).unwrap();
+
+        // The arbiter needs the signature of a party
+        for witness in [
+            escrow::Escrow::arbitration_witness(&sig_arbiter, &sig_arbiter),
+            escrow::Escrow::arbitration_witness(&sig_arbiter, &sig_karl),
+        ] {
+            assert_noop!(
+                Utxo::spend(
+                    Origin::signed(H256::zero()),
+                    tx.clone().with_witness(0, witness)
+                ),
+                "script verification failed"
+            );
+        }
+
+        // Either party can sign with the arbiter
+        for sig_party in [&sig_karl, &sig_alice] {
+            let witness = escrow::Escrow::arbitration_witness(sig_party, &sig_arbiter);
+            assert_ok!(crate::pallet::validate_transaction::<Test>(
+                &tx.clone().with_witness(0, witness)
+            ));
+        }
+        let witness = escrow::Escrow::arbitration_witness(&sig_alice, &sig_arbiter);
+        let tx = tx.with_witness(0, witness);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert_eq!(UtxoStore::<Test>::get(tx.outpoint(0)), Some(refund));
+    })
+}
+
+#[test]
+fn test_utxos_paged() {
+    let (mut test_ext, alice_pub_key, _karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let alice = H256::from(alice_pub_key);
+        let (utxo, input) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input],
+            outputs: (1..=5).map(|value| TransactionOutput::new_pubkey(value, alice)).collect(),
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+
+        let all: Vec<_> = UtxoStore::<Test>::iter().collect();
+        assert!(all.len() > 2);
+        assert!(all.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        let mut pages = Vec::new();
+        let mut start_key = None;
+        loop {
+            let page = Utxo::utxos_paged(start_key, 2);
+            assert!(page.len() <= 2);
+            // The start key itself belongs to the previous page
+            assert!(start_key.map_or(true, |key| page.iter().all(|(outpoint, _)| *outpoint > key)));
+            start_key = page.last().map(|(outpoint, _)| *outpoint);
+            pages.extend(page);
+            if start_key.is_none() {
+                break;
+            }
+        }
+        assert_eq!(pages, all);
+        assert_eq!(Utxo::utxos_paged(None, u32::MAX).len(), all.len());
+
+        // The lowest outpoints are picked first
+        let lowest = all
+            .iter()
+            .find(|(_, utxo)| utxo.destination == Destination::Pubkey(alice_pub_key))
+            .map(|(outpoint, _)| *outpoint);
+        let (_, hashes, _) = crate::pick_utxo::<Test>(&alice, 1);
+        assert_eq!(hashes.first().copied(), lowest);
+    })
+}
+
+#[test]
+fn test_scan_descriptor() {
+    use crate::descriptor::{Descriptor, ScriptDescriptor};
+
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        let alice = H256::from(alice_pub_key);
+        // Wallets exchange descriptors as strings
+        let parse = |descriptor: Descriptor| Descriptor::parse(&descriptor.to_string()).unwrap();
+        let multi = parse(Descriptor::Sh(ScriptDescriptor::After(
+            RawBlockTime::new(10),
+            Box::new(ScriptDescriptor::Multi(
+                1,
+                vec![alice_pub_key, karl_pub_key],
+            )),
+        )));
+        let multi_hash = match multi.destination::<H256>() {
+            Destination::ScriptHash(_, hash) => hash,
+            _ => panic!("not a script hash"),
+        };
+
+        let (utxo, input) = tx_input_gen_no_signature();
+        let mut outputs: Vec<_> =
+            (1..=3).map(|value| TransactionOutput::new_pubkey(value, alice)).collect();
+        outputs.extend((1..=3).map(|value| TransactionOutput::new_script_hash(value, multi_hash)));
+        let tx = Transaction {
+            inputs: vec![input],
+            outputs,
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+
+        let mut expected: Vec<_> =
+            (3..6).map(|i| (tx.outpoint(i), tx.outputs[i as usize].clone())).collect();
+        expected.sort_by_key(|(outpoint, _)| *outpoint);
+        assert_eq!(
+            Utxo::scan_descriptor(&multi, None, u32::MAX),
+            (expected.clone(), None)
+        );
+        // Paging works as for the whole UTXO set
+        assert_eq!(
+            Utxo::scan_descriptor(&multi, None, 2),
+            (expected[..2].to_vec(), Some(expected[1].0))
+        );
+        assert_eq!(
+            Utxo::scan_descriptor(&multi, Some(expected[1].0), 2),
+            (expected[2..].to_vec(), None)
+        );
+
+        // A page scanning a single UTXO at a time may be empty, the pages still add up
+        let destination = multi.destination::<H256>();
+        let (mut found, mut cursor, mut pages) = (vec![], None, 0);
+        loop {
+            let (utxos, next_key) = crate::scan_utxos::<Test>(&destination, cursor, u32::MAX, 1);
+            assert!(utxos.len() <= 1);
+            found.extend(utxos);
+            pages += 1;
+            match next_key {
+                Some(next_key) => cursor = Some(next_key),
+                None => break,
+            }
+        }
+        assert_eq!(found, expected);
+        assert_eq!(pages, UtxoStore::<Test>::iter().count());
+
+        let pk = parse(Descriptor::Pk(alice_pub_key));
+        let (found, _) = Utxo::scan_descriptor(&pk, None, u32::MAX);
+        assert!(found
+            .iter()
+            .all(|(_, utxo)| utxo.destination == Destination::Pubkey(alice_pub_key)));
+        assert!((0..3).all(|i| found.iter().any(|(outpoint, _)| *outpoint == tx.outpoint(i))));
+
+        let karl = parse(Descriptor::Pk(karl_pub_key));
+        assert_eq!(Utxo::scan_descriptor(&karl, None, u32::MAX), (vec![], None));
+    })
+}
+
+#[test]
+// A UTXO set exported from one chain bootstraps another with the same commitment
+fn test_utxo_set_export_import() {
+    use frame_support::{sp_io::TestExternalities, traits::GenesisBuild};
+    use pallet_utxo_rpc_runtime_api::{UtxoSetChunk, UtxoSetExport, UTXO_SET_CHUNK_SIZE};
+
+    let export = || {
+        let utxos = Utxo::utxos_paged(None, UTXO_SET_CHUNK_SIZE)
+            .into_iter()
+            .map(|(outpoint, utxo)| (outpoint, utxo.encode()))
+            .collect();
+        UtxoSetExport::new(vec![UtxoSetChunk::new(utxos)])
+    };
+    let (mut test_ext, alice_pub_key, _karl_pub_key) = alice_test_ext_and_keys();
+    let (all, snapshot) = test_ext.execute_with(|| {
+        let alice = H256::from(alice_pub_key);
+        let (utxo, input) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input],
+            outputs: (1..=5).map(|value| TransactionOutput::new_pubkey(value, alice)).collect(),
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        (UtxoStore::<Test>::iter().collect::<Vec<_>>(), export())
+    });
+    assert_ok!(snapshot.verify());
+
+    // Tampering with the UTXOs or their order breaks the commitment
+    let mut tampered = snapshot.clone();
+    tampered.chunks[0].utxos[0].1 = TransactionOutput::<H256>::new_pubkey(1, H256::zero()).encode();
+    assert_err!(tampered.verify(), "bad chunk hash");
+    let mut utxos = snapshot.chunks[0].utxos.clone();
+    utxos.swap(0, 1);
+    let tampered = UtxoSetExport::new(vec![UtxoSetChunk::new(utxos)]);
+    assert_err!(tampered.verify(), "outpoints not in ascending order");
+    let mut tampered = snapshot.clone();
+    tampered.commitment = H256::zero();
+    assert_err!(tampered.verify(), "bad commitment");
+
+    let mut storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    crate::GenesisConfig::<Test> {
+        genesis_utxos: vec![],
+        locked_utxos: vec![],
+        utxo_snapshot: Some(snapshot.clone()),
+        airdrop_root: None,
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+    TestExternalities::from(storage).execute_with(|| {
+        assert_eq!(UtxoStore::<Test>::iter().collect::<Vec<_>>(), all);
+        let total: Value = all.iter().map(|(_, utxo)| utxo.value).sum();
+        assert_eq!(Utxo::mlt_issuance(), total);
+        assert_ok!(crate::pallet::try_state::<Test>());
+        assert_eq!(export(), snapshot);
+    });
+}
+
+#[test]
+fn test_outpoint_hashes() {
+    use hex_literal::hex;
+
+    // Test vectors for wallets. One input spending the zero outpoint with the witness 0x010203,
+    // two outputs paying 100 units to key 0x07.. and 50 units to key 0x08.., no time lock.
+    let mut tx = Transaction::<H256> {
+        inputs: vec![TransactionInput::new_empty(H256::zero())],
+        outputs: vec![
+            TransactionOutput::new_pubkey(100, H256::repeat_byte(0x07)),
+            TransactionOutput::new_pubkey(50, H256::repeat_byte(0x08)),
+        ],
+        time_lock: Default::default(),
+        expires_at: None,
+    };
+    assert_eq!(
+        tx.outpoint_hashes(),
+        vec![
+            H256(hex!(
+                "986cf0fad65d80bbdfcf8f6f9460a1275bca6b9e631c870d5cf220cebeba1ddd"
+            )),
+            H256(hex!(
+                "b6196995346ffa1cf8cedf213f8dcf42534013109e7f5a61233bdb41b1bb7624"
+            )),
+        ]
+    );
+    tx.inputs[0].witness = vec![1, 2, 3];
+    assert_eq!(
+        tx.outpoint_hashes(),
+        vec![
+            H256(hex!(
+                "4f4810ecc007990d45017dcf999a1d552c09e8f72ea44a09c77f9f2396a32063"
+            )),
+            H256(hex!(
+                "72531cafc41be0d82e1aaa10d442ff7ce7a2f73c4b7b7e3cb1465b009f124dbc"
+            )),
+        ]
+    );
+
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let tx = Transaction {
+            inputs: vec![input0],
+            outputs: vec![
+                TransactionOutput::new_pubkey(100, H256::from(alice_pub_key)),
+                TransactionOutput::new_script_hash(200, H256::repeat_byte(0x09)),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        }
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+
+        let outpoints = tx.outpoint_hashes();
+        assert_eq!(outpoints, vec![tx.outpoint(0), tx.outpoint(1)]);
+        for (outpoint, output) in outpoints.iter().zip(&tx.outputs) {
+            assert_eq!(UtxoStore::<Test>::get(outpoint).as_ref(), Some(output));
+        }
+    })
+}
+
+#[test]
+fn test_immutable_tx_format() {
+    // todo: Testing the compatibility of the old version with the new one - not done yet
+}
+
+#[test]
+fn test_burn_tokens() {
+    execute_with_alice(|alice_pub_key| {
+        // Alice issues 1_000_000_000 MLS-01
         let (utxo0, input0) = tx_input_gen_no_signature();
         let tx = Transaction {
             inputs: vec![input0],
             outputs: vec![
-                TransactionOutput::new_pubkey(
-                    ALICE_GENESIS_BALANCE - u64::MAX as Value,
-                    H256::from(karl_pub_key),
-                ),
                 TransactionOutput::new_p2pk_with_data(
-                    crate::tokens::Mlt(99).to_munit(),
-                    H256::from(karl_pub_key),
+                    0,
+                    H256::from(alice_pub_key),
                     OutputData::TokenIssuanceV1 {
                         token_ticker: "BensT".as_bytes().to_vec(),
                         amount_to_issue: 1_000_000_000,
@@ -1534,376 +7387,682 @@ fn test_token_creation_with_insufficient_fee() {
                         metadata_uri: "mintlayer.org".as_bytes().to_vec(),
                     },
                 ),
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - TokenIssuanceFee::get(),
+                    H256::from(alice_pub_key),
+                ),
             ],
             time_lock: Default::default(),
+            expires_at: None,
         }
-        .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
-
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
         assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
-        let token_utxo_hash = tx.outpoint(1);
-        let token_utxo = tx.outputs[1].clone();
-        let tx = Transaction {
-            inputs: vec![
-                // Use here token issuance for example
-                TransactionInput::new_empty(token_utxo_hash),
-            ],
-            outputs: vec![TransactionOutput::new_p2pk_with_data(
+        let token_id = TokenId::new(&tx.inputs[0]);
+        assert_eq!(
+            Utxo::token_circulating_supply(&token_id),
+            Some(1_000_000_000)
+        );
+
+        let burn = |amount_to_burn: Value| {
+            TransactionOutput::new_p2pk_with_data(
                 0,
-                H256::from(karl_pub_key),
-                OutputData::TokenIssuanceV1 {
-                    token_ticker: b"Enric".to_vec(),
-                    amount_to_issue: 1_000_000_000,
-                    number_of_decimals: 2,
-                    metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                H256::from(alice_pub_key),
+                OutputData::TokenBurnV1 {
+                    token_id: token_id.clone(),
+                    amount_to_burn,
+                },
+            )
+        };
+        let transfer = |amount: Value| {
+            TransactionOutput::new_p2pk_with_data(
+                0,
+                H256::from(alice_pub_key),
+                OutputData::TokenTransferV1 {
+                    token_id: token_id.clone(),
+                    amount,
                 },
+            )
+        };
+        let utxos = [tx.outputs[0].clone(), tx.outputs[1].clone()];
+        let inputs = vec![
+            TransactionInput::new_empty(tx.outpoint(0)),
+            TransactionInput::new_empty(tx.outpoint(1)),
+        ];
+
+        // Tokens can't be dropped silently
+        let drop_tx = Transaction {
+            inputs: inputs.clone(),
+            outputs: vec![TransactionOutput::new_pubkey(
+                ALICE_GENESIS_BALANCE - TokenIssuanceFee::get() - 1,
+                H256::from(alice_pub_key),
             )],
             time_lock: Default::default(),
+            expires_at: None,
         }
-        .sign_unchecked(&[token_utxo], 0, &karl_pub_key);
+        .sign_unchecked(&utxos, 0, &alice_pub_key)
+        .sign_unchecked(&utxos, 1, &alice_pub_key);
         frame_support::assert_err_ignore_postinfo!(
-            Utxo::spend(Origin::signed(H256::zero()), tx),
-            "insufficient fee"
+            Utxo::spend(Origin::signed(H256::zero()), drop_tx),
+            "tokens must be burned explicitly"
         );
-    });
-}
 
-#[test]
-fn test_transfer_and_issuance_in_one_tx() {
-    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
-    test_ext.execute_with(|| {
-        // Alice issue 1_000_000_000 MLS-01, and send them to Karl
-        let (utxo0, input0) = tx_input_gen_no_signature();
-        let tx = Transaction {
-            inputs: vec![input0],
-            outputs: vec![
-                TransactionOutput::new_pubkey(
-                    ALICE_GENESIS_BALANCE - crate::tokens::Mlt(1000).to_munit(),
-                    H256::from(alice_pub_key),
-                ),
-                TransactionOutput::new_p2pk_with_data(
-                    crate::tokens::Mlt(1000).to_munit(),
-                    H256::from(karl_pub_key),
-                    OutputData::TokenIssuanceV1 {
-                        token_ticker: "BensT".as_bytes().to_vec(),
-                        amount_to_issue: 1_000_000_000,
-                        number_of_decimals: 2,
-                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
-                    },
-                ),
-            ],
+        // Alice can't burn more than she has
+        let burn_tx = Transaction {
+            inputs: inputs[..1].to_vec(),
+            outputs: vec![burn(1_000_000_001)],
             time_lock: Default::default(),
+            expires_at: None,
         }
-        .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
-        let first_issuance_token_id = TokenId::new(&tx.inputs[0]);
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
-        let token_utxo_hash = tx.outpoint(1);
-        let token_utxo = tx.outputs[1].clone();
+        .sign_unchecked(&utxos[..1], 0, &alice_pub_key);
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), burn_tx),
+            "output value must not exceed input value"
+        );
 
-        // Let's send 300_000_000 and rest back and create another token
-        let tx = Transaction {
-            inputs: vec![TransactionInput::new_empty(token_utxo_hash)],
-            outputs: vec![
-                TransactionOutput::new_p2pk_with_data(
-                    0,
-                    H256::from(alice_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id: first_issuance_token_id.clone(),
-                        amount: 300_000_000,
-                    },
-                ),
-                TransactionOutput::new_p2pk_with_data(
-                    0,
-                    H256::from(karl_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id: first_issuance_token_id.clone(),
-                        amount: 700_000_000,
-                    },
-                ),
-                TransactionOutput::new_p2pk_with_data(
-                    0,
-                    H256::from(karl_pub_key),
-                    OutputData::TokenIssuanceV1 {
-                        token_ticker: "Token".as_bytes().to_vec(),
-                        amount_to_issue: 5_000_000_000,
-                        // Should be not more than 18 numbers
-                        number_of_decimals: 12,
-                        metadata_uri: "mintlayer.org".as_bytes().to_vec(),
-                    },
-                ),
-            ],
+        // Burn 30% and keep the rest
+        let burn_tx = Transaction {
+            inputs: inputs[..1].to_vec(),
+            outputs: vec![burn(300_000_000), transfer(700_000_000)],
             time_lock: Default::default(),
+            expires_at: None,
         }
-        .sign_unchecked(&[token_utxo.clone()], 0, &karl_pub_key);
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
-        let alice_transfer_utxo_hash = tx.outpoint(0);
-        let karl_transfer_utxo_hash = tx.outpoint(1);
-        let karl_issuance_utxo_hash = tx.outpoint(2);
-        assert!(!UtxoStore::<Test>::contains_key(H256::from(
-            token_utxo_hash
-        )));
-        assert!(UtxoStore::<Test>::contains_key(alice_transfer_utxo_hash));
-        assert!(UtxoStore::<Test>::contains_key(karl_transfer_utxo_hash));
-        assert!(UtxoStore::<Test>::contains_key(karl_issuance_utxo_hash));
+        .sign_unchecked(&utxos[..1], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), burn_tx));
+        assert_eq!(Utxo::total_burned(&token_id), 300_000_000);
+        assert_eq!(Utxo::token_circulating_supply(&token_id), Some(700_000_000));
 
-        // Let's check token transfer
-        UtxoStore::<Test>::get(alice_transfer_utxo_hash)
-            .unwrap()
-            .data
-            .map(|x| match x {
-                OutputData::TokenTransferV1 { token_id, amount } => {
-                    assert_eq!(token_id, first_issuance_token_id);
-                    assert_eq!(amount, 300_000_000);
-                }
-                _ => {
-                    panic!("corrupted data");
-                }
+        // Burn through the extrinsic
+        let alice = Origin::signed(H256::from(alice_pub_key));
+        assert_err!(
+            Utxo::burn(alice.clone(), token_id.clone(), 0),
+            "Value burned must be larger than zero"
+        );
+        assert_err!(
+            Utxo::burn(alice.clone(), token_id.clone(), 700_000_001),
+            "Caller doesn't have enough tokens"
+        );
+        assert_ok!(Utxo::burn(alice, token_id.clone(), 200_000_000));
+        assert_eq!(Utxo::total_burned(&token_id), 500_000_000);
+        assert_eq!(Utxo::token_circulating_supply(&token_id), Some(500_000_000));
+    });
+}
+
+#[test]
+fn test_token_metadata_rules_config() {
+    execute_with_alice(|alice_pub_key| {
+        let alice = H256::from(alice_pub_key);
+        MaxTickerLength::set(&7);
+        TokenTickerCharset::set(&TickerCharset::UppercaseAlpha);
+
+        // Wallets can check the metadata before issuing
+        assert_eq!(Utxo::validate_token_metadata(b"TOOLONG", 2, b""), Ok(()));
+        assert_eq!(
+            Utxo::validate_token_metadata(b"BensT", 2, b""),
+            Err("token ticker must be uppercase letters")
+        );
+        assert_eq!(
+            Utxo::validate_token_metadata(b"MLT", 2, b""),
+            Err("token ticker is reserved")
+        );
+
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::issue_token(
+                Origin::signed(alice),
+                b"BensT".to_vec(),
+                1_000,
+                2,
+                b"mintlayer.org".to_vec(),
+            ),
+            "token ticker must be uppercase letters"
+        );
+        assert_ok!(Utxo::issue_token(
+            Origin::signed(alice),
+            b"TOOLONG".to_vec(),
+            1_000,
+            2,
+            b"mintlayer.org".to_vec(),
+        ));
+
+        MaxTickerLength::set(&5);
+        TokenTickerCharset::set(&TickerCharset::Ascii);
+    })
+}
+
+#[test]
+fn test_issue_token() {
+    execute_with_alice(|alice_pub_key| {
+        System::set_block_number(1);
+        let alice = H256::from(alice_pub_key);
+        let (_, input0) = tx_input_gen_no_signature();
+        assert_ok!(Utxo::issue_token(
+            Origin::signed(alice),
+            b"BensT".to_vec(),
+            1_000,
+            2,
+            b"mintlayer.org".to_vec(),
+        ));
+        // The id is derived from the spent genesis UTXO
+        let token_id = TokenId::new(&input0);
+        assert_eq!(Utxo::token_circulating_supply(&token_id), Some(1_000));
+        assert!(System::events().iter().any(|record| record.event
+            == Event::Utxo(crate::Event::<Test>::TokenIssued(
+                token_id.clone(),
+                alice,
+                1_000
+            ))));
+        // The fee is paid and the rest of the MLT is returned
+        let change = UtxoStore::<Test>::iter_values()
+            .find(|output| {
+                output.data.is_none() && output.destination == Destination::Pubkey(alice_pub_key)
             })
             .unwrap();
+        assert_eq!(
+            change.value,
+            ALICE_GENESIS_BALANCE - TokenIssuanceFee::get()
+        );
 
-        UtxoStore::<Test>::get(karl_transfer_utxo_hash)
-            .unwrap()
-            .data
-            .map(|x| match x {
-                OutputData::TokenTransferV1 { token_id, amount } => {
-                    assert_eq!(token_id, first_issuance_token_id);
-                    assert_eq!(amount, 700_000_000);
-                }
-                _ => {
-                    panic!("corrupted data");
+        // Invalid issuances are rejected like any other transaction
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::issue_token(
+                Origin::signed(alice),
+                b"TOOLONG".to_vec(),
+                1_000,
+                2,
+                b"mintlayer.org".to_vec(),
+            ),
+            "token ticker is too long"
+        );
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::issue_token(
+                Origin::signed(H256::repeat_byte(7)),
+                b"BensT".to_vec(),
+                1_000,
+                2,
+                b"mintlayer.org".to_vec(),
+            ),
+            "Caller doesn't have enough UTXOs"
+        );
+    })
+}
+
+#[test]
+fn test_mint_nft() {
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice = H256::from(alice_pub_key);
+        let collection = H256::repeat_byte(0xc0);
+        let royalty = NftRoyalty {
+            beneficiary: alice_pub_key,
+            basis_points: 500,
+            minimum: 1,
+        };
+        assert_ok!(Utxo::mint_nft(
+            Origin::signed(alice),
+            Some(collection),
+            NftDataHash::Hash32([5; 32]),
+            b"mintlayer.org".to_vec(),
+            Some(royalty.clone()),
+        ));
+        let (token_id, owner, minted_into) = System::events()
+            .into_iter()
+            .find_map(|record| match record.event {
+                Event::Utxo(crate::Event::<Test>::NftMinted(token_id, owner, collection)) => {
+                    Some((token_id, owner, collection))
                 }
+                _ => None,
             })
             .unwrap();
+        assert_eq!((owner, minted_into), (alice, Some(collection)));
+        assert_eq!(Utxo::nft_royalty(&token_id), Some(royalty));
+        assert_eq!(Utxo::nft_collection_owner(collection), Some(alice));
+        assert_eq!(
+            crate::nft_collection::<Test>(collection).collect::<Vec<_>>(),
+            vec![token_id]
+        );
+        let change = UtxoStore::<Test>::iter_values()
+            .find(|output| {
+                output.data.is_none() && output.destination == Destination::Pubkey(alice_pub_key)
+            })
+            .unwrap();
+        assert_eq!(change.value, ALICE_GENESIS_BALANCE - NftMintFee::get());
 
-        // Let's check token issuance
-        UtxoStore::<Test>::get(karl_issuance_utxo_hash)
-            .unwrap()
-            .data
-            .map(|x| match x {
+        // The digital data can only be minted once
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::mint_nft(
+                Origin::signed(alice),
+                None,
+                NftDataHash::Hash32([5; 32]),
+                b"mintlayer.org".to_vec(),
+                None,
+            ),
+            "digital data has already been minted"
+        );
+        // Only the owner mints into a collection
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::mint_nft(
+                Origin::signed(H256::from(karl_pub_key)),
+                Some(collection),
+                NftDataHash::Hash32([6; 32]),
+                b"mintlayer.org".to_vec(),
+                None,
+            ),
+            "nft collection belongs to another account"
+        );
+    })
+}
+
+#[test]
+fn test_storage_deposit() {
+    execute_with_alice(|alice_pub_key| {
+        let alice = H256::from(alice_pub_key);
+        StorageDepositPerByte::set(&2);
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let issuance = |value: Value| {
+            TransactionOutput::new_p2pk_with_data(
+                value,
+                alice,
                 OutputData::TokenIssuanceV1 {
-                    token_ticker,
-                    amount_to_issue,
-                    number_of_decimals,
-                    metadata_uri,
-                } => {
-                    assert_eq!(token_ticker, "Token".as_bytes().to_vec());
-                    assert_eq!(amount_to_issue, 5_000_000_000);
-                    assert_eq!(number_of_decimals, 12);
-                    assert_eq!(metadata_uri, "mintlayer.org".as_bytes().to_vec());
-                }
-                _ => {
-                    panic!("corrupted data");
-                }
-            })
+                    token_ticker: b"BensT".to_vec(),
+                    amount_to_issue: 1_000,
+                    number_of_decimals: 2,
+                    metadata_uri: b"mintlayer.org".to_vec(),
+                },
+            )
+        };
+        let deposit = Utxo::storage_deposit(&issuance(0));
+        assert_eq!(deposit, 2 * issuance(0).data.encoded_size() as Value);
+        // MLT outputs and data carriers store no data in the UTXO set
+        assert_eq!(
+            Utxo::storage_deposit(&TransactionOutput::new_pubkey(1, alice)),
+            0
+        );
+        assert_eq!(
+            Utxo::storage_deposit(&TransactionOutput::new_data_carrier(vec![7; 80])),
+            0
+        );
+        assert_eq!(
+            Utxo::storage_deposit(&TransactionOutput::new_create_pp(
+                0,
+                vec![1; 10],
+                vec![2; 5]
+            )),
+            30
+        );
+
+        let issue = |value: Value| {
+            Transaction {
+                inputs: vec![input0.clone()],
+                outputs: vec![
+                    issuance(value),
+                    TransactionOutput::new_pubkey(
+                        ALICE_GENESIS_BALANCE - TokenIssuanceFee::get() - value,
+                        alice,
+                    ),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key)
+        };
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), issue(deposit - 1)),
+            "output value doesn't cover the storage deposit"
+        );
+        let issued = issue(deposit);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), issued.clone()));
+
+        // The deposit is reclaimed with the value of the output when it's spent
+        let transfer = TransactionOutput::new_p2pk_with_data(
+            0,
+            alice,
+            OutputData::TokenTransferV1 {
+                token_id: TokenId::new(&input0),
+                amount: 1_000,
+            },
+        );
+        let transfer_deposit = Utxo::storage_deposit(&transfer);
+        let change = issued.outputs[1].value + deposit - transfer_deposit;
+        let mut tx = Transaction {
+            inputs: vec![
+                TransactionInput::new_empty(issued.outpoint(0)),
+                TransactionInput::new_empty(issued.outpoint(1)),
+            ],
+            outputs: vec![
+                TransactionOutput {
+                    value: transfer_deposit,
+                    ..transfer
+                },
+                TransactionOutput::new_pubkey(change, alice),
+            ],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        for index in 0..2 {
+            tx = tx.sign_unchecked(&issued.outputs, index, &alice_pub_key);
+        }
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
+        assert_eq!(
+            UtxoStore::<Test>::get(tx.outpoint(1)).unwrap().value,
+            change
+        );
+
+        // The extrinsics put the deposit in the outputs they create
+        assert_ok!(Utxo::issue_token(
+            Origin::signed(alice),
+            b"SecT".to_vec(),
+            1_000,
+            2,
+            b"mintlayer.org".to_vec(),
+        ));
+        let issuance = UtxoStore::<Test>::iter_values()
+            .find(|output| matches!(output.data, Some(OutputData::TokenIssuanceV1 { .. })))
             .unwrap();
-    });
+        assert!(issuance.value > 0);
+        assert_eq!(issuance.value, Utxo::storage_deposit(&issuance));
+        StorageDepositPerByte::set(&0);
+    })
 }
 
 #[test]
-fn test_transfer_for_multiple_tokens() {
-    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
-    test_ext.execute_with(|| {
-        //
-        // Issue token 1 and send all tokens to Karl
-        //
+fn test_escrow_storage_deposit() {
+    execute_with_alice(|alice_pub_key| {
+        let alice = H256::from(alice_pub_key);
+        StorageDepositPerByte::set(&2);
         let (utxo0, input0) = tx_input_gen_no_signature();
-        let tx = Transaction {
+        let token_id = TokenId::new(&input0);
+        let issuance = TransactionOutput::new_p2pk_with_data(
+            0,
+            alice,
+            OutputData::TokenIssuanceV1 {
+                token_ticker: b"BensT".to_vec(),
+                amount_to_issue: 1_000,
+                number_of_decimals: 2,
+                metadata_uri: b"mintlayer.org".to_vec(),
+            },
+        );
+        let deposit = Utxo::storage_deposit(&issuance);
+        let issue = Transaction {
             inputs: vec![input0],
-            outputs: vec![TransactionOutput::new_p2pk_with_data(
-                ALICE_GENESIS_BALANCE - crate::tokens::Mlt(100).to_munit(),
-                H256::from(karl_pub_key),
-                OutputData::TokenIssuanceV1 {
-                    token_ticker: "TKN1".as_bytes().to_vec(),
-                    amount_to_issue: 1_000_000_000,
-                    number_of_decimals: 2,
-                    metadata_uri: "tkn1.mintlayer.org".as_bytes().to_vec(),
-                },
-            )],
-            time_lock: Default::default(),
-        }
-        .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key);
-        let tkn1_token_id = TokenId::new(&tx.inputs[0]);
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
-        let tkn1_utxo_hash = tx.outpoint(0);
-        let tkn1_utxo = tx.outputs[0].clone();
-        //
-        // Issue token 2 and send all tokens to Alice
-        //
-        let input1 = TransactionInput::new_empty(tkn1_utxo_hash);
-        let tx = Transaction {
-            inputs: vec![input1],
             outputs: vec![
-                TransactionOutput::new_p2pk_with_data(
-                    0,
-                    H256::from(alice_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id: tkn1_token_id.clone(),
-                        amount: 1_000_000_000,
-                    },
-                ),
-                TransactionOutput::new_p2pk_with_data(
-                    ALICE_GENESIS_BALANCE - crate::tokens::Mlt(100).to_munit(),
-                    H256::from(alice_pub_key),
-                    OutputData::TokenIssuanceV1 {
-                        token_ticker: "TKN2".as_bytes().to_vec(),
-                        amount_to_issue: 2_000_000_000,
-                        number_of_decimals: 4,
-                        metadata_uri: "tkn2.mintlayer.org".as_bytes().to_vec(),
-                    },
+                TransactionOutput {
+                    value: deposit,
+                    ..issuance
+                },
+                TransactionOutput::new_pubkey(
+                    ALICE_GENESIS_BALANCE - TokenIssuanceFee::get() - deposit,
+                    alice,
                 ),
             ],
             time_lock: Default::default(),
+            expires_at: None,
         }
-        .sign_unchecked(&[tkn1_utxo.clone()], 0, &karl_pub_key);
-        let tkn2_token_id = TokenId::new(&tx.inputs[0]);
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
-        let tkn1_utxo_hash = tx.outpoint(0);
-        let tkn2_utxo_hash = tx.outpoint(1);
-        //
-        // Issue token 3 and send all tokens to Karl
-        //
-        let input1 = TransactionInput::new_empty(tkn1_utxo_hash);
-        let input2 = TransactionInput::new_empty(tkn2_utxo_hash);
-        let prev_utxos = [tx.outputs[0].clone(), tx.outputs[1].clone()];
-        let tx = Transaction {
-            inputs: vec![input1, input2],
+        .sign_unchecked(&[utxo0], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), issue.clone()));
+
+        let mint_input = TransactionInput::new_empty(issue.outpoint(1));
+        let nft_id = TokenId::new(&mint_input);
+        let nft = TransactionOutput::new_p2pk_with_data(
+            0,
+            alice,
+            OutputData::NftMintV1 {
+                token_id: nft_id.clone(),
+                data_hash: NftDataHash::Hash32([3; 32]),
+                metadata_uri: b"mintlayer.org".to_vec(),
+                media_type: None,
+                transferable: true,
+                royalty: None,
+            },
+        );
+        let nft_deposit = Utxo::storage_deposit(&nft);
+        let mint = Transaction {
+            inputs: vec![mint_input],
             outputs: vec![
-                TransactionOutput::new_p2pk_with_data(
-                    0,
-                    H256::from(karl_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id: tkn1_token_id.clone(),
-                        amount: 1_000_000_000,
-                    },
-                ),
-                TransactionOutput::new_p2pk_with_data(
-                    0,
-                    H256::from(karl_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id: tkn2_token_id.clone(),
-                        amount: 2_000_000_000,
-                    },
-                ),
-                TransactionOutput::new_p2pk_with_data(
-                    ALICE_GENESIS_BALANCE - crate::tokens::Mlt(100).to_munit(),
-                    H256::from(karl_pub_key),
-                    OutputData::TokenIssuanceV1 {
-                        token_ticker: "TKN3".as_bytes().to_vec(),
-                        amount_to_issue: 3_000_000_000,
-                        number_of_decimals: 6,
-                        metadata_uri: "tkn3.mintlayer.org".as_bytes().to_vec(),
-                    },
-                ),
+                TransactionOutput {
+                    value: nft_deposit,
+                    ..nft
+                },
+                TransactionOutput::new_pubkey(issue.outputs[1].value - 10 - nft_deposit, alice),
             ],
             time_lock: Default::default(),
+            expires_at: None,
         }
-        .sign_unchecked(&prev_utxos, 0, &alice_pub_key)
-        .sign_unchecked(&prev_utxos, 1, &alice_pub_key);
-        let tkn3_token_id = TokenId::new(&tx.inputs[0]);
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
-        let tkn1_utxo_hash = tx.outpoint(0);
-        let tkn2_utxo_hash = tx.outpoint(1);
-        let tkn3_utxo_hash = tx.outpoint(2);
+        .sign_unchecked(&[issue.outputs[1].clone()], 0, &alice_pub_key);
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), mint.clone()));
 
-        //
-        // Transfer 3 kinds of tokens to Alice and check them all
-        //
-        let input1 = TransactionInput::new_empty(tkn1_utxo_hash);
-        let input2 = TransactionInput::new_empty(tkn2_utxo_hash);
-        let input3 = TransactionInput::new_empty(tkn3_utxo_hash);
-        let prev_utxos = [tx.outputs[0].clone(), tx.outputs[1].clone(), tx.outputs[2].clone()];
-        let tx = Transaction {
-            inputs: vec![input1, input2, input3],
+        // The auction and the order escrow the tokens without value, their deposits are reclaimed
+        let auction = TransactionOutput::new_auction(
+            alice,
+            100,
+            10,
+            OutputData::TokenTransferV1 {
+                token_id: nft_id,
+                amount: 1,
+            },
+        );
+        let order = TransactionOutput::new_order(
+            0,
+            Some(OutputData::TokenTransferV1 {
+                token_id,
+                amount: 1_000,
+            }),
+            alice,
+            None,
+            500,
+        );
+        assert_eq!(Utxo::storage_deposit(&auction), 0);
+        assert_eq!(Utxo::storage_deposit(&order), 0);
+        let spent = [mint.outputs[0].clone(), issue.outputs[0].clone(), mint.outputs[1].clone()];
+        let mut escrow = Transaction {
+            inputs: vec![
+                TransactionInput::new_empty(mint.outpoint(0)),
+                TransactionInput::new_empty(issue.outpoint(0)),
+                TransactionInput::new_empty(mint.outpoint(1)),
+            ],
             outputs: vec![
-                TransactionOutput::new_p2pk_with_data(
-                    0,
-                    H256::from(alice_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id: tkn1_token_id.clone(),
-                        amount: 1_000_000_000,
-                    },
-                ),
-                TransactionOutput::new_p2pk_with_data(
-                    0,
-                    H256::from(alice_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id: tkn2_token_id.clone(),
-                        amount: 2_000_000_000,
-                    },
-                ),
-                TransactionOutput::new_p2pk_with_data(
-                    ALICE_GENESIS_BALANCE - crate::tokens::Mlt(100).to_munit(),
-                    H256::from(alice_pub_key),
-                    OutputData::TokenTransferV1 {
-                        token_id: tkn3_token_id.clone(),
-                        amount: 3_000_000_000,
-                    },
+                auction,
+                order,
+                TransactionOutput::new_pubkey(
+                    spent.iter().map(|utxo| utxo.value).sum::<Value>() - 10,
+                    alice,
                 ),
             ],
             time_lock: Default::default(),
+            expires_at: None,
+        };
+        for index in 0..3 {
+            escrow = escrow.sign_unchecked(&spent, index, &alice_pub_key);
         }
-        .sign_unchecked(&prev_utxos, 0, &karl_pub_key)
-        .sign_unchecked(&prev_utxos, 1, &karl_pub_key)
-        .sign_unchecked(&prev_utxos, 2, &karl_pub_key);
-        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx.clone()));
-        let tkn1_utxo_hash = tx.outpoint(0);
-        let tkn2_utxo_hash = tx.outpoint(1);
-        let tkn3_utxo_hash = tx.outpoint(2);
-        // Check tkn1
-        UtxoStore::<Test>::get(tkn1_utxo_hash)
-            .unwrap()
-            .data
-            .map(|x| match x {
-                OutputData::TokenTransferV1 { token_id, amount } => {
-                    assert_eq!(token_id, tkn1_token_id);
-                    assert_eq!(amount, 1_000_000_000);
-                }
-                _ => {
-                    panic!("corrupted data");
-                }
-            })
-            .unwrap();
-        // Check tkn2
-        UtxoStore::<Test>::get(tkn2_utxo_hash)
-            .unwrap()
-            .data
-            .map(|x| match x {
-                OutputData::TokenTransferV1 { token_id, amount } => {
-                    assert_eq!(token_id, tkn2_token_id);
-                    assert_eq!(amount, 2_000_000_000);
-                }
-                _ => {
-                    panic!("corrupted data");
-                }
-            })
-            .unwrap();
-        // Check tkn3
-        UtxoStore::<Test>::get(tkn3_utxo_hash)
-            .unwrap()
-            .data
-            .map(|x| match x {
-                OutputData::TokenTransferV1 { token_id, amount } => {
-                    assert_eq!(token_id, tkn3_token_id);
-                    assert_eq!(amount, 3_000_000_000);
-                }
-                _ => {
-                    panic!("corrupted data");
-                }
-            })
-            .unwrap();
-    });
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), escrow.clone()));
+        assert!(UtxoStore::<Test>::contains_key(escrow.outpoint(0)));
+        assert!(UtxoStore::<Test>::contains_key(escrow.outpoint(1)));
+        assert_ok!(crate::pallet::try_state::<Test>());
+        StorageDepositPerByte::set(&0);
+    })
 }
 
 #[test]
-fn test_immutable_tx_format() {
-    // todo: Testing the compatibility of the old version with the new one - not done yet
+fn test_token_id() {
+    execute_with_alice(|alice_pub_key| {
+        let (utxo0, input0) = tx_input_gen_no_signature();
+        let mint = |token_id: TokenId| {
+            Transaction {
+                inputs: vec![input0.clone()],
+                outputs: vec![
+                    TransactionOutput::new_p2pk_with_data(
+                        0,
+                        H256::from(alice_pub_key),
+                        OutputData::NftMintV1 {
+                            token_id,
+                            data_hash: NftDataHash::Hash32([3; 32]),
+                            metadata_uri: "mintlayer.org".as_bytes().to_vec(),
+                            media_type: None,
+                            transferable: true,
+                            royalty: None,
+                        },
+                    ),
+                    TransactionOutput::new_pubkey(
+                        ALICE_GENESIS_BALANCE - NftMintFee::get(),
+                        H256::from(alice_pub_key),
+                    ),
+                ],
+                time_lock: Default::default(),
+                expires_at: None,
+            }
+            .sign_unchecked(&[utxo0.clone()], 0, &alice_pub_key)
+        };
+
+        // The id only depends on the outpoint of the first input, not on its signature
+        let token_id = TokenId::from_outpoint(&input0.outpoint);
+        let tx = mint(token_id.clone());
+        assert_eq!(TokenId::new(&input0), token_id);
+        assert_eq!(TokenId::new(&tx.inputs[0]), token_id);
+
+        // The id can't be picked freely
+        let other_id = TokenId::from_outpoint(&H256::repeat_byte(1));
+        frame_support::assert_err_ignore_postinfo!(
+            Utxo::spend(Origin::signed(H256::zero()), mint(other_id)),
+            "nft token id must be derived from the first input"
+        );
+        assert_ok!(Utxo::spend(Origin::signed(H256::zero()), tx));
+        assert!(Utxo::token_issuance_transactions(&token_id).is_some());
+    })
 }
 
 #[test]
-fn test_burn_tokens() {
-    // todo: Burn tokens has not tested yet
+fn test_migrate_to_v1() {
+    use crate::migrations::v0;
+    use frame_support::traits::{GetStorageVersion, StorageVersion};
+
+    let (mut test_ext, alice_pub_key, _karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        // The outputs as stored before the storage version was tracked
+        StorageVersion::new(0).put::<Utxo>();
+        let script_hash = H256::repeat_byte(5);
+        let put_v0 = |key: H256, destination: v0::Destination<H256>| {
+            let output = v0::TransactionOutput {
+                value: 10,
+                destination,
+                data: None,
+            };
+            frame_support::storage::unhashed::put(&UtxoStore::<Test>::hashed_key_for(key), &output);
+        };
+        put_v0(H256::repeat_byte(1), v0::Destination::Pubkey(alice_pub_key));
+        put_v0(
+            H256::repeat_byte(2),
+            v0::Destination::ScriptHash(script_hash),
+        );
+        let issuance = v0::Transaction {
+            inputs: vec![TransactionInput::new_empty(H256::repeat_byte(3))],
+            outputs: vec![v0::TransactionOutput {
+                value: 0,
+                destination: v0::Destination::Pubkey(alice_pub_key),
+                data: Some(OutputData::TokenIssuanceV1 {
+                    token_ticker: b"BTC".to_vec(),
+                    amount_to_issue: 1_000,
+                    number_of_decimals: 2,
+                    metadata_uri: vec![],
+                }),
+            }],
+            time_lock: Default::default(),
+        };
+        let token_id = TokenId::new(&issuance.inputs[0]);
+        frame_support::storage::unhashed::put(
+            &crate::TokenIssuanceTransactions::<Test>::hashed_key_for(&token_id),
+            &issuance,
+        );
+
+        crate::migrations::migrate::<Test>();
+        assert_eq!(Utxo::on_chain_storage_version(), 1);
+        assert_eq!(
+            UtxoStore::<Test>::get(H256::repeat_byte(1)),
+            Some(TransactionOutput::new_pubkey(10, H256::from(alice_pub_key)))
+        );
+        assert_eq!(
+            UtxoStore::<Test>::get(H256::repeat_byte(2)).map(|output| output.destination),
+            Some(Destination::ScriptHash(ScriptVersion::V0, script_hash))
+        );
+
+        // The issuance transaction never expires and keeps its outpoints
+        let tx = Utxo::token_issuance_transactions(&token_id).unwrap();
+        assert_eq!(tx.expires_at, None);
+        assert_eq!(tx.outpoint(0), BlakeTwo256::hash_of(&(&issuance, 0u64)));
+        let expiring = Transaction {
+            expires_at: Some(RawBlockTime::new(100)),
+            ..tx.clone()
+        };
+        assert_ne!(expiring.outpoint(0), tx.outpoint(0));
+
+        // Migrated data is left alone
+        let utxos: Vec<_> = UtxoStore::<Test>::iter().collect();
+        crate::migrations::migrate::<Test>();
+        assert_eq!(UtxoStore::<Test>::iter().collect::<Vec<_>>(), utxos);
+    })
 }
 
 #[test]
-fn test_token_id() {
-    // todo: Testing token id - not done yet
+fn test_migrate_staking_locks_to_v1() {
+    use crate::migrations::v0;
+    use frame_support::traits::StorageVersion;
+
+    let (mut test_ext, alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        StorageVersion::new(0).put::<Utxo>();
+        let (alice, karl) = (H256::from(alice_pub_key), H256::from(karl_pub_key));
+        let lock = v0::TransactionOutput {
+            value: 10,
+            destination: v0::Destination::LockForStaking {
+                stash_account: alice,
+                controller_account: karl,
+                session_key: vec![1, 2, 3],
+            },
+            data: None,
+        };
+        let key = H256::repeat_byte(1);
+        frame_support::storage::unhashed::put(
+            &crate::LockedUtxos::<Test>::hashed_key_for(key),
+            &lock,
+        );
+
+        // The locks of validators staking before the new fields got their defaults
+        crate::migrations::migrate::<Test>();
+        assert_eq!(
+            Utxo::locked_utxos(key).map(|output| output.destination),
+            Some(Destination::LockForStaking {
+                stash_account: alice,
+                controller_account: karl,
+                session_key: vec![1, 2, 3],
+                withdrawal_key: None,
+                secondary_controller: None,
+                commission: Default::default(),
+                min_self_bond: 0,
+            })
+        );
+    })
+}
+
+#[test]
+fn test_migrate_contract_calls_to_v1() {
+    use crate::migrations::v0;
+    use frame_support::traits::StorageVersion;
+
+    let (mut test_ext, _alice_pub_key, karl_pub_key) = alice_test_ext_and_keys();
+    test_ext.execute_with(|| {
+        StorageVersion::new(0).put::<Utxo>();
+        let contract = H256::from(karl_pub_key);
+        let call = v0::TransactionOutput {
+            value: 10,
+            destination: v0::Destination::CallPP(contract, true, vec![1, 2, 3]),
+            data: None,
+        };
+        let key = H256::repeat_byte(1);
+        frame_support::storage::unhashed::put(&UtxoStore::<Test>::hashed_key_for(key), &call);
+
+        // The calls made before ABI hashes existed have none
+        crate::migrations::migrate::<Test>();
+        assert_eq!(
+            UtxoStore::<Test>::get(key).map(|output| output.destination),
+            Some(Destination::CallPP(contract, true, vec![1, 2, 3], None))
+        );
+    })
 }
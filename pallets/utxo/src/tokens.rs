@@ -8,8 +8,9 @@ use frame_support::{dispatch::Vec, RuntimeDebug};
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
 use sp_core::Hasher;
-use sp_core::H160;
+use sp_core::{sr25519, H160, H256};
 use sp_runtime::traits::BlakeTwo256;
+use sp_runtime::Permill;
 use sp_std::vec;
 
 const LENGTH_BYTES_TO_REPRESENT_ID: usize = 20;
@@ -30,15 +31,32 @@ pub struct TokenId {
 }
 
 impl TokenId {
-    // Token id depends on signed or unsigned the same input
+    /// Id of the token issued or the NFT minted by a transaction with given first input.
     pub fn new(first_input: &TransactionInput) -> TokenId {
-        let first_input_hash = BlakeTwo256::hash(first_input.encode().as_slice());
+        Self::from_outpoint(&first_input.outpoint)
+    }
+
+    /// Id of the token issued or the NFT minted by a transaction whose first input spends
+    /// `outpoint`. The lock and the witness of the input don't matter, so clients can compute
+    /// the id before signing, and an outpoint can only be spent once, so ids never collide.
+    pub fn from_outpoint(outpoint: &H256) -> TokenId {
+        let outpoint_hash = BlakeTwo256::hash(outpoint.encode().as_slice());
         TokenId {
             // We are loosing the first bytes of H256 over here and using 20 the last bytes
-            inner: H160::from(first_input_hash),
+            inner: H160::from(outpoint_hash),
         }
     }
 
+    /// Token id from the 20 bytes it consists of
+    pub fn from_h160(inner: H160) -> TokenId {
+        TokenId { inner }
+    }
+
+    /// The 20 bytes the token id consists of
+    pub fn as_h160(&self) -> H160 {
+        self.inner
+    }
+
     pub fn to_string(&self) -> Vec<u8> {
         self.inner.as_bytes().to_mls_b58check(Some(vec![TOKEN_ID_PREFIX])).to_vec()
     }
@@ -82,40 +100,478 @@ pub enum OutputData {
         number_of_decimals: u8,
         metadata_uri: Vec<u8>,
     },
-    // todo: This part isn't fully tested, left for the next PR
-
-    // // Burning a token or NFT
-    // #[codec(index = 3)]
-    // TokenBurnV1 {
-    //     token_id: TokenId,
-    //     amount_to_burn: Value,
-    // },
-    // // A new NFT creation
-    // #[codec(index = 4)]
-    // NftMintV1 {
-    //     token_id: TokenId,
-    //     data_hash: NftDataHash,
-    //     metadata_uri: Vec<u8>,
-    // },
-}
-
-// todo: This part isn't fully tested, left for the next PR
-// #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
-// #[derive(Clone, Encode, Decode, Eq, PartialEq, PartialOrd, Ord, RuntimeDebug)]
-// pub enum NftDataHash {
-//     #[codec(index = 1)]
-//     Hash32([u8; 32]),
-//     #[codec(index = 2)]
-//     Raw(Vec<u8>),
-//     // Or any type that you want to implement
-// }
+    // Burning a token or NFT
+    #[codec(index = 3)]
+    TokenBurnV1 {
+        token_id: TokenId,
+        amount_to_burn: Value,
+    },
+    // A new NFT creation
+    #[codec(index = 4)]
+    NftMintV1 {
+        token_id: TokenId,
+        data_hash: NftDataHash,
+        metadata_uri: Vec<u8>,
+        // MIME type of the digital data, e.g. "image/png"
+        media_type: Option<Vec<u8>>,
+        // Soul-bound NFTs can't be transferred, only burned
+        transferable: bool,
+        // Paid by every later transfer of this NFT, if set
+        royalty: Option<NftRoyalty>,
+    },
+    // Several tokens sent to the same destination in one output. Every token may appear only
+    // once, NFTs are always transferred on their own.
+    #[codec(index = 5)]
+    TokenMultiV1(Vec<(TokenId, Value)>),
+    // Tokens locked under bridge custody, to be minted at the given address of the target chain.
+    // See the `bridge` module.
+    #[codec(index = 6)]
+    TokenLockForBridgeV1 {
+        token_id: TokenId,
+        amount: Value,
+        target_chain: u32,
+        target_address: Vec<u8>,
+    },
+}
+
+/// Fingerprint of the digital data an NFT stands for.
+///
+/// Apart from the legacy `Hash32` and `Raw` forms, the variant declares which algorithm produced
+/// the hash so that marketplaces can verify off-chain assets consistently. The declared algorithm
+/// has to match the hash length, see [NftDataHash::validate].
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, PartialOrd, Ord, RuntimeDebug)]
+pub enum NftDataHash {
+    #[codec(index = 1)]
+    Hash32([u8; 32]),
+    #[codec(index = 2)]
+    Raw(Vec<u8>),
+    /// SHA-256 digest of the data
+    #[codec(index = 3)]
+    Sha256(Vec<u8>),
+    /// Blake2b-256 digest of the data
+    #[codec(index = 4)]
+    Blake2b256(Vec<u8>),
+    /// Binary IPFS CIDv1 of the data
+    #[codec(index = 5)]
+    IpfsCidV1(Vec<u8>),
+}
+
+impl NftDataHash {
+    /// Length of SHA-256 and Blake2b-256 digests
+    const DIGEST_LEN: usize = 32;
+    /// Longest hash we accept
+    const MAX_LEN: usize = 128;
+
+    // Multicodec codes of the multihash functions accepted in CIDs
+    const MULTIHASH_SHA2_256: u64 = 0x12;
+    const MULTIHASH_BLAKE2B_256: u64 = 0xb220;
+
+    /// Check the hash length matches the declared algorithm.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        match self {
+            NftDataHash::Hash32(_) => Ok(()),
+            NftDataHash::Raw(raw) => {
+                ensure!(!raw.is_empty(), "nft data hash can't be empty");
+                ensure!(raw.len() <= Self::MAX_LEN, "nft data hash is too long");
+                Ok(())
+            }
+            NftDataHash::Sha256(hash) | NftDataHash::Blake2b256(hash) => {
+                ensure!(
+                    hash.len() == Self::DIGEST_LEN,
+                    "nft data hash length mismatch"
+                );
+                Ok(())
+            }
+            NftDataHash::IpfsCidV1(cid) => {
+                ensure!(cid.len() <= Self::MAX_LEN, "nft data hash is too long");
+                Self::validate_cid_v1(cid)
+            }
+        }
+    }
+
+    // A binary CIDv1 is `<version><content codec><hash fn><digest length><digest>`,
+    // all but the digest being unsigned varints.
+    fn validate_cid_v1(cid: &[u8]) -> Result<(), &'static str> {
+        let mut rest = cid;
+        let version = read_uvarint(&mut rest).ok_or("invalid ipfs cid")?;
+        ensure!(version == 1, "unsupported ipfs cid version");
+        let _codec = read_uvarint(&mut rest).ok_or("invalid ipfs cid")?;
+        let hash_fn = read_uvarint(&mut rest).ok_or("invalid ipfs cid")?;
+        let digest_len = read_uvarint(&mut rest).ok_or("invalid ipfs cid")?;
+        ensure!(
+            hash_fn == Self::MULTIHASH_SHA2_256 || hash_fn == Self::MULTIHASH_BLAKE2B_256,
+            "unsupported ipfs cid hash function"
+        );
+        ensure!(
+            digest_len == Self::DIGEST_LEN as u64 && rest.len() == Self::DIGEST_LEN,
+            "nft data hash length mismatch"
+        );
+        Ok(())
+    }
+}
+
+/// Read an unsigned LEB128 varint, as used by multiformats, advancing the input.
+fn read_uvarint(input: &mut &[u8]) -> Option<u64> {
+    let bytes: &[u8] = *input;
+    let mut result: u64 = 0;
+    // multiformats limit varints to 9 bytes
+    for (i, byte) in bytes.iter().take(9).enumerate() {
+        result |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            *input = &bytes[i + 1..];
+            return Some(result);
+        }
+    }
+    None
+}
+
+/// Check a media type looks like `type/subtype`.
+pub fn validate_media_type(media_type: &[u8]) -> Result<(), &'static str> {
+    ensure!(
+        media_type.is_ascii(),
+        "media type has none ascii characters"
+    );
+    ensure!(media_type.len() <= 64, "media type is too long");
+    let mut parts = media_type.split(|c| *c == b'/');
+    let valid = matches!(
+        (parts.next(), parts.next(), parts.next()),
+        (Some(main), Some(sub), None) if !main.is_empty() && !sub.is_empty()
+    );
+    ensure!(valid, "invalid media type");
+    Ok(())
+}
+
+/// Characters a token ticker may be made of.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub enum TickerCharset {
+    /// Any ASCII character
+    #[codec(index = 0)]
+    Ascii,
+    /// Uppercase letters `A` to `Z` only
+    #[codec(index = 1)]
+    UppercaseAlpha,
+}
+
+/// Rules the metadata of a new token has to follow. The pallet takes them from its `Config`
+/// constants, wallets can check the metadata of a token with [TokenMetadataRules::validate]
+/// before issuing it.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct TokenMetadataRules {
+    pub max_ticker_length: u32,
+    pub max_decimals: u8,
+    pub max_metadata_uri_length: u32,
+    pub ticker_charset: TickerCharset,
+}
+
+impl TokenMetadataRules {
+    /// The most permissive rules a runtime can configure. The tokens already issued are held to
+    /// them when spent, so that tightening the rules never locks a token.
+    pub const BOUNDS: TokenMetadataRules = TokenMetadataRules {
+        max_ticker_length: 12,
+        max_decimals: 18,
+        max_metadata_uri_length: 256,
+        ticker_charset: TickerCharset::Ascii,
+    };
+
+    /// Whether the rules are at most as permissive as [TokenMetadataRules::BOUNDS].
+    pub fn is_within_bounds(&self) -> bool {
+        self.max_ticker_length <= Self::BOUNDS.max_ticker_length
+            && self.max_decimals <= Self::BOUNDS.max_decimals
+            && self.max_metadata_uri_length <= Self::BOUNDS.max_metadata_uri_length
+    }
+
+    /// Check the metadata of a new token. Whether the ticker is reserved or already taken is
+    /// up to the chain, see `Pallet::validate_token_metadata`.
+    pub fn validate(
+        &self,
+        ticker: &[u8],
+        number_of_decimals: u8,
+        metadata_uri: &[u8],
+    ) -> Result<(), &'static str> {
+        ensure!(ticker.is_ascii(), "token ticker has none ascii characters");
+        self.validate_metadata_uri(metadata_uri)?;
+        ensure!(
+            ticker.len() <= self.max_ticker_length as usize,
+            "token ticker is too long"
+        );
+        ensure!(!ticker.is_empty(), "token ticker can't be empty");
+        ensure!(
+            self.ticker_charset == TickerCharset::Ascii
+                || ticker.iter().all(|c| c.is_ascii_uppercase()),
+            "token ticker must be uppercase letters"
+        );
+        ensure!(number_of_decimals <= self.max_decimals, "too long decimals");
+        Ok(())
+    }
+
+    /// Check the metadata uri of a new token or NFT.
+    pub fn validate_metadata_uri(&self, metadata_uri: &[u8]) -> Result<(), &'static str> {
+        ensure!(
+            metadata_uri.is_ascii(),
+            "metadata uri has none ascii characters"
+        );
+        ensure!(
+            metadata_uri.len() <= self.max_metadata_uri_length as usize,
+            "token metadata uri is too long"
+        );
+        Ok(())
+    }
+}
+
+/// Royalty owed to the NFT creator whenever the NFT changes hands.
+///
+/// Every transfer to another destination owes at least the fixed `minimum`, since the price may
+/// well be paid outside of the transaction. On top of that, the rate is charged on the MLT
+/// proceeds of the seller, i.e. how much more MLT the transaction pays to the destination
+/// currently holding the NFT than that destination puts in.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, PartialOrd, Ord, RuntimeDebug)]
+pub struct NftRoyalty {
+    /// Receiver of the royalty payments
+    pub beneficiary: sr25519::Public,
+    /// Royalty rate in basis points (1/100 of a percent)
+    pub basis_points: u16,
+    /// Least royalty paid by every transfer, must be nonzero
+    pub minimum: Value,
+}
+
+impl NftRoyalty {
+    /// 100% expressed in basis points
+    pub const MAX_BASIS_POINTS: u16 = 10_000;
+
+    /// The royalty amount due on the given proceeds, rounded up and at least the minimum.
+    pub fn amount_due(&self, proceeds: Value) -> Value {
+        // one basis point is 100 parts per million
+        Permill::from_parts(self.basis_points as u32 * 100)
+            .mul_ceil(proceeds)
+            .max(self.minimum)
+    }
+}
 
 impl OutputData {
     pub(crate) fn id(&self, first_input: &TransactionInput) -> Option<TokenId> {
         match self {
             OutputData::TokenTransferV1 { ref token_id, .. } => Some(token_id.clone()),
-            // OutputData::NftMintV1 { .. } |
+            OutputData::NftMintV1 { ref token_id, .. } => Some(token_id.clone()),
+            OutputData::TokenBurnV1 { ref token_id, .. } => Some(token_id.clone()),
             OutputData::TokenIssuanceV1 { .. } => Some(TokenId::new(first_input)),
+            OutputData::TokenLockForBridgeV1 { ref token_id, .. } => Some(token_id.clone()),
+            // There is no single id, see `token_ids`
+            OutputData::TokenMultiV1(_) => None,
+        }
+    }
+
+    /// Ids of all the tokens carried by the data
+    pub(crate) fn token_ids(&self, first_input: &TransactionInput) -> Vec<TokenId> {
+        match self {
+            OutputData::TokenMultiV1(tokens) => {
+                tokens.iter().map(|(token_id, _)| token_id.clone()).collect()
+            }
+            data => data.id(first_input).into_iter().collect(),
+        }
+    }
+}
+
+/// Second version of the output data, see `versioned::VersionedOutputData`.
+///
+/// Token transfers are unified: a transfer carries any number of tokens, so that one token is no
+/// longer a special case of several. The other variants are the same as in [OutputData].
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Eq, PartialEq, PartialOrd, Ord, RuntimeDebug)]
+pub enum OutputDataV2 {
+    // Tokens sent to the destination. Every token may appear only once.
+    #[codec(index = 1)]
+    TokenTransfer(Vec<(TokenId, Value)>),
+    #[codec(index = 2)]
+    TokenIssuance {
+        token_ticker: Vec<u8>,
+        amount_to_issue: Value,
+        number_of_decimals: u8,
+        metadata_uri: Vec<u8>,
+    },
+    #[codec(index = 3)]
+    TokenBurn {
+        token_id: TokenId,
+        amount_to_burn: Value,
+    },
+    #[codec(index = 4)]
+    NftMint {
+        token_id: TokenId,
+        data_hash: NftDataHash,
+        metadata_uri: Vec<u8>,
+        media_type: Option<Vec<u8>>,
+        transferable: bool,
+        royalty: Option<NftRoyalty>,
+    },
+    #[codec(index = 5)]
+    TokenLockForBridge {
+        token_id: TokenId,
+        amount: Value,
+        target_chain: u32,
+        target_address: Vec<u8>,
+    },
+}
+
+impl From<OutputData> for OutputDataV2 {
+    fn from(data: OutputData) -> Self {
+        match data {
+            OutputData::TokenTransferV1 { token_id, amount } => {
+                OutputDataV2::TokenTransfer(vec![(token_id, amount)])
+            }
+            OutputData::TokenMultiV1(tokens) => OutputDataV2::TokenTransfer(tokens),
+            OutputData::TokenIssuanceV1 {
+                token_ticker,
+                amount_to_issue,
+                number_of_decimals,
+                metadata_uri,
+            } => OutputDataV2::TokenIssuance {
+                token_ticker,
+                amount_to_issue,
+                number_of_decimals,
+                metadata_uri,
+            },
+            OutputData::TokenBurnV1 {
+                token_id,
+                amount_to_burn,
+            } => OutputDataV2::TokenBurn {
+                token_id,
+                amount_to_burn,
+            },
+            OutputData::NftMintV1 {
+                token_id,
+                data_hash,
+                metadata_uri,
+                media_type,
+                transferable,
+                royalty,
+            } => OutputDataV2::NftMint {
+                token_id,
+                data_hash,
+                metadata_uri,
+                media_type,
+                transferable,
+                royalty,
+            },
+            OutputData::TokenLockForBridgeV1 {
+                token_id,
+                amount,
+                target_chain,
+                target_address,
+            } => OutputDataV2::TokenLockForBridge {
+                token_id,
+                amount,
+                target_chain,
+                target_address,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cid(hash_fn: &[u8], digest_len: u8, digest: &[u8]) -> Vec<u8> {
+        // version 1, raw codec (0x55)
+        let mut cid = vec![0x01, 0x55];
+        cid.extend_from_slice(hash_fn);
+        cid.push(digest_len);
+        cid.extend_from_slice(digest);
+        cid
+    }
+
+    #[test]
+    fn data_hash_lengths() {
+        assert!(NftDataHash::Sha256(vec![1; 32]).validate().is_ok());
+        assert!(NftDataHash::Blake2b256(vec![1; 32]).validate().is_ok());
+        assert_eq!(
+            NftDataHash::Sha256(vec![1; 20]).validate(),
+            Err("nft data hash length mismatch")
+        );
+        assert_eq!(
+            NftDataHash::Blake2b256(vec![1; 64]).validate(),
+            Err("nft data hash length mismatch")
+        );
+        assert!(NftDataHash::Raw(vec![]).validate().is_err());
+    }
+
+    #[test]
+    fn data_hash_ipfs_cid() {
+        // sha2-256
+        assert!(NftDataHash::IpfsCidV1(cid(&[0x12], 32, &[7; 32])).validate().is_ok());
+        // blake2b-256, its code 0xb220 takes two varint bytes
+        assert!(
+            NftDataHash::IpfsCidV1(cid(&[0xa0, 0xe4, 0x02], 32, &[7; 32]))
+                .validate()
+                .is_ok()
+        );
+        // declared length doesn't match the digest
+        assert_eq!(
+            NftDataHash::IpfsCidV1(cid(&[0x12], 32, &[7; 31])).validate(),
+            Err("nft data hash length mismatch")
+        );
+        // unknown hash function
+        assert!(NftDataHash::IpfsCidV1(cid(&[0x13], 64, &[7; 64])).validate().is_err());
+        // CIDv0 is not supported
+        let mut v0 = cid(&[0x12], 32, &[7; 32]);
+        v0[0] = 0x00;
+        assert!(NftDataHash::IpfsCidV1(v0).validate().is_err());
+        // truncated varint
+        assert!(NftDataHash::IpfsCidV1(vec![0x01, 0x80]).validate().is_err());
+    }
+
+    #[test]
+    fn media_types() {
+        assert!(validate_media_type(b"image/png").is_ok());
+        assert!(validate_media_type(b"model/gltf+json").is_ok());
+        assert!(validate_media_type(b"image").is_err());
+        assert!(validate_media_type(b"image/").is_err());
+        assert!(validate_media_type(b"a/b/c").is_err());
+    }
+
+    #[test]
+    fn token_metadata_rules() {
+        let rules = TokenMetadataRules {
+            max_ticker_length: 5,
+            max_decimals: 8,
+            max_metadata_uri_length: 10,
+            ticker_charset: TickerCharset::UppercaseAlpha,
+        };
+        assert!(rules.is_within_bounds());
+        assert_eq!(rules.validate(b"BENS", 8, b"ml.org"), Ok(()));
+        assert_eq!(
+            rules.validate(b"BENSTK", 8, b"ml.org"),
+            Err("token ticker is too long")
+        );
+        assert_eq!(
+            rules.validate(b"Bens", 8, b"ml.org"),
+            Err("token ticker must be uppercase letters")
+        );
+        assert_eq!(
+            rules.validate(b"BEN5", 8, b"ml.org"),
+            Err("token ticker must be uppercase letters")
+        );
+        assert_eq!(
+            rules.validate(b"BENS", 9, b"ml.org"),
+            Err("too long decimals")
+        );
+        assert_eq!(
+            rules.validate(b"BENS", 8, b"mintlayer.org"),
+            Err("token metadata uri is too long")
+        );
+        let ascii = TokenMetadataRules {
+            ticker_charset: TickerCharset::Ascii,
+            ..rules
+        };
+        assert_eq!(ascii.validate(b"Ben5", 8, b"ml.org"), Ok(()));
+        assert!(TokenMetadataRules::BOUNDS.validate(b"Ben5", 18, &[b'a'; 256]).is_ok());
+        assert!(!TokenMetadataRules {
+            max_decimals: 19,
+            ..rules
         }
+        .is_within_bounds());
     }
 }
@@ -0,0 +1,390 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Script verification
+//!
+//! The scripts of the inputs of a transaction don't depend on each other, so the node can verify
+//! them on several threads. The runtime hands them over through the `script_verifier` host
+//! functions. If the node registered a [ScriptVerifierExt] extension, it verifies them in
+//! parallel, otherwise the runtime verifies them itself one by one. A transaction is valid only if
+//! all of its scripts are, so the result doesn't depend on which of them ran.
+//!
+//! A transaction is verified when it enters the pool and once more when its block is imported.
+//! The node can remember the transactions whose scripts and signatures were found valid by the
+//! pool in a [VerificationCache], so that the import skips verifying them again. Only the pool
+//! adds entries: its rules are stricter than the consensus ones, so its results hold in blocks
+//! too.
+//!
+//! Skipping makes the import depend on state kept by the node outside of the runtime, which it
+//! has to trust: a node whose cache held a key it shouldn't would accept a block the other nodes
+//! reject, and split from them. The cache is only filled by the pool of the node itself, after a
+//! full verification, never by blocks or by peers. Its key, see [cache_key], commits to everything
+//! the verification depends on: the SCALE-encoded transaction with its witnesses, which are the
+//! exact bytes the `spend` call carries, the outputs it spends, the chain the signatures are
+//! bound to and the scripts with the ages of the UTXOs they spend. A transaction differing in any
+//! of them, even only in a witness and so under the same txid, misses the cache and is verified.
+//!
+//! A wasm runtime calling host functions can only run on nodes providing them, so the wasm
+//! runtime only calls them when built with the `host-verifier` feature. Without it, the runtime
+//! verifies everything itself and never skips a verification, which gives the same results. The
+//! native runtime calls them directly and doesn't need the feature.
+//!
+//! Before any script or signature is verified, the witnesses go through a stage checking their
+//! canonicality, see [check_witness_canonicality]. The outpoints of a transaction commit to its
+//! witnesses, so a witness that could be re-encoded by anyone relaying the transaction would
+//! change the outpoints of its outputs and break the transactions already spending them.
+
+use crate::script::{self, InputAge, ScriptRules};
+use crate::sign::{self, ChainBinding};
+use crate::taproot::ScriptPathWitness;
+use crate::{Destination, Transaction, TransactionInput, TransactionOutput};
+use chainscript::Builder;
+use codec::{Decode, Encode};
+use core::time::Duration;
+use sp_core::H256;
+#[cfg(feature = "std")]
+use sp_externalities::ExternalitiesExt;
+use sp_runtime::traits::{BlakeTwo256, Hash};
+use sp_runtime_interface::runtime_interface;
+use sp_std::prelude::*;
+#[cfg(feature = "std")]
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+/// Script spent by the input at `index`, see [script::verify_script].
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug)]
+pub struct ScriptCheck {
+    pub index: u64,
+    /// Age of the spent UTXO in blocks and milliseconds, if it is known
+    age: Option<(u32, u64)>,
+    pub witness: Vec<u8>,
+    pub lock: Vec<u8>,
+}
+
+impl ScriptCheck {
+    pub fn new(index: u64, age: Option<InputAge>, witness: Vec<u8>, lock: Vec<u8>) -> Self {
+        Self {
+            index,
+            age: age.map(|age| (age.blocks, age.time.as_millis() as u64)),
+            witness,
+            lock,
+        }
+    }
+
+    fn age(&self) -> Option<InputAge> {
+        self.age.map(|(blocks, millis)| InputAge {
+            blocks,
+            time: Duration::from_millis(millis),
+        })
+    }
+
+    fn verify<AccountId: Encode>(
+        &self,
+        tx: &Transaction<AccountId>,
+        utxos: &[TransactionOutput<AccountId>],
+        rules: ScriptRules,
+        chain: ChainBinding,
+    ) -> bool {
+        let (witness, lock) = (self.witness.clone(), self.lock.clone());
+        let age = self.age();
+        script::verify_script(tx, utxos, self.index, rules, chain, age, witness, lock).is_ok()
+    }
+}
+
+/// Scripts of a transaction handed over to the node.
+#[derive(Encode, Decode)]
+struct ScriptBatch {
+    /// SCALE-encoded transaction
+    tx: Vec<u8>,
+    /// SCALE-encoded outputs spent by the transaction
+    utxos: Vec<u8>,
+    rules: ScriptRules,
+    chain: ChainBinding,
+    checks: Vec<ScriptCheck>,
+}
+
+#[cfg(feature = "std")]
+sp_externalities::decl_extension! {
+    /// Verifies a SCALE-encoded [ScriptBatch] on the node, usually [verify_batch_parallel] for
+    /// the account id of the runtime. Returns the result of each script in order, `None` if the
+    /// batch can't be decoded.
+    pub struct ScriptVerifierExt(fn(&[u8]) -> Option<Vec<bool>>);
+}
+
+/// Host functions verifying scripts on the node.
+#[runtime_interface]
+pub trait ScriptVerifier {
+    /// Verify a SCALE-encoded [ScriptBatch]. `None` if the node doesn't verify scripts.
+    fn verify_batch(&mut self, batch: &[u8]) -> Option<Vec<bool>> {
+        self.extension::<ScriptVerifierExt>().and_then(|verifier| (verifier.0)(batch))
+    }
+}
+
+/// Verify a SCALE-encoded [ScriptBatch] on the node, `None` if it doesn't.
+#[cfg(any(feature = "std", feature = "host-verifier"))]
+fn verify_batch_on_node(batch: &[u8]) -> Option<Vec<bool>> {
+    script_verifier::verify_batch(batch)
+}
+
+/// The wasm runtime doesn't call [script_verifier] without the `host-verifier` feature.
+#[cfg(not(any(feature = "std", feature = "host-verifier")))]
+fn verify_batch_on_node(_batch: &[u8]) -> Option<Vec<bool>> {
+    None
+}
+
+/// Most threads a batch is verified on.
+#[cfg(feature = "std")]
+const MAX_WORKERS: usize = 8;
+
+/// Verify a SCALE-encoded [ScriptBatch] on up to [MAX_WORKERS] threads.
+#[cfg(feature = "std")]
+pub fn verify_batch_parallel<AccountId>(batch: &[u8]) -> Option<Vec<bool>>
+where
+    AccountId: Encode + Decode + Send + Sync + 'static,
+{
+    let batch = ScriptBatch::decode(&mut &batch[..]).ok()?;
+    let tx = Arc::new(Transaction::<AccountId>::decode(&mut &batch.tx[..]).ok()?);
+    let utxos = Arc::new(Vec::<TransactionOutput<AccountId>>::decode(&mut &batch.utxos[..]).ok()?);
+    let (rules, chain) = (batch.rules, batch.chain);
+    if utxos.len() != tx.inputs.len()
+        || batch.checks.iter().any(|check| check.index as usize >= tx.inputs.len())
+    {
+        return None;
+    }
+
+    let chunk_size = (batch.checks.len() + MAX_WORKERS - 1) / MAX_WORKERS;
+    let workers: Vec<_> = batch
+        .checks
+        .chunks(chunk_size.max(1))
+        .map(|checks| {
+            let (tx, utxos, checks) = (tx.clone(), utxos.clone(), checks.to_vec());
+            std::thread::spawn(move || {
+                checks
+                    .iter()
+                    .map(|check| check.verify(&tx, &utxos, rules, chain))
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let mut results = Vec::with_capacity(batch.checks.len());
+    for worker in workers {
+        results.extend(worker.join().ok()?);
+    }
+    Some(results)
+}
+
+/// Verify the scripts of a transaction, on the node if it verifies scripts. Return whether all
+/// of them are valid.
+pub fn verify_all<AccountId: Encode>(
+    tx: &Transaction<AccountId>,
+    utxos: &[TransactionOutput<AccountId>],
+    rules: ScriptRules,
+    chain: ChainBinding,
+    checks: Vec<ScriptCheck>,
+) -> bool {
+    if checks.len() < 2 {
+        return checks.iter().all(|check| check.verify(tx, utxos, rules, chain));
+    }
+    let batch = ScriptBatch {
+        tx: tx.encode(),
+        utxos: utxos.encode(),
+        rules,
+        chain,
+        checks,
+    };
+    match verify_batch_on_node(&batch.encode()) {
+        Some(results) if results.len() == batch.checks.len() => results.iter().all(|valid| *valid),
+        // Deterministic fallback, the node doesn't verify scripts
+        _ => batch.checks.iter().all(|check| check.verify(tx, utxos, rules, chain)),
+    }
+}
+
+/// Key of the scripts and signatures of a transaction in the [VerificationCache]. It commits to
+/// the transaction with its witnesses, `utxos`, `chain` and `checks`, see the module docs.
+pub fn cache_key<AccountId: Encode>(
+    tx: &Transaction<AccountId>,
+    utxos: &[TransactionOutput<AccountId>],
+    chain: ChainBinding,
+    checks: &[ScriptCheck],
+) -> H256 {
+    BlakeTwo256::hash_of(&(tx, utxos, chain, checks))
+}
+
+/// Check the witness of `input` spending `utxo` is canonical, so that it can't be changed without
+/// the signers:
+/// * signatures are prefixed with their scheme, leave the default sighash out and are canonical,
+///   see [sign::Public::is_canonical_witness],
+/// * witness scripts only push data, with minimal pushes,
+/// * taproot script path witnesses have no trailing bytes,
+/// * programmable pool calls are spent with exactly the `OP_SPEND` witness.
+///
+/// Signatures checked by scripts are only held to be canonical by the pool rules, see
+/// [ScriptRules::Policy]. Witnesses of the UTXOs spent without a check are not checked either.
+pub fn check_witness_canonicality<AccountId>(
+    input: &TransactionInput,
+    utxo: &TransactionOutput<AccountId>,
+) -> Result<(), &'static str> {
+    let canonical = match &utxo.destination {
+        Destination::Pubkey(pubkey)
+        | Destination::Vesting { pubkey, .. }
+        | Destination::Stream { payee: pubkey, .. } => {
+            sign::Public::from(*pubkey).is_canonical_witness(&input.witness)
+        }
+        Destination::Inheritance { owner, .. } if input.lock.is_empty() => {
+            sign::Public::from(*owner).is_canonical_witness(&input.witness)
+        }
+        Destination::Inheritance { heir, .. } => {
+            sign::Public::from(*heir).is_canonical_witness(&input.witness)
+        }
+        Destination::Taproot { internal_key, .. } if input.lock.is_empty() => {
+            sign::Public::from(*internal_key).is_canonical_witness(&input.witness)
+        }
+        Destination::Order { maker, .. } if !input.witness.is_empty() => {
+            sign::Public::from(*maker).is_canonical_witness(&input.witness)
+        }
+        Destination::Taproot { .. } => match ScriptPathWitness::decode(&mut &input.witness[..]) {
+            Ok(path) => path.encode() == input.witness && script::is_push_only(&path.witness),
+            // Rejected by the witness check
+            Err(_) => true,
+        },
+        Destination::ScriptHash(version, _) if version.is_known() => {
+            script::is_push_only(&input.witness)
+        }
+        Destination::CallPP(..) => {
+            input.witness == Builder::new().push_int(0x1337).into_script().into_bytes()
+        }
+        _ => true,
+    };
+    if canonical {
+        Ok(())
+    } else {
+        Err("witness not canonical")
+    }
+}
+
+/// Cache of the keys of the transactions found valid by the pool, the least recently used ones
+/// are dropped when it is full.
+#[cfg(feature = "std")]
+#[derive(Clone)]
+pub struct VerificationCache(Arc<Mutex<CacheEntries>>);
+
+#[cfg(feature = "std")]
+struct CacheEntries {
+    capacity: usize,
+    /// Incremented on each use of an entry
+    clock: u64,
+    last_used: HashMap<H256, u64>,
+    by_last_use: BTreeMap<u64, H256>,
+}
+
+#[cfg(feature = "std")]
+impl CacheEntries {
+    fn touch(&mut self, key: H256) {
+        self.clock += 1;
+        if let Some(last_used) = self.last_used.insert(key, self.clock) {
+            self.by_last_use.remove(&last_used);
+        }
+        self.by_last_use.insert(self.clock, key);
+    }
+}
+
+#[cfg(feature = "std")]
+impl VerificationCache {
+    /// An empty cache holding at most `capacity` keys
+    pub fn new(capacity: usize) -> Self {
+        Self(Arc::new(Mutex::new(CacheEntries {
+            capacity,
+            clock: 0,
+            last_used: HashMap::new(),
+            by_last_use: BTreeMap::new(),
+        })))
+    }
+
+    /// Whether `key` is in the cache, marking it as used if so
+    pub fn contains(&self, key: &H256) -> bool {
+        let mut entries = self.0.lock().expect("verification cache poisoned");
+        let found = entries.last_used.contains_key(key);
+        if found {
+            entries.touch(*key);
+        }
+        found
+    }
+
+    /// Add `key` to the cache, dropping the least recently used key if it is full
+    pub fn insert(&self, key: H256) {
+        let mut entries = self.0.lock().expect("verification cache poisoned");
+        if entries.capacity == 0 {
+            return;
+        }
+        entries.touch(key);
+        if entries.last_used.len() > entries.capacity {
+            let oldest = entries.by_last_use.keys().next().copied();
+            if let Some(key) = oldest.and_then(|last_used| entries.by_last_use.remove(&last_used)) {
+                entries.last_used.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+sp_externalities::decl_extension! {
+    /// Gives the runtime access to the [VerificationCache] of the node.
+    pub struct VerificationCacheExt(VerificationCache);
+}
+
+/// Host functions accessing the [VerificationCache] of the node.
+#[runtime_interface]
+pub trait VerificationCacheApi {
+    /// Whether the scripts and signatures of the transaction with given [cache_key] were found
+    /// valid by the pool. Always `false` if the node has no cache.
+    fn is_verified(&mut self, key: H256) -> bool {
+        self.extension::<VerificationCacheExt>()
+            .map_or(false, |cache| cache.contains(&key))
+    }
+
+    /// Remember that the scripts and signatures of the transaction with given [cache_key] are
+    /// valid.
+    fn set_verified(&mut self, key: H256) {
+        if let Some(cache) = self.extension::<VerificationCacheExt>() {
+            cache.insert(key);
+        }
+    }
+}
+
+/// Whether the scripts and signatures of the transaction with given [cache_key] were found valid
+/// by the pool. Always `false` if the node has no cache.
+#[cfg(any(feature = "std", feature = "host-verifier"))]
+pub fn is_verified(key: H256) -> bool {
+    verification_cache_api::is_verified(key)
+}
+
+/// Always `false`, the wasm runtime doesn't reach the cache without the `host-verifier` feature.
+#[cfg(not(any(feature = "std", feature = "host-verifier")))]
+pub fn is_verified(_key: H256) -> bool {
+    false
+}
+
+/// Remember that the scripts and signatures of the transaction with given [cache_key] are valid,
+/// if the node has a cache.
+#[cfg(any(feature = "std", feature = "host-verifier"))]
+pub fn set_verified(key: H256) {
+    verification_cache_api::set_verified(key)
+}
+
+/// Does nothing, the wasm runtime doesn't reach the cache without the `host-verifier` feature.
+#[cfg(not(any(feature = "std", feature = "host-verifier")))]
+pub fn set_verified(_key: H256) {}
@@ -0,0 +1,183 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Versioned transaction format
+//!
+//! [VersionedTransaction] and [VersionedOutputData] prefix the encoding of a transaction and of
+//! output data with a version byte, so that the format can evolve on a live network. The version
+//! is the SCALE enum index, the payload of a version is the encoding of the matching type:
+//! * transactions: `0x01` followed by a [Transaction],
+//! * output data: `0x01` followed by an [OutputData], `0x02` followed by an [OutputDataV2].
+//!
+//! Unknown versions fail to decode with an error naming the unknown version, rather than being
+//! misread as an older format. The `spend` extrinsic takes version 1 transactions.
+
+use crate::tokens::{OutputData, OutputDataV2};
+use crate::Transaction;
+use codec::{Decode, Encode, Input};
+use frame_support::RuntimeDebug;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+/// A transaction tagged with the version of its format.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Eq, PartialEq, RuntimeDebug)]
+pub enum VersionedTransaction<AccountId> {
+    #[codec(index = 1)]
+    V1(Transaction<AccountId>),
+}
+
+impl<AccountId: Decode> Decode for VersionedTransaction<AccountId> {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+        match input.read_byte()? {
+            1 => Ok(Self::V1(Decode::decode(input)?)),
+            _ => Err("unknown transaction version".into()),
+        }
+    }
+}
+
+impl<AccountId> VersionedTransaction<AccountId> {
+    /// Version of the transaction format
+    pub fn version(&self) -> u8 {
+        match self {
+            Self::V1(_) => 1,
+        }
+    }
+
+    /// The transaction in the format taken by the `spend` extrinsic.
+    pub fn into_v1(self) -> Transaction<AccountId> {
+        match self {
+            Self::V1(tx) => tx,
+        }
+    }
+}
+
+impl<AccountId> From<Transaction<AccountId>> for VersionedTransaction<AccountId> {
+    fn from(tx: Transaction<AccountId>) -> Self {
+        Self::V1(tx)
+    }
+}
+
+/// Output data tagged with the version of its format.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Clone, Encode, Eq, PartialEq, RuntimeDebug)]
+pub enum VersionedOutputData {
+    #[codec(index = 1)]
+    V1(OutputData),
+    #[codec(index = 2)]
+    V2(OutputDataV2),
+}
+
+impl Decode for VersionedOutputData {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+        match input.read_byte()? {
+            1 => Ok(Self::V1(Decode::decode(input)?)),
+            2 => Ok(Self::V2(Decode::decode(input)?)),
+            _ => Err("unknown output data version".into()),
+        }
+    }
+}
+
+impl VersionedOutputData {
+    /// Version of the output data format
+    pub fn version(&self) -> u8 {
+        match self {
+            Self::V1(_) => 1,
+            Self::V2(_) => 2,
+        }
+    }
+
+    /// The output data in the latest format, converting older formats.
+    pub fn into_v2(self) -> OutputDataV2 {
+        match self {
+            Self::V1(data) => data.into(),
+            Self::V2(data) => data,
+        }
+    }
+}
+
+impl From<OutputData> for VersionedOutputData {
+    fn from(data: OutputData) -> Self {
+        Self::V1(data)
+    }
+}
+
+impl From<OutputDataV2> for VersionedOutputData {
+    fn from(data: OutputDataV2) -> Self {
+        Self::V2(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tokens::TokenId;
+    use crate::{TransactionInput, TransactionOutput};
+    use sp_core::H256;
+
+    #[test]
+    fn test_versioned_transaction() {
+        let tx = Transaction::<H256> {
+            inputs: vec![TransactionInput::new_empty(H256::repeat_byte(0x11))],
+            outputs: vec![TransactionOutput::new_pubkey(100, H256::repeat_byte(0x22))],
+            time_lock: Default::default(),
+            expires_at: None,
+        };
+        let versioned = VersionedTransaction::from(tx.clone());
+        let encoded = versioned.encode();
+        assert_eq!(encoded[0], 1);
+        assert_eq!(&encoded[1..], &tx.encode()[..]);
+        assert_eq!(
+            VersionedTransaction::decode(&mut &encoded[..]),
+            Ok(versioned)
+        );
+
+        // A future version is rejected, not misread
+        let mut future = encoded;
+        future[0] = 2;
+        assert_eq!(
+            VersionedTransaction::<H256>::decode(&mut &future[..]),
+            Err("unknown transaction version".into())
+        );
+    }
+
+    #[test]
+    fn test_versioned_output_data() {
+        let token_id = TokenId::from_outpoint(&H256::repeat_byte(0x33));
+        let v1 = OutputData::TokenTransferV1 {
+            token_id: token_id.clone(),
+            amount: 10,
+        };
+        let encoded = VersionedOutputData::from(v1).encode();
+        let decoded = VersionedOutputData::decode(&mut &encoded[..]).unwrap();
+        assert_eq!(decoded.version(), 1);
+        let v2 = OutputDataV2::TokenTransfer(vec![(token_id.clone(), 10)]);
+        assert_eq!(decoded.into_v2(), v2);
+        assert_eq!(
+            OutputDataV2::from(OutputData::TokenMultiV1(vec![(token_id, 10)])),
+            v2
+        );
+
+        let encoded = VersionedOutputData::from(v2.clone()).encode();
+        assert_eq!(
+            VersionedOutputData::decode(&mut &encoded[..]),
+            Ok(VersionedOutputData::V2(v2))
+        );
+        assert_eq!(
+            VersionedOutputData::decode(&mut &[3u8, 1][..]),
+            Err("unknown output data version".into())
+        );
+    }
+}
@@ -0,0 +1,63 @@
+// Copyright (c) 2021 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://spdx.org/licenses/MIT
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Vesting outputs
+//!
+//! A `Destination::Vesting` output locks a `total` grant to a key and releases it linearly over
+//! `duration` blocks, counted from the `start` block. Nothing is released until `cliff` blocks
+//! after the start. The key can spend the UTXO at any time, but the transaction has to pay the
+//! part of the grant that is not released yet back to the same destination. The schedule is
+//! computed from the grant rather than from the spent UTXO, so spending the relocked UTXO again
+//! releases nothing more than the schedule does. Team and investor allocations are
+//! made by putting vesting outputs into the genesis UTXOs.
+
+use crate::tokens::Value;
+use frame_support::sp_runtime::helpers_128bit::multiply_by_rational;
+
+/// Part of `value` still locked at block `now` by a vesting schedule.
+pub fn locked_value(value: Value, start: u64, cliff: u64, duration: u64, now: u64) -> Value {
+    let elapsed = now.saturating_sub(start);
+    if elapsed < cliff {
+        return value;
+    }
+    if elapsed >= duration {
+        return 0;
+    }
+    // `elapsed < duration`, so the vested part never exceeds `value`
+    let vested = multiply_by_rational(value, elapsed.into(), duration.into()).unwrap_or(value);
+    value - vested
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_locked_value() {
+        // 1000 released over 100 blocks from block 10, nothing before block 30
+        let locked = |now| locked_value(1000, 10, 20, 100, now);
+        assert_eq!(locked(0), 1000);
+        assert_eq!(locked(29), 1000);
+        assert_eq!(locked(30), 800);
+        assert_eq!(locked(85), 250);
+        assert_eq!(locked(109), 10);
+        assert_eq!(locked(110), 0);
+        assert_eq!(locked(u64::MAX), 0);
+
+        // no duration releases everything after the cliff
+        assert_eq!(locked_value(1000, 10, 5, 0, 14), 1000);
+        assert_eq!(locked_value(1000, 10, 5, 0, 15), 0);
+    }
+}
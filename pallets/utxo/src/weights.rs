@@ -41,12 +41,22 @@ impl<T: frame_system::Config> crate::WeightInfo for WeightInfo<T> {
             .saturating_add(T::DbWeight::get().writes(3 as Weight))
     }
 
+    //TODO this needs a benchmark
     fn token_create(u: u32) -> Weight {
-        // Under construction
-        (u as Weight)
-            .saturating_add((100 as Weight))
-            .saturating_add(T::DbWeight::get().reads(3 as Weight))
-            .saturating_add(T::DbWeight::get().writes(3 as Weight))
+        (348_270_000 as Weight)
+            //TODO: literally just copying from substrate's
+            .saturating_add((1_146_000 as Weight).saturating_mul(u as Weight))
+            .saturating_add(T::DbWeight::get().reads(4 as Weight))
+            .saturating_add(T::DbWeight::get().writes(6 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn mint_nft(u: u32) -> Weight {
+        (348_270_000 as Weight)
+            //TODO: literally just copying from substrate's
+            .saturating_add((1_146_000 as Weight).saturating_mul(u as Weight))
+            .saturating_add(T::DbWeight::get().reads(6 as Weight))
+            .saturating_add(T::DbWeight::get().writes(8 as Weight))
     }
 
     fn send_to_address(s: u32) -> Weight {
@@ -58,6 +68,15 @@ impl<T: frame_system::Config> crate::WeightInfo for WeightInfo<T> {
             .saturating_add(T::DbWeight::get().writes(3 as Weight))
     }
 
+    //TODO this needs a benchmark
+    fn burn(s: u32) -> Weight {
+        (348_270_000 as Weight)
+            //TODO: literally just copying from substrate's
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().writes(4 as Weight))
+    }
+
     //TODO this needs a benchmark
     fn unlock_request_for_withdrawal(s: u32) -> Weight {
         (548_270_000 as Weight)
@@ -75,4 +94,172 @@ impl<T: frame_system::Config> crate::WeightInfo for WeightInfo<T> {
             .saturating_add(T::DbWeight::get().reads(3 as Weight))
             .saturating_add(T::DbWeight::get().writes(3 as Weight))
     }
+
+    //TODO this needs a benchmark
+    fn watch_destination(s: u32) -> Weight {
+        (348_270_000 as Weight)
+            //TODO: literally just copying from substrate's
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads(4 as Weight))
+            .saturating_add(T::DbWeight::get().writes(4 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn send_to_addresses(s: u32) -> Weight {
+        (348_270_000 as Weight)
+            //TODO: literally just copying from substrate's
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().writes((3 as Weight).saturating_add(s as Weight)))
+    }
+
+    //TODO this needs a benchmark
+    fn leave_staking_pool(s: u32) -> Weight {
+        (548_270_000 as Weight)
+            //TODO: literally just copying from substrate's
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().writes(4 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn rotate_session_keys(s: u32) -> Weight {
+        (348_270_000 as Weight)
+            //TODO: literally just copying from substrate's
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn redelegate_stake(s: u32) -> Weight {
+        (548_270_000 as Weight)
+            //TODO: literally just copying from substrate's
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads(4 as Weight))
+            .saturating_add(T::DbWeight::get().writes(4 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn take_balance_snapshot(s: u32) -> Weight {
+        (548_270_000 as Weight)
+            //TODO: literally just copying from substrate's
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().writes(3 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn release_from_bridge(s: u32) -> Weight {
+        (348_270_000 as Weight)
+            //TODO: literally just copying from substrate's
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(3 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn set_transfer_policy(s: u32) -> Weight {
+        (148_270_000 as Weight)
+            //TODO: literally just copying from substrate's
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn wrap(s: u32) -> Weight {
+        (348_270_000 as Weight)
+            //TODO: literally just copying from substrate's
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().writes(5 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn unwrap(s: u32) -> Weight {
+        (348_270_000 as Weight)
+            //TODO: literally just copying from substrate's
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads((2 as Weight).saturating_add(s as Weight)))
+            .saturating_add(
+                T::DbWeight::get().writes((2 as Weight).saturating_add(2 * s as Weight)),
+            )
+    }
+
+    //TODO this needs a benchmark
+    fn spend_script_bytes(b: u32) -> Weight {
+        (10_000 as Weight).saturating_mul(b as Weight)
+    }
+
+    //TODO this needs a benchmark
+    fn notify_watchers(n: u32) -> Weight {
+        (1_146_000 as Weight)
+            .saturating_mul(n as Weight)
+            .saturating_add(T::DbWeight::get().reads(n as Weight))
+            .saturating_add(T::DbWeight::get().writes(n as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn claim_reward(s: u32) -> Weight {
+        (48_270_000 as Weight)
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads(2 as Weight))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn set_airdrop_root(s: u32) -> Weight {
+        (48_270_000 as Weight)
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn claim(s: u32) -> Weight {
+        (148_270_000 as Weight)
+            //TODO: literally just copying from substrate's
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().writes(4 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn on_finalize(s: u32) -> Weight {
+        (148_270_000 as Weight)
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads(8 as Weight))
+            .saturating_add(T::DbWeight::get().writes(8 as Weight))
+            .saturating_add(T::DbWeight::get().writes((2 as Weight).saturating_mul(s as Weight)))
+    }
+
+    //TODO this needs a benchmark
+    fn consolidate(s: u32) -> Weight {
+        (148_270_000 as Weight)
+            .saturating_add((60_000_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads((2 as Weight).saturating_add(s as Weight)))
+            .saturating_add(T::DbWeight::get().writes((3 as Weight).saturating_mul(s as Weight)))
+    }
+
+    //TODO this needs a benchmark
+    fn set_token_issuer(s: u32) -> Weight {
+        (48_270_000 as Weight)
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn set_paused(s: u32) -> Weight {
+        (48_270_000 as Weight)
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
+
+    //TODO this needs a benchmark
+    fn set_identity_destination(s: u32) -> Weight {
+        (48_270_000 as Weight)
+            .saturating_add((1_146_000 as Weight).saturating_mul(s as Weight))
+            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
 }
@@ -16,7 +16,8 @@ use sp_api::impl_runtime_apis;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use sp_core::{crypto::KeyTypeId, OpaqueMetadata, H256};
 use sp_runtime::traits::{
-    AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, OpaqueKeys, Verify,
+    AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, OpaqueKeys,
+    SaturatedConversion, StaticLookup, Verify,
 };
 use sp_runtime::{
     create_runtime_str, generic, impl_opaque_keys,
@@ -315,10 +316,55 @@ impl pallet_template::Config for Runtime {
 parameter_types! {
     pub const MinimumStake: u128 = MINIMUM_STAKE;
     pub const StakeWithdrawalFee: u128 =  1 * MLT_UNIT;
+    pub const StakeWithdrawalFeeDestination: pallet_utxo::staking::FeeDestination<AccountId> =
+        pallet_utxo::staking::FeeDestination::RewardPool;
+    pub const MaxValidatorCommission: Perbill = Perbill::from_percent(50);
+    pub const TokenIssuanceFee: u128 = 100 * MLT_UNIT;
+    pub const MaxDataCarrierSize: u32 = 80;
+    // A token transfer output, about 40 bytes of data, locks 0.04 MLT
+    pub const StorageDepositPerByte: u128 = MLT_UNIT / 1000;
+    pub AcceptedFeeAssets: Vec<pallet_utxo::priority::FeeAsset> = Vec::new();
+    pub const NftMintFee: u128 = 100 * MLT_UNIT;
+    pub const IssuanceRateLimit: u32 = 10;
+    pub const WatchFee: u128 = 1 * MLT_UNIT;
+    pub const UniqueTickers: bool = true;
+    pub ReservedTickers: Vec<Vec<u8>> = vec![b"MLT".to_vec()];
+    pub const MaxTickerLength: u32 = 5;
+    pub const MaxTokenDecimals: u8 = 18;
+    pub const MaxMetadataUriLength: u32 = 100;
+    pub const TokenTickerCharset: pallet_utxo::tokens::TickerCharset =
+        pallet_utxo::tokens::TickerCharset::Ascii;
+    pub const RewardHistoryDepth: pallet_staking::EraIndex = 84;
+    pub const StxoRetention: BlockNumber = 1 * DAYS;
+    pub const SnapshotPeriod: BlockNumber = 7 * DAYS;
+    pub const SnapshotBatchSize: u32 = 500;
+    // A year of weekly snapshots
+    pub const SnapshotHistoryDepth: u32 = 52;
+    pub const PruningDepth: BlockNumber = 30 * DAYS;
+    pub const NftProvenanceDepth: u32 = 16;
+    pub const MaxTransactionWeightShare: Perbill = Perbill::from_percent(25);
+    pub const MaxWatchers: u32 = 16;
+    pub const MaxStandardWitnessSize: u32 = 10 * 1024;
+    // Smallest output the pool relays, to be raised by operators who don't want to relay dust
+    pub const DustThreshold: u128 = 0;
+    pub const MaxConsolidationInputs: u32 = 100;
+    // Id of the chain the signatures bound to the chain commit to
+    pub ChainId: H256 = <BlakeTwo256 as sp_runtime::traits::Hash>::hash(b"mintlayer-testnet");
+    // Signatures not bound to the chain are accepted for the first 180 days
+    pub const UnboundSignaturesUntil: BlockNumber = 180 * DAYS;
+    pub const NonCanonicalWitnessesUntil: BlockNumber = 180 * DAYS;
+    // Scheme the extrinsics signing on behalf of the caller sign with
+    pub const DefaultSignatureScheme: pallet_utxo::SignatureScheme =
+        pallet_utxo::SignatureScheme::Schnorr;
     pub const RewardReductionPeriod: BlockNumber = 1 * YEARS; // reward reduced every year
     pub const RewardReductionFraction: Percent = Percent::from_percent(25); // reward reduced at 25%
     pub const InitialReward: u128 = 100 * MLT_UNIT;
     pub const DefaultMinimumReward: u128 = 1;
+    // Cap of the MLT issuance, leaving room for the block rewards on top of the genesis supply
+    pub const MaxMoney: u128 = 2 * TEST_NET_MLT_ORIG_SUPPLY;
+    // Minimum fee per million units of weight for a transaction to enter the pool, to be raised
+    // by operators who want a stronger spam protection
+    pub const MinimumFeeRate: u128 = 0;
 }
 
 impl pallet_utxo::Config for Runtime {
@@ -329,6 +375,7 @@ impl pallet_utxo::Config for Runtime {
 
     type RewardReductionFraction = RewardReductionFraction;
     type RewardReductionPeriod = RewardReductionPeriod;
+    type MaxMoney = MaxMoney;
 
     fn authorities() -> Vec<H256> {
         Aura::authorities()
@@ -341,10 +388,95 @@ impl pallet_utxo::Config for Runtime {
     }
 
     type StakingHelper = StakeOps<Runtime>;
+    type BridgeHandler = ();
+    type TransferApprover = ();
+    type UtxoHooks = ();
+    type Currency = Balances;
     type MinimumStake = MinimumStake;
     type StakeWithdrawalFee = StakeWithdrawalFee;
+    type MaxValidatorCommission = MaxValidatorCommission;
+    type FeeDestination = StakeWithdrawalFeeDestination;
+    type BondingDuration = BondingDuration;
+    type TokenIssuanceFee = TokenIssuanceFee;
+    type MaxDataCarrierSize = MaxDataCarrierSize;
+    type StorageDepositPerByte = StorageDepositPerByte;
+    type AcceptedFeeAssets = AcceptedFeeAssets;
+    type NftMintFee = NftMintFee;
+    type IssuanceRateLimit = IssuanceRateLimit;
+    type WatchFee = WatchFee;
+    type UniqueTickers = UniqueTickers;
+    type ReservedTickers = ReservedTickers;
+    type MaxTickerLength = MaxTickerLength;
+    type MaxTokenDecimals = MaxTokenDecimals;
+    type MaxMetadataUriLength = MaxMetadataUriLength;
+    type TokenTickerCharset = TokenTickerCharset;
+    type RewardHistoryDepth = RewardHistoryDepth;
+    type StxoRetention = StxoRetention;
+    type SnapshotPeriod = SnapshotPeriod;
+    type SnapshotBatchSize = SnapshotBatchSize;
+    type SnapshotHistoryDepth = SnapshotHistoryDepth;
+    type PruningDepth = PruningDepth;
+    type NftProvenanceDepth = NftProvenanceDepth;
+    type MaxTransactionWeightShare = MaxTransactionWeightShare;
+    type MaxWatchers = MaxWatchers;
+    type MaxStandardWitnessSize = MaxStandardWitnessSize;
+    type DustThreshold = DustThreshold;
+    type MaxConsolidationInputs = MaxConsolidationInputs;
+    type ChainId = ChainId;
+    type UnboundSignaturesUntil = UnboundSignaturesUntil;
+    type NonCanonicalWitnessesUntil = NonCanonicalWitnessesUntil;
+    type DefaultSignatureScheme = DefaultSignatureScheme;
     type InitialReward = InitialReward;
     type DefaultMinimumReward = DefaultMinimumReward;
+    type PriorityPolicy = pallet_utxo::priority::FeePerWeight<MinimumFeeRate>;
+    type TokenIssuerOrigin = frame_system::EnsureRoot<AccountId>;
+    type PauseOrigin = frame_system::EnsureRoot<AccountId>;
+    type Identities = VerifiedIdentities;
+    type FindAuthor = pallet_session::FindAccountFromAuthorIndex<Self, Aura>;
+}
+
+parameter_types! {
+    pub const BasicDeposit: Balance = 10 * DOLLARS;
+    pub const FieldDeposit: Balance = 25 * CENTS;
+    pub const SubAccountDeposit: Balance = 2 * DOLLARS;
+    pub const MaxSubAccounts: u32 = 100;
+    pub const MaxAdditionalFields: u32 = 100;
+    pub const MaxRegistrars: u32 = 20;
+}
+
+impl pallet_identity::Config for Runtime {
+    type Event = Event;
+    type Currency = Balances;
+    type BasicDeposit = BasicDeposit;
+    type FieldDeposit = FieldDeposit;
+    type SubAccountDeposit = SubAccountDeposit;
+    type MaxSubAccounts = MaxSubAccounts;
+    type MaxAdditionalFields = MaxAdditionalFields;
+    type MaxRegistrars = MaxRegistrars;
+    type Slashed = ();
+    type ForceOrigin = frame_system::EnsureRoot<AccountId>;
+    type RegistrarOrigin = frame_system::EnsureRoot<AccountId>;
+    type WeightInfo = pallet_identity::weights::SubstrateWeight<Runtime>;
+}
+
+/// Display names of the identities of `pallet-identity` a registrar has judged `Reasonable` or
+/// `KnownGood`, so that a name paid to by `send_to_address` has been checked.
+pub struct VerifiedIdentities;
+
+impl pallet_utxo::identity::IdentityProvider<AccountId> for VerifiedIdentities {
+    fn display_name(who: &AccountId) -> Option<Vec<u8>> {
+        let registration = Identity::identity(who)?;
+        let verified = registration.judgements.iter().any(|(_, judgement)| {
+            matches!(
+                judgement,
+                pallet_identity::Judgement::Reasonable | pallet_identity::Judgement::KnownGood
+            )
+        });
+        match registration.info.display {
+            pallet_identity::Data::Raw(name) if verified => Some(name.to_vec()),
+            _ => None,
+        }
+    }
 }
 
 impl pallet_pp::Config for Runtime {
@@ -510,7 +642,7 @@ construct_runtime!(
         Sudo: pallet_sudo::{Pallet, Call, Config<T>, Storage, Event<T>},
         // Include the custom logic from the pallet-template in the runtime.
         TemplateModule: pallet_template::{Pallet, Call, Storage, Event<T>},
-        Utxo: pallet_utxo::{Pallet, Call, Config<T>, Storage, Event<T>},
+        Utxo: pallet_utxo::{Pallet, Call, Config<T>, Storage, Event<T>, Inherent},
         Pp: pallet_pp::{Pallet, Call, Config<T>, Storage, Event<T>},
         Contracts: pallet_contracts::{Pallet, Call, Storage, Event<T>},
         Authorship: pallet_authorship::{Pallet, Call, Storage, Inherent},
@@ -518,6 +650,7 @@ construct_runtime!(
         Session: pallet_session::{Pallet, Call, Config<T>, Storage, Event},
         Aura: pallet_aura::{Pallet, Config<T>},
         Historical: pallet_session_historical::{Pallet},
+        Identity: pallet_identity::{Pallet, Call, Storage, Event<T>},
     }
 );
 
@@ -540,6 +673,7 @@ pub type SignedExtra = (
     frame_system::CheckNonce<Runtime>,
     frame_system::CheckWeight<Runtime>,
     pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+    pallet_utxo::extension::CheckDuplicateSpend<Runtime>,
 );
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
@@ -603,10 +737,20 @@ impl_runtime_apis! {
             block_hash: <Block as BlockT>::Hash,
         ) -> TransactionValidity {
             log::info!("transaction to validate: {:?}",tx);
-            if let Some(pallet_utxo::Call::spend(ref tx)) =
+            if let Some(pallet_utxo::Call::spend(ref utxo_tx)) =
             IsSubType::<pallet_utxo::Call::<Runtime>>::is_sub_type(&tx.function) {
-                match pallet_utxo::validate_transaction::<Runtime>(&tx) {
-                    Ok(valid_tx) => { return Ok(valid_tx); }
+                match pallet_utxo::validate_pool_transaction::<Runtime>(&utxo_tx, source) {
+                    Ok(mut valid_tx) => {
+                        // Tag the outpoints spent by the signer, see `CheckDuplicateSpend`
+                        let signer = tx.signature.as_ref().and_then(|(address, ..)| {
+                            <Runtime as frame_system::Config>::Lookup::lookup(address.clone()).ok()
+                        });
+                        if let Some(signer) = signer {
+                            valid_tx.provides.extend(
+                                pallet_utxo::extension::spend_tags(&signer, utxo_tx));
+                        }
+                        return Ok(valid_tx);
+                    }
                     Err(e) => {
                         log::error!("utxo validation failed: {:?}",e);
                         return Err(TransactionValidityError::Invalid(InvalidTransaction::Custom(1)));
@@ -702,6 +846,187 @@ impl_runtime_apis! {
         fn send() -> u32 {
             Utxo::send()
         }
+
+        fn token_circulating_supply(token_id: Vec<u8>) -> Option<u128> {
+            let token_id = core::str::from_utf8(&token_id).ok()?;
+            let token_id = pallet_utxo::tokens::TokenId::from_string(token_id).ok()?;
+            Utxo::token_circulating_supply(&token_id)
+        }
+
+        fn circulating_supply() -> u128 {
+            Utxo::circulating_supply()
+        }
+
+        fn decode_transaction(
+            tx: Vec<u8>,
+        ) -> Result<pallet_utxo_rpc_runtime_api::TransactionInspection, Vec<u8>> {
+            Utxo::decode_transaction(&tx).map_err(|e| e.as_bytes().to_vec())
+        }
+
+        fn dry_run_spend(
+            tx: Vec<u8>,
+        ) -> Result<pallet_utxo_rpc_runtime_api::DryRunReport, Vec<u8>> {
+            Utxo::dry_run_spend(&tx).map_err(|e| e.as_bytes().to_vec())
+        }
+
+        fn spent_output(outpoint: H256) -> Option<pallet_utxo_rpc_runtime_api::SpentOutput> {
+            Utxo::spent_output(outpoint).map(|(tx_hash, block_number)| {
+                pallet_utxo_rpc_runtime_api::SpentOutput {
+                    tx_hash,
+                    block_number: block_number.into(),
+                }
+            })
+        }
+
+        fn missing_inputs(block_number: u64) -> Vec<pallet_utxo_rpc_runtime_api::MissingInputProof> {
+            Utxo::missing_inputs(block_number.saturated_into())
+                .into_iter()
+                .map(|proof| pallet_utxo_rpc_runtime_api::MissingInputProof {
+                    tx_hash: proof.tx_hash,
+                    outpoint: proof.outpoint,
+                    spent_by: proof.spent_by.map(|(tx_hash, block_number)| {
+                        pallet_utxo_rpc_runtime_api::SpentOutput {
+                            tx_hash,
+                            block_number: block_number.into(),
+                        }
+                    }),
+                })
+                .collect()
+        }
+
+        fn verify_reserve_proof(proof: Vec<u8>) -> Result<u128, Vec<u8>> {
+            let proof = <pallet_utxo::ReserveProof as codec::Decode>::decode(&mut &proof[..])
+                .map_err(|_| b"invalid reserve proof encoding".to_vec())?;
+            Utxo::verify_reserve_proof(&proof).map_err(|e| e.as_bytes().to_vec())
+        }
+
+        fn voting_weight(
+            pubkey: H256,
+            block_number: u64,
+        ) -> Option<pallet_utxo_rpc_runtime_api::VotingWeight> {
+            Utxo::voting_weight(pubkey, block_number.saturated_into()).map(
+                |(snapshot_block, value)| pallet_utxo_rpc_runtime_api::VotingWeight {
+                    snapshot_block: snapshot_block.into(),
+                    value,
+                },
+            )
+        }
+
+        fn staking_info(stash: H256) -> Option<pallet_utxo_rpc_runtime_api::StakingInfo> {
+            Utxo::staking_info(stash)
+        }
+
+        fn list_validator_stakes() -> Vec<pallet_utxo_rpc_runtime_api::ValidatorStake> {
+            Utxo::validator_stakes()
+        }
+
+        fn nft_provenance(token_id: Vec<u8>) -> Vec<pallet_utxo_rpc_runtime_api::NftTransfer> {
+            let token_id = match core::str::from_utf8(&token_id)
+                .ok()
+                .and_then(|token_id| pallet_utxo::tokens::TokenId::from_string(token_id).ok())
+            {
+                Some(token_id) => token_id,
+                None => return Vec::new(),
+            };
+            Utxo::nft_provenance(token_id)
+                .into_iter()
+                .map(|transfer| pallet_utxo_rpc_runtime_api::NftTransfer {
+                    block_number: transfer.block_number.into(),
+                    from: codec::Encode::encode(&transfer.from),
+                    to: codec::Encode::encode(&transfer.to),
+                })
+                .collect()
+        }
+
+        fn utxos_paged(
+            start_key: Option<H256>,
+            limit: u32,
+        ) -> pallet_utxo_rpc_runtime_api::UtxoPage {
+            let utxos = Utxo::utxos_paged(start_key, limit);
+            let next_key = match utxos.last() {
+                Some((outpoint, _)) if utxos.len() == limit.min(pallet_utxo::MAX_UTXOS_PER_PAGE) as usize => {
+                    Some(*outpoint)
+                }
+                _ => None,
+            };
+            pallet_utxo_rpc_runtime_api::UtxoPage {
+                utxos: utxos
+                    .into_iter()
+                    .map(|(outpoint, utxo)| (outpoint, codec::Encode::encode(&utxo)))
+                    .collect(),
+                next_key,
+            }
+        }
+
+        fn scan_descriptor(
+            descriptor: Vec<u8>,
+            start_key: Option<H256>,
+            limit: u32,
+        ) -> Result<pallet_utxo_rpc_runtime_api::UtxoPage, Vec<u8>> {
+            let descriptor = pallet_utxo::descriptor::Descriptor::parse(&descriptor)
+                .map_err(|e| e.as_bytes().to_vec())?;
+            let (utxos, next_key) = Utxo::scan_descriptor(&descriptor, start_key, limit);
+            Ok(pallet_utxo_rpc_runtime_api::UtxoPage {
+                utxos: utxos
+                    .into_iter()
+                    .map(|(outpoint, utxo)| (outpoint, codec::Encode::encode(&utxo)))
+                    .collect(),
+                next_key,
+            })
+        }
+
+        fn utxo_stats() -> pallet_utxo_rpc_runtime_api::UtxoStats {
+            Utxo::utxo_stats()
+        }
+
+        fn token_holders(
+            token_id: Vec<u8>,
+            cursor: Option<H256>,
+            limit: u32,
+        ) -> pallet_utxo_rpc_runtime_api::TokenHolderPage {
+            let token_id = match core::str::from_utf8(&token_id)
+                .ok()
+                .and_then(|token_id| pallet_utxo::tokens::TokenId::from_string(token_id).ok())
+            {
+                Some(token_id) => token_id,
+                None => {
+                    return pallet_utxo_rpc_runtime_api::TokenHolderPage {
+                        holders: Vec::new(),
+                        holder_count: 0,
+                        next_cursor: None,
+                    }
+                }
+            };
+            let holders = Utxo::token_holders(&token_id, cursor, limit);
+            let next_cursor = match holders.last() {
+                Some((owner, _)) if holders.len() == limit.min(pallet_utxo::MAX_HOLDERS_PER_PAGE) as usize => {
+                    Some(*owner)
+                }
+                _ => None,
+            };
+            pallet_utxo_rpc_runtime_api::TokenHolderPage {
+                holders,
+                holder_count: Utxo::token_holder_count(&token_id),
+                next_cursor,
+            }
+        }
+
+        fn outpoint_of(txid: H256, index: u32) -> Option<H256> {
+            Utxo::outpoint_of(txid, index)
+        }
+
+        fn output_ref(outpoint: H256) -> Option<(H256, u32)> {
+            Utxo::output_ref(outpoint)
+        }
+
+        fn validate_token_metadata(
+            ticker: Vec<u8>,
+            number_of_decimals: u8,
+            metadata_uri: Vec<u8>,
+        ) -> Result<(), Vec<u8>> {
+            Utxo::validate_token_metadata(&ticker, number_of_decimals, &metadata_uri)
+                .map_err(|e| e.as_bytes().to_vec())
+        }
     }
 
     impl pallet_contracts_rpc_runtime_api::ContractsApi<
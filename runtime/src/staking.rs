@@ -22,7 +22,7 @@ use frame_support::dispatch::{DispatchResult, DispatchResultWithPostInfo, Vec};
 use frame_support::fail;
 use frame_system::{Config as SysConfig, RawOrigin};
 use pallet_staking::{BalanceOf, Pallet as StakingPallet};
-use pallet_utxo::staking::StakingHelper;
+use pallet_utxo::staking::{EraIndex, StakingHelper};
 use sp_core::sp_std::vec;
 use sp_runtime::traits::StaticLookup;
 
@@ -89,9 +89,12 @@ where
         )
     }
 
-    fn apply_for_validator_role(controller_account: StakeAccountId<T>) -> DispatchResult {
+    fn apply_for_validator_role(
+        controller_account: StakeAccountId<T>,
+        commission: Perbill,
+    ) -> DispatchResult {
         let validator_prefs = pallet_staking::ValidatorPrefs {
-            commission: Perbill::from_percent(0),
+            commission,
             ..Default::default()
         };
 
@@ -166,10 +169,11 @@ where
         controller_account: &StakeAccountId<T>,
         session_key: &Vec<u8>,
         value: u128,
+        commission: Perbill,
     ) -> DispatchResultWithPostInfo {
         Self::bond(controller_account.clone(), stash_account.clone(), value)?;
         Self::set_session_keys(controller_account.clone(), session_key)?;
-        Self::apply_for_validator_role(controller_account.clone())?;
+        Self::apply_for_validator_role(controller_account.clone(), commission)?;
 
         Ok(().into())
     }
@@ -220,4 +224,47 @@ where
 
         Ok(res)
     }
+
+    fn current_era() -> EraIndex {
+        <StakingPallet<T>>::current_era().unwrap_or(0)
+    }
+
+    fn redelegate(
+        stash_account: &StakeAccountId<T>,
+        new_stash_account: &StakeAccountId<T>,
+        new_controller_account: &StakeAccountId<T>,
+        session_key: &Vec<u8>,
+        value: u128,
+        commission: Perbill,
+    ) -> DispatchResultWithPostInfo {
+        // get the controller account, given the stash_account.
+        let controller_account = <StakingPallet<T>>::bonded(stash_account.clone())
+            .ok_or(pallet_utxo::Error::<T>::StashAccountNotFound)?;
+
+        // stop validating, and release the stake at once instead of unbonding it.
+        // The stake is moved, not slashed, so any slashing spans of the stash are dropped.
+        StakingPallet::<T>::chill(RawOrigin::Signed(controller_account).into())?;
+        StakingPallet::<T>::force_unstake(RawOrigin::Root.into(), stash_account.clone(), u32::MAX)?;
+
+        <Self as StakingHelper<T::AccountId>>::lock_for_staking(
+            new_stash_account,
+            new_controller_account,
+            session_key,
+            value,
+            commission,
+        )
+    }
+
+    fn rotate_session_keys(
+        stash_account: &StakeAccountId<T>,
+        session_key: &Vec<u8>,
+    ) -> DispatchResultWithPostInfo {
+        // the session keys belong to the controller account.
+        let controller_account = <StakingPallet<T>>::bonded(stash_account.clone())
+            .ok_or(pallet_utxo::Error::<T>::StashAccountNotFound)?;
+
+        Self::set_session_keys(controller_account, session_key)?;
+
+        Ok(().into())
+    }
 }
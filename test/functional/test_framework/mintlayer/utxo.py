@@ -35,6 +35,10 @@ class Client():
         encoded = self.encode_obj(ty, obj).data
         return '0x' + str(substrateinterface.utils.hasher.blake2_256(encoded))
 
+    """ Id of the token issued by a transaction whose first input spends given outpoint """
+    def token_id(self, outpoint):
+        return '0x' + self.hash_of('H256', outpoint)[-40:]
+
     """ Query the node for the list of utxos """
     def utxos(self, storage_name):
         query = self.substrate.query_map(
@@ -178,6 +182,14 @@ class Destination():
             return DestLockForStaking.load(obj['LockForStaking'])
         if 'LockExtraForStaking' in obj:
             return DestLockExtraForStaking.load(obj['LockExtraForStaking'])
+        if 'CreateStakingPool' in obj:
+            return DestCreateStakingPool.load(obj['CreateStakingPool'])
+        if 'JoinStakingPool' in obj:
+            return DestJoinStakingPool.load(obj['JoinStakingPool'])
+        if 'Vesting' in obj:
+            return DestVesting.load(obj['Vesting'])
+        if 'DataCarrier' in obj:
+            return DestDataCarrier.load(obj['DataCarrier'])
         return None
 
     def type_string(self):
@@ -228,30 +240,35 @@ class DestCreatePP(Destination):
         return { 'CreatePP': { 'code': self.code, 'data': self.data } }
 
 class DestCallPP(Destination):
-    def __init__(self, dest_account, fund, input_data):
+    def __init__(self, dest_account, fund, input_data, abi_hash=None):
         self.acct = dest_account
         self.fund = fund
         self.data = input_data
+        self.abi_hash = abi_hash
 
     @staticmethod
     def load(obj):
-        return DestCallPP(obj['dest_account'], obj['fund'], obj['input_data'])
+        return DestCallPP(obj['dest_account'], obj['fund'], obj['input_data'], obj.get('abi_hash'))
 
     def json(self):
-        return { 'CallPP': { 'dest_account': self.acct, 'fund': self.fund, 'input_data': self.data } }
+        return { 'CallPP': { 'dest_account': self.acct, 'fund': self.fund, 'input_data': self.data, 'abi_hash': self.abi_hash } }
 
 class DestLockForStaking(Destination):
-    def __init__(self, stash_account, controller_account, session_key):
+    def __init__(self, stash_account, controller_account, session_key, withdrawal_key=None, secondary_controller=None, commission=0, min_self_bond=0):
         self.stash = stash_account
         self.controller = controller_account
         self.sesh = session_key
+        self.withdrawal_key = withdrawal_key
+        self.secondary_controller = secondary_controller
+        self.commission = commission
+        self.min_self_bond = min_self_bond
 
     @staticmethod
     def load(obj):
-        return DestLockForStaking(obj['stash_account'], obj['controller_account'], ['session_key'])
+        return DestLockForStaking(obj['stash_account'], obj['controller_account'], ['session_key'], obj.get('withdrawal_key'), obj.get('secondary_controller'), obj.get('commission', 0), obj.get('min_self_bond', 0))
 
     def json(self):
-        return { 'LockForStaking': { 'stash_account': self.stash, 'controller_account': self.controller, 'session_key': self.sesh } }
+        return { 'LockForStaking': { 'stash_account': self.stash, 'controller_account': self.controller, 'session_key': self.sesh, 'withdrawal_key': self.withdrawal_key, 'secondary_controller': self.secondary_controller, 'commission': self.commission, 'min_self_bond': self.min_self_bond } }
 
     def get_ss58_address(self):
         return self.stash
@@ -271,6 +288,67 @@ class DestLockExtraForStaking(Destination):
     def get_ss58_address(self):
         return self.stash
 
+class DestCreateStakingPool(Destination):
+    def __init__(self, stash_account, controller_account, session_key, owner):
+        self.stash = stash_account
+        self.controller = controller_account
+        self.sesh = session_key
+        self.owner = owner
+
+    @staticmethod
+    def load(obj):
+        return DestCreateStakingPool(obj['stash_account'], obj['controller_account'], obj['session_key'], obj['owner'])
+
+    def json(self):
+        return { 'CreateStakingPool': { 'stash_account': self.stash, 'controller_account': self.controller, 'session_key': self.sesh, 'owner': self.owner } }
+
+    def get_ss58_address(self):
+        return self.stash
+
+class DestJoinStakingPool(Destination):
+    def __init__(self, stash_account, member):
+        self.stash = stash_account
+        self.member = member
+
+    @staticmethod
+    def load(obj):
+        return DestJoinStakingPool(obj['stash_account'], obj['member'])
+
+    def json(self):
+        return { 'JoinStakingPool': { 'stash_account': self.stash, 'member': self.member } }
+
+    def get_ss58_address(self):
+        return self.stash
+
+class DestVesting(Destination):
+    def __init__(self, pubkey, total, start, cliff, duration):
+        self.pubkey = pubkey
+        self.total = total
+        self.start = start
+        self.cliff = cliff
+        self.duration = duration
+
+    @staticmethod
+    def load(obj):
+        return DestVesting(obj['pubkey'], obj['total'], obj['start'], obj['cliff'], obj['duration'])
+
+    def json(self):
+        return { 'Vesting': { 'pubkey': self.pubkey, 'total': self.total, 'start': self.start, 'cliff': self.cliff, 'duration': self.duration } }
+
+    def get_pubkey(self):
+        return self.pubkey
+
+class DestDataCarrier(Destination):
+    def __init__(self, data):
+        self.data = data
+
+    @staticmethod
+    def load(obj):
+        return DestDataCarrier(obj)
+
+    def json(self):
+        return { 'DataCarrier': self.data }
+
 
 class Output():
     def __init__(self, value, destination, data):
@@ -311,11 +389,12 @@ class Input():
         }
 
 class Transaction():
-    def __init__(self, client, inputs, outputs, time_lock = 0):
+    def __init__(self, client, inputs, outputs, time_lock = 0, expires_at = None):
         self.client = client
         self.inputs = inputs
         self.outputs = outputs
         self.time_lock = time_lock
+        self.expires_at = expires_at
 
     def type_string(self):
         return 'Transaction'
@@ -324,7 +403,8 @@ class Transaction():
         return {
             'inputs': [ i.json() for i in self.inputs ],
             'outputs': [ o.json() for o in self.outputs ],
-            'time_lock': self.time_lock
+            'time_lock': self.time_lock,
+            'expires_at': self.expires_at
         }
 
     """ Get data to be signed for this transaction """
@@ -342,6 +422,7 @@ class Transaction():
             'inputs': { 'SpecifiedPay': (outpoints_hash, utxos_hash, idx) },
             'outputs': { 'All': outputs_hash },
             'time_lock': self.time_lock,
+            'expires_at': self.expires_at,
             'codesep_pos': 0xffffffff
         }
         return self.client.encode_obj('SignatureData', sigdata)
@@ -357,6 +438,17 @@ class Transaction():
 
     """ Get UTXO ID of n-th output of this transaction """
     def outpoint(self, n):
+        if self.expires_at is None:
+            # Transactions that never expire are hashed without the expiry
+            tx = self.json()
+            outpt = {
+                'inputs': tx['inputs'],
+                'outputs': tx['outputs'],
+                'time_lock': tx['time_lock'],
+                'index': n
+            }
+            encoded = self.client.substrate.encode_scale('OutpointWithoutExpiry', outpt)
+            return '0x' + str(substrateinterface.utils.hasher.blake2_256(encoded.data))
         outpt = {
             'transaction': self.json(),
             'index': n
@@ -24,4 +24,13 @@ pub trait ProgrammablePoolApi {
         fund_contract: bool,
         input_data: &Vec<u8>,
     ) -> Result<(), &'static str>;
+
+    /// Check a call to the contract `dest` before it is paid for, so that a malformed call is
+    /// rejected rather than trapping inside the contract. `abi_hash` is the hash of the contract
+    /// ABI the call was built against, if the caller wants it checked.
+    fn verify_call(
+        dest: &Self::AccountId,
+        abi_hash: Option<H256>,
+        input_data: &Vec<u8>,
+    ) -> Result<(), &'static str>;
 }
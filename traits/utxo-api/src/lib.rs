@@ -16,11 +16,32 @@
 // Author(s): A. Altonen
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use codec::{Decode, Encode};
 use frame_support::{
     dispatch::Vec,
     pallet_prelude::{DispatchError, DispatchResultWithPostInfo},
+    RuntimeDebug,
 };
-use sp_core::{H256, H512};
+use sp_core::{H160, H256, H512};
+
+/// Destination of an output paid by a contract
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub enum PayoutDestination {
+    /// Pay to the public key
+    Pubkey(H256),
+    /// Pay to the hash of a script
+    ScriptHash(H256),
+}
+
+/// Output of a transaction spending the UTXOs of a contract
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct Payout {
+    pub destination: PayoutDestination,
+    /// MLT paid
+    pub value: u128,
+    /// Token paid along with the MLT, by its id and amount
+    pub token: Option<(H160, u128)>,
+}
 
 pub trait UtxoApi {
     type AccountId;
@@ -46,6 +67,10 @@ pub trait UtxoApi {
         outpoints: &Vec<H256>,
     ) -> Result<(), DispatchError>;
 
+    /// UTXOs the contract account `contract` can spend, with their values, in the order of
+    /// their outpoints.
+    fn contract_utxos(contract: &Self::AccountId) -> Vec<(H256, u128)>;
+
     fn send_conscrit_c2c(
         caller: &Self::AccountId,
         destination: &Self::AccountId,
@@ -53,4 +78,12 @@ pub trait UtxoApi {
         data: &Vec<u8>,
         outpoints: &Vec<H256>,
     ) -> Result<(), DispatchError>;
+
+    /// Spend the UTXOs of the contract `caller` at `outpoints` into one output per payout. The
+    /// MLT of the inputs the payouts leave over goes to the fees.
+    fn send_conscrit_payouts(
+        caller: &Self::AccountId,
+        payouts: &Vec<Payout>,
+        outpoints: &Vec<H256>,
+    ) -> Result<(), DispatchError>;
 }